@@ -0,0 +1,82 @@
+//! Ordering of path lists.
+//!
+//! Paths sort byte-wise by default, which is fast but puts `Zebra` before
+//! `apple` and `école` after `zoo`. Builds with the `collate` feature can
+//! sort them the way people read them instead, with the Unicode collation
+//! rules of the locale in `LC_ALL`, `LC_COLLATE` or `LANG`: in English `ö`
+//! sorts with `o`, in Swedish after `z`. Ties are broken byte-wise so the
+//! order stays total.
+
+/// How to order a list of paths.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// By their UTF-8 bytes.
+    #[default]
+    Byte,
+    /// By the collation rules of the current locale (`collate` feature).
+    Locale,
+}
+
+impl Collation {
+    /// Parse a `--collate` value, `byte` or `locale`.
+    pub fn from_arg(value: &str) -> Self {
+        match value {
+            "locale" => Self::Locale,
+            _ => Self::Byte,
+        }
+    }
+
+    /// Sort `paths` in place.
+    pub fn sort(self, paths: &mut [String]) {
+        match self {
+            Self::Byte => paths.sort_unstable(),
+            Self::Locale => sort_locale(paths),
+        }
+    }
+}
+
+#[cfg(feature = "collate")]
+fn sort_locale(paths: &mut [String]) {
+    let locale = env_locale(|var| std::env::var(var).ok());
+    sort_for_locale(paths, &locale);
+}
+
+#[cfg(not(feature = "collate"))]
+fn sort_locale(paths: &mut [String]) {
+    eprintln!("[collate] built without the `collate` feature; sorting byte-wise");
+    paths.sort_unstable();
+}
+
+/// Sort `paths` by the collation rules of `locale`, falling back to the
+/// root rules if there are none for it.
+#[cfg(feature = "collate")]
+pub(crate) fn sort_for_locale(paths: &mut [String], locale: &icu_locid::Locale) {
+    use icu_collator::{Collator, CollatorOptions};
+
+    let collator = Collator::try_new(&locale.into(), CollatorOptions::new())
+        .or_else(|_| Collator::try_new(&Default::default(), CollatorOptions::new()))
+        .expect("root collation data is compiled in");
+    paths.sort_by(|a, b| collator.compare(a, b).then_with(|| a.cmp(b)));
+}
+
+/// The locale the first set variable of `LC_ALL`, `LC_COLLATE` and `LANG`
+/// names, e.g. `sv_SE.UTF-8` as `sv-SE`. `C`, `POSIX` and names that are
+/// not locales mean the root locale.
+#[cfg(feature = "collate")]
+pub(crate) fn env_locale(env: impl Fn(&str) -> Option<String>) -> icu_locid::Locale {
+    let Some(value) = ["LC_ALL", "LC_COLLATE", "LANG"]
+        .into_iter()
+        .find_map(|var| env(var).filter(|v| !v.is_empty()))
+    else {
+        return icu_locid::Locale::UND;
+    };
+    let name = value.split(['.', '@']).next().unwrap_or_default();
+    match name {
+        "C" | "POSIX" => icu_locid::Locale::UND,
+        _ => name.replace('_', "-").parse().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+#[path = "collate_tests.rs"]
+mod tests;
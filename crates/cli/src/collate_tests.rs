@@ -0,0 +1,78 @@
+use super::*;
+
+fn sorted(collation: Collation, paths: &[&str]) -> Vec<String> {
+    let mut paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+    collation.sort(&mut paths);
+    paths
+}
+
+#[test]
+fn byte_order_is_the_default() {
+    assert_eq!(Collation::from_arg("byte"), Collation::default());
+    assert_eq!(
+        sorted(
+            Collation::Byte,
+            &["/d/zoo", "/d/École", "/d/apple", "/d/Zebra"]
+        ),
+        ["/d/Zebra", "/d/apple", "/d/zoo", "/d/École"]
+    );
+}
+
+#[cfg(not(feature = "collate"))]
+#[test]
+fn locale_order_falls_back_to_bytes_without_the_feature() {
+    let paths = ["/d/zoo", "/d/École", "/d/apple", "/d/Zebra"];
+    assert_eq!(
+        sorted(Collation::Locale, &paths),
+        sorted(Collation::Byte, &paths)
+    );
+}
+
+#[cfg(feature = "collate")]
+fn sorted_for(locale: &str, paths: &[&str]) -> Vec<String> {
+    let mut paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+    sort_for_locale(&mut paths, &locale.parse().expect("locale"));
+    paths
+}
+
+#[cfg(feature = "collate")]
+#[test]
+fn locale_order_ignores_case_and_accents_first() {
+    assert_eq!(
+        sorted_for(
+            "en",
+            &["/d/zoo", "/d/École", "/d/apple", "/d/Zebra", "/d/ecole"]
+        ),
+        ["/d/apple", "/d/ecole", "/d/École", "/d/Zebra", "/d/zoo"]
+    );
+}
+
+#[cfg(feature = "collate")]
+#[test]
+fn locale_order_follows_the_locale() {
+    let paths = ["/d/zebra", "/d/öl", "/d/orange"];
+    assert_eq!(sorted_for("en", &paths), ["/d/öl", "/d/orange", "/d/zebra"]);
+    assert_eq!(sorted_for("sv", &paths), ["/d/orange", "/d/zebra", "/d/öl"]);
+}
+
+#[cfg(feature = "collate")]
+#[test]
+fn locale_comes_from_the_environment() {
+    let locale = |vars: &[(&str, &str)]| {
+        env_locale(|var| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        })
+        .to_string()
+    };
+
+    assert_eq!(locale(&[("LANG", "sv_SE.UTF-8")]), "sv-SE");
+    assert_eq!(
+        locale(&[("LANG", "sv_SE.UTF-8"), ("LC_COLLATE", "de_DE@euro")]),
+        "de-DE"
+    );
+    assert_eq!(locale(&[("LC_ALL", "C"), ("LC_COLLATE", "de_DE")]), "und");
+    assert_eq!(locale(&[("LANG", "not a locale")]), "und");
+    assert_eq!(locale(&[]), "und");
+}
@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use blaze_protocol::{
+    DaemonRequest, DaemonResponse, QueryRequest,
+    codec::{read_message, write_message},
+};
+use blaze_runtime::settings;
+use clap::Args;
+use log::{debug, error};
+
+use crate::commands::daemon::daemon_socket_path;
+
+/// Queries used when neither `--query` nor `--queries-file` is given.
+const DEFAULT_MIX: &[&str] = &["readme", "ext:rs main", "^makefile", ".h$", "src lib"];
+
+#[derive(Debug, Args)]
+pub struct BenchDaemonArgs {
+    /// Total number of queries to send
+    #[arg(long, short = 'n', default_value = "1000")]
+    pub requests: usize,
+
+    /// Number of queries in flight at once
+    #[arg(long, short = 'c', default_value = "8")]
+    pub concurrency: usize,
+
+    /// Query to include in the mix (repeatable); sent round-robin
+    #[arg(long, short = 'q', value_name = "QUERY")]
+    pub query: Vec<String>,
+
+    /// Read the query mix from a file, one query per line
+    #[arg(long, value_name = "FILE")]
+    pub queries_file: Option<PathBuf>,
+
+    /// Most hits to request per query (0 for all)
+    #[arg(long, short = 'l')]
+    pub limit: Option<usize>,
+}
+
+pub fn run(args: BenchDaemonArgs) -> ExitCode {
+    match execute(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[bench-daemon] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Outcome of one timed query.
+struct Sample {
+    latency: Duration,
+    ok: bool,
+}
+
+fn execute(args: &BenchDaemonArgs) -> Result<ExitCode> {
+    if args.concurrency == 0 {
+        bail!("--concurrency must be at least 1");
+    }
+    let mix = query_mix(args)?;
+    let socket_path = daemon_socket_path()?;
+    let limit = match args.limit.unwrap_or(settings().limit.value) {
+        0 => None,
+        n => Some(n),
+    };
+
+    let next = AtomicUsize::new(0);
+    let samples = Mutex::new(Vec::with_capacity(args.requests));
+    let started = Instant::now();
+
+    thread::scope(|s| {
+        for _ in 0..args.concurrency.min(args.requests.max(1)) {
+            s.spawn(|| {
+                let mut local = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= args.requests {
+                        break;
+                    }
+                    let query = &mix[i % mix.len()];
+                    let t0 = Instant::now();
+                    let ok = match send_query(&socket_path, query, limit) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            debug!("query {query:?} failed: {e:#}");
+                            false
+                        }
+                    };
+                    local.push(Sample {
+                        latency: t0.elapsed(),
+                        ok,
+                    });
+                }
+                samples
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .extend(local);
+            });
+        }
+    });
+
+    let wall = started.elapsed();
+    let samples = samples.into_inner().unwrap_or_else(|e| e.into_inner());
+    report(args, mix.len(), &samples, wall);
+
+    let failed = samples.iter().filter(|s| !s.ok).count();
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn query_mix(args: &BenchDaemonArgs) -> Result<Vec<String>> {
+    let mut mix = args.query.clone();
+    if let Some(path) = &args.queries_file {
+        mix.extend(read_queries(path)?);
+    }
+    if mix.is_empty() {
+        mix = DEFAULT_MIX.iter().map(|q| q.to_string()).collect();
+    }
+    Ok(mix)
+}
+
+/// Non-empty lines of `path`, skipping `#` comments.
+fn read_queries(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read queries from {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Send one query on a fresh connection and wait for the whole response.
+fn send_query(socket_path: &Path, query: &str, limit: Option<usize>) -> Result<()> {
+    let mut stream = UnixStream::connect(socket_path)
+        .with_context(|| format!("failed to connect to {}", socket_path.display()))?;
+    let req = DaemonRequest::Query(QueryRequest {
+        query: query.to_string(),
+        limit,
+        under: None,
+        session: None,
+        offset: 0,
+    });
+    write_message(&mut stream, &req)?;
+
+    match read_message(&mut stream)? {
+        DaemonResponse::QueryResult(_) => Ok(()),
+        DaemonResponse::Error(msg) => bail!("daemon error: {msg}"),
+        other => bail!("unexpected daemon response: {other:?}"),
+    }
+}
+
+fn report(args: &BenchDaemonArgs, mix_len: usize, samples: &[Sample], wall: Duration) {
+    let failed = samples.iter().filter(|s| !s.ok).count();
+    let mut latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+    latencies.sort_unstable();
+
+    let secs = wall.as_secs_f64();
+    let throughput = if secs > 0.0 {
+        samples.len() as f64 / secs
+    } else {
+        0.0
+    };
+
+    println!(
+        "requests:    {} ({} failed), {} distinct queries",
+        samples.len(),
+        failed,
+        mix_len
+    );
+    println!("concurrency: {}", args.concurrency);
+    println!("wall time:   {:.2}s", secs);
+    println!("throughput:  {throughput:.1} queries/s");
+
+    if latencies.is_empty() {
+        return;
+    }
+    let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    println!("latency:");
+    println!("  min  {}", format_ms(latencies[0]));
+    println!("  mean {}", format_ms(mean));
+    for p in [50.0, 90.0, 99.0] {
+        println!("  p{p:<3} {}", format_ms(percentile(&latencies, p)));
+    }
+    println!("  max  {}", format_ms(latencies[latencies.len() - 1]));
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+pub(crate) fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_ms(d: Duration) -> String {
+    format!("{:>8.2}ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+#[path = "bench_tests.rs"]
+mod tests;
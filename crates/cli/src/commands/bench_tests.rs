@@ -0,0 +1,16 @@
+use super::*;
+
+fn ms(values: &[u64]) -> Vec<Duration> {
+    values.iter().map(|&v| Duration::from_millis(v)).collect()
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let sorted = ms(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+    assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(5));
+    assert_eq!(percentile(&sorted, 90.0), Duration::from_millis(9));
+    assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(10));
+    assert_eq!(percentile(&sorted, 0.0), Duration::from_millis(1));
+    assert_eq!(percentile(&ms(&[7]), 99.0), Duration::from_millis(7));
+}
@@ -0,0 +1,64 @@
+use std::process::ExitCode;
+
+use anyhow::Result;
+use blaze_runtime::Settings;
+use clap::{Args, Subcommand};
+use log::error;
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print each setting's effective value and where it came from.
+    ///
+    /// Example:
+    ///   blaze config show
+    ///   blaze --config ./ci.toml config show
+    Show,
+}
+
+pub fn run(args: ConfigArgs) -> ExitCode {
+    match execute(args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[config] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: ConfigArgs) -> Result<ExitCode> {
+    match args.action {
+        ConfigAction::Show => {
+            // Loaded afresh so warnings are printed below rather than logged.
+            let settings = Settings::load();
+            let found = if settings.file.is_file() {
+                ""
+            } else {
+                " (not found)"
+            };
+            println!("config file: {}{found}", settings.file.display());
+            println!();
+
+            let entries = settings.entries();
+            let width = entries.iter().map(|(key, ..)| key.len()).max().unwrap_or(0);
+            for (key, value, source) in entries {
+                let value = if value.is_empty() { "-" } else { &value };
+                println!("{key:<width$}  {value}  ({source})");
+            }
+
+            if !settings.warnings.is_empty() {
+                println!();
+                for warning in &settings.warnings {
+                    println!("warning: {warning}");
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
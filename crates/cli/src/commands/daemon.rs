@@ -0,0 +1,321 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, bail};
+use blaze_protocol::PROTOCOL_VERSION;
+use blaze_runtime::{DaemonDiscovery, default_socket_path, discovery_path, settings, xdg_or_home};
+use clap::{Args, Subcommand};
+use log::{error, warn};
+
+use crate::commands::version::skew_warning;
+
+/// Name shared by the systemd units.
+const SYSTEMD_UNIT_NAME: &str = "blaze-daemon";
+/// launchd job label.
+const LAUNCHD_LABEL: &str = "dev.blaze.daemon";
+
+#[derive(Debug, Args)]
+pub struct DaemonArgs {
+    #[command(subcommand)]
+    pub action: DaemonAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DaemonAction {
+    /// Register the daemon with the user service manager so it starts on
+    /// login (systemd on Linux, launchd on macOS).
+    ///
+    /// Example:
+    ///   blaze daemon install
+    ///   blaze daemon install --socket-activation
+    Install(InstallArgs),
+
+    /// Remove the service files written by `blaze daemon install`.
+    Uninstall,
+}
+
+#[derive(Debug, Args)]
+pub struct InstallArgs {
+    /// Start the daemon on the first connection instead of at login
+    /// (systemd only)
+    #[arg(long)]
+    pub socket_activation: bool,
+
+    /// Path to the blaze-daemon binary (defaults to the one next to blaze)
+    #[arg(long, value_name = "PATH")]
+    pub daemon_bin: Option<PathBuf>,
+
+    /// Print the generated service files instead of writing them
+    #[arg(long)]
+    pub print: bool,
+}
+
+pub fn run(args: DaemonArgs) -> ExitCode {
+    let result = match args.action {
+        DaemonAction::Install(install) => run_install(&install),
+        DaemonAction::Uninstall => run_uninstall(),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[daemon] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// A service file to write, with its destination.
+struct ServiceFile {
+    path: PathBuf,
+    contents: String,
+}
+
+fn default_daemon_bin() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("failed to locate the blaze executable")?;
+    let bin = exe.with_file_name("blaze-daemon");
+    if !bin.exists() {
+        bail!(
+            "blaze-daemon not found next to {}; pass --daemon-bin",
+            exe.display()
+        );
+    }
+    Ok(bin)
+}
+
+/// Socket of the running daemon.
+///
+/// Uses the path the daemon advertised in its discovery file, falling back to
+/// the default socket when there is none. Fails if the daemon speaks a
+/// different protocol version than this binary, and warns if it is another
+/// build.
+pub(crate) fn daemon_socket_path() -> Result<PathBuf> {
+    let path = discovery_path();
+    let discovery = match DaemonDiscovery::read(&path) {
+        Ok(discovery) => discovery,
+        Err(e) => {
+            warn!("ignoring discovery file {}: {e}", path.display());
+            None
+        }
+    };
+
+    match discovery {
+        Some(d) if d.protocol_version != PROTOCOL_VERSION => bail!(
+            "daemon (pid {}) speaks protocol v{} but this blaze speaks v{}; restart the daemon",
+            d.pid,
+            d.protocol_version,
+            PROTOCOL_VERSION
+        ),
+        Some(d) => {
+            if let Some(warning) = skew_warning(&d) {
+                warn!("{warning}");
+            }
+            Ok(d.socket_path)
+        }
+        None => Ok(settings().socket_path.value.clone()),
+    }
+}
+
+fn systemd_user_dir() -> PathBuf {
+    xdg_or_home("XDG_CONFIG_HOME", ".config").join("systemd/user")
+}
+
+fn launch_agents_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("Library/LaunchAgents")
+}
+
+/// Quote a path for an `Exec*=` line if it contains whitespace.
+fn systemd_quote(path: &Path) -> String {
+    let s = path.to_string_lossy();
+    if s.contains(char::is_whitespace) {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s.into_owned()
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// systemd user service. Without socket activation it is started at login.
+fn systemd_service_unit(daemon_bin: &Path, socket_activation: bool) -> String {
+    let mut unit = String::from("[Unit]\nDescription=Blaze file search daemon\n");
+    if socket_activation {
+        unit.push_str(&format!("Requires={SYSTEMD_UNIT_NAME}.socket\n"));
+    }
+
+    unit.push_str(&format!(
+        "\n[Service]\nExecStart={}\nRestart=on-failure\n",
+        systemd_quote(daemon_bin)
+    ));
+
+    if !socket_activation {
+        unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    }
+
+    unit
+}
+
+/// systemd socket unit that starts the daemon on the first connection.
+fn systemd_socket_unit(socket_path: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Blaze file search daemon socket\n\
+         \n\
+         [Socket]\n\
+         ListenStream={}\n\
+         SocketMode=0600\n\
+         \n\
+         [Install]\n\
+         WantedBy=sockets.target\n",
+        socket_path.display()
+    )
+}
+
+/// launchd agent that starts the daemon at login and restarts it on failure.
+fn launchd_plist(daemon_bin: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ProcessType</key>
+    <string>Background</string>
+</dict>
+</plist>
+"#,
+        xml_escape(&daemon_bin.to_string_lossy())
+    )
+}
+
+fn service_files(args: &InstallArgs, daemon_bin: &Path) -> Result<Vec<ServiceFile>> {
+    if cfg!(target_os = "macos") {
+        if args.socket_activation {
+            bail!("--socket-activation is only supported with systemd");
+        }
+        return Ok(vec![ServiceFile {
+            path: launch_agents_dir().join(format!("{LAUNCHD_LABEL}.plist")),
+            contents: launchd_plist(daemon_bin),
+        }]);
+    }
+
+    let dir = systemd_user_dir();
+    let mut files = vec![ServiceFile {
+        path: dir.join(format!("{SYSTEMD_UNIT_NAME}.service")),
+        contents: systemd_service_unit(daemon_bin, args.socket_activation),
+    }];
+
+    if args.socket_activation {
+        files.push(ServiceFile {
+            path: dir.join(format!("{SYSTEMD_UNIT_NAME}.socket")),
+            contents: systemd_socket_unit(&default_socket_path()),
+        });
+    }
+
+    Ok(files)
+}
+
+fn run_install(args: &InstallArgs) -> Result<ExitCode> {
+    let daemon_bin = match &args.daemon_bin {
+        Some(bin) => bin.clone(),
+        None => default_daemon_bin()?,
+    };
+
+    let files = service_files(args, &daemon_bin)?;
+
+    if args.print {
+        for file in &files {
+            println!("# {}", file.path.display());
+            print!("{}", file.contents);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for file in &files {
+        if let Some(parent) = file.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&file.path, &file.contents)
+            .with_context(|| format!("failed to write {}", file.path.display()))?;
+        eprintln!("[daemon] wrote {}", file.path.display());
+    }
+
+    if cfg!(target_os = "macos") {
+        eprintln!(
+            "[daemon] start it now with: launchctl load -w {}",
+            files[0].path.display()
+        );
+    } else {
+        let unit = if args.socket_activation {
+            format!("{SYSTEMD_UNIT_NAME}.socket")
+        } else {
+            format!("{SYSTEMD_UNIT_NAME}.service")
+        };
+        eprintln!(
+            "[daemon] start it now with: systemctl --user daemon-reload && \
+             systemctl --user enable --now {unit}"
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_uninstall() -> Result<ExitCode> {
+    let paths = if cfg!(target_os = "macos") {
+        vec![launch_agents_dir().join(format!("{LAUNCHD_LABEL}.plist"))]
+    } else {
+        let dir = systemd_user_dir();
+        vec![
+            dir.join(format!("{SYSTEMD_UNIT_NAME}.service")),
+            dir.join(format!("{SYSTEMD_UNIT_NAME}.socket")),
+        ]
+    };
+
+    let mut removed = 0;
+    for path in &paths {
+        match fs::remove_file(path) {
+            Ok(()) => {
+                eprintln!("[daemon] removed {}", path.display());
+                removed += 1;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to remove {}", path.display()));
+            }
+        }
+    }
+
+    if removed == 0 {
+        eprintln!("[daemon] no service files installed");
+    } else if !cfg!(target_os = "macos") {
+        eprintln!(
+            "[daemon] stop the running daemon with: systemctl --user disable --now blaze-daemon.socket blaze-daemon.service"
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,68 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use blaze_engine::{Index, IndexReader, QueryEngine, parse_query, rank_dirs};
+use blaze_runtime::{PinSet, settings};
+use clap::Args;
+use log::error;
+
+#[derive(Debug, Args)]
+pub struct DirsArgs {
+    /// The query expression; text terms match anywhere in the directory path
+    pub query: String,
+
+    /// Maximum number of directories to print
+    #[arg(long, short = 'n', default_value = "20")]
+    pub limit: usize,
+
+    /// Print only the best match, for `cd "$(blaze dirs --first QUERY)"`
+    #[arg(long, conflicts_with = "limit")]
+    pub first: bool,
+
+    /// Index file to search instead of the global index
+    #[arg(long, value_name = "PATH")]
+    pub index: Option<PathBuf>,
+}
+
+/// Exits 1 if no directory matched.
+pub fn run(args: DirsArgs) -> ExitCode {
+    match execute(args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[dirs] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: DirsArgs) -> Result<ExitCode> {
+    let index_path = args
+        .index
+        .clone()
+        .unwrap_or_else(|| settings().index_path.value.clone());
+    let index = Index::open(&index_path)
+        .with_context(|| format!("failed to open index at {}", index_path.display()))?;
+
+    let query = parse_query(&args.query);
+    let pins = PinSet::load();
+    let engine = QueryEngine::new(&index).with_pins(pins.clone());
+    let mut dirs = rank_dirs(&index, &query, &engine.eval_dirs(&query), &pins);
+    dirs.truncate(if args.first { 1 } else { args.limit });
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for &d in &dirs {
+        writeln!(out, "{}", index.reconstruct_dir_path(d))?;
+    }
+    out.flush()?;
+
+    Ok(if dirs.is_empty() {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,353 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use blaze_engine::{
+    CmpOp, Field, Index, IndexReader, LeafExpr, Predicate, Query, QueryExpr, QueryPipeline,
+    RelativeTime, TimeExpr, Value, name_predicate,
+};
+use blaze_fs::native_path;
+use blaze_runtime::{find_indexed_project, settings};
+use clap::Args;
+use log::error;
+
+#[derive(Debug, Args)]
+pub struct FindArgs {
+    /// Starting paths, then an expression built from -name, -iname, -type f,
+    /// -size, -mtime, -maxdepth, -print0, !, -o and parentheses
+    #[arg(
+        value_name = "ARGS",
+        allow_hyphen_values = true,
+        trailing_var_arg = true
+    )]
+    pub args: Vec<String>,
+}
+
+/// A parsed `blaze find` command line.
+#[derive(Debug)]
+pub struct FindCommand {
+    /// Starting paths; `.` if none were given.
+    pub paths: Vec<PathBuf>,
+    /// The expression, as a query over the index.
+    pub query: Query,
+    /// `-maxdepth`: deepest level below a starting path to report.
+    pub max_depth: Option<usize>,
+    /// `-print0`: terminate paths with NUL instead of newline.
+    pub print0: bool,
+}
+
+pub fn run(args: FindArgs) -> ExitCode {
+    match execute(args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[find] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: FindArgs) -> Result<ExitCode> {
+    let cmd = parse_find_args(&args.args)?;
+    let terminator = if cmd.print0 { b'\0' } else { b'\n' };
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    for path in &cmd.paths {
+        let start = path
+            .canonicalize()
+            .with_context(|| format!("cannot access {}", path.display()))?;
+
+        let index_path = index_path_for(&start);
+        let index = Index::open(&index_path)
+            .with_context(|| format!("failed to open index at {}", index_path.display()))?;
+
+        let root = index.root_path().unwrap_or_default();
+        if !start.starts_with(root) {
+            bail!(
+                "{} is outside the indexed root {root}; run `blaze index {}` first",
+                start.display(),
+                start.display()
+            );
+        }
+
+        let pipeline = QueryPipeline::new(&index)
+            .with_query(cmd.query.clone())
+            .execute()
+            .unranked();
+
+        let mut found: Vec<String> = pipeline
+            .results()
+            .iter()
+            .map(|&fid| index.reconstruct_full_path(fid))
+            .filter(|p| within_depth(&start, Path::new(p), cmd.max_depth))
+            .collect();
+        found.sort_unstable();
+
+        for p in found {
+            out.write_all(native_path(&p).as_bytes())?;
+            out.write_all(&[terminator])?;
+        }
+    }
+
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The project index covering `start` if one was built, else the global index.
+fn index_path_for(start: &Path) -> PathBuf {
+    match find_indexed_project(start) {
+        Some((_, path)) => path,
+        None => settings().index_path.value.clone(),
+    }
+}
+
+/// Whether `path` is `start` or lies below it, at most `max_depth` levels down.
+pub(crate) fn within_depth(start: &Path, path: &Path, max_depth: Option<usize>) -> bool {
+    match path.strip_prefix(start) {
+        Ok(rel) => max_depth.is_none_or(|max| rel.components().count() <= max),
+        Err(_) => false,
+    }
+}
+
+/// Parse `find`-style arguments: starting paths, then an expression.
+///
+/// Operators bind as in `find`: `!`/`-not` tightest, then `-a`/`-and` (also
+/// implied between adjacent terms), then `-o`/`-or`.
+pub fn parse_find_args(args: &[String]) -> Result<FindCommand> {
+    let split = args
+        .iter()
+        .position(|a| a.starts_with('-') || a == "(" || a == "!")
+        .unwrap_or(args.len());
+    let (paths, expr) = args.split_at(split);
+
+    let mut parser = FindParser {
+        args: expr,
+        pos: 0,
+        max_depth: None,
+        print0: false,
+    };
+    let expr = if expr.is_empty() {
+        always()
+    } else {
+        parser.parse_or()?
+    };
+    if let Some(extra) = parser.peek() {
+        bail!("unexpected `{extra}`");
+    }
+
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths.iter().map(PathBuf::from).collect()
+    };
+
+    Ok(FindCommand {
+        paths,
+        query: Query { expr },
+        max_depth: parser.max_depth,
+        print0: parser.print0,
+    })
+}
+
+struct FindParser<'a> {
+    args: &'a [String],
+    pos: usize,
+    max_depth: Option<usize>,
+    print0: bool,
+}
+
+impl FindParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.args.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let arg = self.args.get(self.pos)?;
+        self.pos += 1;
+        Some(arg)
+    }
+
+    fn value(&mut self, primary: &str) -> Result<String> {
+        self.next()
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow!("missing argument to `{primary}`"))
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut terms = vec![self.parse_and()?];
+        while matches!(self.peek(), Some("-o" | "-or")) {
+            self.pos += 1;
+            terms.push(self.parse_and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            QueryExpr::Or(terms)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut terms = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                None | Some("-o" | "-or" | ")") => break,
+                Some("-a" | "-and") => self.pos += 1,
+                Some(_) => {}
+            }
+            terms.push(self.parse_unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            QueryExpr::And(terms)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        match self.next() {
+            None => bail!("expected an expression"),
+            Some("!" | "-not") => Ok(QueryExpr::Not(Box::new(self.parse_unary()?))),
+            Some("(") => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(inner),
+                    _ => bail!("missing `)`"),
+                }
+            }
+            Some(")") => bail!("unexpected `)`"),
+            Some(primary) => {
+                let primary = primary.to_owned();
+                self.parse_primary(&primary)
+            }
+        }
+    }
+
+    fn parse_primary(&mut self, primary: &str) -> Result<QueryExpr> {
+        match primary {
+            "-name" | "-iname" => {
+                let pattern = self.value(primary)?;
+                let field = if primary == "-name" {
+                    Field::Name
+                } else {
+                    Field::IName
+                };
+                let pred = name_predicate(field, &pattern)
+                    .ok_or_else(|| anyhow!("invalid pattern `{pattern}` for `{primary}`"))?;
+                Ok(leaf(pred))
+            }
+            "-type" => match self.value(primary)?.as_str() {
+                // Only regular files are indexed, so this always holds.
+                "f" => Ok(always()),
+                t @ ("d" | "l" | "b" | "c" | "p" | "s") => {
+                    bail!("`-type {t}` is not supported: only regular files are indexed")
+                }
+                t => bail!("unknown file type `{t}` for `-type`"),
+            },
+            "-size" => {
+                let arg = self.value(primary)?;
+                parse_size(&arg).ok_or_else(|| anyhow!("invalid argument `{arg}` to `-size`"))
+            }
+            "-mtime" => {
+                let arg = self.value(primary)?;
+                parse_mtime(&arg).ok_or_else(|| anyhow!("invalid argument `{arg}` to `-mtime`"))
+            }
+            "-maxdepth" => {
+                let arg = self.value(primary)?;
+                let depth = arg
+                    .parse()
+                    .map_err(|_| anyhow!("invalid argument `{arg}` to `-maxdepth`"))?;
+                self.max_depth = Some(depth);
+                Ok(always())
+            }
+            "-print" => Ok(always()),
+            "-print0" => {
+                self.print0 = true;
+                Ok(always())
+            }
+            other => bail!("unknown predicate `{other}`"),
+        }
+    }
+}
+
+/// An expression that matches every file.
+fn always() -> QueryExpr {
+    QueryExpr::And(Vec::new())
+}
+
+fn leaf(pred: Predicate) -> QueryExpr {
+    QueryExpr::Leaf(LeafExpr::Predicate(pred))
+}
+
+/// Split `find`'s `+N` / `-N` / `N` numeric arguments.
+fn split_sign(arg: &str) -> (Option<char>, &str) {
+    match arg.chars().next() {
+        Some(c @ ('+' | '-')) => (Some(c), &arg[1..]),
+        _ => (None, arg),
+    }
+}
+
+/// Translate `-size [+-]N[cwbkMG]`.
+///
+/// As in `find`, sizes are rounded up to whole units before comparing, and
+/// a bare number counts 512-byte blocks.
+fn parse_size(arg: &str) -> Option<QueryExpr> {
+    let (sign, rest) = split_sign(arg);
+    let (digits, unit) = match rest.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&rest[..i], c),
+        _ => (rest, 'b'),
+    };
+    let unit: u64 = match unit {
+        'c' => 1,
+        'w' => 2,
+        'b' => 512,
+        'k' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        _ => return None,
+    };
+    let n: u64 = digits.parse().ok()?;
+
+    let size = |op, units: u64| {
+        leaf(Predicate {
+            field: Field::Size,
+            op,
+            value: Value::SizeBytes(units.saturating_mul(unit)),
+        })
+    };
+
+    Some(match sign {
+        Some('+') => size(CmpOp::Gt, n),
+        Some(_) => size(CmpOp::Le, n.saturating_sub(1)),
+        None if n == 0 => size(CmpOp::Eq, 0),
+        None => QueryExpr::And(vec![size(CmpOp::Gt, n - 1), size(CmpOp::Le, n)]),
+    })
+}
+
+/// Translate `-mtime [+-]N`, where N counts whole 24-hour periods of age.
+fn parse_mtime(arg: &str) -> Option<QueryExpr> {
+    let (sign, digits) = split_sign(arg);
+    let n: i64 = digits.parse().ok()?;
+
+    // "Modified more recently than N days ago" and its negation.
+    let newer = |days: i64| {
+        leaf(Predicate {
+            field: Field::Modified,
+            op: CmpOp::Gt,
+            value: Value::Time(TimeExpr::Relative(RelativeTime::Days(days))),
+        })
+    };
+    let older = |days: i64| QueryExpr::Not(Box::new(newer(days)));
+
+    Some(match sign {
+        Some('+') => older(n + 1),
+        Some(_) => newer(n),
+        None => QueryExpr::And(vec![older(n), newer(n + 1)]),
+    })
+}
+
+#[cfg(test)]
+#[path = "find_tests.rs"]
+mod tests;
@@ -0,0 +1,140 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use blaze_engine::{IndexBuilder, StagedIndex};
+use blaze_fs::FileRecord;
+
+use super::*;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn args(s: &str) -> Vec<String> {
+    s.split_whitespace().map(str::to_owned).collect()
+}
+
+fn record(rel: &str, size: u64, age_days: u64) -> FileRecord {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    FileRecord {
+        size,
+        // Half a day into the age bucket, so `-mtime` boundaries are stable.
+        mtime_secs: now - age_days * DAY - DAY / 2,
+        ctime_secs: now,
+        atime_secs: now,
+        ..FileRecord::test_file(Path::new("/src").join(rel))
+    }
+}
+
+fn staged() -> StagedIndex {
+    let mut builder = IndexBuilder::new(PathBuf::from("/src"));
+    builder.add_batch([
+        record("main.rs", 100, 0),
+        record("Makefile", 2_000, 3),
+        record("docs/README.md", 512, 10),
+        record("docs/readme.txt", 0, 30),
+        record("assets/video.mp4", 3 << 30, 7),
+    ]);
+    builder.finish()
+}
+
+/// Names of the files matched by a `find` expression, sorted.
+fn find(expr: &str) -> Vec<String> {
+    let cmd = parse_find_args(&args(expr)).unwrap();
+    let index = staged();
+    let pipeline = QueryPipeline::new(&index)
+        .with_query(cmd.query)
+        .execute()
+        .unranked();
+    let mut names: Vec<String> = pipeline
+        .results()
+        .iter()
+        .map(|&f| index.get_file_name(f).to_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+fn parse_err(expr: &str) -> String {
+    parse_find_args(&args(expr)).unwrap_err().to_string()
+}
+
+#[test]
+fn paths_come_before_the_expression() {
+    let cmd = parse_find_args(&args("src docs -maxdepth 2 -print0")).unwrap();
+    assert_eq!(cmd.paths, [PathBuf::from("src"), PathBuf::from("docs")]);
+    assert_eq!(cmd.max_depth, Some(2));
+    assert!(cmd.print0);
+
+    let cmd = parse_find_args(&args("-name *.rs")).unwrap();
+    assert_eq!(cmd.paths, [PathBuf::from(".")]);
+    assert_eq!(cmd.max_depth, None);
+    assert!(!cmd.print0);
+}
+
+#[test]
+fn name_is_case_sensitive_and_iname_is_not() {
+    assert_eq!(find("-name readme*"), ["readme.txt"]);
+    assert_eq!(find("-iname readme*"), ["README.md", "readme.txt"]);
+    assert_eq!(find("-name *.rs"), ["main.rs"]);
+    assert_eq!(find("-name M*"), ["Makefile"]);
+}
+
+#[test]
+fn size_rounds_up_to_units_like_find() {
+    assert_eq!(find("-size +1G"), ["video.mp4"]);
+    assert_eq!(find("-size -1k"), ["readme.txt"]);
+    // Bare numbers are 512-byte blocks: 100 and 512 bytes are one block.
+    assert_eq!(find("-size 1"), ["README.md", "main.rs"]);
+    assert_eq!(find("-size 2000c"), ["Makefile"]);
+    assert_eq!(find("-size 0"), ["readme.txt"]);
+}
+
+#[test]
+fn mtime_counts_whole_days() {
+    assert_eq!(find("-mtime -7"), ["Makefile", "main.rs"]);
+    assert_eq!(find("-mtime 7"), ["video.mp4"]);
+    assert_eq!(find("-mtime +7"), ["README.md", "readme.txt"]);
+}
+
+#[test]
+fn operators_follow_find_precedence() {
+    // -a binds tighter than -o.
+    assert_eq!(
+        find("-name *.rs -o -iname readme* -size +0"),
+        ["README.md", "main.rs"]
+    );
+    assert_eq!(
+        find("( -name *.rs -o -iname readme* ) -mtime -7"),
+        ["main.rs"]
+    );
+    assert_eq!(find("! -name *.* -type f"), ["Makefile"]);
+    assert_eq!(find("-not -size -2G -a -print"), ["video.mp4"]);
+}
+
+#[test]
+fn unsupported_and_malformed_expressions_are_errors() {
+    assert!(parse_err("-type d").contains("only regular files"));
+    assert!(parse_err("-type x").contains("unknown file type"));
+    assert!(parse_err("-name").contains("missing argument"));
+    assert!(parse_err("-size 10Q").contains("-size"));
+    assert!(parse_err("-mtime soon").contains("-mtime"));
+    assert!(parse_err("-maxdepth -1").contains("-maxdepth"));
+    assert!(parse_err("-newer x").contains("unknown predicate"));
+    assert!(parse_err("( -name a").contains("missing `)`"));
+    assert!(parse_err("-name a )").contains("unexpected `)`"));
+}
+
+#[test]
+fn depth_is_counted_below_the_starting_path() {
+    let start = Path::new("/src");
+    assert!(within_depth(start, Path::new("/src/a.rs"), Some(1)));
+    assert!(!within_depth(start, Path::new("/src/docs/a.md"), Some(1)));
+    assert!(within_depth(start, Path::new("/src/docs/a.md"), None));
+    assert!(!within_depth(start, Path::new("/srcs/a.rs"), None));
+    assert!(within_depth(
+        Path::new("/src/a.rs"),
+        Path::new("/src/a.rs"),
+        Some(0)
+    ));
+}
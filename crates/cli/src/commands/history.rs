@@ -1,7 +1,7 @@
 use std::process::ExitCode;
 
-use blaze_runtime::history::HistoryStore;
-use clap::Args;
+use blaze_runtime::history::{HistoryStore, RetentionPolicy};
+use clap::{Args, Subcommand};
 use log::{error, info};
 
 #[derive(Debug, Args)]
@@ -13,6 +13,30 @@ pub struct HistoryArgs {
     /// Clear all history
     #[arg(long)]
     pub clear: bool,
+
+    #[command(subcommand)]
+    pub action: Option<HistoryAction>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryAction {
+    /// Trim history using the configured retention policy.
+    ///
+    /// Example:
+    ///   blaze history prune
+    ///   blaze history prune --keep 500 --max-age-days 30
+    Prune(PruneArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PruneArgs {
+    /// Keep at most this many of the newest entries (0 = no limit)
+    #[arg(long, value_name = "N")]
+    pub keep: Option<usize>,
+
+    /// Drop entries older than this many days (0 = no limit)
+    #[arg(long, value_name = "DAYS")]
+    pub max_age_days: Option<u32>,
 }
 
 pub fn run(args: HistoryArgs) -> ExitCode {
@@ -24,6 +48,10 @@ pub fn run(args: HistoryArgs) -> ExitCode {
         }
     };
 
+    if let Some(HistoryAction::Prune(prune)) = &args.action {
+        return run_prune(&store, prune);
+    }
+
     if args.clear {
         match store.clear() {
             Ok(_) => {
@@ -45,15 +73,22 @@ pub fn run(args: HistoryArgs) -> ExitCode {
     }
 
     // Print header
-    println!("{:<20}  {:>6}  {:>8}  QUERY", "TIMESTAMP", "HITS", "TIME");
+    println!(
+        "{:<20}  {:<6}  {:>6}  {:>8}  QUERY",
+        "TIMESTAMP", "ORIGIN", "HITS", "TIME"
+    );
     println!("{}", "-".repeat(72));
 
     for query in queries {
         let ts = query.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
 
         println!(
-            "{:<20}  {:>6}  {:>6}ms  {}",
-            ts, query.hits, query.duration_ms, query.raw_query
+            "{:<20}  {:<6}  {:>6}  {:>6}ms  {}",
+            ts,
+            query.origin,
+            query.hits,
+            query.duration_ms,
+            query.display_query()
         );
     }
 
@@ -67,3 +102,27 @@ pub fn run(args: HistoryArgs) -> ExitCode {
 
     ExitCode::from(0)
 }
+
+fn run_prune(store: &HistoryStore, args: &PruneArgs) -> ExitCode {
+    let mut policy: RetentionPolicy = *store.retention();
+    if let Some(keep) = args.keep {
+        policy.max_events = (keep > 0).then_some(keep);
+    }
+    if let Some(days) = args.max_age_days {
+        policy.max_age_days = (days > 0).then_some(days);
+    }
+
+    match store.prune(&policy) {
+        Ok(stats) => {
+            println!(
+                "Pruned {} entries ({} remaining)",
+                stats.removed, stats.kept
+            );
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            error!("[error] Failed to prune history: {}", e);
+            ExitCode::from(1)
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use log::error;
+
+use crate::commands::index::parse_budget;
+
+/// Hooks git runs after the work tree changed under the user.
+const HOOKS: [&str; 2] = ["post-checkout", "post-merge"];
+
+/// Line marking a hook as written by `blaze hook install`.
+const HOOK_MARKER: &str = "# Installed by `blaze hook install`";
+
+#[derive(Debug, Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub action: HookAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HookAction {
+    /// Add post-checkout and post-merge hooks to the current git repository
+    /// that run a time-boxed `blaze index refresh` over the repository, so
+    /// its index stays fresh without a daemon.
+    ///
+    /// Example:
+    ///   blaze hook install
+    ///   blaze hook install --budget 500ms
+    Install(HookInstallArgs),
+
+    /// Remove the hooks written by `blaze hook install`.
+    Uninstall,
+}
+
+#[derive(Debug, Args)]
+pub struct HookInstallArgs {
+    /// Longest each hook may spend refreshing, e.g. `1s` or `500ms`
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_budget)]
+    pub budget: Duration,
+
+    /// Replace hooks of the same name that blaze did not write
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print the hooks instead of writing them
+    #[arg(long)]
+    pub print: bool,
+}
+
+pub fn run(args: HookArgs) -> ExitCode {
+    let result = match args.action {
+        HookAction::Install(install) => run_install(&install),
+        HookAction::Uninstall => run_uninstall(),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[hook] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Hooks directory of the git repository containing the current directory,
+/// honouring `core.hooksPath`.
+fn hooks_dir() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!("not inside a git repository");
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end());
+    let cwd = std::env::current_dir().context("failed to read current directory")?;
+    Ok(cwd.join(path))
+}
+
+/// Quote `s` for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Script for the git hook `hook`, running `blaze` to refresh the
+/// repository's part of the index within `budget`.
+///
+/// The hook never fails the git command: a missing binary or index, or a
+/// refresh error, is ignored. post-checkout skips checkouts of single
+/// files, which git reports with a `0` third argument.
+fn hook_script(hook: &str, blaze: &Path, budget: Duration) -> String {
+    let guard = if hook == "post-checkout" {
+        "[ \"$3\" = 1 ] || exit 0\n"
+    } else {
+        ""
+    };
+    format!(
+        "#!/bin/sh\n\
+         {HOOK_MARKER}: refresh the blaze index for this repository\n\
+         {guard}\
+         BLAZE={blaze}\n\
+         [ -x \"$BLAZE\" ] || exit 0\n\
+         \"$BLAZE\" index refresh --budget {budget}ms \
+         --under \"$(git rev-parse --show-toplevel)\" >/dev/null 2>&1 || true\n",
+        blaze = shell_quote(&blaze.to_string_lossy()),
+        budget = budget.as_millis(),
+    )
+}
+
+/// Whether the hook at `path` was written by `blaze hook install`.
+fn is_blaze_hook(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|s| s.contains(HOOK_MARKER))
+}
+
+/// Write `contents` to `path` as an executable script.
+fn write_hook(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("failed to make {} executable", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Write the blaze hooks into `dir`, leaving hooks blaze did not write
+/// alone unless `force` is set. Returns the hooks left alone.
+fn install_hooks(dir: &Path, blaze: &Path, budget: Duration, force: bool) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut kept = Vec::new();
+    for hook in HOOKS {
+        let path = dir.join(hook);
+        if path.exists() && !force && !is_blaze_hook(&path) {
+            kept.push(path);
+            continue;
+        }
+        write_hook(&path, &hook_script(hook, blaze, budget))?;
+        eprintln!("[hook] wrote {}", path.display());
+    }
+    Ok(kept)
+}
+
+fn run_install(args: &HookInstallArgs) -> Result<ExitCode> {
+    let blaze = std::env::current_exe().context("failed to locate the blaze executable")?;
+
+    if args.print {
+        for hook in HOOKS {
+            println!("# {hook}");
+            print!("{}", hook_script(hook, &blaze, args.budget));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let kept = install_hooks(&hooks_dir()?, &blaze, args.budget, args.force)?;
+    for path in &kept {
+        eprintln!(
+            "[hook] left {} alone: it was not written by blaze (--force replaces it)",
+            path.display()
+        );
+    }
+    Ok(if kept.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn run_uninstall() -> Result<ExitCode> {
+    let dir = hooks_dir()?;
+    let mut removed = 0;
+    for hook in HOOKS {
+        let path = dir.join(hook);
+        if !is_blaze_hook(&path) {
+            continue;
+        }
+        fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))?;
+        eprintln!("[hook] removed {}", path.display());
+        removed += 1;
+    }
+
+    if removed == 0 {
+        eprintln!("[hook] no blaze hooks installed");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+#[path = "hook_tests.rs"]
+mod tests;
@@ -0,0 +1,53 @@
+use super::*;
+
+#[test]
+fn hook_script_refreshes_the_repository_within_the_budget() {
+    let script = hook_script(
+        "post-merge",
+        Path::new("/opt/it's/blaze"),
+        Duration::from_millis(1500),
+    );
+    assert!(script.starts_with("#!/bin/sh\n"));
+    assert!(script.contains(HOOK_MARKER));
+    assert!(script.contains(r"BLAZE='/opt/it'\''s/blaze'"));
+    assert!(
+        script
+            .contains("index refresh --budget 1500ms --under \"$(git rev-parse --show-toplevel)\"")
+    );
+    assert!(!script.contains("$3"));
+}
+
+#[test]
+fn post_checkout_skips_file_checkouts() {
+    let script = hook_script(
+        "post-checkout",
+        Path::new("/bin/blaze"),
+        Duration::from_secs(1),
+    );
+    assert!(script.contains("[ \"$3\" = 1 ] || exit 0"));
+}
+
+#[test]
+fn install_keeps_foreign_hooks_unless_forced() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("hooks");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("post-merge"), "#!/bin/sh\necho mine\n").unwrap();
+    let blaze = Path::new("/bin/blaze");
+    let budget = Duration::from_secs(1);
+
+    let kept = install_hooks(&dir, blaze, budget, false).unwrap();
+    assert_eq!(kept, [dir.join("post-merge")]);
+    assert!(is_blaze_hook(&dir.join("post-checkout")));
+    assert!(!is_blaze_hook(&dir.join("post-merge")));
+
+    // Reinstalling updates blaze's own hooks.
+    let kept = install_hooks(&dir, blaze, Duration::from_secs(2), false).unwrap();
+    assert_eq!(kept.len(), 1);
+    let script = fs::read_to_string(dir.join("post-checkout")).unwrap();
+    assert!(script.contains("--budget 2000ms"));
+
+    let kept = install_hooks(&dir, blaze, budget, true).unwrap();
+    assert!(kept.is_empty());
+    assert!(is_blaze_hook(&dir.join("post-merge")));
+}
@@ -1,87 +1,1065 @@
-use std::{fs, process::ExitCode};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        Arc,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use blaze_engine::{Index, IndexReader};
-use blaze_indexer::build_initial_index;
-use blaze_runtime::{default_index_path, default_scan_root};
+use anyhow::{Context, Result, bail};
+use blaze_engine::{
+    Index, IndexReader, delta_path, export_bundle,
+    flags::{BuildFlags, FileFlags},
+    import_bundle, manifest_path, parse_size, reclassify_index,
+};
+use blaze_fs::{CachePolicy, NetworkOptions, NetworkPolicy, ScanBackend};
+use blaze_indexer::{
+    EnsureOutcome, ScanOptions, ScanProgress, build_initial_index, ensure_index, refresh_index,
+    refresh_within, verify_index,
+};
+use blaze_runtime::{
+    DEFAULT_HASH_MAX_BYTES, DIR_TIMEOUT_SECS, IndexRegistry, NETWORK_OP_TIMEOUT_SECS,
+    SYSTEM_INDEX_PATH, default_scan_root, find_project_root, project_index_path, registry_path,
+    settings,
+};
+use chrono::{DateTime, Local};
 use clap::{Args, Subcommand};
 use log::error;
+use serde_json::{Value, json};
+
+use crate::commands::query::resolve_index_path;
+
+/// How often `--json` reports the number of files scanned so far.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct IndexArgs {
     #[command(subcommand)]
-    pub action: IndexAction,
+    pub action: Option<IndexAction>,
+
+    /// Root directory to index (defaults to the home directory)
+    pub root: Option<PathBuf>,
+
+    /// Use a per-project index for ROOT, or for the project containing the
+    /// current directory if ROOT is omitted
+    #[arg(long)]
+    pub project: bool,
+
+    /// Build the shared index of system paths, readable by every user, at
+    /// `/var/lib/blaze/system.bin`; ROOT defaults to `/`. Must run as root.
+    /// Daemons started with `--system-index` merge it into their results,
+    /// hiding paths their user could not stat
+    #[arg(long, conflicts_with = "project")]
+    pub system: bool,
+
+    /// Store a content hash of each file up to the size limit, enabling `hash:`
+    /// and hash-checked refreshes (kept by later `--refresh` runs)
+    #[arg(long)]
+    pub hash: bool,
+
+    /// Largest file to hash, in MiB (implies --hash; default 16)
+    #[arg(long, value_name = "MIB")]
+    pub hash_max_mib: Option<u64>,
+
+    /// Precompute every directory's path, making path reconstruction during
+    /// queries a single lookup at the cost of a larger index (kept by later
+    /// `--refresh` runs)
+    #[arg(long)]
+    pub dir_paths: bool,
+
+    /// Leave out files larger than SIZE, e.g. `4G` for disk images and
+    /// other huge blobs (kept by later `--refresh` runs)
+    #[arg(long, value_name = "SIZE", value_parser = parse_file_size)]
+    pub max_file_size: Option<u64>,
+
+    /// Leave out empty files (kept by later `--refresh` runs)
+    #[arg(long)]
+    pub skip_empty: bool,
+
+    /// Leave PATH out of the index; repeat for several paths. Added to the
+    /// paths of the `exclude_paths` setting
+    #[arg(long = "exclude-path", value_name = "PATH")]
+    pub exclude_paths: Vec<PathBuf>,
+
+    /// How to enumerate files: `walk` the directory tree, or ask `git` for
+    /// tracked and unignored files (ROOT must be in a git work tree)
+    #[arg(long, value_name = "BACKEND", default_value = "walk", value_parser = ["walk", "git"])]
+    pub backend: String,
+
+    /// Seconds to wait for one directory to be listed before skipping it,
+    /// e.g. on a dead FUSE mount
+    #[arg(long, value_name = "SECS", default_value_t = DIR_TIMEOUT_SECS)]
+    pub dir_timeout: u64,
+
+    /// What to do with network mounts (NFS, SMB, ...) below ROOT: `skip`
+    /// them, or `scan` them slowly, giving up on unresponsive directories
+    #[arg(long, value_name = "POLICY", default_value = "skip", value_parser = ["skip", "scan"])]
+    pub network: String,
+
+    /// Seconds to wait for one network directory before giving up on it
+    #[arg(long, value_name = "SECS", default_value_t = NETWORK_OP_TIMEOUT_SECS)]
+    pub network_timeout: u64,
+
+    /// What to do with directories holding a CACHEDIR.TAG: index them but
+    /// rank them lower (`flag`), or `skip` their contents (kept by later
+    /// `--refresh` runs)
+    #[arg(long, value_name = "POLICY", default_value = "flag", value_parser = ["flag", "skip"])]
+    pub caches: String,
+
+    /// Once the scan is done, read the files that changed while it ran
+    /// again, so their entries match the files as they ended up
+    #[arg(long)]
+    pub recheck_changed: bool,
+
+    /// Report on stdout as newline-delimited JSON events (started,
+    /// progress, warning, skipped_dir, issue, summary, error) instead of
+    /// messages on stderr
+    #[arg(long)]
+    pub json: bool,
+
+    #[command(flatten)]
+    pub mode: IndexModeArgs,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum IndexAction {
+    /// List the indexes blaze has built, from the registry in the cache dir.
+    ///
+    /// Example:
+    ///   blaze index list
+    ///   blaze index list --json
+    List(ListArgs),
+
+    /// Recompute noise flags in an existing index after the noise
+    /// heuristics changed, without rescanning the tree.
+    ///
+    /// Example:
+    ///   BLAZE_NOISE_BUILD='-target' blaze index reclassify
+    ///   blaze index reclassify --global
+    Reclassify(ReclassifyArgs),
+
+    /// Write the current index, with its delta and build settings, to one
+    /// bundle file for use on another machine.
+    ///
+    /// Example:
+    ///   blaze index export --output share.blz --compress
+    Export(ExportArgs),
+
+    /// Install an index exported with `blaze index export` as the global
+    /// index, without scanning anything.
+    ///
+    /// Example:
+    ///   blaze index import share.blz --remap-root /Volumes/share
+    Import(ImportArgs),
+
+    /// Spend at most a time budget re-listing the directories most likely
+    /// to have changed, then record what changed in the index's delta.
+    /// For shell prompts and git hooks; directories left unlisted wait for
+    /// a later refresh.
+    ///
+    /// Example:
+    ///   blaze index refresh --budget 2s
+    ///   blaze index refresh --budget 500ms --under ~/src/blaze
+    Refresh(RefreshArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Print one JSON object per index
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReclassifyArgs {
+    /// Reclassify the global index, even inside a project with its own index
+    #[arg(long)]
+    pub global: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Bundle file to write
+    #[arg(long, short, value_name = "PATH")]
+    pub output: PathBuf,
+
+    /// LZ4-compress the bundle
+    #[arg(long)]
+    pub compress: bool,
+
+    /// Export the global index, even inside a project with its own index
+    #[arg(long)]
+    pub global: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Bundle file written by `blaze index export`
+    pub bundle: PathBuf,
+
+    /// Where the indexed tree is on this machine, if not where it was on
+    /// the exporting one
+    #[arg(long, value_name = "PATH")]
+    pub remap_root: Option<PathBuf>,
+
+    /// Index file to write (defaults to the global index)
+    #[arg(long, value_name = "PATH")]
+    pub index: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RefreshArgs {
+    /// Longest the refresh may take, e.g. `2s` or `500ms`
+    #[arg(long, value_name = "DURATION", value_parser = parse_budget)]
+    pub budget: Duration,
+
+    /// Only list directories at or below PATH, e.g. a repository
+    #[arg(long, value_name = "PATH")]
+    pub under: Option<PathBuf>,
+
+    /// Refresh the global index, even inside a project with its own index
+    #[arg(long)]
+    pub global: bool,
+}
+
+impl IndexArgs {
+    /// Content hashing size cap requested on the command line, if any.
+    pub fn hash_max_bytes(&self) -> Option<u64> {
+        match self.hash_max_mib {
+            Some(mib) => Some(mib.saturating_mul(1024 * 1024)),
+            None if self.hash => Some(DEFAULT_HASH_MAX_BYTES),
+            None => None,
+        }
+    }
+
+    pub fn scan_options(&self) -> ScanOptions {
+        let backend = match self.backend.as_str() {
+            "git" => ScanBackend::Git,
+            _ => ScanBackend::Walk,
+        };
+        let policy = match self.network.as_str() {
+            "scan" => NetworkPolicy::Scan,
+            _ => NetworkPolicy::Skip,
+        };
+        let caches = match self.caches.as_str() {
+            "skip" => CachePolicy::Skip,
+            _ => CachePolicy::Flag,
+        };
+
+        ScanOptions {
+            hash_max_bytes: self.hash_max_bytes(),
+            dir_paths: self.dir_paths,
+            max_file_bytes: self.max_file_size,
+            skip_empty: self.skip_empty,
+            exclude_paths: settings()
+                .exclude_paths
+                .value
+                .iter()
+                .cloned()
+                .chain(self.exclude_paths.iter().cloned())
+                .collect(),
+            backend,
+            dir_timeout: Duration::from_secs(self.dir_timeout),
+            network: NetworkOptions {
+                policy,
+                op_timeout: Duration::from_secs(self.network_timeout),
+                ..NetworkOptions::default()
+            },
+            caches,
+            progress: None,
+            recheck_changed: self.recheck_changed,
+            registry: Some(registry_path()),
+        }
+    }
+}
+
+/// Parse a size such as `512M` or `4G` into bytes, as for `size:`.
+fn parse_file_size(s: &str) -> Result<u64, String> {
+    parse_size(s).ok_or_else(|| format!("invalid size `{s}` (try e.g. 500M or 4G)"))
+}
+
+/// Parse a time budget such as `2s`, `500ms` or `1.5s`; a bare number is
+/// in seconds.
+pub(crate) fn parse_budget(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{s}` (try e.g. 2s or 500ms)");
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1e-3)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        (mins, 60.0)
+    } else {
+        (s, 1.0)
+    };
+    let value: f64 = number.trim().parse().map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(value * scale)
+        .ok()
+        .filter(|d| !d.is_zero())
+        .ok_or_else(invalid)
+}
+
+/// Mutually exclusive index modes. With no flag, `--rebuild` is assumed.
+#[derive(Debug, Default, Args)]
+#[group(multiple = false)]
+pub struct IndexModeArgs {
+    /// Discard any existing index and build a new one from scratch
+    #[arg(long)]
+    pub rebuild: bool,
+
+    /// Rescan and rewrite the index only if files were added, removed or changed
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Check the existing index for structural problems (exit 1 if any)
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Build only if there is no usable index for the root
+    #[arg(long)]
+    pub if_missing: bool,
+
+    /// Show information about the existing index
+    #[arg(long)]
+    pub info: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMode {
+    Rebuild,
+    Refresh,
+    Verify,
+    IfMissing,
     Info,
-    Build {
-        /// Force rebuild even if index exists and is valid
-        #[arg(long, short = 'f')]
-        force: bool,
-    },
+}
+
+impl IndexMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IndexMode::Rebuild => "rebuild",
+            IndexMode::Refresh => "refresh",
+            IndexMode::Verify => "verify",
+            IndexMode::IfMissing => "if_missing",
+            IndexMode::Info => "info",
+        }
+    }
+}
+
+impl IndexModeArgs {
+    pub fn mode(&self) -> IndexMode {
+        if self.refresh {
+            IndexMode::Refresh
+        } else if self.verify {
+            IndexMode::Verify
+        } else if self.if_missing {
+            IndexMode::IfMissing
+        } else if self.info {
+            IndexMode::Info
+        } else {
+            IndexMode::Rebuild
+        }
+    }
 }
 
 pub fn run(args: IndexArgs) -> ExitCode {
-    match execute(args) {
+    if let Some(action) = &args.action {
+        let result = match action {
+            IndexAction::List(list_args) => list(list_args),
+            IndexAction::Reclassify(reclassify_args) => reclassify(reclassify_args),
+            IndexAction::Export(export_args) => export(export_args),
+            IndexAction::Import(import_args) => import(import_args),
+            IndexAction::Refresh(refresh_args) => refresh_budgeted(refresh_args),
+        };
+        return match result {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("[index] {e:#}");
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    let report = Report::new(args.json);
+    match execute(args, &report) {
         Ok(code) => code,
         Err(e) => {
             error!("[error] {e}");
-            eprintln!("[index] {e}");
+            report.error(&e);
             ExitCode::from(2)
         }
     }
 }
 
-fn execute(args: IndexArgs) -> Result<ExitCode> {
-    match args.action {
-        IndexAction::Build { force } => build_index(force),
-        IndexAction::Info => show_info(),
+fn execute(args: IndexArgs, report: &Report) -> Result<ExitCode> {
+    let mut opts = args.scan_options();
+    opts.progress = report.progress.clone();
+    let (root, index_location) = if args.project {
+        let root = match args.root {
+            Some(root) => root,
+            None => {
+                let cwd = std::env::current_dir().context("failed to read current directory")?;
+                find_project_root(&cwd)
+                    .with_context(|| format!("no project root found above {}", cwd.display()))?
+            }
+        };
+        let index_location = project_index_path(&root);
+        (root, index_location)
+    } else if args.system {
+        let root = args.root.unwrap_or_else(|| PathBuf::from("/"));
+        (root, PathBuf::from(SYSTEM_INDEX_PATH))
+    } else {
+        let root = args.root.unwrap_or_else(default_scan_root);
+        (root, settings().index_path.value.clone())
+    };
+
+    let mode = args.mode.mode();
+    let mut started = json!({
+        "mode": mode.as_str(),
+        "index": index_location.display().to_string(),
+    });
+    // --verify and --info read the index without scanning anything.
+    if !matches!(mode, IndexMode::Verify | IndexMode::Info) {
+        started["root"] = json!(root.display().to_string());
+    }
+    let writes = !matches!(mode, IndexMode::Verify | IndexMode::Info);
+    if args.system && writes {
+        ensure_root()?;
+    }
+    report.emit("started", started);
+
+    let code = match mode {
+        IndexMode::Rebuild => rebuild(&root, &index_location, &opts, report),
+        IndexMode::Refresh => refresh(&root, &index_location, &opts, report),
+        IndexMode::Verify => verify(&index_location, report),
+        IndexMode::IfMissing => if_missing(&root, &index_location, &opts, report),
+        IndexMode::Info => show_info(&index_location, report),
+    }?;
+    if args.system && writes {
+        publish(&index_location)?;
+    }
+    Ok(code)
+}
+
+/// Fail unless running as root, who can list every directory, so the system
+/// index covers everything some user might be allowed to see.
+#[cfg(unix)]
+fn ensure_root() -> Result<()> {
+    // SAFETY: geteuid cannot fail.
+    if unsafe { libc::geteuid() } != 0 {
+        bail!("`blaze index --system` must run as root");
     }
+    Ok(())
 }
 
-pub fn build_index(force: bool) -> Result<ExitCode> {
-    let _ = force;
+#[cfg(not(unix))]
+fn ensure_root() -> Result<()> {
+    Ok(())
+}
 
-    let root = default_scan_root();
+/// Make the system index, its sidecars and their directory readable to
+/// every user; the index is written readable by its owner only.
+#[cfg(unix)]
+fn publish(index_location: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = index_location.parent().unwrap_or_else(|| Path::new("."));
+    let paths = [
+        (dir.to_path_buf(), 0o755),
+        (index_location.to_path_buf(), 0o644),
+        (manifest_path(index_location), 0o644),
+        (delta_path(index_location), 0o644),
+    ];
+    for (path, mode) in paths {
+        match fs::set_permissions(&path, fs::Permissions::from_mode(mode)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e)
+                    .with_context(|| format!("failed to make {} readable", path.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn publish(_index_location: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn rebuild(
+    root: &Path,
+    index_location: &Path,
+    opts: &ScanOptions,
+    report: &Report,
+) -> Result<ExitCode> {
+    let started = Instant::now();
+    let (index, scan_warning) = {
+        let _ticker = report.track_progress();
+        build_initial_index(root, index_location, true, opts)?
+    };
+    report.scan_warning(scan_warning.as_deref());
+
+    let elapsed = started.elapsed().as_secs_f64();
+    report.summary(
+        &[format!(
+            "built {}: {} files, {} dirs in {elapsed:.2}s",
+            root.display(),
+            index.live_file_count(),
+            index.dir_count(),
+        )],
+        build_summary("built", &index, index_location, elapsed),
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
 
-    let index_location = default_index_path();
+fn refresh(
+    root: &Path,
+    index_location: &Path,
+    opts: &ScanOptions,
+    report: &Report,
+) -> Result<ExitCode> {
+    let started = Instant::now();
+    let refreshed = {
+        let _ticker = report.track_progress();
+        refresh_index(root, index_location, true, opts)?
+    };
+    report.scan_warning(refreshed.scan_warning.as_deref());
 
-    let (_, atime_warning) = build_initial_index(&root, &index_location, true)?;
+    let elapsed = started.elapsed().as_secs_f64();
+    let files = refreshed.index.live_file_count();
 
-    if let Some(msg) = atime_warning {
-        eprintln!("{msg}");
+    let (outcome, line) = if let Some(reason) = &refreshed.rebuilt {
+        (
+            "rebuilt",
+            format!("{reason}; built {files} files in {elapsed:.2}s"),
+        )
+    } else if refreshed.rewritten {
+        (
+            "refreshed",
+            format!(
+                "refreshed: +{} -{} ~{} ({files} files{}) in {elapsed:.2}s",
+                refreshed.added,
+                refreshed.removed,
+                refreshed.modified,
+                if refreshed.delta { ", as delta" } else { "" },
+            ),
+        )
+    } else {
+        (
+            "up_to_date",
+            format!("up to date ({files} files) in {elapsed:.2}s"),
+        )
+    };
+
+    let mut summary = build_summary(outcome, &refreshed.index, index_location, elapsed);
+    summary["added"] = json!(refreshed.added);
+    summary["removed"] = json!(refreshed.removed);
+    summary["modified"] = json!(refreshed.modified);
+    summary["rewritten"] = json!(refreshed.rewritten);
+    summary["delta"] = json!(refreshed.delta);
+    if let Some(reason) = &refreshed.rebuilt {
+        summary["reason"] = json!(reason.to_string());
     }
+    report.summary(&[line], summary);
 
     Ok(ExitCode::SUCCESS)
 }
 
-fn show_info() -> Result<ExitCode> {
-    let index_location = default_index_path();
+/// Summary fields shared by the modes that produce an index.
+fn build_summary(outcome: &str, index: &Index, index_location: &Path, elapsed: f64) -> Value {
+    json!({
+        "outcome": outcome,
+        "files": index.live_file_count(),
+        "dirs": index.dir_count(),
+        "index_bytes": fs::metadata(index_location).map(|m| m.len()).ok(),
+        "elapsed_secs": elapsed,
+    })
+}
+
+fn verify(index_location: &Path, report: &Report) -> Result<ExitCode> {
+    let verified = verify_index(index_location)?;
+    let summary = json!({
+        "ok": verified.is_ok(),
+        "files_checked": verified.files_checked,
+        "dirs_checked": verified.dirs_checked,
+        "postings_checked": verified.postings_checked,
+        "issue_count": verified.issue_count,
+    });
+
+    if verified.is_ok() {
+        report.summary(
+            &[format!(
+                "ok: {} files, {} dirs, {} postings checked",
+                verified.files_checked, verified.dirs_checked, verified.postings_checked
+            )],
+            summary,
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for issue in &verified.issues {
+        report.issue(&issue.to_string());
+    }
+    let mut lines = Vec::new();
+    let unlisted = verified.issue_count - verified.issues.len();
+    if unlisted > 0 {
+        lines.push(format!("... and {unlisted} more"));
+    }
+    lines.push(format!(
+        "verification failed with {} issue(s); run `blaze index --rebuild`",
+        verified.issue_count
+    ));
+    report.summary(&lines, summary);
+
+    Ok(ExitCode::from(1))
+}
+
+fn if_missing(
+    root: &Path,
+    index_location: &Path,
+    opts: &ScanOptions,
+    report: &Report,
+) -> Result<ExitCode> {
+    let started = Instant::now();
+    let outcome = {
+        let _ticker = report.track_progress();
+        ensure_index(root, index_location, true, opts)?
+    };
+    let elapsed = started.elapsed().as_secs_f64();
+
+    match outcome {
+        EnsureOutcome::Existing(index) => {
+            report.summary(
+                &[format!(
+                    "using existing index at {} ({} files)",
+                    index_location.display(),
+                    index.live_file_count()
+                )],
+                build_summary("existing", &index, index_location, elapsed),
+            );
+        }
+        EnsureOutcome::Built {
+            index,
+            reason,
+            scan_warning,
+        } => {
+            report.scan_warning(scan_warning.as_deref());
+            let mut summary = build_summary("built", &index, index_location, elapsed);
+            summary["reason"] = json!(reason.to_string());
+            report.summary(
+                &[format!("{reason}; built {} files", index.live_file_count())],
+                summary,
+            );
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
 
+fn show_info(index_location: &Path, report: &Report) -> Result<ExitCode> {
     if !index_location.exists() {
-        eprintln!("[index] no index found at {}", index_location.display());
+        report.summary(
+            &[format!("no index found at {}", index_location.display())],
+            json!({ "exists": false }),
+        );
         // Treat absence as a "soft" failure with non-zero exit
         return Ok(ExitCode::from(1));
     }
 
-    let index = Index::open(&index_location)?;
+    let index = Index::open(index_location)?;
 
     let root = index.root_path().unwrap_or("<unknown>");
 
     // Use the IndexReader API for counts.
-    let file_count = index.get_file_count();
+    let file_count = index.live_file_count();
     let dir_count = index.dir_count();
 
-    let meta = fs::metadata(&index_location)?;
+    let meta = fs::metadata(index_location)?;
     let size_bytes = meta.len();
+    let skip_empty = index.build_flags().contains(BuildFlags::SKIP_EMPTY);
+    let skip_caches = index.build_flags().contains(BuildFlags::SKIP_CACHES);
+
+    let mut lines = vec![
+        format!("location: {}", index_location.display()),
+        format!("root:     {}", root),
+        format!("files:    {}", file_count),
+        format!("dirs:     {}", dir_count),
+        format!("size:     {} bytes", size_bytes),
+    ];
+    lines.push(match index.hash_max_bytes() {
+        Some(max) => format!("hashing:  files up to {} bytes", max),
+        None => "hashing:  off".to_string(),
+    });
+    lines.push(format!(
+        "dir paths: {}",
+        if index.has_dir_paths() {
+            "stored"
+        } else {
+            "off"
+        }
+    ));
+    lines.push(match index.max_file_bytes() {
+        Some(max) => format!("max size: {} bytes", max),
+        None => "max size: none".to_string(),
+    });
+    if skip_empty {
+        lines.push("empty files skipped".to_string());
+    }
+    if skip_caches {
+        lines.push("cache contents skipped".to_string());
+    }
+    let scan_times = index.scan_times();
+    if let Some((started, finished)) = scan_times {
+        lines.push(format!(
+            "scanned:  {} to {}",
+            local_time(started),
+            local_time(finished)
+        ));
+    }
+    let changed = (0..index.get_file_count() as u32)
+        .filter(|&f| {
+            !index.is_file_removed(f)
+                && index
+                    .get_file_flags(f)
+                    .contains(FileFlags::CHANGED_DURING_SCAN)
+        })
+        .count();
+    if changed > 0 {
+        lines.push(format!(
+            "{changed} file(s) changed while the scan ran and may be out of date"
+        ));
+    }
+
+    report.summary(
+        &lines,
+        json!({
+            "exists": true,
+            "root": root,
+            "files": file_count,
+            "dirs": dir_count,
+            "index_bytes": size_bytes,
+            "hash_max_bytes": index.hash_max_bytes(),
+            "dir_paths": index.has_dir_paths(),
+            "max_file_bytes": index.max_file_bytes(),
+            "skip_empty": skip_empty,
+            "skip_caches": skip_caches,
+            "scan_started_secs": scan_times.map(|t| t.0),
+            "scan_finished_secs": scan_times.map(|t| t.1),
+            "changed_during_scan": changed,
+        }),
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Format the Unix time `secs` in the local timezone.
+fn local_time(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn list(args: &ListArgs) -> Result<ExitCode> {
+    let path = registry_path();
+    let registry = IndexRegistry::load(&path)
+        .with_context(|| format!("failed to read index registry {}", path.display()))?;
+
+    if args.json {
+        for e in &registry.entries {
+            let obj = json!({
+                "root": e.root.display().to_string(),
+                "index": e.path.display().to_string(),
+                "generation": e.generation,
+                "built_secs": e.built_secs,
+                "files": e.files,
+                "exists": e.path.exists(),
+            });
+            println!("{obj}");
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if registry.entries.is_empty() {
+        eprintln!("[index] no indexes recorded in {}", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!(
+        "{:<40} {:>4} {:<16} {:>9}  INDEX",
+        "ROOT", "GEN", "BUILT", "FILES"
+    );
+    for e in &registry.entries {
+        let built = DateTime::from_timestamp(e.built_secs as i64, 0)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let missing = if e.path.exists() { "" } else { " (missing)" };
+        println!(
+            "{:<40} {:>4} {:<16} {:>9}  {}{missing}",
+            e.root.display(),
+            e.generation,
+            built,
+            e.files,
+            e.path.display()
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn reclassify(args: &ReclassifyArgs) -> Result<ExitCode> {
+    let path = resolve_index_path(args.global);
+    let started = Instant::now();
+    let report = reclassify_index(&path)
+        .with_context(|| format!("failed to reclassify {}", path.display()))?;
+
+    if report.changed == 0 {
+        eprintln!(
+            "[index] noise flags of all {} files are up to date",
+            report.files
+        );
+    } else {
+        eprintln!(
+            "[index] reclassified {} of {} files in {:.2}s",
+            report.changed,
+            report.files,
+            started.elapsed().as_secs_f64()
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn refresh_budgeted(args: &RefreshArgs) -> Result<ExitCode> {
+    let path = resolve_index_path(args.global);
+    let under = match &args.under {
+        Some(under) => Some(
+            std::path::absolute(under)
+                .with_context(|| format!("failed to resolve {}", under.display()))?,
+        ),
+        None => None,
+    };
+    let started = Instant::now();
+    let refreshed = refresh_within(
+        &path,
+        under.as_deref(),
+        true,
+        &ScanOptions::default(),
+        args.budget,
+    )
+    .with_context(|| format!("failed to refresh {}", path.display()))?;
+    let elapsed = started.elapsed().as_secs_f64();
 
-    eprintln!("[index] location: {}", index_location.display());
-    eprintln!("[index] root:     {}", root);
-    eprintln!("[index] files:    {}", file_count);
-    eprintln!("[index] dirs:     {}", dir_count);
-    eprintln!("[index] size:     {} bytes", size_bytes);
+    let left = match refreshed.dirs_left {
+        0 => String::new(),
+        n => format!(", {n} left for later"),
+    };
+    let listed = format!("{} dirs listed{left}", refreshed.dirs_scanned);
+    if refreshed.rewritten {
+        eprintln!(
+            "[index] refreshed: +{} -{} ~{} ({listed}) in {elapsed:.2}s",
+            refreshed.added, refreshed.removed, refreshed.modified
+        );
+    } else {
+        eprintln!("[index] no changes found ({listed}) in {elapsed:.2}s");
+    }
+    if refreshed.oversized {
+        eprintln!("[index] the delta has grown large; run `blaze index --refresh` to fold it in");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn export(args: &ExportArgs) -> Result<ExitCode> {
+    let path = resolve_index_path(args.global);
+    let config = export_bundle(&path, &args.output, args.compress)
+        .with_context(|| format!("failed to export {}", path.display()))?;
+
+    let bytes = fs::metadata(&args.output)
+        .map(|m| m.len())
+        .unwrap_or_default();
+    eprintln!(
+        "[index] exported {} ({} files) to {} ({bytes} bytes)",
+        config.root,
+        config.files,
+        args.output.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn import(args: &ImportArgs) -> Result<ExitCode> {
+    let path = args
+        .index
+        .clone()
+        .unwrap_or_else(|| settings().index_path.value.clone());
+    // Stored paths are joined to the root, so it must not be relative.
+    let remap_root = args
+        .remap_root
+        .as_deref()
+        .map(|root| {
+            fs::canonicalize(root)
+                .with_context(|| format!("cannot use {} as the root", root.display()))
+        })
+        .transpose()?;
+    let config = import_bundle(&args.bundle, &path, remap_root.as_deref())
+        .with_context(|| format!("failed to import {}", args.bundle.display()))?;
 
+    let index = Index::open(&path)
+        .with_context(|| format!("imported index {} does not open", path.display()))?;
+    let root = index.root_path().unwrap_or_default();
+    let absolute = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+    if let Err(e) = IndexRegistry::update(
+        &registry_path(),
+        &absolute,
+        Path::new(root),
+        index.created_secs().unwrap_or_default(),
+        index.live_file_count() as u64,
+    ) {
+        eprintln!("[index] failed to update the index registry: {e}");
+    }
+
+    if root != config.root {
+        eprintln!("[index] remapped root {} to {root}", config.root);
+    } else if !Path::new(root).exists() {
+        eprintln!("[index] root {root} does not exist here; import again with --remap-root");
+    }
+    eprintln!(
+        "[index] imported {} files into {}",
+        index.live_file_count(),
+        path.display()
+    );
     Ok(ExitCode::SUCCESS)
 }
+
+/// Where `blaze index` reports: `[index]` lines on stderr, or with `--json`
+/// one JSON event per line on stdout.
+struct Report {
+    started: Instant,
+    /// Set in JSON mode, where the scan's progress is reported.
+    progress: Option<Arc<ScanProgress>>,
+}
+
+impl Report {
+    fn new(json: bool) -> Self {
+        Self {
+            started: Instant::now(),
+            progress: json.then(Arc::default),
+        }
+    }
+
+    fn is_json(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    /// Write one event in JSON mode; a no-op otherwise.
+    fn emit(&self, event: &str, fields: Value) {
+        if !self.is_json() {
+            return;
+        }
+        let mut obj = json!({ "event": event });
+        if let Value::Object(fields) = fields {
+            obj.as_object_mut().unwrap().extend(fields);
+        }
+        println!("{obj}");
+    }
+
+    /// Emit progress events until the returned ticker is dropped.
+    fn track_progress(&self) -> Option<ProgressTicker> {
+        let progress = Arc::clone(self.progress.as_ref()?);
+        let started = self.started;
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(PROGRESS_INTERVAL) {
+                let event = json!({
+                    "event": "progress",
+                    "files": progress.files(),
+                    "elapsed_secs": started.elapsed().as_secs_f64(),
+                });
+                println!("{event}");
+            }
+        });
+        Some(ProgressTicker {
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Report what the scan left out: a warning per line of `msg`, then in
+    /// JSON mode the skipped directories one by one.
+    fn scan_warning(&self, msg: Option<&str>) {
+        for line in msg.into_iter().flat_map(str::lines) {
+            if self.is_json() {
+                self.emit("warning", json!({ "message": line }));
+            } else {
+                eprintln!("[index] {line}");
+            }
+        }
+        if let Some(progress) = &self.progress {
+            for dir in progress.take_skipped_dirs() {
+                self.emit(
+                    "skipped_dir",
+                    json!({
+                        "path": dir.path.display().to_string(),
+                        "reason": dir.reason.as_str(),
+                    }),
+                );
+            }
+        }
+    }
+
+    fn issue(&self, issue: &str) {
+        if self.is_json() {
+            self.emit("issue", json!({ "message": issue }));
+        } else {
+            eprintln!("[index] {issue}");
+        }
+    }
+
+    /// Finish with `lines` on stderr, or a summary event built from `fields`.
+    fn summary(&self, lines: &[String], mut fields: Value) {
+        if !self.is_json() {
+            for line in lines {
+                eprintln!("[index] {line}");
+            }
+            return;
+        }
+        if fields.get("elapsed_secs").is_none() {
+            fields["elapsed_secs"] = json!(self.started.elapsed().as_secs_f64());
+        }
+        self.emit("summary", fields);
+    }
+
+    fn error(&self, e: &anyhow::Error) {
+        if self.is_json() {
+            self.emit("error", json!({ "message": format!("{e:#}") }));
+        } else {
+            eprintln!("[index] {e:#}");
+        }
+    }
+}
+
+/// Stops the progress thread started by [`Report::track_progress`] on drop.
+struct ProgressTicker {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for ProgressTicker {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "index_tests.rs"]
+mod tests;
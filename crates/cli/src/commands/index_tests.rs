@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn parse_budget_reads_seconds_and_milliseconds() {
+    let cases = [
+        ("2s", Duration::from_secs(2)),
+        ("500ms", Duration::from_millis(500)),
+        ("1.5s", Duration::from_millis(1500)),
+        ("1m", Duration::from_secs(60)),
+        ("3", Duration::from_secs(3)),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_budget(input), Ok(expected), "{input}");
+    }
+}
+
+#[test]
+fn parse_budget_rejects_empty_and_zero_budgets() {
+    for input in ["", "s", "0s", "-1s", "2h", "fast"] {
+        assert!(parse_budget(input).is_err(), "{input}");
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, anyhow};
+use blaze_engine::{DirId, FileId, Index, IndexReader, helpers::join_root_rel};
+use blaze_runtime::settings;
+use clap::Args;
+use globset::{GlobBuilder, GlobMatcher};
+use log::error;
+
+use crate::collate::Collation;
+
+#[derive(Debug, Args)]
+pub struct LocateArgs {
+    /// Patterns to look for: a substring of the path, or a glob over the
+    /// whole path if it contains `*`, `?` or `[`
+    #[arg(value_name = "PATTERN", required = true)]
+    pub patterns: Vec<String>,
+
+    /// Ignore case when matching
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+
+    /// Match against the file name only, not the whole path
+    #[arg(short = 'b', long)]
+    pub basename: bool,
+
+    /// Only print files matching all patterns (default: any)
+    #[arg(short = 'A', long)]
+    pub all: bool,
+
+    /// Only print files that still exist
+    #[arg(short = 'e', long)]
+    pub existing: bool,
+
+    /// Print the number of matches instead of the paths
+    #[arg(short = 'c', long)]
+    pub count: bool,
+
+    /// Stop after N matches
+    #[arg(short = 'l', short_alias = 'n', long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Separate paths with NUL instead of newline
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// Index file to search instead of the global index
+    #[arg(short = 'd', long, value_name = "PATH")]
+    pub database: Option<PathBuf>,
+    /// How to order the paths: by their `byte`s, or by the collation rules
+    /// of `LC_COLLATE` (`locale`, needs the `collate` feature)
+    #[arg(long, value_name = "ORDER", default_value = "byte", value_parser = ["byte", "locale"])]
+    pub collate: String,
+}
+
+/// Exit codes follow mlocate: 0 if something matched, 1 if nothing did.
+pub fn run(args: LocateArgs) -> ExitCode {
+    match execute(args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[locate] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: LocateArgs) -> Result<ExitCode> {
+    let matcher = PathMatcher::new(&args.patterns, args.ignore_case, args.all)?;
+
+    let index_path = args
+        .database
+        .clone()
+        .unwrap_or_else(|| settings().index_path.value.clone());
+    let index = Index::open(&index_path)
+        .with_context(|| format!("failed to open index at {}", index_path.display()))?;
+
+    let mut found: Vec<String> = Vec::new();
+    for_each_path(&index, |fid, path| {
+        let subject = if args.basename {
+            index.get_file_name(fid)
+        } else {
+            path
+        };
+        if matcher.is_match(subject) {
+            found.push(path.to_owned());
+        }
+    });
+    Collation::from_arg(&args.collate).sort(&mut found);
+
+    if args.existing {
+        found.retain(|p| fs::symlink_metadata(p).is_ok());
+    }
+    if let Some(limit) = args.limit {
+        found.truncate(limit);
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if args.count {
+        writeln!(out, "{}", found.len())?;
+    } else {
+        let terminator = if args.null { b'\0' } else { b'\n' };
+        for p in &found {
+            out.write_all(p.as_bytes())?;
+            out.write_all(&[terminator])?;
+        }
+    }
+    out.flush()?;
+
+    Ok(if found.is_empty() {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Call `f` with the absolute path of every indexed file, in index order.
+///
+/// Directory paths are rebuilt once per directory rather than per file.
+pub(crate) fn for_each_path<F>(index: &Index, mut f: F)
+where
+    F: FnMut(FileId, &str),
+{
+    let mut dir_paths: Vec<Option<String>> = vec![None; index.dir_count()];
+    let root = index.root_path().unwrap_or_default().to_owned();
+
+    for fid in 0..index.get_file_count() as FileId {
+        if index.is_file_removed(fid) {
+            continue;
+        }
+        let dir_id: DirId = index.get_file_dir_id(fid);
+        let dir = match dir_paths.get_mut(dir_id as usize) {
+            Some(slot) => slot
+                .get_or_insert_with(|| index.reconstruct_dir_path(dir_id))
+                .as_str(),
+            // Files directly under the root.
+            None => root.as_str(),
+        };
+
+        f(fid, &join_root_rel(dir, index.get_file_name(fid)));
+    }
+}
+
+/// mlocate-style matching of paths against one or more patterns.
+pub(crate) struct PathMatcher {
+    patterns: Vec<Pattern>,
+    ignore_case: bool,
+    match_all: bool,
+}
+
+enum Pattern {
+    /// Lowercased when matching ignores case.
+    Substring(String),
+    /// Anchored at both ends of the path.
+    Glob(GlobMatcher),
+}
+
+impl PathMatcher {
+    pub(crate) fn new(patterns: &[String], ignore_case: bool, match_all: bool) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                if p.contains(['*', '?', '[']) {
+                    GlobBuilder::new(p)
+                        .case_insensitive(ignore_case)
+                        .backslash_escape(true)
+                        .build()
+                        .map(|g| Pattern::Glob(g.compile_matcher()))
+                        .map_err(|e| anyhow!("invalid pattern `{p}`: {e}"))
+                } else if ignore_case {
+                    Ok(Pattern::Substring(p.to_lowercase()))
+                } else {
+                    Ok(Pattern::Substring(p.clone()))
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            patterns,
+            ignore_case,
+            match_all,
+        })
+    }
+
+    pub(crate) fn is_match(&self, path: &str) -> bool {
+        let folded = self.ignore_case.then(|| path.to_lowercase());
+        let haystack = folded.as_deref().unwrap_or(path);
+
+        let hit = |p: &Pattern| match p {
+            Pattern::Substring(s) => haystack.contains(s.as_str()),
+            Pattern::Glob(g) => g.is_match(path),
+        };
+
+        if self.match_all {
+            self.patterns.iter().all(hit)
+        } else {
+            self.patterns.iter().any(hit)
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "locate_tests.rs"]
+mod tests;
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use blaze_engine::IndexBuilder;
+use blaze_fs::FileRecord;
+
+use super::*;
+
+fn matcher(patterns: &[&str], ignore_case: bool, all: bool) -> PathMatcher {
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+    PathMatcher::new(&patterns, ignore_case, all).unwrap()
+}
+
+#[test]
+fn plain_patterns_match_anywhere_in_the_path() {
+    let m = matcher(&["src/ma"], false, false);
+    assert!(m.is_match("/home/me/src/main.rs"));
+    assert!(!m.is_match("/home/me/SRC/main.rs"));
+
+    let m = matcher(&["src/ma"], true, false);
+    assert!(m.is_match("/home/me/SRC/Main.rs"));
+}
+
+#[test]
+fn glob_patterns_match_the_whole_path() {
+    let m = matcher(&["*.rs"], false, false);
+    assert!(m.is_match("/src/main.rs"));
+    assert!(!m.is_match("/src/main.rs.bak"));
+
+    let m = matcher(&["main*"], false, false);
+    assert!(!m.is_match("/src/main.rs"));
+
+    let m = matcher(&["*/README.*"], true, false);
+    assert!(m.is_match("/src/readme.md"));
+}
+
+#[test]
+fn any_pattern_matches_unless_all_is_requested() {
+    let any = matcher(&["docs", ".md"], false, false);
+    let all = matcher(&["docs", ".md"], false, true);
+
+    assert!(any.is_match("/src/docs/intro.txt"));
+    assert!(!all.is_match("/src/docs/intro.txt"));
+    assert!(all.is_match("/src/docs/intro.md"));
+}
+
+#[test]
+fn invalid_globs_are_rejected() {
+    let patterns = vec!["[abc".to_owned()];
+    assert!(PathMatcher::new(&patterns, false, false).is_err());
+}
+
+#[test]
+fn paths_are_rebuilt_from_the_index() {
+    let mut builder = IndexBuilder::new(PathBuf::from("/src"));
+    builder.add_batch([
+        FileRecord::test_file("/src/top.rs"),
+        FileRecord::test_file("/src/docs/README.md"),
+        FileRecord::test_file("/src/docs/api/v1.md"),
+    ]);
+    let index = Index::from_staged(&builder.finish()).unwrap();
+
+    let mut paths = Vec::new();
+    for_each_path(&index, |fid, path| {
+        assert!(Path::new(path).ends_with(index.get_file_name(fid)));
+        paths.push(path.to_owned());
+    });
+    paths.sort();
+
+    assert_eq!(
+        paths,
+        ["/src/docs/README.md", "/src/docs/api/v1.md", "/src/top.rs"]
+    );
+}
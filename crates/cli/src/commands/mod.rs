@@ -1,11 +1,36 @@
+pub mod bench;
+pub mod config;
+pub mod daemon;
+pub mod dirs;
+pub mod find;
 pub mod history;
+pub mod hook;
 pub mod index;
+pub mod locate;
+pub mod pin;
+pub mod plan;
 pub mod query;
+pub mod status;
+pub mod top;
+pub mod updatedb;
+pub mod version;
 
+pub use bench::BenchDaemonArgs;
 use clap::{Parser, Subcommand};
+pub use config::ConfigArgs;
+pub use daemon::DaemonArgs;
+pub use dirs::DirsArgs;
+pub use find::FindArgs;
 pub use history::HistoryArgs;
+pub use hook::HookArgs;
 pub use index::IndexArgs;
+pub use locate::LocateArgs;
+pub use pin::PinArgs;
+pub use plan::PlanArgs;
 pub use query::QueryArgs;
+pub use status::StatusArgs;
+pub use top::TopArgs;
+pub use updatedb::UpdatedbArgs;
 
 /// Common error type for command handlers
 pub type CommandResult<T> = Result<T, Box<dyn std::error::Error>>;
@@ -13,7 +38,7 @@ pub type CommandResult<T> = Result<T, Box<dyn std::error::Error>>;
 #[derive(Parser, Debug)]
 #[command(
     name = "blaze",
-    version,
+    version = blaze_protocol::BUILD,
     about = "Blaze - a fast local code search engine",
     propagate_version = true
 )]
@@ -30,6 +55,7 @@ pub enum Command {
     /// Example:
     ///   blaze index /home/andrew/projects
     ///   blaze --index-dir /var/lib/blaze index --rebuild /data
+    ///   blaze index --project --refresh
     Index(IndexArgs),
 
     /// Execute a search query against the index.
@@ -39,6 +65,85 @@ pub enum Command {
     ///   blaze query -n 20 'name:Cargo.toml'
     Query(QueryArgs),
 
+    /// Show how a query would be evaluated, without running it.
+    ///
+    /// Example:
+    ///   blaze plan 'ext:rs mmap'
+    Plan(PlanArgs),
+
+    /// Search the index with a subset of find(1) syntax.
+    ///
+    /// Example:
+    ///   blaze find ~/src -name '*.rs' -size +10k
+    ///   blaze find . -maxdepth 2 -iname 'readme*' -o -mtime -7
+    Find(FindArgs),
+
+    /// Search directories rather than files, best match first.
+    ///
+    /// Example:
+    ///   blaze dirs 'src commands'
+    ///   cd "$(blaze dirs --first blaze)"
+    Dirs(DirsArgs),
+
+    /// Print indexed paths containing a pattern, like locate(1).
+    ///
+    /// Example:
+    ///   blaze locate -i readme
+    ///   blaze locate -b '*.toml'
+    Locate(LocateArgs),
+
+    /// Rebuild the global index, like updatedb(8).
+    ///
+    /// Example:
+    ///   blaze updatedb
+    ///   blaze updatedb -U /data
+    Updatedb(UpdatedbArgs),
+
+    /// Pin favorite files and directories to the top of query results.
+    ///
+    /// Example:
+    ///   blaze pin add ~/notes/todo.md
+    ///   blaze query 'pinned:yes ext:md'
+    Pin(PinArgs),
+
     /// Show past queries.
     History(HistoryArgs),
+
+    /// Show the state of the index and daemon.
+    ///
+    /// Example:
+    ///   blaze status --verbose
+    Status(StatusArgs),
+
+    /// Show a live list of recently changed files.
+    ///
+    /// Example:
+    ///   blaze top
+    ///   blaze top --root ~/projects 'ext:rs'
+    Top(TopArgs),
+
+    /// Keep a repository's index fresh from git hooks, without a daemon.
+    ///
+    /// Example:
+    ///   blaze hook install --budget 500ms
+    Hook(HookArgs),
+
+    /// Show the settings in effect and where each came from.
+    ///
+    /// Example:
+    ///   blaze config show
+    Config(ConfigArgs),
+
+    /// Manage the background daemon service.
+    ///
+    /// Example:
+    ///   blaze daemon install --socket-activation
+    Daemon(DaemonArgs),
+
+    /// Fire concurrent queries at the daemon and report latency percentiles.
+    ///
+    /// Example:
+    ///   blaze bench-daemon -n 5000 -c 16 -q 'ext:rs main' -q readme
+    #[command(hide = true)]
+    BenchDaemon(BenchDaemonArgs),
 }
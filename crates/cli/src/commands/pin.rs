@@ -0,0 +1,112 @@
+use std::{
+    fs,
+    path::{self, Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, bail};
+use blaze_runtime::PinStore;
+use clap::{Args, Subcommand};
+use log::error;
+
+#[derive(Debug, Args)]
+pub struct PinArgs {
+    #[command(subcommand)]
+    pub action: PinAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PinAction {
+    /// Pin files or directories so they rank first in every query.
+    ///
+    /// Example:
+    ///   blaze pin add ~/notes/todo.md ~/src/blaze
+    Add(PinPaths),
+
+    /// Unpin files or directories.
+    ///
+    /// Example:
+    ///   blaze pin remove ~/src/blaze
+    Remove(PinPaths),
+
+    /// List pinned paths.
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct PinPaths {
+    /// Paths to pin or unpin
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+pub fn run(args: PinArgs) -> ExitCode {
+    match execute(args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[pin] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: PinArgs) -> Result<ExitCode> {
+    let Some(store) = PinStore::new() else {
+        bail!("cannot locate the state directory for pins");
+    };
+
+    match args.action {
+        PinAction::Add(args) => {
+            for path in &args.paths {
+                let path = fs::canonicalize(path)
+                    .with_context(|| format!("cannot pin {}", path.display()))?;
+                let path = pin_str(&path)?;
+                if store.add(path).context("failed to save pins")? {
+                    println!("pinned {path}");
+                } else {
+                    println!("already pinned {path}");
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        PinAction::Remove(args) => {
+            let mut missing = false;
+            for path in &args.paths {
+                // Deleted files can still be unpinned by their old path.
+                let path = match fs::canonicalize(path) {
+                    Ok(path) => path,
+                    Err(_) => path::absolute(path)
+                        .with_context(|| format!("cannot resolve {}", path.display()))?,
+                };
+                let path = pin_str(&path)?;
+                if store.remove(path).context("failed to save pins")? {
+                    println!("unpinned {path}");
+                } else {
+                    eprintln!("[pin] not pinned: {path}");
+                    missing = true;
+                }
+            }
+            Ok(if missing {
+                ExitCode::from(1)
+            } else {
+                ExitCode::SUCCESS
+            })
+        }
+        PinAction::List => {
+            let pins = store.list().context("failed to read pins")?;
+            if pins.is_empty() {
+                println!("No pins yet.");
+            }
+            for pin in pins {
+                println!("{pin}");
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn pin_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .with_context(|| format!("cannot pin non-UTF-8 path {}", path.display()))
+}
@@ -0,0 +1,220 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use blaze_engine::{
+    CandidateCutoffs, Cost, Index, IndexReader, PlanKind, PlanNode, TextAnchor, TextPlan,
+    TextStrategy, Trigram, parse_query, plan_query,
+};
+use blaze_runtime::{planner_overrides, settings};
+use clap::Args;
+use log::error;
+
+#[derive(Debug, Args)]
+pub struct PlanArgs {
+    /// The query expression to plan
+    pub query: String,
+
+    /// Index file to plan against instead of the global index
+    #[arg(long, value_name = "PATH")]
+    pub index: Option<PathBuf>,
+}
+
+pub fn run(args: PlanArgs) -> ExitCode {
+    match execute(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[plan] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: &PlanArgs) -> Result<()> {
+    let index_path = args
+        .index
+        .clone()
+        .unwrap_or_else(|| settings().index_path.value.clone());
+    let index = Index::open(&index_path)
+        .with_context(|| format!("failed to open index at {}", index_path.display()))?;
+
+    let plan = plan_query(&index, &parse_query(&args.query).rewrite());
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "plan for `{}` over {} files (cost: estimated candidates touched)",
+        args.query,
+        index.live_file_count()
+    )?;
+    let cutoffs = CandidateCutoffs::for_index(&index);
+    writeln!(
+        out,
+        "text terms scan up to {} candidates and verify once trigrams leave {}; \
+         trigram selection: {}",
+        cutoffs.small,
+        cutoffs.early_verify,
+        planner_overrides()
+            .trigram_selection
+            .value
+            .unwrap_or_default()
+    )?;
+    write_node(&mut out, &plan, 0, Role::Plain)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Where a node sits in its parent AND.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Plain,
+    /// Evaluated first; its matches are the other children's candidates.
+    Seed,
+    /// Only checked against the seed's matches, in the seed's single pass.
+    Verified,
+}
+
+fn write_node(out: &mut impl Write, node: &PlanNode, depth: usize, role: Role) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    let label = match &node.kind {
+        PlanKind::And {
+            impossible: true, ..
+        } => "AND: a term matches no file, nothing is evaluated".to_owned(),
+        PlanKind::And {
+            pure_text: true, ..
+        } => "AND: seed from one term, verify all terms in one pass".to_owned(),
+        PlanKind::And { .. } => "AND: each child filters the previous one's matches".to_owned(),
+        PlanKind::Or { pure_text: true } => {
+            "OR: verify every term's candidates together".to_owned()
+        }
+        PlanKind::Or { .. } => "OR: union of children".to_owned(),
+        PlanKind::Not {
+            ext_complement: true,
+        } => "NOT: skip files by extension".to_owned(),
+        PlanKind::Not { .. } => "NOT: candidates minus the child's matches".to_owned(),
+        PlanKind::Text { term, plan } => match plan.anchor {
+            TextAnchor::Anywhere => format!("text {term:?}"),
+            TextAnchor::Start => format!("name starts with {term:?}"),
+            TextAnchor::End => format!("name ends with {term:?}"),
+            TextAnchor::Whole => format!("name is {term:?}"),
+        },
+        PlanKind::Predicate(pred) => pred.clone(),
+    };
+    let marker = if role == Role::Seed { "  [seed]" } else { "" };
+    writeln!(out, "{indent}{label}  cost {}{marker}", cost(node.cost))?;
+
+    if let PlanKind::Text { plan, .. } = &node.kind {
+        if role == Role::Verified {
+            writeln!(
+                out,
+                "{indent}    strategy: substring check of the seed's matches"
+            )?;
+        } else {
+            write_text_plan(out, plan, &indent)?;
+        }
+    }
+    let pure_text = matches!(
+        node.kind,
+        PlanKind::And {
+            pure_text: true,
+            ..
+        }
+    );
+    for (i, child) in node.children.iter().enumerate() {
+        let role = match node.seed {
+            Some(seed) if seed == i => Role::Seed,
+            Some(_) if pure_text => Role::Verified,
+            _ => Role::Plain,
+        };
+        write_node(out, child, depth + 1, role)?;
+    }
+    Ok(())
+}
+
+fn write_text_plan(out: &mut impl Write, plan: &TextPlan, indent: &str) -> io::Result<()> {
+    let (selected, selected_names): (&[_], &[_]) = match &plan.strategy {
+        TextStrategy::Trigrams(tris) => (tris, &[]),
+        TextStrategy::NameTrigrams(tris) => (&[], tris),
+        _ => (&[], &[]),
+    };
+    write_trigrams(out, indent, "trigrams", &plan.trigrams, selected)?;
+    write_trigrams(
+        out,
+        indent,
+        "name trigrams",
+        &plan.name_trigrams,
+        selected_names,
+    )?;
+    if let Some(selection) = plan.selection {
+        writeln!(out, "{indent}    selection: {selection}")?;
+    }
+
+    let anchored = plan.anchor != TextAnchor::Anywhere;
+    let strategy = match &plan.strategy {
+        TextStrategy::NoMatch if anchored => "no match: a trigram is in no file name".to_owned(),
+        TextStrategy::NoMatch => "no match: a trigram is in no path".to_owned(),
+        TextStrategy::NameScan if anchored => "linear scan of file names".to_owned(),
+        TextStrategy::NameScan => "linear scan of file names (needle under 3 chars)".to_owned(),
+        TextStrategy::PathScan => "linear scan of names, then full paths".to_owned(),
+        TextStrategy::Trigrams(tris) => format!(
+            "intersect {} trigram posting list(s) (*), keep files below directories \
+             named like {:?}, verify the rest",
+            tris.len(),
+            plan.needle
+        ),
+        TextStrategy::NameTrigrams(tris) if tris.is_empty() => format!(
+            "no file name has {:?}: files below directories named like it",
+            plan.needle
+        ),
+        TextStrategy::NameTrigrams(tris) if anchored => format!(
+            "intersect {} name trigram posting list(s) (*), then verify names",
+            tris.len()
+        ),
+        TextStrategy::NameTrigrams(tris) => format!(
+            "intersect {} name trigram posting list(s) (*), verify names, \
+             add files below directories named like {:?}",
+            tris.len(),
+            plan.needle
+        ),
+        TextStrategy::Extension(ext) => {
+            format!("files with extension {ext:?}, then verify how names end")
+        }
+        TextStrategy::ExactName => {
+            "files with exactly this name, from the exact name index".to_owned()
+        }
+    };
+    writeln!(out, "{indent}    strategy: {strategy}")
+}
+
+/// One line of trigrams with their posting counts, `*` marking `selected`.
+fn write_trigrams(
+    out: &mut impl Write,
+    indent: &str,
+    label: &str,
+    trigrams: &[(Trigram, usize)],
+    selected: &[Trigram],
+) -> io::Result<()> {
+    if trigrams.is_empty() {
+        return Ok(());
+    }
+    let trigrams: Vec<String> = trigrams
+        .iter()
+        .map(|(tri, len)| {
+            let mark = if selected.contains(tri) { "*" } else { "" };
+            format!("{:?} {len}{mark}", String::from_utf8_lossy(&tri.to_bytes()))
+        })
+        .collect();
+    writeln!(out, "{indent}    {label}: {}", trigrams.join(", "))
+}
+
+fn cost(cost: Cost) -> String {
+    match cost {
+        Cost::LINEAR_SCAN => "linear scan".to_owned(),
+        Cost::VERY_BAD => "very broad".to_owned(),
+        Cost(n) => n.to_string(),
+    }
+}
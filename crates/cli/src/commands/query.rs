@@ -1,20 +1,49 @@
 use blaze_protocol::codec::{read_message, write_message};
-use blaze_runtime::blaze_dir;
-use std::io::{Stderr, Stdout};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::io::{self, Stderr, Stdout};
 use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, anyhow};
-use blaze_engine::{Index, PipelineMetrics, to_query_metrics};
-use blaze_protocol::{DaemonRequest, DaemonResponse, QueryRequest};
-use blaze_runtime::default_index_path;
+use blaze_engine::{
+    Credentials, EngineQueryHit, EngineQueryResult, FileId, Index, IndexReader, PipelineMetrics,
+    QueryScope, StagedIndex, can_access, to_query_metrics,
+};
+use blaze_fs::{FilePerms, Watcher, inspect_path, portable_path};
+use blaze_indexer::{
+    ScanOptions, build_in_memory_index, create_scan_context, estimate_file_count,
+    rebuild_corrupt_index,
+};
+use blaze_protocol::{
+    DaemonRequest, DaemonResponse, QueryHit, QueryRequest, QueryResponse, SubscribeRequest,
+};
+use blaze_runtime::history::QueryOrigin;
+use blaze_runtime::{
+    AUTO_INDEX_MAX_FILES, default_scan_root, find_indexed_project, find_repo_root,
+    project_index_path, settings,
+};
+use blaze_runtime::{IndexStamp, LastResults, last_results_path};
 use clap::Args;
+use log::debug;
 
-use crate::commands::CommandResult;
+use crate::commands::{CommandResult, daemon::daemon_socket_path};
 use crate::printer::{
-    ColorChoice, HumanPrinter, JsonPrinter, OutputFormat, PrinterConfig, QueryPrintContext,
-    QueryPrinter, QueryRow,
+    ColorChoice, HumanPrinter, JsonPrinter, OutputFormat, PlumbingPrinter, PrinterConfig,
+    QueryPrintContext, QueryPrinter, QueryRow, ResultOrder,
 };
+use crate::watch::{LiveMatches, MatchDiff};
+
+/// How long `--watch` waits for the first change of a batch at a time.
+const WATCH_POLL: Duration = Duration::from_secs(1);
+
+/// Quiet time after which a batch of changes is re-checked.
+const WATCH_SETTLE: Duration = Duration::from_millis(100);
+
+/// Longest a batch keeps collecting changes while the tree stays busy.
+const WATCH_MAX_BATCH: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Args)]
 pub struct OutputOptions {
@@ -22,6 +51,10 @@ pub struct OutputOptions {
     #[arg(long)]
     pub json: bool,
 
+    /// Output stable `rank<TAB>score<TAB>path` lines only, for scripts and fzf
+    #[arg(long, conflicts_with = "json")]
+    pub plumbing: bool,
+
     /// When to use colors: auto, always, never
     #[arg(long, value_name = "WHEN", default_value = "auto")]
     pub color: String,
@@ -29,16 +62,27 @@ pub struct OutputOptions {
     /// Suppress timing statistics
     #[arg(long, short = 'q')]
     pub quiet: bool,
+
+    /// Show candidate counts for each pipeline stage
+    #[arg(long)]
+    pub stats: bool,
 }
 
 impl OutputOptions {
-    /// Create a printer based on the output options.
-    pub fn make_printer(&self, limit: usize) -> Box<dyn QueryPrinter> {
-        let format = if self.json {
+    /// The output format the flags ask for.
+    pub fn format(&self) -> OutputFormat {
+        if self.plumbing {
+            OutputFormat::Plumbing
+        } else if self.json {
             OutputFormat::Json
         } else {
             OutputFormat::Human
-        };
+        }
+    }
+
+    /// Create a printer based on the output options.
+    pub fn make_printer(&self, limit: Option<usize>) -> Box<dyn QueryPrinter> {
+        let format = self.format();
 
         let color = match self.color.as_str() {
             "always" => ColorChoice::Always,
@@ -50,23 +94,35 @@ impl OutputOptions {
             color,
             limit,
             show_timing: !self.quiet,
+            show_stats: self.stats,
         };
 
         match format {
             OutputFormat::Human => Box::new(HumanPrinter::<Stdout, Stderr>::stdout(cfg)),
             OutputFormat::Json => Box::new(JsonPrinter::<Stdout, Stderr>::stdout(cfg)),
+            OutputFormat::Plumbing => Box::new(PlumbingPrinter::stdout()),
         }
     }
 }
 
 #[derive(Debug, Args)]
 pub struct QueryArgs {
-    /// The query expression to execute
+    /// The query expression to execute; several words are joined with spaces
+    #[arg(value_name = "QUERY", required = true, num_args = 1..)]
+    pub words: Vec<String>,
+
+    /// The query expression, assembled from `words`
+    #[arg(skip)]
     pub query: String,
 
-    /// Maximum number of results to display
-    #[arg(long, short = 'n', default_value = "20")]
-    pub limit: usize,
+    /// Maximum number of results to display; 0 shows all [default: the `limit`
+    /// setting, 20]
+    #[arg(long, short = 'n', value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Show every result, however many (same as `-n 0`)
+    #[arg(long, conflicts_with = "limit")]
+    pub all: bool,
 
     /// Output formatting options
     #[command(flatten)]
@@ -75,6 +131,63 @@ pub struct QueryArgs {
     /// Use the background daemon instead of querying index directly
     #[arg(long)]
     pub daemon: bool,
+
+    /// Always query the global index, even inside a project with its own index
+    #[arg(long, conflicts_with = "root")]
+    pub global: bool,
+
+    /// Search only under DIR, using its project index or indexing it on the fly
+    #[arg(long, value_name = "DIR", conflicts_with = "daemon")]
+    pub root: Option<PathBuf>,
+
+    /// Largest DIR (in files) that --root will index on the fly (0 disables)
+    #[arg(long, value_name = "N", default_value_t = AUTO_INDEX_MAX_FILES)]
+    pub auto_index_limit: usize,
+
+    /// Only match files in the same git repository as PATH
+    #[arg(long, value_name = "PATH")]
+    pub same_repo_as: Option<PathBuf>,
+
+    /// Only match files at or below DIR, searching the usual index
+    #[arg(long, value_name = "DIR")]
+    pub under: Option<PathBuf>,
+
+    /// If the index cannot be read, move it aside and rebuild it
+    #[arg(long, conflicts_with = "daemon")]
+    pub auto_rebuild: bool,
+
+    /// Only search the results of the previous query
+    #[arg(long, conflicts_with_all = ["daemon", "root"])]
+    pub refine: bool,
+
+    /// Leave out results you cannot read, checking each shown result with
+    /// the OS; fewer than the limit may be shown
+    #[arg(long)]
+    pub accessible_only: bool,
+
+    /// Order of the results: by `relevance`, by `path`, or newest first by
+    /// `mtime`; the limit then keeps the first matches in that order
+    #[arg(long, value_name = "ORDER", default_value = "relevance",
+          value_parser = ["relevance", "path", "mtime"])]
+    pub sort: String,
+
+    /// Keep running, printing `+ path` and `- path` as files under the
+    /// searched directory start or stop matching; with --daemon, as the
+    /// daemon's index changes
+    #[arg(long)]
+    pub watch: bool,
+}
+
+impl QueryArgs {
+    /// Number of results to show; `None` for all of them.
+    fn result_limit(&self) -> Option<usize> {
+        let limit = match self.limit {
+            _ if self.all => 0,
+            Some(n) => n,
+            None => settings().limit.value,
+        };
+        (limit > 0).then_some(limit)
+    }
 }
 
 pub fn run(args: QueryArgs) -> ExitCode {
@@ -87,109 +200,667 @@ pub fn run(args: QueryArgs) -> ExitCode {
     }
 }
 
-fn execute(args: QueryArgs) -> CommandResult<ExitCode> {
+fn execute(mut args: QueryArgs) -> CommandResult<ExitCode> {
+    args.query = args.words.join(" ");
+    if let Some(path) = &args.same_repo_as {
+        args.query = restrict_to_repo_of(&args.query, path)?;
+    }
+    if let Some(dir) = &args.under {
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("cannot access {}", dir.display()))?;
+        args.under = Some(dir);
+    }
+
+    let order = ResultOrder::from_arg(&args.sort);
+    if args.daemon && order != ResultOrder::Relevance {
+        return Err(anyhow!(
+            "--sort {} needs the index itself; drop --daemon",
+            order.as_str()
+        )
+        .into());
+    }
+
     if args.daemon {
-        execute_via_daemon(&args)
+        let code = execute_via_daemon(&args)?;
+        if args.watch {
+            return watch_via_daemon(&args);
+        }
+        Ok(code)
     } else {
         execute_local(args)
     }
 }
 
+/// Rewrite `query` so it only matches files in the repository containing
+/// `path`, using a `repo:` predicate on the repository's root path.
+fn restrict_to_repo_of(query: &str, path: &Path) -> CommandResult<String> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("cannot access {}", path.display()))?;
+    let root = find_repo_root(&path)
+        .ok_or_else(|| anyhow!("{} is not inside a git repository", path.display()))?;
+
+    let root = portable_path(&root)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    Ok(format!("({query}) repo:\"{root}\""))
+}
+
+/// The `--under` directory as the engine expects it, once canonicalised:
+/// in the stored form, with `/` separators on Windows too.
+fn under_prefix(args: &QueryArgs) -> Option<String> {
+    args.under
+        .as_ref()
+        .map(|dir| portable_path(dir).into_owned())
+}
+
+/// Pick the index to query: the current project's index if one has been
+/// built (see `blaze index --project`), otherwise the global index.
+pub(crate) fn resolve_index_path(global: bool) -> PathBuf {
+    resolve_index(global).0
+}
+
+/// Like [`resolve_index_path`], also returning the root the index covers.
+fn resolve_index(global: bool) -> (PathBuf, PathBuf) {
+    if !global
+        && let Ok(cwd) = std::env::current_dir()
+        && let Some((root, path)) = find_indexed_project(&cwd)
+    {
+        debug!("using project index for {}", root.display());
+        return (path, root);
+    }
+
+    (settings().index_path.value.clone(), default_scan_root())
+}
+
+/// Open the index at `index_path`.
+///
+/// An index that exists but cannot be read is rebuilt for `root` when
+/// `auto_rebuild` is set; otherwise the error says how to fix it.
+fn open_index(index_path: &Path, root: &Path, auto_rebuild: bool) -> CommandResult<Index> {
+    match Index::open(index_path) {
+        Ok(index) => Ok(index),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData && auto_rebuild => {
+            let (index, warning) =
+                rebuild_corrupt_index(root, index_path, true, &ScanOptions::default(), &e)?;
+            for line in warning.iter().flat_map(|w| w.lines()) {
+                eprintln!("[query] {line}");
+            }
+            Ok(index)
+        }
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(anyhow!(
+            "index at {} cannot be read ({e}); rebuild it with `blaze index --rebuild` \
+             or rerun with --auto-rebuild",
+            index_path.display()
+        )
+        .into()),
+        Err(e) => Err(anyhow!("cannot open index at {}: {e}", index_path.display()).into()),
+    }
+}
+
+/// An index to run a local query against.
+enum LocalIndex {
+    /// Index file mapped from disk.
+    Mapped { index: Box<Index>, path: PathBuf },
+    /// Index built on the fly and never persisted.
+    InMemory(Box<StagedIndex>),
+}
+
+impl LocalIndex {
+    /// Run `query`, handing each hit to `on_hit` as soon as it is ranked.
+    fn stream_query(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        scope: QueryScope,
+        on_hit: impl FnMut(EngineQueryHit) -> io::Result<()>,
+    ) -> io::Result<EngineQueryResult> {
+        match self {
+            LocalIndex::Mapped { index, .. } => {
+                index.stream_query_scoped(query, limit, QueryOrigin::Cli, scope, on_hit)
+            }
+            LocalIndex::InMemory(index) => {
+                index.stream_query_scoped(query, limit, QueryOrigin::Cli, scope, on_hit)
+            }
+        }
+    }
+
+    fn file_mtime(&self, fid: FileId) -> i64 {
+        match self {
+            LocalIndex::Mapped { index, .. } => index.get_file_modified_epoch(fid),
+            LocalIndex::InMemory(index) => index.get_file_modified_epoch(fid),
+        }
+    }
+
+    fn file_path(&self, fid: FileId) -> String {
+        match self {
+            LocalIndex::Mapped { index, .. } => index.reconstruct_full_path(fid),
+            LocalIndex::InMemory(index) => index.reconstruct_full_path(fid),
+        }
+    }
+
+    /// The directory the index covers.
+    fn root(&self) -> PathBuf {
+        PathBuf::from(match self {
+            LocalIndex::Mapped { index, .. } => index.reconstruct_dir_path(u32::MAX),
+            LocalIndex::InMemory(index) => index.reconstruct_dir_path(u32::MAX),
+        })
+    }
+
+    fn file_perms(&self, fid: FileId) -> Option<FilePerms> {
+        match self {
+            LocalIndex::Mapped { index, .. } => index.get_file_perms(fid),
+            LocalIndex::InMemory(index) => index.get_file_perms(fid),
+        }
+    }
+
+    /// Identifies the index for saved result sets; `None` for an index that
+    /// only lives for this query.
+    fn stamp(&self) -> Option<IndexStamp> {
+        match self {
+            LocalIndex::Mapped { index, path } => Some(IndexStamp {
+                index_path: path.clone(),
+                created_secs: index.created_secs().unwrap_or(0),
+                file_count: index.get_file_count() as u64,
+            }),
+            LocalIndex::InMemory(_) => None,
+        }
+    }
+}
+
+/// Open the index for an explicit `--root`.
+///
+/// Prefers a persisted project index for the directory. Otherwise, if the
+/// directory is small enough, it is scanned into an index that lives only
+/// for this query.
+fn open_root_index(
+    root: &Path,
+    auto_index_limit: usize,
+    auto_rebuild: bool,
+) -> CommandResult<LocalIndex> {
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("cannot access {}", root.display()))?;
+
+    let index_path = project_index_path(&root);
+    if index_path.exists() {
+        let index = open_index(&index_path, &root, auto_rebuild)?;
+        return Ok(LocalIndex::Mapped {
+            index: Box::new(index),
+            path: index_path,
+        });
+    }
+
+    if auto_index_limit > 0
+        && let Some(count) = estimate_file_count(&root, auto_index_limit)
+    {
+        debug!("indexing {} on the fly (~{count} files)", root.display());
+        let staged = build_in_memory_index(&root)?;
+        return Ok(LocalIndex::InMemory(Box::new(staged)));
+    }
+
+    Err(anyhow!(
+        "no index for {} and it has more than {auto_index_limit} files; \
+         run `blaze index --project {}` first",
+        root.display(),
+        root.display()
+    )
+    .into())
+}
+
 /// Existing behaviour: open index and run pipeline in-process.
 fn execute_local(args: QueryArgs) -> CommandResult<ExitCode> {
-    let index_path = default_index_path();
-    let index = Index::open(&index_path)?;
+    let index = match &args.root {
+        Some(root) => open_root_index(root, args.auto_index_limit, args.auto_rebuild)?,
+        None => {
+            let (index_path, root) = resolve_index(args.global);
+            let index = open_index(&index_path, &root, args.auto_rebuild)?;
+            LocalIndex::Mapped {
+                index: Box::new(index),
+                path: index_path,
+            }
+        }
+    };
+
+    let stamp = index.stamp();
+    let within = match &stamp {
+        Some(stamp) if args.refine => Some(previous_results(stamp)?),
+        _ => None,
+    };
 
-    run_local(&index, &args)?;
+    let matched = run_local(&index, &args, within)?;
+    let live = args.watch.then(|| {
+        let paths = matched.iter().map(|&fid| index.file_path(fid));
+        LiveMatches::new(index.root(), args.query.clone(), paths)
+    });
 
-    Ok(ExitCode::from(0))
+    if let Some(stamp) = stamp {
+        let last = LastResults {
+            stamp,
+            query: args.query.clone(),
+            file_ids: matched,
+        };
+        if let Err(e) = last.save(&last_results_path()) {
+            debug!("could not save results for --refine: {e}");
+        }
+    }
+
+    match live {
+        Some(live) => watch_local(live, &args),
+        None => Ok(ExitCode::from(0)),
+    }
+}
+
+/// Keep running after the first answer, printing how the matches change as
+/// files below the searched directory are created, modified or removed.
+///
+/// Only runs out on error; the user stops it with Ctrl-C.
+fn watch_local(mut live: LiveMatches, args: &QueryArgs) -> CommandResult<ExitCode> {
+    let dir = args
+        .under
+        .clone()
+        .unwrap_or_else(|| live.root().to_path_buf());
+    let ctx = create_scan_context(&ScanOptions::default())?;
+    let mut watcher = Watcher::new(&dir, ctx.clone())
+        .with_context(|| format!("cannot watch {}", dir.display()))?;
+    if !args.output.quiet {
+        eprintln!(
+            "[watch] watching {} for changes to {} match(es); press Ctrl-C to stop",
+            dir.display(),
+            live.len()
+        );
+    }
+
+    let format = args.output.format();
+    loop {
+        let changes = next_changes(&mut watcher)?
+            .into_iter()
+            .map(|path| {
+                let rec = inspect_path(&path, &ctx).ok().flatten();
+                (path, rec)
+            })
+            .collect();
+        let mut diff = live.update(changes);
+        if args.accessible_only {
+            diff.added.retain(|path| can_access(path));
+        }
+        if diff.is_empty() {
+            continue;
+        }
+
+        diff.print(&mut io::stdout().lock(), format)?;
+        if !args.output.quiet {
+            eprintln!(
+                "[watch] {} added, {} removed; {} match(es)",
+                diff.added.len(),
+                diff.removed.len(),
+                live.len()
+            );
+        }
+    }
 }
 
-fn run_local(index: &Index, args: &QueryArgs) -> CommandResult<()> {
-    let limit = args.limit;
-    let result = index.run_query(&args.query, limit);
+/// Paths changed since the last call: waits for a first change, then keeps
+/// collecting until the tree is quiet for a moment, so a burst of writes is
+/// re-checked once.
+fn next_changes(watcher: &mut Watcher) -> io::Result<BTreeSet<PathBuf>> {
+    let mut changed: BTreeSet<PathBuf> = watcher
+        .wait(WATCH_POLL)?
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+    let started = Instant::now();
+    while !changed.is_empty() && started.elapsed() < WATCH_MAX_BATCH {
+        let more = watcher.wait(WATCH_SETTLE)?;
+        if more.is_empty() {
+            break;
+        }
+        changed.extend(more.into_iter().map(|e| e.path));
+    }
+    Ok(changed)
+}
 
+/// Matches of the previous local query, which must have run against the
+/// same, unchanged index.
+fn previous_results(stamp: &IndexStamp) -> CommandResult<Vec<FileId>> {
+    let last = LastResults::load(&last_results_path())
+        .context("cannot read the previous results")?
+        .ok_or_else(|| anyhow!("nothing to refine; run a query first"))?;
+
+    if last.stamp != *stamp {
+        return Err(anyhow!(
+            "the index changed since `{}` ran (or it ran against another index); \
+             run it again before refining",
+            last.query
+        )
+        .into());
+    }
+
+    debug!(
+        "refining {} result(s) of `{}`",
+        last.file_ids.len(),
+        last.query
+    );
+    Ok(last.file_ids)
+}
+
+/// Run the query and print it, returning every match for `--refine`.
+fn run_local(
+    index: &LocalIndex,
+    args: &QueryArgs,
+    within: Option<Vec<FileId>>,
+) -> CommandResult<Vec<FileId>> {
+    let limit = args.result_limit();
+    let scope = QueryScope {
+        within,
+        under: under_prefix(args),
+        ..QueryScope::default()
+    };
+
+    let order = ResultOrder::from_arg(&args.sort);
     let mut printer = args.output.make_printer(limit);
 
-    let truncated = result.total > limit;
+    // Rows are printed as they come, before the totals are known, so that
+    // `--all` never holds every path at once; only `finish` sees the totals.
+    // Other orders than relevance need every match in hand first.
+    let row_ctx = QueryPrintContext {
+        kind: "query",
+        query: Some(&args.query),
+        total: 0,
+        returned: 0,
+        truncated: false,
+        order,
+        metrics: None,
+    };
+    printer.begin(&row_ctx)?;
+
+    // Results are checked for `--accessible-only` just before they are
+    // shown, so ranking cannot stop at the limit when some may be hidden.
+    let mut access = args.accessible_only.then(AccessFilter::new);
+    let mut returned = 0;
+    let mut held = Vec::new();
+    let rank_limit = if order == ResultOrder::Relevance && access.is_none() {
+        limit
+    } else {
+        None
+    };
+    let result = index.stream_query(&args.query, rank_limit, scope, |hit| {
+        if order != ResultOrder::Relevance {
+            held.push(hit);
+            return Ok(());
+        }
+        if limit.is_some_and(|limit| returned >= limit) {
+            return Ok(());
+        }
+        if let Some(access) = &mut access
+            && !access.allows(&hit.path, index.file_perms(hit.file_id))
+        {
+            return Ok(());
+        }
+        returned += 1;
+        let row = QueryRow {
+            rank: returned,
+            score: hit.score,
+            path: &hit.path,
+            kind: hit.kind,
+        };
+        printer.print_row(&row, &row_ctx)
+    })?;
+
+    sort_hits(&mut held, order, |fid| index.file_mtime(fid));
+    let shown = held.iter().filter(|hit| {
+        (access.as_mut())
+            .is_none_or(|access| access.allows(&hit.path, index.file_perms(hit.file_id)))
+    });
+    for hit in shown.take(limit.unwrap_or(usize::MAX)) {
+        returned += 1;
+        let row = QueryRow {
+            rank: returned,
+            score: hit.score,
+            path: &hit.path,
+            kind: hit.kind,
+        };
+        printer.print_row(&row, &row_ctx)?;
+    }
 
     let metrics = result
         .metrics
         .map(|m: PipelineMetrics| to_query_metrics(&m));
 
+    let total = result.total - access.map_or(0, |a| a.hidden);
     let ctx = QueryPrintContext {
         kind: "query",
         query: result.query_str.as_deref(),
-        total: result.total,
-        truncated,
+        total,
+        returned,
+        truncated: returned < total,
+        order,
         metrics,
     };
 
+    printer.finish(&ctx)?;
+
+    if let Some(profile) = &result.profile {
+        eprintln!("{profile}");
+    }
+
+    Ok(result.matched)
+}
+
+/// Put ranked `hits` in `order`; relevance keeps the rank order. Paths are
+/// unique, so breaking ties on them makes every order total.
+fn sort_hits(hits: &mut [EngineQueryHit], order: ResultOrder, mtime: impl Fn(FileId) -> i64) {
+    match order {
+        ResultOrder::Relevance => {}
+        ResultOrder::Path => hits.sort_unstable_by(|a, b| a.path.cmp(&b.path)),
+        ResultOrder::Mtime => {
+            hits.sort_by_cached_key(|h| (Reverse(mtime(h.file_id)), h.path.clone()))
+        }
+    }
+}
+
+/// Drops results the current user cannot read, for `--accessible-only`.
+///
+/// Only the results about to be shown are checked, so `access(2)` runs
+/// about as often as the limit allows rather than once per match. Totals
+/// therefore still count unreadable matches past the last one shown.
+struct AccessFilter {
+    who: Option<Credentials>,
+    /// Results dropped so far.
+    hidden: usize,
+}
+
+impl AccessFilter {
+    fn new() -> Self {
+        Self {
+            who: Credentials::current().ok(),
+            hidden: 0,
+        }
+    }
+
+    /// Whether to show `path`. Owner and mode recorded in the index rule a
+    /// file out without a syscall; otherwise `access(2)` decides, which also
+    /// catches files made unreadable since they were indexed.
+    fn allows(&mut self, path: &str, perms: Option<FilePerms>) -> bool {
+        let recorded_ok = match (&self.who, perms) {
+            (Some(who), Some(perms)) => who.can_read(perms),
+            _ => true,
+        };
+        let allowed = recorded_ok && can_access(path);
+        if !allowed {
+            self.hidden += 1;
+        }
+        allowed
+    }
+}
+
+/// Daemon mode: send the query over Unix socket and print the response.
+///
+/// A response the daemon cut short of the limit by its own caps is
+/// followed by requests for the next pages until the limit is reached.
+fn execute_via_daemon(args: &QueryArgs) -> CommandResult<ExitCode> {
+    let socket_path = daemon_socket_path()?;
+    let limit = args.result_limit();
+
+    let mut req = QueryRequest {
+        query: args.query.clone(),
+        limit,
+        under: under_prefix(args),
+        session: None,
+        offset: 0,
+    };
+    // Unreadable hits are dropped from each page as it comes, and the pages
+    // after it make up for them, so the limit is still reached.
+    let mut access = args.accessible_only.then(AccessFilter::new);
+    let mut keep = |hits: &mut Vec<QueryHit>| {
+        if let Some(access) = &mut access {
+            hits.retain(|hit| access.allows(&hit.path, None));
+        }
+    };
+    let mut qr = query_daemon(&socket_path, &req)?;
+    let mut hits = std::mem::take(&mut qr.hits);
+    keep(&mut hits);
+    while let Some(next) = qr.next_offset
+        && limit.is_none_or(|limit| hits.len() < limit)
+    {
+        match qr.truncated {
+            Some(cap) => debug!("daemon response capped at {cap:?}; fetching from hit {next}"),
+            None => debug!("unreadable hits dropped; fetching from hit {next}"),
+        }
+        req.offset = next;
+        req.limit = limit.map(|limit| limit - hits.len());
+        let mut page = query_daemon(&socket_path, &req)?;
+        if page.generation != qr.generation {
+            eprintln!(
+                "[query] the daemon's index changed while paging; showing the first {} hits",
+                hits.len()
+            );
+            break;
+        }
+        keep(&mut page.hits);
+        hits.extend(page.hits);
+        qr.truncated = page.truncated;
+        qr.next_offset = page.next_offset;
+    }
+
+    // Reuse the existing printers.
+    let mut printer = args.output.make_printer(limit);
+    let limit = limit.unwrap_or(usize::MAX);
+
+    debug!(
+        "daemon answered from index generation {} (root {}, written at {})",
+        qr.generation,
+        qr.root.as_deref().unwrap_or("?"),
+        qr.index_created_secs.unwrap_or_default()
+    );
+    hits.truncate(limit);
+    let total = qr.total as usize - access.map_or(0, |a| a.hidden);
+    let returned = hits.len();
+    let truncated = returned < total;
+
+    let ctx = QueryPrintContext {
+        kind: "query",
+        query: Some(&args.query),
+        total,
+        returned,
+        truncated,
+        order: ResultOrder::Relevance,
+        metrics: qr.metrics,
+    };
+
     printer.begin(&ctx)?;
 
-    for hit in &result.hits {
+    for (i, hit) in hits.iter().enumerate() {
         let row = QueryRow {
-            rank: hit.rank,
+            rank: i + 1,
+            score: hit.score,
             path: &hit.path,
+            kind: hit.kind,
         };
         printer.print_row(&row, &ctx)?;
     }
 
     printer.finish(&ctx)?;
 
-    Ok(())
+    // History logging is already done in the daemon's pipeline.
+    Ok(ExitCode::from(0))
 }
 
-/// Daemon mode: send the query over Unix socket and print the response.
-fn execute_via_daemon(args: &QueryArgs) -> CommandResult<ExitCode> {
-    let socket_path = blaze_dir().join("daemon.sock");
-
+/// `--watch` with `--daemon`: subscribe to the query and print the changes
+/// the daemon pushes each time it reloads its index.
+fn watch_via_daemon(args: &QueryArgs) -> CommandResult<ExitCode> {
+    let socket_path = daemon_socket_path()?;
     let mut stream = UnixStream::connect(&socket_path).with_context(|| {
         format!(
             "failed to connect to blaze daemon at {}",
             socket_path.display()
         )
     })?;
-
-    let req = DaemonRequest::Query(QueryRequest {
+    let req = SubscribeRequest {
         query: args.query.clone(),
-        limit: Some(args.limit),
-    });
-
-    write_message(&mut stream, &req)?;
-    let resp: DaemonResponse = read_message(&mut stream)?;
-
-    match resp {
-        DaemonResponse::QueryResult(qr) => {
-            // Reuse the existing printers.
-            let mut printer = args.output.make_printer(args.limit);
-
-            let total = qr.total as usize;
-            let truncated = total > args.limit;
-
-            let ctx = QueryPrintContext {
-                kind: "query",
-                query: Some(&args.query),
-                total,
-                truncated,
-                metrics: qr.metrics,
-            };
-
-            printer.begin(&ctx)?;
-
-            for hit in qr.hits.iter().take(args.limit) {
-                let row = QueryRow {
-                    rank: hit.rank as usize,
-                    path: &hit.path,
-                };
-                printer.print_row(&row, &ctx)?;
+        under: under_prefix(args),
+    };
+    write_message(&mut stream, &DaemonRequest::Subscribe(req))?;
+
+    let format = args.output.format();
+    let mut subscribed = false;
+    loop {
+        let response = read_message(&mut stream)
+            .context("the daemon ended the subscription; is it still running?")?;
+        let change = match response {
+            DaemonResponse::ResultsChanged(change) => change,
+            DaemonResponse::Error(msg) => return Err(anyhow!("daemon error: {msg}").into()),
+            other => return Err(anyhow!("unexpected daemon response: {other:?}").into()),
+        };
+        // The first message lists every match, which were just printed.
+        if !subscribed {
+            subscribed = true;
+            if !args.output.quiet {
+                eprintln!(
+                    "[watch] watching the daemon's index for changes to {} match(es); \
+                     press Ctrl-C to stop",
+                    change.total
+                );
             }
+            continue;
+        }
 
-            printer.finish(&ctx)?;
-
-            // History logging is already done in the daemon's pipeline.
-            Ok(ExitCode::from(0))
+        let mut diff = MatchDiff {
+            added: change.added,
+            removed: change.removed,
+        };
+        if args.accessible_only {
+            diff.added.retain(|path| can_access(path));
         }
+        if diff.is_empty() {
+            continue;
+        }
+        diff.print(&mut io::stdout().lock(), format)?;
+        if !args.output.quiet {
+            eprintln!(
+                "[watch] {} added, {} removed; {} match(es)",
+                diff.added.len(),
+                diff.removed.len(),
+                change.total
+            );
+        }
+    }
+}
+
+/// Send one query request to the daemon at `socket_path` and read its
+/// answer.
+fn query_daemon(socket_path: &Path, req: &QueryRequest) -> CommandResult<QueryResponse> {
+    let mut stream = UnixStream::connect(socket_path).with_context(|| {
+        format!(
+            "failed to connect to blaze daemon at {}",
+            socket_path.display()
+        )
+    })?;
+    write_message(&mut stream, &DaemonRequest::Query(req.clone()))?;
+
+    match read_message(&mut stream)? {
+        DaemonResponse::QueryResult(qr) => Ok(qr),
         DaemonResponse::Error(msg) => {
             // Treat daemon-reported error as a CLI error.
             Err(anyhow!("daemon error: {msg}").into())
@@ -197,3 +868,7 @@ fn execute_via_daemon(args: &QueryArgs) -> CommandResult<ExitCode> {
         other => Err(anyhow!("unexpected daemon response: {other:?}").into()),
     }
 }
+
+#[cfg(test)]
+#[path = "query_tests.rs"]
+mod tests;
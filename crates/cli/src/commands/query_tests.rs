@@ -0,0 +1,47 @@
+use blaze_protocol::HitKind;
+
+use super::*;
+
+fn hit(file_id: FileId, path: &str) -> EngineQueryHit {
+    EngineQueryHit {
+        rank: file_id as usize + 1,
+        file_id,
+        score: 0,
+        path: path.to_owned(),
+        kind: HitKind::default(),
+    }
+}
+
+fn paths(hits: &[EngineQueryHit]) -> Vec<&str> {
+    hits.iter().map(|h| h.path.as_str()).collect()
+}
+
+fn ranked() -> Vec<EngineQueryHit> {
+    vec![hit(0, "/b/old"), hit(1, "/c/new"), hit(2, "/a/new")]
+}
+
+/// Files 1 and 2 were modified at the same time, after file 0.
+fn mtime(fid: FileId) -> i64 {
+    if fid == 0 { 100 } else { 200 }
+}
+
+#[test]
+fn relevance_keeps_rank_order() {
+    let mut hits = ranked();
+    sort_hits(&mut hits, ResultOrder::Relevance, mtime);
+    assert_eq!(paths(&hits), ["/b/old", "/c/new", "/a/new"]);
+}
+
+#[test]
+fn path_order_is_byte_wise() {
+    let mut hits = ranked();
+    sort_hits(&mut hits, ResultOrder::Path, mtime);
+    assert_eq!(paths(&hits), ["/a/new", "/b/old", "/c/new"]);
+}
+
+#[test]
+fn mtime_order_is_newest_first_then_by_path() {
+    let mut hits = ranked();
+    sort_hits(&mut hits, ResultOrder::Mtime, mtime);
+    assert_eq!(paths(&hits), ["/a/new", "/c/new", "/b/old"]);
+}
@@ -0,0 +1,230 @@
+use std::{os::unix::net::UnixStream, path::Path, process::ExitCode};
+
+use anyhow::Result;
+use blaze_engine::{BuildStats, Index, IndexBudget, IndexReader, LangUsage, lang_usage};
+use blaze_protocol::{
+    BUILD, BuildInfo, DaemonRequest, DaemonResponse, Readiness,
+    codec::{read_message, write_message},
+};
+use blaze_runtime::{IndexRegistry, registry_path};
+use clap::Args;
+use log::error;
+
+use crate::commands::{daemon::daemon_socket_path, query::resolve_index_path};
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// Break down index size by section and show how much is in memory
+    #[arg(long, short = 'v')]
+    pub verbose: bool,
+
+    /// Break down indexed files by detected language
+    #[arg(long)]
+    pub langs: bool,
+
+    /// Report on the global index, even inside a project with its own index
+    #[arg(long)]
+    pub global: bool,
+}
+
+pub fn run(args: StatusArgs) -> ExitCode {
+    match execute(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[status] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: &StatusArgs) -> Result<ExitCode> {
+    let index_path = resolve_index_path(args.global);
+
+    println!("blaze:    {BUILD}");
+    println!("daemon:   {}", daemon_status());
+    println!("index:    {}", index_path.display());
+
+    if !index_path.exists() {
+        println!("          (not built; run `blaze index`)");
+        return Ok(ExitCode::from(1));
+    }
+
+    let index = Index::open(&index_path)?;
+    println!("root:     {}", index.root_path().unwrap_or("<unknown>"));
+    println!("files:    {}", index.live_file_count());
+    println!("dirs:     {}", index.dir_count());
+    if let Some(generation) = registry_generation(&index_path) {
+        println!("builds:   {generation}");
+    }
+    if let Some(delta) = index.delta_usage() {
+        println!("delta:    +{} -{} files", delta.added, delta.removed);
+    }
+    if let Some(stats) = index.build_stats() {
+        print_build_stats(&stats);
+    }
+
+    if args.verbose {
+        print_budget(&index.budget());
+    } else {
+        println!("size:     {}", format_bytes(file_size(&index_path)));
+    }
+
+    if args.langs {
+        print_langs(&lang_usage(&index), index.live_file_count());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// How many times the registry has seen `index_path` written, if recorded.
+fn registry_generation(index_path: &Path) -> Option<u64> {
+    let registry = IndexRegistry::load(&registry_path()).ok()?;
+    let canonical = index_path.canonicalize().ok()?;
+    registry.get(&canonical).map(|e| e.generation)
+}
+
+fn file_size(path: &Path) -> u64 {
+    path.metadata().map(|m| m.len()).unwrap_or(0)
+}
+
+fn daemon_status() -> String {
+    let Ok(socket_path) = daemon_socket_path() else {
+        return "protocol mismatch (restart the daemon)".to_string();
+    };
+    let Ok(mut stream) = UnixStream::connect(&socket_path) else {
+        return "not running".to_string();
+    };
+
+    let ping = write_message(&mut stream, &DaemonRequest::Ping)
+        .ok()
+        .and_then(|_| read_message::<_, DaemonResponse>(&mut stream).ok());
+
+    let Some(DaemonResponse::Pong(pong)) = ping else {
+        return "not responding".to_string();
+    };
+    let readiness = match pong.readiness {
+        Readiness::Ready => "running",
+        Readiness::Warming => "running (warming index)",
+        Readiness::Starting => "starting (loading index)",
+    };
+    let mut status = format!("{readiness}, blaze {}", pong.build);
+    if pong.build != BuildInfo::current() {
+        status.push_str("\n          (another build than this blaze; restart the daemon)");
+    }
+    status
+}
+
+/// How long the last build took and what it left out, to compare builds
+/// across machines and settings.
+fn print_build_stats(stats: &BuildStats) {
+    let took = match stats.build_millis {
+        ms @ 0..1000 => format!("{ms} ms"),
+        ms => format!("{:.1}s", ms as f64 / 1000.0),
+    };
+    let mut build = format!("{took}, {} dirs listed", stats.dirs_scanned);
+    if stats.peak_memory_bytes > 0 {
+        build.push_str(&format!(
+            ", peak memory {}",
+            format_bytes(stats.peak_memory_bytes)
+        ));
+    }
+    println!("build:    {build}");
+    if stats.dirs_skipped > 0 || stats.files_skipped > 0 {
+        println!(
+            "skipped:  {} dirs, {} files",
+            stats.dirs_skipped, stats.files_skipped
+        );
+    }
+}
+
+fn print_budget(budget: &IndexBudget) {
+    let total = budget.file_bytes.max(1);
+
+    println!("size:     {}", format_bytes(budget.file_bytes));
+    match budget.resident_bytes {
+        Some(resident) => println!(
+            "resident: {} ({:.0}% of index in page cache)",
+            format_bytes(resident),
+            percent(resident.min(budget.file_bytes), total)
+        ),
+        None => println!("resident: unknown"),
+    }
+
+    println!();
+    println!(
+        "{:<22} {:>10} {:>6} {:>10}",
+        "SECTION", "SIZE", "SHARE", "RESIDENT"
+    );
+    for section in &budget.sections {
+        let resident = section
+            .resident_bytes
+            .map_or_else(|| "-".to_string(), format_bytes);
+        println!(
+            "{:<22} {:>10} {:>5.1}% {:>10}",
+            section.name,
+            format_bytes(section.bytes),
+            percent(section.bytes, total),
+            resident
+        );
+    }
+
+    let names = &budget.names;
+    println!();
+    println!(
+        "names:    {} referenced of {} ({:.1}% used)",
+        format_bytes(names.referenced_bytes),
+        format_bytes(names.blob_bytes),
+        percent(names.referenced_bytes, names.blob_bytes.max(1))
+    );
+    println!(
+        "          {} distinct; deduplicating names would save {}",
+        format_bytes(names.distinct_bytes),
+        format_bytes(names.referenced_bytes.saturating_sub(names.distinct_bytes))
+    );
+}
+
+fn print_langs(usage: &[LangUsage], file_count: usize) {
+    let total = file_count.max(1) as u64;
+
+    println!();
+    println!(
+        "{:<22} {:>10} {:>6} {:>10}",
+        "LANGUAGE", "FILES", "SHARE", "SIZE"
+    );
+    for lang in usage {
+        println!(
+            "{:<22} {:>10} {:>5.1}% {:>10}",
+            lang.lang,
+            lang.files,
+            percent(lang.files as u64, total),
+            format_bytes(lang.bytes)
+        );
+    }
+    if usage.is_empty() {
+        println!("(no files with a recognised language)");
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    part as f64 * 100.0 / whole as f64
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for u in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = u;
+    }
+    format!("{value:.1} {unit}")
+}
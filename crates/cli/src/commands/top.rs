@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use blaze_fs::{ChangeKind, FileRecord, FsEvent, ScanContext, Watcher, inspect_path};
+use blaze_indexer::{ScanOptions, create_scan_context};
+use blaze_runtime::default_scan_root;
+use chrono::{DateTime, Local};
+use clap::Args;
+use log::error;
+
+use crate::watch::matching_records;
+
+/// Most paths remembered at once; the least recently changed are dropped.
+const MAX_TRACKED: usize = 10_000;
+
+#[derive(Debug, Args)]
+pub struct TopArgs {
+    /// Only show changed files matching this query
+    pub query: Option<String>,
+
+    /// Directory to watch (defaults to the home directory)
+    #[arg(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Number of files to show
+    #[arg(long, short = 'n', default_value = "20")]
+    pub limit: usize,
+
+    /// Milliseconds between screen refreshes
+    #[arg(long, value_name = "MS", default_value = "500")]
+    pub interval: u64,
+
+    /// Rescan the tree on every refresh instead of using native notifications
+    #[arg(long)]
+    pub poll: bool,
+}
+
+/// Latest change seen for one path.
+struct Change {
+    kind: ChangeKind,
+    at: SystemTime,
+    count: usize,
+}
+
+pub fn run(args: TopArgs) -> ExitCode {
+    match execute(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("[error] {e}");
+            eprintln!("[top] {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn execute(args: &TopArgs) -> Result<ExitCode> {
+    let root = args.root.clone().unwrap_or_else(default_scan_root);
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("cannot access {}", root.display()))?;
+
+    let ctx = create_scan_context(&ScanOptions::default())?;
+    let mut watcher = if args.poll {
+        Watcher::polling(&root, ctx.clone())?
+    } else {
+        Watcher::new(&root, ctx.clone())?
+    };
+
+    let interactive = io::stdout().is_terminal();
+    let interval = Duration::from_millis(args.interval.max(1));
+    let mut changes: HashMap<PathBuf, Change> = HashMap::new();
+
+    if interactive {
+        render(&root, args, &ctx, &changes)?;
+    }
+
+    loop {
+        let events = watcher.wait(interval)?;
+        if events.is_empty() {
+            continue;
+        }
+
+        if !interactive {
+            print_events(&root, args, &ctx, &events)?;
+            continue;
+        }
+
+        for event in events {
+            record(&mut changes, event);
+        }
+        render(&root, args, &ctx, &changes)?;
+    }
+}
+
+fn record(changes: &mut HashMap<PathBuf, Change>, event: FsEvent) {
+    let change = changes.entry(event.path).or_insert(Change {
+        kind: event.kind,
+        at: event.observed_at,
+        count: 0,
+    });
+    change.kind = event.kind;
+    change.at = event.observed_at;
+    change.count += 1;
+
+    if changes.len() > MAX_TRACKED {
+        let mut by_age: Vec<SystemTime> = changes.values().map(|c| c.at).collect();
+        by_age.sort_unstable();
+        let cutoff = by_age[changes.len() - MAX_TRACKED];
+        changes.retain(|_, c| c.at >= cutoff);
+    }
+}
+
+/// Build a record for a changed path. Removed files no longer have
+/// metadata, so they get a placeholder that still matches on name.
+fn change_record(path: &Path, ctx: &ScanContext, at: SystemTime) -> Option<FileRecord> {
+    if let Ok(rec) = inspect_path(path, ctx) {
+        return rec;
+    }
+
+    let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    Some(FileRecord {
+        full_path: path.to_path_buf(),
+        name: path.file_name()?.to_str()?.to_owned(),
+        size: 0,
+        mtime_secs: secs,
+        ctime_secs: secs,
+        atime_secs: secs,
+        ext: path.extension().and_then(|e| e.to_str()).map(str::to_owned),
+        lang: None,
+        content_hash: None,
+        is_dir: false,
+        is_symlink: false,
+        is_special: false,
+        in_trash: false,
+        ignored_glob: false,
+        hidden_os: false,
+        user_excludes: false,
+        generated: false,
+        perms: None,
+    })
+}
+
+/// Keep only the paths matching `query`, evaluated over an in-memory index
+/// of just those paths.
+fn filter_paths<'a>(
+    root: &Path,
+    query: &str,
+    ctx: &ScanContext,
+    paths: Vec<(&'a PathBuf, SystemTime)>,
+) -> Vec<&'a PathBuf> {
+    let mut records = Vec::with_capacity(paths.len());
+    let mut indexed = Vec::with_capacity(paths.len());
+    for (path, at) in paths {
+        if let Some(rec) = change_record(path, ctx, at) {
+            records.push(rec);
+            indexed.push(path);
+        }
+    }
+
+    matching_records(root, query, records)
+        .into_iter()
+        .map(|i| indexed[i])
+        .collect()
+}
+
+fn render(
+    root: &Path,
+    args: &TopArgs,
+    ctx: &ScanContext,
+    changes: &HashMap<PathBuf, Change>,
+) -> Result<()> {
+    let mut recent: Vec<(&PathBuf, &Change)> = changes.iter().collect();
+    recent.sort_unstable_by(|a, b| b.1.at.cmp(&a.1.at).then_with(|| a.0.cmp(b.0)));
+
+    let shown: Vec<&PathBuf> = match args.query.as_deref() {
+        Some(q) => {
+            let paths = recent.iter().map(|(p, c)| (*p, c.at)).collect();
+            let mut matched = filter_paths(root, q, ctx, paths);
+            matched.truncate(args.limit);
+            matched
+        }
+        None => recent.iter().take(args.limit).map(|(p, _)| *p).collect(),
+    };
+
+    let mut out = io::stdout().lock();
+    // Clear screen and home the cursor.
+    write!(out, "\x1b[2J\x1b[H")?;
+    writeln!(
+        out,
+        "blaze top: {} ({} changed){}",
+        root.display(),
+        changes.len(),
+        args.query
+            .as_deref()
+            .map(|q| format!(", filter: {q}"))
+            .unwrap_or_default()
+    )?;
+    writeln!(out)?;
+    writeln!(out, "{:<8}  {:<8}  {:>5}  PATH", "TIME", "CHANGE", "COUNT")?;
+
+    for path in shown {
+        let change = &changes[path];
+        writeln!(
+            out,
+            "{:<8}  {:<8}  {:>5}  {}",
+            format_time(change.at),
+            kind_label(change.kind),
+            change.count,
+            path.display()
+        )?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// Non-interactive output: one line per change, in arrival order.
+fn print_events(root: &Path, args: &TopArgs, ctx: &ScanContext, events: &[FsEvent]) -> Result<()> {
+    let keep: Vec<&PathBuf> = match args.query.as_deref() {
+        Some(q) => {
+            let paths = events.iter().map(|e| (&e.path, e.observed_at)).collect();
+            filter_paths(root, q, ctx, paths)
+        }
+        None => events.iter().map(|e| &e.path).collect(),
+    };
+
+    let mut out = io::stdout().lock();
+    for event in events.iter().filter(|e| keep.contains(&&e.path)) {
+        writeln!(
+            out,
+            "{}\t{}\t{}",
+            format_time(event.observed_at),
+            kind_label(event.kind),
+            event.path.display()
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn kind_label(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Created => "created",
+        ChangeKind::Modified => "modified",
+        ChangeKind::Removed => "removed",
+    }
+}
+
+fn format_time(t: SystemTime) -> String {
+    DateTime::<Local>::from(t).format("%H:%M:%S").to_string()
+}
@@ -0,0 +1,37 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use blaze_runtime::{DIR_TIMEOUT_SECS, NETWORK_OP_TIMEOUT_SECS};
+use clap::Args;
+
+use crate::commands::index::{self, IndexArgs, IndexModeArgs};
+
+#[derive(Debug, Args)]
+pub struct UpdatedbArgs {
+    /// Directory to index (defaults to the home directory)
+    #[arg(short = 'U', long, value_name = "PATH")]
+    pub database_root: Option<PathBuf>,
+}
+
+/// Rebuild the global index, like `blaze index --rebuild`.
+pub fn run(args: UpdatedbArgs) -> ExitCode {
+    index::run(IndexArgs {
+        action: None,
+        root: args.database_root,
+        project: false,
+        system: false,
+        hash: false,
+        hash_max_mib: None,
+        dir_paths: false,
+        max_file_size: None,
+        skip_empty: false,
+        exclude_paths: Vec::new(),
+        backend: "walk".to_owned(),
+        dir_timeout: DIR_TIMEOUT_SECS,
+        network: "skip".to_owned(),
+        network_timeout: NETWORK_OP_TIMEOUT_SECS,
+        caches: "flag".to_owned(),
+        recheck_changed: false,
+        json: false,
+        mode: IndexModeArgs::default(),
+    })
+}
@@ -0,0 +1,85 @@
+//! `blaze --version`, and spotting a daemon left over from another install.
+
+use std::io::{self, Write};
+
+use blaze_engine::INDEX_VERSION;
+use blaze_protocol::{BUILD, PROTOCOL_VERSION};
+use blaze_runtime::{DaemonDiscovery, discovery_path};
+
+/// Optional Cargo features this binary was built with.
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "collate") {
+        features.push("collate");
+    }
+    features
+}
+
+/// Print `blaze <release> (<commit>)`. With `verbose`, also print the index
+/// format and protocol this build speaks, its optional features, and the
+/// build of the running daemon.
+pub fn print(verbose: bool) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "blaze {BUILD}")?;
+    if !verbose {
+        return Ok(());
+    }
+
+    let features = enabled_features();
+    writeln!(out, "index format: v{INDEX_VERSION}")?;
+    writeln!(out, "protocol:     v{PROTOCOL_VERSION}")?;
+    writeln!(
+        out,
+        "features:     {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    )?;
+
+    let daemon = DaemonDiscovery::read(&discovery_path()).ok().flatten();
+    writeln!(out, "daemon:       {}", describe_daemon(daemon.as_ref()))
+}
+
+/// The advertised daemon's build and protocol, flagging any difference from
+/// this binary.
+pub(crate) fn describe_daemon(daemon: Option<&DaemonDiscovery>) -> String {
+    let Some(d) = daemon else {
+        return "not running".to_string();
+    };
+    let build = d.build.as_deref().unwrap_or("unknown build");
+    let mut line = format!("{build}, protocol v{} (pid {})", d.protocol_version, d.pid);
+    if let Some(warning) = skew_warning(d) {
+        line.push_str(&format!("\n              {warning}"));
+    }
+    line
+}
+
+/// Why the advertised daemon does not match this binary, if it does not.
+///
+/// A daemon speaking another protocol cannot be used at all; one that is
+/// merely another build still answers, but may behave differently from what
+/// this binary documents.
+pub(crate) fn skew_warning(daemon: &DaemonDiscovery) -> Option<String> {
+    if daemon.protocol_version != PROTOCOL_VERSION {
+        return Some(format!(
+            "the daemon speaks protocol v{} but this blaze speaks v{PROTOCOL_VERSION}; \
+             restart the daemon",
+            daemon.protocol_version
+        ));
+    }
+    let daemon_build = match daemon.build.as_deref() {
+        Some(build) if build == BUILD => return None,
+        Some(build) => format!("blaze {build}"),
+        None => "an older blaze".to_string(),
+    };
+    Some(format!(
+        "the daemon is {daemon_build} but this is blaze {BUILD}; \
+         restart the daemon to run the same build"
+    ))
+}
+
+#[cfg(test)]
+#[path = "version_tests.rs"]
+mod tests;
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use super::*;
+
+fn daemon(protocol_version: u32, build: Option<&str>) -> DaemonDiscovery {
+    DaemonDiscovery {
+        socket_path: PathBuf::from("/run/blaze.sock"),
+        pid: 42,
+        index_path: PathBuf::from("/data/index.bin"),
+        protocol_version,
+        build: build.map(str::to_string),
+    }
+}
+
+#[test]
+fn a_daemon_of_the_same_build_is_not_flagged() {
+    let same = daemon(PROTOCOL_VERSION, Some(BUILD));
+    assert_eq!(skew_warning(&same), None);
+    assert_eq!(
+        describe_daemon(Some(&same)),
+        format!("{BUILD}, protocol v{PROTOCOL_VERSION} (pid 42)")
+    );
+    assert_eq!(describe_daemon(None), "not running");
+}
+
+#[test]
+fn other_builds_and_protocols_are_flagged() {
+    let other = skew_warning(&daemon(PROTOCOL_VERSION, Some("0.1.0 (0123456789ab)"))).unwrap();
+    assert!(other.starts_with("the daemon is blaze 0.1.0 (0123456789ab) but this is blaze"));
+
+    let unknown = skew_warning(&daemon(PROTOCOL_VERSION, None)).unwrap();
+    assert!(unknown.starts_with("the daemon is an older blaze"));
+
+    let protocol = skew_warning(&daemon(PROTOCOL_VERSION - 1, Some(BUILD))).unwrap();
+    assert!(protocol.contains("restart the daemon"));
+    assert!(protocol.starts_with(&format!(
+        "the daemon speaks protocol v{}",
+        PROTOCOL_VERSION - 1
+    )));
+}
@@ -1,5 +1,7 @@
+pub mod collate;
 mod commands;
 mod printer;
+mod watch;
 
 pub use commands::*;
 pub use printer::*;
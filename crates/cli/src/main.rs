@@ -1,27 +1,80 @@
-use std::process::ExitCode;
+use std::{path::PathBuf, process::ExitCode};
 
-use clap::Parser;
+use blaze_cli::{
+    Command, bench, config, daemon, dirs, find, history, hook, index, locate, pin, plan, query,
+    status, top, updatedb, version,
+};
+use blaze_engine::profile::{self, CountingAlloc};
+use blaze_runtime::{logging, set_config_path};
+use clap::{CommandFactory, Parser, error::ErrorKind};
 
-mod commands;
-mod printer;
-
-use blaze_runtime::logging;
-use commands::Command;
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
 
 #[derive(Debug, Parser)]
-#[command(name = "blaze", version, about = "Blazingly Fast File Search")]
+#[command(
+    name = "blaze",
+    about = "Blazingly Fast File Search",
+    disable_version_flag = true,
+    arg_required_else_help = true
+)]
 pub struct Cli {
+    /// Print version
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// With --version, also print the index format and protocol this build
+    /// speaks, its optional features and the running daemon's build
+    #[arg(long, requires = "version")]
+    pub verbose: bool,
+
+    /// Read settings from this config file instead of
+    /// `$XDG_CONFIG_HOME/blaze/config.toml`
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     #[command(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 }
 
 fn main() -> ExitCode {
     logging::init().ok();
+    profile::init_from_env();
 
     let cli = Cli::parse();
-    match cli.command {
-        Command::Query(args) => commands::query::run(args),
-        Command::Index(args) => commands::index::run(args),
-        Command::History(args) => commands::history::run(args),
+    if cli.version {
+        return match version::print(cli.verbose) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(_) => ExitCode::from(2),
+        };
+    }
+    let Some(command) = cli.command else {
+        Cli::command()
+            .error(ErrorKind::MissingSubcommand, "a subcommand is required")
+            .exit()
+    };
+    if let Some(path) = cli.config {
+        if !path.is_file() {
+            eprintln!("[blaze] config file {} does not exist", path.display());
+            return ExitCode::from(2);
+        }
+        set_config_path(path);
+    }
+    match command {
+        Command::Query(args) => query::run(args),
+        Command::Plan(args) => plan::run(args),
+        Command::Index(args) => index::run(args),
+        Command::Find(args) => find::run(args),
+        Command::Dirs(args) => dirs::run(args),
+        Command::Locate(args) => locate::run(args),
+        Command::Updatedb(args) => updatedb::run(args),
+        Command::Pin(args) => pin::run(args),
+        Command::History(args) => history::run(args),
+        Command::Status(args) => status::run(args),
+        Command::Top(args) => top::run(args),
+        Command::Hook(args) => hook::run(args),
+        Command::Config(args) => config::run(args),
+        Command::Daemon(args) => daemon::run(args),
+        Command::BenchDaemon(args) => bench::run(args),
     }
 }
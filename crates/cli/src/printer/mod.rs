@@ -1,4 +1,4 @@
-use blaze_protocol::QueryMetrics;
+use blaze_protocol::{HitKind, QueryMetrics};
 use std::io::{self, Write};
 
 /// Trait for writing status messages (daemon, indexing progress, etc).
@@ -46,6 +46,8 @@ pub enum OutputFormat {
     Human,
     /// NDJSON (newline-delimited JSON) for machine consumption.
     Json,
+    /// Tab-separated `rank`, `score`, `path` lines; see [`PlumbingPrinter`].
+    Plumbing,
 }
 
 /// Color handling strategy.
@@ -65,18 +67,21 @@ pub enum ColorChoice {
 pub struct PrinterConfig {
     /// Color handling strategy.
     pub color: ColorChoice,
-    /// Maximum number of results to print.
-    pub limit: usize,
+    /// Maximum number of results to print; `None` prints all of them.
+    pub limit: Option<usize>,
     /// Whether to show timing statistics.
     pub show_timing: bool,
+    /// Whether to show per-stage candidate counts.
+    pub show_stats: bool,
 }
 
 impl Default for PrinterConfig {
     fn default() -> Self {
         Self {
             color: ColorChoice::Auto,
-            limit: 100,
+            limit: Some(100),
             show_timing: true,
+            show_stats: false,
         }
     }
 }
@@ -135,6 +140,72 @@ impl<W: Write, E: Write> JsonPrinter<W, E> {
     }
 }
 
+/// Stable, line-oriented output for scripts and fuzzy finders.
+///
+/// This format is a contract and must not change between versions:
+///
+/// - One result per line on stdout, in result order: `RANK\tSCORE\tPATH\n`.
+/// - `RANK` is the 1-based position, `SCORE` a signed integer that is only
+///   comparable within one query, and `PATH` the absolute path, verbatim.
+///   The path is everything after the second tab, so it may contain tabs.
+/// - Results whose path contains a newline are skipped.
+/// - Nothing else is written to stdout: no colors, headers, or summaries.
+///
+/// For example, to pick a file with fzf:
+/// `blaze query --plumbing 'ext:rs' | fzf --delimiter '\t' --with-nth 3`
+pub struct PlumbingPrinter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PlumbingPrinter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl PlumbingPrinter<io::Stdout> {
+    /// Create a printer that writes to stdout.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+/// The order results are printed in, named in the JSON summary.
+///
+/// Each order is total, so the same query against the same index prints
+/// the same results in the same order on every run: ties in relevance go
+/// to the file indexed first, ties in modification time to the lower path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultOrder {
+    /// Highest score first.
+    #[default]
+    Relevance,
+    /// By path, byte-wise.
+    Path,
+    /// Most recently modified first.
+    Mtime,
+}
+
+impl ResultOrder {
+    /// Parse a `--sort` value, `relevance`, `path` or `mtime`.
+    pub fn from_arg(value: &str) -> Self {
+        match value {
+            "path" => Self::Path,
+            "mtime" => Self::Mtime,
+            _ => Self::Relevance,
+        }
+    }
+
+    /// The name used for this order on the command line and in JSON.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Relevance => "relevance",
+            Self::Path => "path",
+            Self::Mtime => "mtime",
+        }
+    }
+}
+
 /// Static context about a print run.
 #[derive(Debug)]
 pub struct QueryPrintContext<'a> {
@@ -144,8 +215,12 @@ pub struct QueryPrintContext<'a> {
     pub query: Option<&'a str>,
     /// Total number of results (before limit)
     pub total: usize,
+    /// Number of results printed.
+    pub returned: usize,
     /// Whether output was truncated due to limit.
     pub truncated: bool,
+    /// Order the rows are printed in.
+    pub order: ResultOrder,
     /// Optional timing metrics.
     pub metrics: Option<QueryMetrics>,
 }
@@ -153,13 +228,17 @@ pub struct QueryPrintContext<'a> {
 /// One row in the result stream.
 ///
 /// This struct is intentionally minimal and generic, allowing future
-/// extension with fields like `line`, `column`, `snippet`, `score`.
+/// extension with fields like `line`, `column`, `snippet`.
 #[derive(Debug)]
 pub struct QueryRow<'a> {
     /// 1-based rank of this result.
     pub rank: usize,
+    /// Relevance score; only comparable within one query.
+    pub score: i32,
     /// Full path to the file.
     pub path: &'a str,
+    /// Directory, symlink, trash and archive markers.
+    pub kind: HitKind,
 }
 
 // QueryPrinter trait
@@ -189,12 +268,15 @@ impl<W: Write, E: Write> QueryPrinter for HumanPrinter<W, E> {
 
     fn print_row(&mut self, row: &QueryRow<'_>, _ctx: &QueryPrintContext) -> io::Result<()> {
         let path = self.format_path(row.path);
-        writeln!(self.out, "{}", path)
+        match row.kind.marker() {
+            Some(marker) => writeln!(self.out, "{path}{marker}"),
+            None => writeln!(self.out, "{path}"),
+        }
     }
 
     fn finish(&mut self, ctx: &QueryPrintContext) -> io::Result<()> {
         if ctx.truncated {
-            let remaining = ctx.total.saturating_sub(self.cfg.limit);
+            let remaining = ctx.total.saturating_sub(ctx.returned);
             writeln!(self.out, "... and {} more results", remaining)?;
         }
 
@@ -212,6 +294,17 @@ impl<W: Write, E: Write> QueryPrinter for HumanPrinter<W, E> {
             )?;
         }
 
+        if self.cfg.show_stats
+            && let Some(m) = &ctx.metrics
+        {
+            let c = &m.candidates;
+            writeln!(
+                self.err,
+                "[stats] candidates: initial {} -> trigram {} -> verified {} -> filtered {}",
+                c.initial, c.trigram, c.verified, c.filtered,
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -227,29 +320,65 @@ impl<W: Write, E: Write> QueryPrinter for JsonPrinter<W, E> {
             "query": ctx.query,
             "rank": row.rank,
             "path": row.path,
+            "dir": row.kind.dir,
+            "symlink": row.kind.symlink,
+            "trash": row.kind.trash,
+            "archive": row.kind.archive,
+            "escaped": row.kind.escaped,
         });
         writeln!(self.out, "{}", obj)
     }
 
     fn finish(&mut self, ctx: &QueryPrintContext) -> io::Result<()> {
-        if self.cfg.show_timing
+        if (self.cfg.show_timing || self.cfg.show_stats)
             && let Some(m) = &ctx.metrics
         {
-            let obj = serde_json::json!({
+            let mut obj = serde_json::json!({
                 "type": "summary",
                 "kind": ctx.kind,
                 "query": ctx.query,
                 "total": ctx.total,
+                "returned": ctx.returned,
                 "truncated": ctx.truncated,
+                "order": ctx.order.as_str(),
                 "timing_ms": {
                     "total": m.total_ms,
                     "exec": m.exec_ms,
                     "rank": m.rank_ms
                 }
             });
+            if self.cfg.show_stats {
+                obj["candidates"] = serde_json::json!({
+                    "initial": m.candidates.initial,
+                    "trigram": m.candidates.trigram,
+                    "verified": m.candidates.verified,
+                    "filtered": m.candidates.filtered,
+                });
+            }
             writeln!(self.err, "{}", obj)?;
         }
 
         Ok(())
     }
 }
+
+impl<W: Write> QueryPrinter for PlumbingPrinter<W> {
+    fn begin(&mut self, _ctx: &QueryPrintContext) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn print_row(&mut self, row: &QueryRow<'_>, _ctx: &QueryPrintContext) -> io::Result<()> {
+        if row.path.contains('\n') {
+            return Ok(());
+        }
+        writeln!(self.out, "{}\t{}\t{}", row.rank, row.score, row.path)
+    }
+
+    fn finish(&mut self, _ctx: &QueryPrintContext) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+#[path = "mod_tests.rs"]
+mod tests;
@@ -0,0 +1,225 @@
+use super::*;
+
+fn ctx() -> QueryPrintContext<'static> {
+    QueryPrintContext {
+        kind: "local",
+        query: Some("foo"),
+        total: 3,
+        returned: 2,
+        truncated: true,
+        order: ResultOrder::Relevance,
+        metrics: None,
+    }
+}
+
+fn plumb(rows: &[QueryRow<'_>]) -> String {
+    let ctx = ctx();
+    let mut printer = PlumbingPrinter::new(Vec::new());
+    printer.begin(&ctx).unwrap();
+    for row in rows {
+        printer.print_row(row, &ctx).unwrap();
+    }
+    printer.finish(&ctx).unwrap();
+    String::from_utf8(printer.out).unwrap()
+}
+
+#[test]
+fn plumbing_format_is_rank_score_path() {
+    let out = plumb(&[
+        QueryRow {
+            rank: 1,
+            score: 420,
+            path: "/home/u/src/main.rs",
+            kind: HitKind::default(),
+        },
+        QueryRow {
+            rank: 2,
+            score: -15,
+            path: "/home/u/My Docs/notes.txt",
+            kind: HitKind::default(),
+        },
+    ]);
+
+    assert_eq!(
+        out,
+        "1\t420\t/home/u/src/main.rs\n2\t-15\t/home/u/My Docs/notes.txt\n"
+    );
+}
+
+#[test]
+fn plumbing_emits_nothing_for_empty_results() {
+    // No header, footer, or truncation notice even when the context asks for it.
+    assert_eq!(plumb(&[]), "");
+}
+
+#[test]
+fn plumbing_keeps_tabs_in_path_verbatim() {
+    let out = plumb(&[QueryRow {
+        rank: 1,
+        score: 0,
+        path: "/tmp/a\tb",
+        kind: HitKind::default(),
+    }]);
+
+    let fields: Vec<&str> = out.trim_end_matches('\n').splitn(3, '\t').collect();
+    assert_eq!(fields, ["1", "0", "/tmp/a\tb"]);
+}
+
+#[test]
+fn plumbing_skips_paths_with_newlines() {
+    let out = plumb(&[
+        QueryRow {
+            rank: 1,
+            score: 10,
+            path: "/tmp/bad\nname",
+            kind: HitKind::default(),
+        },
+        QueryRow {
+            rank: 2,
+            score: 5,
+            path: "/tmp/good",
+            kind: HitKind::default(),
+        },
+    ]);
+
+    assert_eq!(out, "2\t5\t/tmp/good\n");
+}
+
+fn human(rows: &[QueryRow<'_>]) -> String {
+    let ctx = ctx();
+    let mut printer = HumanPrinter {
+        out: Vec::new(),
+        err: Vec::new(),
+        cfg: PrinterConfig {
+            show_timing: false,
+            ..PrinterConfig::default()
+        },
+        use_color: false,
+    };
+    for row in rows {
+        printer.print_row(row, &ctx).unwrap();
+    }
+    String::from_utf8(printer.out).unwrap()
+}
+
+#[test]
+fn human_output_marks_kinds_like_ls_f() {
+    let row = |path, kind| QueryRow {
+        rank: 1,
+        score: 0,
+        path,
+        kind,
+    };
+    let out = human(&[
+        row("/a/plain.txt", HitKind::default()),
+        row(
+            "/a/src",
+            HitKind {
+                dir: true,
+                ..HitKind::default()
+            },
+        ),
+        row(
+            "/a/link",
+            HitKind {
+                symlink: true,
+                dir: true,
+                ..HitKind::default()
+            },
+        ),
+        row(
+            "/a/old.txt",
+            HitKind {
+                trash: true,
+                ..HitKind::default()
+            },
+        ),
+        row(
+            "/a/backup.zip",
+            HitKind {
+                archive: true,
+                ..HitKind::default()
+            },
+        ),
+    ]);
+
+    assert_eq!(
+        out,
+        "/a/plain.txt\n/a/src/\n/a/link@\n/a/old.txt~\n/a/backup.zip#\n"
+    );
+}
+
+#[test]
+fn json_rows_carry_kind_booleans() {
+    let ctx = ctx();
+    let mut printer = JsonPrinter {
+        out: Vec::new(),
+        err: Vec::new(),
+        cfg: PrinterConfig::default(),
+    };
+    let row = QueryRow {
+        rank: 1,
+        score: 0,
+        path: "/a/link",
+        kind: HitKind {
+            symlink: true,
+            ..HitKind::default()
+        },
+    };
+    printer.print_row(&row, &ctx).unwrap();
+
+    let obj: serde_json::Value = serde_json::from_slice(&printer.out).unwrap();
+    assert_eq!(obj["symlink"], true);
+    assert_eq!(obj["dir"], false);
+    assert_eq!(obj["trash"], false);
+    assert_eq!(obj["archive"], false);
+    assert_eq!(obj["escaped"], false);
+}
+
+#[test]
+fn json_summary_names_the_result_order() {
+    let ctx = QueryPrintContext {
+        order: ResultOrder::Mtime,
+        metrics: Some(QueryMetrics {
+            total_ms: 1.0,
+            exec_ms: 0.5,
+            rank_ms: 0.25,
+            candidates: Default::default(),
+        }),
+        ..ctx()
+    };
+    let mut printer = JsonPrinter {
+        out: Vec::new(),
+        err: Vec::new(),
+        cfg: PrinterConfig {
+            show_timing: true,
+            ..PrinterConfig::default()
+        },
+    };
+    printer.finish(&ctx).unwrap();
+
+    let obj: serde_json::Value = serde_json::from_slice(&printer.err).unwrap();
+    assert_eq!(obj["type"], "summary");
+    assert_eq!(obj["order"], "mtime");
+}
+
+#[test]
+fn human_truncation_counts_what_was_not_returned() {
+    // The limit is 100, but only two of three matches came back.
+    let ctx = ctx();
+    let mut printer = HumanPrinter {
+        out: Vec::new(),
+        err: Vec::new(),
+        cfg: PrinterConfig {
+            show_timing: false,
+            ..PrinterConfig::default()
+        },
+        use_color: false,
+    };
+    printer.finish(&ctx).unwrap();
+
+    assert_eq!(
+        String::from_utf8(printer.out).unwrap(),
+        "... and 1 more results\n"
+    );
+}
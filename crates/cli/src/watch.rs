@@ -0,0 +1,158 @@
+//! Keeping a query's matches current while files change.
+//!
+//! `blaze query --watch` does not rewrite the index as the tree changes.
+//! Each changed path is checked against the query on its own, over an
+//! in-memory index of just the changed files, and joins or leaves the
+//! matches accordingly. Paths that did not change keep the answer the index
+//! gave.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use blaze_engine::{IndexBuilder, QueryPipeline};
+use blaze_fs::{FileRecord, portable_path};
+
+use crate::printer::OutputFormat;
+
+/// Which of `records` match `query`, as sorted indices into `records`.
+///
+/// The query runs over an in-memory index of just these records, rooted at
+/// `root`, so it sees them as the full index would.
+pub(crate) fn matching_records(root: &Path, query: &str, records: Vec<FileRecord>) -> Vec<usize> {
+    let mut builder = IndexBuilder::new(root.to_path_buf());
+    builder.add_batch(records);
+    let staged = builder.finish();
+
+    let mut hits = QueryPipeline::new(&staged)
+        .parse(query)
+        .execute()
+        .unranked()
+        .into_results();
+    hits.sort_unstable();
+    hits.into_iter().map(|fid| fid as usize).collect()
+}
+
+/// How the matches of a watched query changed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct MatchDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl MatchDiff {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Print one line per added or removed path: `+ path` and `- path`,
+    /// tab-separated for plumbing, or NDJSON objects.
+    pub(crate) fn print(&self, out: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+        let changes = (self.removed.iter().map(|p| ("removed", p)))
+            .chain(self.added.iter().map(|p| ("added", p)));
+        for (change, path) in changes {
+            let sign = if change == "added" { '+' } else { '-' };
+            match format {
+                OutputFormat::Human => writeln!(out, "{sign} {path}")?,
+                OutputFormat::Plumbing => writeln!(out, "{sign}\t{path}")?,
+                OutputFormat::Json => {
+                    let obj = serde_json::json!({ "change": change, "path": path });
+                    writeln!(out, "{obj}")?
+                }
+            }
+        }
+        out.flush()
+    }
+}
+
+/// The current matches of a query over the files under `root`.
+pub(crate) struct LiveMatches {
+    root: PathBuf,
+    query: String,
+    matches: BTreeSet<String>,
+}
+
+impl LiveMatches {
+    /// Start from `matches`, the paths the index matched.
+    pub(crate) fn new(
+        root: PathBuf,
+        query: String,
+        matches: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            root,
+            query,
+            matches: matches.into_iter().collect(),
+        }
+    }
+
+    /// The directory the query's index covers.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Re-check changed paths: each comes with its record, or `None` if it
+    /// is gone. A path that is gone takes any matches below it along.
+    pub(crate) fn update(&mut self, changes: Vec<(PathBuf, Option<FileRecord>)>) -> MatchDiff {
+        let mut diff = MatchDiff::default();
+        let mut present = Vec::new();
+        let mut records = Vec::new();
+        for (path, rec) in changes {
+            let path = portable_path(&path).into_owned();
+            match rec {
+                Some(rec) => {
+                    present.push(path);
+                    records.push(rec);
+                }
+                None => self.remove_tree(&path, &mut diff.removed),
+            }
+        }
+
+        let mut matching = matching_records(&self.root, &self.query, records).into_iter();
+        let mut next_match = matching.next();
+        for (i, path) in present.into_iter().enumerate() {
+            if next_match == Some(i) {
+                next_match = matching.next();
+                if !self.matches.contains(&path) {
+                    self.matches.insert(path.clone());
+                    diff.added.push(path);
+                }
+            } else if self.matches.remove(&path) {
+                diff.removed.push(path);
+            }
+        }
+
+        diff.added.sort_unstable();
+        diff.removed.sort_unstable();
+        diff.removed.dedup();
+        diff
+    }
+
+    /// Drop `path` and every match below it.
+    fn remove_tree(&mut self, path: &str, removed: &mut Vec<String>) {
+        if self.matches.remove(path) {
+            removed.push(path.to_owned());
+        }
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let below: Vec<String> = self
+            .matches
+            .range(prefix.clone()..)
+            .take_while(|p| p.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for p in below {
+            self.matches.remove(&p);
+            removed.push(p);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "watch_tests.rs"]
+mod tests;
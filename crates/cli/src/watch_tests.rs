@@ -0,0 +1,98 @@
+use super::*;
+
+const ROOT: &str = "/w";
+
+fn live(query: &str, matches: &[&str]) -> LiveMatches {
+    let matches = matches.iter().map(|p| p.to_string());
+    LiveMatches::new(PathBuf::from(ROOT), query.to_owned(), matches)
+}
+
+fn present(path: &str) -> (PathBuf, Option<FileRecord>) {
+    (PathBuf::from(path), Some(FileRecord::test_file(path)))
+}
+
+fn gone(path: &str) -> (PathBuf, Option<FileRecord>) {
+    (PathBuf::from(path), None)
+}
+
+fn diff(added: &[&str], removed: &[&str]) -> MatchDiff {
+    MatchDiff {
+        added: added.iter().map(|p| p.to_string()).collect(),
+        removed: removed.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+#[test]
+fn records_are_matched_over_an_index_of_their_own() {
+    let records = vec![
+        FileRecord::test_file("/w/logs/build.log"),
+        FileRecord::test_file("/w/src/main.rs"),
+        FileRecord::test_file("/w/logs/error.log"),
+    ];
+    assert_eq!(
+        matching_records(Path::new(ROOT), "ext:log", records),
+        [0, 2]
+    );
+}
+
+#[test]
+fn changed_files_join_or_leave_the_matches() {
+    let mut live = live("ext:log", &["/w/old.log", "/w/kept.log"]);
+
+    let changes = vec![
+        present("/w/new.log"),
+        present("/w/new.rs"),
+        gone("/w/old.log"),
+    ];
+    assert_eq!(live.update(changes), diff(&["/w/new.log"], &["/w/old.log"]));
+    assert_eq!(live.len(), 2);
+
+    // Modifying a match that still matches changes nothing.
+    assert!(live.update(vec![present("/w/new.log")]).is_empty());
+    assert!(live.update(vec![gone("/w/never-matched.log")]).is_empty());
+}
+
+#[test]
+fn files_that_stop_matching_are_removed() {
+    let mut live = live("ext:log size:<1kb", &["/w/error.log"]);
+    assert!(live.update(vec![present("/w/error.log")]).is_empty());
+
+    let mut grown = FileRecord::test_file("/w/error.log");
+    grown.size = 4096;
+    let changes = vec![(PathBuf::from("/w/error.log"), Some(grown))];
+    assert_eq!(live.update(changes), diff(&[], &["/w/error.log"]));
+}
+
+#[test]
+fn a_removed_directory_takes_its_matches_along() {
+    let mut live = live(
+        "ext:log",
+        &["/w/a.log", "/w/out/b.log", "/w/out/x/c.log", "/w/outer.log"],
+    );
+    assert_eq!(
+        live.update(vec![gone("/w/out")]),
+        diff(&[], &["/w/out/b.log", "/w/out/x/c.log"])
+    );
+    assert_eq!(live.len(), 2);
+}
+
+#[test]
+fn diffs_print_removals_first_in_each_format() {
+    let changes = diff(&["/w/new.log"], &["/w/old.log"]);
+    let printed = |format| {
+        let mut out = Vec::new();
+        changes.print(&mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(printed(OutputFormat::Human), "- /w/old.log\n+ /w/new.log\n");
+    assert_eq!(
+        printed(OutputFormat::Plumbing),
+        "-\t/w/old.log\n+\t/w/new.log\n"
+    );
+    assert_eq!(
+        printed(OutputFormat::Json),
+        "{\"change\":\"removed\",\"path\":\"/w/old.log\"}\n\
+         {\"change\":\"added\",\"path\":\"/w/new.log\"}\n"
+    );
+}
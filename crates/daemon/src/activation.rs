@@ -0,0 +1,52 @@
+//! systemd socket activation (`sd_listen_fds` protocol).
+//!
+//! When started by a `.socket` unit, the listening socket is passed to us as
+//! file descriptor 3, with `LISTEN_PID` and `LISTEN_FDS` describing it.
+
+use std::env;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+
+use log::warn;
+
+/// First file descriptor passed by the service manager.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Number of sockets passed to this process, if any.
+///
+/// Variables addressed to another process (e.g. inherited from a parent
+/// that was itself socket-activated) are ignored.
+pub fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, own_pid: u32) -> usize {
+    let Some(pid) = listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) else {
+        return 0;
+    };
+    if pid != own_pid {
+        return 0;
+    }
+
+    listen_fds
+        .and_then(|n| n.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+/// Take over the listening socket passed by the service manager, if any.
+pub fn inherited_listener() -> Option<UnixListener> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+
+    match listen_fds(pid.as_deref(), fds.as_deref(), std::process::id()) {
+        0 => None,
+        n => {
+            if n > 1 {
+                warn!("Received {n} sockets from the service manager; using the first");
+            }
+            // SAFETY: the service manager guarantees fd 3 is an open,
+            // listening socket owned by this process.
+            Some(unsafe { UnixListener::from_raw_fd(SD_LISTEN_FDS_START) })
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "activation_tests.rs"]
+mod tests;
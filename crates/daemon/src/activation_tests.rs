@@ -0,0 +1,25 @@
+use super::*;
+
+#[test]
+fn no_sockets_without_env() {
+    assert_eq!(listen_fds(None, None, 42), 0);
+    assert_eq!(listen_fds(None, Some("1"), 42), 0);
+}
+
+#[test]
+fn sockets_for_this_process() {
+    assert_eq!(listen_fds(Some("42"), Some("1"), 42), 1);
+    assert_eq!(listen_fds(Some("42"), Some("2"), 42), 2);
+}
+
+#[test]
+fn ignores_sockets_for_other_process() {
+    assert_eq!(listen_fds(Some("7"), Some("1"), 42), 0);
+}
+
+#[test]
+fn ignores_malformed_values() {
+    assert_eq!(listen_fds(Some("abc"), Some("1"), 42), 0);
+    assert_eq!(listen_fds(Some("42"), Some("many"), 42), 0);
+    assert_eq!(listen_fds(Some("42"), None, 42), 0);
+}
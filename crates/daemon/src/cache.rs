@@ -0,0 +1,121 @@
+//! Recently answered queries, so a repeated or prefetched query is answered
+//! without running it again.
+//!
+//! Entries are keyed by the index generations that answered them, so a
+//! reloaded index never serves stale hits; old entries just age out. Other
+//! inputs to ranking, such as pins and boosts, are not part of the key: a
+//! change to them shows once the entry is evicted or the index reloads.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use blaze_engine::parse_query;
+use blaze_protocol::{QueryRequest, QueryResponse};
+
+/// Everything a cached response depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub generation: u64,
+    /// Generation of the system index merged in, if any.
+    pub system_generation: Option<u64>,
+    /// The query in canonical form, so spellings of one query share an
+    /// entry.
+    pub query: String,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub under: Option<String>,
+}
+
+impl CacheKey {
+    pub fn new(generation: u64, system_generation: Option<u64>, req: &QueryRequest) -> Self {
+        Self {
+            generation,
+            system_generation,
+            query: parse_query(&req.query).canonical_key(),
+            limit: req.limit,
+            offset: req.offset,
+            under: req.under.clone(),
+        }
+    }
+}
+
+struct Entry {
+    response: QueryResponse,
+    last_used: u64,
+}
+
+struct Entries {
+    map: HashMap<CacheKey, Entry>,
+    /// Bumped on every access; the entry with the lowest `last_used` goes
+    /// first.
+    clock: u64,
+}
+
+/// A least-recently-used cache of query responses.
+pub struct QueryCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl QueryCache {
+    /// A cache holding up to `capacity` responses; 0 disables it.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                map: HashMap::with_capacity(capacity),
+                clock: 0,
+            }),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<QueryResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let now = entries.clock;
+        let entry = entries.map.get_mut(key)?;
+        entry.last_used = now;
+        Some(entry.response.clone())
+    }
+
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.lock().unwrap().map.contains_key(key)
+    }
+
+    pub fn insert(&self, key: CacheKey, response: QueryResponse) {
+        if !self.enabled() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let last_used = entries.clock;
+        if entries.map.len() >= self.capacity
+            && !entries.map.contains_key(&key)
+            && let Some(oldest) = entries
+                .map
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+        {
+            entries.map.remove(&oldest);
+        }
+        entries.map.insert(
+            key,
+            Entry {
+                response,
+                last_used,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+}
+
+#[cfg(test)]
+#[path = "cache_tests.rs"]
+mod tests;
@@ -0,0 +1,68 @@
+use super::*;
+
+fn request(query: &str) -> QueryRequest {
+    QueryRequest {
+        query: query.to_string(),
+        limit: Some(20),
+        under: None,
+        session: None,
+        offset: 0,
+    }
+}
+
+fn response(total: u32) -> QueryResponse {
+    QueryResponse {
+        hits: Vec::new(),
+        total,
+        returned: 0,
+        metrics: None,
+        generation: 0,
+        index_created_secs: None,
+        root: None,
+        cached: false,
+        refined: false,
+        truncated: None,
+        next_offset: None,
+    }
+}
+
+fn key(generation: u64, query: &str) -> CacheKey {
+    CacheKey::new(generation, None, &request(query))
+}
+
+#[test]
+fn evicts_the_least_recently_used_entry() {
+    let cache = QueryCache::new(2);
+    cache.insert(key(0, "a"), response(1));
+    cache.insert(key(0, "b"), response(2));
+    // Touching `a` makes `b` the oldest.
+    assert_eq!(cache.get(&key(0, "a")).unwrap().total, 1);
+    cache.insert(key(0, "c"), response(3));
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains(&key(0, "a")));
+    assert!(!cache.contains(&key(0, "b")));
+    assert!(cache.contains(&key(0, "c")));
+}
+
+#[test]
+fn entries_belong_to_one_index_generation() {
+    let cache = QueryCache::new(4);
+    cache.insert(key(0, "a"), response(1));
+
+    assert!(cache.get(&key(1, "a")).is_none());
+    assert!(
+        cache
+            .get(&CacheKey::new(0, Some(1), &request("a")))
+            .is_none()
+    );
+}
+
+#[test]
+fn zero_capacity_keeps_nothing() {
+    let cache = QueryCache::new(0);
+    cache.insert(key(0, "a"), response(1));
+
+    assert!(!cache.enabled());
+    assert!(cache.get(&key(0, "a")).is_none());
+}
@@ -1,7 +1,10 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use anyhow::Result;
-use blaze_runtime::{blaze_dir, default_index_path, default_scan_root};
+use anyhow::{Result, bail};
+use blaze_engine::parse_size;
+use blaze_runtime::{
+    SYSTEM_INDEX_PATH, SettingFlags, default_scan_root, set_config_path, settings,
+};
 use clap::Parser;
 
 #[derive(Debug, Clone)]
@@ -11,15 +14,50 @@ pub struct DaemonConfig {
     pub index_path: PathBuf,
     // Unix domain socket path
     pub socket_path: PathBuf,
-}
-
-fn default_socket_path() -> PathBuf {
-    blaze_dir().join("daemon.sock")
+    // How long shutdown waits for in-flight requests
+    pub drain_timeout: Duration,
+    // How long a query waits for the index while the daemon starts up
+    pub startup_wait: Duration,
+    // Rebuild the index instead of failing when it cannot be read
+    pub auto_rebuild: bool,
+    // Resident memory the daemon may use, in bytes
+    pub memory_limit: Option<u64>,
+    // Queries per second each user may sustain; 0 for no limit
+    pub rate_limit: u32,
+    // Queries each user may send in a burst
+    pub rate_burst: u32,
+    // Lock the index's key tables and file records into memory
+    pub mlock: bool,
+    // Shared index built by root to merge into results
+    pub system_index: Option<PathBuf>,
+    // Query responses to keep for repeated or prefetched queries
+    pub query_cache: usize,
+    // Most queries one prefetch request runs
+    pub prefetch: usize,
+    // Search-as-you-type sessions whose last matches are kept
+    pub sessions: usize,
+    // Socket of a daemon whose index to follow instead of building one
+    pub follow: Option<PathBuf>,
+    // How often to check the followed daemon for a new index
+    pub follow_interval: Duration,
+    // Where the followed daemon's root is mounted here, if elsewhere
+    pub follow_root: Option<PathBuf>,
+    // How often to check for a rewritten index file; zero never checks
+    pub reload_interval: Duration,
+    // Most hits one query response carries; 0 for no cap
+    pub max_hits: usize,
+    // Most bytes of paths one query response carries; 0 for no cap
+    pub max_response_bytes: u64,
 }
 
 #[derive(Debug, Parser)]
-#[command(name = "blaze-daemon", about = "Blaze Daemon")]
+#[command(name = "blaze-daemon", version = blaze_protocol::BUILD, about = "Blaze Daemon")]
 pub struct Cli {
+    /// Read settings from this config file instead of
+    /// `$XDG_CONFIG_HOME/blaze/config.toml`
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     /// Path to index file (optional override)
     #[arg(long)]
     pub index_path: Option<PathBuf>,
@@ -27,23 +65,142 @@ pub struct Cli {
     /// Path to Unix domain socket (optional override)
     #[arg(long)]
     pub socket_path: Option<PathBuf>,
+
+    /// Seconds to wait for in-flight requests on shutdown
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    pub drain_timeout: u64,
+
+    /// Seconds a query waits for the index to load before giving up
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub startup_wait: u64,
+
+    /// Move an unreadable index aside and rebuild it instead of failing
+    #[arg(long)]
+    pub auto_rebuild: bool,
+
+    /// Resident memory to stay within, e.g. `512M` (default: no limit, or
+    /// the `memory_limit` setting)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+    pub memory_limit: Option<u64>,
+
+    /// Queries per second each user may sustain; more are answered with a
+    /// `rate limited` error (default: no limit)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub rate_limit: u32,
+
+    /// Queries each user may send at once before `--rate-limit` applies
+    /// (default: twice the rate)
+    #[arg(long, value_name = "N")]
+    pub rate_burst: Option<u32>,
+
+    /// Lock the index's trigram keys and file records into memory so
+    /// queries never wait on disk (needs a large enough RLIMIT_MEMLOCK)
+    #[arg(long)]
+    pub mlock: bool,
+
+    /// Also search the system-wide index built by `blaze index --system`,
+    /// hiding files this user cannot reach (default path when no value)
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = SYSTEM_INDEX_PATH)]
+    pub system_index: Option<PathBuf>,
+
+    /// Query responses to keep, so repeated and prefetched queries are
+    /// answered at once (0 disables the cache)
+    #[arg(long, value_name = "N", default_value_t = 64)]
+    pub query_cache: usize,
+
+    /// Most queries to run speculatively when a client asks to prefetch:
+    /// the typed query and its completions from history (0 disables)
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub prefetch: usize,
+
+    /// Search-as-you-type sessions to keep the last matches of, so a query
+    /// typed further searches only those (0 disables)
+    #[arg(long, value_name = "N", default_value_t = 64)]
+    pub sessions: usize,
+
+    /// Copy the index of the daemon listening on SOCKET instead of building
+    /// one, and keep the copy up to date
+    #[arg(long, value_name = "SOCKET")]
+    pub follow: Option<PathBuf>,
+
+    /// Seconds between checks for a new index on the followed daemon
+    #[arg(long, value_name = "SECS", default_value_t = 300, requires = "follow")]
+    pub follow_interval: u64,
+
+    /// Where the followed daemon's root is mounted on this machine, if not
+    /// at the same path
+    #[arg(long, value_name = "PATH", requires = "follow")]
+    pub follow_root: Option<PathBuf>,
+
+    /// Seconds between checks for an index rebuilt or refreshed on disk,
+    /// which is then swapped in without a restart (0 disables)
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 5,
+        conflicts_with = "follow"
+    )]
+    pub reload_interval: u64,
+
+    /// Most hits to return for one query, however many it asks for;
+    /// clients fetch the rest page by page (0 for no cap)
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    pub max_hits: usize,
+
+    /// Most bytes of paths to return for one query, e.g. `16M`; clients
+    /// fetch the rest page by page (0 for no cap)
+    #[arg(long, value_name = "SIZE", default_value = "32M", value_parser = parse_size_arg)]
+    pub max_response_bytes: u64,
 }
 
 impl DaemonConfig {
     pub fn from_args(args: &Cli) -> Result<Self> {
         let root = default_scan_root();
-        let index_path = args.index_path.clone().unwrap_or_else(default_index_path);
-        let socket_path = args.socket_path.clone().unwrap_or_else(default_socket_path);
+        let settings = settings().clone().with_flags(&SettingFlags {
+            index_path: args.index_path.clone(),
+            socket_path: args.socket_path.clone(),
+            memory_limit: args.memory_limit,
+            ..SettingFlags::default()
+        });
 
         Ok(Self {
             root,
-            index_path,
-            socket_path,
+            index_path: settings.index_path.value,
+            socket_path: settings.socket_path.value,
+            drain_timeout: Duration::from_secs(args.drain_timeout),
+            startup_wait: Duration::from_secs(args.startup_wait),
+            auto_rebuild: args.auto_rebuild,
+            memory_limit: settings.memory_limit.value,
+            rate_limit: args.rate_limit,
+            rate_burst: args
+                .rate_burst
+                .unwrap_or_else(|| args.rate_limit.saturating_mul(2)),
+            mlock: args.mlock,
+            system_index: args.system_index.clone(),
+            query_cache: args.query_cache,
+            prefetch: args.prefetch,
+            sessions: args.sessions,
+            follow: args.follow.clone(),
+            follow_interval: Duration::from_secs(args.follow_interval),
+            follow_root: args.follow_root.clone(),
+            reload_interval: Duration::from_secs(args.reload_interval),
+            max_hits: args.max_hits,
+            max_response_bytes: args.max_response_bytes,
         })
     }
 
     pub fn from_env() -> Result<Self> {
         let args = Cli::parse();
+        if let Some(path) = &args.config {
+            if !path.is_file() {
+                bail!("config file {} does not exist", path.display());
+            }
+            set_config_path(path.clone());
+        }
         Self::from_args(&args)
     }
 }
+
+fn parse_size_arg(s: &str) -> Result<u64, String> {
+    parse_size(s).ok_or_else(|| format!("invalid size `{s}` (expected e.g. 512M or 2G)"))
+}
@@ -0,0 +1,63 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Counts client connections that are still being served, so shutdown can
+/// wait for them instead of abandoning their worker threads.
+#[derive(Default)]
+pub struct ActiveConnections {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+/// Marks one connection as in flight until dropped.
+pub struct ConnectionGuard {
+    tracker: Arc<ActiveConnections>,
+}
+
+impl ActiveConnections {
+    /// Register a new connection. It stays active until the guard drops.
+    pub fn enter(self: &Arc<Self>) -> ConnectionGuard {
+        *self.count.lock().unwrap() += 1;
+        ConnectionGuard {
+            tracker: Arc::clone(self),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        *self.count.lock().unwrap()
+    }
+
+    /// Block until no connections are active or `timeout` elapses.
+    ///
+    /// Returns the number of connections still active when it gave up.
+    pub fn wait_idle(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.count.lock().unwrap();
+
+        while *count > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            count = self.idle.wait_timeout(count, deadline - now).unwrap().0;
+        }
+
+        *count
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut count = self.tracker.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.tracker.idle.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "drain_tests.rs"]
+mod tests;
@@ -0,0 +1,48 @@
+use std::thread;
+
+use super::*;
+
+#[test]
+fn guards_track_active_connections() {
+    let tracker = Arc::new(ActiveConnections::default());
+
+    let a = tracker.enter();
+    let b = tracker.enter();
+    assert_eq!(tracker.active(), 2);
+
+    drop(a);
+    assert_eq!(tracker.active(), 1);
+    drop(b);
+    assert_eq!(tracker.active(), 0);
+}
+
+#[test]
+fn wait_idle_returns_immediately_without_connections() {
+    let tracker = Arc::new(ActiveConnections::default());
+
+    let start = Instant::now();
+    assert_eq!(tracker.wait_idle(Duration::from_secs(10)), 0);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn wait_idle_waits_for_in_flight_connection() {
+    let tracker = Arc::new(ActiveConnections::default());
+    let guard = tracker.enter();
+
+    let worker = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+    });
+
+    assert_eq!(tracker.wait_idle(Duration::from_secs(10)), 0);
+    worker.join().unwrap();
+}
+
+#[test]
+fn wait_idle_gives_up_after_timeout() {
+    let tracker = Arc::new(ActiveConnections::default());
+    let _guard = tracker.enter();
+
+    assert_eq!(tracker.wait_idle(Duration::from_millis(20)), 1);
+}
@@ -0,0 +1,122 @@
+//! Merging the shared system index into a user's results.
+//!
+//! Root builds one index of system paths (`blaze index --system`) that every
+//! user can read. A per-user daemon started with `--system-index` queries it
+//! next to the user's own index and merges the hits. Results the user could
+//! not `stat` are hidden, using the directory permissions recorded when the
+//! system index was built; a system index without them is not used at all.
+//!
+//! The system index is reopened when root replaces it or its delta.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use blaze_engine::{Credentials, DirAccess, Index, IndexReader, delta_path};
+use log::{info, warn};
+
+/// A loaded system index and what the daemon's user may see of it.
+pub struct SystemSnapshot {
+    pub index: Index,
+    pub access: Arc<DirAccess>,
+    /// Incremented every time the system index is reloaded.
+    pub generation: u64,
+}
+
+/// What identifies one version of a file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What identifies one version of an index and its delta on disk.
+pub type Stamp = (Option<FileStamp>, Option<FileStamp>);
+
+/// The current [`Stamp`] of the index at `path`. Costs two `stat` calls.
+pub fn index_stamp(path: &Path) -> Stamp {
+    (stamp(path), stamp(&delta_path(path)))
+}
+
+struct Loaded {
+    stamp: Stamp,
+    /// `None` if this version could not be used; the reason was logged.
+    snapshot: Option<Arc<SystemSnapshot>>,
+}
+
+pub struct SystemIndex {
+    path: PathBuf,
+    who: Credentials,
+    loaded: Mutex<Option<Loaded>>,
+    loads: AtomicU64,
+}
+
+impl SystemIndex {
+    /// The system index at `path`, seen as the user this process runs as.
+    pub fn new(path: PathBuf) -> io::Result<Self> {
+        Ok(Self {
+            path,
+            who: Credentials::current()?,
+            loaded: Mutex::new(None),
+            loads: AtomicU64::new(0),
+        })
+    }
+
+    /// The system index as it is on disk now, or `None` if it cannot be
+    /// used. Costs two `stat` calls unless the index changed.
+    pub fn current(&self) -> Option<Arc<SystemSnapshot>> {
+        let stamp = index_stamp(&self.path);
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(l) = loaded.as_ref()
+            && l.stamp == stamp
+        {
+            return l.snapshot.clone();
+        }
+
+        let snapshot = match self.open() {
+            Ok(snapshot) => {
+                info!(
+                    "Loaded system index {} ({} files)",
+                    self.path.display(),
+                    snapshot.index.live_file_count()
+                );
+                Some(Arc::new(snapshot))
+            }
+            Err(e) => {
+                warn!("Not using system index {}: {e}", self.path.display());
+                None
+            }
+        };
+        *loaded = Some(Loaded {
+            stamp,
+            snapshot: snapshot.clone(),
+        });
+        snapshot
+    }
+
+    fn open(&self) -> Result<SystemSnapshot, String> {
+        let index = Index::open(&self.path).map_err(|e| e.to_string())?;
+        let access = index
+            .dir_access(&self.who)
+            .ok_or("it has no permissions to check results against; rebuild it")?;
+        Ok(SystemSnapshot {
+            index,
+            access: Arc::new(access),
+            generation: self.loads.fetch_add(1, Ordering::Relaxed) + 1,
+        })
+    }
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some(FileStamp {
+        len: meta.len(),
+        modified: meta.modified().ok(),
+    })
+}
@@ -1,28 +1,63 @@
 use std::sync::Arc;
+use std::thread;
 
+mod activation;
+mod cache;
 mod config;
+mod drain;
+mod federation;
+mod memory;
+mod prefetch;
 mod query;
+mod ratelimit;
+mod replication;
 mod rpc;
+mod session;
 mod state;
+mod subscription;
 
+use blaze_engine::profile::{self, CountingAlloc};
 use blaze_runtime::logging;
 use config::DaemonConfig;
 use state::DaemonState;
 
 use log::info;
 
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
 fn main() -> anyhow::Result<()> {
     logging::init().ok();
+    profile::init_from_env();
 
     let config = DaemonConfig::from_env()?;
 
     info!(
-        "Starting blaze daemon: root={}, index={}, socket={}",
+        "Starting blaze daemon {}: root={}, index={}, socket={}",
+        blaze_protocol::BUILD,
         config.root.display(),
         config.index_path.display(),
         config.socket_path.display(),
     );
 
-    let state = Arc::new(DaemonState::new(config)?);
+    // Serve the socket right away; the index loads and warms in the
+    // background while early requests see `Starting`/`Warming`.
+    let state = Arc::new(DaemonState::starting(config));
+    let loader = Arc::clone(&state);
+    match state.config.follow.clone() {
+        Some(leader) => {
+            info!("Following the index of the daemon at {}", leader.display());
+            thread::Builder::new()
+                .name("index-follower".into())
+                .spawn(move || replication::follow(&loader, &leader))?
+        }
+        None => thread::Builder::new()
+            .name("index-loader".into())
+            .spawn(move || {
+                loader.load();
+                loader.watch_index_file();
+            })?,
+    };
+
     rpc::run_rpc_server(state)
 }
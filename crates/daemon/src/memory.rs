@@ -0,0 +1,160 @@
+//! Keeping the daemon within a memory limit.
+//!
+//! Most of the daemon's footprint is the index it maps, which counts as
+//! resident once paged in; caches and per-query buffers on the heap make up
+//! the rest. With a limit set, each query first checks the resident set
+//! size of the process. Over the limit, the daemon drops the index pages it
+//! holds, then runs queries one at a time, and refuses a query that would
+//! still start over the limit with nothing else running.
+
+use std::{
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use blaze_engine::{Index, WarmSet};
+use log::{info, warn};
+
+/// Reports the memory the process uses, in bytes.
+type UsageProbe = fn() -> Option<u64>;
+
+pub struct MemoryBudget {
+    limit: Option<u64>,
+    usage: UsageProbe,
+    in_flight: Mutex<usize>,
+    idle: Condvar,
+    /// Whether the last check found usage over the limit, so the warning is
+    /// logged once per excursion rather than once per query.
+    over: AtomicBool,
+}
+
+/// Marks one query as running until dropped.
+pub struct QueryPermit<'a> {
+    budget: &'a MemoryBudget,
+}
+
+impl MemoryBudget {
+    /// A budget of `limit` bytes of resident memory, or none.
+    pub fn new(limit: Option<u64>) -> Self {
+        Self::with_probe(limit, resident_bytes)
+    }
+
+    pub(crate) fn with_probe(limit: Option<u64>, usage: UsageProbe) -> Self {
+        if limit.is_some() && usage().is_none() {
+            warn!("Memory limit set, but this platform does not report memory usage; ignoring it");
+        }
+        Self {
+            limit,
+            usage,
+            in_flight: Mutex::new(0),
+            idle: Condvar::new(),
+            over: AtomicBool::new(false),
+        }
+    }
+
+    /// Admit one query against `index`, waiting up to `wait` for queries in
+    /// flight to finish if the daemon is over its limit.
+    pub fn admit(&self, index: &Index, wait: Duration) -> Result<QueryPermit<'_>, String> {
+        let (Some(limit), Some(usage)) = (self.limit, (self.usage)()) else {
+            return Ok(self.enter());
+        };
+        if usage <= limit {
+            if self.over.swap(false, Ordering::Relaxed) {
+                info!("Memory use back under the limit: {}", mib(usage));
+            }
+            return Ok(self.enter());
+        }
+
+        if !self.over.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Memory use {} is over the {} limit; releasing index pages and running queries one at a time",
+                mib(usage),
+                mib(limit)
+            );
+        }
+
+        // Posting lists first: nearly every query reads the hot sections.
+        for set in [WarmSet::Cold, WarmSet::Hot] {
+            index.release(set);
+            if self.under(limit) {
+                return Ok(self.enter());
+            }
+        }
+
+        // What is left is heap; queries in flight give theirs back when
+        // they finish.
+        let deadline = Instant::now() + wait;
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(format!(
+                    "daemon is over its {} memory limit and busy; try again shortly",
+                    mib(limit)
+                ));
+            }
+            in_flight = self.idle.wait_timeout(in_flight, deadline - now).unwrap().0;
+        }
+        if !self.under(limit) {
+            let usage = (self.usage)().unwrap_or_default();
+            return Err(format!(
+                "daemon uses {}, over its {} memory limit; raise --memory-limit",
+                mib(usage),
+                mib(limit)
+            ));
+        }
+
+        *in_flight += 1;
+        Ok(QueryPermit { budget: self })
+    }
+
+    fn under(&self, limit: u64) -> bool {
+        (self.usage)().is_none_or(|usage| usage <= limit)
+    }
+
+    fn enter(&self) -> QueryPermit<'_> {
+        *self.in_flight.lock().unwrap() += 1;
+        QueryPermit { budget: self }
+    }
+}
+
+impl Drop for QueryPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.budget.in_flight.lock().unwrap();
+        *in_flight -= 1;
+        if *in_flight == 0 {
+            self.budget.idle.notify_all();
+        }
+    }
+}
+
+/// Resident set size of this process, from `/proc/self/status`.
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
+
+fn mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+#[path = "memory_tests.rs"]
+mod tests;
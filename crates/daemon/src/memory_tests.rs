@@ -0,0 +1,74 @@
+use std::{path::PathBuf, sync::atomic::AtomicU64};
+
+use blaze_engine::IndexBuilder;
+use blaze_fs::FileRecord;
+
+use super::*;
+
+const MIB: u64 = 1024 * 1024;
+
+fn index() -> Index {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch((0..10).map(|i| FileRecord::test_file(root.join(format!("note{i}.txt")))));
+    Index::from_staged(&builder.finish()).unwrap()
+}
+
+#[test]
+fn admits_queries_within_the_limit() {
+    let budget = MemoryBudget::with_probe(Some(100 * MIB), || Some(50 * MIB));
+    let index = index();
+    let first = budget.admit(&index, Duration::ZERO).unwrap();
+    let second = budget.admit(&index, Duration::ZERO).unwrap();
+    assert_eq!(*budget.in_flight.lock().unwrap(), 2);
+    drop((first, second));
+    assert_eq!(*budget.in_flight.lock().unwrap(), 0);
+}
+
+#[test]
+fn no_limit_or_no_probe_admits_everything() {
+    let index = index();
+    let unlimited = MemoryBudget::with_probe(None, || Some(u64::MAX));
+    assert!(unlimited.admit(&index, Duration::ZERO).is_ok());
+    let unmeasured = MemoryBudget::with_probe(Some(1), || None);
+    assert!(unmeasured.admit(&index, Duration::ZERO).is_ok());
+}
+
+#[test]
+fn refuses_queries_while_over_the_limit() {
+    static USAGE: AtomicU64 = AtomicU64::new(0);
+    USAGE.store(200 * MIB, Ordering::Relaxed);
+    let budget = MemoryBudget::with_probe(Some(100 * MIB), || Some(USAGE.load(Ordering::Relaxed)));
+    let index = index();
+
+    let err = budget.admit(&index, Duration::ZERO).err().unwrap();
+    assert!(err.contains("over its 100.0 MiB memory limit"), "{err}");
+
+    USAGE.store(80 * MIB, Ordering::Relaxed);
+    assert!(budget.admit(&index, Duration::ZERO).is_ok());
+}
+
+#[test]
+fn queues_behind_queries_in_flight_when_over_the_limit() {
+    static USAGE: AtomicU64 = AtomicU64::new(0);
+    USAGE.store(50 * MIB, Ordering::Relaxed);
+    let budget = MemoryBudget::with_probe(Some(100 * MIB), || Some(USAGE.load(Ordering::Relaxed)));
+    let index = index();
+
+    let running = budget.admit(&index, Duration::ZERO).unwrap();
+    USAGE.store(200 * MIB, Ordering::Relaxed);
+    let err = budget
+        .admit(&index, Duration::from_millis(20))
+        .err()
+        .unwrap();
+    assert!(err.contains("busy"), "{err}");
+
+    // Once the running query finishes and frees its buffers, the next runs.
+    std::thread::scope(|s| {
+        let waiter = s.spawn(|| budget.admit(&index, Duration::from_secs(5)).is_ok());
+        std::thread::sleep(Duration::from_millis(20));
+        USAGE.store(80 * MIB, Ordering::Relaxed);
+        drop(running);
+        assert!(waiter.join().unwrap());
+    });
+}
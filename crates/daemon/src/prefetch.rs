@@ -0,0 +1,235 @@
+//! Speculative queries while the user is typing.
+//!
+//! A client that pauses between keystrokes sends a
+//! [`Prefetch`](blaze_protocol::DaemonRequest::Prefetch) with what has been
+//! typed so far. The daemon runs that query, and the longer queries from the
+//! history that start with it, on a background thread and keeps the
+//! responses in its [`QueryCache`](crate::cache::QueryCache), so the next
+//! keystroke is answered without running anything.
+//!
+//! The queries the daemon answered since it started are completed from
+//! too, unless the history is hashed or redacted (`BLAZE_HISTORY_ANONYMIZE`):
+//! then the daemon keeps no query text either, and only the typed query is
+//! prefetched. Completions rank by how often the whole history ran them,
+//! which counts hashed entries as well.
+//!
+//! Only the newest prefetch matters: a later prefetch or any real query
+//! cancels the one in progress. A query already running is finished, so
+//! cancellation takes effect between queries.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use blaze_protocol::QueryRequest;
+use blaze_runtime::history::{HistoryPrivacy, HistoryStore};
+use log::{debug, warn};
+
+use crate::{
+    cache::CacheKey,
+    federation::SystemIndex,
+    query::{ResponseCaps, execute_query},
+    state::{DaemonState, IndexSnapshot},
+};
+
+/// How many recent history entries, and queries answered since the daemon
+/// started, completions are taken from.
+const HISTORY_WINDOW: usize = 500;
+
+pub struct Prefetcher {
+    /// Most queries one prefetch runs, counting the typed one.
+    width: usize,
+    /// Bumped by every prefetch and cancellation; a prefetch stops once it
+    /// no longer matches the ticket it started with.
+    epoch: AtomicU64,
+    /// Queries answered for clients since the daemon started, newest last.
+    answered: Mutex<VecDeque<String>>,
+    /// Query text is only kept in memory when the history keeps it too.
+    privacy: HistoryPrivacy,
+}
+
+impl Prefetcher {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            epoch: AtomicU64::new(0),
+            answered: Mutex::new(VecDeque::new()),
+            privacy: HistoryPrivacy::from_env(),
+        }
+    }
+
+    /// Set the history privacy mode instead of reading it from the environment.
+    #[cfg(test)]
+    pub fn with_privacy(mut self, privacy: HistoryPrivacy) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Keep `query`, answered for a client, to complete later prefetches.
+    /// Nothing is kept unless the history stores plain query text.
+    pub fn remember(&self, query: &str) {
+        if self.width == 0 || self.privacy != HistoryPrivacy::Plain {
+            return;
+        }
+        let mut answered = self.answered.lock().unwrap();
+        if answered.len() == HISTORY_WINDOW {
+            answered.pop_front();
+        }
+        answered.push_back(query.to_string());
+    }
+
+    /// Queries answered since the daemon started, newest first.
+    fn answered(&self) -> Vec<String> {
+        let answered = self.answered.lock().unwrap();
+        answered.iter().rev().cloned().collect()
+    }
+
+    /// Stop the prefetch in progress, if any, before its next query.
+    pub fn cancel(&self) {
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn start(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn is_current(&self, ticket: u64) -> bool {
+        self.epoch.load(Ordering::Relaxed) == ticket
+    }
+}
+
+/// Start prefetching for `req` in the background, returning how many
+/// queries will run. Does nothing while the index is still loading or when
+/// the cache is off.
+pub fn spawn(state: &Arc<DaemonState>, req: QueryRequest) -> usize {
+    let prefetcher = &state.prefetcher;
+    if prefetcher.width == 0 || !state.cache.enabled() {
+        return 0;
+    }
+    let Ok(snapshot) = state.wait_for_snapshot(Duration::ZERO) else {
+        return 0;
+    };
+
+    let ticket = prefetcher.start();
+    let history = HistoryStore::new();
+    let mut recent = prefetcher.answered();
+    let mut frequencies = HashMap::new();
+    if let Some(history) = &history {
+        recent.extend(recent_queries(history));
+        frequencies = history.query_frequencies();
+    }
+    let frequency = |query: &str| {
+        (history.as_ref())
+            .and_then(|history| history.hash_query(query))
+            .and_then(|hash| frequencies.get(&hash).copied())
+            .unwrap_or(0)
+    };
+    let queries = candidates(&req.query, &recent, prefetcher.width, frequency);
+    let count = queries.len();
+
+    let state = Arc::clone(state);
+    let spawned = thread::Builder::new()
+        .name("blaze-prefetch".into())
+        .spawn(move || run(&state, &snapshot, ticket, &req, queries));
+    if let Err(e) = spawned {
+        warn!("Failed to start prefetch thread: {e}");
+        return 0;
+    }
+    count
+}
+
+fn run(
+    state: &DaemonState,
+    snapshot: &IndexSnapshot,
+    ticket: u64,
+    req: &QueryRequest,
+    queries: Vec<String>,
+) {
+    let system = state.system.as_ref().and_then(SystemIndex::current);
+    for query in queries {
+        if !state.prefetcher.is_current(ticket) {
+            debug!("Prefetch for `{}` cancelled", req.query);
+            return;
+        }
+        let req = QueryRequest {
+            query,
+            ..req.clone()
+        };
+        let key = CacheKey::new(
+            snapshot.generation,
+            system.as_ref().map(|s| s.generation),
+            &req,
+        );
+        if state.cache.contains(&key) {
+            continue;
+        }
+        // Speculation never waits for memory or takes it from real queries.
+        let Ok(_permit) = state.memory.admit(&snapshot.index, Duration::ZERO) else {
+            return;
+        };
+        match execute_query(
+            snapshot,
+            system.as_deref(),
+            &req,
+            ResponseCaps::new(&state.config),
+            false,
+            None,
+        ) {
+            Ok((response, _)) => state.cache.insert(key, response),
+            Err(e) => debug!("Prefetching `{}` failed: {e:#}", req.query),
+        }
+    }
+}
+
+/// The queries to prefetch for `typed`: itself, then up to `width - 1`
+/// longer queries from `recent` (newest first) that start with it. The
+/// completions `frequency` says ran most often come first, then the ones
+/// most frequent in `recent`, and the newest among equals.
+pub fn candidates(
+    typed: &str,
+    recent: &[String],
+    width: usize,
+    frequency: impl Fn(&str) -> usize,
+) -> Vec<String> {
+    if width == 0 || typed.trim().is_empty() {
+        return Vec::new();
+    }
+
+    // Count each completion, remembering where it was first (newest) seen.
+    let mut seen: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (age, query) in recent.iter().enumerate() {
+        if query.len() > typed.len() && query.starts_with(typed) {
+            seen.entry(query.as_str()).or_insert((0, age)).0 += 1;
+        }
+    }
+    let mut completions: Vec<(&str, (usize, usize))> = seen.into_iter().collect();
+    completions.sort_by_cached_key(|&(query, (count, age))| {
+        (Reverse(frequency(query)), Reverse(count), age)
+    });
+
+    std::iter::once(typed.to_string())
+        .chain(completions.into_iter().map(|(q, _)| q.to_string()))
+        .take(width)
+        .collect()
+}
+
+/// Query strings kept in the history, newest first.
+fn recent_queries(history: &HistoryStore) -> Vec<String> {
+    history
+        .recent_queries(HISTORY_WINDOW)
+        .into_iter()
+        .map(|q| q.raw_query)
+        .filter(|q| !q.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "prefetch_tests.rs"]
+mod tests;
@@ -0,0 +1,65 @@
+use super::*;
+
+fn recent(queries: &[&str]) -> Vec<String> {
+    queries.iter().map(|q| q.to_string()).collect()
+}
+
+#[test]
+fn completes_from_history_by_frequency_then_recency() {
+    let recent = recent(&[
+        "main.rs",
+        "mai",
+        "main.go",
+        "main.rs",
+        "makefile",
+        "maintainers",
+    ]);
+
+    assert_eq!(
+        candidates("mai", &recent, 4, |_| 0),
+        ["mai", "main.rs", "main.go", "maintainers"]
+    );
+    assert_eq!(candidates("mai", &recent, 2, |_| 0), ["mai", "main.rs"]);
+}
+
+#[test]
+fn nothing_to_prefetch_for_an_empty_buffer() {
+    assert!(candidates("  ", &recent(&["main.rs"]), 4, |_| 0).is_empty());
+    assert!(candidates("mai", &recent(&["main.rs"]), 0, |_| 0).is_empty());
+    assert_eq!(candidates("xyz", &recent(&["main.rs"]), 4, |_| 0), ["xyz"]);
+}
+
+#[test]
+fn completions_the_whole_history_ran_most_come_first() {
+    let recent = recent(&["main.rs", "main.rs", "main.go", "makefile"]);
+    // `main.go` is mostly in the history as hashes, outside the window.
+    let frequency = |query: &str| if query == "main.go" { 9 } else { 0 };
+
+    assert_eq!(
+        candidates("ma", &recent, 4, frequency),
+        ["ma", "main.go", "main.rs", "makefile"]
+    );
+}
+
+#[test]
+fn answered_queries_are_remembered_newest_first() {
+    let prefetcher = Prefetcher::new(4).with_privacy(HistoryPrivacy::Plain);
+    for query in ["main.rs", "main.go"] {
+        prefetcher.remember(query);
+    }
+    assert_eq!(prefetcher.answered(), ["main.go", "main.rs"]);
+
+    // Nothing is kept while prefetching is off.
+    let off = Prefetcher::new(0);
+    off.remember("main.rs");
+    assert!(off.answered().is_empty());
+}
+
+#[test]
+fn private_histories_keep_no_answered_queries() {
+    for privacy in [HistoryPrivacy::Hash, HistoryPrivacy::Redact] {
+        let prefetcher = Prefetcher::new(4).with_privacy(privacy);
+        prefetcher.remember("main.rs");
+        assert!(prefetcher.answered().is_empty());
+    }
+}
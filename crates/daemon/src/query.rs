@@ -1,27 +1,249 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
-use blaze_engine::{Index, PipelineMetrics, to_query_metrics};
-use blaze_protocol::{QueryHit, QueryRequest, QueryResponse};
+use blaze_engine::{EngineQueryHit, PipelineMetrics, QueryScope, to_query_metrics};
+use blaze_protocol::{QueryHit, QueryRequest, QueryResponse, Truncation};
+use blaze_runtime::history::{HistoryStore, QueryEvent, QueryOrigin};
+use log::info;
+
+use crate::{
+    cache::CacheKey,
+    config::DaemonConfig,
+    federation::{SystemIndex, SystemSnapshot},
+    session::Matches,
+    state::{DaemonState, IndexSnapshot},
+};
+
+/// Bytes a hit adds to a response besides its path, roughly.
+const HIT_OVERHEAD_BYTES: u64 = 16;
+
+/// Most a single query response may carry.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseCaps {
+    /// Hits; 0 for no cap.
+    pub max_hits: usize,
+    /// Bytes of hits, counted as their paths plus [`HIT_OVERHEAD_BYTES`];
+    /// 0 for no cap.
+    pub max_bytes: u64,
+}
 
-pub fn execute_query(index: &Index, req: &QueryRequest) -> Result<QueryResponse> {
-    let limit = req.limit.unwrap_or(20) as usize;
-    let result = index.run_query(&req.query, limit);
+impl ResponseCaps {
+    pub fn new(config: &DaemonConfig) -> Self {
+        Self {
+            max_hits: config.max_hits,
+            max_bytes: config.max_response_bytes,
+        }
+    }
+}
+
+/// Run a query against a single index snapshot, merging in the matches the
+/// user may see from the `system` index if one is loaded.
+///
+/// The whole query, including path reconstruction, uses `snapshot`, so the
+/// response never mixes results from two index generations.
+///
+/// Speculative runs pass `record_history: false` so they stay out of the
+/// user's history.
+///
+/// With `within`, only those earlier matches are searched, as for a
+/// refined session query. Every match is returned alongside the response.
+///
+/// The hits returned start at `req.offset` and stop at `req.limit` or at
+/// the first of `caps` reached, whichever comes first.
+pub fn execute_query(
+    snapshot: &IndexSnapshot,
+    system: Option<&SystemSnapshot>,
+    req: &QueryRequest,
+    caps: ResponseCaps,
+    record_history: bool,
+    within: Option<Matches>,
+) -> Result<(QueryResponse, Matches)> {
+    let refined = within.is_some();
+    let (within, system_within) = match within {
+        Some(m) => (Some(m.user), m.system),
+        None => (None, None),
+    };
+    let scope = QueryScope {
+        within,
+        under: req.under.clone(),
+        skip_history: !record_history,
+        ..QueryScope::default()
+    };
+    // Rank no further than the last hit this response can hold.
+    let page = match (req.limit, caps.max_hits) {
+        (limit, 0) => limit,
+        (Some(limit), max) => Some(limit.min(max)),
+        (None, max) => Some(max),
+    };
+    let limit = page.map(|n| n.saturating_add(req.offset));
+    let result = snapshot
+        .index
+        .run_query_scoped(&req.query, limit, QueryOrigin::Daemon, scope);
+    if let Some(profile) = &result.profile {
+        info!("query `{}`\n{profile}", req.query);
+    }
+
+    let mut hits = result.hits;
+    let mut total = result.total;
+    let mut matches = Matches {
+        user: result.matched,
+        system: None,
+    };
+    if let Some(system) = system {
+        let scope = QueryScope {
+            within: system_within,
+            under: req.under.clone(),
+            access: Some(system.access.clone()),
+            skip_history: true,
+        };
+        let extra = system
+            .index
+            .run_query_scoped(&req.query, limit, QueryOrigin::Daemon, scope);
+        let duplicates = merge_hits(&mut hits, extra.hits, limit);
+        total = total + extra.total - duplicates;
+        matches.system = Some(extra.matched);
+    }
 
-    let hits: Vec<QueryHit> = result
-        .hits
+    let mut hits: Vec<QueryHit> = hits
         .into_iter()
+        .skip(req.offset)
         .map(|h| QueryHit {
             rank: h.rank as u32,
+            score: h.score,
             path: h.path,
+            kind: h.kind,
         })
         .collect();
+    let truncated = cap_hits(&mut hits, req.limit, caps);
+    let end = req.offset + hits.len();
+    let next_offset = (end < total).then_some(end);
 
     let metrics = result
         .metrics
         .map(|m: PipelineMetrics| to_query_metrics(&m));
 
-    Ok(QueryResponse {
+    let response = QueryResponse {
+        returned: hits.len() as u32,
         hits,
-        total: result.total as u32,
+        total: total as u32,
         metrics,
-    })
+        generation: snapshot.generation,
+        index_created_secs: snapshot.index.created_secs(),
+        root: snapshot.index.root_path().map(str::to_owned),
+        cached: false,
+        refined,
+        truncated: truncated.filter(|_| next_offset.is_some()),
+        next_offset,
+    };
+    Ok((response, matches))
+}
+
+/// Answer a client's query from the cache if it holds the response,
+/// running it otherwise, over the matches of the session's previous query
+/// when it refines that. Cancels any prefetch in progress, and keeps the
+/// query to complete later prefetches from.
+pub fn answer_query(
+    state: &DaemonState,
+    snapshot: &IndexSnapshot,
+    req: &QueryRequest,
+) -> Result<QueryResponse> {
+    state.prefetcher.cancel();
+    state.prefetcher.remember(&req.query);
+
+    let system = state.system.as_ref().and_then(SystemIndex::current);
+    let key = CacheKey::new(
+        snapshot.generation,
+        system.as_ref().map(|s| s.generation),
+        req,
+    );
+    if let Some(mut response) = state.cache.get(&key) {
+        response.cached = true;
+        log_cached(req, &response);
+        return Ok(response);
+    }
+
+    let generations = (snapshot.generation, system.as_ref().map(|s| s.generation));
+    let within = state.sessions.take(generations.0, generations.1, req);
+    let caps = ResponseCaps::new(&state.config);
+    let (response, matches) = execute_query(snapshot, system.as_deref(), req, caps, true, within)?;
+    state
+        .sessions
+        .record(generations.0, generations.1, req, matches);
+    if state.cache.enabled() {
+        state.cache.insert(key, response.clone());
+    }
+    Ok(response)
+}
+
+/// Record a query answered from the cache in the history, as running it
+/// would have.
+fn log_cached(req: &QueryRequest, response: &QueryResponse) {
+    if let Some(history) = HistoryStore::new() {
+        let event = QueryEvent::new(req.query.clone(), response.total as usize, 0)
+            .with_origin(QueryOrigin::Daemon);
+        history.log_query(event);
+    }
+}
+
+/// Cut `hits`, asked for up to `limit`, down to what one response may carry.
+/// Returns the cap that cut them, if one did.
+///
+/// At least one hit is kept, so paging through a response always advances.
+fn cap_hits(
+    hits: &mut Vec<QueryHit>,
+    limit: Option<usize>,
+    caps: ResponseCaps,
+) -> Option<Truncation> {
+    let mut truncated = None;
+    if caps.max_hits > 0
+        && hits.len() >= caps.max_hits
+        && limit.is_none_or(|limit| limit > caps.max_hits)
+    {
+        hits.truncate(caps.max_hits);
+        truncated = Some(Truncation::MaxHits(caps.max_hits as u32));
+    }
+    if caps.max_bytes > 0 {
+        let mut bytes = 0;
+        let over = hits.iter().position(|hit| {
+            bytes += hit.path.len() as u64 + HIT_OVERHEAD_BYTES;
+            bytes > caps.max_bytes
+        });
+        if let Some(over) = over {
+            hits.truncate(over.max(1));
+            truncated = Some(Truncation::MaxBytes(caps.max_bytes));
+        }
+    }
+    truncated
 }
+
+/// Merge `extra` into `hits` by score, keeping at most `limit`, and rank
+/// the result from 1. A path in both lists is kept once, from `hits`, and
+/// the number of such duplicates is returned.
+///
+/// Only the collected hits are compared, so a file both indexes match
+/// past the limit is not noticed.
+fn merge_hits(
+    hits: &mut Vec<EngineQueryHit>,
+    extra: Vec<EngineQueryHit>,
+    limit: Option<usize>,
+) -> usize {
+    let seen: HashSet<String> = hits.iter().map(|h| h.path.clone()).collect();
+    let before = extra.len();
+    hits.extend(extra.into_iter().filter(|h| !seen.contains(&h.path)));
+    let duplicates = before - (hits.len() - seen.len());
+
+    // Stable, so ties keep the user's own hits first and each index's own
+    // rank order, which is total, after that.
+    hits.sort_by_key(|h| std::cmp::Reverse(h.score));
+    if let Some(limit) = limit {
+        hits.truncate(limit);
+    }
+    for (i, hit) in hits.iter_mut().enumerate() {
+        hit.rank = i + 1;
+    }
+    duplicates
+}
+
+#[cfg(test)]
+#[path = "query_tests.rs"]
+mod tests;
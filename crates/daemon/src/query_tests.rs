@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use blaze_engine::{Index, IndexBuilder};
+use blaze_fs::FileRecord;
+use blaze_protocol::HitKind;
+use clap::Parser;
+
+use super::*;
+use crate::config::Cli;
+
+fn hit(path: &str, score: i32) -> EngineQueryHit {
+    EngineQueryHit {
+        rank: 0,
+        file_id: 0,
+        score,
+        path: path.to_string(),
+        kind: HitKind::default(),
+    }
+}
+
+fn ranked(hits: &[EngineQueryHit]) -> Vec<(usize, &str)> {
+    hits.iter().map(|h| (h.rank, h.path.as_str())).collect()
+}
+
+#[test]
+fn merges_system_hits_by_score() {
+    let mut hits = vec![hit("/home/a/notes.txt", 90), hit("/home/a/todo.txt", 40)];
+    let extra = vec![hit("/etc/notes.conf", 60), hit("/usr/share/notes", 10)];
+
+    assert_eq!(merge_hits(&mut hits, extra, Some(3)), 0);
+    assert_eq!(
+        ranked(&hits),
+        [
+            (1, "/home/a/notes.txt"),
+            (2, "/etc/notes.conf"),
+            (3, "/home/a/todo.txt"),
+        ]
+    );
+}
+
+#[test]
+fn keeps_the_users_copy_of_a_shared_path() {
+    let mut hits = vec![hit("/home/a/x", 50), hit("/home/a/y", 20)];
+    let extra = vec![hit("/home/a/y", 70), hit("/srv/z", 50)];
+
+    assert_eq!(merge_hits(&mut hits, extra, None), 1);
+    // Ties keep the user's hit first; the duplicate keeps the user's score.
+    assert_eq!(
+        ranked(&hits),
+        [(1, "/home/a/x"), (2, "/srv/z"), (3, "/home/a/y")]
+    );
+}
+
+fn query_hit(path: &str) -> QueryHit {
+    QueryHit {
+        rank: 0,
+        score: 0,
+        path: path.to_string(),
+        kind: HitKind::default(),
+    }
+}
+
+#[test]
+fn caps_cut_hits_by_count_then_bytes() {
+    let caps = |max_hits, max_bytes| ResponseCaps {
+        max_hits,
+        max_bytes,
+    };
+    let hits = || {
+        (0..10)
+            .map(|i| query_hit(&format!("/p/{i:04}")))
+            .collect::<Vec<_>>()
+    };
+
+    let mut capped = hits();
+    assert_eq!(
+        cap_hits(&mut capped, None, caps(4, 0)),
+        Some(Truncation::MaxHits(4))
+    );
+    assert_eq!(capped.len(), 4);
+
+    // A limit within the cap is not cut by it.
+    let mut limited = hits();
+    assert_eq!(cap_hits(&mut limited, Some(4), caps(4, 0)), None);
+
+    // Each hit counts its 7-byte path plus the overhead.
+    let per_hit = 7 + HIT_OVERHEAD_BYTES;
+    let mut sized = hits();
+    assert_eq!(
+        cap_hits(&mut sized, None, caps(0, 3 * per_hit + 1)),
+        Some(Truncation::MaxBytes(3 * per_hit + 1))
+    );
+    assert_eq!(sized.len(), 3);
+
+    // One hit is kept even if it alone is over the cap.
+    let mut tiny = hits();
+    cap_hits(&mut tiny, None, caps(0, 1));
+    assert_eq!(tiny.len(), 1);
+}
+
+fn snapshot(files: usize) -> IndexSnapshot {
+    let root = PathBuf::from("/proj");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder
+        .add_batch((0..files).map(|i| FileRecord::test_file(root.join(format!("note{i:03}.txt")))));
+    let index = Index::from_staged(&builder.finish()).unwrap();
+    let config = DaemonConfig::from_args(&Cli::parse_from(["blaze-daemon"])).unwrap();
+    DaemonState::with_index(config, index).snapshot().unwrap()
+}
+
+#[test]
+fn capped_responses_page_through_every_match() {
+    let snapshot = snapshot(25);
+    let caps = ResponseCaps {
+        max_hits: 10,
+        max_bytes: 0,
+    };
+    let mut req = QueryRequest {
+        query: "note".into(),
+        limit: None,
+        under: None,
+        session: None,
+        offset: 0,
+    };
+
+    let mut pages = Vec::new();
+    let mut ranks = Vec::new();
+    loop {
+        let (response, _) = execute_query(&snapshot, None, &req, caps, false, None).unwrap();
+        assert_eq!(response.total, 25);
+        pages.push((response.returned, response.truncated));
+        ranks.extend(response.hits.iter().map(|h| h.rank));
+        match response.next_offset {
+            Some(next) => req.offset = next,
+            None => break,
+        }
+    }
+
+    assert_eq!(
+        pages,
+        [
+            (10, Some(Truncation::MaxHits(10))),
+            (10, Some(Truncation::MaxHits(10))),
+            (5, None),
+        ]
+    );
+    assert_eq!(ranks, (1..=25).collect::<Vec<u32>>());
+}
+
+#[test]
+fn limited_responses_point_past_their_last_hit() {
+    let snapshot = snapshot(5);
+    let req = QueryRequest {
+        query: "note".into(),
+        limit: Some(3),
+        under: None,
+        session: None,
+        offset: 1,
+    };
+    let caps = ResponseCaps {
+        max_hits: 100,
+        max_bytes: 0,
+    };
+
+    let (response, _) = execute_query(&snapshot, None, &req, caps, false, None).unwrap();
+    assert_eq!(response.returned, 3);
+    assert_eq!(response.truncated, None);
+    assert_eq!(response.next_offset, Some(4));
+    assert_eq!(response.hits[0].rank, 2);
+}
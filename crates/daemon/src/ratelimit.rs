@@ -0,0 +1,149 @@
+//! Per-user query rate limiting.
+//!
+//! Clients open a connection per request, so limits are kept per peer user
+//! id rather than per connection: every process a user runs, such as an
+//! editor plugin querying on each keystroke, draws from one token bucket.
+
+use std::{
+    collections::HashMap,
+    os::unix::net::UnixStream,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+/// Least time between warnings about one user being rate limited.
+const WARN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A bucket of `burst` tokens refilled at `rate` tokens per second; each
+/// query takes one.
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+    /// When refusals from this user were last logged.
+    warned: Option<Instant>,
+}
+
+pub struct RateLimiter {
+    /// Queries per second each user may sustain; `0` disables limiting.
+    rate: f64,
+    /// Queries a user may send at once after being idle.
+    burst: f64,
+    /// Buckets by peer uid; `None` for peers whose uid is unknown.
+    buckets: Mutex<HashMap<Option<u32>, TokenBucket>>,
+    allowed: AtomicU64,
+    limited: AtomicU64,
+}
+
+/// Counters since the daemon started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateStats {
+    pub allowed: u64,
+    pub limited: u64,
+}
+
+impl RateLimiter {
+    pub fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+            allowed: AtomicU64::new(0),
+            limited: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a token for one query from `uid`, if it has one left.
+    pub fn check(&self, uid: Option<u32>) -> bool {
+        self.check_at(uid, Instant::now())
+    }
+
+    pub(crate) fn check_at(&self, uid: Option<u32>, now: Instant) -> bool {
+        if self.rate <= 0.0 {
+            self.allowed.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(uid).or_insert(TokenBucket {
+            tokens: self.burst,
+            refilled: now,
+            warned: None,
+        });
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            self.allowed.fetch_add(1, Ordering::Relaxed);
+            true
+        } else {
+            if bucket
+                .warned
+                .is_none_or(|at| now.saturating_duration_since(at) >= WARN_INTERVAL)
+            {
+                warn!(
+                    "Rate limiting queries from uid {}",
+                    uid.map_or("unknown".to_string(), |u| u.to_string())
+                );
+                bucket.warned = Some(now);
+            }
+            self.limited.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    pub fn stats(&self) -> RateStats {
+        RateStats {
+            allowed: self.allowed.load(Ordering::Relaxed),
+            limited: self.limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// User id of the process on the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    use std::os::fd::AsRawFd;
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` and `len` describe a valid `ucred` buffer for the
+    // duration of the call.
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    (rc == 0).then_some(cred.uid)
+}
+
+/// User id of the process on the other end of `stream`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    use std::os::fd::AsRawFd;
+
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: `uid` and `gid` are valid for writes for the call.
+    let rc = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    (rc == 0).then_some(uid)
+}
+
+#[cfg(test)]
+#[path = "ratelimit_tests.rs"]
+mod tests;
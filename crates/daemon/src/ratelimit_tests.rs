@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use super::*;
+
+#[test]
+fn burst_then_refill_at_rate() {
+    let limiter = RateLimiter::new(10, 3);
+    let t0 = Instant::now();
+
+    assert!((0..3).all(|_| limiter.check_at(Some(1000), t0)));
+    assert!(!limiter.check_at(Some(1000), t0));
+
+    // One token every 100ms.
+    assert!(!limiter.check_at(Some(1000), t0 + Duration::from_millis(50)));
+    assert!(limiter.check_at(Some(1000), t0 + Duration::from_millis(150)));
+    assert!(!limiter.check_at(Some(1000), t0 + Duration::from_millis(160)));
+
+    // Idle time refills up to the burst, no further.
+    let later = t0 + Duration::from_secs(60);
+    assert!((0..3).all(|_| limiter.check_at(Some(1000), later)));
+    assert!(!limiter.check_at(Some(1000), later));
+
+    assert_eq!(
+        limiter.stats(),
+        RateStats {
+            allowed: 7,
+            limited: 4
+        }
+    );
+}
+
+#[test]
+fn users_have_separate_buckets() {
+    let limiter = RateLimiter::new(1, 1);
+    let t0 = Instant::now();
+
+    assert!(limiter.check_at(Some(1000), t0));
+    assert!(!limiter.check_at(Some(1000), t0));
+    assert!(limiter.check_at(Some(1001), t0));
+    assert!(limiter.check_at(None, t0));
+}
+
+#[test]
+fn zero_rate_never_limits() {
+    let limiter = RateLimiter::new(0, 1);
+    let t0 = Instant::now();
+    assert!((0..1000).all(|_| limiter.check_at(Some(1000), t0)));
+    assert_eq!(limiter.stats().limited, 0);
+}
+
+#[test]
+fn peer_uid_is_our_own_uid() {
+    let (a, _b) = UnixStream::pair().unwrap();
+    // SAFETY: getuid has no preconditions.
+    let uid = unsafe { libc::getuid() };
+    assert_eq!(peer_uid(&a), Some(uid));
+}
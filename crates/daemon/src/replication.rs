@@ -0,0 +1,190 @@
+//! Following another daemon's index.
+//!
+//! A daemon started with `--follow SOCKET` builds no index of its own: it
+//! copies the index of the daemon listening on SOCKET. The index travels
+//! as an uncompressed index bundle, the format of `blaze index export`, one
+//! [`FetchIndex`](DaemonRequest::FetchIndex) request per piece. Each piece
+//! carries a CRC32, and the finished bundle is imported only if every entry
+//! matches its BLAKE3 hash. The import replaces the local index file
+//! atomically and the new index is swapped in, so queries in flight finish
+//! on the old one.
+//!
+//! Every `--follow-interval` the follower asks again, naming the bundle it
+//! has, and the leader answers [`DaemonResponse::IndexUnchanged`] until its
+//! index changes. A follower fetches the whole index once after starting,
+//! meanwhile serving the copy it kept from its last run.
+//!
+//! To follow a daemon on another machine, forward its socket, e.g. with
+//! `ssh -L`.
+
+use std::{
+    fs,
+    io::Write,
+    os::unix::net::UnixStream,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use blaze_engine::{Index, IndexBundle, IndexReader, import_bundle};
+use blaze_protocol::{
+    DaemonRequest, DaemonResponse, FetchIndexRequest, IndexChunk,
+    codec::{read_message, write_message},
+};
+use log::{debug, info, warn};
+use tempfile::NamedTempFile;
+
+use crate::state::{DaemonState, IndexSnapshot};
+
+/// Bytes a follower asks for at a time.
+const CHUNK_LEN: u32 = 4 << 20;
+
+/// Most bytes the leader sends in one piece, whatever it is asked for.
+const MAX_CHUNK_LEN: usize = 16 << 20;
+
+/// How long a follower waits for one piece.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Times one fetch starts over because the leader's index changed before
+/// it gives up until the next check.
+const MAX_RESTARTS: usize = 3;
+
+/// The bundle served to followers, laid out once per index generation.
+#[derive(Default)]
+pub struct BundleServer {
+    current: Mutex<Option<(u64, Arc<IndexBundle>)>>,
+}
+
+impl BundleServer {
+    fn bundle(&self, snapshot: &IndexSnapshot) -> Arc<IndexBundle> {
+        let mut current = self.current.lock().unwrap();
+        if let Some((generation, bundle)) = current.as_ref()
+            && *generation == snapshot.generation
+        {
+            return Arc::clone(bundle);
+        }
+        let bundle = Arc::new(IndexBundle::new(Arc::clone(&snapshot.index)));
+        *current = Some((snapshot.generation, Arc::clone(&bundle)));
+        bundle
+    }
+}
+
+/// Answer a follower's request for a piece of the index.
+pub fn serve(state: &DaemonState, req: &FetchIndexRequest) -> DaemonResponse {
+    let snapshot = match state.wait_for_snapshot(state.config.startup_wait) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return DaemonResponse::Error(e),
+    };
+    let bundle = state.bundles.bundle(&snapshot);
+    if req.have == Some(bundle.id()) {
+        return DaemonResponse::IndexUnchanged;
+    }
+
+    let max_len = (req.max_len as usize).min(MAX_CHUNK_LEN);
+    let data = bundle.read_at(req.offset, max_len).to_vec();
+    DaemonResponse::IndexChunk(IndexChunk {
+        bundle: bundle.id(),
+        total_len: bundle.len(),
+        offset: req.offset,
+        crc32: crc32fast::hash(&data),
+        data,
+    })
+}
+
+/// Keep the index a copy of the one served at `leader`. Runs for the life
+/// of the daemon.
+pub fn follow(state: &DaemonState, leader: &Path) {
+    match Index::open(&state.config.index_path) {
+        Ok(index) => {
+            info!("Serving the copy kept from the last run while fetching a new one");
+            state.publish(index);
+        }
+        Err(e) => debug!("No usable copy of the followed index: {e}"),
+    }
+
+    let mut have = None;
+    loop {
+        match sync(state, leader, have) {
+            Ok(Some(bundle)) => have = Some(bundle),
+            Ok(None) => debug!("Followed index unchanged"),
+            Err(e) => warn!("Failed to fetch the index from {}: {e:#}", leader.display()),
+        }
+        thread::sleep(state.config.follow_interval);
+    }
+}
+
+/// Fetch the index served at `leader` unless it is still the bundle
+/// `have`, and swap it in. Returns the id of the bundle fetched.
+fn sync(state: &DaemonState, leader: &Path, have: Option<u64>) -> Result<Option<u64>> {
+    let index_path = &state.config.index_path;
+    let dir = index_path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut restarts = 0;
+    let (bundle, tmp) = 'fetch: loop {
+        let mut tmp = NamedTempFile::new_in(dir)?;
+        let mut bundle = None;
+        let mut offset = 0;
+        loop {
+            let req = FetchIndexRequest {
+                have,
+                offset,
+                max_len: CHUNK_LEN,
+            };
+            let chunk = match fetch(leader, req)? {
+                DaemonResponse::IndexChunk(chunk) => chunk,
+                DaemonResponse::IndexUnchanged => return Ok(None),
+                DaemonResponse::Error(e) => bail!("{e}"),
+                other => bail!("unexpected response: {other:?}"),
+            };
+            if crc32fast::hash(&chunk.data) != chunk.crc32 {
+                bail!("piece at offset {} failed its CRC check", chunk.offset);
+            }
+            if bundle.is_some_and(|b| b != chunk.bundle) {
+                restarts += 1;
+                if restarts > MAX_RESTARTS {
+                    bail!("the index kept changing while it was fetched");
+                }
+                debug!("Followed index changed midway; starting over");
+                continue 'fetch;
+            }
+            if chunk.offset != offset || (chunk.data.is_empty() && offset < chunk.total_len) {
+                bail!("piece at offset {} is out of order", chunk.offset);
+            }
+
+            bundle = Some(chunk.bundle);
+            tmp.write_all(&chunk.data)?;
+            offset += chunk.data.len() as u64;
+            if offset >= chunk.total_len {
+                break 'fetch (chunk.bundle, tmp);
+            }
+        }
+    };
+
+    let config = import_bundle(tmp.path(), index_path, state.config.follow_root.as_deref())
+        .context("failed to install the fetched index")?;
+    let index = Index::open(index_path)
+        .with_context(|| format!("fetched index {} does not open", index_path.display()))?;
+    info!(
+        "Fetched the index of {} ({} files) from {}",
+        config.root,
+        index.live_file_count(),
+        leader.display()
+    );
+    state.publish(index);
+    Ok(Some(bundle))
+}
+
+fn fetch(leader: &Path, req: FetchIndexRequest) -> Result<DaemonResponse> {
+    let mut stream = UnixStream::connect(leader)
+        .with_context(|| format!("failed to connect to {}", leader.display()))?;
+    stream.set_read_timeout(Some(FETCH_TIMEOUT))?;
+    write_message(&mut stream, &DaemonRequest::FetchIndex(req))?;
+    read_message(&mut stream)
+}
+
+#[cfg(test)]
+#[path = "replication_tests.rs"]
+mod tests;
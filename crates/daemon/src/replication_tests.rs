@@ -0,0 +1,83 @@
+use std::{
+    os::unix::net::UnixListener,
+    path::{Path, PathBuf},
+};
+
+use blaze_engine::{IndexBuilder, write_index_atomic};
+use blaze_fs::FileRecord;
+use clap::Parser;
+use tempfile::tempdir;
+
+use super::*;
+use crate::config::{Cli, DaemonConfig};
+
+/// Write an index of `rels` under `/srv/artifacts` to `path` and open it.
+fn write_index(path: &Path, rels: &[&str]) -> Index {
+    let root = PathBuf::from("/srv/artifacts");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(rels.iter().map(|rel| FileRecord::test_file(root.join(rel))));
+    write_index_atomic(path, &builder.finish(), 0).unwrap();
+    Index::open(path).unwrap()
+}
+
+fn config(index_path: &Path, follow_root: Option<&str>) -> DaemonConfig {
+    let mut args = vec!["blaze-daemon", "--index-path", index_path.to_str().unwrap()];
+    if let Some(root) = follow_root {
+        args.extend(["--follow", "/unused.sock", "--follow-root", root]);
+    }
+    DaemonConfig::from_args(&Cli::parse_from(args)).unwrap()
+}
+
+/// Answer fetches on `socket` from `leader`, in the background.
+fn serve_on(socket: &Path, leader: Arc<DaemonState>) {
+    let listener = UnixListener::bind(socket).unwrap();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            if let Ok(DaemonRequest::FetchIndex(req)) = read_message(&mut stream) {
+                write_message(&mut stream, &serve(&leader, &req)).unwrap();
+            }
+        }
+    });
+}
+
+fn paths(state: &DaemonState) -> Vec<String> {
+    let snapshot = state.snapshot().unwrap();
+    let mut paths: Vec<String> = (0..snapshot.index.get_file_count() as u32)
+        .filter(|&f| !snapshot.index.is_file_removed(f))
+        .map(|f| snapshot.index.reconstruct_full_path(f))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn follower_copies_the_leaders_index() {
+    let dir = tempdir().unwrap();
+    let leader_path = dir.path().join("leader.bin");
+    let leader = Arc::new(DaemonState::with_index(
+        config(&leader_path, None),
+        write_index(&leader_path, &["lib/a.jar", "lib/b.jar"]),
+    ));
+    let socket = dir.path().join("leader.sock");
+    serve_on(&socket, Arc::clone(&leader));
+
+    let follower = DaemonState::starting(config(
+        &dir.path().join("follower").join("index.bin"),
+        Some("/mnt/artifacts"),
+    ));
+    let first = sync(&follower, &socket, None).unwrap().unwrap();
+    assert_eq!(
+        paths(&follower),
+        ["/mnt/artifacts/lib/a.jar", "/mnt/artifacts/lib/b.jar"]
+    );
+    assert_eq!(follower.readiness(), blaze_protocol::Readiness::Ready);
+
+    // Nothing is fetched until the leader's index changes.
+    assert_eq!(sync(&follower, &socket, Some(first)).unwrap(), None);
+
+    leader.swap_index(write_index(&leader_path, &["lib/c.jar"]));
+    let second = sync(&follower, &socket, Some(first)).unwrap().unwrap();
+    assert_ne!(second, first);
+    assert_eq!(follower.snapshot().unwrap().generation, 1);
+    assert_eq!(paths(&follower), ["/mnt/artifacts/lib/c.jar"]);
+}
@@ -1,21 +1,31 @@
 use std::fs;
-use std::io;
+use std::io::{self, Read};
+use std::os::fd::AsRawFd;
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
 };
+use std::thread;
 
 use anyhow::Context;
 use blaze_protocol::codec::{read_message, write_message};
-use blaze_protocol::{DaemonRequest, DaemonResponse};
-use log::{debug, error, info};
+use blaze_protocol::{BUILD, BuildInfo, DaemonRequest, DaemonResponse, PROTOCOL_VERSION, Pong};
+use blaze_runtime::{DaemonDiscovery, discovery_path};
+use log::{debug, error, info, warn};
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook::flag;
+use signal_hook::low_level::pipe;
 
-use crate::query::execute_query;
+use crate::activation::inherited_listener;
+use crate::drain::ActiveConnections;
+use crate::prefetch;
+use crate::query::answer_query;
+use crate::ratelimit::peer_uid;
+use crate::replication;
 use crate::state::DaemonState;
+use crate::subscription;
 
 /// RAII guard that ensures the Unix socket file is removed on shutdown,
 /// even if we return early or panic.
@@ -25,13 +35,45 @@ struct SocketGuard<'a> {
 
 impl<'a> Drop for SocketGuard<'a> {
     fn drop(&mut self) {
-        if let Err(err) = fs::remove_file(self.path) {
-            if err.kind() != io::ErrorKind::NotFound {
-                error!(
-                    "Failed to remove Unix socket at {} on shutdown: {err}",
-                    self.path.display()
-                );
-            }
+        if let Err(err) = fs::remove_file(self.path)
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            error!(
+                "Failed to remove Unix socket at {} on shutdown: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// RAII guard that removes the discovery file on shutdown, unless another
+/// daemon has replaced it since.
+struct DiscoveryGuard {
+    path: PathBuf,
+}
+
+impl DiscoveryGuard {
+    fn advertise(state: &DaemonState) -> io::Result<Self> {
+        let path = discovery_path();
+        DaemonDiscovery {
+            socket_path: state.config.socket_path.clone(),
+            pid: std::process::id(),
+            index_path: state.config.index_path.clone(),
+            protocol_version: PROTOCOL_VERSION,
+            build: Some(BUILD.to_string()),
+        }
+        .write(&path)?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for DiscoveryGuard {
+    fn drop(&mut self) {
+        if let Err(err) = DaemonDiscovery::remove_if_owned(&self.path, std::process::id()) {
+            error!(
+                "Failed to remove discovery file at {} on shutdown: {err}",
+                self.path.display()
+            );
         }
     }
 }
@@ -41,32 +83,73 @@ pub fn run_rpc_server(state: Arc<DaemonState>) -> anyhow::Result<()> {
 
     let shutdown = Arc::new(AtomicBool::new(false));
 
-    // Register signal handlers. They only set the atomic flag
+    // Signal handlers restart interrupted syscalls, so a blocked accept()
+    // would not notice shutdown until the next client connects. The handlers
+    // set the flag and then write to a self-pipe the accept loop waits on
+    // alongside the listener.
+    let (wake_rx, wake_tx) =
+        UnixStream::pair().context("Failed to create the shutdown wake-up pipe")?;
+    wake_rx
+        .set_nonblocking(true)
+        .context("Failed to make the shutdown wake-up pipe non-blocking")?;
     for sig in [SIGINT, SIGTERM] {
         flag::register(sig, Arc::clone(&shutdown))
             .with_context(|| format!("Failed to register signal handler for {sig}"))?;
+        pipe::register(sig, wake_tx.try_clone()?)
+            .with_context(|| format!("Failed to register signal handler for {sig}"))?;
     }
+    drop(wake_tx);
 
-    // Clean up stale socket if it exists.
-    if socket_path.exists() {
-        fs::remove_file(socket_path).with_context(|| {
-            format!(
-                "Failed to remove existing socket at {}",
-                socket_path.display()
-            )
-        })?;
-    }
+    // A socket-activated daemon serves the socket it was handed; the
+    // service manager owns the socket file, so we must not remove it.
+    let (listener, _socket_guard) = match inherited_listener() {
+        Some(listener) => {
+            info!("Using socket passed by the service manager");
+            (listener, None)
+        }
+        None => {
+            // Clean up stale socket if it exists.
+            if socket_path.exists() {
+                fs::remove_file(socket_path).with_context(|| {
+                    format!(
+                        "Failed to remove existing socket at {}",
+                        socket_path.display()
+                    )
+                })?;
+            }
 
-    let listener = UnixListener::bind(socket_path)
-        .with_context(|| format!("Failed to bind Unix socket at {}", socket_path.display()))?;
+            let listener = UnixListener::bind(socket_path).with_context(|| {
+                format!("Failed to bind Unix socket at {}", socket_path.display())
+            })?;
 
-    // Ensure socket is cleaned up on any exit path.
-    let _socket_guard = SocketGuard {
-        path: socket_path.as_path(),
+            // Ensure socket is cleaned up on any exit path.
+            let guard = SocketGuard {
+                path: socket_path.as_path(),
+            };
+            (listener, Some(guard))
+        }
     };
 
+    // accept() only runs once poll(2) reports a client, but the client may
+    // give up in between; it must not block then.
+    listener
+        .set_nonblocking(true)
+        .context("Failed to make Unix socket non-blocking")?;
+
     info!("blaze daemon listening on {}", socket_path.display());
 
+    // Tell clients where we listen. Without the file they fall back to the
+    // default socket, so failing to write it is not fatal.
+    let _discovery_guard = match DiscoveryGuard::advertise(&state) {
+        Ok(guard) => Some(guard),
+        Err(err) => {
+            warn!("Failed to write discovery file: {err}");
+            None
+        }
+    };
+
+    let connections = Arc::new(ActiveConnections::default());
+
     loop {
         // Fast path: if shutdown already requested, stop accepting.
         if shutdown.load(Ordering::Relaxed) {
@@ -74,15 +157,41 @@ pub fn run_rpc_server(state: Arc<DaemonState>) -> anyhow::Result<()> {
             break;
         }
 
+        match wait_for_client(&listener, &wake_rx) {
+            Ok(true) => {}
+            Ok(false) => {
+                // Woken by a signal; drain the pipe so it does not stay readable.
+                while matches!((&wake_rx).read(&mut [0; 16]), Ok(n) if n > 0) {}
+                continue;
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                error!("Failed to wait for clients: {err}");
+                break;
+            }
+        }
+
         match listener.accept() {
             Ok((stream, _addr)) => {
+                if let Err(err) = stream.set_nonblocking(false) {
+                    error!("Failed to configure client stream: {err}");
+                    continue;
+                }
+
                 let state = state.clone();
-                std::thread::spawn(move || {
-                    if let Err(err) = handle_client(stream, state) {
+                let shutdown = shutdown.clone();
+                let guard = connections.enter();
+                thread::spawn(move || {
+                    let _guard = guard;
+                    if let Err(err) = handle_client(stream, state, &shutdown) {
                         error!("Error while handling client: {err:#}");
                     }
                 });
             }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                // The client gave up before we got to it.
+                continue;
+            }
             Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
                 // System call interrupted by signal
                 if shutdown.load(Ordering::Relaxed) {
@@ -100,26 +209,94 @@ pub fn run_rpc_server(state: Arc<DaemonState>) -> anyhow::Result<()> {
         }
     }
 
+    // Stop accepting before draining so no new client races the shutdown.
+    drop(listener);
+
+    let in_flight = connections.active();
+    if in_flight > 0 {
+        let timeout = state.config.drain_timeout;
+        info!("Waiting up to {timeout:?} for {in_flight} in-flight request(s)");
+
+        let abandoned = connections.wait_idle(timeout);
+        if abandoned > 0 {
+            warn!("Drain timeout elapsed; abandoning {abandoned} request(s)");
+        }
+    }
+
     info!("RPC server shutdown complete.");
     Ok(())
 }
 
-fn handle_client(mut stream: UnixStream, state: Arc<DaemonState>) -> anyhow::Result<()> {
+/// Block until a client is waiting on `listener` (`true`) or a signal
+/// handler wrote to `wake` (`false`).
+fn wait_for_client(listener: &UnixListener, wake: &UnixStream) -> io::Result<bool> {
+    let mut fds = [listener.as_raw_fd(), wake.as_raw_fd()].map(|fd| libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    });
+    // SAFETY: `fds` is valid for reads and writes of `fds.len()` entries for
+    // the call.
+    let rc = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+    if rc < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fds[1].revents == 0)
+}
+
+fn handle_client(
+    mut stream: UnixStream,
+    state: Arc<DaemonState>,
+    shutdown: &AtomicBool,
+) -> anyhow::Result<()> {
     let request: DaemonRequest =
         read_message(&mut stream).context("Failed to read DaemonRequest")?;
 
     debug!("Received request: {:?}", request);
 
     let response = match request {
-        DaemonRequest::Ping => DaemonResponse::Pong,
-        DaemonRequest::Status => DaemonResponse::Status(format!(
-            "root={}, index={}",
-            state.config.root.display(),
-            state.config.index_path.display()
-        )),
-        DaemonRequest::Query(q) => match execute_query(&*state.current_index(), &q) {
-            Ok(resp) => DaemonResponse::QueryResult(resp),
-            Err(e) => DaemonResponse::Error(format!("Query failed: {e:#}")),
+        DaemonRequest::Ping => DaemonResponse::Pong(Pong {
+            readiness: state.readiness(),
+            build: BuildInfo::current(),
+        }),
+        DaemonRequest::Status => {
+            let rates = state.rate_limiter.stats();
+            DaemonResponse::Status(format!(
+                "build={}, root={}, index={}, state={}, queries={}, rate_limited={}, cached={}",
+                BUILD,
+                state.config.root.display(),
+                state.config.index_path.display(),
+                state.readiness().as_str(),
+                rates.allowed,
+                rates.limited,
+                state.cache.len()
+            ))
+        }
+        DaemonRequest::Query(_) | DaemonRequest::Prefetch(_) | DaemonRequest::Subscribe(_)
+            if !state.rate_limiter.check(peer_uid(&stream)) =>
+        {
+            DaemonResponse::Error("rate limited".to_string())
+        }
+        DaemonRequest::FetchIndex(req) => replication::serve(&state, &req),
+        // Answered for as long as the client stays connected.
+        DaemonRequest::Subscribe(req) => {
+            return subscription::serve(&state, &mut stream, &req, shutdown);
+        }
+        DaemonRequest::Prefetch(q) => {
+            DaemonResponse::Prefetching(prefetch::spawn(&state, q) as u32)
+        }
+        DaemonRequest::Query(q) => match state.wait_for_snapshot(state.config.startup_wait) {
+            Ok(snapshot) => match state
+                .memory
+                .admit(&snapshot.index, state.config.startup_wait)
+            {
+                Ok(_permit) => match answer_query(&state, &snapshot, &q) {
+                    Ok(resp) => DaemonResponse::QueryResult(resp),
+                    Err(e) => DaemonResponse::Error(format!("Query failed: {e:#}")),
+                },
+                Err(e) => DaemonResponse::Error(e),
+            },
+            Err(e) => DaemonResponse::Error(e),
         },
     };
 
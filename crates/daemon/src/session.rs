@@ -0,0 +1,134 @@
+//! Search-as-you-type sessions.
+//!
+//! A client sending the prefixes of a query as the user types them names a
+//! session in each [`QueryRequest`]. The daemon keeps the matches of the
+//! session's last query, and when the next one is that query typed further
+//! (see [`is_refinement`]) it only re-verifies those matches instead of
+//! seeding from the trigram index. Each keystroke then costs time in
+//! proportion to the previous result set rather than to the index.
+//!
+//! Matches are kept per index generation: after a reload, or a change to
+//! the system index merged in, the next query runs in full.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use blaze_engine::{FileId, is_refinement, parse_query};
+use blaze_protocol::QueryRequest;
+
+/// Largest result set a session keeps; a broader query is not worth
+/// refining and the next one runs in full.
+const MAX_SESSION_MATCHES: usize = 1 << 18;
+
+/// Every match of a query, per index, in index order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Matches {
+    pub user: Vec<FileId>,
+    /// Matches in the system index, if one was merged in.
+    pub system: Option<Vec<FileId>>,
+}
+
+impl Matches {
+    fn len(&self) -> usize {
+        self.user.len() + self.system.as_ref().map_or(0, Vec::len)
+    }
+}
+
+struct Session {
+    generation: u64,
+    system_generation: Option<u64>,
+    query: String,
+    under: Option<String>,
+    matches: Matches,
+    last_used: u64,
+}
+
+struct Sessions {
+    map: HashMap<u64, Session>,
+    /// Bumped on every access; the session with the lowest `last_used` is
+    /// forgotten first.
+    clock: u64,
+}
+
+/// The last matches of each live session.
+pub struct SessionStore {
+    capacity: usize,
+    sessions: Mutex<Sessions>,
+}
+
+impl SessionStore {
+    /// A store remembering up to `capacity` sessions; 0 disables sessions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            sessions: Mutex::new(Sessions {
+                map: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// Take the matches `req` can be answered from: those of its session's
+    /// previous query, if `req` refines it and the same indexes answer.
+    ///
+    /// The session is left without matches until [`record`](Self::record)
+    /// stores the new ones.
+    pub fn take(
+        &self,
+        generation: u64,
+        system_generation: Option<u64>,
+        req: &QueryRequest,
+    ) -> Option<Matches> {
+        let id = req.session?;
+        let session = self.sessions.lock().unwrap().map.remove(&id)?;
+        let usable = session.generation == generation
+            && session.system_generation == system_generation
+            && session.under == req.under
+            && is_refinement(&parse_query(&session.query), &parse_query(&req.query));
+        usable.then_some(session.matches)
+    }
+
+    /// Remember `matches` as the result of `req` in its session.
+    pub fn record(
+        &self,
+        generation: u64,
+        system_generation: Option<u64>,
+        req: &QueryRequest,
+        matches: Matches,
+    ) {
+        let Some(id) = req.session else {
+            return;
+        };
+        if self.capacity == 0 || matches.len() > MAX_SESSION_MATCHES {
+            return;
+        }
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.clock += 1;
+        let now = sessions.clock;
+        if !sessions.map.contains_key(&id)
+            && sessions.map.len() >= self.capacity
+            && let Some(oldest) = sessions
+                .map
+                .iter()
+                .min_by_key(|(_, s)| s.last_used)
+                .map(|(id, _)| *id)
+        {
+            sessions.map.remove(&oldest);
+        }
+        sessions.map.insert(
+            id,
+            Session {
+                generation,
+                system_generation,
+                query: req.query.clone(),
+                under: req.under.clone(),
+                matches,
+                last_used: now,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+#[path = "session_tests.rs"]
+mod tests;
@@ -0,0 +1,155 @@
+use std::{path::PathBuf, time::Duration};
+
+use blaze_engine::{Index, IndexBuilder};
+use blaze_fs::FileRecord;
+use clap::Parser;
+
+use super::*;
+use crate::{
+    config::{Cli, DaemonConfig},
+    query::answer_query,
+    state::DaemonState,
+};
+
+fn request(session: u64, query: &str) -> QueryRequest {
+    QueryRequest {
+        query: query.to_string(),
+        limit: None,
+        under: None,
+        session: Some(session),
+        offset: 0,
+    }
+}
+
+fn matches(user: &[FileId]) -> Matches {
+    Matches {
+        user: user.to_vec(),
+        system: None,
+    }
+}
+
+#[test]
+fn hands_back_the_matches_of_a_refined_query() {
+    let store = SessionStore::new(4);
+    store.record(1, None, &request(7, "con"), matches(&[1, 4, 9]));
+
+    assert_eq!(
+        store.take(1, None, &request(7, "conf")),
+        Some(matches(&[1, 4, 9]))
+    );
+    // Taken: the session has nothing until the next result is recorded.
+    assert_eq!(store.take(1, None, &request(7, "confi")), None);
+}
+
+#[test]
+fn runs_in_full_when_the_query_or_index_changed() {
+    let store = SessionStore::new(4);
+    let refined = request(7, "conf");
+
+    store.record(1, None, &request(7, "con"), matches(&[1]));
+    assert_eq!(store.take(2, None, &refined), None);
+
+    store.record(1, None, &request(7, "con"), matches(&[1]));
+    assert_eq!(store.take(1, Some(0), &refined), None);
+
+    store.record(1, None, &request(7, "con"), matches(&[1]));
+    assert_eq!(store.take(1, None, &request(7, "cn")), None);
+
+    store.record(1, None, &request(7, "con"), matches(&[1]));
+    assert_eq!(store.take(1, None, &request(8, "conf")), None);
+
+    store.record(1, None, &request(7, "con"), matches(&[1]));
+    let elsewhere = QueryRequest {
+        under: Some("/src".into()),
+        ..refined
+    };
+    assert_eq!(store.take(1, None, &elsewhere), None);
+}
+
+#[test]
+fn forgets_the_least_recently_used_session() {
+    let store = SessionStore::new(2);
+    store.record(1, None, &request(1, "a"), matches(&[1]));
+    store.record(1, None, &request(2, "b"), matches(&[2]));
+    store.record(1, None, &request(3, "c"), matches(&[3]));
+
+    assert_eq!(store.take(1, None, &request(1, "ab")), None);
+    assert!(store.take(1, None, &request(2, "bc")).is_some());
+    assert!(store.take(1, None, &request(3, "cd")).is_some());
+}
+
+#[test]
+fn keeps_nothing_without_a_session() {
+    let store = SessionStore::new(2);
+    let req = QueryRequest {
+        session: None,
+        offset: 0,
+        ..request(1, "a")
+    };
+    store.record(1, None, &req, matches(&[1]));
+    assert_eq!(store.take(1, None, &request(1, "ab")), None);
+
+    let disabled = SessionStore::new(0);
+    disabled.record(1, None, &request(1, "a"), matches(&[1]));
+    assert_eq!(disabled.take(1, None, &request(1, "ab")), None);
+}
+
+fn state() -> DaemonState {
+    let root = PathBuf::from("/proj");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        [
+            "config.toml",
+            "conftest.toml",
+            "src/confirm.toml",
+            "src/config/main.toml",
+            "contrib/readme.toml",
+        ]
+        .iter()
+        .map(|rel| FileRecord::test_file(root.join(rel))),
+    );
+    let index = Index::from_staged(&builder.finish()).unwrap();
+
+    let mut config = DaemonConfig::from_args(&Cli::parse_from(["blaze-daemon"])).unwrap();
+    config.query_cache = 0;
+    config.startup_wait = Duration::from_secs(1);
+    DaemonState::with_index(config, index)
+}
+
+fn paths(state: &DaemonState, req: &QueryRequest) -> (Vec<String>, bool) {
+    let snapshot = state.snapshot().unwrap();
+    let response = answer_query(state, &snapshot, req).unwrap();
+    let mut paths: Vec<_> = response.hits.into_iter().map(|h| h.path).collect();
+    paths.sort();
+    (paths, response.refined)
+}
+
+#[test]
+fn typing_further_refines_the_previous_matches() {
+    let state = state();
+
+    let (all, refined) = paths(&state, &request(1, "con"));
+    assert_eq!(all.len(), 5);
+    assert!(!refined);
+
+    let (conf, refined) = paths(&state, &request(1, "conf"));
+    assert!(refined);
+    assert_eq!(
+        conf,
+        [
+            "/proj/config.toml",
+            "/proj/conftest.toml",
+            "/proj/src/config/main.toml",
+            "/proj/src/confirm.toml",
+        ]
+    );
+
+    let (config, refined) = paths(&state, &request(1, "config main"));
+    assert!(refined);
+    assert_eq!(config, ["/proj/src/config/main.toml"]);
+
+    // Backspacing is not a refinement: the whole index is searched again.
+    let (conf_again, refined) = paths(&state, &request(1, "conf"));
+    assert!(!refined);
+    assert_eq!(conf_again, conf);
+}
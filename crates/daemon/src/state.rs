@@ -1,35 +1,314 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use blaze_engine::Index;
-use blaze_indexer::open_or_build_index;
-use log::warn;
+use blaze_engine::{Index, WarmSet};
+use blaze_indexer::{ScanOptions, open_or_build_index, open_or_heal_index};
+use blaze_protocol::Readiness;
+use log::{error, info, warn};
 
-use crate::config::DaemonConfig;
+use crate::{
+    cache::QueryCache,
+    config::DaemonConfig,
+    federation::{Stamp, SystemIndex, index_stamp},
+    memory::MemoryBudget,
+    prefetch::Prefetcher,
+    ratelimit::RateLimiter,
+    replication::BundleServer,
+    session::SessionStore,
+};
+
+/// A consistent view of the index for the lifetime of one request.
+///
+/// Holding a snapshot keeps its index mapped even if the daemon swaps in a
+/// new one meanwhile; only requests started after the swap see the new index.
+#[derive(Clone)]
+pub struct IndexSnapshot {
+    pub index: Arc<Index>,
+    /// Incremented every time a new index is swapped in.
+    pub generation: u64,
+}
+
+/// Progress of the background index load.
+struct Startup {
+    readiness: Readiness,
+    /// Set if opening or building the index failed.
+    error: Option<String>,
+}
 
 pub struct DaemonState {
     pub config: DaemonConfig,
-    index: RwLock<Arc<Index>>,
+    pub memory: MemoryBudget,
+    pub rate_limiter: RateLimiter,
+    /// The system-wide index to merge into results, if configured.
+    pub system: Option<SystemIndex>,
+    pub cache: QueryCache,
+    pub prefetcher: Prefetcher,
+    pub sessions: SessionStore,
+    /// What this daemon serves to daemons following it.
+    pub bundles: BundleServer,
+    current: RwLock<Option<IndexSnapshot>>,
+    /// The index file and delta the current index was opened from.
+    on_disk: Mutex<Option<Stamp>>,
+    startup: Mutex<Startup>,
+    /// Signalled when the startup state changes or a new index is swapped in.
+    changed: Condvar,
 }
 
 impl DaemonState {
-    pub fn new(config: DaemonConfig) -> anyhow::Result<Self> {
-        let (index, warning) = open_or_build_index(&config.root, &config.index_path, true)?;
+    /// State with no index yet; [`load`](Self::load) brings it up.
+    pub fn starting(config: DaemonConfig) -> Self {
+        Self {
+            memory: MemoryBudget::new(config.memory_limit),
+            rate_limiter: RateLimiter::new(config.rate_limit, config.rate_burst),
+            system: config.system_index.clone().and_then(|path| {
+                SystemIndex::new(path)
+                    .inspect_err(|e| warn!("Not using the system index: {e}"))
+                    .ok()
+            }),
+            cache: QueryCache::new(config.query_cache),
+            prefetcher: Prefetcher::new(config.prefetch),
+            sessions: SessionStore::new(config.sessions),
+            bundles: BundleServer::default(),
+            config,
+            current: RwLock::new(None),
+            on_disk: Mutex::new(None),
+            startup: Mutex::new(Startup {
+                readiness: Readiness::Starting,
+                error: None,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// State that is immediately [`Readiness::Ready`] with `index`.
+    #[cfg(test)]
+    pub fn with_index(config: DaemonConfig, index: Index) -> Self {
+        let state = Self::starting(config);
+        state.install(index);
+        state.set_readiness(Readiness::Ready);
+        state
+    }
+
+    /// Open (or build) the index and page it in.
+    ///
+    /// With `auto_rebuild` set, an unreadable index is moved aside and
+    /// rebuilt rather than failing startup.
+    ///
+    /// The index becomes queryable as soon as its hot sections are warm
+    /// ([`Readiness::Warming`]); the rest is paged in afterwards. Meant to
+    /// run on a background thread while the RPC server already accepts
+    /// connections.
+    pub fn load(&self) {
+        let started = Instant::now();
+        let open = if self.config.auto_rebuild {
+            open_or_heal_index
+        } else {
+            open_or_build_index
+        };
+        let opened = open(
+            &self.config.root,
+            &self.config.index_path,
+            true,
+            &ScanOptions::default(),
+        );
 
-        if let Some(msg) = warning {
-            warn!("{msg}")
+        let index = match opened {
+            Ok((index, warning)) => {
+                *self.on_disk.lock().unwrap() = Some(index_stamp(&self.config.index_path));
+                if let Some(msg) = warning {
+                    warn!("{msg}")
+                }
+                index
+            }
+            Err(e) => {
+                error!("Failed to open index: {e:#}");
+                let mut startup = self.startup.lock().unwrap();
+                startup.error = Some(format!("{e:#}"));
+                self.changed.notify_all();
+                return;
+            }
+        };
+
+        self.bring_up(index, started);
+    }
+
+    /// Publish `index` received from the daemon this one follows: bring it
+    /// up as the first index, or swap it in for the current one.
+    pub fn publish(&self, index: Index) {
+        if self.snapshot().is_none() {
+            self.bring_up(index, Instant::now());
+        } else {
+            self.swap_index(index);
         }
+    }
 
-        Ok(Self {
-            config,
-            index: RwLock::new(Arc::new(index)),
-        })
+    /// Make `index` the first index, answering queries once its hot sections
+    /// are warm and reporting ready once the rest is.
+    fn bring_up(&self, index: Index, started: Instant) {
+        let hot = index.warm(WarmSet::Hot);
+        if self.config.mlock {
+            match index.lock_in_memory() {
+                Ok(bytes) => info!("Locked {bytes} bytes of index keys and file records in memory"),
+                Err(e) => warn!("{e}; continuing without locking"),
+            }
+        }
+        let index = self.install(index);
+        self.set_readiness(Readiness::Warming);
+        info!(
+            "Index open after {:?}; {hot} hot bytes warm, warming the rest",
+            started.elapsed()
+        );
+
+        let cold = index.warm(WarmSet::Cold);
+        self.set_readiness(Readiness::Ready);
+        info!(
+            "Index ready after {:?}; {} bytes warm",
+            started.elapsed(),
+            hot + cold
+        );
+    }
+
+    /// Swap in the index again whenever it is rewritten on disk, e.g. by
+    /// `blaze index --refresh`, checking every `reload_interval`. Runs for
+    /// the life of the daemon once the index is loaded.
+    pub fn watch_index_file(&self) {
+        if self.config.reload_interval.is_zero() || self.snapshot().is_none() {
+            return;
+        }
+        loop {
+            thread::sleep(self.config.reload_interval);
+            self.reload_if_changed();
+        }
+    }
+
+    /// Reopen the index if its file or delta changed since it was opened,
+    /// returning the new generation.
+    pub fn reload_if_changed(&self) -> Option<u64> {
+        let path = &self.config.index_path;
+        let stamp = index_stamp(path);
+        {
+            let mut on_disk = self.on_disk.lock().unwrap();
+            if on_disk.as_ref() == Some(&stamp) {
+                return None;
+            }
+            // Record it either way, so an index that fails to open is
+            // retried only once it changes again.
+            *on_disk = Some(stamp);
+        }
+
+        match Index::open(path) {
+            Ok(index) => {
+                index.warm(WarmSet::Hot);
+                Some(self.swap_index(index))
+            }
+            Err(e) => {
+                warn!(
+                    "Keeping the current index; {} does not open: {e}",
+                    path.display()
+                );
+                None
+            }
+        }
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        self.startup.lock().unwrap().readiness
+    }
+
+    /// Take a snapshot of the current index for one request, if one is open.
+    pub fn snapshot(&self) -> Option<IndexSnapshot> {
+        self.current.read().unwrap().clone()
     }
 
-    pub fn current_index(&self) -> Arc<Index> {
-        self.index.read().unwrap().clone()
+    /// Wait up to `timeout` for the index to become queryable.
+    ///
+    /// Returns as soon as the index is open, even if it is still warming.
+    /// Fails if the deadline passes first or the index could not be loaded.
+    pub fn wait_for_snapshot(&self, timeout: Duration) -> Result<IndexSnapshot, String> {
+        let deadline = Instant::now() + timeout;
+        let mut startup = self.startup.lock().unwrap();
+        loop {
+            if let Some(e) = &startup.error {
+                return Err(format!("index failed to load: {e}"));
+            }
+            if startup.readiness != Readiness::Starting
+                && let Some(snapshot) = self.snapshot()
+            {
+                return Ok(snapshot);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(format!(
+                    "index is still loading after {timeout:?}; try again shortly"
+                ));
+            }
+            startup = self
+                .changed
+                .wait_timeout(startup, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Wait up to `timeout` for an index newer than `generation` to be
+    /// swapped in, returning the current snapshot either way.
+    pub fn wait_for_swap(&self, generation: u64, timeout: Duration) -> Option<IndexSnapshot> {
+        let deadline = Instant::now() + timeout;
+        let mut startup = self.startup.lock().unwrap();
+        loop {
+            let snapshot = self.snapshot();
+            let now = Instant::now();
+            if now >= deadline || snapshot.as_ref().is_some_and(|s| s.generation > generation) {
+                return snapshot;
+            }
+            startup = self
+                .changed
+                .wait_timeout(startup, deadline - now)
+                .unwrap()
+                .0;
+        }
     }
 
-    pub fn swap_index(&self, new_index: Index) {
-        *self.index.write().unwrap() = Arc::new(new_index);
+    /// Replace the current index, returning the new generation.
+    ///
+    /// Requests already holding a snapshot keep using the old index, which
+    /// is unmapped once the last of them finishes.
+    pub fn swap_index(&self, new_index: Index) -> u64 {
+        let generation = {
+            let mut current = self.current.write().unwrap();
+            let generation = current.as_ref().map_or(0, |s| s.generation + 1);
+            *current = Some(IndexSnapshot {
+                index: Arc::new(new_index),
+                generation,
+            });
+            generation
+        };
+        // Notify under the lock `wait_for_swap` checks the snapshot under,
+        // so a waiter cannot miss the swap.
+        let _startup = self.startup.lock().unwrap();
+        self.changed.notify_all();
+        info!("Swapped in index generation {generation}");
+        generation
+    }
+
+    /// Publish the first index, at generation 0.
+    fn install(&self, index: Index) -> Arc<Index> {
+        let index = Arc::new(index);
+        *self.current.write().unwrap() = Some(IndexSnapshot {
+            index: Arc::clone(&index),
+            generation: 0,
+        });
+        index
+    }
+
+    fn set_readiness(&self, readiness: Readiness) {
+        self.startup.lock().unwrap().readiness = readiness;
+        self.changed.notify_all();
     }
 }
+
+#[cfg(test)]
+#[path = "state_tests.rs"]
+mod tests;
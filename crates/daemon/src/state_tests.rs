@@ -0,0 +1,170 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use blaze_engine::{IndexBuilder, QueryPipeline};
+use blaze_fs::FileRecord;
+
+use super::*;
+
+/// Build an index whose every path lives under `/<tag>`.
+fn index_for(tag: &str, files: usize) -> Index {
+    let root = PathBuf::from(format!("/{tag}"));
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..files).map(|i| FileRecord::test_file(root.join(format!("dir{}/note{i}.txt", i % 7)))),
+    );
+    Index::from_staged(&builder.finish()).unwrap()
+}
+
+fn config() -> DaemonConfig {
+    DaemonConfig {
+        root: PathBuf::from("/"),
+        index_path: PathBuf::from("/nonexistent/index.bin"),
+        socket_path: PathBuf::from("/nonexistent/daemon.sock"),
+        drain_timeout: Duration::from_secs(1),
+        startup_wait: Duration::from_secs(1),
+        auto_rebuild: false,
+        memory_limit: None,
+        rate_limit: 0,
+        rate_burst: 0,
+        mlock: false,
+        system_index: None,
+        query_cache: 0,
+        prefetch: 0,
+        sessions: 0,
+        follow: None,
+        follow_interval: Duration::from_secs(1),
+        follow_root: None,
+        reload_interval: Duration::ZERO,
+        max_hits: 0,
+        max_response_bytes: 0,
+    }
+}
+
+fn query_paths(snapshot: &IndexSnapshot, query: &str) -> Vec<String> {
+    QueryPipeline::new(snapshot.index.as_ref())
+        .parse(query)
+        .execute()
+        .rank_with_limit(None)
+        .iter_with_paths()
+        .map(|(_, _, path)| path)
+        .collect()
+}
+
+#[test]
+fn swap_bumps_generation() {
+    let state = DaemonState::with_index(config(), index_for("a", 3));
+    assert_eq!(state.snapshot().unwrap().generation, 0);
+
+    assert_eq!(state.swap_index(index_for("b", 3)), 1);
+    assert_eq!(state.swap_index(index_for("c", 3)), 2);
+    assert_eq!(state.snapshot().unwrap().generation, 2);
+}
+
+#[test]
+fn snapshot_outlives_swap() {
+    let state = DaemonState::with_index(config(), index_for("a", 5));
+    let old = state.snapshot().unwrap();
+
+    state.swap_index(index_for("b", 2));
+
+    let old_paths = query_paths(&old, "note");
+    assert_eq!(old.generation, 0);
+    assert_eq!(old_paths.len(), 5);
+    assert!(old_paths.iter().all(|p| p.starts_with("/a/")));
+
+    let new_paths = query_paths(&state.snapshot().unwrap(), "note");
+    assert_eq!(new_paths.len(), 2);
+    assert!(new_paths.iter().all(|p| p.starts_with("/b/")));
+}
+
+#[test]
+fn reload_during_queries_never_mixes_generations() {
+    // Even generations index /a with 200 files, odd ones /b with 50.
+    let state = Arc::new(DaemonState::with_index(config(), index_for("a", 200)));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let state = Arc::clone(&state);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut queries = 0;
+                while !done.load(Ordering::Relaxed) || queries == 0 {
+                    let snapshot = state.snapshot().unwrap();
+                    let (prefix, expected) = if snapshot.generation.is_multiple_of(2) {
+                        ("/a/", 200)
+                    } else {
+                        ("/b/", 50)
+                    };
+
+                    let paths = query_paths(&snapshot, "note");
+                    assert_eq!(paths.len(), expected);
+                    assert!(paths.iter().all(|p| p.starts_with(prefix)));
+                    queries += 1;
+                }
+            })
+        })
+        .collect();
+
+    for generation in 1..=20u64 {
+        let next = if generation.is_multiple_of(2) {
+            index_for("a", 200)
+        } else {
+            index_for("b", 50)
+        };
+        assert_eq!(state.swap_index(next), generation);
+    }
+    done.store(true, Ordering::Relaxed);
+
+    for reader in readers {
+        reader.join().expect("reader thread panicked");
+    }
+}
+
+#[test]
+fn with_index_is_ready() {
+    let state = DaemonState::with_index(config(), index_for("a", 3));
+    assert_eq!(state.readiness(), Readiness::Ready);
+    assert!(state.wait_for_snapshot(Duration::ZERO).is_ok());
+}
+
+#[test]
+fn query_wait_times_out_while_starting() {
+    let state = DaemonState::starting(config());
+    assert_eq!(state.readiness(), Readiness::Starting);
+    assert!(state.snapshot().is_none());
+
+    let err = state
+        .wait_for_snapshot(Duration::from_millis(20))
+        .err()
+        .unwrap();
+    assert!(err.contains("still loading"), "{err}");
+}
+
+#[test]
+fn waiting_query_is_answered_once_warming() {
+    let state = Arc::new(DaemonState::starting(config()));
+
+    let loader = {
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            state.install(index_for("a", 4));
+            state.set_readiness(Readiness::Warming);
+        })
+    };
+
+    let snapshot = state.wait_for_snapshot(Duration::from_secs(5)).unwrap();
+    assert_eq!(snapshot.generation, 0);
+    assert_eq!(query_paths(&snapshot, "note").len(), 4);
+    loader.join().unwrap();
+    assert_eq!(state.readiness(), Readiness::Warming);
+}
@@ -0,0 +1,168 @@
+//! Queries whose changing matches are pushed to the client.
+//!
+//! A client sends [`DaemonRequest::Subscribe`](blaze_protocol::DaemonRequest)
+//! and keeps the connection open. The daemon answers with every current
+//! match, then re-runs the query whenever it swaps in a new index or the
+//! system index merged into results is rebuilt, and sends the paths that
+//! started or stopped matching. An editor sidebar stays current without
+//! polling, and the daemon only works when an index changes.
+//!
+//! Each subscription holds its connection's thread and the paths of its
+//! matches, so a query matching more than [`MAX_SUBSCRIPTION_MATCHES`]
+//! files is refused.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, ErrorKind},
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use blaze_engine::IndexReader;
+use blaze_protocol::{
+    DaemonResponse, QueryRequest, ResultsChanged, SubscribeRequest, codec::write_message,
+};
+use log::debug;
+
+use crate::{
+    federation::{SystemIndex, SystemSnapshot},
+    query::{ResponseCaps, execute_query},
+    state::{DaemonState, IndexSnapshot},
+};
+
+/// Most files a subscribed query may match.
+pub const MAX_SUBSCRIPTION_MATCHES: usize = 1 << 18;
+
+/// How often a subscription checks, between index swaps, whether the
+/// system index changed, the client hung up or the daemon is shutting down.
+const SUBSCRIPTION_POLL: Duration = Duration::from_millis(500);
+
+/// Serve a subscription on `stream` until the client hangs up or `shutdown`
+/// is set.
+pub fn serve(
+    state: &DaemonState,
+    stream: &mut UnixStream,
+    req: &SubscribeRequest,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let mut snapshot = match state.wait_for_snapshot(state.config.startup_wait) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return write_message(stream, &DaemonResponse::Error(e)),
+    };
+
+    let mut matches = BTreeSet::new();
+    let mut answered = None;
+    loop {
+        let system = state.system.as_ref().and_then(SystemIndex::current);
+        let generations = (snapshot.generation, system.as_ref().map(|s| s.generation));
+        if answered != Some(generations) {
+            let current = {
+                let _permit = match state
+                    .memory
+                    .admit(&snapshot.index, state.config.startup_wait)
+                {
+                    Ok(permit) => permit,
+                    Err(e) => return write_message(stream, &DaemonResponse::Error(e)),
+                };
+                matching_paths(&snapshot, system.as_deref(), req)?
+            };
+            if current.len() > MAX_SUBSCRIPTION_MATCHES {
+                let msg = format!(
+                    "`{}` matches more than {MAX_SUBSCRIPTION_MATCHES} files; \
+                     narrow it down to subscribe",
+                    req.query
+                );
+                return write_message(stream, &DaemonResponse::Error(msg));
+            }
+
+            let change = diff(&matches, &current, snapshot.generation);
+            let first = answered.is_none();
+            if (first || !change.added.is_empty() || !change.removed.is_empty())
+                && let Err(e) = write_message(stream, &DaemonResponse::ResultsChanged(change))
+            {
+                debug!("Subscriber to `{}` went away: {e:#}", req.query);
+                return Ok(());
+            }
+            matches = current;
+            answered = Some(generations);
+        }
+
+        if shutdown.load(Ordering::Relaxed) || hung_up(stream) {
+            return Ok(());
+        }
+        if let Some(newer) = state.wait_for_swap(snapshot.generation, SUBSCRIPTION_POLL) {
+            snapshot = newer;
+        }
+    }
+}
+
+/// Every path matching `req`, in the user's index and the part of the
+/// system index they may see.
+fn matching_paths(
+    snapshot: &IndexSnapshot,
+    system: Option<&SystemSnapshot>,
+    req: &SubscribeRequest,
+) -> Result<BTreeSet<String>> {
+    let query = QueryRequest {
+        query: req.query.clone(),
+        limit: Some(0),
+        under: req.under.clone(),
+        session: None,
+        offset: 0,
+    };
+    let caps = ResponseCaps {
+        max_hits: 0,
+        max_bytes: 0,
+    };
+    let (_, matches) = execute_query(snapshot, system, &query, caps, false, None)?;
+
+    let mut paths: BTreeSet<String> = (matches.user.iter())
+        .map(|&fid| snapshot.index.reconstruct_full_path(fid))
+        .collect();
+    if let (Some(system), Some(ids)) = (system, &matches.system) {
+        paths.extend(
+            ids.iter()
+                .map(|&fid| system.index.reconstruct_full_path(fid)),
+        );
+    }
+    Ok(paths)
+}
+
+/// The change from matching `old` to matching `new`.
+fn diff(old: &BTreeSet<String>, new: &BTreeSet<String>, generation: u64) -> ResultsChanged {
+    ResultsChanged {
+        generation,
+        added: new.difference(old).cloned().collect(),
+        removed: old.difference(new).cloned().collect(),
+        total: new.len() as u32,
+    }
+}
+
+/// Whether the client closed its end. Clients send nothing after
+/// subscribing, so anything readable is left alone.
+fn hung_up(stream: &UnixStream) -> bool {
+    let mut byte = 0u8;
+    // SAFETY: `byte` is valid for a one-byte write for the call.
+    let n = unsafe {
+        libc::recv(
+            stream.as_raw_fd(),
+            (&mut byte as *mut u8).cast(),
+            1,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        )
+    };
+    match n {
+        0 => true,
+        n if n > 0 => false,
+        _ => !matches!(
+            io::Error::last_os_error().kind(),
+            ErrorKind::WouldBlock | ErrorKind::Interrupted
+        ),
+    }
+}
+
+#[cfg(test)]
+#[path = "subscription_tests.rs"]
+mod tests;
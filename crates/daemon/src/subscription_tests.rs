@@ -0,0 +1,127 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+use blaze_engine::{Index, IndexBuilder, write_index_atomic};
+use blaze_fs::FileRecord;
+use blaze_protocol::codec::read_message;
+use clap::Parser;
+use tempfile::tempdir;
+
+use super::*;
+use crate::config::{Cli, DaemonConfig};
+
+/// Write an index of `rels` under `/srv` to `path`.
+fn write_index(path: &Path, rels: &[&str]) {
+    let root = PathBuf::from("/srv");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(rels.iter().map(|rel| FileRecord::test_file(root.join(rel))));
+    write_index_atomic(path, &builder.finish(), 0).unwrap();
+}
+
+fn config(index_path: &Path) -> DaemonConfig {
+    let args = ["blaze-daemon", "--index-path", index_path.to_str().unwrap()];
+    DaemonConfig::from_args(&Cli::parse_from(args)).unwrap()
+}
+
+fn changed(generation: u64, added: &[&str], removed: &[&str], total: u32) -> ResultsChanged {
+    ResultsChanged {
+        generation,
+        added: added.iter().map(|p| p.to_string()).collect(),
+        removed: removed.iter().map(|p| p.to_string()).collect(),
+        total,
+    }
+}
+
+fn next_change(client: &mut UnixStream) -> ResultsChanged {
+    match read_message(client).unwrap() {
+        DaemonResponse::ResultsChanged(change) => change,
+        other => panic!("expected a change, got {other:?}"),
+    }
+}
+
+#[test]
+fn diffs_list_what_started_and_stopped_matching() {
+    let set = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect();
+    assert_eq!(
+        diff(&set(&["/a", "/b"]), &set(&["/b", "/c", "/d"]), 4),
+        changed(4, &["/c", "/d"], &["/a"], 3)
+    );
+    assert_eq!(
+        diff(&set(&["/a"]), &set(&["/a"]), 5),
+        changed(5, &[], &[], 1)
+    );
+}
+
+#[test]
+fn subscribers_get_every_match_then_each_change() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index(&path, &["logs/a.log", "src/main.rs"]);
+    let state = Arc::new(DaemonState::with_index(
+        config(&path),
+        Index::open(&path).unwrap(),
+    ));
+    // The index was not opened by `load`, so this first check records the
+    // file's stamp; later checks only reload after a rewrite.
+    state.reload_if_changed();
+    let generation = state.snapshot().unwrap().generation;
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let subscriber = {
+        let (state, shutdown) = (Arc::clone(&state), Arc::clone(&shutdown));
+        let req = SubscribeRequest {
+            query: "ext:log".into(),
+            under: None,
+        };
+        thread::spawn(move || serve(&state, &mut server, &req, &shutdown))
+    };
+    assert_eq!(
+        next_change(&mut client),
+        changed(generation, &["/srv/logs/a.log"], &[], 1)
+    );
+
+    write_index(&path, &["logs/a.log", "logs/b.log", "src/main.rs"]);
+    assert_eq!(state.reload_if_changed(), Some(generation + 1));
+    assert_eq!(state.reload_if_changed(), None);
+    assert_eq!(
+        next_change(&mut client),
+        changed(generation + 1, &["/srv/logs/b.log"], &[], 2)
+    );
+
+    // A reload that leaves the matches alone is not pushed.
+    write_index(
+        &path,
+        &["logs/a.log", "logs/b.log", "src/main.rs", "src/lib.rs"],
+    );
+    assert_eq!(state.reload_if_changed(), Some(generation + 2));
+    write_index(&path, &["logs/b.log", "src/main.rs"]);
+    assert_eq!(state.reload_if_changed(), Some(generation + 3));
+    assert_eq!(
+        next_change(&mut client),
+        changed(generation + 3, &[], &["/srv/logs/a.log"], 1)
+    );
+
+    drop(client);
+    subscriber.join().unwrap().unwrap();
+}
+
+#[test]
+fn subscriptions_end_when_the_daemon_shuts_down() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index(&path, &["logs/a.log"]);
+    let state = DaemonState::with_index(config(&path), Index::open(&path).unwrap());
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let req = SubscribeRequest {
+        query: "ext:log".into(),
+        under: None,
+    };
+    let shutdown = AtomicBool::new(true);
+    serve(&state, &mut server, &req, &shutdown).unwrap();
+    assert_eq!(next_change(&mut client).total, 1);
+}
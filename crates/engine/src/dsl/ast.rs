@@ -1,14 +1,14 @@
 use chrono::{DateTime, Utc};
 
-use crate::dsl::predicates::Predicate;
+use crate::{dsl::predicates::Predicate, flags::NoiseFlags};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Query {
     pub expr: QueryExpr,
 }
 
 /// Boolean expression over leaves.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum QueryExpr {
     And(Vec<QueryExpr>),
     Or(Vec<QueryExpr>),
@@ -17,18 +17,35 @@ pub enum QueryExpr {
 }
 
 /// Either a free text term or a typed field predicate.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LeafExpr {
     Text(TextTerm),
     Predicate(Predicate),
 }
 
 /// Free-text search term
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TextTerm {
     pub text: String,
     pub is_phrase: bool,
     pub is_glob: bool,
+    /// Where in the file name the text must be; written `^text`, `text$`
+    /// or `^text$`.
+    pub anchor: TextAnchor,
+}
+
+/// Position a text term is anchored at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAnchor {
+    /// Anywhere in the path.
+    #[default]
+    Anywhere,
+    /// The file name starts with the text.
+    Start,
+    /// The file name ends with the text.
+    End,
+    /// The file name is the text.
+    Whole,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,6 +54,17 @@ pub enum Field {
     Size,
     Created,
     Modified,
+    Repo,
+    Lang,
+    Hash,
+    /// File name glob, case-sensitive
+    Name,
+    /// File name glob, case-insensitive
+    IName,
+    /// Covered by one of the user's pins
+    Pinned,
+    /// Under a build, cache, log, ... directory, by stored noise flags
+    Noise,
 }
 
 /// Comparison operator.
@@ -51,15 +79,18 @@ pub enum CmpOp {
 }
 
 /// Typed value for a predicate.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Str(String),
     SizeBytes(u64),
     Time(TimeExpr),
+    Bool(bool),
+    /// Matches files with any of these noise flags
+    Noise(NoiseFlags),
 }
 
 /// Time expressions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TimeExpr {
     Absolute(DateTime<Utc>),
     Relative(RelativeTime),
@@ -71,6 +102,8 @@ pub enum RelativeTime {
     Days(i64),
     Hours(i64),
     Weeks(i64),
+    /// Calendar months: `1mo` before March 31st is February's last day.
+    Months(i64),
     Years(i64),
 }
 
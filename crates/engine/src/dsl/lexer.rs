@@ -1,4 +1,4 @@
-use std::{iter::Peekable, ops::Range, str::CharIndices};
+use std::{borrow::Cow, iter::Peekable, ops::Range, str::CharIndices};
 // TODO: We need to consider how to handle cases where
 // the file name like this_and_that.pdf, this_or_that.pdf
 
@@ -32,7 +32,9 @@ pub enum TokenKind {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token<'a> {
     pub kind: TokenKind,
-    pub lexeme: &'a str,
+    /// Token text without its quotes and with escapes resolved; borrowed
+    /// from the input unless it contained an escape.
+    pub lexeme: Cow<'a, str>,
     pub span: Range<usize>,
 }
 
@@ -49,39 +51,102 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn advance_until(&mut self, end: usize) {
-        while let Some(&(i, _)) = self.chars.peek() {
-            if i >= end {
-                break;
+    /// Scan an identifier or number starting at `start` with `first_char`.
+    ///
+    /// A backslash makes the next delimiter, quote or backslash part of the
+    /// word, so `my\ file.txt` is one identifier; any other backslash is
+    /// kept as is.
+    fn scan_word_or_number(
+        &mut self,
+        start: usize,
+        first_char: char,
+    ) -> (TokenKind, Cow<'a, str>, usize) {
+        let mut unescaped: Option<String> = None;
+        let mut copied = start;
+        let mut end = start;
+        let mut next = Some((start, first_char));
+
+        // Consume until we hit a delimiter
+        while let Some((i, c)) = next {
+            if c == '\\'
+                && let Some(&(j, escaped)) = self.chars.peek()
+                && is_escapable(escaped)
+            {
+                let buf = unescaped.get_or_insert_with(String::new);
+                buf.push_str(&self.input[copied..i]);
+                buf.push(escaped);
+                self.chars.next();
+                copied = j + escaped.len_utf8();
+                end = copied;
+            } else {
+                end = i + c.len_utf8();
             }
-            self.chars.next();
+
+            next = match self.chars.peek() {
+                Some(&(_, c)) if is_delimiter(c) => None,
+                _ => self.chars.next(),
+            };
         }
+
+        let (kind, lexeme) = match unescaped {
+            Some(mut buf) => {
+                buf.push_str(&self.input[copied..end]);
+                (TokenKind::Ident, Cow::Owned(buf))
+            }
+            None => {
+                let lexeme = &self.input[start..end];
+                let kind = if is_number(lexeme) {
+                    TokenKind::Number
+                } else {
+                    classify_keyword(lexeme)
+                };
+                (kind, Cow::Borrowed(lexeme))
+            }
+        };
+
+        (kind, lexeme, end)
     }
 
-    /// Scan an identifier or number starting at `start` with `first_char`.
-    fn scan_word_or_number(&mut self, start: usize, first_char: char) -> (TokenKind, usize) {
-        let mut end = start + first_char.len_utf8();
-        let mut all_ascii_digits = first_char.is_ascii_digit();
+    /// Scan a string opened by `quote` at `start`, up to the matching quote
+    /// or the end of input.
+    ///
+    /// Inside, `\<quote>` and `\\` stand for the character itself; any other
+    /// backslash is kept as is, so Windows-style paths need no doubling.
+    fn scan_quoted(&mut self, start: usize, quote: char) -> Token<'a> {
+        let content_start = start + quote.len_utf8();
+        let mut unescaped: Option<String> = None;
+        let mut copied = content_start;
+        let (mut content_end, mut end) = (self.input.len(), self.input.len());
 
-        // Consume until we hit a delimiter
-        while let Some(&(i, c)) = self.chars.peek() {
-            if is_delimiter(c) {
+        while let Some((i, c)) = self.chars.next() {
+            if c == quote {
+                (content_end, end) = (i, i + c.len_utf8());
                 break;
             }
-            all_ascii_digits &= c.is_ascii_digit();
-            end = i + c.len_utf8();
-            self.chars.next();
+            if c == '\\'
+                && let Some(&(j, escaped)) = self.chars.peek()
+                && (escaped == quote || escaped == '\\')
+            {
+                let buf = unescaped.get_or_insert_with(String::new);
+                buf.push_str(&self.input[copied..i]);
+                buf.push(escaped);
+                self.chars.next();
+                copied = j + escaped.len_utf8();
+            }
         }
 
-        let lexeme = &self.input[start..end];
-
-        let kind = if all_ascii_digits {
-            TokenKind::Number
-        } else {
-            classify_keyword(lexeme)
+        let lexeme = match unescaped {
+            Some(mut buf) => {
+                buf.push_str(&self.input[copied..content_end]);
+                Cow::Owned(buf)
+            }
+            None => Cow::Borrowed(&self.input[content_start..content_end]),
         };
-
-        (kind, end)
+        Token {
+            kind: TokenKind::String,
+            lexeme,
+            span: start..end,
+        }
     }
 
     /// Return the next token from the input.
@@ -93,7 +158,7 @@ impl<'a> Lexer<'a> {
                     let len = self.input.len();
                     return Token {
                         kind: TokenKind::Eof,
-                        lexeme: "",
+                        lexeme: Cow::Borrowed(""),
                         span: len..len,
                     };
                 }
@@ -117,7 +182,7 @@ impl<'a> Lexer<'a> {
                     let end = start + 1;
                     return Token {
                         kind,
-                        lexeme: &self.input[start..end],
+                        lexeme: Cow::Borrowed(&self.input[start..end]),
                         span: start..end,
                     };
                 }
@@ -131,7 +196,7 @@ impl<'a> Lexer<'a> {
                     }
                     return Token {
                         kind,
-                        lexeme: &self.input[start..end],
+                        lexeme: Cow::Borrowed(&self.input[start..end]),
                         span: start..end,
                     };
                 }
@@ -145,33 +210,11 @@ impl<'a> Lexer<'a> {
                     }
                     return Token {
                         kind,
-                        lexeme: &self.input[start..end],
+                        lexeme: Cow::Borrowed(&self.input[start..end]),
                         span: start..end,
                     };
                 }
-                '"' => {
-                    // NOTE: No escaping: the next literal `"` terminates the string.
-                    let content_start = start + 1;
-                    let remainder = &self.input[content_start..];
-                    if let Some(rel_end) = remainder.find('"') {
-                        let content_end = content_start + rel_end;
-                        let end = content_end + 1;
-                        self.advance_until(end);
-                        return Token {
-                            kind: TokenKind::String,
-                            lexeme: &self.input[content_start..content_end],
-                            span: start..end,
-                        };
-                    } else {
-                        let end = self.input.len();
-                        self.advance_until(end);
-                        return Token {
-                            kind: TokenKind::String,
-                            lexeme: &self.input[content_start..end],
-                            span: start..end,
-                        };
-                    }
-                }
+                '"' | '\'' => return self.scan_quoted(start, c),
                 '|' => {
                     // Treat "||" as OR, single '|' as part of an identifier.
                     if let Some(&(_, '|')) = self.chars.peek() {
@@ -179,24 +222,24 @@ impl<'a> Lexer<'a> {
                         let end = start + 2;
                         return Token {
                             kind: TokenKind::Or,
-                            lexeme: &self.input[start..end],
+                            lexeme: Cow::Borrowed(&self.input[start..end]),
                             span: start..end,
                         };
                     } else {
-                        let (kind, end) = self.scan_word_or_number(start, c);
+                        let (kind, lexeme, end) = self.scan_word_or_number(start, c);
                         return Token {
                             kind,
-                            lexeme: &self.input[start..end],
+                            lexeme,
                             span: start..end,
                         };
                     }
                 }
                 _ => {
                     // Identifier or number.
-                    let (kind, end) = self.scan_word_or_number(start, c);
+                    let (kind, lexeme, end) = self.scan_word_or_number(start, c);
                     return Token {
                         kind,
-                        lexeme: &self.input[start..end],
+                        lexeme,
                         span: start..end,
                     };
                 }
@@ -206,13 +249,32 @@ impl<'a> Lexer<'a> {
 }
 
 // Notes
-// "1.5" and "-3" are lexed as identifiers, not numbers.
 // Path-like strings (e.g. "/Users/foo-bar") stay as single identifiers.
 #[inline]
 fn is_delimiter(c: char) -> bool {
     c.is_whitespace() || matches!(c, '(' | ')' | ':' | '>' | '<' | '=' | '"')
 }
 
+/// An optionally signed decimal such as `42`, `-3` or `1.5`.
+///
+/// Unit suffixes (`1.5G`, `-7d`) make the word an identifier; the predicate
+/// parsers split them off.
+fn is_number(word: &str) -> bool {
+    let unsigned = word.strip_prefix(['-', '+']).unwrap_or(word);
+    let (int, frac) = match unsigned.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (unsigned, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    digits(int) && frac.is_none_or(digits)
+}
+
+/// Characters a backslash escapes inside a word.
+#[inline]
+fn is_escapable(c: char) -> bool {
+    is_delimiter(c) || matches!(c, '\\' | '\'')
+}
+
 #[inline]
 fn classify_keyword(lexeme: &str) -> TokenKind {
     match lexeme.len() {
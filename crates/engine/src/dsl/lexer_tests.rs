@@ -1,40 +1,39 @@
 use super::{Token, TokenKind, lex};
 
-fn kinds_lexemes(input: &str) -> Vec<(TokenKind, &str)> {
-    lex(input).into_iter().map(|t| (t.kind, t.lexeme)).collect()
+fn assert_lexes(input: &str, expected: &[(TokenKind, &str)]) {
+    let tokens = lex(input);
+    let got: Vec<(TokenKind, &str)> = tokens.iter().map(|t| (t.kind, t.lexeme.as_ref())).collect();
+    assert_eq!(got, expected);
 }
 
 #[test]
 fn basic_ident_and_number() {
     use TokenKind::*;
-    assert_eq!(
-        kinds_lexemes("foo 123"),
-        vec![(Ident, "foo"), (Number, "123"), (Eof, "")]
-    );
+    assert_lexes("foo 123", &[(Ident, "foo"), (Number, "123"), (Eof, "")]);
 }
 
 #[test]
 fn keywords_are_case_insensitive() {
     use TokenKind::*;
-    assert_eq!(
-        kinds_lexemes("and AND Or not NOT"),
-        vec![
+    assert_lexes(
+        "and AND Or not NOT",
+        &[
             (And, "and"),
             (And, "AND"),
             (Or, "Or"),
             (Not, "not"),
             (Not, "NOT"),
             (Eof, ""),
-        ]
+        ],
     );
 }
 
 #[test]
 fn operators_and_punctuation() {
     use TokenKind::*;
-    assert_eq!(
-        kinds_lexemes("ext:pdf (a>1 AND b>=2) c<3 OR d<=4 e=5"),
-        vec![
+    assert_lexes(
+        "ext:pdf (a>1 AND b>=2) c<3 OR d<=4 e=5",
+        &[
             (Ident, "ext"),
             (Colon, ":"),
             (Ident, "pdf"),
@@ -58,7 +57,7 @@ fn operators_and_punctuation() {
             (Eq, "="),
             (Number, "5"),
             (Eof, ""),
-        ]
+        ],
     );
 }
 
@@ -91,9 +90,9 @@ fn unterminated_string_consumes_to_end() {
 fn bar_variants_behave_as_designed() {
     use TokenKind::*;
     // "a||b" is a single ident, "a || b" uses logical OR, single '|' stays in idents.
-    assert_eq!(
-        kinds_lexemes("a||b a || b a|b |"),
-        vec![
+    assert_lexes(
+        "a||b a || b a|b |",
+        &[
             (Ident, "a||b"),
             (Ident, "a"),
             (Or, "||"),
@@ -101,20 +100,72 @@ fn bar_variants_behave_as_designed() {
             (Ident, "a|b"),
             (Ident, "|"),
             (Eof, ""),
-        ]
+        ],
     );
 }
 
 #[test]
-fn dots_and_minus_stay_in_idents_not_numbers() {
+fn decimals_and_signs_are_numbers() {
     use TokenKind::*;
-    assert_eq!(
-        kinds_lexemes("1.5 -3 file-name.txt"),
-        vec![
-            (Ident, "1.5"),
-            (Ident, "-3"),
+    assert_lexes(
+        "1.5 -3 +2.25 1.5G -7d 1. .5 1.2.3 file-name.txt",
+        &[
+            (Number, "1.5"),
+            (Number, "-3"),
+            (Number, "+2.25"),
+            (Ident, "1.5G"),
+            (Ident, "-7d"),
+            (Ident, "1."),
+            (Ident, ".5"),
+            (Ident, "1.2.3"),
             (Ident, "file-name.txt"),
             (Eof, ""),
-        ]
+        ],
+    );
+}
+
+#[test]
+fn quoted_strings_resolve_escapes() {
+    use TokenKind::*;
+    assert_lexes(
+        r#""say \"hi\"" 'it\'s' "a\\b" "C:\dir""#,
+        &[
+            (String, r#"say "hi""#),
+            (String, "it's"),
+            (String, r"a\b"),
+            (String, r"C:\dir"),
+            (Eof, ""),
+        ],
+    );
+
+    // Spans cover the raw text, quotes and backslashes included.
+    let tokens = lex(r#"'a\'b'"#);
+    assert_eq!(tokens[0].span, 0..6);
+    assert!(matches!(tokens[0].lexeme, std::borrow::Cow::Owned(_)));
+}
+
+#[test]
+fn single_quotes_hold_double_quotes() {
+    use TokenKind::*;
+    assert_lexes(
+        r#"'say "hi"' don't"#,
+        &[(String, r#"say "hi""#), (Ident, "don't"), (Eof, "")],
+    );
+}
+
+#[test]
+fn backslash_escapes_delimiters_in_words() {
+    use TokenKind::*;
+    assert_lexes(
+        r"my\ file.txt a\:b 12\ 3 c:\x",
+        &[
+            (Ident, "my file.txt"),
+            (Ident, "a:b"),
+            (Ident, "12 3"),
+            (Ident, "c"),
+            (Colon, ":"),
+            (Ident, r"\x"),
+            (Eof, ""),
+        ],
     );
 }
@@ -2,8 +2,11 @@ mod ast;
 mod lexer;
 mod parser;
 mod predicates;
+mod refine;
+mod rewrite;
 
 pub use ast::*;
 pub use lexer::{Token, TokenKind};
 pub use parser::parse_query;
 pub use predicates::*;
+pub use refine::is_refinement;
@@ -1,11 +1,15 @@
-use crate::dsl::ast::{LeafExpr, Query, QueryExpr, TextTerm};
+use std::borrow::Cow;
+
+use crate::dsl::ast::{LeafExpr, Query, QueryExpr, TextAnchor, TextTerm};
 use crate::dsl::lexer::{Token, TokenKind, lex};
-use crate::dsl::predicates::parse_field_predicate;
+use crate::dsl::predicates::{
+    CLEAN_NOISE, noise_predicate, parse_field_predicate, parse_noise_categories,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) enum RawAtom<'a> {
     Field {
-        field_name: &'a str,
+        field_name: Cow<'a, str>,
         value_tokens: Vec<Token<'a>>,
     },
     Bare {
@@ -33,7 +37,7 @@ impl<'a> Parser<'a> {
     fn advance(&mut self) -> Token<'a> {
         let tok = self.tokens.get(self.pos).cloned().unwrap_or(Token {
             kind: TokenKind::Eof,
-            lexeme: "",
+            lexeme: Cow::Borrowed(""),
             span: 0..0,
         });
         self.pos += 1;
@@ -120,7 +124,7 @@ impl<'a> Parser<'a> {
             }
             _ => {
                 let atom = self.parse_raw_atom();
-                QueryExpr::Leaf(resolve_atom(atom))
+                resolve_atom(atom)
             }
         }
     }
@@ -135,7 +139,23 @@ impl<'a> Parser<'a> {
 
         if self.peek() == TokenKind::Ident && next_kind == TokenKind::Colon {
             let field_tok = self.advance(); // IDENT
-            self.advance(); // Colon
+            let colon_tok = self.advance(); // Colon
+
+            // `clean:` is a macro whose value is optional, so it only takes a
+            // value written flush against the colon: `clean: report.pdf` keeps
+            // `report.pdf` as a search term.
+            if field_tok.lexeme.eq_ignore_ascii_case(CLEAN_MACRO) {
+                let mut value_tokens = Vec::new();
+                if self.peek() == TokenKind::Ident
+                    && self.tokens[self.pos].span.start == colon_tok.span.end
+                {
+                    value_tokens.push(self.advance());
+                }
+                return RawAtom::Field {
+                    field_name: field_tok.lexeme,
+                    value_tokens,
+                };
+            }
 
             // For field predicates, consume:
             // - Optional comparison operator (>, <, >=, <=, =)
@@ -160,6 +180,26 @@ impl<'a> Parser<'a> {
                 value_tokens.push(self.advance());
             }
 
+            // Times of day carry colons of their own: `2024-05-01T10:30`
+            // lexes as `2024-05-01T10`, `:`, `30`. Keep the pieces written
+            // flush together in the value.
+            if TIME_FIELDS
+                .iter()
+                .any(|f| field_tok.lexeme.eq_ignore_ascii_case(f))
+            {
+                while let Some(last) = value_tokens.last()
+                    && last.kind != TokenKind::String
+                    && self.peek() == TokenKind::Colon
+                    && self.tokens[self.pos].span.start == last.span.end
+                    && let Some(next) = self.tokens.get(self.pos + 1)
+                    && matches!(next.kind, TokenKind::Ident | TokenKind::Number)
+                    && next.span.start == self.tokens[self.pos].span.end
+                {
+                    value_tokens.push(self.advance()); // Colon
+                    value_tokens.push(self.advance());
+                }
+            }
+
             RawAtom::Field {
                 field_name: field_tok.lexeme,
                 value_tokens,
@@ -191,31 +231,67 @@ pub fn parse_query(input: &str) -> Query {
     Query { expr }
 }
 
-/// Resolve a RawAtom into a typed leaf: predicate or text term.
-fn resolve_atom(atom: RawAtom<'_>) -> LeafExpr {
+/// Field name of the noise-excluding macro.
+const CLEAN_MACRO: &str = "clean";
+
+/// Fields whose values may contain a time of day.
+const TIME_FIELDS: [&str; 2] = ["created", "modified"];
+
+/// Resolve a RawAtom into an expression: a predicate or text leaf, or the
+/// expansion of a macro such as `clean:`.
+fn resolve_atom(atom: RawAtom<'_>) -> QueryExpr {
     match atom {
         RawAtom::Field {
             field_name,
             value_tokens,
         } => {
             let field_name_lc = field_name.to_ascii_lowercase();
-            let pred = parse_field_predicate(&field_name_lc, &value_tokens);
+            if field_name_lc == CLEAN_MACRO
+                && let Some(expr) = expand_clean(&value_tokens)
+            {
+                return expr;
+            }
 
-            match pred {
+            let leaf = match parse_field_predicate(&field_name_lc, &value_tokens) {
                 Some(p) => LeafExpr::Predicate(p),
-                None => LeafExpr::Text(text_from_field_atom(field_name, &value_tokens)),
-            }
+                None => LeafExpr::Text(text_from_field_atom(&field_name, &value_tokens)),
+            };
+            QueryExpr::Leaf(leaf)
         }
-        RawAtom::Bare { tokens } => LeafExpr::Text(text_from_tokens(&tokens)),
+        RawAtom::Bare { tokens } => QueryExpr::Leaf(LeafExpr::Text(text_from_tokens(&tokens))),
     }
 }
 
+/// Expand `clean:` (or `clean:build,log`) into `NOT noise:<categories>`.
+/// Returns `None` for unknown categories so the atom falls back to text.
+fn expand_clean(value_tokens: &[Token<'_>]) -> Option<QueryExpr> {
+    let flags = match value_tokens.first() {
+        None => CLEAN_NOISE,
+        Some(tok) => parse_noise_categories(&tok.lexeme)?,
+    };
+    let leaf = QueryExpr::Leaf(LeafExpr::Predicate(noise_predicate(flags)));
+    Some(QueryExpr::Not(Box::new(leaf)))
+}
+
 pub(crate) fn text_from_tokens(tokens: &[Token<'_>]) -> TextTerm {
     if tokens.is_empty() {
         return TextTerm {
             text: String::new(),
             is_phrase: false,
             is_glob: false,
+            anchor: TextAnchor::Anywhere,
+        };
+    }
+
+    if let [tok] = tokens
+        && tok.kind == TokenKind::Ident
+        && let Some((text, anchor)) = split_anchor(&tok.lexeme)
+    {
+        return TextTerm {
+            is_phrase: false,
+            is_glob: text.contains('*') || text.contains('?'),
+            text: text.to_owned(),
+            anchor,
         };
     }
 
@@ -225,7 +301,7 @@ pub(crate) fn text_from_tokens(tokens: &[Token<'_>]) -> TextTerm {
         if i > 0 {
             text.push(' ');
         }
-        text.push_str(t.lexeme);
+        text.push_str(&t.lexeme);
     }
 
     let first_kind = tokens[0].kind;
@@ -233,9 +309,33 @@ pub(crate) fn text_from_tokens(tokens: &[Token<'_>]) -> TextTerm {
         is_phrase: matches!(first_kind, TokenKind::String),
         is_glob: text.contains('*') || text.contains('?'),
         text,
+        anchor: TextAnchor::Anywhere,
     }
 }
 
+/// Split a leading `^` and a trailing `$` off a bare word.
+///
+/// Anchors apply to the file name, so words with a `/`, or with nothing
+/// left between the anchors, stay plain text; so does anything quoted,
+/// which is how to search for a literal `^` or `$`.
+fn split_anchor(word: &str) -> Option<(&str, TextAnchor)> {
+    let (word, start) = match word.strip_prefix('^') {
+        Some(rest) => (rest, true),
+        None => (word, false),
+    };
+    let (word, end) = match word.strip_suffix('$') {
+        Some(rest) => (rest, true),
+        None => (word, false),
+    };
+    let anchor = match (start, end) {
+        (false, false) => return None,
+        (true, false) => TextAnchor::Start,
+        (false, true) => TextAnchor::End,
+        (true, true) => TextAnchor::Whole,
+    };
+    (!word.is_empty() && !word.contains('/')).then_some((word, anchor))
+}
+
 fn text_from_field_atom(field_name: &str, value_tokens: &[Token<'_>]) -> TextTerm {
     let mut s = String::new();
     s.push_str(field_name);
@@ -244,13 +344,14 @@ fn text_from_field_atom(field_name: &str, value_tokens: &[Token<'_>]) -> TextTer
         if i > 0 {
             s.push(' ');
         }
-        s.push_str(t.lexeme);
+        s.push_str(&t.lexeme);
     }
 
     TextTerm {
         is_phrase: false,
         is_glob: s.contains('*') || s.contains('?'),
         text: s,
+        anchor: TextAnchor::Anywhere,
     }
 }
 
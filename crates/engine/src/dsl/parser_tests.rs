@@ -1,5 +1,7 @@
 use super::parse_query;
-use crate::dsl::ast::{CmpOp, Field, LeafExpr, QueryExpr, Value};
+use crate::dsl::ast::{
+    CmpOp, Field, LeafExpr, QueryExpr, RelativeTime, TextAnchor, TimeExpr, Value,
+};
 
 fn expr(input: &str) -> QueryExpr {
     parse_query(input).expr
@@ -26,6 +28,13 @@ fn is_glob(expr: &QueryExpr) -> bool {
     }
 }
 
+fn anchor(expr: &QueryExpr) -> TextAnchor {
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Text(term)) => term.anchor,
+        _ => panic!("expected text leaf, got {:?}", expr),
+    }
+}
+
 fn predicate_leaf(expr: &QueryExpr) -> &crate::dsl::predicates::Predicate {
     match expr {
         QueryExpr::Leaf(LeafExpr::Predicate(p)) => p,
@@ -251,6 +260,12 @@ fn ext_field_parses_to_predicate() {
     }
 }
 
+#[test]
+fn name_field_uses_smart_case() {
+    assert_eq!(predicate_leaf(&expr("name:*.rs")).field, Field::IName);
+    assert_eq!(predicate_leaf(&expr("name:Cargo.*")).field, Field::Name);
+}
+
 #[test]
 fn size_field_with_gt_operator_parses_to_predicate() {
     let q = expr("size:>10");
@@ -264,3 +279,166 @@ fn size_field_with_gt_operator_parses_to_predicate() {
         other => panic!("expected Value::SizeBytes(_), got {:?}", other),
     }
 }
+
+#[test]
+fn size_field_accepts_decimals() {
+    for input in ["size:>1.5G", "size: > 1.5G", "size:>1536M"] {
+        let q = expr(input);
+        let p = predicate_leaf(&q);
+        assert_eq!(p.op, CmpOp::Gt, "input: {input}");
+        assert!(
+            matches!(p.value, Value::SizeBytes(v) if v == 3 << 29),
+            "input: {input}, got {:?}",
+            p.value
+        );
+    }
+}
+
+#[test]
+fn signed_relative_times_pick_the_comparison() {
+    for (input, op) in [
+        ("modified:7d", CmpOp::Ge),
+        ("modified:-7d", CmpOp::Ge),
+        ("modified:+7d", CmpOp::Lt),
+        ("modified:<-7d", CmpOp::Lt),
+    ] {
+        let q = expr(input);
+        let p = predicate_leaf(&q);
+        assert_eq!(p.op, op, "input: {input}");
+        assert!(
+            matches!(
+                p.value,
+                Value::Time(TimeExpr::Relative(RelativeTime::Days(7)))
+            ),
+            "input: {input}, got {:?}",
+            p.value
+        );
+    }
+}
+
+#[test]
+fn time_of_day_values_keep_their_colons() {
+    let want = chrono::DateTime::parse_from_rfc3339("2024-05-01T08:30:00Z")
+        .unwrap()
+        .to_utc();
+    for (input, op) in [
+        ("modified:2024-05-01T10:30:00+02:00", CmpOp::Ge),
+        ("modified:<2024-05-01T08:30:00Z", CmpOp::Lt),
+        ("created:>=\"2024-05-01T08:30:00Z\"", CmpOp::Ge),
+    ] {
+        let q = expr(input);
+        let p = predicate_leaf(&q);
+        assert_eq!(p.op, op, "input: {input}");
+        assert!(
+            matches!(p.value, Value::Time(TimeExpr::Absolute(dt)) if dt == want),
+            "input: {input}, got {:?}",
+            p.value
+        );
+    }
+
+    // Unquoted, a time after a space is not part of the date.
+    match expr("modified:2024-05-01 10:30") {
+        QueryExpr::And(children) => {
+            let p = predicate_leaf(&children[0]);
+            assert!(matches!(p.value, Value::Time(TimeExpr::Absolute(_))));
+        }
+        other => panic!("expected And, got {:?}", other),
+    }
+}
+
+#[test]
+fn ext_field_normalizes_case_dot_and_aliases() {
+    for input in ["ext:.PDF", "ext:Pdf"] {
+        let q = expr(input);
+        match &predicate_leaf(&q).value {
+            Value::Str(s) => assert_eq!(s, "pdf", "input: {input}"),
+            other => panic!("expected Value::Str(\"pdf\"), got {:?}", other),
+        }
+    }
+
+    for input in ["ext:jpeg", "ext:JPG", "ext:.jpe"] {
+        let q = expr(input);
+        match &predicate_leaf(&q).value {
+            Value::Str(s) => assert_eq!(s, "jpg", "input: {input}"),
+            other => panic!("expected Value::Str(\"jpg\"), got {:?}", other),
+        }
+    }
+}
+
+fn clean_flags(expr: &QueryExpr) -> crate::flags::NoiseFlags {
+    let QueryExpr::Not(inner) = expr else {
+        panic!("expected NOT expansion, got {:?}", expr);
+    };
+    let p = predicate_leaf(inner);
+    assert_eq!(p.field, Field::Noise);
+    match p.value {
+        Value::Noise(flags) => flags,
+        ref other => panic!("expected Value::Noise(_), got {:?}", other),
+    }
+}
+
+#[test]
+fn clean_macro_leaves_spaced_term_as_text() {
+    use crate::dsl::predicates::CLEAN_NOISE;
+
+    let q = expr("clean: report.pdf");
+    let QueryExpr::And(children) = &q else {
+        panic!("expected AND, got {:?}", q);
+    };
+    assert_eq!(children.len(), 2);
+    assert_eq!(clean_flags(&children[0]), CLEAN_NOISE);
+    assert_eq!(text_leaf(&children[1]), "report.pdf");
+
+    assert_eq!(clean_flags(&expr("CLEAN:")), CLEAN_NOISE);
+}
+
+#[test]
+fn clean_macro_accepts_adjacent_categories() {
+    use crate::flags::NoiseFlags;
+
+    assert_eq!(
+        clean_flags(&expr("clean:build,log")),
+        NoiseFlags::BUILD_DIR | NoiseFlags::LOG_DIR
+    );
+    // Unknown categories fall back to plain text, like other bad predicates.
+    assert_eq!(text_leaf(&expr("clean:nope")), "clean:nope");
+}
+
+#[test]
+fn escaped_quotes_in_field_values() {
+    let e = expr(r#"name:"weird \"file\".txt""#);
+    let p = predicate_leaf(&e);
+    assert_eq!(p.field, Field::IName);
+    assert!(matches!(&p.value, Value::Str(s) if s == r#"weird "file".txt"#));
+
+    let e = expr(r#"name:'it\'s here.txt'"#);
+    assert!(matches!(&predicate_leaf(&e).value, Value::Str(s) if s == "it's here.txt"));
+}
+
+#[test]
+fn single_quoted_and_escaped_terms_are_one_text_leaf() {
+    let e = expr("'my notes'");
+    assert_eq!(text_leaf(&e), "my notes");
+    assert!(is_phrase(&e));
+
+    assert_eq!(text_leaf(&expr(r"my\ notes")), "my notes");
+}
+
+#[test]
+fn carets_and_dollars_anchor_bare_words() {
+    for (input, text, expected) in [
+        ("^Makefile", "Makefile", TextAnchor::Start),
+        (".rs$", ".rs", TextAnchor::End),
+        ("^Cargo.toml$", "Cargo.toml", TextAnchor::Whole),
+        ("foo", "foo", TextAnchor::Anywhere),
+        // Quoted, path-like or empty: kept as written.
+        ("\"^foo\"", "^foo", TextAnchor::Anywhere),
+        ("^src/main", "^src/main", TextAnchor::Anywhere),
+        ("^$", "^$", TextAnchor::Anywhere),
+        ("a^b$c", "a^b$c", TextAnchor::Anywhere),
+    ] {
+        let q = expr(input);
+        assert_eq!(text_leaf(&q), text, "{input}");
+        assert_eq!(anchor(&q), expected, "{input}");
+    }
+}
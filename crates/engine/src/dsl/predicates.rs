@@ -1,5 +1,9 @@
 use crate::dsl::{CmpOp, Field, RelativeTime, TimeExpr, TimeMacro, Token, TokenKind, Value};
-use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use crate::flags::NoiseFlags;
+pub use blaze_runtime::parse_size;
+use blaze_runtime::{TIMEZONE_ENV, canonical_ext, lang_by_name, language, normalize_ext};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use globset::{GlobBuilder, GlobMatcher};
 
 #[derive(Debug)]
 enum DateParseError {
@@ -7,7 +11,7 @@ enum DateParseError {
     InvalidDate,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Predicate {
     pub field: Field,
     pub op: CmpOp,
@@ -21,7 +25,13 @@ pub(crate) fn parse_field_predicate(
     match field_name.to_ascii_lowercase().as_str() {
         "created" => parse_created_predicate(value_tokens),
         "ext" => parse_ext_predicate(value_tokens),
+        "hash" => parse_hash_predicate(value_tokens),
+        "lang" => parse_lang_predicate(value_tokens),
         "modified" => parse_modified_predicate(value_tokens),
+        "name" => parse_name_predicate(value_tokens),
+        "noise" => parse_noise_predicate(value_tokens),
+        "pinned" => parse_pinned_predicate(value_tokens),
+        "repo" => parse_repo_predicate(value_tokens),
         "size" => parse_size_predicate(value_tokens),
         _ => None,
     }
@@ -30,28 +40,192 @@ pub(crate) fn parse_field_predicate(
 fn join_lexemes(tokens: &[Token<'_>]) -> String {
     let mut s = String::new();
     for t in tokens {
-        s.push_str(t.lexeme);
+        s.push_str(&t.lexeme);
     }
     s
 }
 
+/// Parse `ext:` values.
+///
+/// The value is normalized the same way the walker and builder normalize
+/// extensions, then resolved through the alias table so that `ext:jpeg`
+/// and `ext:jpg` produce the same predicate.
 fn parse_ext_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
     let tok = value_tokens.first()?;
-    let mut ext = tok.lexeme.trim();
+    let ext = normalize_ext(&tok.lexeme)?;
+
+    Some(Predicate {
+        field: Field::Ext,
+        op: CmpOp::Eq,
+        value: Value::Str(canonical_ext(&ext).to_owned()),
+    })
+}
 
-    if let Some(stripped) = ext.strip_prefix('.') {
-        ext = stripped;
+/// Parse `lang:` values.
+///
+/// Aliases resolve to the canonical language name (`lang:py` is
+/// `lang:python`). Unknown names are kept as-is and match nothing.
+fn parse_lang_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    let raw = tok.lexeme.trim();
+    if raw.is_empty() {
+        return None;
     }
-    if ext.is_empty() {
+
+    let name = match lang_by_name(raw).and_then(language) {
+        Some(lang) => lang.name.to_owned(),
+        None => raw.to_ascii_lowercase(),
+    };
+
+    Some(Predicate {
+        field: Field::Lang,
+        op: CmpOp::Eq,
+        value: Value::Str(name),
+    })
+}
+
+/// Parse `name:` values.
+///
+/// The value is a glob over the file name (`*`, `?`, `[...]`). Matching is
+/// case-insensitive unless the pattern contains an uppercase letter.
+fn parse_name_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    let pattern = tok.lexeme.trim();
+    if pattern.is_empty() {
         return None;
     }
 
-    let ext_lower = ext.to_ascii_lowercase();
+    let field = if pattern.chars().any(char::is_uppercase) {
+        Field::Name
+    } else {
+        Field::IName
+    };
+    name_predicate(field, pattern)
+}
+
+/// Build a `Field::Name` or `Field::IName` predicate, validating the glob.
+pub fn name_predicate(field: Field, pattern: &str) -> Option<Predicate> {
+    compile_name_glob(pattern, field == Field::IName)?;
 
     Some(Predicate {
-        field: Field::Ext,
+        field,
         op: CmpOp::Eq,
-        value: Value::Str(ext_lower),
+        value: Value::Str(pattern.to_owned()),
+    })
+}
+
+/// Compile a file name glob. `*` and `?` also match a leading `.`, as in `find`.
+pub(crate) fn compile_name_glob(pattern: &str, case_insensitive: bool) -> Option<GlobMatcher> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .backslash_escape(true)
+        .build()
+        .ok()
+        .map(|g| g.compile_matcher())
+}
+
+/// Whether a name glob has no wildcards, so it only matches the name it
+/// spells.
+pub(crate) fn is_literal_name(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '[', ']', '{', '}', '\\'])
+}
+
+/// Parse `repo:` values.
+///
+/// The value is either a repository name (the name of the directory holding
+/// `.git`) or, if it starts with `/`, the absolute path of a repository root.
+fn parse_repo_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    let value = tok.lexeme.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    Some(Predicate {
+        field: Field::Repo,
+        op: CmpOp::Eq,
+        value: Value::Str(value.to_owned()),
+    })
+}
+
+/// Parse `pinned:` values: `yes`/`true`/`1` or `no`/`false`/`0`.
+fn parse_pinned_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    let pinned = match tok.lexeme.trim().to_ascii_lowercase().as_str() {
+        "yes" | "true" | "1" => true,
+        "no" | "false" | "0" => false,
+        _ => return None,
+    };
+
+    Some(Predicate {
+        field: Field::Pinned,
+        op: CmpOp::Eq,
+        value: Value::Bool(pinned),
+    })
+}
+
+/// Noise categories `clean:` leaves out when given no value.
+pub const CLEAN_NOISE: NoiseFlags = NoiseFlags::BUILD_DIR
+    .union(NoiseFlags::CACHE_DIR)
+    .union(NoiseFlags::LOG_DIR)
+    .union(NoiseFlags::SYSTEM_DIR)
+    .union(NoiseFlags::GENERATED);
+
+/// Parse a comma-separated list of noise categories such as `build,cache`.
+pub(crate) fn parse_noise_categories(value: &str) -> Option<NoiseFlags> {
+    let mut flags = NoiseFlags::empty();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        flags |= match name.to_ascii_lowercase().as_str() {
+            "build" => NoiseFlags::BUILD_DIR,
+            "cache" => NoiseFlags::CACHE_DIR,
+            "log" | "logs" => NoiseFlags::LOG_DIR,
+            "system" => NoiseFlags::SYSTEM_DIR,
+            "appdata" => NoiseFlags::APP_DATA_DIR,
+            "hashy" => NoiseFlags::HASHY_SEG,
+            "deep" => NoiseFlags::VERY_DEEP,
+            "generated" => NoiseFlags::GENERATED,
+            _ => return None,
+        };
+    }
+    (!flags.is_empty()).then_some(flags)
+}
+
+/// Build a predicate matching files with any of `flags`.
+pub fn noise_predicate(flags: NoiseFlags) -> Predicate {
+    Predicate {
+        field: Field::Noise,
+        op: CmpOp::Eq,
+        value: Value::Noise(flags),
+    }
+}
+
+/// Parse `noise:` values: one or more categories, comma-separated.
+fn parse_noise_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    parse_noise_categories(&tok.lexeme).map(noise_predicate)
+}
+
+/// Parse `hash:` values.
+///
+/// The value is either a content hash as 16 hex digits (the file's XXH3-64
+/// digest, as printed by `xxhsum -H3`) or, if it starts with
+/// `/`, the absolute path of an indexed file whose stored hash is used.
+fn parse_hash_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
+    let tok = value_tokens.first()?;
+    let value = tok.lexeme.trim();
+
+    let value = if value.starts_with('/') {
+        value.to_owned()
+    } else if value.len() == 16 && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        value.to_ascii_lowercase()
+    } else {
+        return None;
+    };
+
+    Some(Predicate {
+        field: Field::Hash,
+        op: CmpOp::Eq,
+        value: Value::Str(value),
     })
 }
 
@@ -113,22 +287,25 @@ fn parse_time_field_predicate(field: Field, value_tokens: &[Token<'_>]) -> Optio
         }
 
         let raw = tok.lexeme.trim();
-        if let Some(rt) = parse_relative_time_literal(raw) {
-            return Some(time_pred(field, CmpOp::Ge, TimeExpr::Relative(rt)));
+        if let Some((op, rt)) = parse_relative_time_literal(raw) {
+            return Some(time_pred(field, op, TimeExpr::Relative(rt)));
         }
     }
 
     let s = join_lexemes(value_tokens);
     let s = s.trim();
     let (op0, rest) = extract_cmp_op(s);
-    let op = if rest == s { CmpOp::Ge } else { op0 };
+    let explicit_op = rest != s;
+    let op = if explicit_op { op0 } else { CmpOp::Ge };
     let rest = rest.trim();
 
-    if let Ok(dt) = parse_ymd_date(rest) {
+    if let Ok(dt) = parse_datetime(rest, DateZone::from_env()) {
         return Some(time_pred(field, op, TimeExpr::Absolute(dt)));
     }
 
-    if let Some(rt) = parse_relative_time_literal(rest) {
+    // An explicit operator overrides the one a sign implies.
+    if let Some((implied, rt)) = parse_relative_time_literal(rest) {
+        let op = if explicit_op { op } else { implied };
         return Some(time_pred(field, op, TimeExpr::Relative(rt)));
     }
 
@@ -143,44 +320,102 @@ fn parse_created_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
     parse_time_field_predicate(Field::Created, value_tokens)
 }
 
-fn parse_ymd_date(s: &str) -> Result<DateTime<Utc>, DateParseError> {
-    let date =
-        NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| DateParseError::InvalidFormat)?;
-    let dt = date
-        .and_hms_opt(0, 0, 0)
-        .ok_or(DateParseError::InvalidDate)?;
-    Ok(Utc.from_utc_datetime(&dt))
+/// How query dates and times without an offset are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateZone {
+    /// In the local timezone of the process parsing the query.
+    #[default]
+    Local,
+    Utc,
 }
 
-/// Parses literals like '-7d', '2w', '3m', '1y'
-fn parse_relative_time_literal(s: &str) -> Option<RelativeTime> {
-    let s = s.trim();
-    if s.is_empty() {
-        return None;
+impl DateZone {
+    /// `Utc` if `BLAZE_TIMEZONE` is `utc`, `Local` otherwise.
+    pub fn from_env() -> Self {
+        match std::env::var(TIMEZONE_ENV) {
+            Ok(val) if val.eq_ignore_ascii_case("utc") => DateZone::Utc,
+            _ => DateZone::Local,
+        }
     }
 
-    let (sign, rest) = if let Some(r) = s.strip_prefix('-') {
-        (-1i64, r)
-    } else {
-        (1i64, s)
-    };
+    fn resolve(self, naive: NaiveDateTime) -> Result<DateTime<Utc>, DateParseError> {
+        match self {
+            DateZone::Utc => Ok(Utc.from_utc_datetime(&naive)),
+            // Clocks going back repeat an hour: take its first pass. Clocks
+            // going forward skip one: read the time an hour later.
+            DateZone::Local => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .or_else(|| {
+                    Local
+                        .from_local_datetime(&(naive + Duration::hours(1)))
+                        .earliest()
+                })
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok_or(DateParseError::InvalidDate),
+        }
+    }
+}
 
-    if rest.len() < 2 {
-        return None;
+/// Formats of an absolute time without an offset, most specific first.
+const NAIVE_DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Parse an absolute time: RFC 3339 (`2024-05-01T10:30:00+02:00`), a date
+/// and time (`2024-05-01 10:30`, seconds optional) or a bare date, which
+/// means its midnight. Values without an offset are read in `zone`.
+fn parse_datetime(s: &str, zone: DateZone) -> Result<DateTime<Utc>, DateParseError> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
     }
 
-    let (num_str, unit_str) = rest.split_at(rest.len() - 1);
-    let n: i64 = num_str.trim().parse().ok()?;
-    let n = n * sign;
+    let naive = NAIVE_DATETIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or(DateParseError::InvalidFormat)?;
+    zone.resolve(naive)
+}
+
+/// Parses literals like '7d', '-7d', '+2w', '3h', '3mo', '1y' into a
+/// duration and the comparison the literal implies on its own.
+///
+/// As with `find -mtime`, `7d` and `-7d` mean "within the last 7 days" and
+/// `+7d` means "more than 7 days ago". File times have no use for minutes,
+/// so `m` is a month, like `mo`.
+fn parse_relative_time_literal(s: &str) -> Option<(CmpOp, RelativeTime)> {
+    let s = s.trim();
+    let (op, rest) = match s.as_bytes().first()? {
+        b'-' => (CmpOp::Ge, &s[1..]),
+        b'+' => (CmpOp::Lt, &s[1..]),
+        _ => (CmpOp::Ge, s),
+    };
+
+    let unit_start = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&i| i > 0)?;
+    let (num_str, unit_str) = rest.split_at(unit_start);
+    let n: i64 = num_str.parse().ok()?;
     let unit = unit_str.to_ascii_lowercase();
 
-    match unit.as_str() {
-        "d" => Some(RelativeTime::Days(n)),
-        "h" => Some(RelativeTime::Hours(n)),
-        "w" => Some(RelativeTime::Weeks(n)),
-        "y" => Some(RelativeTime::Years(n)),
-        _ => None,
-    }
+    let rt = match unit.as_str() {
+        "d" => RelativeTime::Days(n),
+        "h" => RelativeTime::Hours(n),
+        "w" => RelativeTime::Weeks(n),
+        "m" | "mo" => RelativeTime::Months(n),
+        "y" => RelativeTime::Years(n),
+        _ => return None,
+    };
+    Some((op, rt))
 }
 
 /// Parses the size predicates
@@ -214,90 +449,6 @@ fn parse_size_predicate(value_tokens: &[Token<'_>]) -> Option<Predicate> {
     })
 }
 
-/// Detects if a unit suffix indicates bits using smartcasing.
-///
-/// This is very similar to how Vim smartcasing operates. The goal
-/// is to allow the user to use units like Mega[BIT]s or Mega[Byte]s.
-/// Smartcase logic:
-///  1) All lowercase (i.e. `mb`, `kb`) will be interpreted as bytes
-///  2) Prefix starst with uppercase + lowercase `b` (e.g., `Mb`, `Kb`) will be interpreted as bits
-///  3) All uppercase (e.g., `MB`, `KB`) will be interpreted as bytes
-///  4) No `b` or `B` suffix will default to bytes
-#[inline]
-fn is_bits_unit(unit: &[u8]) -> bool {
-    let Some(&last) = unit.last() else {
-        return false;
-    };
-
-    last == b'b' && unit.len() > 1 && unit[0].is_ascii_uppercase()
-}
-
-const KIB: u64 = 1024;
-const MIB: u64 = KIB * 1024;
-const GIB: u64 = MIB * 1024;
-const TIB: u64 = GIB * 1024;
-
-/// Parse sizes like "10MB", "500k", "5G", "10Mb" into **bytes**.
-/// Prefix letters K/M/G/T (optionally with 'i' for KiB/MiB/etc.) use 1024-based multipliers.
-/// No unit means raw bytes.
-fn parse_size(s: &str) -> Option<u64> {
-    let s = s.trim();
-    if s.is_empty() {
-        return None;
-    }
-
-    let bytes = s.as_bytes();
-
-    // Split into number part and unit part by scanning backwards for alphabetic chars.
-    let mut split = bytes.len();
-    for i in (0..bytes.len()).rev() {
-        if bytes[i].is_ascii_alphabetic() {
-            split = i;
-        } else {
-            break;
-        }
-    }
-
-    let (num_bytes, unit_bytes) = bytes.split_at(split);
-
-    // Parse number from the byte slice (safe since digits are ASCII).
-    let num_str = std::str::from_utf8(num_bytes).ok()?.trim();
-    let num: u64 = num_str.parse().ok()?;
-
-    if unit_bytes.is_empty() {
-        return Some(num); // raw bytes, no unit
-    }
-
-    let is_bits = is_bits_unit(unit_bytes);
-
-    let last = *unit_bytes.last().unwrap(); // safe: not empty
-    let prefix_bytes = if last == b'b' || last == b'B' {
-        &unit_bytes[..unit_bytes.len() - 1]
-    } else {
-        unit_bytes
-    };
-
-    let mut lower = prefix_bytes.to_vec();
-    lower.make_ascii_lowercase();
-
-    let factor: u64 = match lower.as_slice() {
-        b"" => 1,
-        b"k" | b"ki" => KIB,
-        b"m" | b"mi" => MIB,
-        b"g" | b"gi" => GIB,
-        b"t" | b"ti" => TIB,
-        _ => return None,
-    };
-
-    let value = num.saturating_mul(factor);
-
-    if is_bits {
-        Some(value / 8)
-    } else {
-        Some(value)
-    }
-}
-
 #[cfg(test)]
 #[path = "predicates_tests.rs"]
 mod tests;
@@ -43,8 +43,8 @@ fn parse_time_macro_recognizes_macros() {
 }
 
 #[test]
-fn parse_ymd_date_parses_valid_date_at_midnight_utc() {
-    let dt = parse_ymd_date("2025-11-30").expect("valid date");
+fn parse_datetime_parses_bare_date_at_midnight() {
+    let dt = parse_datetime("2025-11-30", DateZone::Utc).expect("valid date");
     assert_eq!(dt.year(), 2025);
     assert_eq!(dt.month(), 11);
     assert_eq!(dt.day(), 30);
@@ -55,117 +55,113 @@ fn parse_ymd_date_parses_valid_date_at_midnight_utc() {
 }
 
 #[test]
-fn parse_ymd_date_rejects_invalid_format() {
-    match parse_ymd_date("not-a-date") {
+fn parse_datetime_rejects_invalid_format() {
+    match parse_datetime("not-a-date", DateZone::Utc) {
         Err(DateParseError::InvalidFormat) => {}
         other => panic!("expected InvalidFormat, got {:?}", other),
     }
 
     // Structurally OK but invalid date.
-    match parse_ymd_date("2025-02-30") {
+    match parse_datetime("2025-02-30", DateZone::Utc) {
         Err(DateParseError::InvalidFormat) => {}
         other => panic!("expected InvalidFormat for invalid date, got {:?}", other),
     }
+
+    match parse_datetime("2025-11-30 25:00", DateZone::Utc) {
+        Err(DateParseError::InvalidFormat) => {}
+        other => panic!("expected InvalidFormat for invalid time, got {:?}", other),
+    }
 }
 
 #[test]
-fn parse_relative_time_literal_parses_supported_units() {
-    let cases: &[(&str, Option<RelativeTime>)] = &[
-        ("7d", Some(RelativeTime::Days(7))),
-        ("-7d", Some(RelativeTime::Days(-7))),
-        ("3h", Some(RelativeTime::Hours(3))),
-        ("2w", Some(RelativeTime::Weeks(2))),
-        ("1y", Some(RelativeTime::Years(1))),
-        ("  10d  ", Some(RelativeTime::Days(10))),
-        ("", None),
-        ("   ", None),
-        ("x", None),
-        ("1", None),
-        ("d", None),
-        // We don't interpret +5d as "five days into the future"
-        // because it doesn't make logical sense
-        ("+5d", Some(RelativeTime::Days(5))),
-        ("5q", None),
-    ];
-
-    for (input, expected) in cases {
-        let got = parse_relative_time_literal(input);
-        assert_eq!(got, *expected, "input: {:?}", input);
+fn parse_datetime_accepts_time_of_day() {
+    let want = Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap();
+    for input in [
+        "2024-05-01 10:30",
+        "2024-05-01T10:30",
+        "2024-05-01 10:30:00",
+        "2024-05-01T10:30:00",
+    ] {
+        let got = parse_datetime(input, DateZone::Utc).expect(input);
+        assert_eq!(got, want, "input: {input:?}");
     }
 }
 
 #[test]
-fn is_bits_unit_smartcase_behavior() {
-    let cases: &[(&[u8], bool)] = &[
-        (b"", false),
-        (b"b", false),
-        (b"kb", false),
-        (b"mb", false),
-        (b"MB", false),
-        (b"Kb", true),
-        (b"Mb", true),
-        (b"Gb", true),
-        (b"Tb", true),
-        (b"KiB", false),
-        (b"MiB", false),
-        (b"Kib", true),
-    ];
-
-    for (unit, expected) in cases {
-        let got = is_bits_unit(unit);
-        assert_eq!(got, *expected, "unit: {:?}", std::str::from_utf8(unit));
+fn parse_datetime_honours_rfc3339_offsets_in_any_zone() {
+    let want = Utc.with_ymd_and_hms(2024, 5, 1, 8, 30, 0).unwrap();
+    for zone in [DateZone::Utc, DateZone::Local] {
+        assert_eq!(
+            parse_datetime("2024-05-01T10:30:00+02:00", zone).unwrap(),
+            want
+        );
+        assert_eq!(parse_datetime("2024-05-01T08:30:00Z", zone).unwrap(), want);
     }
 }
 
 #[test]
-fn parse_size_parses_raw_bytes_and_units() {
-    let cases: &[(&str, Option<u64>)] = &[
-        ("0", Some(0)),
-        ("10", Some(10)),
-        ("  10  ", Some(10)),
-        ("10k", Some(10 * KIB)),
-        ("10K", Some(10 * KIB)),
-        ("10kb", Some(10 * KIB)),
-        ("10KB", Some(10 * KIB)),
-        ("10Ki", Some(10 * KIB)),
-        ("10KiB", Some(10 * KIB)),
-        ("1m", Some(1 * MIB)),
-        ("1M", Some(1 * MIB)),
-        ("1Mi", Some(1 * MIB)),
-        ("1MiB", Some(1 * MIB)),
-        ("2g", Some(2 * GIB)),
-        ("2G", Some(2 * GIB)),
-        ("2GiB", Some(2 * GIB)),
-        ("3t", Some(3 * TIB)),
-        ("3T", Some(3 * TIB)),
-        ("3Ti", Some(3 * TIB)),
-        ("3TiB", Some(3 * TIB)),
+fn parse_datetime_reads_naive_values_in_local_time() {
+    let naive = NaiveDate::from_ymd_opt(2024, 5, 1)
+        .unwrap()
+        .and_hms_opt(10, 30, 0)
+        .unwrap();
+    let want = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap()
+        .with_timezone(&Utc);
+    assert_eq!(
+        parse_datetime("2024-05-01 10:30", DateZone::Local).unwrap(),
+        want
+    );
+}
+
+#[test]
+fn parse_relative_time_literal_parses_supported_units() {
+    use CmpOp::{Ge, Lt};
+
+    let cases: &[(&str, Option<(CmpOp, RelativeTime)>)] = &[
+        ("7d", Some((Ge, RelativeTime::Days(7)))),
+        ("3h", Some((Ge, RelativeTime::Hours(3)))),
+        ("2w", Some((Ge, RelativeTime::Weeks(2)))),
+        ("1y", Some((Ge, RelativeTime::Years(1)))),
+        ("  10d  ", Some((Ge, RelativeTime::Days(10)))),
+        // Signs work like `find -mtime`: `-` is newer than, `+` older than.
+        ("-7d", Some((Ge, RelativeTime::Days(7)))),
+        ("+5d", Some((Lt, RelativeTime::Days(5)))),
+        // `m` is months; there is no minutes unit.
+        ("3m", Some((Ge, RelativeTime::Months(3)))),
+        ("3mo", Some((Ge, RelativeTime::Months(3)))),
+        ("+12MO", Some((Lt, RelativeTime::Months(12)))),
+        ("3min", None),
+        ("--7d", None),
+        ("+-7d", None),
         ("", None),
         ("   ", None),
-        ("abc", None),
-        ("10x", None),
+        ("x", None),
+        ("1", None),
+        ("d", None),
+        ("-d", None),
+        ("5q", None),
     ];
 
     for (input, expected) in cases {
-        let got = parse_size(input);
+        let got = parse_relative_time_literal(input);
         assert_eq!(got, *expected, "input: {:?}", input);
     }
 }
 
 #[test]
-fn parse_size_handles_bits_via_smartcase() {
-    let one_mib_bytes = MIB;
-    let expected_one_megabit_bytes = one_mib_bytes / 8;
-
-    let cases: &[(&str, Option<u64>)] = &[
-        ("1Mb", Some(expected_one_megabit_bytes)),
-        ("1mb", Some(1 * MIB)),
-        ("1MB", Some(1 * MIB)),
-        ("8Kb", Some(KIB)),
-    ];
-
-    for (input, expected) in cases {
-        let got = parse_size(input);
-        assert_eq!(got, *expected, "input: {:?}", input);
-    }
+fn noise_categories_parse_comma_lists() {
+    assert_eq!(
+        parse_noise_categories("Cache, logs"),
+        Some(NoiseFlags::CACHE_DIR | NoiseFlags::LOG_DIR)
+    );
+    assert_eq!(parse_noise_categories("deep"), Some(NoiseFlags::VERY_DEEP));
+    assert_eq!(
+        parse_noise_categories("generated"),
+        Some(NoiseFlags::GENERATED)
+    );
+    assert_eq!(parse_noise_categories(""), None);
+    assert_eq!(parse_noise_categories("build,bogus"), None);
 }
@@ -0,0 +1,73 @@
+use crate::dsl::ast::{LeafExpr, Query, QueryExpr, TextAnchor, TextTerm};
+
+/// Whether every file matching `next` also matches `prev`, judged from the
+/// queries alone.
+///
+/// True when `next` is `prev` typed further: its last text term extended,
+/// more terms added after it, or both. Such a query can be answered by
+/// re-verifying the matches of `prev` instead of seeding from trigrams.
+///
+/// Only conjunctions are compared; anything else, or a change to an earlier
+/// term, is not a refinement. An empty `prev` matches everything, so
+/// nothing is gained by refining it and it is not a refinement either.
+pub fn is_refinement(prev: &Query, next: &Query) -> bool {
+    let (Some(prev), Some(next)) = (conjuncts(&prev.expr), conjuncts(&next.expr)) else {
+        return false;
+    };
+    let Some((last, earlier)) = prev.split_last() else {
+        return false;
+    };
+    if next.len() < prev.len() || next[..earlier.len()] != *earlier {
+        return false;
+    }
+
+    let extended = &next[earlier.len()];
+    extended == last || extends(last, extended)
+}
+
+/// The terms of a conjunction, or of a single leaf; `None` for anything
+/// else.
+fn conjuncts(expr: &QueryExpr) -> Option<Vec<&QueryExpr>> {
+    match expr {
+        QueryExpr::And(children) => {
+            let mut out = Vec::with_capacity(children.len());
+            for child in children {
+                match child {
+                    QueryExpr::And(_) => out.extend(conjuncts(child)?),
+                    _ => out.push(child),
+                }
+            }
+            Some(out)
+        }
+        QueryExpr::Leaf(_) | QueryExpr::Not(_) => Some(vec![expr]),
+        QueryExpr::Or(_) => None,
+    }
+}
+
+/// Whether text term `next` is text term `prev` with more characters typed.
+///
+/// Only terms matched as a plain substring, or at the start of the file
+/// name, narrow as they grow; globs, terms anchored at the end and terms
+/// with a `/` do not.
+fn extends(prev: &QueryExpr, next: &QueryExpr) -> bool {
+    let (QueryExpr::Leaf(LeafExpr::Text(prev)), QueryExpr::Leaf(LeafExpr::Text(next))) =
+        (prev, next)
+    else {
+        return false;
+    };
+    narrows_when_extended(prev)
+        && narrows_when_extended(next)
+        && prev.is_phrase == next.is_phrase
+        && prev.anchor == next.anchor
+        && next.text.starts_with(&prev.text)
+}
+
+fn narrows_when_extended(term: &TextTerm) -> bool {
+    !term.is_glob
+        && !term.text.contains('/')
+        && matches!(term.anchor, TextAnchor::Anywhere | TextAnchor::Start)
+}
+
+#[cfg(test)]
+#[path = "refine_tests.rs"]
+mod tests;
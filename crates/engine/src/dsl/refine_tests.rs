@@ -0,0 +1,55 @@
+use super::is_refinement;
+use crate::dsl::parse_query;
+
+fn refines(prev: &str, next: &str) -> bool {
+    is_refinement(&parse_query(prev), &parse_query(next))
+}
+
+#[test]
+fn extending_the_last_term_refines() {
+    assert!(refines("con", "conf"));
+    assert!(refines("src conf", "src config"));
+    assert!(refines("^read", "^readme"));
+    assert!(refines("\"my doc", "\"my docs\""));
+}
+
+#[test]
+fn adding_terms_refines() {
+    assert!(refines("config", "config ext:toml"));
+    assert!(refines("config", "config not test"));
+    assert!(refines("src con", "src config main"));
+    assert!(refines("ext:rs", "ext:rs main"));
+}
+
+#[test]
+fn the_same_query_refines() {
+    assert!(refines("config ext:toml", "config ext:toml"));
+}
+
+#[test]
+fn changing_an_earlier_term_does_not_refine() {
+    assert!(!refines("src conf", "srcs conf"));
+    assert!(!refines("conf", "cof"));
+    assert!(!refines("config", "con"));
+    assert!(!refines("ext:r", "ext:rs"));
+}
+
+#[test]
+fn terms_that_do_not_narrow_as_they_grow_do_not_refine() {
+    assert!(!refines("*.r", "*.rs"));
+    assert!(!refines("src/ma", "src/main"));
+    assert!(!refines("conf", "^conf"));
+    assert!(!refines("not tes", "not test"));
+}
+
+#[test]
+fn alternatives_do_not_refine() {
+    assert!(!refines("config", "config OR readme"));
+    assert!(!refines("a OR b", "a OR bc"));
+}
+
+#[test]
+fn an_empty_query_is_not_refined() {
+    assert!(!refines("", "config"));
+    assert!(!refines("  ", "config"));
+}
@@ -0,0 +1,144 @@
+use crate::dsl::ast::{LeafExpr, Query, QueryExpr, TextAnchor};
+
+impl Query {
+    /// The query rewritten into an equivalent form that is cheaper to
+    /// evaluate and the same for queries that differ only in spelling:
+    ///
+    /// - nested ANDs and ORs are flattened, and a single-child AND or OR
+    ///   is replaced by its child;
+    /// - repeated children of an AND or OR are dropped;
+    /// - `NOT NOT x` becomes `x`, unless `x` has text terms that would then
+    ///   take part in term ordering;
+    /// - predicates move ahead of the other children of an AND, in a fixed
+    ///   order, and two or more text terms beside them are grouped into one
+    ///   AND, so they are verified together in a single pass.
+    ///
+    /// Unanchored text terms keep their order, and an AND keeps repeats of
+    /// them, because terms must match the path in the order they are typed
+    /// (see `apply_path_order_filter`).
+    pub fn rewrite(self) -> Query {
+        Query {
+            expr: rewrite_expr(self.expr),
+        }
+    }
+
+    /// A key equal for queries that [`rewrite`](Self::rewrite) to the same
+    /// expression, e.g. to cache results by.
+    pub fn canonical_key(&self) -> String {
+        format!("{:?}", self.clone().rewrite().expr)
+    }
+}
+
+fn rewrite_expr(expr: QueryExpr) -> QueryExpr {
+    match expr {
+        QueryExpr::Not(inner) => match rewrite_expr(*inner) {
+            QueryExpr::Not(folded) if !is_ordered(&folded) => *folded,
+            inner => QueryExpr::Not(Box::new(inner)),
+        },
+        QueryExpr::And(children) => rewrite_and(children),
+        QueryExpr::Or(children) => rewrite_or(children),
+        leaf @ QueryExpr::Leaf(_) => leaf,
+    }
+}
+
+fn rewrite_and(children: Vec<QueryExpr>) -> QueryExpr {
+    let mut flat: Vec<QueryExpr> = Vec::with_capacity(children.len());
+    for child in children.into_iter().map(rewrite_expr) {
+        flatten_and(child, &mut flat);
+    }
+
+    let mut predicates = Vec::new();
+    let mut rest = Vec::new();
+    for child in flat {
+        if !is_ordered_text(&child) && (predicates.contains(&child) || rest.contains(&child)) {
+            continue;
+        }
+        match child {
+            QueryExpr::Leaf(LeafExpr::Predicate(_)) => predicates.push(child),
+            child => rest.push(child),
+        }
+    }
+    predicates.sort_by_cached_key(|p| format!("{p:?}"));
+
+    // Regrouping is safe while nothing else takes part in term ordering:
+    // negations do not, alternatives do through their first branch.
+    let texts = rest.iter().filter(|c| is_text(c)).count();
+    let groupable = rest
+        .iter()
+        .all(|c| is_text(c) || matches!(c, QueryExpr::Not(_)));
+    if texts >= 2 && texts < predicates.len() + rest.len() && groupable {
+        let (texts, negations): (Vec<_>, Vec<_>) = rest.into_iter().partition(is_text);
+        rest = negations;
+        rest.push(QueryExpr::And(texts));
+    }
+
+    let mut out = predicates;
+    out.extend(rest);
+    if out.len() == 1 {
+        out.pop().unwrap()
+    } else {
+        QueryExpr::And(out)
+    }
+}
+
+/// Push the children of `expr`, and of ANDs among them such as grouped
+/// text terms, onto `out`.
+fn flatten_and(expr: QueryExpr, out: &mut Vec<QueryExpr>) {
+    match expr {
+        QueryExpr::And(children) => {
+            for child in children {
+                flatten_and(child, out);
+            }
+        }
+        expr => out.push(expr),
+    }
+}
+
+fn rewrite_or(children: Vec<QueryExpr>) -> QueryExpr {
+    let mut out: Vec<QueryExpr> = Vec::with_capacity(children.len());
+    for child in children.into_iter().map(rewrite_expr) {
+        let grandchildren = match child {
+            QueryExpr::Or(grandchildren) => grandchildren,
+            child => vec![child],
+        };
+        for child in grandchildren {
+            if !out.contains(&child) {
+                out.push(child);
+            }
+        }
+    }
+
+    if out.len() == 1 {
+        out.pop().unwrap()
+    } else {
+        QueryExpr::Or(out)
+    }
+}
+
+fn is_text(expr: &QueryExpr) -> bool {
+    matches!(expr, QueryExpr::Leaf(LeafExpr::Text(_)))
+}
+
+/// A text term that must match the path in order with the other such
+/// terms, see `apply_path_order_filter`.
+fn is_ordered_text(expr: &QueryExpr) -> bool {
+    matches!(
+        expr,
+        QueryExpr::Leaf(LeafExpr::Text(term)) if term.anchor == TextAnchor::Anywhere
+    )
+}
+
+/// Whether `expr` contributes text terms to the ordering check: those
+/// outside negations, and in the first branch of an alternative.
+fn is_ordered(expr: &QueryExpr) -> bool {
+    match expr {
+        QueryExpr::And(children) => children.iter().any(is_ordered),
+        QueryExpr::Or(children) => children.first().is_some_and(is_ordered),
+        QueryExpr::Not(_) => false,
+        leaf => is_ordered_text(leaf),
+    }
+}
+
+#[cfg(test)]
+#[path = "rewrite_tests.rs"]
+mod tests;
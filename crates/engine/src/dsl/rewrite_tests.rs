@@ -0,0 +1,140 @@
+use crate::dsl::ast::{LeafExpr, Query, QueryExpr};
+use crate::{QueryPipeline, StagedIndex, parse_query};
+
+fn rewritten(input: &str) -> QueryExpr {
+    parse_query(input).rewrite().expr
+}
+
+/// A compact rendering of an expression: text terms as written,
+/// predicates by field, `&(...)`, `|(...)` and `!` for the operators.
+fn shape(expr: &QueryExpr) -> String {
+    let list = |children: &[QueryExpr]| children.iter().map(shape).collect::<Vec<_>>().join(" ");
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Text(term)) => term.text.clone(),
+        QueryExpr::Leaf(LeafExpr::Predicate(p)) => format!("{:?}", p.field).to_lowercase(),
+        QueryExpr::Not(inner) => format!("!{}", shape(inner)),
+        QueryExpr::And(children) => format!("&({})", list(children)),
+        QueryExpr::Or(children) => format!("|({})", list(children)),
+    }
+}
+
+#[test]
+fn flattens_nested_ands_and_ors() {
+    assert_eq!(shape(&rewritten("a (b (c d))")), "&(a b c d)");
+    assert_eq!(shape(&rewritten("a OR (b OR (c OR d))")), "|(a b c d)");
+    assert_eq!(shape(&rewritten("(((a)))")), "a");
+    assert_eq!(shape(&rewritten("a (b OR c) d")), "&(a |(b c) d)");
+}
+
+#[test]
+fn drops_repeated_children() {
+    assert_eq!(shape(&rewritten("a OR b OR a")), "|(a b)");
+    assert_eq!(shape(&rewritten("ext:rs ext:rs main")), "&(ext main)");
+    assert_eq!(shape(&rewritten("^main ^main")), "main");
+    // Repeated plain terms must both match, in order, so they stay.
+    assert_eq!(shape(&rewritten("a a")), "&(a a)");
+}
+
+#[test]
+fn folds_double_negation() {
+    assert_eq!(shape(&rewritten("NOT (NOT ext:rs)")), "ext");
+    assert_eq!(shape(&rewritten("NOT (NOT (NOT ^a))")), "!a");
+    assert_eq!(
+        shape(&rewritten("b NOT (NOT (ext:rs ^a))")),
+        "&(ext &(b a))"
+    );
+    // Text terms under the negations would start to count for ordering.
+    assert_eq!(shape(&rewritten("b NOT (NOT a)")), "&(b !!a)");
+}
+
+#[test]
+fn hoists_predicates_and_groups_text_terms() {
+    assert_eq!(shape(&rewritten("main ext:rs")), "&(ext main)");
+    assert_eq!(
+        shape(&rewritten("src main size:>1k ext:rs")),
+        "&(ext size &(src main))"
+    );
+    assert_eq!(
+        shape(&rewritten("src NOT test main ext:rs")),
+        "&(ext !test &(src main))"
+    );
+    // An alternative takes part in term ordering, so nothing is regrouped.
+    assert_eq!(
+        shape(&rewritten("src (a OR b) main ext:rs")),
+        "&(ext src |(a b) main)"
+    );
+}
+
+#[test]
+fn rewriting_twice_changes_nothing() {
+    for input in [
+        "src main size:>1k ext:rs",
+        "a OR (b OR a) NOT (NOT c)",
+        "(a b) (c (d ext:md))",
+        "src NOT test main ext:rs",
+    ] {
+        let once = parse_query(input).rewrite();
+        assert_eq!(once.clone().rewrite(), once, "{input}");
+    }
+}
+
+#[test]
+fn canonical_key_ignores_spelling() {
+    let key = |q: &str| parse_query(q).canonical_key();
+    assert_eq!(key("ext:rs size:>1k main"), key("size:>1k (main ext:rs)"));
+    assert_eq!(key("a OR b"), key("(a) OR (b OR a)"));
+    assert_eq!(key("ext:rs"), key("NOT (NOT ext:rs)"));
+    assert_ne!(key("main test"), key("test main"));
+    assert_ne!(key("ext:rs"), key("ext:md"));
+}
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &[
+            "src/main.rs",
+            "src/main.py",
+            "src/bin/main_test.rs",
+            "tests/main_test.rs",
+            "main/src/lib.rs",
+            "docs/guide.md",
+            "docs/main/readme.md",
+            "src/docs/main.md",
+        ],
+    )
+}
+
+fn matched(index: &StagedIndex, query: Query) -> Vec<u32> {
+    QueryPipeline::new(index)
+        .with_query(query)
+        .execute()
+        .rank_with_limit(None)
+        .matched()
+        .to_vec()
+}
+
+#[test]
+fn rewritten_queries_match_the_same_files() {
+    let index = staged();
+    for input in [
+        "src main",
+        "main src",
+        "src main ext:rs",
+        "ext:rs src main NOT test",
+        "src (main OR lib) ext:rs",
+        "(src main) OR (docs main) OR (src main)",
+        "main main",
+        "NOT (NOT (src main)) ext:rs",
+        "NOT (NOT (ext:rs ^main)) src",
+        "size:>1k ext:md docs main",
+        "ext:rs ext:rs (ext:rs OR ext:py)",
+        "NOT (ext:rs OR ext:md) src",
+    ] {
+        let query = parse_query(input);
+        assert_eq!(
+            matched(&index, query.clone().rewrite()),
+            matched(&index, query),
+            "{input}"
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use blaze_runtime::BoostRules;
+use hashbrown::HashMap;
+
+use crate::{DirId, FileId, IndexReader};
+
+/// The configured boost of each of `candidates` under a boosted directory.
+///
+/// Rules are matched once per directory, so this stays cheap for large
+/// result sets; files without a boost are left out.
+pub(crate) fn boosted_files<I: IndexReader>(
+    index: &I,
+    boosts: &BoostRules,
+    candidates: &[FileId],
+) -> HashMap<FileId, i32> {
+    if boosts.is_empty() {
+        return HashMap::new();
+    }
+
+    let mut dirs: HashMap<DirId, i32> = HashMap::new();
+    candidates
+        .iter()
+        .filter_map(|&fid| {
+            let dir = index.get_file_dir_id(fid);
+            let score = *dirs
+                .entry(dir)
+                .or_insert_with(|| boosts.score_for_dir(&index.reconstruct_dir_path(dir)));
+            (score != 0).then_some((fid, score))
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+use blaze_runtime::PinSet;
+
+use crate::{
+    DirId, Field, IndexReader, LeafExpr, Predicate, QueryExpr, TextAnchor, TextTerm, Value,
+    build_trigrams_for_string,
+    dsl::compile_name_glob,
+    eval::{
+        UnionBuilder, diff_sorted, intersect_sorted,
+        text::{contains_lowercase_ascii, name_matches_anchored},
+    },
+    profile::{self, Op},
+};
+
+/// Evaluate `expr` over directories instead of files.
+///
+/// Text terms match anywhere in the directory's absolute path and are seeded
+/// from the directory trigram index; anchored ones (`^src`) match the
+/// directory's own name. `name:` globs match the directory's own
+/// name and `pinned:` works as for files; other predicates describe file
+/// contents or metadata, so they match no directory.
+///
+/// Returns a *sorted* subset of `candidates`.
+pub(crate) fn eval_dir_expr<I: IndexReader>(
+    index: &I,
+    expr: &QueryExpr,
+    candidates: &[DirId],
+    pins: &PinSet,
+) -> Vec<DirId> {
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Text(term)) => eval_dir_text(index, term, candidates),
+        QueryExpr::Leaf(LeafExpr::Predicate(pred)) => {
+            eval_dir_predicate(index, pred, candidates, pins)
+        }
+        QueryExpr::And(children) => {
+            let mut current = candidates.to_vec();
+            for child in children {
+                if current.is_empty() {
+                    break;
+                }
+                current = eval_dir_expr(index, child, &current, pins);
+            }
+            current
+        }
+        QueryExpr::Or(children) => {
+            let mut acc = UnionBuilder::new(candidates.last().map_or(0, |&d| d as usize + 1));
+            for child in children {
+                if acc.len() == candidates.len() {
+                    break;
+                }
+                acc.add(eval_dir_expr(index, child, candidates, pins));
+            }
+            acc.into_sorted()
+        }
+        QueryExpr::Not(inner) => {
+            diff_sorted(candidates, &eval_dir_expr(index, inner, candidates, pins))
+        }
+    }
+}
+
+fn eval_dir_text<I: IndexReader>(index: &I, term: &TextTerm, candidates: &[DirId]) -> Vec<DirId> {
+    let needle = term.text.to_lowercase();
+
+    // Only directories holding every trigram of the needle can match.
+    let mut seeded = candidates.to_vec();
+    for tri in build_trigrams_for_string(&needle) {
+        let mut lookup = profile::span(Op::TrigramLookup, 0);
+        let Some(postings) = index.query_dir_trigram(tri) else {
+            return Vec::new();
+        };
+        lookup.set_items(postings.len());
+        drop(lookup);
+        seeded = intersect_sorted(&seeded, &postings);
+        if seeded.is_empty() {
+            return seeded;
+        }
+    }
+
+    let _span = profile::span(Op::Verify, seeded.len());
+    if term.anchor != TextAnchor::Anywhere {
+        return seeded
+            .into_iter()
+            .filter(|&d| name_matches_anchored(index.get_dir_name(d), &needle, term.anchor))
+            .collect();
+    }
+    seeded
+        .into_iter()
+        .filter(|&d| contains_lowercase_ascii(&index.reconstruct_dir_path(d), &needle))
+        .collect()
+}
+
+fn eval_dir_predicate<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[DirId],
+    pins: &PinSet,
+) -> Vec<DirId> {
+    match (pred.field, &pred.value) {
+        (Field::Name | Field::IName, Value::Str(pattern)) => {
+            let Some(glob) = compile_name_glob(pattern, pred.field == Field::IName) else {
+                return Vec::new();
+            };
+            candidates
+                .iter()
+                .copied()
+                .filter(|&d| glob.is_match(index.get_dir_name(d)))
+                .collect()
+        }
+        (Field::Pinned, &Value::Bool(wanted)) => candidates
+            .iter()
+            .copied()
+            .filter(|&d| pins.covers(&index.reconstruct_dir_path(d)) == wanted)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
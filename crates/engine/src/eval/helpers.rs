@@ -1,12 +1,14 @@
 use std::cmp::Ordering;
 
-use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, TimeZone, Utc};
 
+use crate::profile::{self, Op};
 use crate::{CmpOp, RelativeTime, TimeExpr, TimeMacro};
 
 /// Adaptive intersection into `out`: linear vs galloping.
 #[inline]
 pub fn intersect_adaptive_into<T: Ord + Copy>(a: &[T], b: &[T], out: &mut Vec<T>) {
+    let _span = profile::span(Op::Intersect, a.len() + b.len());
     out.clear();
 
     if a.is_empty() || b.is_empty() {
@@ -50,6 +52,7 @@ pub fn intersect_sorted_into<T: Ord + Copy>(a: &[T], b: &[T], out: &mut Vec<T>)
 /// Owning wrapper around `intersect_sorted_into`.
 #[inline]
 pub fn intersect_sorted<T: Ord + Copy>(a: &[T], b: &[T]) -> Vec<T> {
+    let _span = profile::span(Op::Intersect, a.len() + b.len());
     let mut out = Vec::with_capacity(a.len().min(b.len()));
     intersect_sorted_into(a, b, &mut out);
     out
@@ -123,6 +126,101 @@ pub fn union_sorted<T: Ord + Copy>(a: &[T], b: &[T]) -> Vec<T> {
     out
 }
 
+/// Sorted union of sorted id lists, added one at a time.
+///
+/// Lists are merged while the union is sparse. Once it holds more than one
+/// id in 32 of `0..bound` it becomes a bitset over that range, so a union of
+/// many broad lists never takes more than a bit per id plus the list being
+/// added, however many lists there are.
+#[derive(Debug)]
+pub struct UnionBuilder {
+    bound: usize,
+    len: usize,
+    state: UnionState,
+}
+
+#[derive(Debug)]
+enum UnionState {
+    Sparse(Vec<u32>),
+    Dense(Vec<u64>),
+}
+
+impl UnionBuilder {
+    /// A union of ids expected to stay below `bound`; larger ids still work.
+    pub fn new(bound: usize) -> Self {
+        Self {
+            bound,
+            len: 0,
+            state: UnionState::Sparse(Vec::new()),
+        }
+    }
+
+    /// Number of distinct ids added so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add the sorted `ids`.
+    pub fn add(&mut self, ids: Vec<u32>) {
+        if ids.is_empty() {
+            return;
+        }
+        match &mut self.state {
+            UnionState::Sparse(acc) => {
+                *acc = if acc.is_empty() {
+                    ids
+                } else {
+                    union_sorted(acc, &ids)
+                };
+                self.len = acc.len();
+                if self.len.saturating_mul(32) > self.bound {
+                    let mut bits = vec![0u64; self.bound.div_ceil(64)];
+                    set_bits(&mut bits, acc);
+                    self.state = UnionState::Dense(bits);
+                }
+            }
+            UnionState::Dense(bits) => self.len += set_bits(bits, &ids),
+        }
+    }
+
+    /// The union, ascending.
+    pub fn into_sorted(self) -> Vec<u32> {
+        match self.state {
+            UnionState::Sparse(acc) => acc,
+            UnionState::Dense(bits) => {
+                let mut out = Vec::with_capacity(self.len);
+                for (w, &word) in bits.iter().enumerate() {
+                    let mut word = word;
+                    while word != 0 {
+                        out.push((w * 64) as u32 + word.trailing_zeros());
+                        word &= word - 1;
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Set the bits of `ids`, growing `bits` as needed; returns how many were
+/// not set before.
+fn set_bits(bits: &mut Vec<u64>, ids: &[u32]) -> usize {
+    let mut added = 0;
+    for &id in ids {
+        let (w, bit) = (id as usize / 64, 1u64 << (id % 64));
+        if w >= bits.len() {
+            bits.resize(w + 1, 0);
+        }
+        added += usize::from(bits[w] & bit == 0);
+        bits[w] |= bit;
+    }
+    added
+}
+
 /// Difference of two sorted slices.
 #[inline]
 pub fn diff_sorted<T: Ord + Copy>(base: &[T], sub: &[T]) -> Vec<T> {
@@ -194,11 +292,28 @@ fn resolve_relative_time(rel: &RelativeTime, now: DateTime<Utc>) -> i64 {
         RelativeTime::Days(n) => Duration::days(*n),
         RelativeTime::Hours(n) => Duration::hours(*n),
         RelativeTime::Weeks(n) => Duration::weeks(*n),
-        RelativeTime::Years(n) => Duration::days(*n * 365),
+        RelativeTime::Months(n) => return months_before(now, *n).timestamp(),
+        RelativeTime::Years(n) => return months_before(now, n.saturating_mul(12)).timestamp(),
     };
     (now - duration).timestamp()
 }
 
+/// `now` moved back `n` calendar months, clamping the day to the target
+/// month's length (March 31st minus one month is February 28th or 29th).
+fn months_before(now: DateTime<Utc>, n: i64) -> DateTime<Utc> {
+    let months = Months::new(u32::try_from(n.unsigned_abs()).unwrap_or(u32::MAX));
+    let shifted = if n >= 0 {
+        now.checked_sub_months(months)
+    } else {
+        now.checked_add_months(months)
+    };
+    shifted.unwrap_or(if n >= 0 {
+        DateTime::<Utc>::MIN_UTC
+    } else {
+        DateTime::<Utc>::MAX_UTC
+    })
+}
+
 fn resolve_time_macro(mac: &TimeMacro, now: DateTime<Utc>) -> i64 {
     match mac {
         TimeMacro::Today => start_of_day(now).timestamp(),
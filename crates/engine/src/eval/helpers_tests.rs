@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+use proptest::{collection::btree_set, prelude::*};
+
 use super::*;
 
 fn assert_intersect_sorted(a: &[i32], b: &[i32], expected: &[i32]) {
@@ -102,6 +106,38 @@ fn union_sorted_basic_cases() {
     );
 }
 
+#[test]
+fn union_builder_stays_sparse_for_few_ids() {
+    let mut union = UnionBuilder::new(10_000);
+    union.add(vec![5, 9]);
+    union.add(Vec::new());
+    union.add(vec![1, 5, 7]);
+
+    assert_eq!(union.len(), 4);
+    assert!(matches!(union.state, UnionState::Sparse(_)));
+    assert_eq!(union.into_sorted(), [1, 5, 7, 9]);
+}
+
+#[test]
+fn union_builder_turns_dense_and_matches_pairwise_unions() {
+    let lists: Vec<Vec<u32>> = (1..=5)
+        .map(|step| (0..1_000).step_by(step).collect())
+        .chain([vec![999, 1_200]])
+        .collect();
+
+    let mut union = UnionBuilder::new(1_000);
+    let mut expected: Vec<u32> = Vec::new();
+    for list in &lists {
+        union.add(list.clone());
+        expected = union_sorted(&expected, list);
+    }
+
+    assert!(matches!(union.state, UnionState::Dense(_)));
+    // Ids past the bound still land in the union.
+    assert_eq!(union.len(), expected.len());
+    assert_eq!(union.into_sorted(), expected);
+}
+
 #[test]
 fn diff_sorted_basic_cases() {
     // Both empty
@@ -137,3 +173,90 @@ fn generics_work_for_non_integers() {
     let diff = diff_sorted(&a, &b);
     assert_eq!(diff, vec!['a', 'c']);
 }
+
+#[test]
+fn relative_months_and_years_follow_the_calendar() {
+    let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap();
+    let resolve = |rel, now| resolve_time_expr(&TimeExpr::Relative(rel), now);
+
+    // Clamped to the end of a shorter month.
+    assert_eq!(
+        resolve(RelativeTime::Months(1), at(2024, 3, 31)),
+        at(2024, 2, 29).timestamp()
+    );
+    assert_eq!(
+        resolve(RelativeTime::Months(13), at(2024, 1, 15)),
+        at(2022, 12, 15).timestamp()
+    );
+    assert_eq!(
+        resolve(RelativeTime::Years(1), at(2024, 2, 29)),
+        at(2023, 2, 28).timestamp()
+    );
+}
+
+/// Sorted, duplicate-free ids below `bound`, as the evaluator's id lists are.
+fn id_list(bound: u32, max_len: usize) -> impl Strategy<Value = Vec<u32>> {
+    btree_set(0..bound, 0..=max_len).prop_map(|ids| ids.into_iter().collect())
+}
+
+fn as_set(ids: &[u32]) -> BTreeSet<u32> {
+    ids.iter().copied().collect()
+}
+
+fn sorted(set: BTreeSet<u32>) -> Vec<u32> {
+    set.into_iter().collect()
+}
+
+proptest! {
+    #[test]
+    fn set_helpers_match_btree_set(a in id_list(512, 200), b in id_list(512, 200)) {
+        let (sa, sb) = (as_set(&a), as_set(&b));
+
+        let both = sorted(&sa & &sb);
+        prop_assert_eq!(intersect_sorted(&a, &b), both.clone());
+        prop_assert_eq!(intersect_adaptive(&a, &b), both);
+        prop_assert_eq!(union_sorted(&a, &b), sorted(&sa | &sb));
+        prop_assert_eq!(diff_sorted(&a, &b), sorted(&sa - &sb));
+    }
+
+    // Lists of very different lengths take the galloping path.
+    #[test]
+    fn skewed_intersections_match_btree_set(
+        small in id_list(4096, 16),
+        large in id_list(4096, 2048),
+    ) {
+        let both = sorted(&as_set(&small) & &as_set(&large));
+        prop_assert_eq!(intersect_adaptive(&small, &large), both.clone());
+        prop_assert_eq!(intersect_adaptive(&large, &small), both);
+    }
+
+    #[test]
+    fn intersections_into_a_used_buffer_replace_it(
+        a in id_list(512, 200),
+        b in id_list(512, 200),
+        stale in id_list(512, 20),
+    ) {
+        let both = sorted(&as_set(&a) & &as_set(&b));
+        let mut out = stale.clone();
+        intersect_sorted_into(&a, &b, &mut out);
+        prop_assert_eq!(&out, &both);
+
+        let mut out = stale;
+        intersect_adaptive_into(&a, &b, &mut out);
+        prop_assert_eq!(out, both);
+    }
+
+    // Enough ids to turn the builder dense part way through, and some
+    // beyond its bound.
+    #[test]
+    fn union_builders_match_btree_set(lists in prop::collection::vec(id_list(1200, 100), 0..8)) {
+        let mut builder = UnionBuilder::new(1024);
+        let mut expected = BTreeSet::new();
+        for ids in lists {
+            expected.extend(ids.iter().copied());
+            builder.add(ids);
+            prop_assert_eq!(builder.len(), expected.len());
+        }
+        prop_assert_eq!(builder.into_sorted(), sorted(expected));
+    }
+}
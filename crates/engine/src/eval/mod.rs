@@ -1,39 +1,178 @@
+use std::{borrow::Cow, cell::RefCell, mem, rc::Rc, sync::Arc};
+
+use blaze_runtime::PinSet;
 use chrono::{DateTime, Utc};
 
+mod boosts;
+mod dirs;
 mod helpers;
+mod path_cache;
+mod pins;
 mod planner;
 mod predicates;
 mod rank;
+mod scratch;
+mod stats;
+mod subtree;
 mod text;
 
-pub use helpers::{diff_sorted, intersect_adaptive, intersect_sorted, union_sorted};
+use helpers::intersect_adaptive_into;
+pub use helpers::{UnionBuilder, diff_sorted, intersect_adaptive, intersect_sorted, union_sorted};
 use log::debug;
-use planner::{estimate_cost, estimate_cost_simple};
-use predicates::eval_predicate;
+pub use path_cache::PathCache;
+pub use planner::{Cost, PlanKind, PlanNode, plan_query};
+use planner::{order_and_children, pure_text_terms, rank_conjunction_terms};
+use predicates::{eval_ext_complement, eval_predicate, indexed_matches, negatable_ext};
 pub use rank::*;
+use scratch::Scratch;
+pub use stats::EvalStats;
+pub use subtree::DirSubtree;
+pub use text::{CandidateCutoffs, TextPlan, TextStrategy};
 
 use crate::{
-    dsl::{LeafExpr, Query, QueryExpr, TextTerm},
-    eval::{
-        planner::{Cost, estimate_text_term_cost},
-        text::filter_candidates_by_all_terms,
-    },
-    index::{FileId, IndexReader},
+    dsl::{LeafExpr, Predicate, Query, QueryExpr, TextTerm},
+    eval::{stats::EvalCounters, text::filter_candidates_by_all_terms},
+    index::{DirAccess, DirId, FileId, IndexReader},
 };
 
+type LeafMatches<'a> = Vec<(Predicate, Option<Rc<Cow<'a, [FileId]>>>)>;
+
 pub struct QueryEngine<'a, I: IndexReader + Sync> {
     index: &'a I,
+    counters: EvalCounters,
+    stats: std::cell::Cell<EvalStats>,
+    pins: PinSet,
+    paths: PathCache,
+    /// Files to evaluate against instead of the whole index.
+    within: Option<Vec<FileId>>,
+    /// Only files in these directories can match.
+    subtree: Option<DirSubtree>,
+    /// Only files the querying user could reach can match.
+    access: Option<Arc<DirAccess>>,
+    /// Every file each predicate the index lists directly matches (`None`
+    /// if it does not list them), so a predicate repeated in a query is
+    /// looked up once and each occurrence just intersects its candidates.
+    leaf_matches: RefCell<LeafMatches<'a>>,
+    /// Id buffers reused by the stages of this query, from the thread's pool.
+    scratch: Scratch,
 }
 
 impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
     pub fn new(index: &'a I) -> Self {
-        Self { index }
+        Self {
+            index,
+            counters: EvalCounters::default(),
+            stats: std::cell::Cell::new(EvalStats::default()),
+            pins: PinSet::default(),
+            paths: PathCache::default(),
+            within: None,
+            subtree: None,
+            access: None,
+            leaf_matches: RefCell::default(),
+            scratch: Scratch::from_thread(),
+        }
+    }
+
+    /// Pins that `pinned:` predicates are evaluated against.
+    pub fn with_pins(mut self, pins: PinSet) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Cache that text verification records matching paths in, so later
+    /// stages of the same query can reuse them.
+    pub fn with_path_cache(mut self, paths: PathCache) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Only consider `file_ids` rather than every file in the index.
+    ///
+    /// Ids are sorted and deduplicated, and ids the index does not have (or
+    /// has removed) are dropped.
+    pub fn with_candidates(mut self, mut file_ids: Vec<FileId>) -> Self {
+        let count = self.index.get_file_count() as FileId;
+        file_ids.retain(|&fid| fid < count && !self.index.is_file_removed(fid));
+        file_ids.sort_unstable();
+        file_ids.dedup();
+        self.within = Some(file_ids);
+        self
+    }
+
+    /// Only match files in `subtree`, e.g. for `--under`.
+    pub fn with_subtree(mut self, subtree: DirSubtree) -> Self {
+        self.subtree = Some(subtree);
+        self
+    }
+
+    /// Only match files in directories `access` allows.
+    pub fn with_access(mut self, access: Arc<DirAccess>) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// Hand the path cache on to ranking and output.
+    pub fn into_path_cache(self) -> PathCache {
+        self.paths
     }
 
     pub fn eval_query(&self, query: &Query) -> Vec<FileId> {
         let timestamp = Utc::now();
-        let candidates: Vec<FileId> = (0..self.index.get_file_count() as FileId).collect();
-        self.eval_expr(&query.expr, &candidates, timestamp)
+        let mut all = Vec::new();
+        let candidates: &[FileId] = match &self.within {
+            Some(within) => within,
+            None => {
+                // Under a directory, only its entries need evaluating.
+                all = match self.subtree.as_ref().and_then(|s| s.files(self.index)) {
+                    Some(files) => self.scratch.copy_of(&files),
+                    None => {
+                        let mut all = self.scratch.buffer();
+                        all.extend(
+                            (0..self.index.get_file_count() as FileId)
+                                .filter(|&fid| !self.index.is_file_removed(fid)),
+                        );
+                        all
+                    }
+                };
+                &all
+            }
+        };
+
+        self.counters.reset();
+        let mut hits = self.eval_expr(&query.expr, candidates, timestamp);
+        // Trigram postings still list removed files.
+        hits.retain(|&fid| !self.index.is_file_removed(fid));
+        if let Some(subtree) = &self.subtree {
+            hits = subtree.filter_files(self.index, hits);
+        }
+        if let Some(access) = &self.access {
+            hits = access.filter_files(self.index, hits);
+        }
+
+        self.stats.set(EvalStats {
+            initial_candidates: candidates.len(),
+            trigram_candidates: self.counters.trigram_candidates(),
+            verified_candidates: hits.len(),
+        });
+        self.scratch.recycle(all);
+
+        hits
+    }
+
+    /// Evaluate `query` against directories rather than files.
+    ///
+    /// Returns matching directory ids in index order; use
+    /// [`IndexReader::reconstruct_dir_path`] to turn them into paths.
+    pub fn eval_dirs(&self, query: &Query) -> Vec<DirId> {
+        let candidates: Vec<DirId> = (0..self.index.dir_count() as DirId)
+            .filter(|&d| !self.index.is_dir_removed(d))
+            .collect();
+        dirs::eval_dir_expr(self.index, &query.expr, &candidates, &self.pins)
+    }
+
+    /// Candidate counts from the most recent `eval_query` call.
+    pub fn stats(&self) -> EvalStats {
+        self.stats.get()
     }
 
     fn eval_expr(
@@ -47,38 +186,21 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
 
             QueryExpr::And(children) => {
                 if children.is_empty() {
-                    return candidates.to_vec();
+                    return self.scratch.copy_of(candidates);
                 }
 
                 // Detect pure-text conjunction: AND of only Text leaves.
-                let text_terms: Vec<&TextTerm> = children
-                    .iter()
-                    .filter_map(|c| match c {
-                        QueryExpr::Leaf(LeafExpr::Text(t)) => Some(t),
-                        _ => None,
-                    })
-                    .collect();
-
-                if text_terms.len() >= 2 && text_terms.len() == children.len() {
+                if let Some(text_terms) = pure_text_terms(children) {
                     return self.eval_pure_text_conjunction(&text_terms, candidates, timestamp);
                 }
 
-                let mut ordered = children.clone();
-
-                let use_index_costs = text_terms.len() >= 2;
-                if use_index_costs {
-                    ordered.sort_by_cached_key(|child| estimate_cost(self.index, child));
-                } else {
-                    ordered.sort_by_key(estimate_cost_simple);
-                }
-
-                let mut current = candidates.to_vec();
-                for child in ordered {
+                let mut current = self.scratch.copy_of(candidates);
+                for child in order_and_children(self.index, children) {
                     if current.is_empty() {
                         break;
                     }
-                    let subset = self.eval_expr(&child, &current, timestamp);
-                    current = subset;
+                    let subset = self.eval_expr(child, &current, timestamp);
+                    self.scratch.recycle(mem::replace(&mut current, subset));
                 }
                 current
             }
@@ -88,29 +210,53 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
                     return Vec::new();
                 }
 
-                // We maintain the invariant that all candidate sets are sorted.
-                let mut acc: Vec<FileId> = Vec::new();
+                // Detect pure-text disjunction: OR of only Text leaves.
+                if let Some(text_terms) = pure_text_terms(children) {
+                    return text::eval_text_disjunction(
+                        self.index,
+                        &text_terms,
+                        candidates,
+                        &self.counters,
+                        &self.paths,
+                        &self.scratch,
+                    );
+                }
 
+                // Each child's matches are folded in and dropped before the
+                // next child runs, so broad children never pile up.
+                let mut acc = UnionBuilder::new(candidates.last().map_or(0, |&f| f as usize + 1));
                 for child in children {
-                    let subset = self.eval_expr(child, candidates, timestamp);
-                    if acc.is_empty() {
-                        // Fast path: first non-empty subset, take it as-is
-                        acc = subset;
-                    } else if !subset.is_empty() {
-                        acc = union_sorted(&acc, &subset);
+                    // Matches are subsets of the candidates: once all of them
+                    // match, later children cannot add anything.
+                    if acc.len() == candidates.len() {
+                        break;
                     }
+                    acc.add(self.eval_expr(child, candidates, timestamp));
                 }
-
-                acc
+                acc.into_sorted()
             }
 
             QueryExpr::Not(inner) => {
+                if let Some(ext) = negatable_ext(inner) {
+                    let excluded = match inner.as_ref() {
+                        QueryExpr::Leaf(LeafExpr::Predicate(pred)) => self.indexed_matches(pred),
+                        _ => None,
+                    };
+                    return eval_ext_complement(
+                        self.index,
+                        ext,
+                        excluded.as_deref().map(|e| &e[..]),
+                        candidates,
+                    );
+                }
                 let inner_ids = self.eval_expr(inner, candidates, timestamp);
-                if inner_ids.is_empty() {
-                    candidates.to_vec()
+                let outer = if inner_ids.is_empty() {
+                    self.scratch.copy_of(candidates)
                 } else {
                     diff_sorted(candidates, &inner_ids)
-                }
+                };
+                self.scratch.recycle(inner_ids);
+                outer
             }
         }
     }
@@ -123,10 +269,37 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
         timestamp: DateTime<Utc>,
     ) -> Vec<FileId> {
         match leaf {
-            LeafExpr::Text(term) => text::eval_text_term(self.index, term, candidates),
-            LeafExpr::Predicate(pred) => eval_predicate(self.index, pred, candidates, timestamp),
+            LeafExpr::Text(term) => text::eval_text_term(
+                self.index,
+                term,
+                candidates,
+                &self.counters,
+                &self.paths,
+                &self.scratch,
+            ),
+            LeafExpr::Predicate(pred) => match self.indexed_matches(pred) {
+                Some(matches) => {
+                    let mut out = self.scratch.buffer();
+                    intersect_adaptive_into(candidates, &matches, &mut out);
+                    out
+                }
+                None => eval_predicate(self.index, pred, candidates, timestamp, &self.pins),
+            },
         }
     }
+
+    /// Every file `pred` matches, if the index lists them; looked up once
+    /// per engine.
+    fn indexed_matches(&self, pred: &Predicate) -> Option<Rc<Cow<'a, [FileId]>>> {
+        if let Some((_, matches)) = self.leaf_matches.borrow().iter().find(|(p, _)| p == pred) {
+            return matches.clone();
+        }
+        let matches = indexed_matches(self.index, pred).map(Rc::new);
+        self.leaf_matches
+            .borrow_mut()
+            .push((pred.clone(), matches.clone()));
+        matches
+    }
     /// Optimised evaluation for AND of only text terms.
     ///
     /// Strategy:
@@ -147,74 +320,49 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
         }
         if terms.is_empty() {
             // AND over no terms is identity: keep current candidates.
-            return candidates.to_vec();
+            return self.scratch.copy_of(candidates);
         }
 
-        let file_count = self.index.get_file_count();
-        if file_count == 0 {
+        if self.index.get_file_count() == 0 {
             return Vec::new();
         }
 
-        // "Broad" threshold: a term whose effective cost exceeds this is considered
-        // too broad to seed from if we have any more selective alternative.
-        let broad_threshold: u64 = ((file_count as f64) * 0.6) as u64;
-
-        // Compute costs and detect impossible/broad terms.
-        let mut term_costs: Vec<(Cost, &TextTerm, bool)> = Vec::with_capacity(terms.len());
-
-        for &term in terms {
-            let cost = estimate_text_term_cost(self.index, term);
-
-            // Perfect anchor: this term cannot match any file in the index.
-            // In an AND conjunction, that makes the whole expression unsatisfiable.
-            if cost == Cost::ZERO {
-                return Vec::new();
-            }
-
-            // Broad if:
-            //   - cost is above 60% of total files, or
-            //   - the estimator has already classified it as ultra-broad / linear-scan.
-            let is_broad =
-                cost.0 > broad_threshold || cost == Cost::VERY_BAD || cost == Cost::LINEAR_SCAN;
-
-            term_costs.push((cost, term, is_broad));
-        }
-
-        // Sort by cost (ascending) – most selective first.
-        term_costs.sort_unstable_by_key(|(cost, _, _)| *cost);
+        // Most selective first; a term that matches nothing empties the AND.
+        let Some((ranked, seed)) = rank_conjunction_terms(self.index, terms) else {
+            return Vec::new();
+        };
 
         #[cfg(debug_assertions)]
         {
             debug!("[DEBUG] Pure-text AND term costs:");
-            for (cost, term, is_broad) in &term_costs {
+            for t in &ranked {
                 debug!(
                     "  '{}': cost={} {}",
-                    term.text,
-                    cost.0,
-                    if *is_broad { "(BROAD)" } else { "" }
+                    t.term.text,
+                    t.cost.0,
+                    if t.broad { "(BROAD)" } else { "" }
                 );
             }
         }
 
-        // Choose seed:
-        // - Prefer the most selective *non-broad* term.
-        // - If all are broad, fall back to the cheapest term.
-        let seed_term: &TextTerm =
-            if let Some((_, term, _)) = term_costs.iter().find(|(_, _, is_broad)| !*is_broad) {
-                *term
-            } else {
-                term_costs[0].1
-            };
+        let seed_term = ranked[seed].term;
 
         #[cfg(debug_assertions)]
         debug!("[DEBUG] Pure-text AND: seeding from '{}'", seed_term.text);
 
         // Evaluate the seed term with the full text engine (trigram + verification),
         // but restricted to the current candidate set.
-        let seed_candidates = text::eval_text_term(self.index, seed_term, candidates);
+        let seed_candidates = text::eval_text_term(
+            self.index,
+            seed_term,
+            candidates,
+            &self.counters,
+            &self.paths,
+            &self.scratch,
+        );
 
         if seed_candidates.is_empty() {
-            return Vec::new();
+            return seed_candidates;
         }
 
         #[cfg(debug_assertions)]
@@ -226,7 +374,9 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
 
         // Single-pass verification: check *all* terms (including the seed) against each
         // candidate path exactly once (filename first, then full path if needed).
-        let filtered = filter_candidates_by_all_terms(self.index, terms, &seed_candidates);
+        let filtered =
+            filter_candidates_by_all_terms(self.index, terms, &seed_candidates, &self.paths);
+        self.scratch.recycle(seed_candidates);
 
         #[cfg(debug_assertions)]
         debug!(
@@ -237,3 +387,7 @@ impl<'a, I: IndexReader + Sync> QueryEngine<'a, I> {
         filtered
     }
 }
+
+#[cfg(test)]
+#[path = "mod_tests.rs"]
+mod tests;
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+
+use super::*;
+use crate::{Field, IndexBuilder, StagedIndex, parse_query};
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &[
+            "src/main.rs",
+            "src/main.py",
+            "tests/main_test.rs",
+            "docs/guide.md",
+        ],
+    )
+}
+
+#[test]
+fn with_candidates_restricts_evaluation() {
+    let index = staged();
+    let query = parse_query("ext:rs");
+
+    assert_eq!(QueryEngine::new(&index).eval_query(&query), [0, 2]);
+
+    // Unsorted, duplicated and out-of-range ids are cleaned up.
+    let engine = QueryEngine::new(&index).with_candidates(vec![2, 1, 1, 99]);
+    assert_eq!(engine.eval_query(&query), [2]);
+    assert_eq!(engine.stats().initial_candidates, 2);
+}
+
+#[test]
+fn negated_ext_skips_files_by_extension() {
+    let index = staged();
+    let query = parse_query("NOT ext:rs");
+
+    // Fewer postings than candidates: the postings are subtracted.
+    assert_eq!(QueryEngine::new(&index).eval_query(&query), [1, 3]);
+
+    // More postings than candidates: candidates are checked one by one.
+    let engine = QueryEngine::new(&index).with_candidates(vec![0, 1]);
+    assert_eq!(engine.eval_query(&query), [1]);
+
+    // Other negations still diff the child's matches.
+    let query = parse_query("NOT (ext:rs OR ext:md)");
+    assert_eq!(QueryEngine::new(&index).eval_query(&query), [1]);
+}
+
+#[test]
+fn or_unions_many_children() {
+    let index = staged();
+
+    let query = parse_query("ext:md OR ext:py OR guide");
+    assert_eq!(QueryEngine::new(&index).eval_query(&query), [1, 3]);
+
+    // Every file matches by the second child; the rest change nothing.
+    let query = parse_query("ext:rs OR NOT ext:rs OR ext:md OR missing");
+    assert_eq!(QueryEngine::new(&index).eval_query(&query), [0, 1, 2, 3]);
+}
+
+#[test]
+fn clean_macro_drops_noisy_directories() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("docs/report.pdf")),
+        FileRecord::test_file(root.join("target/debug/report.pdf")),
+        FileRecord::test_file(root.join(".cache/report.pdf")),
+    ]);
+    let index = builder.finish();
+    let engine = QueryEngine::new(&index);
+
+    assert_eq!(engine.eval_query(&parse_query("report.pdf")), [0, 1, 2]);
+    assert_eq!(engine.eval_query(&parse_query("clean: report.pdf")), [0]);
+    assert_eq!(
+        engine.eval_query(&parse_query("clean:cache report.pdf")),
+        [0, 1]
+    );
+}
+
+#[test]
+fn repeated_predicates_are_looked_up_once() {
+    let index = staged();
+    let engine = QueryEngine::new(&index);
+    let query = parse_query("(ext:rs src) OR (ext:rs tests) OR (NOT ext:rs docs)");
+
+    assert_eq!(engine.eval_query(&query), [0, 2, 3]);
+    let memo = engine.leaf_matches.borrow();
+    assert_eq!(memo.len(), 1);
+    assert_eq!(memo[0].1.as_deref().map(|m| &m[..]), Some(&[0, 2][..]));
+}
+
+#[test]
+fn predicates_without_postings_are_scanned() {
+    let index = staged();
+    let engine = QueryEngine::new(&index);
+    let query = parse_query("size:<2 ext:rs main");
+
+    assert_eq!(engine.eval_query(&query), [0, 2]);
+    // `ext:` came from its postings; `size:` has none and is not kept.
+    let memo = engine.leaf_matches.borrow();
+    assert!(
+        memo.iter()
+            .all(|(pred, m)| m.is_some() == (pred.field == Field::Ext))
+    );
+}
+
+#[test]
+fn evaluation_leaves_its_buffers_for_the_next_query() {
+    let index = staged();
+    let query = parse_query("main NOT ext:py");
+
+    let engine = QueryEngine::new(&index);
+    assert_eq!(engine.eval_query(&query), [0, 2]);
+    drop(engine);
+
+    let engine = QueryEngine::new(&index);
+    assert!(engine.scratch.buffer().capacity() > 0);
+    assert_eq!(engine.eval_query(&query), [0, 2]);
+}
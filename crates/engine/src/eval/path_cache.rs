@@ -0,0 +1,98 @@
+use std::{cell::RefCell, sync::Arc};
+
+use hashbrown::HashMap;
+
+use crate::profile::{self, Op};
+use crate::{FileId, IndexReader};
+
+/// Full paths reconstructed while answering one query.
+///
+/// Text verification, the path-order filter, ranking and printing can all
+/// ask for the same file's path. The cache keeps each path (and its lowercase
+/// form, for ranking) so it is only rebuilt once per query. Verification only
+/// keeps paths that matched, so scanning many candidates does not fill it
+/// with files that are about to be dropped.
+///
+/// Like [`EvalCounters`](super::stats::EvalCounters), the cache is filled
+/// through `&self` and is meant for a single query on one thread.
+#[derive(Debug, Default)]
+pub struct PathCache {
+    paths: RefCell<HashMap<FileId, Arc<str>>>,
+    lower: RefCell<HashMap<FileId, Arc<str>>>,
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Full path of `fid`, reconstructed on first use.
+    pub fn path<I: IndexReader>(&self, index: &I, fid: FileId) -> Arc<str> {
+        if let Some(path) = self.paths.borrow().get(&fid) {
+            return Arc::clone(path);
+        }
+        let path: Arc<str> = reconstruct(index, fid).into();
+        self.paths.borrow_mut().insert(fid, Arc::clone(&path));
+        path
+    }
+
+    /// Lowercase full path of `fid`, computed on first use.
+    pub fn path_lower<I: IndexReader>(&self, index: &I, fid: FileId) -> Arc<str> {
+        if let Some(lower) = self.lower.borrow().get(&fid) {
+            return Arc::clone(lower);
+        }
+        let path = self.path(index, fid);
+        let lower = if path.bytes().any(|b| b.is_ascii_uppercase()) || !path.is_ascii() {
+            path.to_lowercase().into()
+        } else {
+            path
+        };
+        self.lower.borrow_mut().insert(fid, Arc::clone(&lower));
+        lower
+    }
+
+    /// Full path of `fid` for output, dropping it and its lowercase form
+    /// from the cache: printing is the last use, and long result lists
+    /// should not pile up here.
+    pub fn take<I: IndexReader>(&self, index: &I, fid: FileId) -> Arc<str> {
+        self.lower.borrow_mut().remove(&fid);
+        let cached = self.paths.borrow_mut().remove(&fid);
+        cached.unwrap_or_else(|| reconstruct(index, fid).into())
+    }
+
+    /// Test `fid`'s full path with `pred`, keeping the path only if it matched.
+    pub fn path_matches<I: IndexReader>(
+        &self,
+        index: &I,
+        fid: FileId,
+        pred: impl FnOnce(&str) -> bool,
+    ) -> bool {
+        if let Some(path) = self.paths.borrow().get(&fid) {
+            return pred(path);
+        }
+        let path = reconstruct(index, fid);
+        let matched = pred(&path);
+        if matched {
+            self.paths.borrow_mut().insert(fid, path.into());
+        }
+        matched
+    }
+
+    /// Number of cached paths.
+    pub fn len(&self) -> usize {
+        self.paths.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.borrow().is_empty()
+    }
+}
+
+fn reconstruct<I: IndexReader>(index: &I, fid: FileId) -> String {
+    let _span = profile::span(Op::PathReconstruct, 1);
+    index.reconstruct_full_path(fid)
+}
+
+#[cfg(test)]
+#[path = "path_cache_tests.rs"]
+mod tests;
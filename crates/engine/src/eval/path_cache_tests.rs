@@ -0,0 +1,52 @@
+use super::*;
+use crate::StagedIndex;
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &["Docs/Guide.md", "src/main.rs", "src/engine/query.rs"],
+    )
+}
+
+#[test]
+fn paths_are_built_once_and_shared() {
+    let index = staged();
+    let cache = PathCache::new();
+
+    let first = cache.path(&index, 0);
+    assert_eq!(&*first, "/data/Docs/Guide.md");
+    assert!(Arc::ptr_eq(&first, &cache.path(&index, 0)));
+    assert_eq!(&*cache.path_lower(&index, 0), "/data/docs/guide.md");
+
+    // Already-lowercase paths share one allocation.
+    let path = cache.path(&index, 1);
+    assert!(Arc::ptr_eq(&path, &cache.path_lower(&index, 1)));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn only_matching_paths_are_kept() {
+    let index = staged();
+    let cache = PathCache::new();
+
+    assert!(!cache.path_matches(&index, 1, |p| p.contains("engine")));
+    assert!(cache.path_matches(&index, 2, |p| p.contains("engine")));
+
+    assert_eq!(cache.len(), 1);
+    assert!(cache.path_matches(&index, 2, |p| p.ends_with("query.rs")));
+}
+
+#[test]
+fn taken_paths_leave_the_cache() {
+    let index = staged();
+    let cache = PathCache::new();
+
+    let cached = cache.path(&index, 0);
+    cache.path_lower(&index, 0);
+    assert!(Arc::ptr_eq(&cached, &cache.take(&index, 0)));
+    assert!(cache.is_empty());
+
+    // Paths that were never cached are built without being kept.
+    assert_eq!(&*cache.take(&index, 1), &*index.reconstruct_full_path(1));
+    assert!(cache.is_empty());
+}
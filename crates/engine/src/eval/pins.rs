@@ -0,0 +1,54 @@
+use blaze_runtime::PinSet;
+use hashbrown::HashMap;
+
+use crate::{FileId, IndexReader};
+
+/// How the pins apply to the files of one directory.
+enum DirPins {
+    /// The directory is pinned or lies below a pinned directory.
+    All,
+    /// Only files with these names are pinned.
+    Names(Vec<String>),
+}
+
+/// The subset of `candidates` covered by `pins`, in candidate order.
+///
+/// Each directory's path is rebuilt once, from the first candidate in it,
+/// so this stays cheap for large result sets.
+pub(crate) fn pinned_files<I: IndexReader>(
+    index: &I,
+    pins: &PinSet,
+    candidates: &[FileId],
+) -> Vec<FileId> {
+    if pins.is_empty() {
+        return Vec::new();
+    }
+
+    let mut dirs: HashMap<u32, DirPins> = HashMap::new();
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| {
+            let name = index.get_file_name(fid);
+            let dir = dirs.entry(index.get_file_dir_id(fid)).or_insert_with(|| {
+                let path = index.reconstruct_full_path(fid);
+                let dir_path = match path.strip_suffix(name).map(|p| p.trim_end_matches('/')) {
+                    Some("") => "/",
+                    Some(dir_path) => dir_path,
+                    None => return DirPins::Names(Vec::new()),
+                };
+
+                if pins.covers(dir_path) {
+                    DirPins::All
+                } else {
+                    DirPins::Names(pins.names_in(dir_path).map(str::to_owned).collect())
+                }
+            });
+
+            match dir {
+                DirPins::All => true,
+                DirPins::Names(names) => names.iter().any(|n| n == name),
+            }
+        })
+        .collect()
+}
@@ -1,9 +1,18 @@
 // TODO: See whether we can refactor the duplicate code
 use crate::{
-    Field, IndexReader, LeafExpr, Predicate, QueryExpr, TextTerm,
+    CmpOp, Field, IndexReader, LeafExpr, Predicate, Query, QueryExpr, TextAnchor, TextTerm, Value,
+    dsl::is_literal_name,
+    eval::{
+        predicates::negatable_ext,
+        text::{TextPlan, plan_text_term, seed_ext},
+    },
     trigram::{Trigram, build_trigrams_for_string},
 };
 
+/// A text term whose estimated cost is above this share of the index is
+/// too broad to seed a pure-text AND from.
+const BROAD_TERM_SHARE: f64 = 0.6;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Cost(pub u64);
 
@@ -39,9 +48,13 @@ pub fn estimate_cost_simple(expr: &QueryExpr) -> Cost {
 }
 
 fn estimate_predicate_cost_simple(pred: &Predicate) -> Cost {
+    if literal_name(pred).is_some() {
+        // Looked up in the exact name index.
+        return Cost(5);
+    }
     match pred.field {
-        Field::Ext => Cost(10),
-        Field::Size => Cost(20),
+        Field::Ext | Field::Lang | Field::Repo | Field::Hash => Cost(10),
+        Field::Size | Field::Name | Field::IName | Field::Pinned | Field::Noise => Cost(20),
         Field::Created => Cost(25),
         Field::Modified => Cost(25),
     }
@@ -73,7 +86,12 @@ fn estimate_cost_internal<I: IndexReader>(
 ) -> Cost {
     match expr {
         QueryExpr::Leaf(LeafExpr::Predicate(pred)) => {
-            estimate_predicate_cost::<I>(pred, candidate_count)
+            if let Some(name) = literal_name(pred)
+                && let Some(files) = index.files_named(name)
+            {
+                return Cost(files.len() as u64);
+            }
+            estimate_predicate_cost(pred, candidate_count)
         }
         QueryExpr::Leaf(LeafExpr::Text(term)) => estimate_text_term_cost(index, term),
         QueryExpr::Not(inner) => estimate_cost_internal(index, inner, candidate_count) + Cost(1),
@@ -85,17 +103,32 @@ fn estimate_cost_internal<I: IndexReader>(
     }
 }
 
-fn estimate_predicate_cost<I: IndexReader>(pred: &Predicate, candidate_count: usize) -> Cost {
+/// The name a `name:` or `iname:` predicate spells out, if it has no
+/// wildcards.
+fn literal_name(pred: &Predicate) -> Option<&str> {
+    match (&pred.field, &pred.value) {
+        (Field::Name | Field::IName, Value::Str(pattern)) if is_literal_name(pattern) => {
+            Some(pattern)
+        }
+        _ => None,
+    }
+}
+
+fn estimate_predicate_cost(pred: &Predicate, candidate_count: usize) -> Cost {
     let n = candidate_count as u64;
 
     match pred.field {
-        Field::Ext => Cost(n),
-        Field::Size => Cost(2 * n),
+        Field::Ext | Field::Lang | Field::Repo | Field::Hash => Cost(n),
+        Field::Size | Field::Name | Field::IName | Field::Pinned | Field::Noise => Cost(2 * n),
         Field::Created | Field::Modified => Cost(3 * n),
     }
 }
 
 pub fn estimate_text_term_cost<I: IndexReader>(index: &I, term: &TextTerm) -> Cost {
+    if term.anchor != TextAnchor::Anywhere {
+        return estimate_anchored_term_cost(index, term);
+    }
+
     let search_text = term.text.as_str();
     let trigrams: Vec<Trigram> = build_trigrams_for_string(search_text);
 
@@ -112,6 +145,8 @@ pub fn estimate_text_term_cost<I: IndexReader>(index: &I, term: &TextTerm) -> Co
 
     let file_threshold = (file_count as f64 * 0.30) as usize;
     let dir_threshold = (dir_count as f64 * 0.30) as usize;
+    // Terms without a `/` are seeded from file names, see `TextStrategy`.
+    let by_name = !search_text.contains('/') && index.has_name_trigrams();
 
     let mut file_cost: u64 = 0;
     let mut dir_cost: u64 = 0;
@@ -130,6 +165,11 @@ pub fn estimate_text_term_cost<I: IndexReader>(index: &I, term: &TextTerm) -> Co
             impossible = true;
             break;
         }
+        let f_len = if by_name {
+            index.name_trigram_postings_len(*tri)
+        } else {
+            f_len
+        };
 
         if f_len > 0 && f_len <= file_threshold {
             file_cost += f_len as u64;
@@ -160,3 +200,278 @@ pub fn estimate_text_term_cost<I: IndexReader>(index: &I, term: &TextTerm) -> Co
     // We should actively avoid this
     Cost::VERY_BAD
 }
+
+/// Anchored terms only look at file names, so they cost the postings they
+/// are seeded from: their extension's files, or their rarest filename
+/// trigrams.
+fn estimate_anchored_term_cost<I: IndexReader>(index: &I, term: &TextTerm) -> Cost {
+    let file_count = index.get_file_count();
+    if file_count == 0 {
+        return Cost::ZERO;
+    }
+
+    let needle = term.text.to_lowercase();
+    if term.anchor == TextAnchor::Whole
+        && let Some(files) = index.files_named(&needle)
+    {
+        return Cost(files.len() as u64);
+    }
+    if let Some(ext) = seed_ext(index, &needle, term.anchor)
+        && let Some(files) = index.files_with_ext(&ext)
+    {
+        return Cost(files.len() as u64);
+    }
+
+    let trigrams = build_trigrams_for_string(&needle);
+    if trigrams.is_empty() || !index.has_name_trigrams() {
+        return Cost::LINEAR_SCAN;
+    }
+
+    let threshold = (file_count as f64 * 0.30) as usize;
+    let mut cost: u64 = 0;
+    for tri in trigrams {
+        match index.name_trigram_postings_len(tri) {
+            0 => return Cost::ZERO,
+            len if len <= threshold => cost += len as u64,
+            _ => {}
+        }
+    }
+    if cost > 0 { Cost(cost) } else { Cost::VERY_BAD }
+}
+
+/// Order in which an AND evaluates `children`, cheapest first.
+///
+/// Index-aware costs are only worth computing when two or more text terms
+/// compete for the lead.
+pub(crate) fn order_and_children<'e, I: IndexReader>(
+    index: &I,
+    children: &'e [QueryExpr],
+) -> Vec<&'e QueryExpr> {
+    let text_terms: usize = children.iter().map(text_leaves).sum();
+
+    let mut ordered: Vec<&QueryExpr> = children.iter().collect();
+    if text_terms >= 2 {
+        ordered.sort_by_cached_key(|child| estimate_cost(index, child));
+    } else {
+        ordered.sort_by_key(|child| estimate_cost_simple(child));
+    }
+    ordered
+}
+
+/// Text terms `expr` consists of: 1 for a text term, the number of terms
+/// for an AND of text terms only, 0 otherwise.
+fn text_leaves(expr: &QueryExpr) -> usize {
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Text(_)) => 1,
+        QueryExpr::And(children)
+            if children
+                .iter()
+                .all(|c| matches!(c, QueryExpr::Leaf(LeafExpr::Text(_)))) =>
+        {
+            children.len()
+        }
+        _ => 0,
+    }
+}
+
+/// A term of a pure-text AND with its estimated cost.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RankedTerm<'t> {
+    pub cost: Cost,
+    pub term: &'t TextTerm,
+    /// Too broad to seed from while a more selective term exists.
+    pub broad: bool,
+}
+
+/// Terms of a pure-text AND, most selective first, and the position of the
+/// one to seed from: the most selective term that is not broad, or the
+/// cheapest if all are. `None` if some term cannot match at all.
+pub(crate) fn rank_conjunction_terms<'t, I: IndexReader>(
+    index: &I,
+    terms: &[&'t TextTerm],
+) -> Option<(Vec<RankedTerm<'t>>, usize)> {
+    let file_count = index.get_file_count();
+    let broad_threshold = (file_count as f64 * BROAD_TERM_SHARE) as u64;
+
+    let mut ranked = Vec::with_capacity(terms.len());
+    for &term in terms {
+        let cost = estimate_text_term_cost(index, term);
+
+        // Perfect anchor: this term cannot match any file in the index, so
+        // neither can the conjunction.
+        if cost == Cost::ZERO {
+            return None;
+        }
+
+        let broad = cost.0 > broad_threshold || cost == Cost::VERY_BAD || cost == Cost::LINEAR_SCAN;
+        ranked.push(RankedTerm { cost, term, broad });
+    }
+
+    ranked.sort_by_key(|t| t.cost);
+    let seed = ranked.iter().position(|t| !t.broad).unwrap_or(0);
+    Some((ranked, seed))
+}
+
+/// One node of a [`plan_query`] tree.
+#[derive(Debug, Clone)]
+pub struct PlanNode {
+    pub kind: PlanKind,
+    /// Estimated number of candidates this subtree touches.
+    pub cost: Cost,
+    /// Children in the order they are evaluated.
+    pub children: Vec<PlanNode>,
+    /// Child that drives an AND: its matches are the candidates of the rest.
+    pub seed: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlanKind {
+    /// `pure_text`: seeded from one term, then every term is verified in a
+    /// single pass; `impossible`: some term matches no file, so nothing runs.
+    And {
+        pure_text: bool,
+        impossible: bool,
+    },
+    /// `pure_text`: every term's trigram candidates are verified together.
+    Or {
+        pure_text: bool,
+    },
+    /// `ext_complement`: the child is an `ext:` predicate, so files are
+    /// skipped by extension without evaluating it.
+    Not {
+        ext_complement: bool,
+    },
+    Text {
+        term: String,
+        plan: TextPlan,
+    },
+    /// A field predicate, e.g. `ext = "rs"`.
+    Predicate(String),
+}
+
+/// How `query` would be evaluated against `index`, without running it.
+///
+/// Each leaf is planned as if every live file were a candidate; inside an
+/// AND the later children actually see fewer, so their real strategy can be
+/// cheaper.
+pub fn plan_query<I: IndexReader>(index: &I, query: &Query) -> PlanNode {
+    plan_expr(index, &query.expr, index.live_file_count())
+}
+
+fn plan_expr<I: IndexReader>(index: &I, expr: &QueryExpr, candidate_count: usize) -> PlanNode {
+    let cost = estimate_cost(index, expr);
+    let node = |kind, children, seed| PlanNode {
+        kind,
+        cost,
+        children,
+        seed,
+    };
+
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Text(term)) => node(
+            PlanKind::Text {
+                term: term.text.clone(),
+                plan: plan_text_term(index, term, candidate_count),
+            },
+            Vec::new(),
+            None,
+        ),
+        QueryExpr::Leaf(LeafExpr::Predicate(pred)) => node(
+            PlanKind::Predicate(describe_predicate(pred)),
+            Vec::new(),
+            None,
+        ),
+        QueryExpr::Not(inner) => node(
+            PlanKind::Not {
+                ext_complement: negatable_ext(inner).is_some(),
+            },
+            vec![plan_expr(index, inner, candidate_count)],
+            None,
+        ),
+        QueryExpr::Or(children) => node(
+            PlanKind::Or {
+                pure_text: pure_text_terms(children).is_some(),
+            },
+            children
+                .iter()
+                .map(|c| plan_expr(index, c, candidate_count))
+                .collect(),
+            None,
+        ),
+        QueryExpr::And(children) => match pure_text_terms(children) {
+            Some(terms) => {
+                let ranked = rank_conjunction_terms(index, &terms);
+                let impossible = ranked.is_none();
+                let (order, seed) = match ranked {
+                    Some((ranked, seed)) => (ranked.iter().map(|t| t.term).collect(), seed),
+                    None => (terms, 0),
+                };
+                let children = order
+                    .into_iter()
+                    .map(|term| {
+                        let leaf = QueryExpr::Leaf(LeafExpr::Text(term.clone()));
+                        plan_expr(index, &leaf, candidate_count)
+                    })
+                    .collect();
+                node(
+                    PlanKind::And {
+                        pure_text: true,
+                        impossible,
+                    },
+                    children,
+                    Some(seed),
+                )
+            }
+            None => node(
+                PlanKind::And {
+                    pure_text: false,
+                    impossible: false,
+                },
+                order_and_children(index, children)
+                    .into_iter()
+                    .map(|c| plan_expr(index, c, candidate_count))
+                    .collect(),
+                (!children.is_empty()).then_some(0),
+            ),
+        },
+    }
+}
+
+/// The text terms of `children`, if there are at least two and nothing else.
+///
+/// Anchored terms are left to the general path: the shared passes check
+/// needles anywhere in the path.
+pub(crate) fn pure_text_terms(children: &[QueryExpr]) -> Option<Vec<&TextTerm>> {
+    let terms: Vec<&TextTerm> = children
+        .iter()
+        .map_while(|c| match c {
+            QueryExpr::Leaf(LeafExpr::Text(t)) if t.anchor == TextAnchor::Anywhere => Some(t),
+            _ => None,
+        })
+        .collect();
+    (terms.len() >= 2 && terms.len() == children.len()).then_some(terms)
+}
+
+fn describe_predicate(pred: &Predicate) -> String {
+    let field = format!("{:?}", pred.field).to_ascii_lowercase();
+    let op = match pred.op {
+        CmpOp::Eq => "=",
+        CmpOp::Ne => "!=",
+        CmpOp::Gt => ">",
+        CmpOp::Ge => ">=",
+        CmpOp::Lt => "<",
+        CmpOp::Le => "<=",
+    };
+    let value = match &pred.value {
+        Value::Str(s) => format!("{s:?}"),
+        Value::SizeBytes(n) => format!("{n} bytes"),
+        Value::Bool(b) => b.to_string(),
+        Value::Time(t) => format!("{t:?}"),
+        Value::Noise(flags) => format!("{flags:?}"),
+    };
+    format!("{field} {op} {value}")
+}
+
+#[cfg(test)]
+#[path = "planner_tests.rs"]
+mod tests;
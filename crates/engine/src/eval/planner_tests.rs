@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+
+use super::*;
+use crate::{IndexBuilder, StagedIndex, TextStrategy, parse_query};
+
+/// Enough notes to get past the small-candidate cutoff, plus one rare file.
+fn staged() -> StagedIndex {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder
+        .add_batch((0..3_000).map(|i| FileRecord::test_file(root.join(format!("notes/n{i}.txt")))));
+    builder.add_batch([FileRecord::test_file(root.join("notes/zebra.txt"))]);
+    builder.finish()
+}
+
+fn text_plan(node: &PlanNode) -> &TextPlan {
+    match &node.kind {
+        PlanKind::Text { plan, .. } => plan,
+        other => panic!("not a text leaf: {other:?}"),
+    }
+}
+
+#[test]
+fn pure_text_and_seeds_from_the_rare_term() {
+    let index = staged();
+    let plan = plan_query(&index, &parse_query("notes zebra"));
+
+    assert!(matches!(
+        plan.kind,
+        PlanKind::And {
+            pure_text: true,
+            impossible: false
+        }
+    ));
+    let seed = &plan.children[plan.seed.unwrap()];
+    let zebra = text_plan(seed);
+    assert_eq!(zebra.needle, "zebra");
+    assert!(matches!(zebra.strategy, TextStrategy::NameTrigrams(ref t) if !t.is_empty()));
+    assert!(zebra.name_trigrams.iter().all(|&(_, len)| len == 1));
+
+    // Every file has "notes" in its path, but only a directory is named
+    // like it.
+    let notes = text_plan(&plan.children[1]);
+    assert_eq!(
+        notes.strategy,
+        TextStrategy::NameTrigrams(Default::default())
+    );
+}
+
+#[test]
+fn terms_with_a_slash_use_path_trigrams() {
+    let index = staged();
+
+    let plan = plan_query(&index, &parse_query("notes/zebra"));
+    let zebra = text_plan(&plan);
+    assert!(matches!(zebra.strategy, TextStrategy::Trigrams(ref t) if !t.is_empty()));
+    assert!(zebra.trigrams.iter().all(|&(_, len)| len == 1));
+    assert!(zebra.name_trigrams.is_empty());
+
+    // Every file has "notes" in its path: too broad to intersect.
+    let plan = plan_query(&index, &parse_query("/notes"));
+    assert_eq!(text_plan(&plan).strategy, TextStrategy::PathScan);
+}
+
+#[test]
+fn missing_trigram_and_short_needles_are_reported() {
+    let index = staged();
+
+    let plan = plan_query(&index, &parse_query("qqqq"));
+    assert_eq!(plan.cost, Cost::ZERO);
+    assert_eq!(text_plan(&plan).strategy, TextStrategy::NoMatch);
+
+    let plan = plan_query(&index, &parse_query("ze"));
+    assert_eq!(text_plan(&plan).strategy, TextStrategy::NameScan);
+}
+
+#[test]
+fn mixed_and_puts_the_cheapest_child_first() {
+    let index = staged();
+    let plan = plan_query(&index, &parse_query("ext:txt zebra"));
+
+    assert_eq!(plan.seed, Some(0));
+    assert!(matches!(&plan.children[0].kind, PlanKind::Predicate(p) if p == "ext = \"txt\""));
+    assert!(matches!(&plan.children[1].kind, PlanKind::Text { term, .. } if term == "zebra"));
+}
+
+#[test]
+fn negated_ext_is_planned_as_a_complement() {
+    let index = staged();
+
+    let plan = plan_query(&index, &parse_query("NOT ext:txt"));
+    assert!(matches!(
+        plan.kind,
+        PlanKind::Not {
+            ext_complement: true
+        }
+    ));
+
+    let plan = plan_query(&index, &parse_query("NOT zebra"));
+    assert!(matches!(
+        plan.kind,
+        PlanKind::Not {
+            ext_complement: false
+        }
+    ));
+}
+
+#[test]
+fn anchored_terms_are_planned_one_by_one() {
+    let index = staged();
+    let plan = plan_query(&index, &parse_query("notes ^zebra"));
+
+    assert!(matches!(
+        plan.kind,
+        PlanKind::And {
+            pure_text: false,
+            ..
+        }
+    ));
+    // Each of "zebra"'s three trigrams is in one file name; every path
+    // has "notes".
+    let seed = text_plan(&plan.children[0]);
+    assert_eq!(seed.needle, "zebra");
+    assert_eq!(plan.children[0].cost, Cost(3));
+
+    let plan = plan_query(&index, &parse_query(".txt$"));
+    assert_eq!(plan.cost, Cost(3_001));
+    assert_eq!(
+        text_plan(&plan).strategy,
+        TextStrategy::Extension("txt".into())
+    );
+}
@@ -1,8 +1,17 @@
+use std::borrow::Cow;
+
+use blaze_runtime::{PinSet, canonical_ext, lang_by_name};
 use chrono::{DateTime, Utc};
 
 use crate::{
-    Field, FileId, IndexReader, Predicate, Value,
-    eval::helpers::{cmp_i64, cmp_str_ci, cmp_u64, resolve_time_expr},
+    CmpOp, Field, FileId, IndexReader, LeafExpr, Predicate, QueryExpr, RepoId, Value,
+    dsl::{compile_name_glob, is_literal_name},
+    eval::{
+        helpers::{
+            cmp_i64, cmp_str_ci, cmp_u64, diff_sorted, intersect_adaptive, resolve_time_expr,
+        },
+        pins::pinned_files,
+    },
 };
 
 pub fn eval_predicate<I: IndexReader>(
@@ -10,15 +19,231 @@ pub fn eval_predicate<I: IndexReader>(
     pred: &Predicate,
     candidates: &[FileId],
     now: DateTime<Utc>,
+    pins: &PinSet,
 ) -> Vec<FileId> {
     match pred.field {
         Field::Ext => eval_predicate_ext(index, pred, candidates),
         Field::Size => eval_predicate_size(index, pred, candidates),
         Field::Modified => eval_predicate_modified(index, pred, candidates, now),
         Field::Created => eval_predicate_created(index, pred, candidates, now),
+        Field::Repo => eval_predicate_repo(index, pred, candidates),
+        Field::Lang => eval_predicate_lang(index, pred, candidates),
+        Field::Hash => eval_predicate_hash(index, pred, candidates),
+        Field::Name | Field::IName => eval_predicate_name(index, pred, candidates),
+        Field::Pinned => eval_predicate_pinned(index, pred, candidates, pins),
+        Field::Noise => eval_predicate_noise(index, pred, candidates),
+    }
+}
+
+/// The extension `expr` matches if it is a plain `ext:` predicate, whose
+/// complement needs no evaluation of the predicate itself.
+pub(crate) fn negatable_ext(expr: &QueryExpr) -> Option<&str> {
+    match expr {
+        QueryExpr::Leaf(LeafExpr::Predicate(Predicate {
+            field: Field::Ext,
+            op: CmpOp::Eq,
+            value: Value::Str(ext),
+        })) => Some(ext),
+        _ => None,
+    }
+}
+
+/// Candidates whose extension is not `ext`.
+///
+/// Subtracts `excluded`, the files with the extension, when there are no
+/// more of them than candidates, and otherwise skips candidates by
+/// extension.
+pub(crate) fn eval_ext_complement<I: IndexReader>(
+    index: &I,
+    ext: &str,
+    excluded: Option<&[FileId]>,
+    candidates: &[FileId],
+) -> Vec<FileId> {
+    if let Some(excluded) = excluded
+        && excluded.len() <= candidates.len()
+    {
+        return diff_sorted(candidates, excluded);
+    }
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| !canonical_ext(index.get_file_ext(fid)).eq_ignore_ascii_case(ext))
+        .collect()
+}
+
+/// Every file `pred` matches, in index order, if the index lists them
+/// directly: `ext:` from the per-extension postings, and a name without
+/// wildcards from the exact-name index. Such a set costs little more to
+/// build than to intersect with candidates.
+pub(crate) fn indexed_matches<'i, I: IndexReader>(
+    index: &'i I,
+    pred: &Predicate,
+) -> Option<Cow<'i, [FileId]>> {
+    match (pred.field, pred.op, &pred.value) {
+        // Files without an extension have no postings.
+        (Field::Ext, CmpOp::Eq, Value::Str(ext)) if !ext.is_empty() => index.files_with_ext(ext),
+        (Field::Name | Field::IName, _, Value::Str(pattern)) if is_literal_name(pattern) => {
+            let named = index.files_named(pattern)?;
+            if pred.field == Field::IName {
+                return Some(Cow::Owned(named));
+            }
+            Some(
+                named
+                    .into_iter()
+                    .filter(|&fid| index.get_file_name(fid) == pattern)
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn eval_predicate_noise<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+) -> Vec<u32> {
+    let Value::Noise(wanted) = pred.value else {
+        return Vec::new();
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| index.get_file_noise_bits(fid).intersects(wanted))
+        .collect()
+}
+
+fn eval_predicate_pinned<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+    pins: &PinSet,
+) -> Vec<u32> {
+    let Value::Bool(wanted) = pred.value else {
+        return Vec::new();
+    };
+
+    let pinned = pinned_files(index, pins, candidates);
+    if wanted {
+        pinned
+    } else {
+        diff_sorted(candidates, &pinned)
     }
 }
 
+fn eval_predicate_name<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+) -> Vec<u32> {
+    let Value::Str(ref pattern) = pred.value else {
+        return Vec::new();
+    };
+
+    // A pattern without wildcards is looked up by name instead of matched
+    // against every candidate.
+    if let Some(named) = indexed_matches(index, pred) {
+        return intersect_adaptive(candidates, &named);
+    }
+
+    let Some(glob) = compile_name_glob(pattern, pred.field == Field::IName) else {
+        return Vec::new();
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| glob.is_match(index.get_file_name(fid)))
+        .collect()
+}
+
+fn eval_predicate_hash<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+) -> Vec<u32> {
+    let Value::Str(ref wanted) = pred.value else {
+        return Vec::new();
+    };
+
+    let hash = if wanted.starts_with('/') {
+        stored_hash_of(index, wanted)
+    } else {
+        u64::from_str_radix(wanted, 16).ok()
+    };
+    let Some(hash) = hash else {
+        return Vec::new();
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| index.get_file_hash(fid) == Some(hash))
+        .collect()
+}
+
+/// Stored content hash of the indexed file at `path`, if it was hashed.
+fn stored_hash_of<I: IndexReader>(index: &I, path: &str) -> Option<u64> {
+    let name = path.rsplit('/').next()?;
+    (0..index.get_file_count() as FileId)
+        // Compare names first to avoid rebuilding every path.
+        .filter(|&fid| index.get_file_name(fid) == name && !index.is_file_removed(fid))
+        .find(|&fid| index.reconstruct_full_path(fid) == path)
+        .and_then(|fid| index.get_file_hash(fid))
+}
+
+fn eval_predicate_lang<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+) -> Vec<u32> {
+    let Value::Str(ref wanted) = pred.value else {
+        return Vec::new();
+    };
+    let Some(lang) = lang_by_name(wanted) else {
+        return Vec::new();
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| index.get_file_lang(fid) == Some(lang))
+        .collect()
+}
+
+fn eval_predicate_repo<I: IndexReader>(
+    index: &I,
+    pred: &Predicate,
+    candidates: &[u32],
+) -> Vec<u32> {
+    let Value::Str(ref wanted) = pred.value else {
+        return Vec::new();
+    };
+
+    // Repository names need not be unique, so collect every match.
+    let repos: Vec<RepoId> = if wanted.starts_with('/') {
+        let wanted = wanted.trim_end_matches('/');
+        (0..index.repo_count() as RepoId)
+            .filter(|&r| index.get_repo_root_path(r).trim_end_matches('/') == wanted)
+            .collect()
+    } else {
+        (0..index.repo_count() as RepoId)
+            .filter(|&r| index.get_repo_name(r).eq_ignore_ascii_case(wanted))
+            .collect()
+    };
+
+    if repos.is_empty() {
+        return Vec::new();
+    }
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&fid| index.get_file_repo(fid).is_some_and(|r| repos.contains(&r)))
+        .collect()
+}
+
 fn eval_predicate_size<I: IndexReader>(
     index: &I,
     pred: &Predicate,
@@ -44,8 +269,9 @@ fn eval_predicate_ext<I: IndexReader>(index: &I, pred: &Predicate, candidates: &
     };
 
     let mut out = Vec::new();
+    // `wanted` is already canonical; stored extensions may be aliases.
     for &fid in candidates {
-        let ext = index.get_file_ext(fid);
+        let ext = canonical_ext(index.get_file_ext(fid));
         if cmp_str_ci(ext, wanted, pred.op) {
             out.push(fid);
         }
@@ -1,11 +1,19 @@
 mod path_order;
 mod scoring;
 
+use std::{cmp::Ordering, sync::Arc};
+
+use blaze_runtime::{BoostRules, PinSet};
 use chrono::{DateTime, Utc};
+use hashbrown::{HashMap, HashSet};
 
 pub use path_order::apply_path_order_filter;
 
-use crate::{FileId, IndexReader, LeafExpr, Query, QueryExpr, flags::NoiseFlags};
+use crate::{
+    DirId, FileId, IndexReader, LeafExpr, Query, QueryExpr, TextAnchor, TextTerm,
+    eval::{boosts::boosted_files, path_cache::PathCache, pins::pinned_files},
+    flags::NoiseFlags,
+};
 
 /**
 Extracted features for a single file, used during ranking.
@@ -20,9 +28,11 @@ struct FileFeatures<'a, I: IndexReader> {
     /// The file ID in the index.
     fid: FileId,
     /// Cached lowercase full path (computed on first access).
-    full_path_lower: Option<String>,
+    full_path_lower: Option<Arc<str>>,
     /// Reference to the index for lazy lookups.
     index: &'a I,
+    /// Paths already reconstructed for this query.
+    paths: &'a PathCache,
     /// Last modified time as Unix epoch seconds.
     modified_epoch: i64,
     /// Cached lowercase filename (computed on first access).
@@ -36,9 +46,10 @@ struct FileFeatures<'a, I: IndexReader> {
 impl<'a, I: IndexReader> FileFeatures<'a, I> {
     /// Extract features for a file from the index.
     #[inline]
-    pub fn extract(index: &'a I, fid: FileId) -> Self {
+    pub fn extract(index: &'a I, paths: &'a PathCache, fid: FileId) -> Self {
         Self {
             index,
+            paths,
             fid,
             name_lower: None,
             full_path_lower: None,
@@ -49,6 +60,12 @@ impl<'a, I: IndexReader> FileFeatures<'a, I> {
         }
     }
 
+    /// Get the file ID.
+    #[inline]
+    pub fn fid(&self) -> FileId {
+        self.fid
+    }
+
     /// Get the file extension.
     #[inline]
     pub fn ext(&self) -> &str {
@@ -87,9 +104,7 @@ impl<'a, I: IndexReader> FileFeatures<'a, I> {
     #[inline]
     pub fn full_path_lower(&mut self) -> Option<&str> {
         if self.full_path_lower.is_none() {
-            let full_path = self.index.reconstruct_full_path(self.fid);
-            let full_path_lower = full_path.to_lowercase();
-            self.full_path_lower = Some(full_path_lower);
+            self.full_path_lower = Some(self.paths.path_lower(self.index, self.fid));
         }
         self.full_path_lower.as_deref()
     }
@@ -98,16 +113,48 @@ impl<'a, I: IndexReader> FileFeatures<'a, I> {
 pub struct RankingContext {
     /// Text terms extracted from the query, lowercased for matching.
     pub terms: Vec<String>,
+    /// Anchored text terms, lowercased, which only score against the file
+    /// name.
+    pub anchored: Vec<(String, TextAnchor)>,
     /// Current time for recency scoring.
     pub now: DateTime<Utc>,
+    /// Hits covered by the user's pins.
+    pub pinned: HashSet<FileId>,
+    /// Files named exactly as a text term, from the exact name index.
+    pub exact_names: HashSet<FileId>,
+    /// Configured boosts of hits under boosted directories.
+    pub boosted: HashMap<FileId, i32>,
+}
+
+/// What the user asked to lift in the results, beyond the query itself.
+#[derive(Debug, Clone, Copy)]
+pub struct RankBoosts<'a> {
+    /// Pinned paths, which outrank everything else.
+    pub pins: &'a PinSet,
+    /// Constant boosts for files under configured directories.
+    pub dirs: &'a BoostRules,
 }
 
 impl RankingContext {
     /// Create a new ranking context from a query.
     pub fn from_query(query: &Query, now: DateTime<Utc>) -> Self {
-        let mut terms = Vec::new();
-        collect_text_terms(&query.expr, &mut terms);
-        Self { terms, now }
+        let mut all = Vec::new();
+        collect_text_terms(&query.expr, &mut all);
+        let (mut terms, mut anchored) = (Vec::new(), Vec::new());
+        for term in all {
+            match term.anchor {
+                TextAnchor::Anywhere => terms.push(term.text.to_lowercase()),
+                anchor => anchored.push((term.text.to_lowercase(), anchor)),
+            }
+        }
+        Self {
+            terms,
+            anchored,
+            now,
+            pinned: HashSet::new(),
+            exact_names: HashSet::new(),
+            boosted: HashMap::new(),
+        }
     }
 }
 
@@ -118,6 +165,9 @@ impl RankingContext {
 /// 2. Computes a score for each file
 /// 3. Returns top results sorted by score (descending)
 ///
+/// Files with equal scores are ordered by ascending [`FileId`], so the same
+/// query against the same index ranks the same way on every run.
+///
 /// `limit = None` means "no explicit limit" (return all hits, ranked).
 /// `limit = Some(0)` returns an empty result immediately.
 pub fn rank<I: IndexReader>(
@@ -126,12 +176,35 @@ pub fn rank<I: IndexReader>(
     hits: &[FileId],
     now: DateTime<Utc>,
     limit: Option<usize>,
+    boosts: RankBoosts<'_>,
+    paths: &PathCache,
 ) -> Vec<FileId> {
+    rank_scored(index, query, hits, now, limit, boosts, paths)
+        .into_iter()
+        .map(|(fid, _)| fid)
+        .collect()
+}
+
+/// Like [`rank`], but keeps each file's relevance score.
+///
+/// Scores are only comparable within a single query.
+pub fn rank_scored<I: IndexReader>(
+    index: &I,
+    query: &Query,
+    hits: &[FileId],
+    now: DateTime<Utc>,
+    limit: Option<usize>,
+    boosts: RankBoosts<'_>,
+    paths: &PathCache,
+) -> Vec<(FileId, i32)> {
     if hits.is_empty() {
         return Vec::new();
     }
 
-    let ctx = RankingContext::from_query(query, now);
+    let mut ctx = RankingContext::from_query(query, now);
+    ctx.pinned = pinned_files(index, boosts.pins, hits).into_iter().collect();
+    ctx.boosted = boosted_files(index, boosts.dirs, hits);
+    ctx.exact_names = exact_name_files(index, &ctx);
 
     let effective_limit = match limit {
         None => hits.len(),
@@ -145,14 +218,14 @@ pub fn rank<I: IndexReader>(
     const TWO_PASS_RATIO: usize = 10; // hits / limit ratio
 
     if hits.len() > TWO_PASS_THRESHOLD && hits.len() / effective_limit > TWO_PASS_RATIO {
-        return rank_two_pass(index, &ctx, hits, effective_limit);
+        return rank_two_pass(index, &ctx, hits, effective_limit, paths);
     }
 
     // Single-pass ranking: extract features and compute full scores.
     let mut scored: Vec<(FileId, i32)> = hits
         .iter()
         .map(|&fid| {
-            let mut features = FileFeatures::extract(index, fid);
+            let mut features = FileFeatures::extract(index, paths, fid);
             let score = scoring::compute_score(&mut features, &ctx);
             (fid, score)
         })
@@ -161,19 +234,69 @@ pub fn rank<I: IndexReader>(
     // Use partial sort if we only need top N results.
     if effective_limit < scored.len() / 2 {
         // Partial sort: O(n + k log k) instead of O(n log n).
-        scored.select_nth_unstable_by(effective_limit, |a, b| {
-            b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))
-        });
+        scored.select_nth_unstable_by(effective_limit, by_rank);
         scored.truncate(effective_limit);
         // The prefix is unordered after select_nth, so sort it.
-        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.sort_by(by_rank);
     } else {
         // Full sort when limit is large relative to hits.
-        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.sort_by(by_rank);
         scored.truncate(effective_limit);
     }
 
-    scored.into_iter().map(|(fid, _)| fid).collect()
+    scored
+}
+
+/// Rank order: higher scores first, then lower file IDs. A total order, so
+/// the result never depends on the order hits arrive in.
+fn by_rank(a: &(FileId, i32), b: &(FileId, i32)) -> Ordering {
+    b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))
+}
+
+/// Files named exactly as one of `ctx`'s terms, ignoring case.
+fn exact_name_files<I: IndexReader>(index: &I, ctx: &RankingContext) -> HashSet<FileId> {
+    ctx.terms
+        .iter()
+        .chain(ctx.anchored.iter().map(|(term, _)| term))
+        .filter_map(|term| index.files_named(term))
+        .flatten()
+        .collect()
+}
+
+/// Order directories from [`QueryEngine::eval_dirs`](crate::QueryEngine::eval_dirs)
+/// for jumping to them: pinned directories first, then those whose own name
+/// matches a query term, then shallower paths.
+pub fn rank_dirs<I: IndexReader>(
+    index: &I,
+    query: &Query,
+    dirs: &[DirId],
+    pins: &PinSet,
+) -> Vec<DirId> {
+    let mut all = Vec::new();
+    collect_text_terms(&query.expr, &mut all);
+    let terms: Vec<String> = all.iter().map(|t| t.text.to_lowercase()).collect();
+
+    let mut keyed: Vec<((bool, u8, usize, String), DirId)> = dirs
+        .iter()
+        .map(|&d| {
+            let path = index.reconstruct_dir_path(d);
+            let name = index.get_dir_name(d).to_lowercase();
+            let name_match = terms
+                .iter()
+                .map(|t| match name.as_str() {
+                    n if n == t => 2,
+                    n if n.contains(t.as_str()) => 1,
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0);
+            let depth = path.matches('/').count();
+            ((!pins.covers(&path), 2 - name_match, depth, path), d)
+        })
+        .collect();
+    keyed.sort_unstable();
+
+    keyed.into_iter().map(|(_, d)| d).collect()
 }
 
 /// Two-pass ranking: quick score all, then full score only top candidates.
@@ -188,12 +311,13 @@ fn rank_two_pass<I: IndexReader>(
     ctx: &RankingContext,
     hits: &[FileId],
     limit: usize,
-) -> Vec<FileId> {
+    paths: &PathCache,
+) -> Vec<(FileId, i32)> {
     // Pass 1: Quick score all files using cheap features only.
     let mut quick_scored: Vec<(FileId, i32)> = hits
         .iter()
         .map(|&fid| {
-            let features = FileFeatures::extract(index, fid);
+            let features = FileFeatures::extract(index, paths, fid);
             let score = scoring::compute_quick_score(&features, ctx);
             (fid, score)
         })
@@ -201,31 +325,28 @@ fn rank_two_pass<I: IndexReader>(
 
     // Select top candidates with buffer (3x limit to ensure we don't miss good matches).
     let candidate_limit = (limit * 3).min(quick_scored.len());
-    quick_scored.select_nth_unstable_by(candidate_limit, |a, b| {
-        b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))
-    });
+    quick_scored.select_nth_unstable_by(candidate_limit, by_rank);
     quick_scored.truncate(candidate_limit);
 
     // Pass 2: Full score only the top candidates.
     let mut fully_scored: Vec<(FileId, i32)> = quick_scored
         .into_iter()
         .map(|(fid, _quick_score)| {
-            let mut features = FileFeatures::extract(index, fid);
+            let mut features = FileFeatures::extract(index, paths, fid);
             let score = scoring::compute_score(&mut features, ctx);
             (fid, score)
         })
         .collect();
 
     // Final sort and limit.
-    fully_scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    fully_scored.sort_by(by_rank);
     fully_scored.truncate(limit);
 
-    fully_scored.into_iter().map(|(fid, _)| fid).collect()
+    fully_scored
 }
 
-/// Recursively collect text terms from a query expression.
-/// Terms are lowercased here so we avoid a second allocation pass.
-fn collect_text_terms(expr: &QueryExpr, out: &mut Vec<String>) {
+/// Recursively collect the non-empty text terms of a query expression.
+fn collect_text_terms<'q>(expr: &'q QueryExpr, out: &mut Vec<&'q TextTerm>) {
     match expr {
         QueryExpr::And(children) | QueryExpr::Or(children) => {
             for child in children {
@@ -237,9 +358,13 @@ fn collect_text_terms(expr: &QueryExpr, out: &mut Vec<String>) {
         }
         QueryExpr::Leaf(LeafExpr::Text(term)) => {
             if !term.text.is_empty() {
-                out.push(term.text.to_lowercase());
+                out.push(term);
             }
         }
         QueryExpr::Leaf(_) => {}
     }
 }
+
+#[cfg(test)]
+#[path = "mod_tests.rs"]
+mod tests;
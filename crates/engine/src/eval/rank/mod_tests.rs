@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+use blaze_runtime::{BoostRules, PinSet};
+use chrono::{TimeZone, Utc};
+
+use super::*;
+use crate::{IndexBuilder, StagedIndex, parse_query};
+
+/// `count` files that score alike for `notes`: same name, same depth.
+fn look_alikes(count: usize) -> StagedIndex {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..count).map(|i| FileRecord::test_file(root.join(format!("d{i:04}/notes.txt")))),
+    );
+    builder.finish()
+}
+
+fn ranked(index: &StagedIndex, hits: &[FileId], limit: Option<usize>) -> Vec<(FileId, i32)> {
+    let (pins, dirs) = (PinSet::default(), BoostRules::default());
+    let boosts = RankBoosts {
+        pins: &pins,
+        dirs: &dirs,
+    };
+    let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let query = parse_query("notes");
+    rank_scored(index, &query, hits, now, limit, boosts, &PathCache::new())
+}
+
+#[test]
+fn ties_rank_by_file_id_whatever_the_hit_order() {
+    let index = look_alikes(8);
+    let hits: Vec<FileId> = (0..8).collect();
+    let mut shuffled = hits.clone();
+    shuffled.reverse();
+    shuffled.swap(1, 5);
+
+    let ranked_in_order = ranked(&index, &hits, None);
+    assert!(ranked_in_order.windows(2).all(|w| w[0].1 == w[1].1));
+    assert_eq!(
+        ranked_in_order
+            .iter()
+            .map(|&(fid, _)| fid)
+            .collect::<Vec<_>>(),
+        hits
+    );
+    assert_eq!(ranked(&index, &shuffled, None), ranked_in_order);
+    assert_eq!(ranked(&index, &shuffled, Some(3)), ranked_in_order[..3]);
+}
+
+#[test]
+fn two_pass_ranking_breaks_ties_the_same_way() {
+    // Enough hits per result to take the two-pass path.
+    let index = look_alikes(1500);
+    let mut hits: Vec<FileId> = (0..1500).collect();
+    hits.reverse();
+
+    let top: Vec<FileId> = ranked(&index, &hits, Some(5))
+        .into_iter()
+        .map(|(fid, _)| fid)
+        .collect();
+    assert_eq!(top, [0, 1, 2, 3, 4]);
+}
@@ -1,4 +1,4 @@
-use crate::{FileId, IndexReader, LeafExpr, Query, QueryExpr};
+use crate::{FileId, IndexReader, LeafExpr, PathCache, Query, QueryExpr, TextAnchor};
 
 /// Check if terms appear in order within a path.
 ///
@@ -35,11 +35,12 @@ pub fn terms_match_in_order(path_lower: &str, terms: &[&str]) -> bool {
 
 /// Extract plain text terms from query in order (for path-order filtering).
 ///
-/// Only collects bare text terms, ignoring field predicates like `ext:rs`.
+/// Only collects bare text terms, ignoring field predicates like `ext:rs`
+/// and anchored terms, whose place in the file name is already fixed.
 /// Terms are lowercased for case-insensitive matching.
 fn collect_text_terms_in_order(expr: &QueryExpr, out: &mut Vec<String>) {
     match expr {
-        QueryExpr::Leaf(LeafExpr::Text(term)) => {
+        QueryExpr::Leaf(LeafExpr::Text(term)) if term.anchor == TextAnchor::Anywhere => {
             let t = term.text.trim().to_lowercase();
             if !t.is_empty() {
                 out.push(t);
@@ -61,8 +62,9 @@ fn collect_text_terms_in_order(expr: &QueryExpr, out: &mut Vec<String>) {
         QueryExpr::Not(_) => {
             // Don't include negated terms in order check
         }
-        QueryExpr::Leaf(LeafExpr::Predicate(_)) => {
-            // Predicates don't participate in path-order matching
+        QueryExpr::Leaf(_) => {
+            // Predicates and anchored terms don't participate in path-order
+            // matching
         }
     }
 }
@@ -74,7 +76,8 @@ fn collect_text_terms_in_order(expr: &QueryExpr, out: &mut Vec<String>) {
 pub fn apply_path_order_filter<I: IndexReader>(
     index: &I,
     query: &Query,
-    file_ids: Vec<FileId>,
+    mut file_ids: Vec<FileId>,
+    paths: &PathCache,
 ) -> Vec<FileId> {
     let mut terms = Vec::new();
     collect_text_terms_in_order(&query.expr, &mut terms);
@@ -86,13 +89,11 @@ pub fn apply_path_order_filter<I: IndexReader>(
 
     let term_refs: Vec<&str> = terms.iter().map(|s| s.as_str()).collect();
 
+    file_ids.retain(|&fid| {
+        let path = paths.path_lower(index, fid);
+        terms_match_in_order(&path, &term_refs)
+    });
     file_ids
-        .into_iter()
-        .filter(|&fid| {
-            let path = index.reconstruct_full_path(fid).to_lowercase();
-            terms_match_in_order(&path, &term_refs)
-        })
-        .collect()
 }
 
 // #[cfg(test)]
@@ -1,5 +1,5 @@
 use crate::{
-    IndexReader,
+    IndexReader, TextAnchor,
     eval::rank::{FileFeatures, RankingContext},
     flags::NoiseFlags,
 };
@@ -12,12 +12,18 @@ const SCORE_NAME_PREFIX: i32 = 80;
 const SCORE_NAME_CONTAINS_BASE: i32 = 40;
 /// Minimum score for substring match.
 const SCORE_NAME_CONTAINS_MIN: i32 = 10;
+/// Filename starts or ends as an anchored term (`^read`, `.rs$`) asks.
+const SCORE_NAME_ANCHORED: i32 = 100;
 
 /// Path component exact match.
 const SCORE_PATH_COMPONENT: i32 = 30;
 /// Path contains term
 const SCORE_PATH_CONTAINS: i32 = 15;
 
+/// Pinned file, or a file below a pinned directory. Large enough to beat any
+/// combination of match, recency and noise scores.
+const SCORE_PINNED: i32 = 1000;
+
 /// Recency thresholds (in seconds).
 const SECS_PER_DAY: i64 = 86_400;
 const SECS_PER_WEEK: i64 = 7 * SECS_PER_DAY;
@@ -39,6 +45,7 @@ const PENALTY_HASHY_SEG: i32 = 40;
 const PENALTY_VERY_DEEP: i32 = 10;
 const PENALTY_APP_DATA_DIR: i32 = 50;
 const PENALTY_LOG_DIR: i32 = 40;
+const PENALTY_GENERATED: i32 = 80;
 
 // Depth at which we start penalising (components, not characters).
 const DEPTH_PENALTY_START: u8 = 8;
@@ -84,6 +91,8 @@ pub(super) fn compute_score<I: IndexReader>(
     score += score_recency(features, ctx);
     score += score_path_depth(features);
     score += score_type_category(features);
+    score += score_pinned(features, ctx);
+    score += score_dir_boost(features, ctx);
     score -= noise_penalty(features);
 
     score
@@ -95,6 +104,9 @@ pub(super) fn compute_score<I: IndexReader>(
 /// - Recency (cheap: just `modified_epoch`)
 /// - File type category (cheap: just extension)
 /// - Noise penalty (cheap: pre-computed flags)
+/// - Pins (cheap: resolved once per query)
+/// - Exact name matches (cheap: looked up once per query)
+/// - Directory boosts (cheap: matched once per directory)
 pub(super) fn compute_quick_score<I: IndexReader>(
     features: &FileFeatures<'_, I>,
     ctx: &RankingContext,
@@ -105,25 +117,61 @@ pub(super) fn compute_quick_score<I: IndexReader>(
     score += score_recency(features, ctx);
     score += score_type_category(features);
     score += score_path_depth(features);
+    score += score_pinned(features, ctx);
+    score += score_exact_name(features, ctx);
+    score += score_dir_boost(features, ctx);
     score -= noise_penalty(features);
 
     score
 }
 
+/// Exact filename bonus for files found in the exact name index, so the
+/// quick pass keeps them without comparing names.
+#[inline]
+fn score_exact_name<I: IndexReader>(features: &FileFeatures<'_, I>, ctx: &RankingContext) -> i32 {
+    if ctx.exact_names.contains(&features.fid()) {
+        SCORE_NAME_EXACT
+    } else {
+        0
+    }
+}
+
 /// Score based on filename matching query terms.
 /// Rewards matches in the following descending order:
-/// Exact match > Prefix match > Substring match (position-adjusted).
+/// Exact match > Anchored match > Prefix match > Substring match
+/// (position-adjusted).
 #[inline]
 pub(super) fn score_name_match<I: IndexReader>(
     features: &mut FileFeatures<'_, I>,
     ctx: &RankingContext,
 ) -> i32 {
-    if ctx.terms.is_empty() {
+    if ctx.terms.is_empty() && ctx.anchored.is_empty() {
         return 0;
     }
 
     let name_lower = features.name_lower();
-    sum_term_scores(ctx, |term| score_term_in_name(name_lower, term))
+    let anchored: i32 = ctx
+        .anchored
+        .iter()
+        .map(|(term, anchor)| score_anchored_in_name(name_lower, term, *anchor))
+        .sum();
+    anchored + sum_term_scores(ctx, |term| score_term_in_name(name_lower, term))
+}
+
+/// Score an anchored term against a filename.
+fn score_anchored_in_name(name: &str, term: &str, anchor: TextAnchor) -> i32 {
+    let holds = match anchor {
+        TextAnchor::Start => name.starts_with(term),
+        TextAnchor::End => name.ends_with(term),
+        TextAnchor::Whole | TextAnchor::Anywhere => false,
+    };
+    if name == term {
+        SCORE_NAME_EXACT
+    } else if holds {
+        SCORE_NAME_ANCHORED
+    } else {
+        0
+    }
 }
 
 /// Score a single term against a filename.
@@ -191,7 +239,8 @@ pub(super) fn score_recency<I: IndexReader>(
         NoiseFlags::BUILD_DIR
             | NoiseFlags::CACHE_DIR
             | NoiseFlags::APP_DATA_DIR
-            | NoiseFlags::LOG_DIR,
+            | NoiseFlags::LOG_DIR
+            | NoiseFlags::GENERATED,
     ) {
         return 0;
     }
@@ -205,6 +254,28 @@ pub(super) fn score_recency<I: IndexReader>(
         .unwrap_or(0)
 }
 
+/// Boost files the user pinned, regardless of noise or recency.
+#[inline]
+pub(super) fn score_pinned<I: IndexReader>(
+    features: &FileFeatures<'_, I>,
+    ctx: &RankingContext,
+) -> i32 {
+    if ctx.pinned.contains(&features.fid()) {
+        SCORE_PINNED
+    } else {
+        0
+    }
+}
+
+/// Constant boost configured for the file's directory.
+#[inline]
+pub(super) fn score_dir_boost<I: IndexReader>(
+    features: &FileFeatures<'_, I>,
+    ctx: &RankingContext,
+) -> i32 {
+    ctx.boosted.get(&features.fid()).copied().unwrap_or(0)
+}
+
 /// Score based on file type category.
 ///
 /// Documents and code files are boosted; binaries are penalized.
@@ -244,7 +315,8 @@ pub(super) fn score_type_category<I: IndexReader>(features: &FileFeatures<'_, I>
             | NoiseFlags::CACHE_DIR
             | NoiseFlags::APP_DATA_DIR
             | NoiseFlags::LOG_DIR
-            | NoiseFlags::SYSTEM_DIR,
+            | NoiseFlags::SYSTEM_DIR
+            | NoiseFlags::GENERATED,
     ) {
         base / 3
     } else {
@@ -263,6 +335,7 @@ pub(super) fn score_type_category<I: IndexReader>(features: &FileFeatures<'_, I>
 /// - Deeply nested paths
 /// - Application data directories
 /// - Log/debug directories
+/// - Generated trees recognised by their markers (`CACHEDIR.TAG`, ...)
 #[inline]
 pub(super) fn noise_penalty<I: IndexReader>(features: &FileFeatures<'_, I>) -> i32 {
     let flags = features.noise_flags();
@@ -289,6 +362,9 @@ pub(super) fn noise_penalty<I: IndexReader>(features: &FileFeatures<'_, I>) -> i
     if flags.contains(NoiseFlags::LOG_DIR) {
         penalty += PENALTY_LOG_DIR;
     }
+    if flags.contains(NoiseFlags::GENERATED) {
+        penalty += PENALTY_GENERATED;
+    }
 
     penalty
 }
@@ -0,0 +1,86 @@
+//! Id buffers reused across the stages of a query, and across queries.
+//!
+//! Evaluating a query builds many short-lived id lists: the candidate set,
+//! the survivors of each AND child, intermediate trigram intersections.
+//! [`Scratch`] hands out cleared buffers and takes them back once a stage is
+//! done with them, so a buffer grown by one stage is reused by the next.
+//!
+//! The free buffers live in a thread-local pool that an engine takes when it
+//! is created and gives back when it is dropped. A daemon thread answering
+//! many queries keeps its buffers between them, while concurrent queries on
+//! other threads never share one.
+
+use std::{cell::RefCell, mem};
+
+use crate::{index::FileId, profile};
+
+/// Buffers kept per thread; a query rarely has more in flight at once.
+const MAX_BUFFERS: usize = 8;
+
+/// Buffers that grew past this many ids are freed rather than kept, so one
+/// huge query does not pin its memory on the thread.
+const MAX_RETAINED_IDS: usize = 1 << 20;
+
+thread_local! {
+    static POOL: RefCell<Vec<Vec<FileId>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A pool of cleared id buffers for one query.
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    free: RefCell<Vec<Vec<FileId>>>,
+}
+
+impl Scratch {
+    /// The buffers earlier queries on this thread left behind.
+    pub(crate) fn from_thread() -> Self {
+        let free = POOL
+            .try_with(|pool| mem::take(&mut *pool.borrow_mut()))
+            .unwrap_or_default();
+        Self {
+            free: RefCell::new(free),
+        }
+    }
+
+    /// An empty buffer, reused if one is free.
+    pub(crate) fn buffer(&self) -> Vec<FileId> {
+        let buf = self.free.borrow_mut().pop();
+        profile::count_buffer(buf.is_some());
+        buf.unwrap_or_default()
+    }
+
+    /// A buffer holding a copy of `ids`.
+    pub(crate) fn copy_of(&self, ids: &[FileId]) -> Vec<FileId> {
+        let mut buf = self.buffer();
+        buf.extend_from_slice(ids);
+        buf
+    }
+
+    /// Hand `buf` back once its contents are no longer needed.
+    pub(crate) fn recycle(&self, mut buf: Vec<FileId>) {
+        if buf.capacity() == 0 || buf.capacity() > MAX_RETAINED_IDS {
+            return;
+        }
+        let mut free = self.free.borrow_mut();
+        if free.len() < MAX_BUFFERS {
+            buf.clear();
+            free.push(buf);
+        }
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let mut free = mem::take(self.free.get_mut());
+        // `try_with`: an engine may be dropped while the thread exits.
+        let _ = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            free.truncate(MAX_BUFFERS.saturating_sub(pool.len()));
+            pool.append(&mut free);
+        });
+    }
+}
+
+#[cfg(test)]
+#[path = "scratch_tests.rs"]
+mod tests;
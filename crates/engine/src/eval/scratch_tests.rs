@@ -0,0 +1,45 @@
+use std::thread;
+
+use super::*;
+
+#[test]
+fn recycled_buffers_are_handed_out_cleared() {
+    let scratch = Scratch::default();
+    let buf = scratch.copy_of(&[1, 2, 3]);
+    let capacity = buf.capacity();
+    scratch.recycle(buf);
+
+    let buf = scratch.buffer();
+    assert!(buf.is_empty());
+    assert_eq!(buf.capacity(), capacity);
+    assert!(scratch.free.borrow().is_empty());
+}
+
+#[test]
+fn unallocated_and_oversized_buffers_are_not_kept() {
+    let scratch = Scratch::default();
+    scratch.recycle(Vec::new());
+    scratch.recycle(Vec::with_capacity(MAX_RETAINED_IDS + 1));
+    assert!(scratch.free.borrow().is_empty());
+
+    for _ in 0..MAX_BUFFERS + 2 {
+        scratch.recycle(vec![0]);
+    }
+    assert_eq!(scratch.free.borrow().len(), MAX_BUFFERS);
+}
+
+#[test]
+fn buffers_outlive_a_query_on_their_thread_only() {
+    let scratch = Scratch::from_thread();
+    scratch.recycle(vec![7; 100]);
+    scratch.recycle(vec![7; 10]);
+    drop(scratch);
+
+    let elsewhere = thread::spawn(|| Scratch::from_thread().free.borrow().len());
+    assert_eq!(elsewhere.join().unwrap(), 0);
+
+    let scratch = Scratch::from_thread();
+    assert_eq!(scratch.free.borrow().len(), 2);
+    assert!(scratch.buffer().capacity() >= 10);
+    assert!(Scratch::from_thread().free.borrow().is_empty(), "taken");
+}
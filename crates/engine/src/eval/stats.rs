@@ -0,0 +1,41 @@
+use std::cell::Cell;
+
+/// Candidate counts collected while evaluating a query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvalStats {
+    /// Size of the candidate universe the query started from.
+    pub initial_candidates: usize,
+    /// Candidates handed to substring verification, summed over text leaves.
+    ///
+    /// For trigram-capable terms this is the trigram-filtered set; for terms
+    /// that fall back to a linear scan it is the whole candidate set.
+    pub trigram_candidates: usize,
+    /// Candidates that matched the full boolean expression.
+    pub verified_candidates: usize,
+}
+
+/// Interior-mutable counters threaded through evaluation.
+///
+/// Evaluation works on `&self`, so counters use `Cell` rather than `&mut`.
+#[derive(Debug, Default)]
+pub(crate) struct EvalCounters {
+    trigram_candidates: Cell<usize>,
+}
+
+impl EvalCounters {
+    #[inline]
+    pub(crate) fn add_trigram_candidates(&self, n: usize) {
+        self.trigram_candidates
+            .set(self.trigram_candidates.get().saturating_add(n));
+    }
+
+    #[inline]
+    pub(crate) fn trigram_candidates(&self) -> usize {
+        self.trigram_candidates.get()
+    }
+
+    #[inline]
+    pub(crate) fn reset(&self) {
+        self.trigram_candidates.set(0);
+    }
+}
@@ -0,0 +1,114 @@
+use std::borrow::Cow;
+
+use crate::{DirId, FileId, IndexReader};
+
+/// Directory id used for files directly under the index root.
+const ROOT_DIR: DirId = DirId::MAX;
+
+/// The directories at or below one directory of an index, for `--under`.
+///
+/// Membership is resolved once per query from the parent links, into a
+/// bitset over [`DirId`]s, so filtering candidates is a lookup of each
+/// file's `dir_id` rather than a string comparison on its reconstructed path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirSubtree {
+    /// The prefix covers the whole index.
+    All,
+    /// The prefix is not a directory of the index.
+    Empty,
+    /// The subtree of directory `top`: bit `d` of `bits` is set when
+    /// directory `d` is in it.
+    Dirs { top: DirId, bits: Vec<u64> },
+}
+
+impl DirSubtree {
+    /// Resolve the absolute directory `prefix` against `index`.
+    pub fn resolve<I: IndexReader>(index: &I, prefix: &str) -> Self {
+        let root = index.reconstruct_dir_path(ROOT_DIR);
+        let root = root.trim_end_matches('/');
+        let prefix = prefix.trim_end_matches('/');
+
+        if prefix == root
+            || root
+                .strip_prefix(prefix)
+                .is_some_and(|r| r.starts_with('/'))
+        {
+            return DirSubtree::All;
+        }
+        let Some(rel) = prefix.strip_prefix(root).and_then(|r| r.strip_prefix('/')) else {
+            return DirSubtree::Empty;
+        };
+
+        let components: Vec<&str> = rel.split('/').filter(|c| !c.is_empty()).collect();
+        match find_dir(index, &components) {
+            Some(top) => DirSubtree::Dirs {
+                top,
+                bits: descendants(index, top),
+            },
+            None => DirSubtree::Empty,
+        }
+    }
+
+    pub fn contains_dir(&self, dir: DirId) -> bool {
+        match self {
+            DirSubtree::All => true,
+            DirSubtree::Empty => false,
+            DirSubtree::Dirs { bits, .. } => bits
+                .get(dir as usize / 64)
+                .is_some_and(|word| word & (1 << (dir % 64)) != 0),
+        }
+    }
+
+    /// Every entry in the subtree, in `FileId` order; `None` for the whole
+    /// index.
+    pub fn files<'i, I: IndexReader>(&self, index: &'i I) -> Option<Cow<'i, [FileId]>> {
+        match self {
+            DirSubtree::All => None,
+            DirSubtree::Empty => Some(Cow::Borrowed(&[])),
+            DirSubtree::Dirs { top, .. } => Some(index.files_in_dir(*top, true)),
+        }
+    }
+
+    /// Keep the files whose directory is in the subtree, preserving order.
+    pub fn filter_files<I: IndexReader>(&self, index: &I, files: Vec<FileId>) -> Vec<FileId> {
+        match self {
+            DirSubtree::All => files,
+            DirSubtree::Empty => Vec::new(),
+            DirSubtree::Dirs { .. } => files
+                .into_iter()
+                .filter(|&fid| self.contains_dir(index.get_file_dir_id(fid)))
+                .collect(),
+        }
+    }
+}
+
+/// The directory reached from the root by `components`, matched by name.
+fn find_dir<I: IndexReader>(index: &I, components: &[&str]) -> Option<DirId> {
+    components.iter().try_fold(ROOT_DIR, |dir, name| {
+        index
+            .dir_children(dir)
+            .iter()
+            .copied()
+            .find(|&child| index.get_dir_name(child) == *name)
+    })
+}
+
+/// Bitset of `top` and every directory below it.
+fn descendants<I: IndexReader>(index: &I, top: DirId) -> Vec<u64> {
+    let mut bits = vec![0u64; index.dir_count().div_ceil(64)];
+    let mut stack = vec![top];
+    while let Some(dir) = stack.pop() {
+        let (word, bit) = (dir as usize / 64, 1 << (dir % 64));
+        // Guards against a parent cycle in a damaged index.
+        if bits[word] & bit != 0 {
+            continue;
+        }
+        bits[word] |= bit;
+        stack.extend_from_slice(index.dir_children(dir));
+    }
+    bits
+}
+
+#[cfg(test)]
+#[path = "subtree_tests.rs"]
+mod tests;
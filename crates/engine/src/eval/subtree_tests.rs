@@ -0,0 +1,93 @@
+use super::*;
+use crate::{QueryEngine, StagedIndex, parse_query};
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &[
+            "top.txt",
+            "src/main.rs",
+            "src/eval/text.rs",
+            "docs/src/guide.md",
+            "srcs/other.rs",
+        ],
+    )
+}
+
+fn under(index: &StagedIndex, prefix: &str) -> Vec<FileId> {
+    let all: Vec<FileId> = (0..index.get_file_count() as FileId).collect();
+    DirSubtree::resolve(index, prefix).filter_files(index, all)
+}
+
+fn names(index: &StagedIndex, files: &[FileId]) -> Vec<String> {
+    let mut names: Vec<String> = files
+        .iter()
+        .map(|&fid| index.reconstruct_full_path(fid))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn subtree_includes_nested_directories_only() {
+    let index = staged();
+
+    assert_eq!(
+        names(&index, &under(&index, "/data/src")),
+        ["/data/src/eval/text.rs", "/data/src/main.rs"]
+    );
+    assert_eq!(
+        names(&index, &under(&index, "/data/docs/src/")),
+        ["/data/docs/src/guide.md"]
+    );
+}
+
+#[test]
+fn root_and_above_cover_everything() {
+    let index = staged();
+
+    assert_eq!(DirSubtree::resolve(&index, "/data"), DirSubtree::All);
+    assert_eq!(DirSubtree::resolve(&index, "/"), DirSubtree::All);
+    assert_eq!(under(&index, "/data/").len(), index.get_file_count());
+}
+
+#[test]
+fn unknown_or_outside_prefixes_match_nothing() {
+    let index = staged();
+
+    for prefix in ["/data/nope", "/data/src/main.rs", "/elsewhere", "/dat"] {
+        assert_eq!(
+            DirSubtree::resolve(&index, prefix),
+            DirSubtree::Empty,
+            "prefix: {prefix}"
+        );
+    }
+}
+
+#[test]
+fn subtree_files_match_the_filter() {
+    let index = staged();
+
+    for prefix in ["/data/src", "/data/docs", "/data/nope"] {
+        let subtree = DirSubtree::resolve(&index, prefix);
+        assert_eq!(
+            subtree.files(&index).unwrap().into_owned(),
+            under(&index, prefix),
+            "prefix: {prefix}"
+        );
+    }
+    assert!(DirSubtree::All.files(&index).is_none());
+}
+
+#[test]
+fn queries_under_a_directory_only_evaluate_its_entries() {
+    let index = staged();
+    let engine = QueryEngine::new(&index).with_subtree(DirSubtree::resolve(&index, "/data/src"));
+
+    let hits = engine.eval_query(&parse_query("rs"));
+    assert_eq!(
+        names(&index, &hits),
+        ["/data/src/eval/text.rs", "/data/src/main.rs"]
+    );
+    assert_eq!(engine.stats().initial_candidates, 2);
+}
@@ -1,20 +1,88 @@
+use std::{borrow::Cow, mem};
+
+use blaze_runtime::{
+    DEFAULT_RARE_TRIGRAMS, PlannerOverrides, TrigramSelection, canonical_ext, normalize_ext,
+    planner_overrides,
+};
 use smallvec::SmallVec;
 
 use crate::{
-    FileId, IndexReader, TextTerm, Trigram, build_trigrams_for_string,
-    eval::helpers::intersect_adaptive_into, intersect_adaptive,
+    DirId, FileId, IndexReader, TextAnchor, TextTerm, Trigram, UnionBuilder, build_query_trigrams,
+    build_trigrams_for_string,
+    eval::{
+        helpers::{diff_sorted, intersect_adaptive_into},
+        path_cache::PathCache,
+        scratch::Scratch,
+        stats::EvalCounters,
+    },
+    flags::FileFlags,
+    intersect_adaptive,
+    profile::{self, Op},
+    union_sorted,
 };
 
-/// How many candidates are "small enough" to skip trigram intersection.
-const SMALL_CANDIDATE_CUTOFF: usize = 2_000;
-/// When current trigram-filtered candidate set is <= this, stop intersecting further trigrams
-/// and go straight to full verification.
-const EARLY_VERIFY_CUTOFF: usize = 256;
+/// Bounds of [`CandidateCutoffs::small`], which is otherwise one candidate
+/// per `SMALL_CANDIDATE_RATIO` files.
+const SMALL_CANDIDATE_MIN: usize = 256;
+const SMALL_CANDIDATE_MAX: usize = 50_000;
+const SMALL_CANDIDATE_RATIO: usize = 100;
+/// Bounds of [`CandidateCutoffs::early_verify`], which is otherwise one
+/// candidate per `EARLY_VERIFY_RATIO` files.
+const EARLY_VERIFY_MIN: usize = 64;
+const EARLY_VERIFY_MAX: usize = 8_192;
+const EARLY_VERIFY_RATIO: usize = 800;
 /// Skip trigrams that hit more than this fraction of all files (too common).
 const MAX_TRIGRAM_GLOBAL_SHARE: f64 = 0.30;
-/// Maximum number of trigrams to use per query.
-/// Using only the rarest N trigrams gives most of the filtering power.
-const MAX_TRIGRAMS_PER_QUERY: usize = 3;
+/// Needles at least this long are covered with sparse trigrams by
+/// [`TrigramSelection::Auto`] when none of their trigrams is rare on its own:
+/// trigrams that overlap come from the same part of a path, so spreading them
+/// over the needle filters harder for the same number of lookups.
+const SPARSE_MIN_NEEDLE: usize = 12;
+/// Candidate sets at least this large are verified in on-disk order; below
+/// it the sort costs more than the page faults it saves.
+const LOCALITY_SORT_MIN: usize = 1_024;
+
+/// Candidate-set sizes at which text evaluation changes strategy.
+///
+/// Postings grow with the index, so the larger it is, the more candidates
+/// it pays to verify directly instead of intersecting postings: both
+/// cutoffs scale with the number of files, within fixed bounds. At 200k
+/// files they are 2,000 and 250. [`PlannerOverrides`] can fix either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CandidateCutoffs {
+    /// Candidates that are "small enough" to skip trigram intersection.
+    pub small: usize,
+    /// Once trigram filtering has cut the candidates to this many, stop
+    /// intersecting further trigrams and go straight to verification.
+    pub early_verify: usize,
+}
+
+impl CandidateCutoffs {
+    /// Cutoffs for an index of `file_count` files, without overrides.
+    pub fn scaled(file_count: usize) -> Self {
+        Self {
+            small: (file_count / SMALL_CANDIDATE_RATIO)
+                .clamp(SMALL_CANDIDATE_MIN, SMALL_CANDIDATE_MAX),
+            early_verify: (file_count / EARLY_VERIFY_RATIO)
+                .clamp(EARLY_VERIFY_MIN, EARLY_VERIFY_MAX),
+        }
+    }
+
+    /// Cutoffs for `index`, with the user's overrides applied.
+    pub fn for_index<I: IndexReader>(index: &I) -> Self {
+        Self::scaled(index.get_file_count()).with_overrides(planner_overrides())
+    }
+
+    fn with_overrides(self, overrides: &PlannerOverrides) -> Self {
+        Self {
+            small: overrides.small_candidate_cutoff.value.unwrap_or(self.small),
+            early_verify: overrides
+                .early_verify_cutoff
+                .value
+                .unwrap_or(self.early_verify),
+        }
+    }
+}
 
 /// State derived from a single text term.
 struct TextSearchState {
@@ -22,6 +90,11 @@ struct TextSearchState {
     needle_lower: String,
     /// Pre-computed trigrams for the term.
     trigrams: Vec<Trigram>,
+    /// The term has no `/`, so it is looked up by file and directory name
+    /// rather than by path.
+    by_name: bool,
+    /// Anchored terms match the file name alone.
+    anchor: TextAnchor,
 }
 
 impl TextSearchState {
@@ -32,6 +105,8 @@ impl TextSearchState {
         Self {
             needle_lower: search.to_lowercase(),
             trigrams,
+            by_name: !term.text.contains('/'),
+            anchor: term.anchor,
         }
     }
 
@@ -39,13 +114,68 @@ impl TextSearchState {
     fn is_trigram_capable(&self) -> bool {
         !self.trigrams.is_empty()
     }
+
+    /// Whether a file name matches the term by itself.
+    #[inline]
+    fn name_matches(&self, name: &str) -> bool {
+        name_matches_anchored(name, &self.needle_lower, self.anchor)
+    }
+}
+
+/// Case-insensitive match of `needle_lower` against a file or directory
+/// name, at the position `anchor` requires.
+///
+/// `needle_lower` must already be lowercased.
+#[inline]
+pub(crate) fn name_matches_anchored(name: &str, needle_lower: &str, anchor: TextAnchor) -> bool {
+    if anchor == TextAnchor::Anywhere {
+        return contains_lowercase_ascii(name, needle_lower);
+    }
+
+    if name.is_ascii() {
+        let (h, n) = (name.as_bytes(), needle_lower.as_bytes());
+        return match anchor {
+            TextAnchor::Start => h.len() >= n.len() && h[..n.len()].eq_ignore_ascii_case(n),
+            TextAnchor::End => h.len() >= n.len() && h[h.len() - n.len()..].eq_ignore_ascii_case(n),
+            _ => h.eq_ignore_ascii_case(n),
+        };
+    }
+
+    let name = name.to_lowercase();
+    match anchor {
+        TextAnchor::Start => name.starts_with(needle_lower),
+        TextAnchor::End => name.ends_with(needle_lower),
+        _ => name == needle_lower,
+    }
+}
+
+/// Extension to seed an anchored needle from: `.rs$` is looked up in the
+/// extension table, and so is any suffix if the index has no filename
+/// trigrams to narrow it down with.
+///
+/// A file named just `.rs` has no extension, so, as with `ext:rs`, it is not
+/// found this way.
+pub(crate) fn seed_ext<I: IndexReader>(
+    index: &I,
+    needle_lower: &str,
+    anchor: TextAnchor,
+) -> Option<String> {
+    if anchor != TextAnchor::End {
+        return None;
+    }
+    let (stem, ext) = needle_lower.rsplit_once('.')?;
+    let ext = normalize_ext(ext)?;
+    let whole_ext = stem.is_empty();
+    let has_name_trigrams =
+        index.has_name_trigrams() && !build_trigrams_for_string(needle_lower).is_empty();
+    (whole_ext || !has_name_trigrams).then(|| canonical_ext(&ext).to_owned())
 }
 
 /// Case-insensitive substring match optimized for ASCII haystacks.
 ///
 /// `needle_lower` must already be lowercased.
 #[inline]
-fn contains_lowercase_ascii(haystack: &str, needle_lower: &str) -> bool {
+pub(crate) fn contains_lowercase_ascii(haystack: &str, needle_lower: &str) -> bool {
     if needle_lower.is_empty() {
         return true;
     }
@@ -85,13 +215,16 @@ pub fn extract_search_term(text: &str) -> &str {
 /// Evaluate a single text term against the index using full-path trigram filtering.
 ///
 /// Returns a *sorted* subset of `candidates`.
-pub fn eval_text_term<I: IndexReader>(
+pub(crate) fn eval_text_term<I: IndexReader>(
     index: &I,
     term: &TextTerm,
     candidates: &[FileId],
+    counters: &EvalCounters,
+    paths: &PathCache,
+    scratch: &Scratch,
 ) -> Vec<FileId> {
     let state = TextSearchState::new(term);
-    eval_text_base_with_state(index, &state, candidates)
+    eval_text_base_with_state(index, &state, candidates, counters, paths, scratch)
 }
 
 /// Filter candidates by checking *all* text terms in a single pass.
@@ -103,6 +236,7 @@ pub fn filter_candidates_by_all_terms<I: IndexReader>(
     index: &I,
     terms: &[&TextTerm],
     candidates: &[FileId],
+    paths: &PathCache,
 ) -> Vec<FileId> {
     if candidates.is_empty() || terms.is_empty() {
         return candidates.to_vec();
@@ -115,24 +249,18 @@ pub fn filter_candidates_by_all_terms<I: IndexReader>(
         .collect();
     let needle_refs: Vec<&str> = needles.iter().map(|s| s.as_str()).collect();
 
-    let mut out = Vec::with_capacity(candidates.len());
-
-    for &fid in candidates {
+    retain_in_locality_order(index, candidates, |fid| {
         // Fast path: try filename first (no path reconstruction).
         let name = index.get_file_name(fid);
         if path_contains_all_terms(name, &needle_refs) {
-            out.push(fid);
-            continue;
+            return true;
         }
 
         // Slow path: reconstruct full path only if needed.
-        let path = index.reconstruct_full_path(fid);
-        if path_contains_all_terms(&path, &needle_refs) {
-            out.push(fid);
-        }
-    }
-
-    out
+        paths.path_matches(index, fid, |path| {
+            path_contains_all_terms(path, &needle_refs)
+        })
+    })
 }
 
 /// Check whether *all* needles appear (case-insensitive) in the given path.
@@ -146,24 +274,155 @@ fn path_contains_all_terms(path: &str, needles: &[&str]) -> bool {
     true
 }
 
-/// Core implementation of text search against the base index.
-fn eval_text_base_with_state<I: IndexReader>(
+/// Check whether *any* needle appears (case-insensitive) in the given path.
+#[inline]
+fn path_contains_any_term(path: &str, needles: &[&str]) -> bool {
+    needles
+        .iter()
+        .any(|&needle| contains_lowercase_ascii(path, needle))
+}
+
+/// How a single text term should be resolved against a candidate set.
+enum TrigramSeed {
+    /// Trigrams prove that no candidate can contain the needle.
+    Empty,
+    /// Very short needle: scan filenames only.
+    NameScan,
+    /// Tiny candidate set or all trigrams too broad: scan filenames, then
+    /// full paths.
+    PathScan,
+    /// Trigram-filtered candidates that still need substring verification,
+    /// and those that match as they are, being below a directory whose name
+    /// has the needle.
+    Candidates {
+        paths: Vec<FileId>,
+        matched: Vec<FileId>,
+    },
+    /// Filename-trigram candidates, whose names still need verification,
+    /// and candidates that match as they are: those below a directory whose
+    /// name has the needle, or named exactly as an anchored needle.
+    Names {
+        names: Vec<FileId>,
+        matched: Vec<FileId>,
+    },
+}
+
+/// How a text term will be resolved, decided from trigram statistics alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextStrategy {
+    /// Some trigram of the needle is in no path, so nothing can match.
+    NoMatch,
+    /// Needle too short for trigrams: scan filenames only.
+    NameScan,
+    /// Few candidates, or every trigram too broad: scan filenames, then
+    /// full paths.
+    PathScan,
+    /// Intersect these trigrams' postings, rarest first, then verify the
+    /// survivors, except those below directories whose name has the needle,
+    /// which are found through the directory trigrams and match as they are.
+    Trigrams(SmallVec<[Trigram; 8]>),
+    /// Intersect these trigrams' filename postings and verify the names,
+    /// then, unless the term is anchored, add the files below directories
+    /// whose name has the needle. No trigrams means no file name has the
+    /// needle.
+    NameTrigrams(SmallVec<[Trigram; 8]>),
+    /// Anchored needle ending in an extension: take the files with this
+    /// canonical extension and check how their names end.
+    Extension(String),
+    /// Needle anchored at both ends: take the files with exactly this name
+    /// from the exact name section, which need no verification.
+    ExactName,
+}
+
+/// What the planner knows about a text term before running it.
+#[derive(Debug, Clone)]
+pub struct TextPlan {
+    /// Lowercased part of the term that is searched for.
+    pub needle: String,
+    /// Every trigram of the needle, with the length of its postings.
+    pub trigrams: Vec<(Trigram, usize)>,
+    /// Every trigram of the needle, with the length of its filename
+    /// postings; empty unless the term is looked up by name.
+    pub name_trigrams: Vec<(Trigram, usize)>,
+    /// Where in the file name the needle must be.
+    pub anchor: TextAnchor,
+    pub strategy: TextStrategy,
+    /// How the intersected trigrams were picked, if any are.
+    pub selection: Option<TrigramSelection>,
+}
+
+/// Plan `term` against `candidate_count` candidates without evaluating it.
+pub(crate) fn plan_text_term<I: IndexReader>(
+    index: &I,
+    term: &TextTerm,
+    candidate_count: usize,
+) -> TextPlan {
+    let state = TextSearchState::new(term);
+    let name_trigrams = if uses_name_trigrams(index, &state) {
+        state
+            .trigrams
+            .iter()
+            .map(|&tri| (tri, index.name_trigram_postings_len(tri)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let (strategy, selection) = choose_strategy_with_selection(index, &state, candidate_count);
+    TextPlan {
+        trigrams: state
+            .trigrams
+            .iter()
+            .map(|&tri| (tri, index.trigram_postings_len(tri)))
+            .collect(),
+        name_trigrams,
+        anchor: state.anchor,
+        strategy,
+        selection,
+        needle: state.needle_lower,
+    }
+}
+
+/// Decide how to resolve `state` against `candidate_count` candidates,
+/// picking the trigrams that are selective enough to be worth intersecting.
+fn choose_strategy<I: IndexReader>(
     index: &I,
     state: &TextSearchState,
-    candidates: &[FileId],
-) -> Vec<FileId> {
-    if candidates.is_empty() {
-        return Vec::new();
+    candidate_count: usize,
+) -> TextStrategy {
+    choose_strategy_with_selection(index, state, candidate_count).0
+}
+
+/// [`choose_strategy`], with how the intersected trigrams were picked if
+/// any are.
+fn choose_strategy_with_selection<I: IndexReader>(
+    index: &I,
+    state: &TextSearchState,
+    candidate_count: usize,
+) -> (TextStrategy, Option<TrigramSelection>) {
+    if candidate_count == 0 {
+        return (TextStrategy::NoMatch, None);
+    }
+
+    if state.anchor != TextAnchor::Anywhere {
+        return choose_anchored_strategy(index, state, candidate_count);
     }
 
-    // Very short needles or tiny candidate sets: just scan.
-    if !state.is_trigram_capable() || candidates.len() <= SMALL_CANDIDATE_CUTOFF {
-        return eval_short_text_linear_scan(index, &state.needle_lower, candidates);
+    // Very short needles only hint at filenames.
+    if !state.is_trigram_capable() {
+        return (TextStrategy::NameScan, None);
+    }
+
+    // Tiny candidate sets (small indexes, refined or already-filtered
+    // results): scanning is cheaper than intersecting postings, but still
+    // has to see directory names.
+    let cutoffs = CandidateCutoffs::for_index(index);
+    if candidate_count <= cutoffs.small {
+        return (TextStrategy::PathScan, None);
     }
 
     let file_count = index.get_file_count();
     if file_count == 0 {
-        return Vec::new();
+        return (TextStrategy::NoMatch, None);
     }
 
     // Choose informative trigrams, ordered by rarity.
@@ -175,7 +434,7 @@ fn eval_text_base_with_state<I: IndexReader>(
 
         if len == 0 {
             // Missing trigram => no file path contains the full needle.
-            return Vec::new();
+            return (TextStrategy::NoMatch, None);
         }
 
         if len <= threshold {
@@ -183,64 +442,554 @@ fn eval_text_base_with_state<I: IndexReader>(
         }
     }
 
+    // Directory names in a path make its trigrams common; a file's own
+    // name is far more selective.
+    if uses_name_trigrams(index, state) {
+        let mut names: SmallVec<[(Trigram, usize); 8]> = SmallVec::new();
+        let mut missing = false;
+        for &tri in &state.trigrams {
+            match index.name_trigram_postings_len(tri) {
+                0 => missing = true,
+                len if len <= threshold => names.push((tri, len)),
+                _ => {}
+            }
+        }
+        if missing {
+            return (TextStrategy::NameTrigrams(SmallVec::new()), None);
+        }
+        if !names.is_empty() {
+            let (selection, names) =
+                select_trigrams(configured_selection(), state, names, cutoffs.early_verify);
+            return (TextStrategy::NameTrigrams(names), Some(selection));
+        }
+    }
+
     if items.is_empty() {
         // All trigrams are too broad; trigram seeding doesn't help.
-        return eval_text_linear_scan_with_paths(index, &state.needle_lower, candidates);
+        return (TextStrategy::PathScan, None);
+    }
+
+    let (selection, items) =
+        select_trigrams(configured_selection(), state, items, cutoffs.early_verify);
+    (TextStrategy::Trigrams(items), Some(selection))
+}
+
+/// Anchored needles are only ever checked against file names, seeded from
+/// the extension table or the filename trigrams; path trigrams and directory
+/// names play no part.
+fn choose_anchored_strategy<I: IndexReader>(
+    index: &I,
+    state: &TextSearchState,
+    candidate_count: usize,
+) -> (TextStrategy, Option<TrigramSelection>) {
+    let cutoffs = CandidateCutoffs::for_index(index);
+    if candidate_count <= cutoffs.small {
+        return (TextStrategy::NameScan, None);
+    }
+    if state.anchor == TextAnchor::Whole && index.has_exact_names() {
+        return (TextStrategy::ExactName, None);
+    }
+    if let Some(ext) = seed_ext(index, &state.needle_lower, state.anchor) {
+        return (TextStrategy::Extension(ext), None);
+    }
+    if !uses_name_trigrams(index, state) {
+        return (TextStrategy::NameScan, None);
     }
 
+    let threshold = (index.get_file_count() as f64 * MAX_TRIGRAM_GLOBAL_SHARE) as usize;
+    let mut names: SmallVec<[(Trigram, usize); 8]> = SmallVec::new();
+    for &tri in &state.trigrams {
+        match index.name_trigram_postings_len(tri) {
+            0 => return (TextStrategy::NoMatch, None),
+            len if len <= threshold => names.push((tri, len)),
+            _ => {}
+        }
+    }
+    if names.is_empty() {
+        return (TextStrategy::NameScan, None);
+    }
+    let (selection, names) =
+        select_trigrams(configured_selection(), state, names, cutoffs.early_verify);
+    (TextStrategy::NameTrigrams(names), Some(selection))
+}
+
+/// The [`TrigramSelection`] set in the `[planner]` table or the
+/// environment.
+fn configured_selection() -> TrigramSelection {
+    planner_overrides()
+        .trigram_selection
+        .value
+        .unwrap_or_default()
+}
+
+/// The trigrams to intersect out of the selective `items` of `state`, each
+/// with its posting count, by `selection`; rarest first. Returns the
+/// selection used, which is never [`TrigramSelection::Auto`].
+fn select_trigrams(
+    selection: TrigramSelection,
+    state: &TextSearchState,
+    mut items: SmallVec<[(Trigram, usize); 8]>,
+    early_verify: usize,
+) -> (TrigramSelection, SmallVec<[Trigram; 8]>) {
     items.sort_unstable_by_key(|&(_, len)| len);
-    items.truncate(MAX_TRIGRAMS_PER_QUERY);
+    let selection = match selection {
+        TrigramSelection::Auto => auto_selection(&state.needle_lower, &items, early_verify),
+        selection => selection,
+    };
+
+    match selection {
+        TrigramSelection::RareFirst(k) => items.truncate(k),
+        TrigramSelection::Sparse => {
+            let sparse = build_query_trigrams(&state.needle_lower);
+            if items.iter().any(|(tri, _)| sparse.contains(tri)) {
+                items.retain(|(tri, _)| sparse.contains(tri));
+            } else {
+                // Every sparse trigram is too broad: the rarest still helps.
+                items.truncate(1);
+            }
+        }
+        TrigramSelection::Full | TrigramSelection::Auto => {}
+    }
+    (selection, items.into_iter().map(|(tri, _)| tri).collect())
+}
+
+/// The selection [`TrigramSelection::Auto`] makes for `needle`, whose
+/// selective trigrams are `items`, rarest first.
+///
+/// - With no more selective trigrams than the rarest few, all of them.
+/// - If the rarest already cuts the candidates down to the point where they
+///   are verified, intersection stops there anyway: the rarest few.
+/// - Otherwise every trigram helps, so all of them for short needles, and
+///   trigrams spread over the needle for long ones.
+fn auto_selection(
+    needle: &str,
+    items: &[(Trigram, usize)],
+    early_verify: usize,
+) -> TrigramSelection {
+    match items.first() {
+        _ if items.len() <= DEFAULT_RARE_TRIGRAMS => TrigramSelection::Full,
+        Some(&(_, rarest)) if rarest <= early_verify => {
+            TrigramSelection::RareFirst(DEFAULT_RARE_TRIGRAMS)
+        }
+        _ if needle.len() >= SPARSE_MIN_NEEDLE => TrigramSelection::Sparse,
+        _ => TrigramSelection::Full,
+    }
+}
+
+/// Whether `state` is looked up through filename trigrams.
+fn uses_name_trigrams<I: IndexReader>(index: &I, state: &TextSearchState) -> bool {
+    state.by_name && index.has_name_trigrams()
+}
+
+/// Resolve `state` against `candidates`, intersecting trigram postings when
+/// they are selective enough to be worth it.
+fn seed_from_trigrams<I: IndexReader>(
+    index: &I,
+    state: &TextSearchState,
+    candidates: &[FileId],
+    scratch: &Scratch,
+) -> TrigramSeed {
+    match choose_strategy(index, state, candidates.len()) {
+        TextStrategy::NoMatch => TrigramSeed::Empty,
+        TextStrategy::NameScan => TrigramSeed::NameScan,
+        TextStrategy::PathScan => TrigramSeed::PathScan,
+        TextStrategy::Trigrams(tris) => {
+            // Intersect candidate set with trigram postings.
+            let tri_candidates =
+                get_file_trigram_candidates(index, TrigramSource::Path, &tris, candidates, scratch);
+            if tri_candidates.is_empty() {
+                scratch.recycle(tri_candidates);
+                return TrigramSeed::Empty;
+            }
+            // Files below a matching directory would otherwise only match
+            // once their whole path is rebuilt.
+            let matched = files_in_matching_dirs(index, state, &tri_candidates);
+            if matched.is_empty() {
+                return TrigramSeed::Candidates {
+                    paths: tri_candidates,
+                    matched,
+                };
+            }
+            let paths = diff_sorted(&tri_candidates, &matched);
+            scratch.recycle(tri_candidates);
+            TrigramSeed::Candidates { paths, matched }
+        }
+        TextStrategy::NameTrigrams(tris) => {
+            let names =
+                get_file_trigram_candidates(index, TrigramSource::Name, &tris, candidates, scratch);
+            let matched = if state.anchor == TextAnchor::Anywhere {
+                files_in_matching_dirs(index, state, candidates)
+            } else {
+                Vec::new()
+            };
+            if names.is_empty() && matched.is_empty() {
+                TrigramSeed::Empty
+            } else {
+                TrigramSeed::Names { names, matched }
+            }
+        }
+        TextStrategy::Extension(ext) => {
+            let Some(postings) = index.files_with_ext(&ext) else {
+                return TrigramSeed::NameScan;
+            };
+            let mut names = scratch.buffer();
+            intersect_adaptive_into(candidates, &postings, &mut names);
+            if names.is_empty() {
+                TrigramSeed::Empty
+            } else {
+                TrigramSeed::Names {
+                    names,
+                    matched: Vec::new(),
+                }
+            }
+        }
+        TextStrategy::ExactName => {
+            let Some(named) = index.files_named(&state.needle_lower) else {
+                return TrigramSeed::NameScan;
+            };
+            // Like the trigram postings, leave out directory entries and
+            // files hidden by default.
+            let named: Vec<FileId> = named
+                .into_iter()
+                .filter(|&fid| {
+                    let flags = index.get_file_flags(fid);
+                    !flags.contains(FileFlags::IS_DIR) && flags.is_default_visible()
+                })
+                .collect();
+            let matched = intersect_adaptive(candidates, &named);
+            if matched.is_empty() {
+                TrigramSeed::Empty
+            } else {
+                TrigramSeed::Names {
+                    names: Vec::new(),
+                    matched,
+                }
+            }
+        }
+    }
+}
+
+/// Candidates directly or indirectly inside a directory whose name contains
+/// the needle, found through the directory trigrams.
+///
+/// Like the file trigram postings, this leaves out directory entries and
+/// files hidden by default.
+fn files_in_matching_dirs<I: IndexReader>(
+    index: &I,
+    state: &TextSearchState,
+    candidates: &[FileId],
+) -> Vec<FileId> {
+    // Directories whose path has every trigram: a superset of those below
+    // a matching name.
+    let mut dirs: Option<Vec<DirId>> = None;
+    for &tri in &state.trigrams {
+        let Some(postings) = index.query_dir_trigram(tri) else {
+            return Vec::new();
+        };
+        dirs = Some(match dirs {
+            None => postings.into_owned(),
+            Some(dirs) => intersect_adaptive(dirs.as_slice(), &postings),
+        });
+    }
+    let Some(dirs) = dirs.filter(|d| !d.is_empty()) else {
+        return Vec::new();
+    };
+
+    let _span = profile::span(Op::Verify, dirs.len());
+    let needle = state.needle_lower.as_str();
+    let mut memo = DirMatches::new(index.dir_count());
+    let mut files = UnionBuilder::new(index.get_file_count());
+    for dir in dirs {
+        if memo.matches(index, needle, dir) {
+            files.add(
+                index
+                    .dir_files(dir)
+                    .iter()
+                    .copied()
+                    .filter(|&fid| {
+                        let flags = index.get_file_flags(fid);
+                        !flags.contains(FileFlags::IS_DIR) && flags.is_default_visible()
+                    })
+                    .collect(),
+            );
+        }
+    }
+    intersect_adaptive(candidates, &files.into_sorted())
+}
+
+/// Whether directories are, or are below, a directory whose name contains
+/// a needle; each directory's name is checked at most once.
+struct DirMatches(Vec<Option<bool>>);
+
+impl DirMatches {
+    fn new(dir_count: usize) -> Self {
+        Self(vec![None; dir_count])
+    }
 
-    let effective_tris: SmallVec<[Trigram; 8]> = items.into_iter().map(|(t, _)| t).collect();
+    fn matches<I: IndexReader>(&mut self, index: &I, needle: &str, dir: DirId) -> bool {
+        let mut chain: SmallVec<[DirId; 16]> = SmallVec::new();
+        let mut current = dir;
+        let matched = loop {
+            let Some(known) = self.0.get(current as usize) else {
+                // Past the root.
+                break false;
+            };
+            if let Some(matched) = *known {
+                break matched;
+            }
+            if contains_lowercase_ascii(index.get_dir_name(current), needle) {
+                self.0[current as usize] = Some(true);
+                break true;
+            }
+            chain.push(current);
+            current = index.get_dir_parent(current);
+        };
+        for d in chain {
+            self.0[d as usize] = Some(matched);
+        }
+        matched
+    }
+}
+
+/// Core implementation of text search against the base index.
+fn eval_text_base_with_state<I: IndexReader>(
+    index: &I,
+    state: &TextSearchState,
+    candidates: &[FileId],
+    counters: &EvalCounters,
+    paths: &PathCache,
+    scratch: &Scratch,
+) -> Vec<FileId> {
+    match seed_from_trigrams(index, state, candidates, scratch) {
+        TrigramSeed::Empty => Vec::new(),
+        TrigramSeed::NameScan => {
+            counters.add_trigram_candidates(candidates.len());
+            eval_short_text_linear_scan(index, state, candidates)
+        }
+        TrigramSeed::PathScan => {
+            counters.add_trigram_candidates(candidates.len());
+            eval_text_linear_scan_with_paths(index, &state.needle_lower, candidates, paths)
+        }
+        TrigramSeed::Candidates {
+            paths: tri_candidates,
+            matched,
+        } => {
+            counters.add_trigram_candidates(tri_candidates.len() + matched.len());
+            // Full verification via substring matching on full path.
+            let verified = verify_candidates_any_term(
+                index,
+                &[state.needle_lower.as_str()],
+                &tri_candidates,
+                paths,
+            );
+            scratch.recycle(tri_candidates);
+            merge_matched(verified, matched, scratch)
+        }
+        TrigramSeed::Names { mut names, matched } => {
+            counters.add_trigram_candidates(names.len() + matched.len());
+            let _span = profile::span(Op::Verify, names.len());
+            names.retain(|&fid| state.name_matches(index.get_file_name(fid)));
+            merge_matched(names, matched, scratch)
+        }
+    }
+}
 
-    // Intersect candidate set with trigram postings.
-    let tri_candidates = get_file_trigram_candidates(index, &effective_tris, candidates);
+/// Sorted union of verified candidates and files already known to match,
+/// handing back whichever buffers it does not return.
+fn merge_matched(verified: Vec<FileId>, matched: Vec<FileId>, scratch: &Scratch) -> Vec<FileId> {
+    if matched.is_empty() {
+        scratch.recycle(matched);
+        return verified;
+    }
+    if verified.is_empty() {
+        scratch.recycle(verified);
+        return matched;
+    }
+    let merged = union_sorted(&verified, &matched);
+    scratch.recycle(verified);
+    scratch.recycle(matched);
+    merged
+}
 
-    if tri_candidates.is_empty() {
+/// Evaluate an OR of text terms.
+///
+/// Rather than evaluating each term against the full candidate set and
+/// unioning the results, we:
+/// 1. Union the trigram-filtered candidates of every selective term.
+/// 2. Verify that union once, keeping files that match *any* of those terms.
+/// 3. Resolve non-selective terms with a single shared linear scan.
+///
+/// Returns a *sorted* subset of `candidates`.
+pub(crate) fn eval_text_disjunction<I: IndexReader>(
+    index: &I,
+    terms: &[&TextTerm],
+    candidates: &[FileId],
+    counters: &EvalCounters,
+    paths: &PathCache,
+    scratch: &Scratch,
+) -> Vec<FileId> {
+    if candidates.is_empty() || terms.is_empty() {
         return Vec::new();
     }
 
-    // Full verification via substring matching on full path.
-    let mut out = Vec::with_capacity(tri_candidates.len());
+    let states: Vec<TextSearchState> = terms.iter().map(|t| TextSearchState::new(t)).collect();
 
-    for &fid in &tri_candidates {
-        // Try filenames first so as to avoid path reconstruction for many cases.
+    let mut seeded_needles: Vec<&str> = Vec::new();
+    let mut name_scan_needles: Vec<&str> = Vec::new();
+    let mut path_scan_needles: Vec<&str> = Vec::new();
+    let bound = candidates.last().map_or(0, |&f| f as usize + 1);
+    let mut seed_union = UnionBuilder::new(bound);
+    // Already known to match, e.g. files below a directory with the needle.
+    let mut matched = UnionBuilder::new(bound);
+
+    for state in &states {
+        let needle = state.needle_lower.as_str();
+        match seed_from_trigrams(index, state, candidates, scratch) {
+            TrigramSeed::Empty => {}
+            TrigramSeed::NameScan => name_scan_needles.push(needle),
+            TrigramSeed::PathScan => path_scan_needles.push(needle),
+            TrigramSeed::Candidates {
+                paths: tri_candidates,
+                matched: known,
+            } => {
+                seed_union.add(tri_candidates);
+                seeded_needles.push(needle);
+                matched.add(known);
+            }
+            TrigramSeed::Names {
+                names,
+                matched: known,
+            } => {
+                seed_union.add(names);
+                seeded_needles.push(needle);
+                matched.add(known);
+            }
+        }
+    }
+    let seed_union = seed_union.into_sorted();
+
+    counters.add_trigram_candidates(seed_union.len() + matched.len());
+    let verified = verify_candidates_any_term(index, &seeded_needles, &seed_union, paths);
+    scratch.recycle(seed_union);
+    let seeded = merge_matched(verified, matched.into_sorted(), scratch);
+
+    if name_scan_needles.is_empty() && path_scan_needles.is_empty() {
+        return seeded;
+    }
+
+    counters.add_trigram_candidates(candidates.len());
+
+    // One pass over the candidates for every term that could not be seeded.
+    let span = profile::span(Op::Verify, candidates.len());
+    let mut scanned = scratch.buffer();
+    for &fid in candidates {
         let name = index.get_file_name(fid);
-        if contains_lowercase_ascii(name, &state.needle_lower) {
-            out.push(fid);
+        if path_contains_any_term(name, &name_scan_needles)
+            || path_contains_any_term(name, &path_scan_needles)
+        {
+            scanned.push(fid);
             continue;
         }
 
-        // If filename doesn't match, check the full path
-        let path = index.reconstruct_full_path(fid);
-        if contains_lowercase_ascii(&path, &state.needle_lower) {
-            out.push(fid);
+        if !path_scan_needles.is_empty()
+            && paths.path_matches(index, fid, |path| {
+                path_contains_any_term(path, &path_scan_needles)
+            })
+        {
+            scanned.push(fid);
         }
     }
 
-    out
+    drop(span);
+
+    merge_matched(seeded, scanned, scratch)
+}
+
+/// Keep candidates whose filename or full path contains *any* of `needles`.
+///
+/// Filenames are checked first so paths are only reconstructed when needed.
+fn verify_candidates_any_term<I: IndexReader>(
+    index: &I,
+    needles: &[&str],
+    candidates: &[FileId],
+    paths: &PathCache,
+) -> Vec<FileId> {
+    if needles.is_empty() {
+        return Vec::new();
+    }
+
+    let _span = profile::span(Op::Verify, candidates.len());
+    retain_in_locality_order(index, candidates, |fid| {
+        // Try filenames first so as to avoid path reconstruction for many cases.
+        let name = index.get_file_name(fid);
+        if path_contains_any_term(name, needles) {
+            return true;
+        }
+
+        // If filename doesn't match, check the full path
+        paths.path_matches(index, fid, |path| path_contains_any_term(path, needles))
+    })
+}
+
+/// Keep the candidates for which `keep` returns true, preserving their order.
+///
+/// Trigram-filtered candidates are spread across the whole index, so checking
+/// them in FileId order touches file metadata, names and directory entries
+/// at random. Large sets are instead visited sorted by directory and name
+/// offset, so consecutive checks share pages and cached directory paths on a
+/// cold cache; the survivors are then put back in their original order.
+fn retain_in_locality_order<I: IndexReader>(
+    index: &I,
+    candidates: &[FileId],
+    mut keep: impl FnMut(FileId) -> bool,
+) -> Vec<FileId> {
+    if candidates.len() < LOCALITY_SORT_MIN {
+        return candidates
+            .iter()
+            .copied()
+            .filter(|&fid| keep(fid))
+            .collect();
+    }
+
+    let mut order: Vec<((u32, u32), u32)> = candidates
+        .iter()
+        .enumerate()
+        .map(|(pos, &fid)| (index.get_file_locality(fid), pos as u32))
+        .collect();
+    order.sort_unstable();
+
+    let mut kept: Vec<u32> = order
+        .into_iter()
+        .map(|(_, pos)| pos)
+        .filter(|&pos| keep(candidates[pos as usize]))
+        .collect();
+    kept.sort_unstable();
+
+    kept.into_iter()
+        .map(|pos| candidates[pos as usize])
+        .collect()
 }
 
 /// Evaluate queries that contains 2 or fewer characters
 ///
 /// Normally, a user that enters only 2 characters will generally not know
 /// what specifically they are searching for. Instead of using the query as a filter,
-/// we simply return hints.
+/// we simply return hints. Anchored terms are scanned the same way, since
+/// they only match file names anyway.
 fn eval_short_text_linear_scan<I: IndexReader>(
     index: &I,
-    needle_lower: &str,
+    state: &TextSearchState,
     candidates: &[FileId],
 ) -> Vec<FileId> {
-    if needle_lower.is_empty() {
+    if state.needle_lower.is_empty() {
         return candidates.to_vec();
     }
 
-    let mut out = Vec::new();
-    out.reserve(candidates.len());
+    let _span = profile::span(Op::Verify, candidates.len());
+    let mut out = Vec::with_capacity(candidates.len());
 
     for &fid in candidates {
-        let name = index.get_file_name(fid);
-        if contains_lowercase_ascii(name, needle_lower) {
+        if state.name_matches(index.get_file_name(fid)) {
             out.push(fid);
         }
     }
@@ -255,13 +1004,14 @@ fn eval_text_linear_scan_with_paths<I: IndexReader>(
     index: &I,
     needle_lower: &str,
     candidates: &[FileId],
+    paths: &PathCache,
 ) -> Vec<FileId> {
     if needle_lower.is_empty() {
         return candidates.to_vec();
     }
 
-    let mut out = Vec::new();
-    out.reserve(candidates.len());
+    let _span = profile::span(Op::Verify, candidates.len());
+    let mut out = Vec::with_capacity(candidates.len());
 
     for &fid in candidates {
         // Fast path: filename first.
@@ -272,8 +1022,9 @@ fn eval_text_linear_scan_with_paths<I: IndexReader>(
         }
 
         // Slow path: full path includes directories.
-        let path = index.reconstruct_full_path(fid);
-        if contains_lowercase_ascii(&path, needle_lower) {
+        if paths.path_matches(index, fid, |path| {
+            contains_lowercase_ascii(path, needle_lower)
+        }) {
             out.push(fid);
         }
     }
@@ -281,13 +1032,41 @@ fn eval_text_linear_scan_with_paths<I: IndexReader>(
     out
 }
 
-/// Intersect global trigram postings with the current candidate set.
+/// Which file trigram postings a lookup reads.
+#[derive(Debug, Clone, Copy)]
+enum TrigramSource {
+    /// Trigrams of the path relative to the index root.
+    Path,
+    /// Trigrams of the file name alone.
+    Name,
+}
+
+impl TrigramSource {
+    fn postings<I: IndexReader>(self, index: &I, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        match self {
+            TrigramSource::Path => index.query_trigram(tri),
+            TrigramSource::Name => index.query_name_trigram(tri),
+        }
+    }
+
+    fn postings_len<I: IndexReader>(self, index: &I, tri: Trigram) -> usize {
+        match self {
+            TrigramSource::Path => index.trigram_postings_len(tri),
+            TrigramSource::Name => index.name_trigram_postings_len(tri),
+        }
+    }
+}
+
+/// Intersect global trigram postings from `source` with the current
+/// candidate set.
 ///
 /// Both `candidates` and postings are assumed sorted ascending.
 fn get_file_trigram_candidates<I: IndexReader>(
     index: &I,
+    source: TrigramSource,
     trigrams: &[Trigram],
     candidates: &[FileId],
+    scratch: &Scratch,
 ) -> Vec<FileId> {
     if trigrams.is_empty() || candidates.is_empty() {
         return Vec::new();
@@ -296,62 +1075,41 @@ fn get_file_trigram_candidates<I: IndexReader>(
     // Sort trigrams by postings length (rarest first).
     let mut tris: SmallVec<[(Trigram, usize); 8]> = SmallVec::new();
     tris.extend(trigrams.iter().copied().map(|t| {
-        let len = index.trigram_postings_len(t);
+        let len = source.postings_len(index, t);
         (t, len)
     }));
     tris.sort_unstable_by_key(|&(_, len)| len);
+    let early_verify = CandidateCutoffs::for_index(index).early_verify;
 
-    let mut buf_a: Vec<FileId> = Vec::new();
-    let mut buf_b: Vec<FileId> = Vec::new();
-    let mut current_is_a = true;
-    let mut has_current = false;
+    // Intersect back and forth between two buffers.
+    let mut current = scratch.buffer();
+    let mut next = scratch.buffer();
+    let mut first = true;
 
     for (tri, _) in tris {
-        let postings = match index.query_trigram(tri) {
-            Some(v) => v,
-            None => return Vec::new(),
+        let mut lookup = profile::span(Op::TrigramLookup, 0);
+        let Some(postings) = source.postings(index, tri) else {
+            current.clear();
+            break;
         };
+        lookup.set_items(postings.len());
+        drop(lookup);
 
-        if !has_current {
-            // First intersection: postings ∩ candidates
-            buf_a = intersect_adaptive(candidates, postings);
-            if buf_a.is_empty() {
-                return Vec::new();
-            }
-            if buf_a.len() <= EARLY_VERIFY_CUTOFF {
-                return buf_a;
-            }
-            has_current = true;
-            current_is_a = true;
-            continue;
-        }
+        // First intersection: postings ∩ candidates
+        let input = if first { candidates } else { &current };
+        intersect_adaptive_into(input, &postings, &mut next);
+        mem::swap(&mut current, &mut next);
+        first = false;
 
-        if current_is_a {
-            intersect_adaptive_into(buf_a.as_slice(), postings, &mut buf_b);
-            if buf_b.is_empty() {
-                return Vec::new();
-            }
-            if buf_b.len() <= EARLY_VERIFY_CUTOFF {
-                return buf_b;
-            }
-            current_is_a = false;
-        } else {
-            intersect_adaptive_into(buf_b.as_slice(), postings, &mut buf_a);
-            if buf_a.is_empty() {
-                return Vec::new();
-            }
-            if buf_a.len() <= EARLY_VERIFY_CUTOFF {
-                return buf_a;
-            }
-            current_is_a = true;
+        if current.len() <= early_verify {
+            break;
         }
     }
 
-    if !has_current {
-        Vec::new()
-    } else if current_is_a {
-        buf_a
-    } else {
-        buf_b
-    }
+    scratch.recycle(next);
+    current
 }
+
+#[cfg(test)]
+#[path = "text_tests.rs"]
+mod tests;
@@ -0,0 +1,321 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+use blaze_runtime::{Setting, Source};
+
+use super::*;
+use crate::{IndexBuilder, LeafExpr, QueryExpr, StagedIndex, parse_query};
+
+/// Files whose ids interleave four directories, as a parallel walk produces.
+fn interleaved(count: usize) -> StagedIndex {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..count).map(|i| FileRecord::test_file(root.join(format!("d{}/f{i}", i % 4)))),
+    );
+    builder.finish()
+}
+
+#[test]
+fn locality_order_visits_by_directory_and_keeps_input_order() {
+    let index = interleaved(LOCALITY_SORT_MIN * 2);
+    let candidates: Vec<FileId> = (0..index.get_file_count() as FileId).rev().collect();
+
+    let mut visited = Vec::new();
+    let kept = retain_in_locality_order(&index, &candidates, |fid| {
+        visited.push(fid);
+        fid % 3 == 0
+    });
+
+    // Every candidate is checked once, one directory at a time.
+    assert_eq!(visited.len(), candidates.len());
+    let dir_changes = visited
+        .windows(2)
+        .filter(|w| index.get_file_dir_id(w[0]) != index.get_file_dir_id(w[1]))
+        .count();
+    assert_eq!(dir_changes, 3);
+
+    // Survivors come back in the caller's (here descending) order.
+    let expected: Vec<FileId> = candidates.iter().copied().filter(|f| f % 3 == 0).collect();
+    assert_eq!(kept, expected);
+}
+
+#[test]
+fn small_sets_are_checked_in_place() {
+    let index = interleaved(16);
+    let candidates: Vec<FileId> = (0..16).collect();
+
+    let mut visited = Vec::new();
+    let kept = retain_in_locality_order(&index, &candidates, |fid| {
+        visited.push(fid);
+        true
+    });
+
+    assert_eq!(visited, candidates);
+    assert_eq!(kept, candidates);
+}
+
+/// The text term `text` parses to.
+fn term(text: &str) -> TextTerm {
+    match parse_query(text).expr {
+        QueryExpr::Leaf(LeafExpr::Text(term)) => term,
+        other => panic!("expected text leaf, got {other:?}"),
+    }
+}
+
+/// Paths of the files `text` matches among all of `index`'s files.
+fn matching(index: &StagedIndex, text: &str) -> Vec<String> {
+    let term = term(text);
+    let candidates: Vec<FileId> = (0..index.get_file_count() as FileId).collect();
+    let hits = eval_text_term(
+        index,
+        &term,
+        &candidates,
+        &EvalCounters::default(),
+        &PathCache::default(),
+        &Scratch::default(),
+    );
+    hits.into_iter()
+        .map(|fid| index.reconstruct_full_path(fid))
+        .collect()
+}
+
+#[test]
+fn name_trigrams_match_names_and_directories_like_paths_do() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..SMALL_CANDIDATE_MIN + 1)
+            .map(|i| FileRecord::test_file(root.join(format!("notes/n{i}")))),
+    );
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/Zebra.rs")),
+        FileRecord::test_file(root.join("zebras/inner/a.txt")),
+        FileRecord::test_file(root.join("zoo/zebra")),
+    ]);
+    let index = builder.finish();
+
+    let by_name = matching(&index, "zebra");
+    assert_eq!(
+        by_name,
+        [
+            "/data/src/Zebra.rs",
+            "/data/zebras/inner/a.txt",
+            "/data/zoo/zebra"
+        ]
+    );
+    // A leading `/` searches the same needle through path trigrams.
+    assert_eq!(matching(&index, "/zebra"), by_name);
+
+    // No file is named like "notes"; every file below the directory is.
+    assert_eq!(matching(&index, "notes").len(), SMALL_CANDIDATE_MIN + 1);
+}
+
+#[test]
+fn anchored_terms_match_the_start_or_end_of_file_names() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..SMALL_CANDIDATE_MIN + 1)
+            .map(|i| FileRecord::test_file(root.join(format!("notes/n{i}")))),
+    );
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/Makefile")),
+        FileRecord::test_file(root.join("src/makefile.am")),
+        FileRecord::test_file(root.join("src/main.rs")),
+        FileRecord::test_file(root.join("src/lib.RS")),
+        FileRecord::test_file(root.join("src/rs.md")),
+        FileRecord::test_file(root.join("makefile/inner.txt")),
+        FileRecord::test_file(root.join("img/a.jpeg")),
+        FileRecord::test_file(root.join("img/b.jpg")),
+    ]);
+    let index = builder.finish();
+    let count = index.get_file_count();
+
+    assert_eq!(
+        matching(&index, "^makefile"),
+        ["/data/src/Makefile", "/data/src/makefile.am"]
+    );
+    assert_eq!(matching(&index, "^makefile$"), ["/data/src/Makefile"]);
+    assert_eq!(matching(&index, "file$"), ["/data/src/Makefile"]);
+    // Quoted, `^` is just a character.
+    assert!(matching(&index, "\"^makefile\"").is_empty());
+
+    // A bare extension is looked up in the extension table.
+    let rs = term(".rs$");
+    assert_eq!(
+        plan_text_term(&index, &rs, count).strategy,
+        TextStrategy::Extension("rs".into())
+    );
+    assert_eq!(
+        matching(&index, ".rs$"),
+        ["/data/src/main.rs", "/data/src/lib.RS"]
+    );
+    // `jpeg` files are found through `jpg`, but only their names end in it.
+    assert_eq!(matching(&index, ".jpeg$"), ["/data/img/a.jpeg"]);
+
+    let makefile = term("^makefile");
+    assert!(matches!(
+        plan_text_term(&index, &makefile, count).strategy,
+        TextStrategy::NameTrigrams(tris) if !tris.is_empty()
+    ));
+    assert_eq!(
+        plan_text_term(&index, &term("^zebra"), count).strategy,
+        TextStrategy::NoMatch
+    );
+
+    // A whole name is looked up in the exact name index.
+    assert_eq!(
+        plan_text_term(&index, &term("^makefile$"), count).strategy,
+        TextStrategy::ExactName
+    );
+    assert!(matching(&index, "^inner$").is_empty());
+}
+
+#[test]
+fn candidate_cutoffs_scale_with_the_index() {
+    let cutoffs = |files: usize| {
+        let c = CandidateCutoffs::scaled(files);
+        (c.small, c.early_verify)
+    };
+
+    // Small indexes intersect postings early; large ones scan more.
+    assert_eq!(cutoffs(0), (256, 64));
+    assert_eq!(cutoffs(10_000), (256, 64));
+    assert_eq!(cutoffs(200_000), (2_000, 250));
+    assert_eq!(cutoffs(1_000_000), (10_000, 1_250));
+    assert_eq!(cutoffs(10_000_000), (50_000, 8_192));
+}
+
+#[test]
+fn overrides_fix_the_cutoffs() {
+    let overrides = PlannerOverrides {
+        small_candidate_cutoff: Setting {
+            value: Some(10),
+            source: Source::File,
+        },
+        ..PlannerOverrides::default()
+    };
+    let cutoffs = CandidateCutoffs::scaled(200_000).with_overrides(&overrides);
+    assert_eq!((cutoffs.small, cutoffs.early_verify), (10, 250));
+}
+
+#[test]
+fn small_candidate_sets_are_scanned() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder
+        .add_batch((0..1_000).map(|i| FileRecord::test_file(root.join(format!("notes/n{i}.txt")))));
+    builder.add_batch([FileRecord::test_file(root.join("src/zebra.rs"))]);
+    let index = builder.finish();
+    let state = TextSearchState::new(&term("zebra"));
+    let small = CandidateCutoffs::for_index(&index).small;
+
+    assert!(matches!(
+        choose_strategy(&index, &state, small),
+        TextStrategy::PathScan
+    ));
+    assert!(matches!(
+        choose_strategy(&index, &state, small + 1),
+        TextStrategy::NameTrigrams(_) | TextStrategy::Trigrams(_)
+    ));
+}
+
+#[test]
+fn path_trigram_candidates_below_matching_directories_skip_verification() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch((0..1_000).map(|i| FileRecord::test_file(root.join(format!("other/o{i}")))));
+    builder
+        .add_batch((0..300).map(|i| FileRecord::test_file(root.join(format!("notes/inner/n{i}")))));
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/notes.md")),
+        FileRecord::test_file(root.join("src/nxtes.md")),
+    ]);
+    let index = builder.finish();
+    let candidates: Vec<FileId> = (0..index.get_file_count() as FileId).collect();
+
+    // A `/` in the term makes it a path term, seeded from path trigrams.
+    let state = TextSearchState::new(&term("/notes"));
+    let TrigramSeed::Candidates { paths, matched } =
+        seed_from_trigrams(&index, &state, &candidates, &Scratch::default())
+    else {
+        panic!("expected path trigram candidates");
+    };
+    assert_eq!(matched.len(), 300);
+    assert!(
+        matched
+            .iter()
+            .all(|&fid| index.reconstruct_full_path(fid).starts_with("/data/notes/"))
+    );
+    assert!(paths.iter().all(|fid| !matched.contains(fid)));
+
+    let hits = matching(&index, "/notes");
+    assert_eq!(hits.len(), 301);
+    assert!(hits.contains(&"/data/src/notes.md".to_owned()));
+}
+
+/// `needle`'s trigrams with made-up posting counts, `i * step + base`.
+fn counted(needle: &str, base: usize, step: usize) -> SmallVec<[(Trigram, usize); 8]> {
+    build_trigrams_for_string(needle)
+        .into_iter()
+        .enumerate()
+        .map(|(i, tri)| (tri, base + i * step))
+        .collect()
+}
+
+#[test]
+fn trigram_selections_pick_subsets_of_the_needles_trigrams() {
+    let state = TextSearchState::new(&term("lib_controller"));
+    let items = counted("lib_controller", 1_000, 10);
+    let all = build_trigrams_for_string("lib_controller");
+    let select = |selection| select_trigrams(selection, &state, items.clone(), 64);
+
+    let (used, full) = select(TrigramSelection::Full);
+    assert_eq!(used, TrigramSelection::Full);
+    assert_eq!(full.len(), all.len());
+
+    let (_, rare) = select(TrigramSelection::RareFirst(2));
+    let rarest: Vec<Trigram> = items.iter().take(2).map(|&(tri, _)| tri).collect();
+    assert_eq!(rare.as_slice(), rarest);
+
+    let (_, sparse) = select(TrigramSelection::Sparse);
+    let expected = build_query_trigrams("lib_controller");
+    assert_eq!(sparse.len(), expected.len());
+    assert!(sparse.iter().all(|tri| expected.contains(tri)));
+
+    // Only the selective trigrams are handed in; with no sparse one among
+    // them the rarest is used.
+    let broad_sparse: SmallVec<[(Trigram, usize); 8]> = items
+        .iter()
+        .copied()
+        .filter(|(tri, _)| !expected.contains(tri))
+        .collect();
+    let (_, sparse) = select_trigrams(TrigramSelection::Sparse, &state, broad_sparse, 64);
+    assert_eq!(sparse.len(), 1);
+}
+
+#[test]
+fn auto_selection_follows_needle_length_and_trigram_counts() {
+    let pick = |needle: &str, base: usize| {
+        let state = TextSearchState::new(&term(needle));
+        select_trigrams(
+            TrigramSelection::Auto,
+            &state,
+            counted(needle, base, 10),
+            64,
+        )
+        .0
+    };
+
+    // Few trigrams: all of them.
+    assert_eq!(pick("main.rs", 1_000), TrigramSelection::Full);
+    // A rare trigram leaves few enough candidates on its own.
+    assert_eq!(
+        pick("controller", 5),
+        TrigramSelection::RareFirst(DEFAULT_RARE_TRIGRAMS)
+    );
+    // Only common trigrams: every one helps, spread out on long needles.
+    assert_eq!(pick("controller", 1_000), TrigramSelection::Full);
+    assert_eq!(pick("lib_controller", 1_000), TrigramSelection::Sparse);
+}
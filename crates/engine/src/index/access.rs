@@ -0,0 +1,195 @@
+//! Which files of a shared index a user could reach.
+//!
+//! Indexes record the owner and mode of each directory at build time. A
+//! system-wide index is built by root, who can see everything; before its
+//! results are shown to someone else, [`DirAccess`] works out which
+//! directories that user could pass through, and files they could not
+//! `stat` (because some directory above them is not searchable to them) are
+//! dropped. The index root itself is assumed searchable.
+//!
+//! Files record their own owner and mode as well, and [`can_access`] asks
+//! the OS about a path directly, for checking a handful of results.
+
+use std::io;
+
+use blaze_fs::FilePerms;
+
+use super::{DirId, DirPerms, FileId, Index, IndexReader};
+
+/// Directory id used for files directly under the index root.
+const ROOT_DIR: DirId = DirId::MAX;
+
+/// The user a query runs for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    /// Primary and supplementary groups.
+    pub groups: Vec<u32>,
+}
+
+impl Credentials {
+    /// The credentials of this process.
+    #[cfg(unix)]
+    pub fn current() -> io::Result<Self> {
+        // SAFETY: getuid and getgid cannot fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        // SAFETY: a zero-length call only returns the group count.
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut groups = vec![0 as libc::gid_t; count as usize];
+        // SAFETY: `groups` has room for `count` entries.
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        if count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        groups.truncate(count as usize);
+        groups.push(gid);
+
+        Ok(Self { uid, groups })
+    }
+
+    #[cfg(not(unix))]
+    pub fn current() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file permissions are not checked on this platform",
+        ))
+    }
+
+    /// Whether these credentials may search (`x`) a directory with `perms`.
+    pub fn can_search(&self, perms: DirPerms) -> bool {
+        self.permits(perms.uid, perms.gid, perms.mode, 0o1)
+    }
+
+    /// Whether these credentials may read (`r`) a file with `perms`.
+    pub fn can_read(&self, perms: FilePerms) -> bool {
+        self.permits(perms.uid, perms.gid, perms.mode, 0o4)
+    }
+
+    /// Whether the owner, group or other bits that apply to these
+    /// credentials include `bit` (`0o4`, `0o2` or `0o1`).
+    fn permits(&self, uid: u32, gid: u32, mode: u32, bit: u32) -> bool {
+        if self.uid == 0 {
+            return true;
+        }
+        let shift = if self.uid == uid {
+            6
+        } else if self.groups.contains(&gid) {
+            3
+        } else {
+            0
+        };
+        mode & (bit << shift) != 0
+    }
+}
+
+/// Whether this process can read `path` now, asking the OS rather than the
+/// index (`access(2)`, which also covers every directory above it).
+#[cfg(unix)]
+pub fn can_access(path: &str) -> bool {
+    let Ok(path) = std::ffi::CString::new(path) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string.
+    unsafe { libc::access(path.as_ptr(), libc::R_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn can_access(path: &str) -> bool {
+    std::fs::metadata(path).is_ok()
+}
+
+/// The directories of an index one user can reach from its root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirAccess {
+    /// Bit `d` is set when directory `d` and every directory above it are
+    /// searchable.
+    reachable: Vec<u64>,
+}
+
+impl DirAccess {
+    pub fn contains_dir(&self, dir: DirId) -> bool {
+        dir == ROOT_DIR
+            || self
+                .reachable
+                .get(dir as usize / 64)
+                .is_some_and(|word| word & (1 << (dir % 64)) != 0)
+    }
+
+    /// Keep the files in reachable directories, preserving order.
+    pub fn filter_files<I: IndexReader>(&self, index: &I, files: Vec<FileId>) -> Vec<FileId> {
+        files
+            .into_iter()
+            .filter(|&fid| self.contains_dir(index.get_file_dir_id(fid)))
+            .collect()
+    }
+}
+
+impl Index {
+    /// Whether the index recorded directory permissions.
+    pub fn has_dir_perms(&self) -> bool {
+        self.dir_perms_len != 0
+    }
+
+    /// Owner and mode of `dir` when it was indexed.
+    pub fn dir_perms(&self, dir: DirId) -> Option<DirPerms> {
+        if let Some(delta) = self.delta.as_deref()
+            && let Some(own) = delta.dir(dir)
+        {
+            return delta.index.dir_perms(own);
+        }
+        self.dir_perms_raw().get(dir as usize).copied()
+    }
+
+    /// The directories `who` can reach, or `None` if the index recorded no
+    /// permissions to check. Directories without recorded permissions are
+    /// unreachable.
+    pub fn dir_access(&self, who: &Credentials) -> Option<DirAccess> {
+        if !self.has_dir_perms() {
+            return None;
+        }
+
+        let count = self.dir_count();
+        // 0 unknown, 1 reachable, 2 unreachable.
+        let mut state = vec![0u8; count];
+        let mut chain = Vec::new();
+        for start in 0..count as DirId {
+            let mut dir = start;
+            // Walk up to the first directory already decided, or the root.
+            let mut reachable = loop {
+                if dir == ROOT_DIR {
+                    break true;
+                }
+                match state.get(dir as usize) {
+                    Some(1) => break true,
+                    // A chain longer than the index has directories loops.
+                    Some(0) if chain.len() < count => {
+                        chain.push(dir);
+                        dir = self.get_dir_parent(dir);
+                    }
+                    _ => break false,
+                }
+            };
+            // Decide the chain from the top down.
+            while let Some(dir) = chain.pop() {
+                reachable &= self
+                    .dir_perms(dir)
+                    .is_some_and(|perms| who.can_search(perms));
+                state[dir as usize] = if reachable { 1 } else { 2 };
+            }
+        }
+
+        let mut bits = vec![0u64; count.div_ceil(64)];
+        for (dir, _) in state.iter().enumerate().filter(|&(_, &s)| s == 1) {
+            bits[dir / 64] |= 1 << (dir % 64);
+        }
+        Some(DirAccess { reachable: bits })
+    }
+}
+
+#[cfg(test)]
+#[path = "access_tests.rs"]
+mod tests;
@@ -0,0 +1,177 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use blaze_fs::{FilePerms, FileRecord};
+use blaze_runtime::history::QueryOrigin;
+use tempfile::tempdir;
+
+use super::*;
+use crate::{IndexBuilder, QueryScope, write_index_atomic};
+
+fn record(root: &Path, rel: &str, perms: Option<(u32, u32, u32)>) -> FileRecord {
+    let full_path = root.join(rel.trim_end_matches('/'));
+    let record = if rel.ends_with('/') {
+        FileRecord::test_dir(full_path)
+    } else {
+        FileRecord::test_file(full_path)
+    };
+    FileRecord {
+        perms: perms.map(|(uid, gid, mode)| FilePerms { uid, gid, mode }),
+        ..record
+    }
+}
+
+fn user(uid: u32, groups: &[u32]) -> Credentials {
+    Credentials {
+        uid,
+        groups: groups.to_vec(),
+    }
+}
+
+/// An index of `/sys` as root would build it:
+///
+/// ```text
+/// /sys/motd                      root-level file
+/// /sys/pub/     0755 root:root   pub/readme
+/// /sys/alice/   0700 1000:1000   alice/notes/todo (notes/ is 0755)
+/// /sys/staff/   0750 root:50     staff/plan
+/// /sys/orphan/  no record        orphan/file (hidden from users)
+/// ```
+fn system_index(dir: &Path) -> Index {
+    let root = PathBuf::from("/sys");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        record(&root, "motd", None),
+        record(&root, "pub/", Some((0, 0, 0o755))),
+        record(&root, "pub/readme", None),
+        record(&root, "alice/", Some((1000, 1000, 0o700))),
+        record(&root, "alice/notes/", Some((1000, 1000, 0o755))),
+        record(&root, "alice/notes/todo", None),
+        record(&root, "staff/", Some((0, 50, 0o750))),
+        record(&root, "staff/plan", None),
+        record(&root, "orphan/file", None),
+    ]);
+
+    let path = dir.join("index.bin");
+    write_index_atomic(&path, &builder.finish(), 0).unwrap();
+    Index::open(&path).unwrap()
+}
+
+fn visible(index: &Index, who: &Credentials) -> Vec<String> {
+    let scope = QueryScope {
+        access: Some(Arc::new(index.dir_access(who).unwrap())),
+        ..QueryScope::default()
+    };
+    let result = index.run_query_scoped("", None, QueryOrigin::Cli, scope);
+    let mut paths: Vec<String> = result.hits.into_iter().map(|h| h.path).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn search_permission_follows_owner_group_and_other_bits() {
+    let perms = DirPerms {
+        uid: 1000,
+        gid: 50,
+        mode: 0o710,
+    };
+    assert!(user(1000, &[1000]).can_search(perms));
+    assert!(user(2000, &[50]).can_search(perms));
+    assert!(!user(2000, &[2000]).can_search(perms));
+    assert!(user(0, &[0]).can_search(perms));
+
+    // The owner bits apply to the owner even when the group would allow.
+    let group_only = DirPerms {
+        mode: 0o070,
+        ..perms
+    };
+    assert!(!user(1000, &[50]).can_search(group_only));
+}
+
+#[test]
+fn hides_files_below_unsearchable_directories() {
+    let dir = tempdir().unwrap();
+    let index = system_index(dir.path());
+    assert!(index.has_dir_perms());
+
+    // A directory can be stat'ed where its parent can be searched, even
+    // if its own contents are hidden.
+    let top = [
+        "/sys/alice",
+        "/sys/motd",
+        "/sys/pub",
+        "/sys/pub/readme",
+        "/sys/staff",
+    ];
+    assert_eq!(visible(&index, &user(2000, &[2000])), top);
+    assert_eq!(
+        visible(&index, &user(2000, &[2000, 50])),
+        [&top[..], &["/sys/staff/plan"]].concat()
+    );
+    assert_eq!(
+        visible(&index, &user(1000, &[1000])),
+        [
+            &["/sys/alice", "/sys/alice/notes", "/sys/alice/notes/todo"],
+            &top[1..]
+        ]
+        .concat()
+    );
+    // A directory without a record is searchable by root alone.
+    assert!(
+        visible(&index, &user(0, &[0]))
+            .iter()
+            .any(|p| p == "/sys/orphan/file")
+    );
+}
+
+#[test]
+fn indexes_without_permissions_have_no_access_map() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([record(&root, "src/main.rs", None)]);
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &builder.finish(), 0).unwrap();
+    let index = Index::open(&path).unwrap();
+
+    assert!(!index.has_dir_perms());
+    assert_eq!(index.dir_access(&user(1000, &[1000])), None);
+}
+
+#[test]
+fn read_permission_follows_owner_group_and_other_bits() {
+    let perms = FilePerms {
+        uid: 1000,
+        gid: 50,
+        mode: 0o640,
+    };
+    assert!(user(1000, &[1000]).can_read(perms));
+    assert!(user(2000, &[50]).can_read(perms));
+    assert!(!user(2000, &[2000]).can_read(perms));
+    assert!(user(0, &[0]).can_read(perms));
+}
+
+#[test]
+fn file_owner_and_mode_are_stored() {
+    let dir = tempdir().unwrap();
+    let index = system_index(dir.path());
+    let perms_of = |name: &str| {
+        let fid = (0..index.get_file_count() as FileId)
+            .find(|&fid| index.get_file_name(fid) == name)
+            .unwrap();
+        index.get_file_perms(fid)
+    };
+
+    assert_eq!(
+        perms_of("alice"),
+        Some(FilePerms {
+            uid: 1000,
+            gid: 1000,
+            mode: 0o700
+        })
+    );
+    assert_eq!(perms_of("motd"), None);
+}
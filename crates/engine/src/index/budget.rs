@@ -0,0 +1,191 @@
+use hashbrown::HashSet;
+
+use super::{Index, SectionDesc};
+
+/// Disk and memory footprint of one index section.
+#[derive(Debug, Clone)]
+pub struct SectionUsage {
+    pub name: &'static str,
+    /// Size of the section on disk.
+    pub bytes: u64,
+    /// Bytes of the section currently in the page cache, if known.
+    pub resident_bytes: Option<u64>,
+}
+
+/// How much of the names blob is actually referenced.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NamesUsage {
+    /// Size of the names blob.
+    pub blob_bytes: u64,
+    /// Bytes referenced by file, dir, and root path entries.
+    pub referenced_bytes: u64,
+    /// Bytes that would remain if identical names were stored once.
+    pub distinct_bytes: u64,
+}
+
+/// Breakdown of where an index's disk and memory go.
+#[derive(Debug, Clone)]
+pub struct IndexBudget {
+    /// Total size of the mapped index file.
+    pub file_bytes: u64,
+    /// Bytes of the whole file in the page cache, if known.
+    pub resident_bytes: Option<u64>,
+    pub sections: Vec<SectionUsage>,
+    pub names: NamesUsage,
+}
+
+impl Index {
+    /// Measure per-section sizes, page-cache residency, and names blob usage.
+    ///
+    /// Residency comes from `mincore(2)` and is only available on Unix. The
+    /// names analysis walks every file record, so it is proportional to index
+    /// size.
+    pub fn budget(&self) -> IndexBudget {
+        let pages = if self.bytes.is_mapped() {
+            resident_pages(&self.bytes)
+        } else {
+            None
+        };
+        let h = &self.header;
+
+        let sections = [
+            ("metadata", h.metadata),
+            ("ext_table", h.ext_table),
+            ("dirs", h.dirs),
+            ("files_meta", h.files_meta),
+            ("names_blob", h.names_blob),
+            ("ext_index_keys", h.ext_index_keys),
+            ("ext_index_postings", h.ext_index_postings),
+            ("trigram_keys", h.trigram_keys),
+            ("trigram_postings", h.trigram_postings),
+            ("dir_trigram_keys", h.dir_trigram_keys),
+            ("dir_trigram_postings", h.dir_trigram_postings),
+            ("repos", h.repos),
+            ("file_repos", h.file_repos),
+            ("file_hashes", h.file_hashes),
+            ("dir_paths", h.dir_paths),
+            ("name_trigram_keys", h.name_trigram_keys),
+            ("name_trigram_postings", h.name_trigram_postings),
+            ("exact_name_keys", h.exact_name_keys),
+            ("exact_name_postings", h.exact_name_postings),
+            ("dir_perms", h.dir_perms),
+            ("dir_files", h.dir_files),
+            ("dir_file_postings", h.dir_file_postings),
+        ]
+        .into_iter()
+        .map(|(name, desc)| SectionUsage {
+            name,
+            bytes: desc.len,
+            resident_bytes: pages.as_ref().map(|p| p.resident_in(desc)),
+        })
+        .collect();
+
+        IndexBudget {
+            file_bytes: self.bytes.len() as u64,
+            resident_bytes: pages
+                .as_ref()
+                .map(|p| p.resident_in(SectionDesc::new(0, self.bytes.len() as u64))),
+            sections,
+            names: self.names_usage(),
+        }
+    }
+
+    fn names_usage(&self) -> NamesUsage {
+        let mut usage = NamesUsage {
+            blob_bytes: self.names_blob_len as u64,
+            ..NamesUsage::default()
+        };
+        let mut seen: HashSet<&str> = HashSet::new();
+
+        let names = self
+            .file_metas()
+            .iter()
+            .map(|m| self.get_name(m.name_offset, m.name_len))
+            .chain(
+                self.dirs()
+                    .iter()
+                    .map(|d| self.get_name(d.name_offset, d.name_len)),
+            )
+            .chain(self.root_path());
+
+        for name in names {
+            usage.referenced_bytes += name.len() as u64;
+            if seen.insert(name) {
+                usage.distinct_bytes += name.len() as u64;
+            }
+        }
+
+        usage
+    }
+}
+
+/// Per-page residency of a mapping.
+struct ResidentPages {
+    page_size: u64,
+    resident: Vec<bool>,
+}
+
+impl ResidentPages {
+    fn resident_in(&self, desc: SectionDesc) -> u64 {
+        if desc.len == 0 {
+            return 0;
+        }
+        let start = desc.offset;
+        let end = desc.offset + desc.len;
+        let first = start / self.page_size;
+        let last = (end - 1) / self.page_size;
+
+        (first..=last)
+            .filter(|&page| self.resident.get(page as usize).copied().unwrap_or(false))
+            .map(|page| {
+                let page_start = page * self.page_size;
+                let page_end = page_start + self.page_size;
+                end.min(page_end) - start.max(page_start)
+            })
+            .sum()
+    }
+}
+
+#[cfg(unix)]
+fn resident_pages(map: &[u8]) -> Option<ResidentPages> {
+    if map.is_empty() {
+        return None;
+    }
+
+    // SAFETY: sysconf has no preconditions.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    let page_size = page_size as usize;
+
+    let page_count = map.len().div_ceil(page_size);
+    let mut vec = vec![0u8; page_count];
+
+    // SAFETY: the mapping starts on a page boundary and spans `map.len()`
+    // bytes; `vec` has one byte per page as mincore requires.
+    let rc = unsafe {
+        libc::mincore(
+            map.as_ptr() as *mut libc::c_void,
+            map.len(),
+            vec.as_mut_ptr() as *mut _,
+        )
+    };
+    if rc != 0 {
+        return None;
+    }
+
+    Some(ResidentPages {
+        page_size: page_size as u64,
+        resident: vec.into_iter().map(|b| b & 1 != 0).collect(),
+    })
+}
+
+#[cfg(not(unix))]
+fn resident_pages(_map: &[u8]) -> Option<ResidentPages> {
+    None
+}
+
+#[cfg(test)]
+#[path = "budget_tests.rs"]
+mod tests;
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+
+use crate::{Index, IndexBuilder};
+
+fn test_index() -> Index {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("a/Makefile")),
+        FileRecord::test_file(root.join("b/Makefile")),
+        FileRecord::test_file(root.join("b/a/README")),
+    ]);
+    Index::from_staged(&builder.finish()).unwrap()
+}
+
+#[test]
+fn sections_fit_within_file() {
+    let budget = test_index().budget();
+
+    assert_eq!(budget.sections.len(), 22);
+    let total: u64 = budget.sections.iter().map(|s| s.bytes).sum();
+    assert!(total <= budget.file_bytes);
+
+    for section in &budget.sections {
+        if let Some(resident) = section.resident_bytes {
+            assert!(resident <= section.bytes, "{}", section.name);
+        }
+    }
+    if let Some(resident) = budget.resident_bytes {
+        assert!(resident <= budget.file_bytes);
+    }
+}
+
+#[test]
+fn names_usage_counts_duplicates() {
+    let names = test_index().budget().names;
+
+    // root + Makefile x2 + README + dirs a, b, a (as child of b)
+    let referenced = "/data".len() + 2 * "Makefile".len() + "README".len() + 3;
+    let distinct = "/data".len() + "Makefile".len() + "README".len() + 2;
+
+    assert_eq!(names.blob_bytes, referenced as u64);
+    assert_eq!(names.referenced_bytes, referenced as u64);
+    assert_eq!(names.distinct_bytes, distinct as u64);
+}
@@ -1,13 +1,19 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use blaze_fs::FileRecord;
-use hashbrown::{HashMap, hash_map::Entry};
+use blaze_fs::{FilePerms, FileRecord, escape_name, normalize_root, portable_path};
+use blaze_runtime::{lang_for_ext, normalize_ext};
+use hashbrown::{HashMap, HashSet, hash_map::Entry};
 
 use crate::{
-    DirId, ExtId, ExtKey, FileId,
+    DirId, ExtId, ExtKey, FileId, RepoId,
+    helpers::name_hash,
     index::{
-        DirMeta, FileMeta, TrigramKey,
-        flags::{FileFlags, classify_noise, compute_file_flags},
+        BuildStats, DirFilesRange, DirMeta, DirPerms, DirTree, FileMeta, NameKey, RepoMeta,
+        TrigramKey,
+        flags::{BuildFlags, FileFlags, NoiseFlags, classify_noise, compute_file_flags},
     },
     trigram::{Trigram, build_trigrams_for_bytes},
 };
@@ -21,14 +27,47 @@ pub struct StagedIndex {
     pub files: Vec<FileMeta>,
     pub ext_table: Vec<String>,
 
+    pub repos: Vec<RepoMeta>,
+    /// Repository of each file, `u32::MAX` for files outside any repository.
+    pub file_repos: Vec<RepoId>,
+    /// Content hash of each file, `0` if not hashed; empty when no file was hashed.
+    pub file_hashes: Vec<u64>,
+    /// Size cap used for content hashing, `0` if hashing was off.
+    pub hash_max_bytes: u64,
+    /// Options the index was built with.
+    pub build_flags: BuildFlags,
+    /// Largest file indexed when [`BuildFlags::MAX_FILE_SIZE`] is set.
+    pub max_file_bytes: u64,
+    /// Unix time the scan behind the index started, `0` if unknown.
+    pub scan_started_secs: u64,
+    /// Unix time the scan behind the index finished, `0` if unknown.
+    pub scan_finished_secs: u64,
+    /// How the scan and build went; all zero unless the indexer filled it in.
+    pub build_stats: BuildStats,
+    /// Encoded `dir_paths` section; empty unless [`StagedIndex::store_dir_paths`] ran.
+    pub dir_paths: Vec<u8>,
+    /// Owner and mode of each directory; empty when no record carried them.
+    pub dir_perms: Vec<DirPerms>,
+
     pub ext_index_keys: Vec<ExtKey>,
     pub ext_index_postings: Vec<u32>,
 
     pub file_trigram_keys: Vec<TrigramKey>,
     pub file_trigram_postings: Vec<u32>,
+    pub name_trigram_keys: Vec<TrigramKey>,
+    pub name_trigram_postings: Vec<u32>,
+    pub exact_name_keys: Vec<NameKey>,
+    pub exact_name_postings: Vec<u32>,
 
     pub dir_trigram_keys: Vec<TrigramKey>,
     pub dir_trigram_postings: Vec<u32>,
+
+    /// Entries of each directory, then of the root, in `dir_file_postings`.
+    pub dir_files: Vec<DirFilesRange>,
+    pub dir_file_postings: Vec<FileId>,
+
+    /// Built from `dirs` and `files` on first use.
+    pub(crate) dir_tree: OnceLock<DirTree>,
 }
 
 /// IndexBuilder is responsible for ingesting FileRecords
@@ -44,9 +83,24 @@ pub struct IndexBuilder {
     ext_map: HashMap<String, ExtId>,
     ext_postings: Vec<Vec<FileId>>,
     file_trigrams: HashMap<Trigram, Vec<FileId>>,
+    name_trigrams: HashMap<Trigram, Vec<FileId>>,
+    /// Every entry by lowercased name hash, hidden files and directories
+    /// included.
+    exact_names: HashMap<u64, Vec<FileId>>,
     dir_trigrams: HashMap<Trigram, Vec<DirId>>,
+    /// Directories holding a `.git` entry; `u32::MAX` is the root itself.
+    repo_roots: HashSet<DirId>,
+    /// Content hash of each file, `0` if not hashed.
+    file_hashes: Vec<u64>,
+    /// Owner and mode of each directory with a record, by relative path.
+    dir_perms: HashMap<PathBuf, FilePerms>,
     root_path_offset: u32,
     root_path_len: u32,
+    /// Records modified at or after this Unix time are flagged
+    /// [`FileFlags::CHANGED_DURING_SCAN`].
+    changed_since: Option<u64>,
+    /// Another path to `root`, see [`IndexBuilder::alias_root`].
+    alias: Option<PathBuf>,
 }
 
 /// Narrow u64 timestamp to u32 for on-disk storage.
@@ -79,6 +133,7 @@ fn pack_trigram_map(map: HashMap<Trigram, Vec<u32>>) -> (Vec<TrigramKey>, Vec<u3
     let mut offset: u32 = 0;
     for (tri, mut v) in entries {
         v.sort_unstable(); // in-place
+        v.dedup();
 
         let len = v.len() as u32;
         postings.extend_from_slice(&v);
@@ -96,6 +151,32 @@ fn pack_trigram_map(map: HashMap<Trigram, Vec<u32>>) -> (Vec<TrigramKey>, Vec<u3
     (keys, postings)
 }
 
+fn pack_name_map(map: HashMap<u64, Vec<FileId>>) -> (Vec<NameKey>, Vec<u32>) {
+    let mut entries: Vec<(u64, Vec<FileId>)> = map.into_iter().collect();
+    entries.sort_unstable_by_key(|(hash, _)| *hash);
+
+    let total_postings: usize = entries.iter().map(|(_, v)| v.len()).sum();
+    let mut keys = Vec::with_capacity(entries.len());
+    let mut postings = Vec::with_capacity(total_postings);
+
+    let mut offset: u32 = 0;
+    for (hash, v) in entries {
+        // v is already sorted by FileId (files are added in id order)
+        let len = v.len() as u32;
+        postings.extend_from_slice(&v);
+
+        keys.push(NameKey {
+            hash,
+            postings_offset: offset,
+            postings_len: len,
+        });
+
+        offset += len;
+    }
+
+    (keys, postings)
+}
+
 fn pack_ext_postings(ext_postings: Vec<Vec<FileId>>) -> (Vec<ExtKey>, Vec<u32>) {
     let mut keys = Vec::with_capacity(ext_postings.len());
     let total_postings: usize = ext_postings.iter().map(|v| v.len()).sum();
@@ -134,9 +215,10 @@ fn path_trigrams(path: &Path) -> Vec<Trigram> {
 
 #[cfg(not(unix))]
 fn path_trigrams(path: &Path) -> Vec<Trigram> {
-    // Fallback: no direct access to raw bytes, so we rely on UTF-8.
-    let s = path.to_string_lossy();
-    build_trigrams_for_string(&s)
+    // Fallback: no direct access to raw bytes, so we rely on UTF-8, with
+    // the `/` separators queries use.
+    let s = portable_path(path);
+    crate::trigram::build_trigrams_for_string(&s)
 }
 
 // TODO: Move this out
@@ -149,17 +231,18 @@ pub struct BuildResult {
 
 impl IndexBuilder {
     pub fn new(root: PathBuf) -> Self {
+        let root = normalize_root(root);
         let mut names_blob = Vec::with_capacity(1024);
 
-        // Intern root path string up front
-        let root_str = root.to_string_lossy();
+        // Intern root path string up front, with `/` separators on Windows
+        // too (`C:/Users/me`), like every other stored path.
+        let root_str = portable_path(&root);
         let (root_path_offset, root_path_len) = intern_string(&mut names_blob, &root_str);
 
         // ext_table[0] reserved for "no extension"
-        let ext_table = vec![];
+        let ext_table = vec![String::new()];
 
-        let mut ext_postings = Vec::new();
-        ext_postings.push(Vec::new());
+        let ext_postings = vec![Vec::new()];
 
         Self {
             root,
@@ -171,9 +254,16 @@ impl IndexBuilder {
             ext_table,
             ext_map: HashMap::new(),
             file_trigrams: HashMap::new(),
+            name_trigrams: HashMap::new(),
+            exact_names: HashMap::new(),
             dir_trigrams: HashMap::new(),
+            repo_roots: HashSet::new(),
+            file_hashes: Vec::new(),
+            dir_perms: HashMap::new(),
             root_path_offset,
             root_path_len,
+            changed_since: None,
+            alias: None,
         }
     }
 
@@ -199,8 +289,11 @@ impl IndexBuilder {
 
         let rel = match full_path.strip_prefix(&self.root) {
             Ok(p) => p,
-            // fallback to absolute path
-            Err(_) => full_path.as_path(),
+            Err(_) => match self.alias.as_deref().map(|a| full_path.strip_prefix(a)) {
+                Some(Ok(p)) => p,
+                // fallback to absolute path
+                _ => full_path.as_path(),
+            },
         };
 
         let rel_dir = rel.parent().unwrap_or_else(|| Path::new(""));
@@ -209,20 +302,49 @@ impl IndexBuilder {
 
         let dir_id = self.get_or_insert_dir(rel_dir);
 
+        if record.name == ".git" {
+            self.repo_roots.insert(dir_id);
+        }
+
         self.ext_postings[ext_id as usize].push(file_id);
 
-        let path_str = full_path.to_string_lossy();
+        let path_str = portable_path(full_path);
 
-        let (noise_flags, path_depth) = classify_noise(&path_str);
+        let (mut noise_flags, path_depth) = classify_noise(&path_str);
+        if record.generated {
+            noise_flags |= NoiseFlags::GENERATED;
+        }
 
-        let file_flags = compute_file_flags(&record, record.ignored_glob, record.user_excludes);
+        let mut file_flags = compute_file_flags(&record, record.ignored_glob, record.user_excludes);
+        if self
+            .changed_since
+            .is_some_and(|since| record.mtime_secs > since)
+        {
+            file_flags.insert(FileFlags::CHANGED_DURING_SCAN);
+        }
 
+        // The walker also sniffs `#!` lines; fall back to the extension for
+        // records built elsewhere.
+        let lang_id = record
+            .lang
+            .or_else(|| {
+                let ext = record.ext.as_deref().and_then(normalize_ext)?;
+                lang_for_ext(&ext)
+            })
+            .unwrap_or(0);
+
+        let perms = record.perms.unwrap_or(FilePerms {
+            uid: 0,
+            gid: 0,
+            mode: 0,
+        });
         self.files.push(FileMeta {
             atime_secs,
             ctime_secs,
             dir_id,
             ext_id,
             flag_bits: file_flags.bits(),
+            lang_id,
             mtime_secs,
             name_len,
             name_offset,
@@ -230,10 +352,65 @@ impl IndexBuilder {
             path_depth,
             size: record.size,
             _reserved: 0,
+            uid: perms.uid,
+            gid: perms.gid,
+            mode: perms.mode,
+            _reserved2: 0,
         });
+        // `0` marks "not hashed", so a genuine zero hash is nudged to 1.
+        self.file_hashes
+            .push(record.content_hash.map_or(0, |h| h.max(1)));
+
+        self.exact_names
+            .entry(name_hash(&record.name.to_lowercase()))
+            .or_default()
+            .push(file_id);
 
         // Build trigram index for files and dirs (relative path only).
-        self.add_trigrams(file_id, &record, rel, dir_id, file_flags);
+        self.add_trigrams(file_id, &record, rel, file_flags);
+    }
+
+    /// Accept records under `alias`, another path to the root such as a
+    /// symlink to it, as if they were under the root itself.
+    ///
+    /// The root is stored as given to [`IndexBuilder::new`]; records that
+    /// are under neither path are stored by their absolute path.
+    pub fn alias_root(&mut self, alias: PathBuf) {
+        self.alias = Some(normalize_root(alias));
+    }
+
+    /// Flag records added from now on that were modified after the second
+    /// of the Unix time `since` with [`FileFlags::CHANGED_DURING_SCAN`].
+    ///
+    /// A scan passes the time it started, so files written while it ran
+    /// can be told apart from files whose records are settled. Files
+    /// modified in that same second are not flagged: timestamps cannot tell
+    /// whether they were written before the scan started or after.
+    pub fn flag_changed_since(&mut self, since: u64) {
+        self.changed_since = Some(since);
+    }
+
+    /// Mark the directory containing `git_entry` as a repository root.
+    ///
+    /// `add_record` does this for any record named `.git`. Callers that drop
+    /// records before they reach the builder (e.g. directories) should pass
+    /// those `.git` paths here so repository detection still sees them.
+    pub fn add_repo_marker(&mut self, git_entry: &Path) {
+        let rel = git_entry.strip_prefix(&self.root).unwrap_or(git_entry);
+        let rel_dir = rel.parent().unwrap_or_else(|| Path::new(""));
+        let dir_id = self.get_or_insert_dir(rel_dir);
+        self.repo_roots.insert(dir_id);
+    }
+
+    /// Record the owner and mode of the directory at `dir`.
+    ///
+    /// `add_record` does this for directory records; like
+    /// [`add_repo_marker`](Self::add_repo_marker), this is for callers that
+    /// drop those records before they reach the builder. Directories that
+    /// end up holding nothing indexed are not added for it.
+    pub fn add_dir_perms(&mut self, dir: &Path, perms: FilePerms) {
+        let rel = dir.strip_prefix(&self.root).unwrap_or(dir);
+        self.dir_perms.insert(rel.to_path_buf(), perms);
     }
 
     /// Get or create a DirId for a *relative* directory path.
@@ -257,8 +434,8 @@ impl IndexBuilder {
         // Directory name is the last component
         let name = rel_dir
             .file_name()
-            .map(|os| os.to_string_lossy().into_owned())
-            .unwrap_or_else(String::new);
+            .map(|os| escape_name(os).into_owned())
+            .unwrap_or_default();
 
         let (name_offset, name_len) = intern_string(&mut self.names_blob, &name);
 
@@ -271,18 +448,27 @@ impl IndexBuilder {
             _reserved: 0,
         });
 
+        // Directory trigram index: relative directory path only.
+        for tri in path_trigrams(rel_dir) {
+            self.dir_trigrams.entry(tri).or_default().push(id);
+        }
+
         self.dir_map.insert(rel_dir.to_path_buf(), id);
         id
     }
 
+    /// Intern an extension into the ext table.
+    ///
+    /// Extensions are normalized with `normalize_ext` so that the table never
+    /// holds two spellings of the same extension (e.g. `PDF` and `pdf`).
     pub fn intern_ext(&mut self, ext: Option<&str>) -> ExtId {
-        match ext {
+        match ext.and_then(normalize_ext) {
             None => 0,
-            Some(e) => match self.ext_map.entry(e.to_string()) {
+            Some(e) => match self.ext_map.entry(e) {
                 Entry::Occupied(o) => *o.get(),
                 Entry::Vacant(v) => {
                     let id = self.ext_table.len() as ExtId;
-                    self.ext_table.push(e.to_string());
+                    self.ext_table.push(v.key().clone());
                     self.ext_postings.push(Vec::new());
                     v.insert(id);
                     id
@@ -291,19 +477,12 @@ impl IndexBuilder {
         }
     }
 
-    fn add_trigrams(
-        &mut self,
-        file_id: FileId,
-        rec: &FileRecord,
-        rel: &Path,
-        dir_id: DirId,
-        flags: FileFlags,
-    ) {
+    fn add_trigrams(&mut self, file_id: FileId, rec: &FileRecord, rel: &Path, flags: FileFlags) {
         if rec.is_dir {
-            // Directory trigram index: relative directory path only.
-            let trigrams = path_trigrams(rel);
-            for tri in trigrams {
-                self.dir_trigrams.entry(tri).or_default().push(dir_id);
+            // Interning the directory indexes its trigrams.
+            self.get_or_insert_dir(rel);
+            if let Some(perms) = rec.perms {
+                self.dir_perms.insert(rel.to_path_buf(), perms);
             }
             return;
         }
@@ -318,14 +497,113 @@ impl IndexBuilder {
         for tri in trigrams {
             self.file_trigrams.entry(tri).or_default().push(file_id);
         }
+
+        // Filename trigram index: the same files, name only, from the same
+        // bytes as the path.
+        let name_trigrams = match rec.full_path.file_name() {
+            Some(name) => path_trigrams(Path::new(name)),
+            None => build_trigrams_for_bytes(rec.name.as_bytes()),
+        };
+        for tri in name_trigrams {
+            self.name_trigrams.entry(tri).or_default().push(file_id);
+        }
     }
 
-    pub fn finish(self) -> StagedIndex {
+    /// Assign every file to its innermost enclosing repository.
+    ///
+    /// Nested repositories (submodules, vendored checkouts) form their own
+    /// boundary: their files belong to the nested repository only.
+    fn resolve_repos(&mut self) -> (Vec<RepoMeta>, Vec<RepoId>) {
+        let mut repos = Vec::new();
+
+        let root_repo = if self.repo_roots.contains(&u32::MAX) {
+            self.push_repo(&mut repos, u32::MAX)
+        } else {
+            u32::MAX
+        };
+
+        // Parents are interned before their children, so one forward pass
+        // sees every parent's repository before the child needs it.
+        let mut dir_repos: Vec<RepoId> = Vec::with_capacity(self.dirs.len());
+        for id in 0..self.dirs.len() {
+            let repo = if self.repo_roots.contains(&(id as DirId)) {
+                self.push_repo(&mut repos, id as DirId)
+            } else {
+                match self.dirs[id].parent {
+                    u32::MAX => root_repo,
+                    parent => dir_repos[parent as usize],
+                }
+            };
+            dir_repos.push(repo);
+        }
+
+        let file_repos = self
+            .files
+            .iter()
+            .map(|f| match f.dir_id {
+                u32::MAX => root_repo,
+                d => dir_repos[d as usize],
+            })
+            .collect();
+
+        (repos, file_repos)
+    }
+
+    /// Append a repository rooted at `root_dir`, named after that directory.
+    fn push_repo(&mut self, repos: &mut Vec<RepoMeta>, root_dir: DirId) -> RepoId {
+        let (name_offset, name_len) = if root_dir == u32::MAX {
+            let name = self
+                .root
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            intern_string(&mut self.names_blob, &name)
+        } else {
+            // Reuse the directory's interned name.
+            let dir = &self.dirs[root_dir as usize];
+            (dir.name_offset, dir.name_len)
+        };
+
+        repos.push(RepoMeta {
+            name_offset,
+            name_len,
+            root_dir,
+            _reserved: 0,
+        });
+        (repos.len() - 1) as RepoId
+    }
+
+    pub fn finish(mut self) -> StagedIndex {
+        let (repos, file_repos) = self.resolve_repos();
         let (file_trigram_keys, file_trigram_postings) = pack_trigram_map(self.file_trigrams);
+        let (name_trigram_keys, name_trigram_postings) = pack_trigram_map(self.name_trigrams);
         let (dir_trigram_keys, dir_trigram_postings) = pack_trigram_map(self.dir_trigrams);
+        let (exact_name_keys, exact_name_postings) = pack_name_map(self.exact_names);
         let (ext_index_keys, ext_index_postings) = pack_ext_postings(self.ext_postings);
+        let file_hashes = if self.file_hashes.iter().any(|&h| h != 0) {
+            self.file_hashes
+        } else {
+            Vec::new()
+        };
+        // Directories without a record of their own (interned for a file
+        // below them) get no permissions at all, so they hide their contents.
+        let dir_perms = if self.dir_perms.is_empty() {
+            Vec::new()
+        } else {
+            let mut perms = vec![DirPerms::default(); self.dirs.len()];
+            for (rel, &id) in &self.dir_map {
+                if let Some(p) = self.dir_perms.get(rel) {
+                    perms[id as usize] = DirPerms {
+                        uid: p.uid,
+                        gid: p.gid,
+                        mode: p.mode,
+                    };
+                }
+            }
+            perms
+        };
 
-        StagedIndex {
+        let mut staged = StagedIndex {
             root: self.root,
             names_blob: self.names_blob,
             root_path_offset: self.root_path_offset,
@@ -333,12 +611,51 @@ impl IndexBuilder {
             dirs: self.dirs,
             files: self.files,
             ext_table: self.ext_table,
+            repos,
+            file_repos,
+            file_hashes,
+            hash_max_bytes: 0,
+            build_flags: BuildFlags::empty(),
+            max_file_bytes: 0,
+            scan_started_secs: 0,
+            scan_finished_secs: 0,
+            build_stats: BuildStats::default(),
+            dir_paths: Vec::new(),
+            dir_perms,
             ext_index_keys,
             ext_index_postings,
             file_trigram_keys,
             file_trigram_postings,
+            name_trigram_keys,
+            name_trigram_postings,
+            exact_name_keys,
+            exact_name_postings,
             dir_trigram_keys,
             dir_trigram_postings,
-        }
+            dir_files: Vec::new(),
+            dir_file_postings: Vec::new(),
+            dir_tree: OnceLock::new(),
+        };
+        // Group entries by directory once the tree is known; the tree is
+        // kept, as queries on the staged index would build it anyway.
+        let tree = DirTree::build(&staged);
+        (staged.dir_files, staged.dir_file_postings) = tree.pack_files();
+        staged.dir_tree = OnceLock::from(tree);
+        staged
     }
 }
+
+#[cfg(any(test, feature = "test-support"))]
+impl StagedIndex {
+    /// An index of [`FileRecord::test_file`]s at `rels` under `root`, for tests.
+    pub fn of_files(root: impl Into<PathBuf>, rels: &[&str]) -> Self {
+        let mut builder = IndexBuilder::new(root.into());
+        let root = builder.root.clone();
+        builder.add_batch(rels.iter().map(|rel| FileRecord::test_file(root.join(rel))));
+        builder.finish()
+    }
+}
+
+#[cfg(test)]
+#[path = "builder_tests.rs"]
+mod tests;
@@ -0,0 +1,353 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+use blaze_runtime::{BoostRules, ConfigFile, PinSet};
+
+use super::*;
+use crate::{Index, IndexReader, QueryEngine, QueryPipeline, parse_query, rank_dirs};
+
+/// `/src` holds two repos, `app` with a submodule `app/vendor/lib`, and
+/// `tool` whose `.git` is a worktree file; `notes.txt` is in neither.
+fn staged() -> StagedIndex {
+    let root = PathBuf::from("/src");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        // Files arrive before the `.git` entry that makes their repo.
+        FileRecord::test_file(root.join("app/src/main.rs")),
+        FileRecord::test_file(root.join("app/vendor/lib/lib.rs")),
+        FileRecord::test_dir(root.join("app/.git")),
+        FileRecord::test_file(root.join("app/vendor/lib/.git")),
+        FileRecord::test_file(root.join("tool/.git")),
+        FileRecord::test_file(root.join("tool/run.rs")),
+        FileRecord::test_file(root.join("notes.txt")),
+    ]);
+    builder.finish()
+}
+
+fn repo_query<I: IndexReader + Sync>(index: &I, query: &str) -> Vec<String> {
+    let pipeline = QueryPipeline::new(index)
+        .parse(query)
+        .execute()
+        .rank_with_limit(None);
+    let mut paths: Vec<String> = pipeline.iter_with_paths().map(|(_, _, p)| p).collect();
+    paths.sort();
+    paths
+}
+
+fn file_repo_name<I: IndexReader>(index: &I, path: &str) -> Option<String> {
+    let fid = (0..index.get_file_count() as FileId)
+        .find(|&f| index.reconstruct_full_path(f) == path)
+        .unwrap();
+    index
+        .get_file_repo(fid)
+        .map(|r| index.get_repo_name(r).to_owned())
+}
+
+#[test]
+fn files_belong_to_innermost_repo() {
+    let staged = staged();
+
+    assert_eq!(staged.repo_count(), 3);
+    assert_eq!(
+        file_repo_name(&staged, "/src/app/src/main.rs").as_deref(),
+        Some("app")
+    );
+    assert_eq!(
+        file_repo_name(&staged, "/src/app/vendor/lib/lib.rs").as_deref(),
+        Some("lib")
+    );
+    assert_eq!(
+        file_repo_name(&staged, "/src/tool/run.rs").as_deref(),
+        Some("tool")
+    );
+    assert_eq!(file_repo_name(&staged, "/src/notes.txt"), None);
+}
+
+#[test]
+fn repo_root_paths_are_absolute() {
+    let staged = staged();
+    let mut roots: Vec<String> = (0..staged.repo_count() as RepoId)
+        .map(|r| staged.get_repo_root_path(r))
+        .collect();
+    roots.sort();
+
+    assert_eq!(roots, ["/src/app", "/src/app/vendor/lib", "/src/tool"]);
+}
+
+#[test]
+fn index_root_can_be_a_repo() {
+    let root = PathBuf::from("/work/blaze");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("README.md")),
+        FileRecord::test_file(root.join("crates/engine/lib.rs")),
+    ]);
+    builder.add_repo_marker(&root.join(".git"));
+    let staged = builder.finish();
+
+    assert_eq!(staged.repo_count(), 1);
+    assert_eq!(staged.get_repo_name(0), "blaze");
+    assert_eq!(staged.get_repo_root_path(0), "/work/blaze");
+    assert!((0..2).all(|f| staged.get_file_repo(f) == Some(0)));
+}
+
+#[test]
+fn repo_predicate_matches_name_and_path() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+    assert!(mapped.verify().is_ok());
+
+    let app = [
+        "/src/app/.git".to_owned(),
+        "/src/app/src/main.rs".to_owned(),
+    ];
+    assert_eq!(repo_query(&staged, "repo:app"), app);
+    assert_eq!(repo_query(&mapped, "repo:APP"), app);
+    assert_eq!(repo_query(&mapped, "repo:\"/src/app/\""), app);
+    assert_eq!(
+        repo_query(&mapped, "rs repo:/src/app/vendor/lib"),
+        ["/src/app/vendor/lib/lib.rs"]
+    );
+    assert!(repo_query(&mapped, "repo:missing").is_empty());
+}
+
+#[test]
+fn hash_predicate_finds_copies() {
+    let root = PathBuf::from("/src");
+    let hashed = |rel: &str, hash: Option<u64>| FileRecord {
+        content_hash: hash,
+        ..FileRecord::test_file(root.join(rel))
+    };
+
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        hashed("a/logo.png", Some(0x00c0_ffee_0000_0001)),
+        hashed("b/logo-copy.png", Some(0x00c0_ffee_0000_0001)),
+        hashed("b/other.png", Some(0x0bad_cafe_0000_0002)),
+        hashed("big.iso", None),
+    ]);
+    let mut staged = builder.finish();
+    staged.hash_max_bytes = 1024;
+    let mapped = Index::from_staged(&staged).unwrap();
+    assert!(mapped.verify().is_ok());
+    assert_eq!(mapped.hash_max_bytes(), Some(1024));
+    assert_eq!(mapped.get_file_hash(3), None);
+
+    let copies = [
+        "/src/a/logo.png".to_owned(),
+        "/src/b/logo-copy.png".to_owned(),
+    ];
+    assert_eq!(repo_query(&staged, "hash:00c0ffee00000001"), copies);
+    assert_eq!(repo_query(&mapped, "hash:00C0FFEE00000001"), copies);
+    assert_eq!(repo_query(&mapped, "hash:/src/b/logo-copy.png"), copies);
+    assert!(repo_query(&mapped, "hash:/src/big.iso").is_empty());
+    assert!(repo_query(&mapped, "hash:/src/missing.png").is_empty());
+}
+
+#[test]
+fn unhashed_index_has_no_hashes() {
+    let mapped = Index::from_staged(&staged()).unwrap();
+
+    assert_eq!(mapped.hash_max_bytes(), None);
+    assert!((0..mapped.get_file_count() as FileId).all(|f| mapped.get_file_hash(f).is_none()));
+}
+
+#[test]
+fn pins_filter_and_outrank_other_hits() {
+    let staged = staged();
+    let pins = PinSet::new(["/src/notes.txt", "/src/tool/"]);
+    let query = |q: &str| {
+        let pipeline = QueryPipeline::new(&staged)
+            .with_pins(pins.clone())
+            .parse(q)
+            .execute()
+            .rank_with_limit(None);
+        pipeline
+            .iter_with_paths()
+            .map(|(_, _, p)| p)
+            .collect::<Vec<_>>()
+    };
+
+    let mut pinned = query("pinned:yes");
+    pinned.sort();
+    assert_eq!(
+        pinned,
+        ["/src/notes.txt", "/src/tool/.git", "/src/tool/run.rs"]
+    );
+    let unpinned = query("pinned:no");
+    assert_eq!(unpinned.len(), staged.get_file_count() - pinned.len());
+    assert!(unpinned.iter().all(|p| !pinned.contains(p)));
+
+    // The pinned file outranks the other matches, whatever their scores.
+    let ranked = query("main.rs OR lib.rs OR run.rs");
+    assert_eq!(ranked.first().map(String::as_str), Some("/src/tool/run.rs"));
+}
+
+#[test]
+fn directory_boosts_lift_files_below_them() {
+    let staged = staged();
+    let top = |config: &str| {
+        let pipeline = QueryPipeline::new(&staged)
+            .with_boosts(BoostRules::resolve(
+                &ConfigFile::parse(PathBuf::from("config.toml"), config),
+                &mut Vec::new(),
+            ))
+            .parse("main.rs OR lib.rs OR run.rs")
+            .execute()
+            .rank_with_limit(Some(1));
+        pipeline.iter_with_paths().map(|(_, _, p)| p).next()
+    };
+
+    assert_eq!(
+        top("[boost]\n\"/src/app/vendor/**\" = 500\n").as_deref(),
+        Some("/src/app/vendor/lib/lib.rs")
+    );
+    assert_eq!(
+        top("[boost]\n\"/src/*/src\" = 500\n").as_deref(),
+        Some("/src/app/src/main.rs")
+    );
+    assert_eq!(
+        top("[boost]\n\"/src/app\" = -500\n").as_deref(),
+        Some("/src/tool/run.rs")
+    );
+}
+
+fn dir_query<I: IndexReader + Sync>(index: &I, query: &str) -> Vec<String> {
+    let query = parse_query(query);
+    let dirs = QueryEngine::new(index).eval_dirs(&query);
+    rank_dirs(index, &query, &dirs, &PinSet::default())
+        .into_iter()
+        .map(|d| index.reconstruct_dir_path(d))
+        .collect()
+}
+
+#[test]
+fn eval_dirs_matches_directory_paths() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    for (query, expected) in [
+        ("vendor", &["/src/app/vendor", "/src/app/vendor/lib"][..]),
+        ("lib", &["/src/app/vendor/lib"]),
+        ("app src", &["/src/app/src"]),
+        ("name:t*", &["/src/tool"]),
+        ("missing", &[]),
+    ] {
+        assert_eq!(dir_query(&staged, query), expected, "{query}");
+        assert_eq!(dir_query(&mapped, query), expected, "{query}");
+    }
+}
+
+#[test]
+fn build_stats_are_stored_after_the_index_metadata() {
+    let root = PathBuf::from("/src");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([FileRecord::test_file(root.join("main.rs"))]);
+    let mut staged = builder.finish();
+    assert_eq!(Index::from_staged(&staged).unwrap().build_stats(), None);
+
+    let stats = BuildStats {
+        build_millis: 1_250,
+        dirs_scanned: 40,
+        dirs_skipped: 2,
+        files_skipped: 7,
+        peak_memory_bytes: 64 << 20,
+    };
+    staged.build_stats = stats;
+    let index = Index::from_staged(&staged).unwrap();
+    assert_eq!(index.build_stats(), Some(stats));
+    assert_eq!(index.root_path(), Some("/src"));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names_are_indexed_escaped() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    use crate::hit_kind;
+
+    let root = PathBuf::from("/src");
+    let dir = root.join(OsStr::from_bytes(b"caf\xe9"));
+    let rec = FileRecord::test_file(dir.join(OsStr::from_bytes(b"r\xe9sum\xe9_notes.txt")));
+    let mut builder = IndexBuilder::new(root);
+    builder.add_batch([rec, FileRecord::test_file("/src/readme.md")]);
+    let staged = builder.finish();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    fn check<I: IndexReader>(index: &I) {
+        let fid = (0..index.get_file_count() as FileId)
+            .find(|&f| index.get_file_name(f) != "readme.md")
+            .unwrap();
+        assert_eq!(
+            index.reconstruct_full_path(fid),
+            "/src/caf\\xE9/r\\xE9sum\\xE9_notes.txt"
+        );
+        assert!(hit_kind(index, fid).escaped);
+        assert!(!hit_kind(index, 1 - fid).escaped);
+    }
+    check(&staged);
+    check(&mapped);
+
+    // Trigrams come from the raw bytes, so the valid parts still match.
+    assert_eq!(
+        repo_query(&mapped, "_notes"),
+        ["/src/caf\\xE9/r\\xE9sum\\xE9_notes.txt"]
+    );
+    assert_eq!(
+        repo_query(&mapped, "name:*notes.txt"),
+        ["/src/caf\\xE9/r\\xE9sum\\xE9_notes.txt"]
+    );
+}
+
+#[test]
+fn files_changed_during_the_scan_are_flagged() {
+    let root = PathBuf::from("/src");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.flag_changed_since(1_700_000_100);
+    let at = |rel: &str, mtime_secs: u64| FileRecord {
+        mtime_secs,
+        ..FileRecord::test_file(root.join(rel))
+    };
+    builder.add_batch([
+        at("early.rs", 1_700_000_000),
+        // Written in the second the scan started, most likely before it.
+        at("boundary.rs", 1_700_000_100),
+        at("late.rs", 1_700_000_101),
+    ]);
+    let mut staged = builder.finish();
+    staged.scan_started_secs = 1_700_000_100;
+    staged.scan_finished_secs = 1_700_000_160;
+
+    let index = Index::from_staged(&staged).unwrap();
+    let changed: Vec<String> = (0..index.get_file_count() as FileId)
+        .filter(|&f| {
+            index
+                .get_file_flags(f)
+                .contains(FileFlags::CHANGED_DURING_SCAN)
+        })
+        .map(|f| index.reconstruct_full_path(f))
+        .collect();
+    assert_eq!(changed, ["/src/late.rs"]);
+    assert_eq!(index.scan_times(), Some((1_700_000_100, 1_700_000_160)));
+}
+
+#[test]
+fn records_under_an_alias_of_the_root_are_stored_relative_to_it() {
+    let root = PathBuf::from("/var/home/me");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.alias_root(PathBuf::from("/home/me/"));
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/main.rs")),
+        FileRecord::test_file("/home/me/src/lib.rs"),
+    ]);
+    let staged = builder.finish();
+
+    let paths: Vec<String> = (0..staged.get_file_count() as FileId)
+        .map(|f| staged.reconstruct_full_path(f))
+        .collect();
+    assert_eq!(
+        paths,
+        ["/var/home/me/src/main.rs", "/var/home/me/src/lib.rs"]
+    );
+    assert_eq!(staged.dir_count(), 1);
+}
@@ -0,0 +1,544 @@
+//! Index bundles for moving an index between machines.
+//!
+//! [`export_bundle`] packs an index, its delta if it has one, and a snapshot
+//! of the settings it was built with into a single file. [`import_bundle`]
+//! unpacks it elsewhere, optionally pointing the index at a different root,
+//! so an index of a shared drive built on one machine can be searched on
+//! another that mounts the drive at a different path, without rescanning
+//! it over the network.
+//!
+//! Each entry records the length and BLAKE3 hash of its contents, like the
+//! index [manifest](super::manifest), and is refused on import unless they
+//! match. Entries may be LZ4-compressed. The importing side writes a fresh
+//! manifest for what it imported.
+//!
+//! Bundle layout (little-endian):
+//!
+//! ```text
+//! BundleHeader
+//! EntryHeader, then stored_len bytes     // once per entry
+//! ```
+//!
+//! The config entry is UTF-8 text, one setting per line:
+//!
+//! ```text
+//! blaze-bundle-config 1
+//! root <path>
+//! created_secs <n>
+//! files <n>
+//! ...
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{borrow::Cow, fs, io::Write};
+use std::{
+    fmt::Write as _,
+    io::{self, Error, ErrorKind},
+    mem,
+    path::Path,
+    sync::Arc,
+};
+
+use blaze_fs::{normalize_root, portable_path};
+use bytemuck::{Pod, Zeroable, bytes_of, pod_read_unaligned};
+use crc32fast::Hasher;
+#[cfg(not(target_arch = "wasm32"))]
+use tempfile::NamedTempFile;
+
+use super::{
+    Index, IndexHeader, IndexMeta, IndexReader, MIN_HEADER_SIZE, check_header_size, decode_header,
+    delta::Delta,
+    persist::{INDEX_MAGIC, INDEX_VERSION, SECTION_ALIGNMENT, align_up},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::{
+    delta::set_delta_root, delta_path, persist::write_bytes_atomic, remove_delta, write_manifest,
+};
+
+pub const BUNDLE_MAGIC: u32 = u32::from_le_bytes(*b"BLZB");
+pub const BUNDLE_VERSION: u32 = 1;
+
+const CONFIG_HEADER: &str = "blaze-bundle-config 1";
+
+const ENTRY_INDEX: u32 = 1;
+const ENTRY_DELTA: u32 = 2;
+const ENTRY_CONFIG: u32 = 3;
+
+/// Entry contents are an LZ4 block.
+const FLAG_LZ4: u32 = 1 << 0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct BundleHeader {
+    magic: u32,
+    version: u32,
+    entry_count: u32,
+    _reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct EntryHeader {
+    kind: u32,
+    flags: u32,
+    /// Length of the contents once decompressed
+    raw_len: u64,
+    /// Length of the bytes following this header
+    stored_len: u64,
+    /// BLAKE3 hash of the decompressed contents
+    hash: [u8; 32],
+}
+
+impl BundleHeader {
+    fn new(entry_count: usize) -> Self {
+        Self {
+            magic: BUNDLE_MAGIC,
+            version: BUNDLE_VERSION,
+            entry_count: entry_count as u32,
+            _reserved: 0,
+        }
+    }
+}
+
+impl EntryHeader {
+    /// Header for `raw`, stored as `stored_len` bytes.
+    fn new(kind: u32, raw: &[u8], stored_len: usize, compressed: bool) -> Self {
+        Self {
+            kind,
+            flags: if compressed { FLAG_LZ4 } else { 0 },
+            raw_len: raw.len() as u64,
+            stored_len: stored_len as u64,
+            hash: *blake3::hash(raw).as_bytes(),
+        }
+    }
+}
+
+/// The entries of a bundle of `index`, whose config is `config_text`.
+fn entries<'a>(index: &'a Index, config_text: &'a str) -> Vec<(u32, &'a [u8])> {
+    let mut entries: Vec<(u32, &[u8])> = vec![
+        (ENTRY_CONFIG, config_text.as_bytes()),
+        (ENTRY_INDEX, &index.bytes),
+    ];
+    if let Some(delta) = &index.delta {
+        entries.push((ENTRY_DELTA, &delta.index.bytes));
+    }
+    entries
+}
+
+/// How the exported index was built, as recorded in its bundle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleConfig {
+    /// Root the index was built for on the exporting machine.
+    pub root: String,
+    pub created_secs: u64,
+    pub files: u64,
+    pub dirs: u64,
+    /// Whether a delta was exported along with the index.
+    pub delta: bool,
+    pub build_flags: u32,
+    /// `0` if hashing was off.
+    pub hash_max_bytes: u64,
+    /// `0` if file sizes were not limited.
+    pub max_file_bytes: u64,
+}
+
+impl BundleConfig {
+    fn of(index: &Index) -> Self {
+        Self {
+            root: index.root_path().unwrap_or_default().to_string(),
+            created_secs: index.created_secs().unwrap_or_default(),
+            files: index.live_file_count() as u64,
+            dirs: index.dir_count() as u64,
+            delta: index.delta.is_some(),
+            build_flags: index.build_flags().bits(),
+            hash_max_bytes: index.hash_max_bytes().unwrap_or_default(),
+            max_file_bytes: index.max_file_bytes().unwrap_or_default(),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let mut out = format!("{CONFIG_HEADER}\n");
+        let _ = writeln!(out, "root {}", self.root);
+        for (name, value) in [
+            ("created_secs", self.created_secs),
+            ("files", self.files),
+            ("dirs", self.dirs),
+            ("delta", self.delta as u64),
+            ("build_flags", self.build_flags as u64),
+            ("hash_max_bytes", self.hash_max_bytes),
+            ("max_file_bytes", self.max_file_bytes),
+        ] {
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+
+    /// Parse an encoded config, skipping settings it does not know so
+    /// later versions can add some.
+    fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != CONFIG_HEADER {
+            return None;
+        }
+
+        let mut config = Self::default();
+        for line in lines {
+            let (name, value) = line.split_once(' ')?;
+            if name == "root" {
+                config.root = value.to_string();
+                continue;
+            }
+            let Ok(n) = value.parse::<u64>() else {
+                continue;
+            };
+            match name {
+                "created_secs" => config.created_secs = n,
+                "files" => config.files = n,
+                "dirs" => config.dirs = n,
+                "delta" => config.delta = n != 0,
+                "build_flags" => config.build_flags = n as u32,
+                "hash_max_bytes" => config.hash_max_bytes = n,
+                "max_file_bytes" => config.max_file_bytes = n,
+                _ => {}
+            }
+        }
+        Some(config)
+    }
+}
+
+/// Write the index at `index_path`, with its delta if it has one, to a
+/// bundle at `bundle_path`, LZ4-compressing the entries if `compress` is set.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_bundle(
+    index_path: &Path,
+    bundle_path: &Path,
+    compress: bool,
+) -> io::Result<BundleConfig> {
+    // Taking the bytes from the mapped index keeps the index and its delta
+    // consistent even if the index is rewritten meanwhile.
+    let index = Index::open(index_path)?;
+    let config = BundleConfig::of(&index);
+
+    let config_text = config.encode();
+    let entries = entries(&index, &config_text);
+
+    let parent = bundle_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut tmp = NamedTempFile::new_in(parent)?;
+    {
+        let mut out = io::BufWriter::new(tmp.as_file_mut());
+        out.write_all(bytes_of(&BundleHeader::new(entries.len())))?;
+        for (kind, raw) in entries {
+            let compressed = compress.then(|| lz4_flex::block::compress(raw));
+            let stored = compressed.as_deref().unwrap_or(raw);
+            let entry = EntryHeader::new(kind, raw, stored.len(), compress);
+            out.write_all(bytes_of(&entry))?;
+            out.write_all(stored)?;
+        }
+        out.flush()?;
+    }
+    tmp.as_file().sync_all()?;
+    tmp.persist(bundle_path).map_err(|e| e.error)?;
+    Ok(config)
+}
+
+/// An uncompressed bundle of an open index, read a piece at a time, for
+/// serving the index to another daemon.
+///
+/// Only the bundle's headers and config are held in memory; the index and
+/// its delta are read from the index's own mapping.
+pub struct IndexBundle {
+    index: Arc<Index>,
+    parts: Vec<Part>,
+    len: u64,
+    id: u64,
+}
+
+enum Part {
+    Bytes(Vec<u8>),
+    Index,
+    Delta,
+}
+
+impl IndexBundle {
+    /// Lay out a bundle of `index`, hashing its contents.
+    pub fn new(index: Arc<Index>) -> Self {
+        let config_text = BundleConfig::of(&index).encode();
+        let entries = entries(&index, &config_text);
+        let mut headers = bytes_of(&BundleHeader::new(entries.len())).to_vec();
+        let mut parts = Vec::new();
+        for (kind, raw) in entries {
+            headers.extend_from_slice(bytes_of(&EntryHeader::new(kind, raw, raw.len(), false)));
+            match kind {
+                ENTRY_INDEX => parts.extend([Part::Bytes(mem::take(&mut headers)), Part::Index]),
+                ENTRY_DELTA => parts.extend([Part::Bytes(mem::take(&mut headers)), Part::Delta]),
+                _ => headers.extend_from_slice(raw),
+            }
+        }
+        if !headers.is_empty() {
+            parts.push(Part::Bytes(headers));
+        }
+
+        // The headers hold a hash of every entry, so they identify the contents.
+        let mut hasher = blake3::Hasher::new();
+        for part in &parts {
+            if let Part::Bytes(bytes) = part {
+                hasher.update(bytes);
+            }
+        }
+        let hash = hasher.finalize();
+        let mut bundle = Self {
+            index,
+            parts,
+            len: 0,
+            id: u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap()),
+        };
+        bundle.len = bundle
+            .parts
+            .iter()
+            .map(|p| bundle.part(p).len() as u64)
+            .sum();
+        bundle
+    }
+
+    /// Total length of the bundle in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Identifies the bundle's contents: bundles of different indexes have
+    /// different ids.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Up to `max_len` bytes of the bundle from `offset`; fewer at the end of
+    /// one of its parts, and none past its end.
+    pub fn read_at(&self, offset: u64, max_len: usize) -> &[u8] {
+        let mut start = offset;
+        for part in &self.parts {
+            let bytes = self.part(part);
+            if start < bytes.len() as u64 {
+                let rest = &bytes[start as usize..];
+                return &rest[..rest.len().min(max_len)];
+            }
+            start -= bytes.len() as u64;
+        }
+        &[]
+    }
+
+    fn part<'a>(&'a self, part: &'a Part) -> &'a [u8] {
+        match part {
+            Part::Bytes(bytes) => bytes,
+            Part::Index => &self.index.bytes,
+            Part::Delta => self.index.delta.as_ref().map_or(&[], |d| &d.index.bytes),
+        }
+    }
+}
+
+/// Contents of a bundle, checked against their hashes.
+struct Bundle {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    config: BundleConfig,
+    index: Vec<u8>,
+    delta: Option<Vec<u8>>,
+}
+
+impl Bundle {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read(path)?, &path.display().to_string())
+    }
+
+    /// Parse the bundle in `bytes`, naming it `name` in errors.
+    fn parse(bytes: &[u8], name: &str) -> io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("{name}: {msg}"));
+
+        let mut rest: &[u8] = bytes;
+        let mut take = |n: usize| -> io::Result<&[u8]> {
+            if rest.len() < n {
+                return Err(invalid("bundle is truncated"));
+            }
+            let (head, tail) = rest.split_at(n);
+            rest = tail;
+            Ok(head)
+        };
+
+        let header: BundleHeader = pod_read_unaligned(take(mem::size_of::<BundleHeader>())?);
+        if header.magic != BUNDLE_MAGIC {
+            return Err(invalid("not a blaze index bundle"));
+        }
+        if header.version != BUNDLE_VERSION {
+            return Err(invalid("bundle version is not supported"));
+        }
+
+        let mut config = None;
+        let mut index = None;
+        let mut delta = None;
+        for _ in 0..header.entry_count {
+            let entry: EntryHeader = pod_read_unaligned(take(mem::size_of::<EntryHeader>())?);
+            let stored =
+                take(usize::try_from(entry.stored_len).map_err(|_| invalid("entry too large"))?)?;
+            let raw = if entry.flags & FLAG_LZ4 != 0 {
+                lz4_flex::block::decompress(stored, entry.raw_len as usize)
+                    .map_err(|_| invalid("entry does not decompress"))?
+            } else {
+                stored.to_vec()
+            };
+            if raw.len() as u64 != entry.raw_len || blake3::hash(&raw).as_bytes() != &entry.hash {
+                return Err(invalid("entry does not match its hash"));
+            }
+            match entry.kind {
+                ENTRY_CONFIG => {
+                    let text =
+                        String::from_utf8(raw).map_err(|_| invalid("config is not UTF-8"))?;
+                    config = Some(
+                        BundleConfig::decode(&text)
+                            .ok_or_else(|| invalid("config is malformed"))?,
+                    );
+                }
+                ENTRY_INDEX => index = Some(raw),
+                ENTRY_DELTA => delta = Some(raw),
+                // Entries added by later versions are optional extras.
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            config: config.ok_or_else(|| invalid("bundle has no config"))?,
+            index: index.ok_or_else(|| invalid("bundle has no index"))?,
+            delta,
+        })
+    }
+}
+
+/// Install the index in the bundle at `bundle_path` as the index at
+/// `index_path`, replacing any index and delta there, and write its
+/// manifest. With `root`, the index is pointed at `root` instead of the
+/// root it was built for.
+///
+/// Returns the config recorded by the exporting side.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn import_bundle(
+    bundle_path: &Path,
+    index_path: &Path,
+    root: Option<&Path>,
+) -> io::Result<BundleConfig> {
+    let bundle = Bundle::read(bundle_path)?;
+    let index = match root {
+        Some(root) => set_root(&bundle.index, root)?,
+        None => {
+            read_header(&bundle.index)?;
+            bundle.index
+        }
+    };
+
+    // As for a full index write: drop the old delta first, so a crash
+    // leaves the new index without a delta rather than with the old one.
+    remove_delta(index_path)?;
+    write_bytes_atomic(index_path, &index)?;
+    if let Some(delta) = &bundle.delta {
+        let delta = match root {
+            Some(root) => Cow::Owned(set_delta_root(delta, root)?),
+            None => Cow::Borrowed(delta),
+        };
+        write_bytes_atomic(&delta_path(index_path), &delta)?;
+    }
+    write_manifest(index_path)?;
+    Ok(bundle.config)
+}
+
+impl Index {
+    /// Load the index in the bundle held in `bytes`, with its delta
+    /// overlaid if it has one, without installing it anywhere.
+    ///
+    /// The index is read from memory, so this works where files cannot be
+    /// mapped, e.g. to search an exported bundle from wasm.
+    pub fn from_bundle(bytes: &[u8]) -> io::Result<Self> {
+        let bundle = Bundle::parse(bytes, "bundle")?;
+        let mut index = Index::from_bytes(&bundle.index)?;
+        if let Some(delta) = &bundle.delta {
+            index.delta = Delta::from_index(Index::from_bytes(delta)?, &index)?.map(Box::new);
+        }
+        Ok(index)
+    }
+}
+
+/// The header of the index in `bytes`, if it is one this version reads.
+fn read_header(bytes: &[u8]) -> io::Result<IndexHeader> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    if bytes.len() < MIN_HEADER_SIZE {
+        return Err(invalid("bundled index too small for header"));
+    }
+    let header = decode_header(bytes);
+    if header.magic != INDEX_MAGIC {
+        return Err(invalid("bundled file is not an index"));
+    }
+    if header.version != INDEX_VERSION {
+        return Err(invalid(
+            "bundled index was written by another version of blaze",
+        ));
+    }
+    check_header_size(&header, bytes.len())?;
+    Ok(header)
+}
+
+/// Point the index in `bytes` at `root`. Stored paths are relative to the
+/// root, so they stay as they are.
+///
+/// The new root is appended to the names blob, and the sections after it
+/// move back by a multiple of the section alignment so they stay aligned.
+/// The old root is left in the blob, unused.
+pub fn set_root(bytes: &[u8], root: &Path) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    let mut header = read_header(bytes)?;
+
+    let meta_start = header.metadata.offset as usize;
+    let meta_end = meta_start + mem::size_of::<IndexMeta>();
+    if (header.metadata.len as usize) < mem::size_of::<IndexMeta>() || meta_end > bytes.len() {
+        return Err(invalid("index has no metadata"));
+    }
+    let mut meta: IndexMeta = pod_read_unaligned(&bytes[meta_start..meta_end]);
+
+    let blob = header.names_blob;
+    let blob_end = (blob.offset + blob.len) as usize;
+    if blob.len == 0 || blob_end > bytes.len() {
+        return Err(invalid("index names are out of bounds"));
+    }
+
+    let root = portable_path(&normalize_root(root.to_path_buf())).into_owned();
+    let shift = align_up(root.len() as u64, SECTION_ALIGNMENT);
+    meta.root_path_offset =
+        u32::try_from(blob.len).map_err(|_| invalid("index names are too large"))?;
+    meta.root_path_len = root.len() as u32;
+
+    for section in header.sections_mut() {
+        if section.offset as usize >= blob_end {
+            section.offset += shift;
+        }
+    }
+    header.names_blob.len += root.len() as u64;
+    // Only the part of the header the index has is rewritten; fields past
+    // the ones this version knows are kept as they are.
+    let stored = (header.header_size as usize).min(mem::size_of::<IndexHeader>());
+    header.header_crc32 = 0;
+    let mut hasher = Hasher::new();
+    hasher.update(&bytes_of(&header)[..stored]);
+    header.header_crc32 = hasher.finalize();
+
+    let mut out = Vec::with_capacity(bytes.len() + shift as usize);
+    out.extend_from_slice(&bytes[..blob_end]);
+    out.extend_from_slice(root.as_bytes());
+    out.resize(blob_end + shift as usize, 0);
+    out.extend_from_slice(&bytes[blob_end..]);
+    out[..stored].copy_from_slice(&bytes_of(&header)[..stored]);
+    out[meta_start..meta_end].copy_from_slice(bytes_of(&meta));
+    Ok(out)
+}
+
+#[cfg(test)]
+#[path = "bundle_tests.rs"]
+mod tests;
@@ -0,0 +1,209 @@
+use std::{fs, io::ErrorKind, path::Path, sync::Arc};
+
+use tempfile::tempdir;
+
+use super::*;
+use crate::{StagedDelta, StagedIndex, write_index_atomic};
+
+fn paths(index: &Index) -> Vec<String> {
+    let mut paths: Vec<String> = (0..index.get_file_count() as u32)
+        .filter(|&f| !index.is_file_removed(f))
+        .map(|f| index.reconstruct_full_path(f))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn config_round_trips() {
+    let config = BundleConfig {
+        root: "/mnt/my share".to_string(),
+        created_secs: 1_700_000_000,
+        files: 3,
+        dirs: 2,
+        delta: true,
+        build_flags: 5,
+        hash_max_bytes: 16,
+        max_file_bytes: 0,
+    };
+    assert_eq!(BundleConfig::decode(&config.encode()), Some(config));
+    assert_eq!(BundleConfig::decode("root /x\n"), None);
+}
+
+#[test]
+fn imports_under_a_new_root() {
+    for compress in [false, true] {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.bin");
+        write_index_atomic(
+            &path,
+            &StagedIndex::of_files("/mnt/share", &["src/main.rs", "docs/readme.md"]),
+            0,
+        )
+        .unwrap();
+
+        let bundle = dir.path().join("share.blz");
+        let exported = export_bundle(&path, &bundle, compress).unwrap();
+        assert_eq!(exported.root, "/mnt/share");
+        assert_eq!(exported.files, 2);
+
+        let imported_path = dir.path().join("laptop").join("index.bin");
+        let imported =
+            import_bundle(&bundle, &imported_path, Some(Path::new("/Volumes/share"))).unwrap();
+        assert_eq!(imported, exported);
+
+        let index = Index::open_verified(&imported_path).unwrap();
+        assert_eq!(index.root_path(), Some("/Volumes/share"));
+        assert_eq!(
+            paths(&index),
+            [
+                "/Volumes/share/docs/readme.md",
+                "/Volumes/share/src/main.rs"
+            ]
+        );
+        assert!(
+            index
+                .query_trigram_on_disk(crate::Trigram::from_bytes(b'm', b'a', b'i'))
+                .is_some()
+        );
+    }
+}
+
+#[test]
+fn carries_the_delta() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/mnt/share", &["src/main.rs"]),
+        0,
+    )
+    .unwrap();
+    let base = Index::open(&path).unwrap();
+    StagedDelta::new(
+        &base,
+        StagedIndex::of_files("/mnt/share", &["src/new.rs"]),
+        vec![],
+        vec![],
+    )
+    .write_atomic(&path)
+    .unwrap();
+    drop(base);
+
+    let bundle = dir.path().join("share.blz");
+    assert!(export_bundle(&path, &bundle, true).unwrap().delta);
+
+    let imported_path = dir.path().join("imported.bin");
+    import_bundle(&bundle, &imported_path, Some(Path::new("/srv/share"))).unwrap();
+    let index = Index::open_verified(&imported_path).unwrap();
+    assert!(index.delta_usage().is_some());
+    assert_eq!(
+        paths(&index),
+        ["/srv/share/src/main.rs", "/srv/share/src/new.rs"]
+    );
+}
+
+#[test]
+fn damaged_bundle_is_refused() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/mnt/share", &["src/main.rs"]),
+        0,
+    )
+    .unwrap();
+    let bundle = dir.path().join("share.blz");
+    export_bundle(&path, &bundle, false).unwrap();
+
+    let mut bytes = fs::read(&bundle).unwrap();
+    let at = bytes.windows(7).position(|w| w == b"main.rs").unwrap();
+    bytes[at] = b'x';
+    fs::write(&bundle, &bytes).unwrap();
+
+    let imported_path = dir.path().join("imported.bin");
+    let err = import_bundle(&bundle, &imported_path, None).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("does not match its hash"));
+    assert!(!imported_path.exists());
+
+    fs::write(&bundle, b"not a bundle").unwrap();
+    assert!(import_bundle(&bundle, &imported_path, None).is_err());
+}
+
+#[test]
+fn served_bundle_imports_like_an_export() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/mnt/share", &["src/main.rs", "docs/readme.md"]),
+        0,
+    )
+    .unwrap();
+    let index = Arc::new(Index::open(&path).unwrap());
+
+    let bundle = IndexBundle::new(Arc::clone(&index));
+    assert_eq!(IndexBundle::new(index).id(), bundle.id());
+
+    let mut bytes = Vec::new();
+    while (bytes.len() as u64) < bundle.len() {
+        let piece = bundle.read_at(bytes.len() as u64, 100);
+        assert!(!piece.is_empty() && piece.len() <= 100);
+        bytes.extend_from_slice(piece);
+    }
+    assert!(bundle.read_at(bundle.len(), 100).is_empty());
+
+    let served = dir.path().join("served.blz");
+    fs::write(&served, &bytes).unwrap();
+    let imported_path = dir.path().join("follower.bin");
+    let config = import_bundle(&served, &imported_path, None).unwrap();
+    assert_eq!(config.files, 2);
+    assert_eq!(
+        paths(&Index::open_verified(&imported_path).unwrap()),
+        ["/mnt/share/docs/readme.md", "/mnt/share/src/main.rs"]
+    );
+}
+
+#[test]
+fn opens_a_bundle_from_memory() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/mnt/share", &["src/main.rs", "docs/readme.md"]),
+        0,
+    )
+    .unwrap();
+    let base = Index::open(&path).unwrap();
+    StagedDelta::new(
+        &base,
+        StagedIndex::of_files("/mnt/share", &["src/new.rs"]),
+        vec![],
+        vec![],
+    )
+    .write_atomic(&path)
+    .unwrap();
+    drop(base);
+
+    let bundle = dir.path().join("share.blz");
+    export_bundle(&path, &bundle, true).unwrap();
+    // An odd offset leaves the index bytes unaligned within the buffer.
+    let mut bytes = vec![0];
+    bytes.extend(fs::read(&bundle).unwrap());
+
+    let index = Index::from_bundle(&bytes[1..]).unwrap();
+    assert!(index.delta_usage().is_some());
+    assert_eq!(
+        paths(&index),
+        [
+            "/mnt/share/docs/readme.md",
+            "/mnt/share/src/main.rs",
+            "/mnt/share/src/new.rs"
+        ]
+    );
+    assert!(index.budget().resident_bytes.is_none());
+
+    let err = Index::from_bundle(b"not a bundle").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,62 @@
+//! Storage behind an [`Index`](super::Index).
+//!
+//! An index read from disk is mapped. Where there is no mmap, as on wasm,
+//! or where the bytes arrive in memory, as from a bundle, they are copied
+//! into an owned buffer instead. The buffer is allocated as `u64` words so
+//! the sections keep the 8-byte alignment their tables are cast with.
+
+use std::{mem, ops::Deref};
+
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+
+pub(crate) enum IndexBytes {
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(Mmap),
+    Owned {
+        words: Vec<u64>,
+        len: usize,
+    },
+}
+
+impl IndexBytes {
+    /// Copy `bytes` into an aligned buffer.
+    pub(crate) fn copy_from(bytes: &[u8]) -> Self {
+        let mut words = vec![0u64; bytes.len().div_ceil(mem::size_of::<u64>())];
+        bytemuck::cast_slice_mut::<u64, u8>(&mut words)[..bytes.len()].copy_from_slice(bytes);
+        Self::Owned {
+            words,
+            len: bytes.len(),
+        }
+    }
+
+    /// Whether the bytes are mapped from a file, so the page cache holds them.
+    pub(crate) fn is_mapped(&self) -> bool {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped(_) => true,
+            Self::Owned { .. } => false,
+        }
+    }
+
+    /// The mapping, if the bytes are mapped from a file.
+    #[cfg(unix)]
+    pub(crate) fn mapping(&self) -> Option<&Mmap> {
+        match self {
+            Self::Mapped(mmap) => Some(mmap),
+            Self::Owned { .. } => None,
+        }
+    }
+}
+
+impl Deref for IndexBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped(mmap) => mmap,
+            Self::Owned { words, len } => &bytemuck::cast_slice(words)[..*len],
+        }
+    }
+}
@@ -7,9 +7,13 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use blaze_fs::canonical_root;
 use bytemuck::from_bytes;
 
-use super::{INDEX_MAGIC, INDEX_VERSION, IndexHeader, IndexMeta};
+use super::{
+    INDEX_MAGIC, INDEX_VERSION, IndexHeader, IndexMeta, MIN_HEADER_SIZE, check_header_size,
+    decode_header,
+};
 
 pub enum IndexCompatibility {
     Missing,
@@ -22,7 +26,7 @@ pub enum IndexCompatibility {
 /// Check index header compatibility (existence, magic, version, flags).
 ///
 /// This is a *cheap* probe:
-/// - Only reads the fixed-size header from disk
+/// - Only reads the header from disk
 /// - Returns `Ok(Missing/Corrupt/…/Ok(..))` for logical outcomes
 /// - Only returns `Err(io::Error)` for actual OS/I/O failures (e.g. open denied)
 pub fn check_index_header(path: &Path) -> io::Result<IndexCompatibility> {
@@ -30,17 +34,22 @@ pub fn check_index_header(path: &Path) -> io::Result<IndexCompatibility> {
         return Ok(IndexCompatibility::Missing);
     }
 
-    let mut file = File::open(path)?;
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
 
-    let mut buf = [0u8; mem::size_of::<IndexHeader>()];
-    if let Err(e) = file.read_exact(&mut buf) {
+    let mut buf = Vec::with_capacity(mem::size_of::<IndexHeader>());
+    if let Err(e) = file
+        .take(mem::size_of::<IndexHeader>() as u64)
+        .read_to_end(&mut buf)
+    {
         eprintln!("[index] failed to read header from {path:?}: {e}");
         return Ok(IndexCompatibility::Corrupt);
     }
-
-    // SAFETY: IndexHeader is Pod, buffer is exactly the right size.
-    let header_ref: &IndexHeader = from_bytes(&buf);
-    let header: IndexHeader = *header_ref;
+    if buf.len() < MIN_HEADER_SIZE {
+        eprintln!("[index] header of {path:?} is truncated");
+        return Ok(IndexCompatibility::Corrupt);
+    }
+    let header = decode_header(&buf);
 
     // Basic sanity: magic
     if header.magic != INDEX_MAGIC {
@@ -55,6 +64,10 @@ pub fn check_index_header(path: &Path) -> io::Result<IndexCompatibility> {
         });
     }
 
+    if check_header_size(&header, file_len as usize).is_err() {
+        return Ok(IndexCompatibility::Corrupt);
+    }
+
     Ok(IndexCompatibility::Ok(Box::new(header)))
 }
 
@@ -84,7 +97,7 @@ fn read_index_root(path: &Path, header: &IndexHeader) -> io::Result<PathBuf> {
     let root_off = meta.root_path_offset as u64;
     let root_len = meta.root_path_len as u64;
 
-    if root_off.checked_add(root_len).unwrap_or(u64::MAX) > names_desc.len {
+    if root_off.saturating_add(root_len) > names_desc.len {
         return Err(io::Error::new(
             ErrorKind::InvalidData,
             "root path lies outside names_blob section",
@@ -115,15 +128,11 @@ pub fn check_index_compatibility(
         IndexCompatibility::Ok(header) => {
             match read_index_root(path, &header) {
                 Ok(on_disk_root) => {
-                    // Canonicalise the requested root; if that fails, fall back.
-                    let canonical_requested = requested_root
-                        .canonicalize()
-                        .unwrap_or_else(|_| requested_root.to_path_buf());
-
-                    // Canonicalise the stored root as well; if it no longer exists,
-                    // compare the raw stored path.
-                    let canonical_on_disk =
-                        on_disk_root.canonicalize().unwrap_or(on_disk_root.clone());
+                    // Either root may be reached through a symlink, a trailing
+                    // slash or `.` segments; roots that no longer exist are
+                    // cleaned up lexically.
+                    let canonical_requested = canonical_root(requested_root);
+                    let canonical_on_disk = canonical_root(&on_disk_root);
 
                     if canonical_on_disk != canonical_requested {
                         Ok(IndexCompatibility::RootMismatch {
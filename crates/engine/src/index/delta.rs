@@ -0,0 +1,465 @@
+//! Delta files overlaid on an index between full rewrites.
+//!
+//! A refresh that changed a small part of the tree writes `<index>.delta`
+//! instead of rewriting the whole index. The delta is itself an index of
+//! the added and modified files, followed by a sidecar recording which
+//! files and directories of the base index are gone, where each of the
+//! delta's directories sits in the base tree, and which repository each
+//! added file belongs to.
+//!
+//! [`Index::open`] overlays the delta, so readers see one index. Base file
+//! ids come first and keep their numbering; the delta's files follow.
+//! Removed and modified base files keep their ids but never match again.
+//! Directories the base already has are shared, and new ones are numbered
+//! after the base's.
+//!
+//! Each refresh rewrites the delta against the base, so there is at most
+//! one; writing a full index removes it.
+//!
+//! Sidecar layout, appended after the delta's index bytes (little-endian):
+//!
+//! ```text
+//! u32 removed_files[removed_files]   // base file ids
+//! u32 removed_dirs[removed_dirs]     // base dir ids
+//! u32 dir_map[dir count of the delta]
+//! u32 file_repos[file count of the delta]
+//! DeltaTrailer
+//! ```
+
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Error, ErrorKind},
+    mem,
+    path::{Path, PathBuf},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::HashMap, fs::File, io::Write};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bytemuck::bytes_of;
+use bytemuck::{Pod, Zeroable, pod_read_unaligned};
+#[cfg(not(target_arch = "wasm32"))]
+use tempfile::NamedTempFile;
+
+use super::{DirId, FileId, Index, IndexReader, RepoId, tombstones::Tombstones};
+#[cfg(not(target_arch = "wasm32"))]
+use super::{StagedIndex, bundle::set_root, persist::copy_mode, write_index_to, write_manifest};
+
+pub const DELTA_MAGIC: u32 = u32::from_le_bytes(*b"BLZD");
+pub const DELTA_VERSION: u32 = 1;
+
+/// Where the delta for the index at `index_path` lives.
+pub fn delta_path(index_path: &Path) -> PathBuf {
+    let mut path = index_path.as_os_str().to_owned();
+    path.push(".delta");
+    PathBuf::from(path)
+}
+
+/// Delete the delta for the index at `index_path`, if there is one.
+pub fn remove_delta(index_path: &Path) -> io::Result<()> {
+    match fs::remove_file(delta_path(index_path)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// How much of an overlaid index comes from its delta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaUsage {
+    /// Files the delta adds, including new versions of modified files.
+    pub added: usize,
+    /// Base files the delta hides, including old versions of modified files.
+    pub removed: usize,
+}
+
+/// Fixed-size end of a delta file.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct DeltaTrailer {
+    /// Offset of the sidecar arrays
+    sidecar_offset: u64,
+    /// `created_secs` of the base index the delta applies to
+    base_created_secs: u64,
+    base_file_count: u32,
+    base_dir_count: u32,
+    removed_files: u32,
+    removed_dirs: u32,
+    /// Length of `dir_map`, the delta's directory count
+    dir_map: u32,
+    /// Length of `file_repos`, the delta's file count
+    file_repos: u32,
+    version: u32,
+    magic: u32,
+}
+
+/// Changes to a base index, staged for writing as its delta file.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct StagedDelta {
+    added: StagedIndex,
+    removed_files: Vec<FileId>,
+    removed_dirs: Vec<DirId>,
+    dir_map: Vec<DirId>,
+    file_repos: Vec<RepoId>,
+    base_created_secs: u64,
+    base_file_count: u32,
+    base_dir_count: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StagedDelta {
+    /// Stage `added`, an index of the files added or modified since `base`
+    /// was written, along with the base files and directories that are gone.
+    ///
+    /// `base` may already have a delta; only its base part is used, so the
+    /// new delta replaces the old one.
+    pub fn new(
+        base: &Index,
+        added: StagedIndex,
+        mut removed_files: Vec<FileId>,
+        mut removed_dirs: Vec<DirId>,
+    ) -> Self {
+        removed_files.sort_unstable();
+        removed_files.dedup();
+        removed_dirs.sort_unstable();
+        removed_dirs.dedup();
+
+        let base_dirs = base.header.dir_count;
+        let by_path: HashMap<String, DirId> = (0..base_dirs)
+            .map(|d| (base.reconstruct_dir_path(d), d))
+            .collect();
+        let mut dir_map: Vec<DirId> = Vec::with_capacity(added.dirs.len());
+        let mut next = base_dirs;
+        for d in 0..added.dirs.len() as DirId {
+            let mapped = match by_path.get(&added.reconstruct_dir_path(d)) {
+                Some(&base_dir) => base_dir,
+                None => {
+                    let id = next;
+                    next += 1;
+                    id
+                }
+            };
+            dir_map.push(mapped);
+        }
+
+        // Repository of every base directory, then of the delta's own.
+        let base_repos = base.repos();
+        let repo_rooted_at = |dir: DirId| {
+            base_repos
+                .iter()
+                .position(|r| r.root_dir == dir)
+                .map(|r| r as RepoId)
+        };
+        let root_repo = repo_rooted_at(u32::MAX).unwrap_or(u32::MAX);
+        let mut dir_repos: Vec<RepoId> = Vec::with_capacity(base_dirs as usize);
+        for (d, dir) in base.dirs().iter().enumerate() {
+            // Parents are always interned before their children.
+            let inherited = match dir.parent {
+                u32::MAX => root_repo,
+                p => dir_repos.get(p as usize).copied().unwrap_or(u32::MAX),
+            };
+            dir_repos.push(repo_rooted_at(d as DirId).unwrap_or(inherited));
+        }
+        for (d, dir) in added.dirs.iter().enumerate() {
+            if dir_map[d] >= base_dirs {
+                let repo = match dir.parent {
+                    u32::MAX => root_repo,
+                    p => dir_repos[dir_map[p as usize] as usize],
+                };
+                dir_repos.push(repo);
+            }
+        }
+        let file_repos = added
+            .files
+            .iter()
+            .map(|f| match f.dir_id {
+                u32::MAX => root_repo,
+                d => dir_repos[dir_map[d as usize] as usize],
+            })
+            .collect();
+
+        Self {
+            added,
+            removed_files,
+            removed_dirs,
+            dir_map,
+            file_repos,
+            base_created_secs: base.base_created_secs(),
+            base_file_count: base.header.file_count,
+            base_dir_count: base_dirs,
+        }
+    }
+
+    /// Files the delta adds, including new versions of modified files.
+    pub fn added_files(&self) -> usize {
+        self.added.files.len()
+    }
+
+    /// Base files the delta hides, including old versions of modified files.
+    pub fn removed_files(&self) -> usize {
+        self.removed_files.len()
+    }
+
+    /// Write the delta next to the index at `index_path`, replacing any
+    /// previous one atomically.
+    pub fn write_atomic(&self, index_path: &Path) -> io::Result<()> {
+        let path = delta_path(index_path);
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let tmp = NamedTempFile::new_in(parent)?;
+
+        write_index_to(tmp.as_file(), &self.added, 0)?;
+        copy_mode(tmp.as_file(), index_path)?;
+        let mut file: &File = tmp.as_file();
+        let sidecar_offset = file.metadata()?.len();
+
+        let mut sidecar = Vec::new();
+        for ids in [
+            &self.removed_files,
+            &self.removed_dirs,
+            &self.dir_map,
+            &self.file_repos,
+        ] {
+            for id in ids {
+                sidecar.extend_from_slice(&id.to_le_bytes());
+            }
+        }
+        let trailer = DeltaTrailer {
+            sidecar_offset,
+            base_created_secs: self.base_created_secs,
+            base_file_count: self.base_file_count,
+            base_dir_count: self.base_dir_count,
+            removed_files: self.removed_files.len() as u32,
+            removed_dirs: self.removed_dirs.len() as u32,
+            dir_map: self.dir_map.len() as u32,
+            file_repos: self.file_repos.len() as u32,
+            version: DELTA_VERSION,
+            magic: DELTA_MAGIC,
+        };
+        sidecar.extend_from_slice(bytes_of(&trailer));
+        file.write_all(&sidecar)?;
+
+        file.sync_all()?;
+        tmp.persist(&path).map_err(|e| e.error)?;
+        write_manifest(index_path)
+    }
+}
+
+/// Point the delta in `bytes` at `root`, as [`set_root`] does for an
+/// index, moving its sidecar along with the sections after the names.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_delta_root(bytes: &[u8], root: &Path) -> io::Result<Vec<u8>> {
+    let trailer_len = mem::size_of::<DeltaTrailer>();
+    let mut trailer: DeltaTrailer = bytes
+        .len()
+        .checked_sub(trailer_len)
+        .map(|at| pod_read_unaligned(&bytes[at..]))
+        .filter(|t: &DeltaTrailer| t.magic == DELTA_MAGIC && t.version == DELTA_VERSION)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "index delta: not a delta file"))?;
+
+    let mut out = set_root(bytes, root)?;
+    trailer.sidecar_offset += (out.len() - bytes.len()) as u64;
+    let at = out.len() - trailer_len;
+    out[at..].copy_from_slice(bytes_of(&trailer));
+    Ok(out)
+}
+
+/// A delta file opened on top of its base index.
+pub(crate) struct Delta {
+    pub(crate) index: Index,
+    base_files: u32,
+    base_dirs: u32,
+    /// Base files the delta removes.
+    removed_files: Tombstones,
+    /// Base directories the delta removes.
+    removed_dirs: Tombstones,
+    /// Overlaid id of each of the delta's directories.
+    dir_map: Vec<DirId>,
+    /// The delta's id for each new directory, in overlaid order.
+    new_dirs: Vec<DirId>,
+    /// Repository of each of the delta's files, as a base [`RepoId`].
+    file_repos: Vec<RepoId>,
+}
+
+impl Delta {
+    /// Open the delta for the index at `index_path`, whose base is `base`.
+    ///
+    /// A missing delta, or one left over from an earlier base, is `None`.
+    pub(crate) fn open(index_path: &Path, base: &Index) -> io::Result<Option<Self>> {
+        match Index::open_base(&delta_path(index_path)) {
+            Ok(index) => Self::from_index(index, base),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Overlay the delta loaded as `index` on `base`, or `None` if it was
+    /// written for an earlier base.
+    pub(crate) fn from_index(index: Index, base: &Index) -> io::Result<Option<Self>> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("index delta: {msg}"));
+        let bytes: &[u8] = &index.bytes;
+        let trailer_len = mem::size_of::<DeltaTrailer>();
+        let trailer: DeltaTrailer = bytes
+            .len()
+            .checked_sub(trailer_len)
+            .map(|at| pod_read_unaligned(&bytes[at..]))
+            .ok_or_else(|| invalid("file too small"))?;
+        if trailer.magic != DELTA_MAGIC || trailer.version != DELTA_VERSION {
+            return Err(invalid("not a delta file"));
+        }
+        if trailer.base_created_secs != base.base_created_secs()
+            || trailer.base_file_count != base.header.file_count
+            || trailer.base_dir_count != base.header.dir_count
+        {
+            return Ok(None);
+        }
+        if trailer.dir_map as usize != index.dir_count()
+            || trailer.file_repos as usize != index.get_file_count()
+        {
+            return Err(invalid("sidecar does not match its index"));
+        }
+
+        let mut words = bytes
+            .get(trailer.sidecar_offset as usize..bytes.len() - trailer_len)
+            .ok_or_else(|| invalid("sidecar out of bounds"))?
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()));
+        let mut take = |n: u32| -> io::Result<Vec<u32>> {
+            let ids: Vec<u32> = words.by_ref().take(n as usize).collect();
+            if ids.len() == n as usize {
+                Ok(ids)
+            } else {
+                Err(invalid("sidecar truncated"))
+            }
+        };
+        let removed_files = take(trailer.removed_files)?;
+        let removed_dirs = take(trailer.removed_dirs)?;
+        let dir_map = take(trailer.dir_map)?;
+        let file_repos = take(trailer.file_repos)?;
+
+        let base_files = trailer.base_file_count;
+        let base_dirs = trailer.base_dir_count;
+        let new_dirs: Vec<DirId> = (0..dir_map.len() as DirId)
+            .filter(|&d| dir_map[d as usize] >= base_dirs)
+            .collect();
+        let consistent = new_dirs
+            .iter()
+            .enumerate()
+            .all(|(i, &d)| dir_map[d as usize] == base_dirs + i as DirId)
+            && dir_map
+                .iter()
+                .all(|&d| d < base_dirs + new_dirs.len() as DirId);
+        if !consistent {
+            return Err(invalid("directory map out of range"));
+        }
+
+        Ok(Some(Self {
+            index,
+            base_files,
+            base_dirs,
+            removed_files: Tombstones::from_ids(&removed_files, base_files),
+            removed_dirs: Tombstones::from_ids(&removed_dirs, base_dirs),
+            dir_map,
+            new_dirs,
+            file_repos,
+        }))
+    }
+
+    /// Files the delta adds.
+    pub(crate) fn added_files(&self) -> usize {
+        self.index.get_file_count()
+    }
+
+    /// Base files the delta hides.
+    pub(crate) fn removed_files(&self) -> usize {
+        self.removed_files.len()
+    }
+
+    /// Overlaid file count, removed files included.
+    pub(crate) fn file_count(&self) -> usize {
+        self.base_files as usize + self.added_files()
+    }
+
+    /// Overlaid directory count, removed directories included.
+    pub(crate) fn dir_count(&self) -> usize {
+        self.base_dirs as usize + self.new_dirs.len()
+    }
+
+    /// The delta's id for an overlaid file id past the base.
+    #[inline]
+    pub(crate) fn file(&self, id: FileId) -> Option<FileId> {
+        id.checked_sub(self.base_files)
+    }
+
+    /// The delta's id for an overlaid directory id past the base.
+    #[inline]
+    pub(crate) fn dir(&self, id: DirId) -> Option<DirId> {
+        let new = id.checked_sub(self.base_dirs)?;
+        self.new_dirs.get(new as usize).copied()
+    }
+
+    /// Overlaid id of one of the delta's directories.
+    #[inline]
+    pub(crate) fn map_dir(&self, dir: DirId) -> DirId {
+        self.dir_map.get(dir as usize).copied().unwrap_or(u32::MAX)
+    }
+
+    pub(crate) fn file_repo(&self, file: FileId) -> Option<RepoId> {
+        self.file_repos
+            .get(file as usize)
+            .copied()
+            .filter(|&r| r != u32::MAX)
+    }
+
+    #[inline]
+    pub(crate) fn is_file_removed(&self, id: FileId) -> bool {
+        self.removed_files.contains(id)
+    }
+
+    #[inline]
+    pub(crate) fn is_dir_removed(&self, id: DirId) -> bool {
+        self.removed_dirs.contains(id)
+    }
+
+    /// Base file postings followed by the delta's, shifted past the base.
+    ///
+    /// Removed base files are dropped from the postings so they cost
+    /// nothing to verify.
+    pub(crate) fn file_postings<'a>(
+        &self,
+        base: Option<&'a [u32]>,
+        delta: Option<&[u32]>,
+    ) -> Option<Cow<'a, [u32]>> {
+        let base = base.map(|p| self.removed_files.filter(p));
+        let Some(delta) = delta.filter(|p| !p.is_empty()) else {
+            return base;
+        };
+        let mut postings = base.map_or_else(Vec::new, Cow::into_owned);
+        postings.extend(delta.iter().map(|&f| f + self.base_files));
+        Some(Cow::Owned(postings))
+    }
+
+    /// Base directory postings followed by the delta's new directories.
+    pub(crate) fn dir_postings<'a>(
+        &self,
+        base: Option<&'a [u32]>,
+        delta: Option<&[u32]>,
+    ) -> Option<Cow<'a, [u32]>> {
+        let new: Vec<DirId> = delta
+            .unwrap_or_default()
+            .iter()
+            .map(|&d| self.map_dir(d))
+            .filter(|&d| d >= self.base_dirs)
+            .collect();
+        let base = base.map(|p| self.removed_dirs.filter(p));
+        if new.is_empty() {
+            return base;
+        }
+        let mut postings = base.map_or_else(Vec::new, Cow::into_owned);
+        postings.extend(new);
+        Some(Cow::Owned(postings))
+    }
+}
+
+#[cfg(test)]
+#[path = "delta_tests.rs"]
+mod tests;
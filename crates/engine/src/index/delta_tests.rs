@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use tempfile::tempdir;
+
+use super::*;
+use crate::{DirId, QueryPipeline, write_index_atomic};
+
+fn query(index: &Index, q: &str) -> Vec<String> {
+    let pipeline = QueryPipeline::new(index)
+        .parse(q)
+        .execute()
+        .rank_with_limit(Some(100));
+    let mut paths: Vec<String> = pipeline.iter_with_paths().map(|(_, _, p)| p).collect();
+    paths.sort();
+    paths
+}
+
+fn dir_id(index: &Index, path: &str) -> DirId {
+    (0..index.dir_count() as DirId)
+        .find(|&d| index.reconstruct_dir_path(d) == path)
+        .unwrap()
+}
+
+/// A base index of `src/main.rs`, `src/old.rs` and `docs/guide.md`, with a
+/// delta that removes `src/old.rs` and the `docs` directory and adds
+/// `src/new.rs` and `tests/basic.rs`.
+fn overlaid(dir: &Path) -> (PathBuf, Index) {
+    let path = dir.join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/data", &["src/main.rs", "src/old.rs", "docs/guide.md"]),
+        0,
+    )
+    .unwrap();
+
+    let base = Index::open(&path).unwrap();
+    let old = (0..base.get_file_count() as FileId)
+        .find(|&f| base.get_file_name(f) == "old.rs")
+        .unwrap();
+    let guide = (0..base.get_file_count() as FileId)
+        .find(|&f| base.get_file_name(f) == "guide.md")
+        .unwrap();
+    let docs = dir_id(&base, "/data/docs");
+    StagedDelta::new(
+        &base,
+        StagedIndex::of_files("/data", &["src/new.rs", "tests/basic.rs"]),
+        vec![old, guide],
+        vec![docs],
+    )
+    .write_atomic(&path)
+    .unwrap();
+
+    let index = Index::open(&path).unwrap();
+    (path, index)
+}
+
+#[test]
+fn overlay_adds_and_hides_files() {
+    let dir = tempdir().unwrap();
+    let (_, index) = overlaid(dir.path());
+
+    assert_eq!(index.live_file_count(), 3);
+    assert_eq!(
+        index.delta_usage(),
+        Some(DeltaUsage {
+            added: 2,
+            removed: 2
+        })
+    );
+    assert_eq!(
+        query(&index, "rs"),
+        [
+            "/data/src/main.rs",
+            "/data/src/new.rs",
+            "/data/tests/basic.rs"
+        ]
+    );
+    assert!(query(&index, "old").is_empty());
+    assert!(query(&index, "ext:md").is_empty());
+}
+
+#[test]
+fn removed_files_leave_the_postings() {
+    let dir = tempdir().unwrap();
+    let (_, index) = overlaid(dir.path());
+
+    // Only the removed `old.rs` contains "old".
+    let postings = index.query_trigram(crate::Trigram::from_bytes(b'o', b'l', b'd'));
+    assert_eq!(postings.as_deref(), Some(&[][..]));
+}
+
+#[test]
+fn ext_postings_follow_the_overlay() {
+    let dir = tempdir().unwrap();
+    let (_, index) = overlaid(dir.path());
+
+    let rs: Vec<String> = index
+        .files_with_ext("rs")
+        .unwrap()
+        .iter()
+        .map(|&f| index.reconstruct_full_path(f))
+        .collect();
+    assert_eq!(
+        rs,
+        [
+            "/data/src/main.rs",
+            "/data/src/new.rs",
+            "/data/tests/basic.rs"
+        ]
+    );
+    assert_eq!(index.files_with_ext("md").as_deref(), Some(&[][..]));
+}
+
+#[test]
+fn overlay_shares_existing_directories() {
+    let dir = tempdir().unwrap();
+    let (_, index) = overlaid(dir.path());
+
+    let src = dir_id(&index, "/data/src");
+    let names: Vec<&str> = index
+        .dir_files(src)
+        .iter()
+        .map(|&f| index.get_file_name(f))
+        .collect();
+    assert_eq!(names, ["main.rs", "new.rs"]);
+    assert_eq!(index.files_in_dir(src, true), index.dir_files(src));
+
+    let tests = dir_id(&index, "/data/tests");
+    assert!(tests as usize >= index.base_dir_count());
+    assert_eq!(index.get_dir_parent(tests), u32::MAX);
+
+    let mut top: Vec<&str> = index
+        .dir_children(u32::MAX)
+        .iter()
+        .map(|&d| index.get_dir_name(d))
+        .collect();
+    top.sort();
+    assert_eq!(top, ["src", "tests"], "docs was removed");
+
+    // Files below the root come from the overlay, not the stale section.
+    let mut all: Vec<String> = index
+        .files_in_dir(u32::MAX, true)
+        .iter()
+        .map(|&f| index.reconstruct_full_path(f))
+        .collect();
+    all.sort();
+    assert!(all.contains(&"/data/src/new.rs".to_owned()));
+    assert!(all.iter().all(|p| !p.starts_with("/data/docs")));
+}
+
+#[test]
+fn full_write_drops_the_delta() {
+    let dir = tempdir().unwrap();
+    let (path, _) = overlaid(dir.path());
+
+    write_index_atomic(&path, &StagedIndex::of_files("/data", &["a.txt"]), 0).unwrap();
+
+    assert!(!delta_path(&path).exists());
+    let index = Index::open(&path).unwrap();
+    assert_eq!(index.delta_usage(), None);
+    assert_eq!(index.get_file_count(), 1);
+}
+
+#[test]
+fn delta_for_another_base_is_ignored() {
+    let dir = tempdir().unwrap();
+    let (path, _) = overlaid(dir.path());
+
+    // Replace the base behind the delta's back.
+    let other = dir.path().join("other.bin");
+    write_index_atomic(
+        &other,
+        &StagedIndex::of_files("/data", &["a.txt", "b.txt"]),
+        0,
+    )
+    .unwrap();
+    std::fs::rename(&other, &path).unwrap();
+
+    let index = Index::open(&path).unwrap();
+    assert_eq!(index.delta_usage(), None);
+    assert_eq!(index.get_file_count(), 2);
+}
@@ -0,0 +1,192 @@
+//! Optional precomputed directory paths.
+//!
+//! Directory paths are normally rebuilt by walking `DirMeta::parent` chains,
+//! which costs one names_blob lookup per component for every path a query
+//! prints or ranks. The `dir_paths` section stores each directory's relative
+//! path in `DirId` order, front-coded: an entry keeps only the bytes that
+//! differ from the previous path. Every [`RESTART_INTERVAL`]-th entry is
+//! stored whole so a lookup decodes at most one block.
+//!
+//! Layout (little-endian):
+//!
+//! ```text
+//! u32 dir_count
+//! u32 block_offsets[ceil(dir_count / RESTART_INTERVAL)]   // into `entries`
+//! entries: (varint shared_len, varint suffix_len, suffix bytes)*
+//! ```
+
+use super::{DirMeta, StagedIndex};
+use crate::helpers::blob_str;
+
+/// Entries per front-coded block.
+pub const RESTART_INTERVAL: usize = 16;
+
+impl StagedIndex {
+    /// Precompute the `dir_paths` section, trading index size for path
+    /// reconstruction that no longer walks parent chains.
+    pub fn store_dir_paths(&mut self) {
+        self.dir_paths = encode_dir_paths(&relative_dir_paths(&self.dirs, &self.names_blob));
+    }
+}
+
+/// Relative path of every directory, parents first, in `DirId` order.
+pub(crate) fn relative_dir_paths(dirs: &[DirMeta], names_blob: &[u8]) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::with_capacity(dirs.len());
+    for dir in dirs {
+        let name = blob_str(names_blob, dir.name_offset, dir.name_len);
+        // Parents are always interned before their children.
+        let path = match paths.get(dir.parent as usize) {
+            Some(parent) if !parent.is_empty() => format!("{parent}/{name}"),
+            _ => name.to_owned(),
+        };
+        paths.push(path);
+    }
+    paths
+}
+
+/// Front-code `paths` into the `dir_paths` section format.
+pub fn encode_dir_paths<S: AsRef<str>>(paths: &[S]) -> Vec<u8> {
+    let block_count = paths.len().div_ceil(RESTART_INTERVAL);
+    let mut offsets = Vec::with_capacity(block_count);
+    let mut entries = Vec::new();
+
+    let mut prev: &[u8] = &[];
+    for (i, path) in paths.iter().enumerate() {
+        let path = path.as_ref().as_bytes();
+        let shared = if i % RESTART_INTERVAL == 0 {
+            offsets.push(entries.len() as u32);
+            0
+        } else {
+            common_prefix_len(prev, path)
+        };
+        write_varint(&mut entries, shared);
+        write_varint(&mut entries, path.len() - shared);
+        entries.extend_from_slice(&path[shared..]);
+        prev = path;
+    }
+
+    let mut out = Vec::with_capacity(4 * (1 + offsets.len()) + entries.len());
+    out.extend_from_slice(&(paths.len() as u32).to_le_bytes());
+    for off in offsets {
+        out.extend_from_slice(&off.to_le_bytes());
+    }
+    out.extend_from_slice(&entries);
+    out
+}
+
+/// Read-only view over an encoded `dir_paths` section.
+#[derive(Debug, Clone, Copy)]
+pub struct DirPaths<'a> {
+    len: usize,
+    offsets: &'a [u8],
+    entries: &'a [u8],
+}
+
+impl<'a> DirPaths<'a> {
+    /// Returns `None` if `bytes` is empty or its table does not fit.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        let len = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let table_end = 4 + 4 * len.div_ceil(RESTART_INTERVAL);
+        Some(Self {
+            len,
+            offsets: bytes.get(4..table_end)?,
+            entries: bytes.get(table_end..)?,
+        })
+    }
+
+    /// Number of directories covered.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append the relative path of directory `id` to `out`.
+    ///
+    /// Returns `false`, leaving `out` unchanged, if `id` is out of range or
+    /// the section is malformed.
+    pub fn push_path(&self, id: usize, out: &mut String) -> bool {
+        if id >= self.len {
+            return false;
+        }
+        let block = id / RESTART_INTERVAL;
+        let Some(off) = self.offsets.get(4 * block..4 * block + 4) else {
+            return false;
+        };
+        let mut pos = u32::from_le_bytes(off.try_into().unwrap()) as usize;
+
+        let mut path: Vec<u8> = Vec::new();
+        for _ in block * RESTART_INTERVAL..=id {
+            let Some((shared, suffix, next)) = self.entry(pos) else {
+                return false;
+            };
+            if shared > path.len() {
+                return false;
+            }
+            path.truncate(shared);
+            path.extend_from_slice(suffix);
+            pos = next;
+        }
+
+        match std::str::from_utf8(&path) {
+            Ok(s) => {
+                out.push_str(s);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Decode every path, checking that the section is well-formed.
+    pub fn decode_all(&self) -> Option<Vec<String>> {
+        (0..self.len)
+            .map(|id| {
+                let mut s = String::new();
+                self.push_path(id, &mut s).then_some(s)
+            })
+            .collect()
+    }
+
+    /// Entry at `pos`: `(shared_len, suffix, next_pos)`.
+    fn entry(&self, pos: usize) -> Option<(usize, &'a [u8], usize)> {
+        let (shared, pos) = read_varint(self.entries, pos)?;
+        let (suffix_len, pos) = read_varint(self.entries, pos)?;
+        let end = pos.checked_add(suffix_len)?;
+        Some((shared, self.entries.get(pos..end)?, end))
+    }
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn read_varint(bytes: &[u8], mut pos: usize) -> Option<(usize, usize)> {
+    let mut v = 0usize;
+    let mut shift = 0;
+    loop {
+        let b = *bytes.get(pos)?;
+        pos += 1;
+        v |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 {
+            return Some((v, pos));
+        }
+        shift += 7;
+        if shift >= usize::BITS {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "dir_paths_tests.rs"]
+mod tests;
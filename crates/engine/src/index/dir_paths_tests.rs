@@ -0,0 +1,35 @@
+use super::*;
+
+#[test]
+fn front_coded_paths_round_trip() {
+    let paths: Vec<String> = (0..40)
+        .map(|i| format!("home/user/projects/app/src/module_{i:02}"))
+        .chain([
+            "".to_owned(),
+            "ünïcode/dïr".to_owned(),
+            "ünïcode/dïrs".to_owned(),
+        ])
+        .collect();
+
+    let encoded = encode_dir_paths(&paths);
+    let raw: usize = paths.iter().map(String::len).sum();
+    assert!(encoded.len() < raw / 2, "{} >= {}", encoded.len(), raw / 2);
+
+    let view = DirPaths::new(&encoded).unwrap();
+    assert_eq!(view.len(), paths.len());
+    assert_eq!(view.decode_all().unwrap(), paths);
+
+    let mut out = "/root/".to_owned();
+    assert!(view.push_path(17, &mut out));
+    assert_eq!(out, "/root/home/user/projects/app/src/module_17");
+    assert!(!view.push_path(paths.len(), &mut out));
+}
+
+#[test]
+fn truncated_section_is_rejected() {
+    let encoded = encode_dir_paths(&["a/b", "a/c"]);
+
+    assert!(DirPaths::new(&[]).is_none());
+    let view = DirPaths::new(&encoded[..encoded.len() - 1]).unwrap();
+    assert!(view.decode_all().is_none());
+}
@@ -0,0 +1,114 @@
+//! Parent-to-child adjacency for the directory tree.
+//!
+//! The index only stores upward links (`DirMeta::parent`, `FileMeta::dir_id`).
+//! [`DirTree`] inverts them into two compressed adjacency lists, built on
+//! first use so queries that never browse the tree do not pay for it. The
+//! index root, `u32::MAX`, gets the slot after the last directory.
+
+use super::{DirFilesRange, DirId, FileId, IndexReader};
+
+/// Children and files of every directory, in id order.
+#[derive(Debug, Clone)]
+pub struct DirTree {
+    /// `children[child_starts[d]..child_starts[d + 1]]` are the subdirectories of `d`.
+    child_starts: Vec<u32>,
+    children: Vec<DirId>,
+    /// `files[file_starts[d]..file_starts[d + 1]]` are the entries directly in `d`.
+    file_starts: Vec<u32>,
+    files: Vec<FileId>,
+}
+
+impl DirTree {
+    /// Invert the parent links of `index`, leaving out removed entries.
+    pub fn build<I: IndexReader + ?Sized>(index: &I) -> Self {
+        let root = index.dir_count();
+        // Unknown parents, including `u32::MAX`, hang off the root.
+        let slot = |d: DirId| (d as usize).min(root);
+
+        let (child_starts, children) = group(
+            root + 1,
+            (0..root as DirId)
+                .map(|d| (!index.is_dir_removed(d)).then(|| slot(index.get_dir_parent(d)))),
+        );
+        let (file_starts, files) = group(
+            root + 1,
+            (0..index.get_file_count() as FileId)
+                .map(|f| (!index.is_file_removed(f)).then(|| slot(index.get_file_dir_id(f)))),
+        );
+        Self {
+            child_starts,
+            children,
+            file_starts,
+            files,
+        }
+    }
+
+    /// Subdirectories directly inside `dir`; `u32::MAX` is the root.
+    pub fn children(&self, dir: DirId) -> &[DirId] {
+        range(&self.child_starts, &self.children, dir)
+    }
+
+    /// Entries directly inside `dir`, in `FileId` order; `u32::MAX` is the root.
+    pub fn files(&self, dir: DirId) -> &[FileId] {
+        range(&self.file_starts, &self.files, dir)
+    }
+
+    /// Lay out the entries of every directory depth-first from the root, for
+    /// the `dir_files` and `dir_file_postings` sections. The root's range
+    /// comes after the last directory's; directories not below the root,
+    /// only possible in a damaged index, get an empty one.
+    pub fn pack_files(&self) -> (Vec<DirFilesRange>, Vec<FileId>) {
+        let root = self.file_starts.len() - 2;
+        let mut ranges = vec![DirFilesRange::default(); root + 1];
+        let mut postings = Vec::with_capacity(self.files.len());
+
+        // `(dir, false)` enters a directory, `(dir, true)` leaves it.
+        let mut stack = vec![(DirId::MAX, false)];
+        while let Some((dir, leaving)) = stack.pop() {
+            let slot = (dir as usize).min(root);
+            if leaving {
+                ranges[slot].subtree_end = postings.len() as u32;
+                continue;
+            }
+            ranges[slot].start = postings.len() as u32;
+            postings.extend_from_slice(self.files(dir));
+            ranges[slot].direct_end = postings.len() as u32;
+            stack.push((dir, true));
+            stack.extend(self.children(dir).iter().rev().map(|&child| (child, false)));
+        }
+        (ranges, postings)
+    }
+}
+
+/// Counting sort of item ids by `slots`, which are all below `slot_count`;
+/// items without a slot are left out. Ids stay ascending within each slot.
+fn group(
+    slot_count: usize,
+    slots: impl Iterator<Item = Option<usize>> + Clone,
+) -> (Vec<u32>, Vec<u32>) {
+    let mut starts = vec![0u32; slot_count + 1];
+    for s in slots.clone().flatten() {
+        starts[s + 1] += 1;
+    }
+    for i in 1..starts.len() {
+        starts[i] += starts[i - 1];
+    }
+
+    let mut next = starts.clone();
+    let mut items = vec![0u32; starts[slot_count] as usize];
+    for (id, s) in slots.enumerate() {
+        let Some(s) = s else { continue };
+        items[next[s] as usize] = id as u32;
+        next[s] += 1;
+    }
+    (starts, items)
+}
+
+fn range<'a>(starts: &[u32], items: &'a [u32], dir: DirId) -> &'a [u32] {
+    let slot = (dir as usize).min(starts.len() - 2);
+    &items[starts[slot] as usize..starts[slot + 1] as usize]
+}
+
+#[cfg(test)]
+#[path = "dir_tree_tests.rs"]
+mod tests;
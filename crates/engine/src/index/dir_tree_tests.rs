@@ -0,0 +1,117 @@
+use crate::{DirId, FileId, Index, IndexReader, StagedIndex};
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &[
+            "top.txt",
+            "src/main.rs",
+            "src/eval/text.rs",
+            "src/lib.rs",
+            "docs/guide.md",
+        ],
+    )
+}
+
+fn child_names<I: IndexReader>(index: &I, dir: DirId) -> Vec<&str> {
+    let mut names: Vec<&str> = index
+        .dir_children(dir)
+        .iter()
+        .map(|&d| index.get_dir_name(d))
+        .collect();
+    names.sort();
+    names
+}
+
+fn file_names<I: IndexReader>(index: &I, dir: DirId) -> Vec<&str> {
+    index
+        .dir_files(dir)
+        .iter()
+        .map(|&f| index.get_file_name(f))
+        .collect()
+}
+
+fn dir_named<I: IndexReader>(index: &I, name: &str) -> DirId {
+    (0..index.dir_count() as DirId)
+        .find(|&d| index.get_dir_name(d) == name)
+        .unwrap()
+}
+
+#[test]
+fn children_and_files_follow_the_tree() {
+    let index = staged();
+    let src = dir_named(&index, "src");
+
+    assert_eq!(child_names(&index, u32::MAX), ["docs", "src"]);
+    assert_eq!(file_names(&index, u32::MAX), ["top.txt"]);
+    assert_eq!(child_names(&index, src), ["eval"]);
+    assert_eq!(file_names(&index, src), ["main.rs", "lib.rs"]);
+
+    let eval = dir_named(&index, "eval");
+    assert!(index.dir_children(eval).is_empty());
+    assert_eq!(file_names(&index, eval), ["text.rs"]);
+}
+
+#[test]
+fn every_entry_is_listed_once() {
+    let index = staged();
+
+    let mut files: Vec<FileId> = (0..index.dir_count() as DirId)
+        .chain([u32::MAX])
+        .flat_map(|d| index.dir_files(d).to_vec())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        (0..index.get_file_count() as FileId).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn mapped_index_matches_staged() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    for dir in (0..staged.dir_count() as DirId).chain([u32::MAX]) {
+        assert_eq!(staged.dir_children(dir), mapped.dir_children(dir));
+        assert_eq!(staged.dir_files(dir), mapped.dir_files(dir));
+    }
+}
+
+#[test]
+fn files_in_dir_lists_direct_or_nested_entries_in_id_order() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+    let src = dir_named(&staged, "src");
+
+    for index in [&staged as &dyn IndexReader, &mapped] {
+        let names = |dir, recursive| -> Vec<&str> {
+            index
+                .files_in_dir(dir, recursive)
+                .iter()
+                .map(|&f| index.get_file_name(f))
+                .collect()
+        };
+        assert_eq!(names(src, false), ["main.rs", "lib.rs"]);
+        assert_eq!(names(src, true), ["main.rs", "text.rs", "lib.rs"]);
+        assert_eq!(names(u32::MAX, false), ["top.txt"]);
+        assert_eq!(
+            index.files_in_dir(u32::MAX, true).len(),
+            index.get_file_count()
+        );
+    }
+}
+
+#[test]
+fn packed_ranges_nest_depth_first() {
+    let staged = staged();
+    let src = dir_named(&staged, "src") as usize;
+    let eval = dir_named(&staged, "eval") as usize;
+    let root = &staged.dir_files[staged.dir_count()];
+
+    assert_eq!(staged.dir_files.len(), staged.dir_count() + 1);
+    assert_eq!(root.start, 0);
+    assert_eq!(root.subtree_end as usize, staged.dir_file_postings.len());
+    let (src, eval) = (staged.dir_files[src], staged.dir_files[eval]);
+    assert!(src.direct_end <= eval.start && eval.subtree_end <= src.subtree_end);
+}
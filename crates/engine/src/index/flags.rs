@@ -1,6 +1,6 @@
 use bitflags::bitflags;
 use blaze_fs::FileRecord;
-use blaze_runtime::{CACHE_COMPONENTS, NOISY_COMPONENTS, SYSTEM_ROOTS};
+use blaze_runtime::{NoiseComponents, SYSTEM_ROOTS, noise_components};
 
 const VERY_DEEP_THRESHOLD: usize = 15;
 
@@ -9,7 +9,7 @@ bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct FileFlags: u16 {
         // Structural
-        const IS_DIR = 0b0000_0000_00000_0001;
+        const IS_DIR = 0b0000_0000_0000_0001;
         /// Whether the file is a symlink.
         const IS_SYMLINK = 0b0000_0000_0000_0010;
         /// Special files include socket, fifo, device etc.
@@ -23,7 +23,15 @@ bitflags! {
         /// Whether the user explicitly hid the file
         const EXCLUDED_USER = 0b0000_0000_0010_0000;
         /// Whether the particular file is in the "Trash".
-        const IN_TRASH = 0b00000_0000_100_0000;
+        const IN_TRASH = 0b0000_0000_0100_0000;
+        /// Whether the file's owner and mode were recorded.
+        const HAS_PERMS = 0b0000_0000_1000_0000;
+        /// Whether the name is not valid UTF-8. It is stored with the
+        /// offending bytes escaped, see `blaze_fs::escape_name`.
+        const ESCAPED_NAME = 0b0000_0001_0000_0000;
+        /// Whether the file was modified after the scan started, so its
+        /// record may not match the file as it ended up.
+        const CHANGED_DURING_SCAN = 0b0000_0010_0000_0000;
     }
 }
 
@@ -49,6 +57,24 @@ bitflags! {
         const APP_DATA_DIR = 0b0010_0000;
         /// Log/debug directories: debug, logs, sessionstore-logs
         const LOG_DIR      = 0b0100_0000;
+        /// Trees that markers next to them show to be generated, such as
+        /// a directory holding `CACHEDIR.TAG`. Set from the walk, not the
+        /// path, see `FileRecord::generated`.
+        const GENERATED    = 0b1000_0000;
+    }
+}
+
+bitflags! {
+    /// Options an index was built with, stored in `IndexMeta::build_flags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct BuildFlags: u32 {
+        /// Empty regular files were left out.
+        const SKIP_EMPTY    = 0b0000_0001;
+        /// Files above `IndexMeta::max_file_bytes` were left out.
+        const MAX_FILE_SIZE = 0b0000_0010;
+        /// The contents of directories tagged with `CACHEDIR.TAG` were
+        /// left out.
+        const SKIP_CACHES   = 0b0000_0100;
     }
 }
 
@@ -61,6 +87,12 @@ bitflags! {
 /// Paths are assumed to be valid UTF-8. Non-UTF-8 paths should be handled
 /// by the caller (e.g., using `to_string_lossy()`).
 pub fn classify_noise(path: &str) -> (NoiseFlags, u8) {
+    classify_noise_with(path, noise_components())
+}
+
+/// Like [`classify_noise`], but with explicit noise component lists instead
+/// of the process-wide ones.
+pub fn classify_noise_with(path: &str, components: &NoiseComponents) -> (NoiseFlags, u8) {
     let mut flags = NoiseFlags::empty();
 
     // System roots check - case-insensitive on macOS, exact on Linux
@@ -93,13 +125,13 @@ pub fn classify_noise(path: &str) -> (NoiseFlags, u8) {
             in_hidden_app_dir = true;
         }
 
-        if !has_build && is_noisy_component(comp) {
+        if !has_build && matches_component(comp, |c| components.is_build(c)) {
             has_build = true;
         }
-        if !has_cache && is_cache_component(comp) {
+        if !has_cache && matches_component(comp, |c| components.is_cache(c)) {
             has_cache = true;
         }
-        if !has_log && is_log_component(comp) {
+        if !has_log && matches_component(comp, |c| components.is_log(c)) {
             has_log = true;
         }
         if !has_hash && is_hashy(comp) {
@@ -126,11 +158,8 @@ pub fn classify_noise(path: &str) -> (NoiseFlags, u8) {
     }
 
     let depth_u8 = depth.min(255) as u8;
-    match depth > VERY_DEEP_THRESHOLD {
-        true => {
-            flags |= NoiseFlags::VERY_DEEP;
-        }
-        false => (),
+    if depth > VERY_DEEP_THRESHOLD {
+        flags |= NoiseFlags::VERY_DEEP;
     }
 
     (flags, depth_u8)
@@ -152,49 +181,16 @@ fn is_system_path(path: &str) -> bool {
     }
 }
 
-/// Check if component matches a noisy (build/dependency) directory
-#[inline]
-fn is_noisy_component(comp: &str) -> bool {
-    #[cfg(target_os = "macos")]
-    {
-        let comp_lower = comp.to_ascii_lowercase();
-        NOISY_COMPONENTS.iter().any(|n| *n == comp_lower)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        NOISY_COMPONENTS.contains(&comp)
-    }
-}
-
-/// Check if component matches a cache directory
-#[inline]
-fn is_cache_component(comp: &str) -> bool {
-    #[cfg(target_os = "macos")]
-    {
-        let comp_lower = comp.to_ascii_lowercase();
-        CACHE_COMPONENTS.iter().any(|n| *n == comp_lower)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        CACHE_COMPONENTS.contains(&comp)
-    }
-}
-
-/// Check if component matches a log/debug directory
+/// Check a component against one of the noise component lists.
 #[inline]
-fn is_log_component(comp: &str) -> bool {
+fn matches_component(comp: &str, is_match: impl Fn(&str) -> bool) -> bool {
     #[cfg(target_os = "macos")]
     {
-        use blaze_runtime::LOG_COMPONENTS;
-
-        let comp_lower = comp.to_ascii_lowercase();
-        LOG_COMPONENTS.iter().any(|n| *n == comp_lower)
+        is_match(&comp.to_ascii_lowercase())
     }
     #[cfg(not(target_os = "macos"))]
     {
-        use blaze_runtime::LOG_COMPONENTS;
-
-        LOG_COMPONENTS.contains(&comp)
+        is_match(comp)
     }
 }
 
@@ -362,6 +358,20 @@ pub fn compute_file_flags(
     if excluded_user {
         flags.insert(FileFlags::EXCLUDED_USER);
     }
+    if input.perms.is_some() {
+        flags.insert(FileFlags::HAS_PERMS);
+    }
+    if input
+        .full_path
+        .file_name()
+        .is_some_and(|n| n.to_str().is_none())
+    {
+        flags.insert(FileFlags::ESCAPED_NAME);
+    }
 
     flags
 }
+
+#[cfg(test)]
+#[path = "flags_tests.rs"]
+mod tests;
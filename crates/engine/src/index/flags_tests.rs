@@ -0,0 +1,21 @@
+use super::*;
+
+#[test]
+fn classify_noise_with_honours_overridden_components() {
+    let path = "/home/me/proj/target/release/app";
+    let defaults = NoiseComponents::default();
+    assert!(
+        classify_noise_with(path, &defaults)
+            .0
+            .contains(NoiseFlags::BUILD_DIR)
+    );
+
+    let mut custom = NoiseComponents::default();
+    custom.build.value.retain(|c| c != "target");
+    custom.log.value.push("release".to_owned());
+
+    let (flags, depth) = classify_noise_with(path, &custom);
+    assert!(!flags.contains(NoiseFlags::BUILD_DIR));
+    assert!(flags.contains(NoiseFlags::LOG_DIR));
+    assert_eq!(depth, 6);
+}
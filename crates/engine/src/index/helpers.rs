@@ -3,7 +3,7 @@ use std::str;
 /// Decode a UTF-8 string slice from a byte blob using (offset, len).
 /// Returns "" if the range is invalid or not valid UTF-8.
 #[inline]
-pub fn blob_str<'a>(blob: &'a [u8], off: u32, len: u32) -> &'a str {
+pub fn blob_str(blob: &[u8], off: u32, len: u32) -> &str {
     let start = off as usize;
 
     // saturating/checked arithmetic to avoid panics on corrupt offsets
@@ -46,3 +46,15 @@ pub fn join_root_rel(root: &str, rel: &str) -> String {
 
     out
 }
+
+/// Stable 64-bit FNV-1a hash of a lowercased file name, as stored in the
+/// exact name section.
+#[inline]
+pub fn name_hash(name_lower: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    name_lower
+        .bytes()
+        .fold(OFFSET, |h, b| (h ^ b as u64).wrapping_mul(PRIME))
+}
@@ -0,0 +1,54 @@
+use blaze_runtime::language;
+
+use super::{FileId, IndexReader};
+
+/// How many indexed files, and how many bytes, belong to one language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LangUsage {
+    /// Canonical language name (see `blaze_runtime::lang`).
+    pub lang: &'static str,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Count files per detected language, most common first.
+///
+/// Files without a detected language are not included.
+pub fn lang_usage<I: IndexReader>(index: &I) -> Vec<LangUsage> {
+    let mut counts: Vec<(usize, u64)> = Vec::new();
+
+    for fid in 0..index.get_file_count() as FileId {
+        if index.is_file_removed(fid) {
+            continue;
+        }
+        let Some(lang) = index.get_file_lang(fid) else {
+            continue;
+        };
+        let slot = lang as usize;
+        if slot >= counts.len() {
+            counts.resize(slot + 1, (0, 0));
+        }
+        counts[slot].0 += 1;
+        counts[slot].1 += index.get_file_size(fid);
+    }
+
+    let mut usage: Vec<LangUsage> = counts
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (files, _))| *files > 0)
+        .filter_map(|(id, (files, bytes))| {
+            Some(LangUsage {
+                lang: language(id as u8)?.name,
+                files,
+                bytes,
+            })
+        })
+        .collect();
+
+    usage.sort_by(|a, b| b.files.cmp(&a.files).then(a.lang.cmp(b.lang)));
+    usage
+}
+
+#[cfg(test)]
+#[path = "langs_tests.rs"]
+mod tests;
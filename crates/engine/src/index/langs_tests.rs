@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use blaze_fs::FileRecord;
+use blaze_runtime::lang_by_name;
+
+use super::*;
+use crate::{Index, IndexBuilder, QueryPipeline};
+
+fn record(root: &Path, rel: &str, size: u64, lang: Option<&str>) -> FileRecord {
+    FileRecord {
+        size,
+        lang: lang.and_then(lang_by_name),
+        ..FileRecord::test_file(root.join(rel))
+    }
+}
+
+fn test_index() -> Index {
+    let root = PathBuf::from("/proj");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        record(&root, "src/main.rs", 100, None),
+        record(&root, "src/lib.RS", 50, None),
+        record(&root, "tools/gen.py", 10, None),
+        // Extension-less script whose language the walker sniffed.
+        record(&root, "bin/deploy", 5, Some("shell")),
+        record(&root, "README", 1, None),
+    ]);
+    Index::from_staged(&builder.finish()).unwrap()
+}
+
+fn paths(index: &Index, query: &str) -> Vec<String> {
+    let pipeline = QueryPipeline::new(index)
+        .parse(query)
+        .execute()
+        .rank_with_limit(None);
+    let mut paths: Vec<String> = pipeline.iter_with_paths().map(|(_, _, p)| p).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn usage_counts_files_and_bytes_per_language() {
+    let usage = lang_usage(&test_index());
+
+    assert_eq!(
+        usage,
+        [
+            LangUsage {
+                lang: "rust",
+                files: 2,
+                bytes: 150
+            },
+            LangUsage {
+                lang: "python",
+                files: 1,
+                bytes: 10
+            },
+            LangUsage {
+                lang: "shell",
+                files: 1,
+                bytes: 5
+            },
+        ]
+    );
+}
+
+#[test]
+fn lang_predicate_accepts_names_and_aliases() {
+    let index = test_index();
+
+    assert_eq!(
+        paths(&index, "lang:rust"),
+        ["/proj/src/lib.RS", "/proj/src/main.rs"]
+    );
+    assert_eq!(paths(&index, "lang:Py"), ["/proj/tools/gen.py"]);
+    assert_eq!(paths(&index, "lang:bash"), ["/proj/bin/deploy"]);
+    assert!(paths(&index, "lang:klingon").is_empty());
+    assert_eq!(paths(&index, "NOT lang:rust src"), Vec::<String>::new());
+}
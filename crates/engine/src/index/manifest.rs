@@ -0,0 +1,192 @@
+//! BLAKE3 manifests for detecting corrupted or tampered indexes.
+//!
+//! Every write of an index, its delta, or its noise flags also writes
+//! `<index>.manifest`, recording the length and BLAKE3 hash of the index
+//! file and of the delta overlaid on it, if any. With [`VERIFY_MANIFEST_ENV`]
+//! set, [`Index::open`](super::Index::open) hashes the files it mapped and
+//! refuses them, with [`ErrorKind::InvalidData`], unless they match.
+//!
+//! A manifest only detects tampering by someone who cannot also rewrite it:
+//! for a system-wide index, both files should be writable by the indexing
+//! user alone. The manifest is written after the file it describes, so a
+//! crash in between leaves an index that fails verification until the next
+//! write.
+//!
+//! Manifest layout (UTF-8 text, one entry per line):
+//!
+//! ```text
+//! blaze-manifest 1
+//! index <len> <blake3 hex>
+//! delta <len> <blake3 hex>    // only with a delta
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fmt::Write as _, fs::File, io::Write};
+use std::{
+    fs,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use blaze_runtime::VERIFY_MANIFEST_ENV;
+#[cfg(not(target_arch = "wasm32"))]
+use tempfile::NamedTempFile;
+
+use super::delta_path;
+#[cfg(not(target_arch = "wasm32"))]
+use super::persist::copy_mode;
+
+const MANIFEST_HEADER: &str = "blaze-manifest 1";
+
+/// Where the manifest for the index at `index_path` lives.
+pub fn manifest_path(index_path: &Path) -> PathBuf {
+    let mut path = index_path.as_os_str().to_owned();
+    path.push(".manifest");
+    PathBuf::from(path)
+}
+
+/// Whether [`VERIFY_MANIFEST_ENV`] asks `Index::open` to verify manifests.
+pub fn verify_manifest_enabled() -> bool {
+    std::env::var(VERIFY_MANIFEST_ENV)
+        .is_ok_and(|val| val == "1" || val.eq_ignore_ascii_case("true"))
+}
+
+/// Length and hash of one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Digest {
+    len: u64,
+    hash: blake3::Hash,
+}
+
+impl Digest {
+    fn of(bytes: &[u8]) -> Self {
+        Self {
+            len: bytes.len() as u64,
+            hash: blake3::hash(bytes),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn of_file(path: &Path) -> io::Result<Option<Self>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader(&file)?;
+        Ok(Some(Self {
+            len: file.metadata()?.len(),
+            hash: hasher.finalize(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Manifest {
+    index: Digest,
+    delta: Option<Digest>,
+}
+
+impl Manifest {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn encode(&self) -> String {
+        let mut out = format!("{MANIFEST_HEADER}\n");
+        for (name, digest) in [("index", Some(self.index)), ("delta", self.delta)] {
+            if let Some(d) = digest {
+                let _ = writeln!(out, "{name} {} {}", d.len, d.hash.to_hex());
+            }
+        }
+        out
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != MANIFEST_HEADER {
+            return None;
+        }
+
+        let mut index = None;
+        let mut delta = None;
+        for line in lines {
+            let mut fields = line.split(' ');
+            let (name, len, hash) = (fields.next()?, fields.next()?, fields.next()?);
+            let digest = Digest {
+                len: len.parse().ok()?,
+                hash: blake3::Hash::from_hex(hash).ok()?,
+            };
+            match name {
+                "index" => index = Some(digest),
+                "delta" => delta = Some(digest),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            index: index?,
+            delta,
+        })
+    }
+}
+
+/// Hash the index at `index_path`, and its delta if it has one, and write
+/// their manifest atomically.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_manifest(index_path: &Path) -> io::Result<()> {
+    let index = Digest::of_file(index_path)?
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "index to write a manifest for"))?;
+    let manifest = Manifest {
+        index,
+        delta: Digest::of_file(&delta_path(index_path))?,
+    };
+
+    let path = manifest_path(index_path);
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut tmp = NamedTempFile::new_in(parent)?;
+    tmp.write_all(manifest.encode().as_bytes())?;
+    copy_mode(tmp.as_file(), index_path)?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(&path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Check the mapped bytes of the index at `index_path`, and of the delta
+/// overlaid on it, against its manifest.
+pub(crate) fn check_manifest(
+    index_path: &Path,
+    index: &[u8],
+    delta: Option<&[u8]>,
+) -> io::Result<()> {
+    let path = manifest_path(index_path);
+    let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(invalid(format!(
+                "index manifest {} is missing",
+                path.display()
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+    let manifest = Manifest::decode(&text)
+        .ok_or_else(|| invalid(format!("index manifest {} is malformed", path.display())))?;
+
+    if manifest.index != Digest::of(index) {
+        return Err(invalid(format!(
+            "{} does not match its manifest",
+            index_path.display()
+        )));
+    }
+    if manifest.delta != delta.map(Digest::of) {
+        return Err(invalid(format!(
+            "{} does not match its manifest",
+            delta_path(index_path).display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "manifest_tests.rs"]
+mod tests;
@@ -0,0 +1,96 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use tempfile::tempdir;
+
+use super::*;
+use crate::{Index, IndexReader, StagedDelta, StagedIndex, write_index_atomic};
+
+fn open_error(path: &Path) -> String {
+    let err = Index::open_verified(path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    err.to_string()
+}
+
+#[test]
+fn manifest_round_trips() {
+    let manifest = Manifest {
+        index: Digest::of(b"index"),
+        delta: Some(Digest::of(b"delta")),
+    };
+    assert_eq!(Manifest::decode(&manifest.encode()), Some(manifest));
+    assert_eq!(Manifest::decode("blaze-manifest 1\n"), None);
+    assert_eq!(Manifest::decode("index 5 abc\n"), None);
+}
+
+#[test]
+fn written_index_verifies() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &StagedIndex::of_files("/data", &["src/main.rs"]), 0).unwrap();
+
+    assert!(manifest_path(&path).exists());
+    assert_eq!(Index::open_verified(&path).unwrap().get_file_count(), 1);
+}
+
+#[test]
+fn tampered_index_is_refused() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(
+        &path,
+        &StagedIndex::of_files("/data", &["src/main.rs", "src/lib.rs"]),
+        0,
+    )
+    .unwrap();
+
+    // Flip a byte of a file name; the header and bounds stay valid.
+    let mut bytes = fs::read(&path).unwrap();
+    let at = bytes.windows(7).position(|w| w == b"main.rs").unwrap();
+    bytes[at] = b'x';
+    fs::write(&path, &bytes).unwrap();
+
+    Index::open(&path).unwrap();
+    assert!(open_error(&path).contains("does not match its manifest"));
+}
+
+#[test]
+fn missing_manifest_is_refused() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &StagedIndex::of_files("/data", &["src/main.rs"]), 0).unwrap();
+    fs::remove_file(manifest_path(&path)).unwrap();
+
+    assert!(open_error(&path).contains("is missing"));
+}
+
+#[test]
+fn manifest_covers_the_delta() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &StagedIndex::of_files("/data", &["src/main.rs"]), 0).unwrap();
+    let base = Index::open(&path).unwrap();
+    StagedDelta::new(
+        &base,
+        StagedIndex::of_files("/data", &["src/new.rs"]),
+        vec![],
+        vec![],
+    )
+    .write_atomic(&path)
+    .unwrap();
+    drop(base);
+
+    assert_eq!(Index::open_verified(&path).unwrap().get_file_count(), 2);
+
+    // A delta the manifest does not know about is refused.
+    let manifest = fs::read(manifest_path(&path)).unwrap();
+    StagedDelta::new(
+        &Index::open(&path).unwrap(),
+        StagedIndex::of_files("/data", &["src/other.rs"]),
+        vec![],
+        vec![],
+    )
+    .write_atomic(&path)
+    .unwrap();
+    fs::write(manifest_path(&path), manifest).unwrap();
+    assert!(open_error(&path).contains("index.bin.delta"));
+}
@@ -1,33 +1,71 @@
 use std::{
+    borrow::Cow,
     fs::File,
     io::{self, Error, ErrorKind},
     mem,
     path::Path,
     str,
+    sync::OnceLock,
 };
 
-use bytemuck::{Pod, Zeroable, cast_slice, from_bytes};
-use memmap2::{Mmap, MmapOptions};
+use bytemuck::{Pod, Zeroable, bytes_of_mut, cast_slice, from_bytes, pod_read_unaligned};
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::MmapOptions;
 
-use crate::{Trigram, helpers::blob_str};
+use self::bytes::IndexBytes;
+use crate::{Trigram, delta::Delta, flags::BuildFlags, helpers::blob_str};
 
+pub mod access;
+pub mod budget;
 pub mod builder;
+pub mod bundle;
+mod bytes;
 pub mod compat;
+pub mod delta;
+pub mod dir_paths;
+pub mod dir_tree;
 pub mod flags;
 pub mod helpers;
+pub mod langs;
+pub mod manifest;
 pub mod persist;
 pub mod reader;
-
+#[cfg(not(target_arch = "wasm32"))]
+pub mod reclassify;
+pub mod tombstones;
+pub mod verify;
+pub mod warm;
+
+pub use access::{Credentials, DirAccess, can_access};
+pub use budget::{IndexBudget, NamesUsage, SectionUsage};
 pub use builder::*;
+pub use bundle::{BundleConfig, IndexBundle};
+#[cfg(not(target_arch = "wasm32"))]
+pub use bundle::{export_bundle, import_bundle};
+#[cfg(not(target_arch = "wasm32"))]
+pub use delta::StagedDelta;
+pub use delta::{DeltaUsage, delta_path, remove_delta};
+pub use dir_paths::DirPaths;
+pub use dir_tree::DirTree;
+pub use langs::{LangUsage, lang_usage};
+#[cfg(not(target_arch = "wasm32"))]
+pub use manifest::write_manifest;
+pub use manifest::{manifest_path, verify_manifest_enabled};
 pub use persist::*;
 pub use reader::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use reclassify::{ReclassifyReport, reclassify_index};
+pub use tombstones::Tombstones;
+pub use verify::VerifyReport;
+pub use warm::WarmSet;
 
 pub type FileId = u32;
 pub type DirId = u32;
 pub type ExtId = u16;
+pub type RepoId = u32;
 
 pub struct Index {
-    mmap: Mmap,
+    bytes: IndexBytes,
     header: IndexHeader,
     ext_table: Vec<String>,
     file_metas_offset: usize,
@@ -51,6 +89,35 @@ pub struct Index {
     dir_trigram_keys_len: usize,
     dir_trigram_postings_offset: usize,
     dir_trigram_postings_len: usize,
+
+    repos_offset: usize,
+    repos_len: usize,
+    file_repos_offset: usize,
+    file_repos_len: usize,
+    file_hashes_offset: usize,
+    file_hashes_len: usize,
+    dir_paths_offset: usize,
+    dir_paths_len: usize,
+    name_trigram_keys_offset: usize,
+    name_trigram_keys_len: usize,
+    name_trigram_postings_offset: usize,
+    name_trigram_postings_len: usize,
+    exact_name_keys_offset: usize,
+    exact_name_keys_len: usize,
+    exact_name_postings_offset: usize,
+    exact_name_postings_len: usize,
+    dir_perms_offset: usize,
+    dir_perms_len: usize,
+    dir_files_offset: usize,
+    dir_files_len: usize,
+    dir_file_postings_offset: usize,
+    dir_file_postings_len: usize,
+
+    /// Built on first call to [`IndexReader::dir_children`] or
+    /// [`IndexReader::dir_files`].
+    dir_tree: OnceLock<DirTree>,
+    /// Changes overlaid since the index file was written, see [`delta`].
+    delta: Option<Box<Delta>>,
 }
 
 /// Describes a section within the index file.
@@ -100,7 +167,7 @@ pub struct IndexHeader {
     pub magic: u32,
     /// Index format version
     pub version: u32,
-    /// Size of this header in bytes (for forward compatibility)
+    /// Size of this header in bytes, see [`decode_header`]
     pub header_size: u32,
     /// CRC32 of header bytes [0..header_size), with this field set to 0
     pub header_crc32: u32,
@@ -131,6 +198,88 @@ pub struct IndexHeader {
 
     pub dir_trigram_keys: SectionDesc,
     pub dir_trigram_postings: SectionDesc,
+
+    /// Repository table (`RepoMeta`)
+    pub repos: SectionDesc,
+    /// Repository of each file (`RepoId`, `u32::MAX` for none)
+    pub file_repos: SectionDesc,
+    /// Content hash of each file (`u64`, `0` for none); empty if hashing was off
+    pub file_hashes: SectionDesc,
+    /// Front-coded relative path of each directory; empty unless requested
+    pub dir_paths: SectionDesc,
+
+    /// Trigrams of file names alone, for the same files as `trigram_keys`
+    pub name_trigram_keys: SectionDesc,
+    pub name_trigram_postings: SectionDesc,
+
+    /// Files by the hash of their lowercased name (`NameKey`), for exact
+    /// name lookups
+    pub exact_name_keys: SectionDesc,
+    pub exact_name_postings: SectionDesc,
+
+    /// Owner and mode of each directory (`DirPerms`); empty where the
+    /// platform has none
+    pub dir_perms: SectionDesc,
+
+    /// Where the entries of each directory, and of the root after the last
+    /// one, sit in `dir_file_postings` (`DirFilesRange`)
+    pub dir_files: SectionDesc,
+    pub dir_file_postings: SectionDesc,
+}
+
+impl IndexHeader {
+    /// Every section the header describes.
+    pub(crate) fn sections_mut(&mut self) -> [&mut SectionDesc; 22] {
+        let IndexHeader {
+            metadata,
+            ext_table,
+            dirs,
+            files_meta,
+            names_blob,
+            ext_index_keys,
+            ext_index_postings,
+            trigram_keys,
+            trigram_postings,
+            dir_trigram_keys,
+            dir_trigram_postings,
+            repos,
+            file_repos,
+            file_hashes,
+            dir_paths,
+            name_trigram_keys,
+            name_trigram_postings,
+            exact_name_keys,
+            exact_name_postings,
+            dir_perms,
+            dir_files,
+            dir_file_postings,
+            ..
+        } = self;
+        [
+            metadata,
+            ext_table,
+            dirs,
+            files_meta,
+            names_blob,
+            ext_index_keys,
+            ext_index_postings,
+            trigram_keys,
+            trigram_postings,
+            dir_trigram_keys,
+            dir_trigram_postings,
+            repos,
+            file_repos,
+            file_hashes,
+            dir_paths,
+            name_trigram_keys,
+            name_trigram_postings,
+            exact_name_keys,
+            exact_name_postings,
+            dir_perms,
+            dir_files,
+            dir_file_postings,
+        ]
+    }
 }
 
 // Disk Structs
@@ -149,6 +298,34 @@ pub struct IndexMeta {
     pub build_flags: u32,
     /// Reserved
     pub _reserved: u32,
+    /// Size cap for content hashing, `0` if hashing was off
+    pub hash_max_bytes: u64,
+    /// Largest file indexed, `0` if sizes were not limited
+    pub max_file_bytes: u64,
+    /// Unix timestamp when the scan started, `0` if not recorded
+    pub scan_started_secs: u64,
+    /// Unix timestamp when the scan finished, `0` if not recorded
+    pub scan_finished_secs: u64,
+}
+
+/// How the build behind an index went, stored in the metadata section right
+/// after [`IndexMeta`]. Indexes written before it was added lack it, and so
+/// do indexes not built from a scan, whose stats are all zero.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct BuildStats {
+    /// Milliseconds from the start of the scan until the index was ready to
+    /// write
+    pub build_millis: u64,
+    /// Directories the scan descended into
+    pub dirs_scanned: u64,
+    /// Directories the scan came across but left out: excluded, network
+    /// mounts, timed out or caches whose contents were skipped
+    pub dirs_skipped: u64,
+    /// Files left out by the size limits
+    pub files_skipped: u64,
+    /// Peak resident memory of the building process in bytes, `0` if unknown
+    pub peak_memory_bytes: u64,
 }
 
 bitflags::bitflags! {
@@ -186,8 +363,15 @@ pub struct FileMeta {
     pub noise_bits: u8,
     /// Path depth (number of components)
     pub path_depth: u8,
+    /// Detected language (`blaze_runtime::LangId`, 0 if unknown)
+    pub lang_id: u8,
     /// Padding for 8-byte alignment (struct contains u64, so must be 8-byte aligned)
-    pub _reserved: u16,
+    pub _reserved: u8,
+    /// Owner, group and permission bits; only set with `FileFlags::HAS_PERMS`
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub _reserved2: u32,
 }
 
 #[repr(C)]
@@ -201,6 +385,27 @@ pub struct DirMeta {
     pub _reserved: u16,
 }
 
+/// Owner and permission bits of a directory when it was indexed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DirPerms {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+/// A git repository detected during the walk.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct RepoMeta {
+    /// Repository name (its root directory's name) in names_blob
+    pub name_offset: u32,
+    pub name_len: u32,
+    /// Directory holding `.git`; u32::MAX when it is the index root
+    pub root_dir: DirId,
+    pub _reserved: u32,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct ExtKey {
@@ -228,21 +433,103 @@ pub struct TrigramKey {
     pub _reserved: u32,
 }
 
+/// Posting list of the files whose lowercased name hashes to `hash`.
+///
+/// Names are hashed with [`helpers::name_hash`]; a collision merges two names'
+/// postings, so callers compare the names of the files they get back.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct NameKey {
+    pub hash: u64,
+    pub postings_offset: u32,
+    // Number of FileIds
+    pub postings_len: u32,
+}
+
+/// Entries of one directory in the `dir_file_postings` section.
+///
+/// Directories are laid out depth-first, so everything below a directory
+/// is one run of postings: `start..direct_end` are the entries directly in
+/// it, in `FileId` order, and `start..subtree_end` the entries at any depth.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DirFilesRange {
+    pub start: u32,
+    pub direct_end: u32,
+    pub subtree_end: u32,
+    pub _reserved: u32,
+}
+
 /// The on-disk, mmap'd Index.
 /// Provides zero-copy access to the Index.
 /// Do NOT use this to build an index. There is a dedicated builder for that.
 /// See [IndexBuilder]
 impl Index {
+    /// Open the index at `path`, with its delta overlaid if it has one.
+    ///
+    /// With [`VERIFY_MANIFEST_ENV`](blaze_runtime::VERIFY_MANIFEST_ENV) set,
+    /// this verifies the index against its manifest like [`Self::open_verified`].
     pub fn open(path: &Path) -> io::Result<Self> {
-        let (mmap, header) = map_and_read_header(path)?;
-        verify_index_header(&mmap, &header)?;
-        let ext_table = decode_ext_table(&mmap, &header)?;
-        Ok(Self::from_mmap(mmap, header, ext_table))
+        if verify_manifest_enabled() {
+            return Self::open_verified(path);
+        }
+        let mut index = Self::open_base(path)?;
+        index.delta = Delta::open(path, &index)?.map(Box::new);
+        Ok(index)
     }
 
-    fn from_mmap(mmap: Mmap, header: IndexHeader, ext_table: Vec<String>) -> Self {
+    /// Open the index at `path` like [`Self::open`], failing with
+    /// [`ErrorKind::InvalidData`] unless the index and its delta match the
+    /// BLAKE3 hashes in its manifest (see [`manifest`]).
+    ///
+    /// Every page of both files is read to hash them.
+    pub fn open_verified(path: &Path) -> io::Result<Self> {
+        let mut index = Self::open_base(path)?;
+        index.delta = Delta::open(path, &index)?.map(Box::new);
+        manifest::check_manifest(
+            path,
+            &index.bytes,
+            index.delta.as_ref().map(|d| &d.index.bytes[..]),
+        )?;
+        Ok(index)
+    }
+
+    /// Open the index file at `path` on its own, ignoring any delta.
+    pub(crate) fn open_base(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::from_index_bytes(map_file(&file)?)
+    }
+
+    /// Load the index file held in `bytes` on its own, without a delta.
+    ///
+    /// The bytes are copied into an aligned buffer, so this works where
+    /// files cannot be mapped, e.g. on wasm.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::from_index_bytes(IndexBytes::copy_from(bytes))
+    }
+
+    fn from_index_bytes(bytes: IndexBytes) -> io::Result<Self> {
+        let header = read_header(&bytes)?;
+        verify_index_header(&bytes, &header)?;
+        let ext_table = decode_ext_table(&bytes, &header)?;
+        Ok(Self::from_parts(bytes, header, ext_table))
+    }
+
+    /// Load a staged index without persisting it.
+    ///
+    /// The index is serialized into an unnamed temporary file (never linked
+    /// into the filesystem) and mapped like an on-disk index, so it behaves
+    /// exactly like one for queries. The file is reclaimed on drop.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_staged(staged: &StagedIndex) -> io::Result<Self> {
+        let file = tempfile::tempfile()?;
+        write_index_to(&file, staged, 0)?;
+        Self::from_index_bytes(map_file(&file)?)
+    }
+
+    fn from_parts(bytes: IndexBytes, header: IndexHeader, ext_table: Vec<String>) -> Self {
         Self {
-            mmap,
+            bytes,
             header,
             ext_table,
             file_metas_offset: header.files_meta.offset as usize,
@@ -263,6 +550,30 @@ impl Index {
             dir_trigram_keys_len: header.dir_trigram_keys.len as usize,
             dir_trigram_postings_offset: header.dir_trigram_postings.offset as usize,
             dir_trigram_postings_len: header.dir_trigram_postings.len as usize,
+            repos_offset: header.repos.offset as usize,
+            repos_len: header.repos.len as usize,
+            file_repos_offset: header.file_repos.offset as usize,
+            file_repos_len: header.file_repos.len as usize,
+            file_hashes_offset: header.file_hashes.offset as usize,
+            file_hashes_len: header.file_hashes.len as usize,
+            dir_paths_offset: header.dir_paths.offset as usize,
+            dir_paths_len: header.dir_paths.len as usize,
+            name_trigram_keys_offset: header.name_trigram_keys.offset as usize,
+            name_trigram_keys_len: header.name_trigram_keys.len as usize,
+            name_trigram_postings_offset: header.name_trigram_postings.offset as usize,
+            name_trigram_postings_len: header.name_trigram_postings.len as usize,
+            exact_name_keys_offset: header.exact_name_keys.offset as usize,
+            exact_name_keys_len: header.exact_name_keys.len as usize,
+            exact_name_postings_offset: header.exact_name_postings.offset as usize,
+            exact_name_postings_len: header.exact_name_postings.len as usize,
+            dir_perms_offset: header.dir_perms.offset as usize,
+            dir_perms_len: header.dir_perms.len as usize,
+            dir_files_offset: header.dir_files.offset as usize,
+            dir_files_len: header.dir_files.len as usize,
+            dir_file_postings_offset: header.dir_file_postings.offset as usize,
+            dir_file_postings_len: header.dir_file_postings.len as usize,
+            dir_tree: OnceLock::new(),
+            delta: None,
         }
     }
 
@@ -270,47 +581,75 @@ impl Index {
     fn file_metas(&self) -> &[FileMeta] {
         let start = self.file_metas_offset;
         let end = start + self.file_metas_len_bytes;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn dirs(&self) -> &[DirMeta] {
         let start = self.dirs_offset;
         let end = start + self.dirs_len_bytes;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn names_blob(&self) -> &[u8] {
-        &self.mmap[self.names_blob_offset..self.names_blob_offset + self.names_blob_len]
+        &self.bytes[self.names_blob_offset..self.names_blob_offset + self.names_blob_len]
     }
 
     #[inline]
     fn trigram_keys(&self) -> &[TrigramKey] {
         let start = self.trigram_keys_offset;
         let end = start + self.trigram_keys_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn trigram_postings_raw(&self) -> &[u32] {
         let start = self.trigram_postings_offset;
         let end = start + self.trigram_postings_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn dir_trigram_keys(&self) -> &[TrigramKey] {
         let start = self.dir_trigram_keys_offset;
         let end = start + self.dir_trigram_keys_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn dir_trigram_postings_raw(&self) -> &[u32] {
         let start = self.dir_trigram_postings_offset;
         let end = start + self.dir_trigram_postings_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn name_trigram_keys(&self) -> &[TrigramKey] {
+        let start = self.name_trigram_keys_offset;
+        let end = start + self.name_trigram_keys_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn name_trigram_postings_raw(&self) -> &[u32] {
+        let start = self.name_trigram_postings_offset;
+        let end = start + self.name_trigram_postings_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn exact_name_keys(&self) -> &[NameKey] {
+        let start = self.exact_name_keys_offset;
+        let end = start + self.exact_name_keys_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn exact_name_postings_raw(&self) -> &[u32] {
+        let start = self.exact_name_postings_offset;
+        let end = start + self.exact_name_postings_len;
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
@@ -327,18 +666,73 @@ impl Index {
         Some(&postings[start..end])
     }
 
+    #[inline]
+    fn repos(&self) -> &[RepoMeta] {
+        let start = self.repos_offset;
+        let end = start + self.repos_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn file_repos(&self) -> &[RepoId] {
+        let start = self.file_repos_offset;
+        let end = start + self.file_repos_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn file_hashes(&self) -> &[u64] {
+        let start = self.file_hashes_offset;
+        let end = start + self.file_hashes_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    /// Precomputed directory paths, if the index was built with them.
+    #[inline]
+    fn dir_paths(&self) -> Option<DirPaths<'_>> {
+        let start = self.dir_paths_offset;
+        let end = start + self.dir_paths_len;
+        DirPaths::new(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn dir_perms_raw(&self) -> &[DirPerms] {
+        let start = self.dir_perms_offset;
+        let end = start + self.dir_perms_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn dir_files_raw(&self) -> &[DirFilesRange] {
+        let start = self.dir_files_offset;
+        let end = start + self.dir_files_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    #[inline]
+    fn dir_file_postings_raw(&self) -> &[u32] {
+        let start = self.dir_file_postings_offset;
+        let end = start + self.dir_file_postings_len;
+        cast_slice(&self.bytes[start..end])
+    }
+
+    /// Whether directory paths are precomputed in this index.
+    pub fn has_dir_paths(&self) -> bool {
+        self.dir_paths_len != 0
+    }
+
     #[inline]
     fn ext_keys(&self) -> &[ExtKey] {
         let start = self.ext_index_keys_offset;
         let end = start + self.ext_index_keys_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
     fn ext_postings_raw(&self) -> &[u32] {
         let start = self.ext_index_postings_offset;
         let end = start + self.ext_index_postings_len;
-        cast_slice(&self.mmap[start..end])
+        cast_slice(&self.bytes[start..end])
     }
 
     #[inline]
@@ -361,6 +755,17 @@ impl Index {
         &postings[start..end]
     }
 
+    /// This file's own postings for [`IndexReader::files_with_ext`], ignoring
+    /// any delta; `None` if the file has no extension index.
+    pub(crate) fn ext_postings_matching(&self, ext: &str) -> Option<Cow<'_, [FileId]>> {
+        if self.ext_keys().is_empty() {
+            return None;
+        }
+        Some(reader::postings_for_ext(&self.ext_table, ext, |id| {
+            self.ext_postings(id)
+        }))
+    }
+
     /// Zero-copy file trigram lookup.
     #[inline]
     pub fn query_trigram_on_disk(&self, tri: Trigram) -> Option<&[u32]> {
@@ -373,6 +778,27 @@ impl Index {
         self.trigram_postings_slice(key)
     }
 
+    /// Zero-copy *filename* trigram lookup.
+    #[inline]
+    pub fn query_name_trigram_on_disk(&self, tri: Trigram) -> Option<&[u32]> {
+        reader::lookup_postings(
+            self.name_trigram_keys(),
+            self.name_trigram_postings_raw(),
+            tri,
+        )
+    }
+
+    /// Zero-copy exact name lookup: the files whose lowercased name hashes
+    /// like `name_lower`.
+    #[inline]
+    pub fn query_exact_name_on_disk(&self, name_lower: &str) -> Option<&[u32]> {
+        reader::lookup_name_postings(
+            self.exact_name_keys(),
+            self.exact_name_postings_raw(),
+            name_lower,
+        )
+    }
+
     /// Zero-copy *directory* trigram lookup.
     #[inline]
     pub fn query_dir_trigram_on_disk(&self, tri: Trigram) -> Option<&[u32]> {
@@ -404,6 +830,90 @@ impl Index {
         Some(self.get_name(meta.root_path_offset, meta.root_path_len))
     }
 
+    /// Unix time the index was last written, if recorded: its delta's if it
+    /// has one, the index file's otherwise.
+    pub fn created_secs(&self) -> Option<u64> {
+        match &self.delta {
+            Some(delta) => delta.index.created_secs(),
+            None => self.read_index_meta().map(|m| m.created_secs),
+        }
+    }
+
+    /// Unix times the scan behind the index, or behind its delta, started
+    /// and finished, if recorded.
+    pub fn scan_times(&self) -> Option<(u64, u64)> {
+        let meta = match &self.delta {
+            Some(delta) => delta.index.read_index_meta(),
+            None => self.read_index_meta(),
+        }?;
+        (meta.scan_started_secs != 0).then_some((meta.scan_started_secs, meta.scan_finished_secs))
+    }
+
+    /// How the build behind the index, or behind its delta, went, if
+    /// recorded.
+    pub fn build_stats(&self) -> Option<BuildStats> {
+        let index = match &self.delta {
+            Some(delta) => &delta.index,
+            None => self,
+        };
+        let desc = index.header.metadata;
+        let stored = mem::size_of::<IndexMeta>() + mem::size_of::<BuildStats>();
+        if desc.len < stored as u64 {
+            return None;
+        }
+        let start = desc.offset as usize + mem::size_of::<IndexMeta>();
+        let end = desc.offset as usize + stored;
+        let stats: BuildStats = pod_read_unaligned(&index.bytes[start..end]);
+        (stats != BuildStats::default()).then_some(stats)
+    }
+
+    /// Unix time the index file itself was written, `0` if not recorded.
+    pub(crate) fn base_created_secs(&self) -> u64 {
+        self.read_index_meta().map_or(0, |m| m.created_secs)
+    }
+
+    /// Files in the index file itself; overlaid files are numbered after them.
+    pub fn base_file_count(&self) -> usize {
+        self.header.file_count as usize
+    }
+
+    /// Directories in the index file itself; overlaid directories are
+    /// numbered after them.
+    pub fn base_dir_count(&self) -> usize {
+        self.header.dir_count as usize
+    }
+
+    /// What the overlaid delta changes, if the index has one.
+    pub fn delta_usage(&self) -> Option<DeltaUsage> {
+        self.delta.as_deref().map(|d| DeltaUsage {
+            added: d.added_files(),
+            removed: d.removed_files(),
+        })
+    }
+
+    /// Options the index was built with.
+    pub fn build_flags(&self) -> BuildFlags {
+        self.read_index_meta()
+            .map(|m| BuildFlags::from_bits_truncate(m.build_flags))
+            .unwrap_or_default()
+    }
+
+    /// Size limit for indexed files, if the index was built with one.
+    pub fn max_file_bytes(&self) -> Option<u64> {
+        self.read_index_meta()
+            .filter(|m| {
+                BuildFlags::from_bits_truncate(m.build_flags).contains(BuildFlags::MAX_FILE_SIZE)
+            })
+            .map(|m| m.max_file_bytes)
+    }
+
+    /// Size cap used for content hashing when the index was built, if enabled.
+    pub fn hash_max_bytes(&self) -> Option<u64> {
+        self.read_index_meta()
+            .map(|m| m.hash_max_bytes)
+            .filter(|&n| n != 0)
+    }
+
     fn read_index_meta(&self) -> Option<&IndexMeta> {
         let desc = self.header.metadata;
         if desc.len < mem::size_of::<IndexMeta>() as u64 {
@@ -411,7 +921,7 @@ impl Index {
         }
         let start = desc.offset as usize;
         let end = start + mem::size_of::<IndexMeta>();
-        Some(from_bytes(&self.mmap[start..end]))
+        Some(from_bytes(&self.bytes[start..end]))
     }
 
     pub fn reconstruct_relative_path(&self, file_id: FileId) -> String {
@@ -419,10 +929,23 @@ impl Index {
         let dirs = self.dirs();
 
         let meta = &metas[file_id as usize];
+        let name = self.get_name(meta.name_offset, meta.name_len);
+
+        if let Some(paths) = self.dir_paths() {
+            let mut path = String::with_capacity(64);
+            if meta.dir_id == u32::MAX || paths.push_path(meta.dir_id as usize, &mut path) {
+                if !path.is_empty() {
+                    path.push('/');
+                }
+                path.push_str(name);
+                return path;
+            }
+        }
+
         let mut components: Vec<&str> = Vec::with_capacity(meta.path_depth as usize + 1);
 
         // file name
-        components.push(self.get_name(meta.name_offset, meta.name_len));
+        components.push(name);
 
         // dir chain
         let mut d = meta.dir_id;
@@ -458,30 +981,72 @@ impl Index {
     }
 }
 
-fn map_and_read_header(path: &Path) -> io::Result<(Mmap, IndexHeader)> {
-    let file = File::open(path)?;
-    let mmap = unsafe { MmapOptions::new().map(&file)? };
+#[cfg(not(target_arch = "wasm32"))]
+fn map_file(file: &File) -> io::Result<IndexBytes> {
+    Ok(IndexBytes::Mapped(unsafe { MmapOptions::new().map(file)? }))
+}
+
+/// Without mmap the whole file is read into memory.
+#[cfg(target_arch = "wasm32")]
+fn map_file(mut file: &File) -> io::Result<IndexBytes> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(IndexBytes::copy_from(&bytes))
+}
 
-    let file_len = mmap.len();
-    let header_size = mem::size_of::<IndexHeader>();
+/// Smallest header an index can have: the fixed fields and the sections
+/// every index has, up to the names blob.
+pub(crate) const MIN_HEADER_SIZE: usize = mem::offset_of!(IndexHeader, ext_index_keys);
 
-    if file_len < header_size {
+fn read_header(bytes: &[u8]) -> io::Result<IndexHeader> {
+    if bytes.len() < MIN_HEADER_SIZE {
         return Err(Error::new(
             ErrorKind::InvalidData,
             "index file too small for header",
         ));
     }
+    Ok(decode_header(bytes))
+}
 
-    let header_bytes = &mmap[..header_size];
-    let header: IndexHeader = *from_bytes(header_bytes);
+/// The header at the start of `bytes`, as far as this version knows it.
+///
+/// New fields and sections are only ever appended to the header, and
+/// `header_size` says how much of it was written. Bytes past the fields
+/// this version knows are skipped; sections past the end of a shorter
+/// header are left zeroed, which reads as absent. Check `header_size` with
+/// [`check_header_size`] before trusting the result.
+pub(crate) fn decode_header(bytes: &[u8]) -> IndexHeader {
+    let mut header = IndexHeader::zeroed();
+    let known = bytes.len().min(mem::size_of::<IndexHeader>());
+    bytes_of_mut(&mut header)[..known].copy_from_slice(&bytes[..known]);
+    // Past a shorter header are the sections that follow it.
+    let stored = (header.header_size as usize).min(known);
+    bytes_of_mut(&mut header)[stored..].fill(0);
+    header
+}
 
-    Ok((mmap, header))
+/// Fail unless `header` says it is at least [`MIN_HEADER_SIZE`] bytes and
+/// fits in the `file_len` bytes of its index.
+pub(crate) fn check_header_size(header: &IndexHeader, file_len: usize) -> io::Result<()> {
+    let header_size = header.header_size as usize;
+    if header_size < MIN_HEADER_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "index header too small"));
+    }
+    if header_size > file_len {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "index file too small for header",
+        ));
+    }
+    Ok(())
 }
 
-fn decode_ext_table(mmap: &Mmap, header: &IndexHeader) -> io::Result<Vec<String>> {
+fn decode_ext_table(bytes: &[u8], header: &IndexHeader) -> io::Result<Vec<String>> {
     let ext_off = header.ext_table.offset as usize;
     let ext_end = ext_off + header.ext_table.len as usize;
-    let ext_bytes = &mmap[ext_off..ext_end];
+    let ext_bytes = &bytes[ext_off..ext_end];
 
     let mut exts = Vec::new();
 
@@ -497,17 +1062,8 @@ fn decode_ext_table(mmap: &Mmap, header: &IndexHeader) -> io::Result<Vec<String>
     Ok(exts)
 }
 
-fn verify_index_header(mmap: &Mmap, header: &IndexHeader) -> io::Result<()> {
-    let file_len = mmap.len();
-    let header_size = mem::size_of::<IndexHeader>();
-
-    // Basic bound check: header must fit
-    if file_len < header_size {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "index file too small for header",
-        ));
-    }
+fn verify_index_header(bytes: &[u8], header: &IndexHeader) -> io::Result<()> {
+    let file_len = bytes.len();
 
     if header.magic != INDEX_MAGIC {
         return Err(Error::new(ErrorKind::InvalidData, "invalid index magic"));
@@ -517,6 +1073,9 @@ fn verify_index_header(mmap: &Mmap, header: &IndexHeader) -> io::Result<()> {
         return Err(Error::new(ErrorKind::InvalidData, "index version mismatch"));
     }
 
+    // Basic bound check: header must fit
+    check_header_size(header, file_len)?;
+
     for section in [
         header.metadata,
         header.ext_table,
@@ -529,6 +1088,17 @@ fn verify_index_header(mmap: &Mmap, header: &IndexHeader) -> io::Result<()> {
         header.trigram_postings,
         header.dir_trigram_keys,
         header.dir_trigram_postings,
+        header.repos,
+        header.file_repos,
+        header.file_hashes,
+        header.dir_paths,
+        header.name_trigram_keys,
+        header.name_trigram_postings,
+        header.exact_name_keys,
+        header.exact_name_postings,
+        header.dir_perms,
+        header.dir_files,
+        header.dir_file_postings,
     ] {
         let start = section.offset as usize;
         let len = section.len as usize;
@@ -547,7 +1117,7 @@ fn verify_index_header(mmap: &Mmap, header: &IndexHeader) -> io::Result<()> {
     }
 
     // TODO: header CRC32 check
-    // compute_crc32(&mmap[..header.header_size as usize], with header_crc32 field zeroed)
+    // compute_crc32(&bytes[..header.header_size as usize], with header_crc32 field zeroed)
 
     Ok(())
 }
@@ -1,5 +1,11 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
 use super::*;
 use crate::trigram::Trigram;
+use blaze_fs::FileRecord;
 use memmap2::{Mmap, MmapMut};
 
 fn build_test_index_for_trigrams() -> Index {
@@ -100,10 +106,21 @@ fn build_test_index_for_trigrams() -> Index {
         trigram_postings: SectionDesc::new(file_posts_offset as u64, file_posts_len_bytes as u64),
         dir_trigram_keys: SectionDesc::new(dir_keys_offset as u64, dir_keys_len_bytes as u64),
         dir_trigram_postings: SectionDesc::new(dir_posts_offset as u64, dir_posts_len_bytes as u64),
+        repos: SectionDesc::new(0, 0),
+        file_repos: SectionDesc::new(0, 0),
+        file_hashes: SectionDesc::new(0, 0),
+        dir_paths: SectionDesc::new(0, 0),
+        name_trigram_keys: SectionDesc::new(0, 0),
+        name_trigram_postings: SectionDesc::new(0, 0),
+        exact_name_keys: SectionDesc::new(0, 0),
+        exact_name_postings: SectionDesc::new(0, 0),
+        dir_perms: SectionDesc::new(0, 0),
+        dir_files: SectionDesc::new(0, 0),
+        dir_file_postings: SectionDesc::new(0, 0),
     };
 
     Index {
-        mmap,
+        bytes: IndexBytes::Mapped(mmap),
         header,
         ext_table: Vec::new(),
         file_metas_offset: 0,
@@ -124,6 +141,30 @@ fn build_test_index_for_trigrams() -> Index {
         dir_trigram_keys_len: dir_keys_len_bytes,
         dir_trigram_postings_offset: dir_posts_offset,
         dir_trigram_postings_len: dir_posts_len_bytes,
+        repos_offset: 0,
+        repos_len: 0,
+        file_repos_offset: 0,
+        file_repos_len: 0,
+        file_hashes_offset: 0,
+        file_hashes_len: 0,
+        dir_paths_offset: 0,
+        dir_paths_len: 0,
+        name_trigram_keys_offset: 0,
+        name_trigram_keys_len: 0,
+        name_trigram_postings_offset: 0,
+        name_trigram_postings_len: 0,
+        exact_name_keys_offset: 0,
+        exact_name_keys_len: 0,
+        exact_name_postings_offset: 0,
+        exact_name_postings_len: 0,
+        dir_perms_offset: 0,
+        dir_perms_len: 0,
+        dir_files_offset: 0,
+        dir_files_len: 0,
+        dir_file_postings_offset: 0,
+        dir_file_postings_len: 0,
+        dir_tree: std::sync::OnceLock::new(),
+        delta: None,
     }
 }
 
@@ -160,3 +201,91 @@ fn query_dir_trigram_on_disk_returns_correct_postings() {
 
     assert!(idx.query_dir_trigram_on_disk(tri_bar).is_none());
 }
+
+/// A written index of a few files.
+fn index_file_bytes() -> Vec<u8> {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        ["src/main.rs", "docs/guide.md"].map(|rel| FileRecord::test_file(root.join(rel))),
+    );
+
+    let mut file = tempfile::tempfile().unwrap();
+    write_index_to(&file, &builder.finish(), 0).unwrap();
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+fn file_paths(index: &Index) -> Vec<String> {
+    (0..index.get_file_count() as FileId)
+        .map(|fid| index.reconstruct_full_path(fid))
+        .collect()
+}
+
+#[test]
+fn header_fields_past_the_known_ones_are_skipped() {
+    let bytes = index_file_bytes();
+    let mut header = decode_header(&bytes);
+    let known = mem::size_of::<IndexHeader>();
+
+    // As a later version would write it: the header grows by a field, and
+    // every section moves back to make room.
+    const GROWTH: usize = 16;
+    header.header_size += GROWTH as u32;
+    for section in header.sections_mut() {
+        section.offset += GROWTH as u64;
+    }
+    let mut grown = bytemuck::bytes_of(&header).to_vec();
+    grown.extend_from_slice(&[0xAB; GROWTH]);
+    grown.extend_from_slice(&bytes[known..]);
+
+    let index = Index::from_bytes(&grown).unwrap();
+    assert_eq!(
+        file_paths(&index),
+        file_paths(&Index::from_bytes(&bytes).unwrap())
+    );
+}
+
+#[test]
+fn sections_past_a_shorter_header_are_absent() {
+    let mut bytes = index_file_bytes();
+    let mut header = decode_header(&bytes);
+    assert!(header.dir_files.len > 0);
+
+    // As an older version would have written it, before the directory
+    // entry sections.
+    header.header_size = mem::offset_of!(IndexHeader, dir_files) as u32;
+    bytes[..mem::size_of::<IndexHeader>()].copy_from_slice(bytemuck::bytes_of(&header));
+
+    let index = Index::from_bytes(&bytes).unwrap();
+    assert_eq!(index.header.dir_files.len, 0);
+    assert_eq!(index.header.dir_file_postings.len, 0);
+    assert_eq!(index.header.dir_perms.len, header.dir_perms.len);
+    assert_eq!(
+        file_paths(&index),
+        ["/data/src/main.rs", "/data/docs/guide.md"]
+    );
+}
+
+#[test]
+fn header_size_must_cover_the_core_sections_and_fit_the_file() {
+    let bytes = index_file_bytes();
+    for header_size in [0, MIN_HEADER_SIZE - 1, bytes.len() + 1] {
+        let mut bytes = bytes.clone();
+        let mut header = decode_header(&bytes);
+        header.header_size = header_size as u32;
+        bytes[..mem::size_of::<IndexHeader>()].copy_from_slice(bytemuck::bytes_of(&header));
+
+        let err = Index::from_bytes(&bytes)
+            .err()
+            .expect("header size is invalid");
+        assert_eq!(err.kind(), ErrorKind::InvalidData, "{header_size}");
+    }
+
+    let err = Index::from_bytes(&bytes[..MIN_HEADER_SIZE - 1])
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
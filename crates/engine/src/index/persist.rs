@@ -1,31 +1,38 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
 use std::{
-    fs::{self, File},
+    fs::File,
     io::{self, BufWriter, Write},
-    path::Path,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
 use tempfile::NamedTempFile;
 
 use bytemuck::{bytes_of, cast_slice};
 use crc32fast::Hasher;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::index::{remove_delta, write_manifest};
 use crate::{
     ExtKey,
-    index::{DirMeta, FileMeta, IndexHeader, IndexMeta, SectionDesc, StagedIndex, TrigramKey},
+    index::{
+        DirFilesRange, DirMeta, DirPerms, FileMeta, IndexHeader, IndexMeta, NameKey, RepoMeta,
+        SectionDesc, StagedIndex, TrigramKey,
+    },
 };
 
 /// Alignment for sections containing structs with u64/u32 fields.
 /// Kept consistent with the rest of the index layout.
-const SECTION_ALIGNMENT: u64 = 8;
+pub(crate) const SECTION_ALIGNMENT: u64 = 8;
 /// Magic number: "BLZE" in little-endian
 pub const INDEX_MAGIC: u32 = 0x455A4C42;
 
-pub const INDEX_VERSION: u32 = 1;
+pub const INDEX_VERSION: u32 = 12;
 
 /// Align `value` up to the next multiple of `alignment`
 #[inline]
-fn align_up(value: u64, alignment: u64) -> u64 {
+pub(crate) fn align_up(value: u64, alignment: u64) -> u64 {
     debug_assert!(alignment.is_power_of_two());
     (value + alignment - 1) & !(alignment - 1)
 }
@@ -53,11 +60,26 @@ fn encode_file_metas(files: &[FileMeta]) -> Vec<u8> {
     cast_slice(files).to_vec()
 }
 
+/// Encode directory permissions
+fn encode_dir_perms(perms: &[DirPerms]) -> Vec<u8> {
+    cast_slice(perms).to_vec()
+}
+
+/// Encode repository table
+fn encode_repos(repos: &[RepoMeta]) -> Vec<u8> {
+    cast_slice(repos).to_vec()
+}
+
 /// Encode a slice of u32 IDs (e.g. sorted FileIds) as raw bytes.
 fn encode_u32_slice(ids: &[u32]) -> Vec<u8> {
     cast_slice(ids).to_vec()
 }
 
+/// Encode a slice of u64 values as raw bytes.
+fn encode_u64_slice(values: &[u64]) -> Vec<u8> {
+    cast_slice(values).to_vec()
+}
+
 fn encode_ext_keys(keys: &[ExtKey]) -> Vec<u8> {
     cast_slice(keys).to_vec()
 }
@@ -67,6 +89,16 @@ fn encode_trigram_keys(keys: &[TrigramKey]) -> Vec<u8> {
     cast_slice(keys).to_vec()
 }
 
+/// Encode directory entry ranges (Pod, repr(C)).
+fn encode_dir_files(ranges: &[DirFilesRange]) -> Vec<u8> {
+    cast_slice(ranges).to_vec()
+}
+
+/// Encode exact name keys (Pod, repr(C)).
+fn encode_name_keys(keys: &[NameKey]) -> Vec<u8> {
+    cast_slice(keys).to_vec()
+}
+
 /// Write a `StagedIndex` to an open file positioned at start.
 ///
 /// `flags_bits` is the raw bitmask
@@ -82,11 +114,15 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
         created_secs,
         root_path_offset: index.root_path_offset,
         root_path_len: index.root_path_len,
-        // TODO: Currently no build-time options. We might just add them later
-        build_flags: 0,
+        build_flags: index.build_flags.bits(),
         _reserved: 0,
+        hash_max_bytes: index.hash_max_bytes,
+        max_file_bytes: index.max_file_bytes,
+        scan_started_secs: index.scan_started_secs,
+        scan_finished_secs: index.scan_finished_secs,
     };
-    let index_meta_bytes = bytes_of(&index_meta);
+    let mut index_meta_bytes = bytes_of(&index_meta).to_vec();
+    index_meta_bytes.extend_from_slice(bytes_of(&index.build_stats));
 
     // Sections
     let ext_table_bytes = encode_ext_table(&index.ext_table);
@@ -105,6 +141,18 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
     let dir_trigram_keys_bytes = encode_trigram_keys(&index.dir_trigram_keys);
     let dir_trigram_postings_bytes = encode_u32_slice(&index.dir_trigram_postings);
 
+    let repos_bytes = encode_repos(&index.repos);
+    let file_repos_bytes = encode_u32_slice(&index.file_repos);
+    let file_hashes_bytes = encode_u64_slice(&index.file_hashes);
+    let dir_paths_bytes = &index.dir_paths;
+    let name_trigram_keys_bytes = encode_trigram_keys(&index.name_trigram_keys);
+    let name_trigram_postings_bytes = encode_u32_slice(&index.name_trigram_postings);
+    let exact_name_keys_bytes = encode_name_keys(&index.exact_name_keys);
+    let exact_name_postings_bytes = encode_u32_slice(&index.exact_name_postings);
+    let dir_perms_bytes = encode_dir_perms(&index.dir_perms);
+    let dir_files_bytes = encode_dir_files(&index.dir_files);
+    let dir_file_postings_bytes = encode_u32_slice(&index.dir_file_postings);
+
     // Computes section offset
     let header_size = std::mem::size_of::<IndexHeader>() as u64;
     let mut offset = header_size;
@@ -162,7 +210,64 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
     offset = align_up(offset, SECTION_ALIGNMENT);
     let dir_trigram_postings_section =
         SectionDesc::new(offset, dir_trigram_postings_bytes.len() as u64);
-    let _final_end = dir_trigram_postings_section.offset + dir_trigram_postings_section.len;
+    offset += dir_trigram_postings_section.len;
+
+    // repos: contains u32, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let repos_section = SectionDesc::new(offset, repos_bytes.len() as u64);
+    offset += repos_section.len;
+
+    // file repos: u32 array, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let file_repos_section = SectionDesc::new(offset, file_repos_bytes.len() as u64);
+    offset += file_repos_section.len;
+
+    // file hashes: u64 array, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let file_hashes_section = SectionDesc::new(offset, file_hashes_bytes.len() as u64);
+    offset += file_hashes_section.len;
+
+    // dir paths: front-coded bytes with a u32 block table, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let dir_paths_section = SectionDesc::new(offset, dir_paths_bytes.len() as u64);
+    offset += dir_paths_section.len;
+
+    // filename trigram keys: contains u32, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let name_trigram_keys_section = SectionDesc::new(offset, name_trigram_keys_bytes.len() as u64);
+    offset += name_trigram_keys_section.len;
+
+    // filename trigram postings: u32 array, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let name_trigram_postings_section =
+        SectionDesc::new(offset, name_trigram_postings_bytes.len() as u64);
+    offset += name_trigram_postings_section.len;
+
+    // exact name keys: contains u64, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let exact_name_keys_section = SectionDesc::new(offset, exact_name_keys_bytes.len() as u64);
+    offset += exact_name_keys_section.len;
+
+    // exact name postings: u32 array, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let exact_name_postings_section =
+        SectionDesc::new(offset, exact_name_postings_bytes.len() as u64);
+    offset += exact_name_postings_section.len;
+
+    // dir perms: u32 triples, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let dir_perms_section = SectionDesc::new(offset, dir_perms_bytes.len() as u64);
+    offset += dir_perms_section.len;
+
+    // dir file ranges: u32 quadruples, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let dir_files_section = SectionDesc::new(offset, dir_files_bytes.len() as u64);
+    offset += dir_files_section.len;
+
+    // dir file postings: u32 array, align
+    offset = align_up(offset, SECTION_ALIGNMENT);
+    let dir_file_postings_section = SectionDesc::new(offset, dir_file_postings_bytes.len() as u64);
+    let _final_end = dir_file_postings_section.offset + dir_file_postings_section.len;
 
     // Header (CRC32 over header bytes with crc field zeroed)
     let mut header = IndexHeader {
@@ -186,6 +291,17 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
         trigram_postings: trigram_postings_section,
         dir_trigram_keys: dir_trigram_keys_section,
         dir_trigram_postings: dir_trigram_postings_section,
+        repos: repos_section,
+        file_repos: file_repos_section,
+        file_hashes: file_hashes_section,
+        dir_paths: dir_paths_section,
+        name_trigram_keys: name_trigram_keys_section,
+        name_trigram_postings: name_trigram_postings_section,
+        exact_name_keys: exact_name_keys_section,
+        exact_name_postings: exact_name_postings_section,
+        dir_perms: dir_perms_section,
+        dir_files: dir_files_section,
+        dir_file_postings: dir_file_postings_section,
     };
 
     let mut hasher = Hasher::new();
@@ -213,7 +329,7 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
     // metadata
     write_padding(&mut writer, pos, metadata_section.offset)?;
     pos = metadata_section.offset;
-    writer.write_all(index_meta_bytes)?;
+    writer.write_all(&index_meta_bytes)?;
     pos += metadata_section.len;
 
     // ext_table
@@ -268,13 +384,114 @@ pub fn write_index_to(file: &File, index: &StagedIndex, index_flags: u32) -> io:
 
     // dir trigram postings
     write_padding(&mut writer, pos, dir_trigram_postings_section.offset)?;
+    pos = dir_trigram_postings_section.offset;
     writer.write_all(&dir_trigram_postings_bytes)?;
+    pos += dir_trigram_postings_section.len;
+
+    // repos
+    write_padding(&mut writer, pos, repos_section.offset)?;
+    pos = repos_section.offset;
+    writer.write_all(&repos_bytes)?;
+    pos += repos_section.len;
+
+    // file repos
+    write_padding(&mut writer, pos, file_repos_section.offset)?;
+    pos = file_repos_section.offset;
+    writer.write_all(&file_repos_bytes)?;
+    pos += file_repos_section.len;
+
+    // file hashes
+    write_padding(&mut writer, pos, file_hashes_section.offset)?;
+    pos = file_hashes_section.offset;
+    writer.write_all(&file_hashes_bytes)?;
+    pos += file_hashes_section.len;
+
+    // dir paths
+    write_padding(&mut writer, pos, dir_paths_section.offset)?;
+    pos = dir_paths_section.offset;
+    writer.write_all(dir_paths_bytes)?;
+    pos += dir_paths_section.len;
+
+    // filename trigram keys
+    write_padding(&mut writer, pos, name_trigram_keys_section.offset)?;
+    pos = name_trigram_keys_section.offset;
+    writer.write_all(&name_trigram_keys_bytes)?;
+    pos += name_trigram_keys_section.len;
+
+    // filename trigram postings
+    write_padding(&mut writer, pos, name_trigram_postings_section.offset)?;
+    pos = name_trigram_postings_section.offset;
+    writer.write_all(&name_trigram_postings_bytes)?;
+    pos += name_trigram_postings_section.len;
+
+    // exact name keys
+    write_padding(&mut writer, pos, exact_name_keys_section.offset)?;
+    pos = exact_name_keys_section.offset;
+    writer.write_all(&exact_name_keys_bytes)?;
+    pos += exact_name_keys_section.len;
+
+    // exact name postings
+    write_padding(&mut writer, pos, exact_name_postings_section.offset)?;
+    pos = exact_name_postings_section.offset;
+    writer.write_all(&exact_name_postings_bytes)?;
+    pos += exact_name_postings_section.len;
+
+    // dir perms
+    write_padding(&mut writer, pos, dir_perms_section.offset)?;
+    pos = dir_perms_section.offset;
+    writer.write_all(&dir_perms_bytes)?;
+    pos += dir_perms_section.len;
+
+    // dir file ranges
+    write_padding(&mut writer, pos, dir_files_section.offset)?;
+    pos = dir_files_section.offset;
+    writer.write_all(&dir_files_bytes)?;
+    pos += dir_files_section.len;
+
+    // dir file postings
+    write_padding(&mut writer, pos, dir_file_postings_section.offset)?;
+    writer.write_all(&dir_file_postings_bytes)?;
 
     writer.flush()?;
     Ok(())
 }
 
+/// Give `file` the permission bits of the index at `index_path`, if there
+/// is one, so rewriting a shared index keeps it readable to the same users.
+/// Temporary files are otherwise created readable by their owner only.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn copy_mode(file: &File, index_path: &Path) -> io::Result<()> {
+    match fs::metadata(index_path) {
+        Ok(meta) => file.set_permissions(meta.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replace `path` with `bytes` via a synced temporary file and a rename.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write_bytes_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let mut tmp = NamedTempFile::new_in(parent)?;
+    tmp.write_all(bytes)?;
+    copy_mode(tmp.as_file(), path)?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).map_err(|e| e.error)?;
+
+    #[cfg(unix)]
+    {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
 /// Atomic index write
+#[cfg(not(target_arch = "wasm32"))]
 pub fn write_index_atomic(path: &Path, index: &StagedIndex, flags_bits: u32) -> io::Result<()> {
     let parent = path.parent().unwrap_or_else(|| Path::new("."));
     fs::create_dir_all(parent)?;
@@ -282,11 +499,18 @@ pub fn write_index_atomic(path: &Path, index: &StagedIndex, flags_bits: u32) ->
     let tmp = NamedTempFile::new_in(parent)?;
 
     write_index_to(tmp.as_file(), index, flags_bits)?;
+    copy_mode(tmp.as_file(), path)?;
 
     tmp.as_file().sync_all()?;
 
+    // A delta only applies to the index it was written against. Dropping it
+    // first means a crash leaves the old index without its delta (stale, but
+    // consistent) rather than the new index with the old delta.
+    remove_delta(path)?;
+
     // Atomically rename temp file to target path
     tmp.persist(path).map_err(|e| e.error)?;
+    write_manifest(path)?;
 
     #[cfg(unix)]
     {
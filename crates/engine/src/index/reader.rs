@@ -1,53 +1,192 @@
+use std::borrow::Cow;
+
+use blaze_fs::FilePerms;
+use blaze_runtime::{LangId, canonical_ext};
+
 use crate::{
-    index::{DirId, FileId, Index, flags::NoiseFlags},
+    index::{
+        DirFilesRange, DirId, DirTree, ExtId, FileId, FileMeta, Index, NameKey, RepoId,
+        StagedIndex, TrigramKey,
+        delta::Delta,
+        flags::{FileFlags, NoiseFlags},
+        helpers::{blob_str, join_root_rel, name_hash},
+    },
     trigram::Trigram,
 };
 
 pub trait IndexReader {
-    /// Get number of indexed files
+    /// Get number of indexed files, counting ids that [`is_file_removed`]
+    /// still holds
+    ///
+    /// [`is_file_removed`]: IndexReader::is_file_removed
     fn get_file_count(&self) -> usize;
+    /// Number of indexed files that are not removed
+    fn live_file_count(&self) -> usize {
+        self.get_file_count()
+    }
     /// Directory count
     fn dir_count(&self) -> usize;
+    /// Whether a file was removed by a delta after the index was written;
+    /// its id stays allocated but must never match
+    fn is_file_removed(&self, _id: FileId) -> bool {
+        false
+    }
+    /// Whether a directory was removed by a delta after the index was written
+    fn is_dir_removed(&self, _id: DirId) -> bool {
+        false
+    }
     /// Get the filename
     fn get_file_name(&self, id: FileId) -> &str;
     fn get_file_dir_id(&self, id: FileId) -> u32;
+    /// Sort key placing files near each other on disk: directory, then
+    /// offset of the name in the names blob
+    fn get_file_locality(&self, id: FileId) -> (DirId, u32);
     fn get_dir_name(&self, id: DirId) -> &str;
+    /// Parent of a directory; `u32::MAX` for directories directly under the root
+    fn get_dir_parent(&self, id: DirId) -> DirId;
+    /// Subdirectories directly inside a directory; `u32::MAX` is the root
+    fn dir_children(&self, id: DirId) -> &[DirId];
+    /// Entries (files and directories) directly inside a directory, in
+    /// `FileId` order; `u32::MAX` is the root
+    fn dir_files(&self, id: DirId) -> &[FileId];
+    /// Entries directly inside a directory or, if `recursive`, at any depth
+    /// below it, in `FileId` order; `u32::MAX` is the root
+    fn files_in_dir(&self, id: DirId, recursive: bool) -> Cow<'_, [FileId]> {
+        walk_dir_files(self, id, recursive)
+    }
     /// Get file extension
     /// Returns lowercase extension, empty string if None
     fn get_file_ext(&self, id: FileId) -> &str;
+    /// Files whose extension canonicalizes to `ext`, which is canonical, in
+    /// id order; `None` if the reader keeps no per-extension postings
+    fn files_with_ext(&self, _ext: &str) -> Option<Cow<'_, [FileId]>> {
+        None
+    }
     /// Get file size
     fn get_file_size(&self, id: FileId) -> u64;
     /// Get the modified time as seconds since Unix epoch
     fn get_file_modified_epoch(&self, id: FileId) -> i64;
     /// Get the created time as seconds since Unix epoch
     fn get_file_created_epoch(&self, id: FileId) -> i64;
+    /// Get the file flags (directory, symlink, trash, ...).
+    fn get_file_flags(&self, id: FileId) -> FileFlags;
     /// Get the noise classification flags.
     fn get_file_noise_bits(&self, id: FileId) -> NoiseFlags;
     /// Get the noise classification flags.
     fn get_file_path_depth(&self, id: FileId) -> u8;
+    /// Get the detected language of the file, if any
+    fn get_file_lang(&self, id: FileId) -> Option<LangId>;
+    /// Get the content hash of the file, if it was hashed at index time
+    fn get_file_hash(&self, id: FileId) -> Option<u64>;
+    /// Get the owner and mode of the file, if they were recorded
+    fn get_file_perms(&self, _id: FileId) -> Option<FilePerms> {
+        None
+    }
+    /// Get the git repository containing the file, if any
+    fn get_file_repo(&self, id: FileId) -> Option<RepoId>;
+    /// Number of git repositories found in the index
+    fn repo_count(&self) -> usize;
+    /// Repository name (the name of its root directory)
+    fn get_repo_name(&self, id: RepoId) -> &str;
+    /// Absolute path of the repository's root directory
+    fn get_repo_root_path(&self, id: RepoId) -> String;
     /// Query a trigram slice
-    fn query_trigram(&self, tri: Trigram) -> Option<&[u32]>;
+    fn query_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>>;
     /// Query Directory Trigram
-    fn query_dir_trigram(&self, tri: Trigram) -> Option<&[u32]>;
+    fn query_dir_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>>;
 
     #[inline]
     fn trigram_postings_len(&self, tri: Trigram) -> usize {
         self.query_trigram(tri).map_or(0, |p| p.len())
     }
 
+    /// Whether the reader indexes file names on their own; without that,
+    /// text terms are filtered by full-path trigrams only
+    fn has_name_trigrams(&self) -> bool {
+        false
+    }
+    /// Query a filename trigram slice: the files whose name alone has `tri`
+    fn query_name_trigram(&self, _tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        None
+    }
+
+    #[inline]
+    fn name_trigram_postings_len(&self, tri: Trigram) -> usize {
+        self.query_name_trigram(tri).map_or(0, |p| p.len())
+    }
+
+    /// Whether the reader can look files up by their whole name
+    fn has_exact_names(&self) -> bool {
+        false
+    }
+    /// Query the exact name section: the files whose lowercased name hashes
+    /// like `name_lower`, which may include a colliding name
+    fn query_exact_name(&self, _name_lower: &str) -> Option<Cow<'_, [u32]>> {
+        None
+    }
+
+    /// Live files and directories named `name`, ignoring case, or `None` if
+    /// the reader has no exact name section.
+    fn files_named(&self, name: &str) -> Option<Vec<FileId>> {
+        if !self.has_exact_names() {
+            return None;
+        }
+        let lower = name.to_lowercase();
+        let Some(postings) = self.query_exact_name(&lower) else {
+            return Some(Vec::new());
+        };
+        Some(
+            postings
+                .iter()
+                .copied()
+                .filter(|&fid| {
+                    !self.is_file_removed(fid) && name_eq_lower(self.get_file_name(fid), &lower)
+                })
+                .collect(),
+        )
+    }
+
     fn reconstruct_full_path(&self, id: FileId) -> String;
+    /// Absolute path of a directory; `u32::MAX` is the index root
+    fn reconstruct_dir_path(&self, id: DirId) -> String;
 }
 
+/// Files and directories past the index file's own are served from its
+/// delta, see [`crate::index::delta`].
 impl IndexReader for Index {
     fn get_file_count(&self) -> usize {
-        self.header.file_count as usize
+        match &self.delta {
+            Some(delta) => delta.file_count(),
+            None => self.header.file_count as usize,
+        }
+    }
+
+    fn live_file_count(&self) -> usize {
+        match &self.delta {
+            Some(delta) => delta.file_count() - delta.removed_files(),
+            None => self.header.file_count as usize,
+        }
     }
 
     fn dir_count(&self) -> usize {
-        self.header.dir_count as usize
+        match &self.delta {
+            Some(delta) => delta.dir_count(),
+            None => self.header.dir_count as usize,
+        }
+    }
+
+    fn is_file_removed(&self, id: FileId) -> bool {
+        self.delta.as_ref().is_some_and(|d| d.is_file_removed(id))
+    }
+
+    fn is_dir_removed(&self, id: DirId) -> bool {
+        self.delta.as_ref().is_some_and(|d| d.is_dir_removed(id))
     }
 
     fn get_dir_name(&self, id: DirId) -> &str {
+        if let Some((delta, dir)) = self.delta_dir(id) {
+            return delta.index.get_dir_name(dir);
+        }
         let dirs = self.dirs();
         if let Some(dir) = dirs.get(id as usize) {
             self.get_name(dir.name_offset, dir.name_len)
@@ -56,7 +195,36 @@ impl IndexReader for Index {
         }
     }
 
+    fn get_dir_parent(&self, id: DirId) -> DirId {
+        if let Some((delta, dir)) = self.delta_dir(id) {
+            return delta.map_dir(delta.index.get_dir_parent(dir));
+        }
+        self.dirs()
+            .get(id as usize)
+            .map(|d| d.parent)
+            .unwrap_or(u32::MAX)
+    }
+
+    fn dir_children(&self, id: DirId) -> &[DirId] {
+        self.dir_tree().children(id)
+    }
+
+    fn dir_files(&self, id: DirId) -> &[FileId] {
+        match self.packed_dir_files(id, false) {
+            Some(Cow::Borrowed(files)) => files,
+            _ => self.dir_tree().files(id),
+        }
+    }
+
+    fn files_in_dir(&self, id: DirId, recursive: bool) -> Cow<'_, [FileId]> {
+        self.packed_dir_files(id, recursive)
+            .unwrap_or_else(|| walk_dir_files(self, id, recursive))
+    }
+
     fn get_file_name(&self, id: FileId) -> &str {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_name(file);
+        }
         let metas = self.file_metas();
         if let Some(meta) = metas.get(id as usize) {
             self.get_name(meta.name_offset, meta.name_len)
@@ -66,13 +234,30 @@ impl IndexReader for Index {
     }
 
     fn get_file_dir_id(&self, id: FileId) -> DirId {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.map_dir(delta.index.get_file_dir_id(file));
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| m.dir_id)
             .unwrap_or(u32::MAX)
     }
 
+    fn get_file_locality(&self, id: FileId) -> (DirId, u32) {
+        if let Some((delta, file)) = self.delta_file(id) {
+            let (dir, offset) = delta.index.get_file_locality(file);
+            return (delta.map_dir(dir), offset);
+        }
+        self.file_metas()
+            .get(id as usize)
+            .map(|m| (m.dir_id, m.name_offset))
+            .unwrap_or((u32::MAX, u32::MAX))
+    }
+
     fn get_file_ext(&self, id: FileId) -> &str {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_ext(file);
+        }
         let metas = self.file_metas();
         if let Some(meta) = metas.get(id as usize) {
             if meta.ext_id == 0 {
@@ -88,7 +273,24 @@ impl IndexReader for Index {
         }
     }
 
+    fn files_with_ext(&self, ext: &str) -> Option<Cow<'_, [FileId]>> {
+        let base = self.ext_postings_matching(ext)?;
+        let Some(delta) = &self.delta else {
+            return Some(base);
+        };
+        let added = delta.index.ext_postings_matching(ext)?;
+        match base {
+            Cow::Borrowed(base) => delta.file_postings(Some(base), Some(&added)),
+            Cow::Owned(base) => delta
+                .file_postings(Some(&base), Some(&added))
+                .map(|p| Cow::Owned(p.into_owned())),
+        }
+    }
+
     fn get_file_size(&self, id: FileId) -> u64 {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_size(file);
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| m.size)
@@ -96,6 +298,9 @@ impl IndexReader for Index {
     }
 
     fn get_file_modified_epoch(&self, id: FileId) -> i64 {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_modified_epoch(file);
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| m.mtime_secs as i64)
@@ -103,13 +308,36 @@ impl IndexReader for Index {
     }
 
     fn get_file_created_epoch(&self, id: FileId) -> i64 {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_created_epoch(file);
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| m.ctime_secs as i64)
             .unwrap_or(0)
     }
 
+    fn get_file_flags(&self, id: FileId) -> FileFlags {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_flags(file);
+        }
+        self.file_metas()
+            .get(id as usize)
+            .map(|m| FileFlags::from_bits_truncate(m.flag_bits))
+            .unwrap_or(FileFlags::empty())
+    }
+
+    fn get_file_perms(&self, id: FileId) -> Option<FilePerms> {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_perms(file);
+        }
+        self.file_metas().get(id as usize).and_then(file_perms)
+    }
+
     fn get_file_noise_bits(&self, id: FileId) -> NoiseFlags {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_noise_bits(file);
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| NoiseFlags::from_bits_truncate(m.noise_bits))
@@ -117,24 +345,555 @@ impl IndexReader for Index {
     }
 
     fn get_file_path_depth(&self, id: FileId) -> u8 {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_path_depth(file);
+        }
         self.file_metas()
             .get(id as usize)
             .map(|m| m.path_depth)
             .unwrap_or(0)
     }
 
-    fn query_trigram(&self, tri: Trigram) -> Option<&[u32]> {
-        self.query_trigram_on_disk(tri)
+    fn get_file_lang(&self, id: FileId) -> Option<LangId> {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_lang(file);
+        }
+        self.file_metas()
+            .get(id as usize)
+            .map(|m| m.lang_id)
+            .filter(|&l| l != 0)
+    }
+
+    fn get_file_hash(&self, id: FileId) -> Option<u64> {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.get_file_hash(file);
+        }
+        self.file_hashes()
+            .get(id as usize)
+            .copied()
+            .filter(|&h| h != 0)
+    }
+
+    fn get_file_repo(&self, id: FileId) -> Option<RepoId> {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.file_repo(file);
+        }
+        self.file_repos()
+            .get(id as usize)
+            .copied()
+            .filter(|&r| r != u32::MAX)
+    }
+
+    fn repo_count(&self) -> usize {
+        self.repos().len()
+    }
+
+    fn get_repo_name(&self, id: RepoId) -> &str {
+        self.repos()
+            .get(id as usize)
+            .map(|r| self.get_name(r.name_offset, r.name_len))
+            .unwrap_or("")
+    }
+
+    fn get_repo_root_path(&self, id: RepoId) -> String {
+        self.repos()
+            .get(id as usize)
+            .map(|r| self.reconstruct_dir_path(r.root_dir))
+            .unwrap_or_default()
+    }
+
+    fn query_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        let base = self.query_trigram_on_disk(tri);
+        match &self.delta {
+            Some(delta) => delta.file_postings(base, delta.index.query_trigram_on_disk(tri)),
+            None => base.map(Cow::Borrowed),
+        }
+    }
+
+    fn query_dir_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        let base = self.query_dir_trigram_on_disk(tri);
+        match &self.delta {
+            Some(delta) => delta.dir_postings(base, delta.index.query_dir_trigram_on_disk(tri)),
+            None => base.map(Cow::Borrowed),
+        }
+    }
+
+    fn trigram_postings_len(&self, tri: Trigram) -> usize {
+        let len = |postings: Option<&[u32]>| postings.map_or(0, |p| p.len());
+        len(self.query_trigram_on_disk(tri))
+            + self
+                .delta
+                .as_ref()
+                .map_or(0, |d| len(d.index.query_trigram_on_disk(tri)))
+    }
+
+    fn has_name_trigrams(&self) -> bool {
+        true
+    }
+
+    fn query_name_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        let base = self.query_name_trigram_on_disk(tri);
+        match &self.delta {
+            Some(delta) => delta.file_postings(base, delta.index.query_name_trigram_on_disk(tri)),
+            None => base.map(Cow::Borrowed),
+        }
+    }
+
+    fn name_trigram_postings_len(&self, tri: Trigram) -> usize {
+        let len = |postings: Option<&[u32]>| postings.map_or(0, |p| p.len());
+        len(self.query_name_trigram_on_disk(tri))
+            + self
+                .delta
+                .as_ref()
+                .map_or(0, |d| len(d.index.query_name_trigram_on_disk(tri)))
     }
 
-    fn query_dir_trigram(&self, tri: Trigram) -> Option<&[u32]> {
-        self.query_dir_trigram_on_disk(tri)
+    fn has_exact_names(&self) -> bool {
+        true
+    }
+
+    fn query_exact_name(&self, name_lower: &str) -> Option<Cow<'_, [u32]>> {
+        let base = self.query_exact_name_on_disk(name_lower);
+        match &self.delta {
+            Some(delta) => {
+                delta.file_postings(base, delta.index.query_exact_name_on_disk(name_lower))
+            }
+            None => base.map(Cow::Borrowed),
+        }
     }
 
     fn reconstruct_full_path(&self, id: FileId) -> String {
+        if let Some((delta, file)) = self.delta_file(id) {
+            return delta.index.reconstruct_full_path(file);
+        }
         // Prefer the stored root + relative path, but don't panic if metadata
         // is inconsistent or missing.
         self.reconstruct_absolute_path(id)
             .unwrap_or_else(|| self.get_file_name(id).to_owned())
     }
+
+    fn reconstruct_dir_path(&self, id: DirId) -> String {
+        if let Some((delta, dir)) = self.delta_dir(id) {
+            return delta.index.reconstruct_dir_path(dir);
+        }
+        let root = self.root_path().unwrap_or("");
+        if let Some(paths) = self.dir_paths() {
+            let mut rel = String::with_capacity(64);
+            if paths.push_path(id as usize, &mut rel) {
+                return join_root_rel(root, &rel);
+            }
+        }
+
+        let dirs = self.dirs();
+
+        let mut components: Vec<&str> = Vec::new();
+        let mut d = id;
+        while let Some(dir) = dirs.get(d as usize) {
+            let name = self.get_name(dir.name_offset, dir.name_len);
+            if !name.is_empty() {
+                components.push(name);
+            }
+            d = dir.parent;
+        }
+        components.reverse();
+
+        join_root_rel(root, &components.join("/"))
+    }
 }
+
+/// Postings of every extension in `ext_table` that canonicalizes to `ext`,
+/// merged in id order when aliases such as `jpeg` and `jpg` were both seen.
+pub(crate) fn postings_for_ext<'a>(
+    ext_table: &[String],
+    ext: &str,
+    postings: impl Fn(ExtId) -> &'a [FileId],
+) -> Cow<'a, [FileId]> {
+    let mut matching = ext_table
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| canonical_ext(e).eq_ignore_ascii_case(ext))
+        .map(|(id, _)| postings(id as ExtId));
+    let Some(first) = matching.next() else {
+        return Cow::Borrowed(&[]);
+    };
+    let mut rest = matching.peekable();
+    if rest.peek().is_none() {
+        return Cow::Borrowed(first);
+    }
+    let mut merged = first.to_vec();
+    for p in rest {
+        merged.extend_from_slice(p);
+    }
+    merged.sort_unstable();
+    Cow::Owned(merged)
+}
+
+/// Look up `tri` in a sorted key table and slice its postings.
+#[inline]
+pub(crate) fn lookup_postings<'a>(
+    keys: &[TrigramKey],
+    postings: &'a [u32],
+    tri: Trigram,
+) -> Option<&'a [u32]> {
+    let idx = keys
+        .binary_search_by_key(&tri.as_u32(), |k| k.trigram)
+        .ok()?;
+    let key = &keys[idx];
+
+    let start = key.postings_offset as usize;
+    let end = start + key.postings_len as usize;
+    postings.get(start..end)
+}
+
+/// Look up `name_lower` in a sorted exact name key table and slice its
+/// postings.
+#[inline]
+pub(crate) fn lookup_name_postings<'a>(
+    keys: &[NameKey],
+    postings: &'a [u32],
+    name_lower: &str,
+) -> Option<&'a [u32]> {
+    let idx = keys
+        .binary_search_by_key(&name_hash(name_lower), |k| k.hash)
+        .ok()?;
+    let key = &keys[idx];
+
+    let start = key.postings_offset as usize;
+    let end = start + key.postings_len as usize;
+    postings.get(start..end)
+}
+
+/// Whether `name` lowercases to `lower`, without allocating for ASCII names.
+#[inline]
+/// The recorded owner and mode of a file, if any.
+/// Entries of directory `id` (`u32::MAX` is the root) laid out by
+/// [`DirTree::pack_files`]; `None` if there are no ranges, or they do not
+/// fit the postings.
+fn packed_dir_files<'a>(
+    ranges: &[DirFilesRange],
+    postings: &'a [FileId],
+    id: DirId,
+    recursive: bool,
+) -> Option<Cow<'a, [FileId]>> {
+    let root = ranges.len().checked_sub(1)?;
+    let range = ranges[(id as usize).min(root)];
+    if !recursive || range.subtree_end == range.direct_end {
+        return postings
+            .get(range.start as usize..range.direct_end as usize)
+            .map(Cow::Borrowed);
+    }
+    // Subdirectories follow in depth-first order, not by id.
+    let mut files = postings
+        .get(range.start as usize..range.subtree_end as usize)?
+        .to_vec();
+    files.sort_unstable();
+    Some(Cow::Owned(files))
+}
+
+/// [`IndexReader::files_in_dir`] from the parent-to-child links.
+fn walk_dir_files<I: IndexReader + ?Sized>(
+    index: &I,
+    id: DirId,
+    recursive: bool,
+) -> Cow<'_, [FileId]> {
+    if !recursive {
+        return Cow::Borrowed(index.dir_files(id));
+    }
+    let mut files = Vec::new();
+    let mut seen = vec![false; index.dir_count() + 1];
+    let mut stack = vec![id];
+    while let Some(dir) = stack.pop() {
+        // Guards against a parent cycle in a damaged index.
+        if std::mem::replace(&mut seen[(dir as usize).min(index.dir_count())], true) {
+            continue;
+        }
+        files.extend_from_slice(index.dir_files(dir));
+        stack.extend_from_slice(index.dir_children(dir));
+    }
+    files.sort_unstable();
+    Cow::Owned(files)
+}
+
+fn file_perms(meta: &FileMeta) -> Option<FilePerms> {
+    FileFlags::from_bits_truncate(meta.flag_bits)
+        .contains(FileFlags::HAS_PERMS)
+        .then_some(FilePerms {
+            uid: meta.uid,
+            gid: meta.gid,
+            mode: meta.mode,
+        })
+}
+
+fn name_eq_lower(name: &str, lower: &str) -> bool {
+    if name.is_ascii() {
+        name.len() == lower.len() && name.eq_ignore_ascii_case(lower)
+    } else {
+        name.to_lowercase() == lower
+    }
+}
+
+/// Query a freshly built index directly from the builder's buffers,
+/// without writing it out and mapping it back in.
+impl IndexReader for StagedIndex {
+    fn get_file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    fn dir_count(&self) -> usize {
+        self.dirs.len()
+    }
+
+    fn get_dir_name(&self, id: DirId) -> &str {
+        self.dirs
+            .get(id as usize)
+            .map(|d| blob_str(&self.names_blob, d.name_offset, d.name_len))
+            .unwrap_or("")
+    }
+
+    fn get_dir_parent(&self, id: DirId) -> DirId {
+        self.dirs
+            .get(id as usize)
+            .map(|d| d.parent)
+            .unwrap_or(u32::MAX)
+    }
+
+    fn dir_children(&self, id: DirId) -> &[DirId] {
+        self.dir_tree().children(id)
+    }
+
+    fn dir_files(&self, id: DirId) -> &[FileId] {
+        self.dir_tree().files(id)
+    }
+
+    fn files_in_dir(&self, id: DirId, recursive: bool) -> Cow<'_, [FileId]> {
+        packed_dir_files(&self.dir_files, &self.dir_file_postings, id, recursive)
+            .unwrap_or_else(|| walk_dir_files(self, id, recursive))
+    }
+
+    fn get_file_name(&self, id: FileId) -> &str {
+        self.files
+            .get(id as usize)
+            .map(|m| blob_str(&self.names_blob, m.name_offset, m.name_len))
+            .unwrap_or("")
+    }
+
+    fn get_file_dir_id(&self, id: FileId) -> DirId {
+        self.files
+            .get(id as usize)
+            .map(|m| m.dir_id)
+            .unwrap_or(u32::MAX)
+    }
+
+    fn get_file_locality(&self, id: FileId) -> (DirId, u32) {
+        self.files
+            .get(id as usize)
+            .map(|m| (m.dir_id, m.name_offset))
+            .unwrap_or((u32::MAX, u32::MAX))
+    }
+
+    fn get_file_ext(&self, id: FileId) -> &str {
+        match self.files.get(id as usize) {
+            Some(meta) if meta.ext_id != 0 => self
+                .ext_table
+                .get(meta.ext_id as usize)
+                .map(|s| s.as_str())
+                .unwrap_or(""),
+            _ => "",
+        }
+    }
+
+    fn files_with_ext(&self, ext: &str) -> Option<Cow<'_, [FileId]>> {
+        Some(postings_for_ext(&self.ext_table, ext, |id| {
+            self.ext_index_keys
+                .get(id as usize)
+                .and_then(|key| {
+                    let start = key.postings_offset as usize;
+                    self.ext_index_postings
+                        .get(start..start + key.postings_len as usize)
+                })
+                .unwrap_or_default()
+        }))
+    }
+
+    fn get_file_size(&self, id: FileId) -> u64 {
+        self.files.get(id as usize).map(|m| m.size).unwrap_or(0)
+    }
+
+    fn get_file_modified_epoch(&self, id: FileId) -> i64 {
+        self.files
+            .get(id as usize)
+            .map(|m| m.mtime_secs as i64)
+            .unwrap_or(0)
+    }
+
+    fn get_file_created_epoch(&self, id: FileId) -> i64 {
+        self.files
+            .get(id as usize)
+            .map(|m| m.ctime_secs as i64)
+            .unwrap_or(0)
+    }
+
+    fn get_file_flags(&self, id: FileId) -> FileFlags {
+        self.files
+            .get(id as usize)
+            .map(|m| FileFlags::from_bits_truncate(m.flag_bits))
+            .unwrap_or(FileFlags::empty())
+    }
+
+    fn get_file_perms(&self, id: FileId) -> Option<FilePerms> {
+        self.files.get(id as usize).and_then(file_perms)
+    }
+
+    fn get_file_noise_bits(&self, id: FileId) -> NoiseFlags {
+        self.files
+            .get(id as usize)
+            .map(|m| NoiseFlags::from_bits_truncate(m.noise_bits))
+            .unwrap_or(NoiseFlags::empty())
+    }
+
+    fn get_file_path_depth(&self, id: FileId) -> u8 {
+        self.files
+            .get(id as usize)
+            .map(|m| m.path_depth)
+            .unwrap_or(0)
+    }
+
+    fn get_file_lang(&self, id: FileId) -> Option<LangId> {
+        self.files
+            .get(id as usize)
+            .map(|m| m.lang_id)
+            .filter(|&l| l != 0)
+    }
+
+    fn get_file_hash(&self, id: FileId) -> Option<u64> {
+        self.file_hashes
+            .get(id as usize)
+            .copied()
+            .filter(|&h| h != 0)
+    }
+
+    fn get_file_repo(&self, id: FileId) -> Option<RepoId> {
+        self.file_repos
+            .get(id as usize)
+            .copied()
+            .filter(|&r| r != u32::MAX)
+    }
+
+    fn repo_count(&self) -> usize {
+        self.repos.len()
+    }
+
+    fn get_repo_name(&self, id: RepoId) -> &str {
+        self.repos
+            .get(id as usize)
+            .map(|r| blob_str(&self.names_blob, r.name_offset, r.name_len))
+            .unwrap_or("")
+    }
+
+    fn get_repo_root_path(&self, id: RepoId) -> String {
+        self.repos
+            .get(id as usize)
+            .map(|r| self.dir_path(r.root_dir, None))
+            .unwrap_or_default()
+    }
+
+    fn query_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        lookup_postings(&self.file_trigram_keys, &self.file_trigram_postings, tri)
+            .map(Cow::Borrowed)
+    }
+
+    fn query_dir_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        lookup_postings(&self.dir_trigram_keys, &self.dir_trigram_postings, tri).map(Cow::Borrowed)
+    }
+
+    fn has_name_trigrams(&self) -> bool {
+        true
+    }
+
+    fn query_name_trigram(&self, tri: Trigram) -> Option<Cow<'_, [u32]>> {
+        lookup_postings(&self.name_trigram_keys, &self.name_trigram_postings, tri)
+            .map(Cow::Borrowed)
+    }
+
+    fn has_exact_names(&self) -> bool {
+        true
+    }
+
+    fn query_exact_name(&self, name_lower: &str) -> Option<Cow<'_, [u32]>> {
+        lookup_name_postings(&self.exact_name_keys, &self.exact_name_postings, name_lower)
+            .map(Cow::Borrowed)
+    }
+
+    fn reconstruct_full_path(&self, id: FileId) -> String {
+        let Some(meta) = self.files.get(id as usize) else {
+            return String::new();
+        };
+        self.dir_path(meta.dir_id, Some(self.get_file_name(id)))
+    }
+
+    fn reconstruct_dir_path(&self, id: DirId) -> String {
+        self.dir_path(id, None)
+    }
+}
+
+impl Index {
+    fn dir_tree(&self) -> &DirTree {
+        self.dir_tree.get_or_init(|| DirTree::build(self))
+    }
+
+    /// Entries of `id` from the `dir_files` section; `None` if the index
+    /// has none, or a delta changed the tree since it was written.
+    fn packed_dir_files(&self, id: DirId, recursive: bool) -> Option<Cow<'_, [FileId]>> {
+        if self.delta.is_some() {
+            return None;
+        }
+        packed_dir_files(
+            self.dir_files_raw(),
+            self.dir_file_postings_raw(),
+            id,
+            recursive,
+        )
+    }
+
+    /// The delta and its own id for a file the delta added.
+    #[inline]
+    fn delta_file(&self, id: FileId) -> Option<(&Delta, FileId)> {
+        let delta = self.delta.as_deref()?;
+        delta.file(id).map(|file| (delta, file))
+    }
+
+    /// The delta and its own id for a directory the delta added.
+    #[inline]
+    fn delta_dir(&self, id: DirId) -> Option<(&Delta, DirId)> {
+        let delta = self.delta.as_deref()?;
+        delta.dir(id).map(|dir| (delta, dir))
+    }
+}
+
+impl StagedIndex {
+    fn dir_tree(&self) -> &DirTree {
+        self.dir_tree.get_or_init(|| DirTree::build(self))
+    }
+
+    /// Absolute path of `dir_id` (`u32::MAX` is the root), optionally
+    /// followed by a final `leaf` component.
+    fn dir_path(&self, dir_id: DirId, leaf: Option<&str>) -> String {
+        let mut components: Vec<&str> = leaf.into_iter().collect();
+        let mut d = dir_id;
+        while let Some(dir) = self.dirs.get(d as usize) {
+            let name = blob_str(&self.names_blob, dir.name_offset, dir.name_len);
+            if !name.is_empty() {
+                components.push(name);
+            }
+            d = dir.parent;
+        }
+        components.reverse();
+
+        let root = blob_str(&self.names_blob, self.root_path_offset, self.root_path_len);
+        join_root_rel(root, &components.join("/"))
+    }
+}
+
+#[cfg(test)]
+#[path = "reader_tests.rs"]
+mod tests;
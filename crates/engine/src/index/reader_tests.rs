@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+
+use super::*;
+use crate::{IndexBuilder, QueryPipeline, Trigram, build_trigrams_for_string};
+
+fn staged() -> StagedIndex {
+    StagedIndex::of_files(
+        "/data",
+        &[
+            "README",
+            "src/main.rs",
+            "src/engine/query.rs",
+            "docs/Guide.MD",
+        ],
+    )
+}
+
+#[test]
+fn staged_reader_matches_mapped_index() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    assert_eq!(staged.get_file_count(), mapped.get_file_count());
+    assert_eq!(staged.dir_count(), mapped.dir_count());
+
+    for fid in 0..staged.get_file_count() as FileId {
+        assert_eq!(staged.get_file_name(fid), mapped.get_file_name(fid));
+        assert_eq!(staged.get_file_dir_id(fid), mapped.get_file_dir_id(fid));
+        assert_eq!(staged.get_file_ext(fid), mapped.get_file_ext(fid));
+        assert_eq!(staged.get_file_size(fid), mapped.get_file_size(fid));
+        assert_eq!(
+            staged.get_file_modified_epoch(fid),
+            mapped.get_file_modified_epoch(fid)
+        );
+        assert_eq!(
+            staged.get_file_created_epoch(fid),
+            mapped.get_file_created_epoch(fid)
+        );
+        assert_eq!(
+            staged.get_file_noise_bits(fid),
+            mapped.get_file_noise_bits(fid)
+        );
+        assert_eq!(
+            staged.get_file_path_depth(fid),
+            mapped.get_file_path_depth(fid)
+        );
+        assert_eq!(
+            staged.reconstruct_full_path(fid),
+            mapped.reconstruct_full_path(fid)
+        );
+    }
+
+    for dir in 0..staged.dir_count() as DirId {
+        assert_eq!(staged.get_dir_name(dir), mapped.get_dir_name(dir));
+    }
+
+    for tri in build_trigrams_for_string("src/engine/query.rs") {
+        assert_eq!(staged.query_trigram(tri), mapped.query_trigram(tri));
+        assert_eq!(staged.query_dir_trigram(tri), mapped.query_dir_trigram(tri));
+        assert_eq!(
+            staged.query_name_trigram(tri),
+            mapped.query_name_trigram(tri)
+        );
+    }
+
+    // Only `query.rs` has "que" in its name; "src" is only in directories.
+    let que = Trigram::from_bytes(b'q', b'u', b'e');
+    assert_eq!(mapped.query_name_trigram(que).as_deref(), Some(&[2][..]));
+    let src = Trigram::from_bytes(b's', b'r', b'c');
+    assert_eq!(mapped.query_name_trigram(src), None);
+    assert!(mapped.query_trigram(src).is_some());
+}
+
+#[test]
+fn files_are_looked_up_by_their_whole_name() {
+    let staged = staged();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    for reader in [&staged as &dyn IndexReader, &mapped] {
+        assert_eq!(reader.files_named("readme"), Some(vec![0]));
+        assert_eq!(reader.files_named("GUIDE.md"), Some(vec![3]));
+        assert_eq!(reader.files_named("query"), Some(vec![]));
+        assert_eq!(reader.files_named("main.rs.bak"), Some(vec![]));
+    }
+    assert_eq!(
+        staged.query_exact_name("main.rs"),
+        mapped.query_exact_name("main.rs")
+    );
+}
+
+#[test]
+fn staged_reader_handles_out_of_range_ids() {
+    let staged = staged();
+    let missing = staged.get_file_count() as FileId;
+
+    assert_eq!(staged.get_file_name(missing), "");
+    assert_eq!(staged.get_file_ext(missing), "");
+    assert_eq!(staged.get_file_dir_id(missing), u32::MAX);
+    assert_eq!(staged.reconstruct_full_path(missing), "");
+    assert_eq!(staged.get_dir_name(u32::MAX), "");
+}
+
+#[test]
+fn staged_index_runs_queries() {
+    let staged = staged();
+
+    let pipeline = QueryPipeline::new(&staged)
+        .parse("query ext:rs")
+        .execute()
+        .rank_with_limit(Some(10));
+    let paths: Vec<String> = pipeline.iter_with_paths().map(|(_, _, p)| p).collect();
+
+    assert_eq!(paths, ["/data/src/engine/query.rs"]);
+}
+
+#[test]
+fn files_with_ext_merges_aliases() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("a.jpg")),
+        FileRecord::test_file(root.join("b.rs")),
+        FileRecord::test_file(root.join("c.jpeg")),
+        FileRecord::test_file(root.join("d.JPG")),
+    ]);
+    let staged = builder.finish();
+    let mapped = Index::from_staged(&staged).unwrap();
+
+    for reader in [&staged as &dyn IndexReader, &mapped] {
+        assert_eq!(
+            reader.files_with_ext("jpg").as_deref(),
+            Some(&[0, 2, 3][..])
+        );
+        assert_eq!(reader.files_with_ext("rs").as_deref(), Some(&[1][..]));
+        assert_eq!(reader.files_with_ext("md").as_deref(), Some(&[][..]));
+    }
+}
@@ -0,0 +1,81 @@
+//! Recompute noise flags of an existing index without rescanning.
+//!
+//! `noise_bits` and `path_depth` are derived from a file's path alone, so
+//! when the noise heuristics change they can be recomputed from the paths
+//! the index already stores. The one exception, `NoiseFlags::GENERATED`,
+//! comes from the walk and is kept. Only the `FileMeta` section changes;
+//! every other byte, the header included, is copied as is.
+
+use std::{io, path::Path};
+
+use bytemuck::cast_slice;
+
+use crate::index::{
+    FileMeta, Index, IndexReader, delta_path,
+    flags::{NoiseFlags, classify_noise},
+    persist::write_bytes_atomic,
+    write_manifest,
+};
+
+/// Outcome of [`reclassify_index`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReclassifyReport {
+    /// Files whose noise flags were recomputed.
+    pub files: usize,
+    /// Files whose noise flags or depth changed.
+    pub changed: usize,
+}
+
+/// Recompute the noise flags of every file in the index at `path`, and of
+/// its delta if there is one, rewriting each file that changed.
+pub fn reclassify_index(path: &Path) -> io::Result<ReclassifyReport> {
+    let mut report = reclassify_file(path)?;
+
+    let delta = delta_path(path);
+    if delta.exists() {
+        let delta_report = reclassify_file(&delta)?;
+        report.files += delta_report.files;
+        report.changed += delta_report.changed;
+    }
+
+    if report.changed > 0 {
+        write_manifest(path)?;
+    }
+    Ok(report)
+}
+
+fn reclassify_file(path: &Path) -> io::Result<ReclassifyReport> {
+    let index = Index::open_base(path)?;
+    let mut metas: Vec<FileMeta> = index.file_metas().to_vec();
+
+    let mut changed = 0;
+    for (fid, meta) in metas.iter_mut().enumerate() {
+        let (mut noise, depth) = classify_noise(&index.reconstruct_full_path(fid as u32));
+        noise |= NoiseFlags::from_bits_truncate(meta.noise_bits) & NoiseFlags::GENERATED;
+        if meta.noise_bits != noise.bits() || meta.path_depth != depth {
+            meta.noise_bits = noise.bits();
+            meta.path_depth = depth;
+            changed += 1;
+        }
+    }
+
+    let report = ReclassifyReport {
+        files: metas.len(),
+        changed,
+    };
+    if changed == 0 {
+        return Ok(report);
+    }
+
+    let mut bytes = index.bytes.to_vec();
+    let start = index.file_metas_offset;
+    bytes[start..start + index.file_metas_len_bytes].copy_from_slice(cast_slice(&metas));
+    drop(index);
+
+    write_bytes_atomic(path, &bytes)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "reclassify_tests.rs"]
+mod tests;
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+use tempfile::tempdir;
+
+use super::*;
+use crate::{IndexBuilder, flags::NoiseFlags, write_index_atomic};
+
+fn file_id(index: &Index, path: &str) -> u32 {
+    (0..index.get_file_count() as u32)
+        .find(|&f| index.reconstruct_full_path(f) == path)
+        .unwrap()
+}
+
+#[test]
+fn recomputes_stale_noise_flags() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        ["src/main.rs", "node_modules/pkg/index.js"]
+            .iter()
+            .map(|rel| FileRecord::test_file(root.join(rel))),
+    );
+    let mut staged = builder.finish();
+    let expected: Vec<(u8, u8)> = staged
+        .files
+        .iter()
+        .map(|m| (m.noise_bits, m.path_depth))
+        .collect();
+    // As if written by older heuristics.
+    for meta in &mut staged.files {
+        meta.noise_bits = 0;
+        meta.path_depth = 0;
+    }
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &staged, 0).unwrap();
+
+    let report = reclassify_index(&path).unwrap();
+    assert_eq!(report.files, 2);
+    assert_eq!(report.changed, 2);
+
+    let index = Index::open(&path).unwrap();
+    let actual: Vec<(u8, u8)> = index
+        .file_metas()
+        .iter()
+        .map(|m| (m.noise_bits, m.path_depth))
+        .collect();
+    assert_eq!(actual, expected);
+    let vendored = file_id(&index, "/data/node_modules/pkg/index.js");
+    assert!(
+        index
+            .get_file_noise_bits(vendored)
+            .contains(NoiseFlags::BUILD_DIR)
+    );
+    drop(index);
+
+    // Nothing left to change: the file is not rewritten.
+    assert_eq!(
+        reclassify_index(&path).unwrap(),
+        ReclassifyReport {
+            files: 2,
+            changed: 0
+        }
+    );
+}
+
+#[test]
+fn keeps_generated_flags_from_the_walk() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/main.rs")),
+        FileRecord {
+            generated: true,
+            ..FileRecord::test_file(root.join("pkgs/cache/blob"))
+        },
+    ]);
+    let staged = builder.finish();
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &staged, 0).unwrap();
+    reclassify_index(&path).unwrap();
+
+    let index = Index::open(&path).unwrap();
+    let generated = |path: &str| {
+        index
+            .get_file_noise_bits(file_id(&index, path))
+            .contains(NoiseFlags::GENERATED)
+    };
+    assert!(generated("/data/pkgs/cache/blob"));
+    assert!(!generated("/data/src/main.rs"));
+}
@@ -0,0 +1,65 @@
+//! Bitmaps of deleted ids.
+//!
+//! A delta hides base files and directories without rewriting the postings
+//! that list them: their ids stay allocated and a [`Tombstones`] bitmap says
+//! they are gone. Readers consult it wherever ids enter a result, so a
+//! deleted file stops matching as soon as the delta is written.
+
+use std::borrow::Cow;
+
+/// Ids below a fixed bound that are deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tombstones {
+    /// Bit `i` is set when id `i` is deleted.
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl Tombstones {
+    /// Tombstones for `ids`; ids at or above `bound` are ignored.
+    pub fn from_ids(ids: &[u32], bound: u32) -> Self {
+        let mut bits = vec![0u64; (bound as usize).div_ceil(64)];
+        let mut len = 0;
+        for &id in ids.iter().filter(|&&id| id < bound) {
+            let (word, bit) = (id as usize / 64, 1u64 << (id % 64));
+            if bits[word] & bit == 0 {
+                bits[word] |= bit;
+                len += 1;
+            }
+        }
+        Self { bits, len }
+    }
+
+    #[inline]
+    pub fn contains(&self, id: u32) -> bool {
+        self.bits
+            .get(id as usize / 64)
+            .is_some_and(|word| word & (1 << (id % 64)) != 0)
+    }
+
+    /// Number of deleted ids.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `ids` without the deleted ones; borrowed when none are.
+    pub fn filter<'a>(&self, ids: &'a [u32]) -> Cow<'a, [u32]> {
+        if self.is_empty() || !ids.iter().any(|&id| self.contains(id)) {
+            return Cow::Borrowed(ids);
+        }
+        Cow::Owned(
+            ids.iter()
+                .copied()
+                .filter(|&id| !self.contains(id))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+#[path = "tombstones_tests.rs"]
+mod tests;
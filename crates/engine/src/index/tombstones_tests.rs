@@ -0,0 +1,24 @@
+use std::borrow::Cow;
+
+use super::*;
+
+#[test]
+fn counts_each_id_once_and_ignores_out_of_range() {
+    let dead = Tombstones::from_ids(&[3, 64, 3, 200], 100);
+
+    assert_eq!(dead.len(), 2);
+    assert!(dead.contains(3) && dead.contains(64));
+    assert!(!dead.contains(4) && !dead.contains(200));
+}
+
+#[test]
+fn filter_borrows_unless_something_is_dropped() {
+    let dead = Tombstones::from_ids(&[2], 10);
+
+    assert!(matches!(dead.filter(&[1, 3, 5]), Cow::Borrowed(_)));
+    assert_eq!(dead.filter(&[1, 2, 3]).as_ref(), [1, 3]);
+    assert!(matches!(
+        Tombstones::default().filter(&[1, 2]),
+        Cow::Borrowed(_)
+    ));
+}
@@ -0,0 +1,411 @@
+use std::mem;
+
+use blaze_runtime::language;
+use bytemuck::{Pod, try_cast_slice};
+
+use super::{
+    DirFilesRange, DirMeta, DirPerms, ExtKey, FileMeta, Index, IndexMeta, NameKey, RepoMeta,
+    SectionDesc, TrigramKey, dir_paths::relative_dir_paths,
+};
+
+/// Maximum number of issues recorded before verification stops collecting.
+const MAX_REPORTED_ISSUES: usize = 32;
+
+/// Result of a structural consistency check over an on-disk index.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Number of file records checked.
+    pub files_checked: usize,
+    /// Number of directory records checked.
+    pub dirs_checked: usize,
+    /// Number of posting entries checked across all posting sections.
+    pub postings_checked: usize,
+    /// Human-readable descriptions of each problem found (capped).
+    pub issues: Vec<String>,
+    /// Total number of problems found, including those not recorded.
+    pub issue_count: usize,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issue_count == 0
+    }
+
+    fn issue(&mut self, msg: impl FnOnce() -> String) {
+        self.issue_count += 1;
+        if self.issues.len() < MAX_REPORTED_ISSUES {
+            self.issues.push(msg());
+        }
+    }
+}
+
+impl Index {
+    /// Check internal invariants of the index that `open` does not.
+    ///
+    /// `open` only validates the header and section bounds; this walks every
+    /// record and posting list, so it is proportional to index size.
+    pub fn verify(&self) -> VerifyReport {
+        let mut report = VerifyReport::default();
+
+        let Some(files) =
+            self.section::<FileMeta>("files_meta", self.header.files_meta, &mut report)
+        else {
+            return report;
+        };
+        let Some(dirs) = self.section::<DirMeta>("dirs", self.header.dirs, &mut report) else {
+            return report;
+        };
+        let names_len = self.header.names_blob.len;
+
+        if self.header.file_count as usize != files.len() {
+            report.issue(|| {
+                format!(
+                    "header file_count {} does not match {} file records",
+                    self.header.file_count,
+                    files.len()
+                )
+            });
+        }
+        if self.header.dir_count as usize != dirs.len() {
+            report.issue(|| {
+                format!(
+                    "header dir_count {} does not match {} dir records",
+                    self.header.dir_count,
+                    dirs.len()
+                )
+            });
+        }
+
+        if self.header.metadata.len < mem::size_of::<IndexMeta>() as u64 {
+            report.issue(|| "metadata section too small for IndexMeta".to_string());
+        } else if let Some(meta) = self.read_index_meta() {
+            let end = u64::from(meta.root_path_offset) + u64::from(meta.root_path_len);
+            if end > names_len {
+                report.issue(|| "root path lies outside names_blob".to_string());
+            }
+        }
+
+        let ext_keys =
+            self.section::<ExtKey>("ext_index_keys", self.header.ext_index_keys, &mut report);
+
+        for (id, file) in files.iter().enumerate() {
+            if u64::from(file.name_offset) + u64::from(file.name_len) > names_len {
+                report.issue(|| format!("file {id}: name lies outside names_blob"));
+            }
+            if file.dir_id != u32::MAX && file.dir_id as usize >= dirs.len() {
+                report.issue(|| format!("file {id}: dir_id {} out of range", file.dir_id));
+            }
+            if let Some(keys) = ext_keys
+                && file.ext_id as usize >= keys.len()
+            {
+                report.issue(|| format!("file {id}: ext_id {} out of range", file.ext_id));
+            }
+            if file.lang_id != 0 && language(file.lang_id).is_none() {
+                report.issue(|| format!("file {id}: lang_id {} is unknown", file.lang_id));
+            }
+        }
+        report.files_checked = files.len();
+
+        for (id, dir) in dirs.iter().enumerate() {
+            if u64::from(dir.name_offset) + u64::from(dir.name_len) > names_len {
+                report.issue(|| format!("dir {id}: name lies outside names_blob"));
+            }
+            // Parents are always interned before their children, which also
+            // rules out cycles in the parent chain.
+            if dir.parent != u32::MAX && dir.parent as usize >= id {
+                report.issue(|| format!("dir {id}: parent {} is not an earlier dir", dir.parent));
+            }
+        }
+        report.dirs_checked = dirs.len();
+
+        if let Some(repos) = self.section::<RepoMeta>("repos", self.header.repos, &mut report) {
+            for (id, repo) in repos.iter().enumerate() {
+                if u64::from(repo.name_offset) + u64::from(repo.name_len) > names_len {
+                    report.issue(|| format!("repo {id}: name lies outside names_blob"));
+                }
+                if repo.root_dir != u32::MAX && repo.root_dir as usize >= dirs.len() {
+                    report.issue(|| format!("repo {id}: root_dir {} out of range", repo.root_dir));
+                }
+            }
+
+            if let Some(file_repos) =
+                self.section::<u32>("file_repos", self.header.file_repos, &mut report)
+            {
+                if file_repos.len() != files.len() {
+                    report.issue(|| {
+                        format!(
+                            "file_repos has {} entries for {} files",
+                            file_repos.len(),
+                            files.len()
+                        )
+                    });
+                }
+                for (id, &repo) in file_repos.iter().enumerate() {
+                    if repo != u32::MAX && repo as usize >= repos.len() {
+                        report.issue(|| format!("file {id}: repo {repo} out of range"));
+                    }
+                }
+            }
+        }
+
+        if let Some(hashes) =
+            self.section::<u64>("file_hashes", self.header.file_hashes, &mut report)
+            && !hashes.is_empty()
+            && hashes.len() != files.len()
+        {
+            report.issue(|| {
+                format!(
+                    "file_hashes has {} entries for {} files",
+                    hashes.len(),
+                    files.len()
+                )
+            });
+        }
+
+        if let Some(perms) =
+            self.section::<DirPerms>("dir_perms", self.header.dir_perms, &mut report)
+            && !perms.is_empty()
+            && perms.len() != dirs.len()
+        {
+            report.issue(|| {
+                format!(
+                    "dir_perms has {} entries for {} dirs",
+                    perms.len(),
+                    dirs.len()
+                )
+            });
+        }
+
+        if self.has_dir_paths() {
+            match self.dir_paths().and_then(|p| p.decode_all()) {
+                None => report.issue(|| "dir_paths section is malformed".to_string()),
+                Some(paths) if paths.len() != dirs.len() => report.issue(|| {
+                    format!(
+                        "dir_paths has {} entries for {} dirs",
+                        paths.len(),
+                        dirs.len()
+                    )
+                }),
+                Some(paths) => {
+                    let expected = relative_dir_paths(dirs, self.names_blob());
+                    for (id, (path, want)) in paths.iter().zip(&expected).enumerate() {
+                        if path != want {
+                            report.issue(|| format!("dir {id}: stored path {path:?} != {want:?}"));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(keys) = ext_keys
+            && let Some(postings) = self.section::<u32>(
+                "ext_index_postings",
+                self.header.ext_index_postings,
+                &mut report,
+            )
+        {
+            for (idx, key) in keys.iter().enumerate() {
+                if key.ext_id as usize != idx {
+                    report.issue(|| format!("ext key {idx}: ext_id {} out of order", key.ext_id));
+                }
+                let label = || format!("ext {}", key.ext_id);
+                check_postings(
+                    &mut report,
+                    label,
+                    postings,
+                    key.postings_offset,
+                    key.postings_len,
+                    files.len(),
+                );
+            }
+        }
+
+        self.check_trigram_sections(
+            &mut report,
+            "file",
+            self.header.trigram_keys,
+            self.header.trigram_postings,
+            files.len(),
+        );
+        self.check_trigram_sections(
+            &mut report,
+            "dir",
+            self.header.dir_trigram_keys,
+            self.header.dir_trigram_postings,
+            dirs.len(),
+        );
+        self.check_trigram_sections(
+            &mut report,
+            "name",
+            self.header.name_trigram_keys,
+            self.header.name_trigram_postings,
+            files.len(),
+        );
+        self.check_exact_name_sections(&mut report, files.len());
+        self.check_dir_file_sections(&mut report, dirs.len(), files.len());
+
+        report
+    }
+
+    fn check_trigram_sections(
+        &self,
+        report: &mut VerifyReport,
+        kind: &str,
+        keys_desc: SectionDesc,
+        postings_desc: SectionDesc,
+        id_limit: usize,
+    ) {
+        let Some(keys) = self.section::<TrigramKey>(kind, keys_desc, report) else {
+            return;
+        };
+        let Some(postings) = self.section::<u32>(kind, postings_desc, report) else {
+            return;
+        };
+
+        for (idx, pair) in keys.windows(2).enumerate() {
+            if pair[0].trigram >= pair[1].trigram {
+                report.issue(|| format!("{kind} trigram keys not sorted at {}", idx + 1));
+            }
+        }
+
+        for key in keys {
+            let label = || format!("{kind} trigram {:#08x}", key.trigram);
+            check_postings(
+                report,
+                label,
+                postings,
+                key.postings_offset,
+                key.postings_len,
+                id_limit,
+            );
+        }
+    }
+
+    fn check_exact_name_sections(&self, report: &mut VerifyReport, id_limit: usize) {
+        let kind = "exact name";
+        let Some(keys) = self.section::<NameKey>(kind, self.header.exact_name_keys, report) else {
+            return;
+        };
+        let Some(postings) = self.section::<u32>(kind, self.header.exact_name_postings, report)
+        else {
+            return;
+        };
+
+        for (idx, pair) in keys.windows(2).enumerate() {
+            if pair[0].hash >= pair[1].hash {
+                report.issue(|| format!("{kind} keys not sorted at {}", idx + 1));
+            }
+        }
+
+        for key in keys {
+            let label = || format!("{kind} hash {:#016x}", key.hash);
+            check_postings(
+                report,
+                label,
+                postings,
+                key.postings_offset,
+                key.postings_len,
+                id_limit,
+            );
+        }
+    }
+
+    fn check_dir_file_sections(
+        &self,
+        report: &mut VerifyReport,
+        dir_count: usize,
+        id_limit: usize,
+    ) {
+        let kind = "dir_files";
+        let Some(ranges) = self.section::<DirFilesRange>(kind, self.header.dir_files, report)
+        else {
+            return;
+        };
+        let Some(postings) = self.section::<u32>(kind, self.header.dir_file_postings, report)
+        else {
+            return;
+        };
+        if ranges.is_empty() {
+            return;
+        }
+        if ranges.len() != dir_count + 1 {
+            report.issue(|| {
+                format!(
+                    "{kind} has {} entries for {dir_count} dirs and the root",
+                    ranges.len()
+                )
+            });
+        }
+
+        for (dir, range) in ranges.iter().enumerate() {
+            if range.start > range.direct_end
+                || range.direct_end > range.subtree_end
+                || range.subtree_end as usize > postings.len()
+            {
+                report.issue(|| format!("{kind} {dir}: range out of order or bounds"));
+                continue;
+            }
+            let label = || format!("{kind} {dir}");
+            check_postings(
+                report,
+                label,
+                postings,
+                range.start,
+                range.direct_end - range.start,
+                id_limit,
+            );
+        }
+    }
+
+    /// Reinterpret a section as a slice of `T`, reporting misaligned or
+    /// mis-sized sections instead of panicking.
+    fn section<T: Pod>(
+        &self,
+        name: &str,
+        desc: SectionDesc,
+        report: &mut VerifyReport,
+    ) -> Option<&[T]> {
+        let start = desc.offset as usize;
+        let end = start + desc.len as usize;
+        match try_cast_slice(&self.bytes[start..end]) {
+            Ok(slice) => Some(slice),
+            Err(e) => {
+                report.issue(|| format!("{name} section is not a valid array: {e}"));
+                None
+            }
+        }
+    }
+}
+
+/// Check that a posting list lies in bounds, is strictly ascending, and only
+/// references ids below `id_limit`.
+fn check_postings(
+    report: &mut VerifyReport,
+    label: impl Fn() -> String,
+    postings: &[u32],
+    offset: u32,
+    len: u32,
+    id_limit: usize,
+) {
+    let start = offset as usize;
+    let end = start + len as usize;
+    let Some(list) = postings.get(start..end) else {
+        report.issue(|| format!("{}: postings lie outside section", label()));
+        return;
+    };
+
+    report.postings_checked += list.len();
+
+    if list.windows(2).any(|w| w[0] >= w[1]) {
+        report.issue(|| format!("{}: postings not strictly ascending", label()));
+    }
+    if let Some(&max) = list.last()
+        && max as usize >= id_limit
+    {
+        report.issue(|| format!("{}: posting {max} out of range", label()));
+    }
+}
+
+#[cfg(test)]
+#[path = "verify_tests.rs"]
+mod tests;
@@ -0,0 +1,156 @@
+use std::{
+    fs::OpenOptions,
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use blaze_fs::FileRecord;
+use tempfile::tempdir;
+
+use super::*;
+use crate::{FileId, IndexBuilder, IndexReader, flags::BuildFlags, write_index_atomic};
+
+fn write_test_index(dir: &Path) -> PathBuf {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/main.rs")),
+        FileRecord::test_file(root.join("src/lib.rs")),
+        FileRecord::test_file(root.join("docs/guide/intro.md")),
+        FileRecord::test_file(root.join("README")),
+    ]);
+
+    let path = dir.join("index.bin");
+    write_index_atomic(&path, &builder.finish(), 0).expect("write index");
+    path
+}
+
+#[test]
+fn verify_accepts_freshly_built_index() {
+    let dir = tempdir().unwrap();
+    let path = write_test_index(dir.path());
+
+    let report = Index::open(&path).unwrap().verify();
+
+    assert!(report.is_ok(), "unexpected issues: {:?}", report.issues);
+    assert_eq!(report.files_checked, 4);
+    assert_eq!(report.dirs_checked, 3);
+    assert!(report.postings_checked > 0);
+}
+
+#[test]
+fn first_interned_extension_does_not_alias_no_extension() {
+    let dir = tempdir().unwrap();
+    let path = write_test_index(dir.path());
+
+    let index = Index::open(&path).unwrap();
+
+    // ext id 0 stands for "no extension", so the first extension interned
+    // must not be handed id 0.
+    for fid in 0..index.get_file_count() as FileId {
+        let full_path = index.reconstruct_full_path(fid);
+        let expected = Path::new(&full_path)
+            .extension()
+            .map_or(String::new(), |e| e.to_string_lossy().into_owned());
+        assert_eq!(index.get_file_ext(fid), expected, "{full_path}");
+    }
+}
+
+#[test]
+fn verify_reports_out_of_range_dir_id() {
+    let dir = tempdir().unwrap();
+    let path = write_test_index(dir.path());
+
+    let files_offset = Index::open(&path).unwrap().header.files_meta.offset;
+    let dir_id_offset = mem::offset_of!(FileMeta, dir_id) as u64;
+
+    let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(files_offset + dir_id_offset))
+        .unwrap();
+    file.write_all(&9_999u32.to_ne_bytes()).unwrap();
+    drop(file);
+
+    let report = Index::open(&path).unwrap().verify();
+
+    assert!(!report.is_ok());
+    assert!(
+        report.issues.iter().any(|i| i.contains("dir_id 9999")),
+        "issues: {:?}",
+        report.issues
+    );
+}
+
+#[test]
+fn from_staged_loads_without_a_path() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("a.txt")),
+        FileRecord::test_file(root.join("sub/b.rs")),
+    ]);
+
+    let index = Index::from_staged(&builder.finish()).expect("load staged index");
+
+    assert_eq!(index.get_file_count(), 2);
+    assert_eq!(index.reconstruct_full_path(1), "/data/sub/b.rs");
+    // The first interned extension must not collide with "no extension".
+    assert_eq!(index.get_file_ext(0), "txt");
+    assert_eq!(index.get_file_ext(1), "rs");
+    assert!(index.verify().is_ok());
+}
+
+#[test]
+fn stored_dir_paths_match_parent_chains() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([
+        FileRecord::test_file(root.join("src/main.rs")),
+        FileRecord::test_file(root.join("docs/guide/intro.md")),
+        FileRecord::test_file(root.join("docs/guide/advanced/tips.md")),
+        FileRecord::test_file(root.join("README")),
+    ]);
+    let staged = builder.finish();
+    let plain = Index::from_staged(&staged).unwrap();
+
+    let mut staged = staged;
+    staged.store_dir_paths();
+    let stored = Index::from_staged(&staged).unwrap();
+
+    assert!(!plain.has_dir_paths());
+    assert!(stored.has_dir_paths());
+    assert!(stored.verify().is_ok());
+    for fid in 0..stored.get_file_count() as u32 {
+        assert_eq!(
+            stored.reconstruct_full_path(fid),
+            plain.reconstruct_full_path(fid)
+        );
+    }
+    for did in 0..stored.dir_count() as u32 {
+        assert_eq!(
+            stored.reconstruct_dir_path(did),
+            plain.reconstruct_dir_path(did)
+        );
+    }
+    assert_eq!(
+        stored.reconstruct_full_path(2),
+        "/data/docs/guide/advanced/tips.md"
+    );
+}
+
+#[test]
+fn build_options_round_trip() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch([FileRecord::test_file(root.join("a.txt"))]);
+    let mut staged = builder.finish();
+
+    let plain = Index::from_staged(&staged).unwrap();
+    assert_eq!(plain.build_flags(), BuildFlags::empty());
+    assert_eq!(plain.max_file_bytes(), None);
+
+    staged.build_flags = BuildFlags::SKIP_EMPTY | BuildFlags::MAX_FILE_SIZE;
+    staged.max_file_bytes = 1 << 30;
+    let limited = Index::from_staged(&staged).unwrap();
+    assert!(limited.build_flags().contains(BuildFlags::SKIP_EMPTY));
+    assert_eq!(limited.max_file_bytes(), Some(1 << 30));
+}
@@ -0,0 +1,238 @@
+//! Paging a freshly mapped index into memory.
+//!
+//! A large index opened cold faults its pages in one by one as the first
+//! queries touch them. Warming reads one byte per page up front, hot
+//! sections first, so a long-running process such as the daemon pays that
+//! cost before its first query rather than during it.
+//!
+//! Releasing does the opposite, for a process that needs the memory back:
+//! the pages are dropped and read from the file again when next touched.
+//!
+//! Locking goes further than warming for the sections every query starts
+//! from: their pages stay resident for as long as the index is mapped, so
+//! memory pressure cannot evict them.
+
+use std::{
+    hint::black_box,
+    io::{self, Error, ErrorKind},
+    ops::Range,
+};
+
+#[cfg(unix)]
+use memmap2::UncheckedAdvice;
+
+use super::{Index, SectionDesc};
+
+/// Stride used to touch pages. Touching every 4 KiB also faults in every
+/// page of a mapping that uses larger pages.
+const TOUCH_STRIDE: usize = 4096;
+
+/// Which sections [`Index::warm`] pages in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmSet {
+    /// Sections nearly every query reads: key tables, per-file and
+    /// per-directory records, and names.
+    Hot,
+    /// Everything else, mostly posting lists and per-file side tables.
+    Cold,
+}
+
+impl Index {
+    /// Touch every page of the sections in `set`, returning the bytes covered.
+    pub fn warm(&self, set: WarmSet) -> u64 {
+        self.sections(set)
+            .into_iter()
+            .map(|desc| self.touch(desc))
+            .sum()
+    }
+
+    /// Drop the resident pages of the sections in `set`, returning the bytes
+    /// covered. Only supported on Unix; elsewhere nothing is released.
+    pub fn release(&self, set: WarmSet) -> u64 {
+        self.sections(set)
+            .into_iter()
+            .map(|desc| self.release_section(desc))
+            .sum()
+    }
+
+    /// Lock the trigram key tables and per-file records into memory,
+    /// returning the bytes locked. The pages are unlocked when the index is
+    /// dropped.
+    ///
+    /// Fails without locking anything if the sections do not fit within
+    /// `RLIMIT_MEMLOCK`, or on platforms without `mlock`.
+    pub fn lock_in_memory(&self) -> io::Result<u64> {
+        let h = &self.header;
+        let ranges: Vec<Range<usize>> = [
+            h.trigram_keys,
+            h.name_trigram_keys,
+            h.dir_trigram_keys,
+            h.files_meta,
+        ]
+        .into_iter()
+        .map(|desc| self.section_range(desc))
+        .filter(|range| !range.is_empty())
+        .collect();
+        let total: u64 = ranges.iter().map(|r| r.len() as u64).sum();
+
+        for (i, range) in ranges.iter().enumerate() {
+            if let Err(e) = lock_range(&self.bytes[range.clone()]) {
+                for locked in &ranges[..i] {
+                    unlock_range(&self.bytes[locked.clone()]);
+                }
+                return Err(lock_error(e, total));
+            }
+        }
+        Ok(total)
+    }
+
+    fn sections(&self, set: WarmSet) -> Vec<SectionDesc> {
+        let h = &self.header;
+        match set {
+            WarmSet::Hot => vec![
+                h.metadata,
+                h.ext_table,
+                h.dirs,
+                h.files_meta,
+                h.names_blob,
+                h.ext_index_keys,
+                h.trigram_keys,
+                h.dir_trigram_keys,
+                h.name_trigram_keys,
+                h.exact_name_keys,
+                h.dir_paths,
+                h.dir_files,
+            ],
+            WarmSet::Cold => vec![
+                h.ext_index_postings,
+                h.trigram_postings,
+                h.dir_trigram_postings,
+                h.name_trigram_postings,
+                h.exact_name_postings,
+                h.repos,
+                h.file_repos,
+                h.file_hashes,
+                h.dir_file_postings,
+            ],
+        }
+    }
+
+    /// The bytes of `desc` within the mapping.
+    fn section_range(&self, desc: SectionDesc) -> Range<usize> {
+        let start = (desc.offset as usize).min(self.bytes.len());
+        let end = start
+            .saturating_add(desc.len as usize)
+            .min(self.bytes.len());
+        start..end
+    }
+
+    fn touch(&self, desc: SectionDesc) -> u64 {
+        let bytes = &self.bytes[self.section_range(desc)];
+
+        let mut sum = 0u8;
+        for i in (0..bytes.len()).step_by(TOUCH_STRIDE) {
+            sum = sum.wrapping_add(bytes[i]);
+        }
+        black_box(sum);
+        bytes.len() as u64
+    }
+
+    #[cfg(unix)]
+    fn release_section(&self, desc: SectionDesc) -> u64 {
+        let range = self.section_range(desc);
+        if range.is_empty() {
+            return 0;
+        }
+        // Owned bytes have no file to read dropped pages back from.
+        let Some(mmap) = self.bytes.mapping() else {
+            return 0;
+        };
+        // SAFETY: the mapping is a read-only view of the file, so dropped
+        // pages are read back from it unchanged when next touched.
+        let released = unsafe {
+            mmap.unchecked_advise_range(UncheckedAdvice::DontNeed, range.start, range.len())
+        };
+        match released {
+            Ok(()) => range.len() as u64,
+            Err(_) => 0,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn release_section(&self, _desc: SectionDesc) -> u64 {
+        0
+    }
+}
+
+#[cfg(unix)]
+fn lock_range(bytes: &[u8]) -> io::Result<()> {
+    // SAFETY: `bytes` lies within a live mapping; mlock rounds the range
+    // out to whole pages.
+    match unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.len()) } {
+        0 => Ok(()),
+        _ => Err(Error::last_os_error()),
+    }
+}
+
+#[cfg(unix)]
+fn unlock_range(bytes: &[u8]) {
+    // SAFETY: as for `lock_range`.
+    unsafe { libc::munlock(bytes.as_ptr().cast(), bytes.len()) };
+}
+
+#[cfg(not(unix))]
+fn lock_range(_bytes: &[u8]) -> io::Result<()> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "locking memory is not supported on this platform",
+    ))
+}
+
+#[cfg(not(unix))]
+fn unlock_range(_bytes: &[u8]) {}
+
+/// Explain a failed `mlock` of `total` bytes, naming the limit that is
+/// usually the cause.
+fn lock_error(e: Error, total: u64) -> Error {
+    let mib = |bytes: u64| format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0));
+    match (e.kind(), memlock_limit()) {
+        (ErrorKind::OutOfMemory | ErrorKind::PermissionDenied, Some(limit)) => Error::new(
+            e.kind(),
+            format!(
+                "cannot lock {} of index sections in memory: RLIMIT_MEMLOCK is {} \
+                 (raise it with `ulimit -l` or LimitMEMLOCK= in the service unit)",
+                mib(total),
+                mib(limit)
+            ),
+        ),
+        _ => Error::new(
+            e.kind(),
+            format!(
+                "cannot lock {} of index sections in memory: {e}",
+                mib(total)
+            ),
+        ),
+    }
+}
+
+/// The soft `RLIMIT_MEMLOCK`, in bytes, unless unlimited or unknown.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // `rlim_t` is not `u64` everywhere
+fn memlock_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is valid for writes for the call.
+    let rc = unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) };
+    (rc == 0 && limit.rlim_cur != libc::RLIM_INFINITY).then_some(limit.rlim_cur as u64)
+}
+
+#[cfg(not(unix))]
+fn memlock_limit() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+#[path = "warm_tests.rs"]
+mod tests;
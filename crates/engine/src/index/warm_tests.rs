@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+
+use super::WarmSet;
+use crate::{Index, IndexBuilder, QueryPipeline};
+
+#[test]
+fn hot_and_cold_cover_every_section() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..50).map(|i| FileRecord::test_file(root.join(format!("src/m{}/file{i}.rs", i % 5)))),
+    );
+    let index = Index::from_staged(&builder.finish()).unwrap();
+
+    let sections: u64 = index.budget().sections.iter().map(|s| s.bytes).sum();
+    let hot = index.warm(WarmSet::Hot);
+    let cold = index.warm(WarmSet::Cold);
+
+    assert!(hot > 0);
+    assert_eq!(hot + cold, sections);
+}
+
+fn query(index: &Index, q: &str) -> Vec<String> {
+    QueryPipeline::new(index)
+        .parse(q)
+        .execute()
+        .rank_with_limit(Some(100))
+        .iter_with_paths()
+        .map(|(_, _, p)| p)
+        .collect()
+}
+
+#[test]
+fn released_pages_are_read_back_on_demand() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..50).map(|i| FileRecord::test_file(root.join(format!("src/m{}/file{i}.rs", i % 5)))),
+    );
+    let index = Index::from_staged(&builder.finish()).unwrap();
+
+    let before = query(&index, "file1");
+    let released = index.release(WarmSet::Hot) + index.release(WarmSet::Cold);
+
+    if cfg!(unix) {
+        let sections: u64 = index.budget().sections.iter().map(|s| s.bytes).sum();
+        assert_eq!(released, sections);
+    }
+    assert!(!before.is_empty());
+    assert_eq!(query(&index, "file1"), before);
+}
+
+#[cfg(unix)]
+#[test]
+fn locks_key_tables_and_file_records() {
+    let root = PathBuf::from("/data");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(
+        (0..50).map(|i| FileRecord::test_file(root.join(format!("src/m{}/file{i}.rs", i % 5)))),
+    );
+    let index = Index::from_staged(&builder.finish()).unwrap();
+
+    let h = &index.header;
+    let expected =
+        h.trigram_keys.len + h.name_trigram_keys.len + h.dir_trigram_keys.len + h.files_meta.len;
+    assert_eq!(index.lock_in_memory().unwrap(), expected);
+    assert!(!query(&index, "file1").is_empty());
+}
+
+#[test]
+fn lock_errors_name_the_memlock_limit() {
+    let err = super::lock_error(
+        std::io::Error::from(std::io::ErrorKind::OutOfMemory),
+        3 << 20,
+    );
+    let msg = err.to_string();
+    assert!(
+        msg.starts_with("cannot lock 3.0 MiB of index sections"),
+        "{msg}"
+    );
+    if super::memlock_limit().is_some() {
+        assert!(msg.contains("RLIMIT_MEMLOCK"), "{msg}");
+    }
+}
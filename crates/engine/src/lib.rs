@@ -2,12 +2,16 @@ mod dsl;
 mod eval;
 mod index;
 mod pipeline;
+pub mod profile;
 mod query_runner;
 mod trigram;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use dsl::*;
 pub use eval::*;
 pub use index::*;
 pub use pipeline::*;
 pub use pipeline::{PipelineMetrics, to_query_metrics};
-pub use trigram::{Trigram, build_trigrams_for_string};
+pub use query_runner::{EngineQueryHit, EngineQueryResult, QueryScope, hit_kind};
+pub use trigram::{Trigram, build_query_trigrams, build_trigrams_for_string};
@@ -1,13 +1,20 @@
+use std::mem;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use blaze_protocol::QueryMetrics;
+use blaze_fs::native_path;
+use blaze_protocol::{CandidateCounts, QueryMetrics};
+#[cfg(not(target_arch = "wasm32"))]
 use blaze_runtime::history::{HistoryStore, QueryEvent};
+use blaze_runtime::{BoostRules, PinSet, history::QueryOrigin};
 use chrono::{DateTime, Utc};
+#[cfg(not(target_arch = "wasm32"))]
 use log::debug;
 
 use crate::{
-    FileId, IndexReader, Query, QueryEngine, eval::apply_path_order_filter, parse_query, rank,
+    DirAccess, DirSubtree, FileId, IndexReader, PathCache, Query, QueryEngine, RankBoosts,
+    eval::apply_path_order_filter, parse_query, rank_scored,
 };
 /// Shared, state-independent pipeline context.
 struct PipelineCtx<'a, I: IndexReader> {
@@ -19,6 +26,20 @@ struct PipelineCtx<'a, I: IndexReader> {
     query_str: Option<String>,
     /// Root path for history logging.
     root: Option<PathBuf>,
+    /// Process executing the query, recorded in history.
+    origin: QueryOrigin,
+    /// Pinned paths, for `pinned:` and the ranking boost.
+    pins: PinSet,
+    /// Directory boosts for ranking.
+    boosts: BoostRules,
+    /// Paths reconstructed so far, shared by evaluation, ranking and output.
+    paths: PathCache,
+    /// Files to evaluate against instead of the whole index.
+    within: Option<Vec<FileId>>,
+    /// Absolute directory that matches must be under.
+    under: Option<String>,
+    /// Directories the querying user can reach, for shared indexes.
+    access: Option<Arc<DirAccess>>,
     /// Total number of logical results (after path-order filter),
     /// even if we only store the top N ranked results.
     result_total: usize,
@@ -40,7 +61,11 @@ pub struct ExecutedState {
 
 /// Results ranked, ready for consumption.
 pub struct RankedState {
+    /// Every result after the path-order filter, in index order.
+    matched: Vec<FileId>,
     results: Vec<FileId>,
+    /// Relevance score per result; empty if ranking was skipped.
+    scores: Vec<i32>,
 }
 
 /// Stages for which we record timings.
@@ -51,7 +76,7 @@ pub enum Stage {
     Rank,
 }
 
-/// Timing metrics collected during pipeline execution.
+/// Timing and candidate-count metrics collected during pipeline execution.
 #[derive(Debug, Clone, Default)]
 pub struct PipelineMetrics {
     /// Time spent parsing the query string.
@@ -60,6 +85,14 @@ pub struct PipelineMetrics {
     pub exec_time: Option<Duration>,
     /// Time spent ranking results.
     pub rank_time: Option<Duration>,
+    /// Number of files the query started from.
+    pub initial_candidates: Option<usize>,
+    /// Candidates handed to substring verification after trigram filtering.
+    pub trigram_candidates: Option<usize>,
+    /// Candidates that matched the query after verification.
+    pub verified_candidates: Option<usize>,
+    /// Results left after the path-order filter.
+    pub filtered_candidates: Option<usize>,
 }
 
 impl PipelineMetrics {
@@ -80,6 +113,13 @@ pub trait Timer {
     where
         F: FnOnce() -> R;
 
+    /// Record non-timing metrics, such as candidate counts.
+    fn record<F>(&mut self, _f: F)
+    where
+        F: FnOnce(&mut PipelineMetrics),
+    {
+    }
+
     /// Return metrics if timing is enabled.
     fn metrics(&self) -> Option<&PipelineMetrics> {
         None
@@ -137,6 +177,13 @@ impl Timer for MetricsTimer {
         result
     }
 
+    fn record<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PipelineMetrics),
+    {
+        f(&mut self.metrics);
+    }
+
     fn metrics(&self) -> Option<&PipelineMetrics> {
         Some(&self.metrics)
     }
@@ -165,10 +212,17 @@ impl<'a, I: IndexReader> QueryPipeline<'a, I, InitialState, NoopTimer> {
                 now: Utc::now(),
                 query_str: None,
                 root: None,
+                origin: QueryOrigin::Cli,
+                pins: PinSet::default(),
+                boosts: BoostRules::default(),
+                paths: PathCache::default(),
+                within: None,
+                under: None,
+                access: None,
                 result_total: 0,
             },
             state: InitialState,
-            timer: NoopTimer::default(),
+            timer: NoopTimer,
         }
     }
 }
@@ -181,6 +235,13 @@ impl<'a, I: IndexReader> QueryPipeline<'a, I, InitialState, MetricsTimer> {
                 now: Utc::now(),
                 query_str: None,
                 root: None,
+                origin: QueryOrigin::Cli,
+                pins: PinSet::default(),
+                boosts: BoostRules::default(),
+                paths: PathCache::default(),
+                within: None,
+                under: None,
+                access: None,
                 result_total: 0,
             },
             state: InitialState,
@@ -196,6 +257,43 @@ impl<'a, I: IndexReader, S, T: Timer> QueryPipeline<'a, I, S, T> {
         self
     }
 
+    /// Set which process is executing the query, for history logging.
+    pub fn with_origin(mut self, origin: QueryOrigin) -> Self {
+        self.ctx.origin = origin;
+        self
+    }
+
+    /// Set the pinned paths used by `pinned:` and ranking.
+    pub fn with_pins(mut self, pins: PinSet) -> Self {
+        self.ctx.pins = pins;
+        self
+    }
+
+    /// Set the directory boosts used by ranking.
+    pub fn with_boosts(mut self, boosts: BoostRules) -> Self {
+        self.ctx.boosts = boosts;
+        self
+    }
+
+    /// Only evaluate the query against `file_ids`, e.g. an earlier query's
+    /// results.
+    pub fn with_candidates(mut self, file_ids: Vec<FileId>) -> Self {
+        self.ctx.within = Some(file_ids);
+        self
+    }
+
+    /// Only match files at or below the absolute directory `prefix`.
+    pub fn with_under(mut self, prefix: impl Into<String>) -> Self {
+        self.ctx.under = Some(prefix.into());
+        self
+    }
+
+    /// Only match files the user `access` was resolved for could reach.
+    pub fn with_access(mut self, access: Arc<DirAccess>) -> Self {
+        self.ctx.access = Some(access);
+        self
+    }
+
     /// Access timing metrics, if enabled.
     pub fn metrics(&self) -> Option<&PipelineMetrics> {
         self.timer.metrics()
@@ -221,7 +319,7 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, InitialState, T> {
             mut timer,
         } = self;
 
-        let query = timer.measure(Stage::Parse, || parse_query(query_str));
+        let query = timer.measure(Stage::Parse, || parse_query(query_str).rewrite());
         ctx.query_str = Some(query_str.to_string());
 
         QueryPipeline {
@@ -255,17 +353,36 @@ impl<'a, I: IndexReader + Sync, T: Timer> QueryPipeline<'a, I, ParsedState, T> {
     /// Returns matching file IDs (unranked, in index order).
     pub fn execute(self) -> QueryPipeline<'a, I, ExecutedState, T> {
         let QueryPipeline {
-            ctx,
+            mut ctx,
             state: ParsedState { query },
             mut timer,
         } = self;
 
-        let engine = QueryEngine::new(ctx.index);
+        let mut engine = QueryEngine::new(ctx.index)
+            .with_pins(ctx.pins.clone())
+            .with_path_cache(mem::take(&mut ctx.paths));
+        if let Some(within) = ctx.within.take() {
+            engine = engine.with_candidates(within);
+        }
+        if let Some(prefix) = &ctx.under {
+            engine = engine.with_subtree(DirSubtree::resolve(ctx.index, prefix));
+        }
+        if let Some(access) = &ctx.access {
+            engine = engine.with_access(Arc::clone(access));
+        }
 
         // QueryEngine decides how to handle timestamps for predicate evaluation.
         // Ranking uses `ctx.now` separately.
         let hits = timer.measure(Stage::Exec, || engine.eval_query(&query));
 
+        let stats = engine.stats();
+        timer.record(|m| {
+            m.initial_candidates = Some(stats.initial_candidates);
+            m.trigram_candidates = Some(stats.trigram_candidates);
+            m.verified_candidates = Some(stats.verified_candidates);
+        });
+        ctx.paths = engine.into_path_cache();
+
         QueryPipeline {
             ctx,
             state: ExecutedState { query, hits },
@@ -305,17 +422,30 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, ExecutedState, T> {
         } = self;
 
         // Apply path-order filter before ranking.
-        let filtered = apply_path_order_filter(ctx.index, &query, hits);
+        let filtered = apply_path_order_filter(ctx.index, &query, hits, &ctx.paths);
         ctx.result_total = filtered.len();
+        timer.record(|m| m.filtered_candidates = Some(filtered.len()));
 
         let index = ctx.index;
         let now = ctx.now;
+        let boosts = RankBoosts {
+            pins: &ctx.pins,
+            dirs: &ctx.boosts,
+        };
+        let paths = &ctx.paths;
 
-        let ranked = timer.measure(Stage::Rank, || rank(index, &query, &filtered, now, limit));
+        let ranked = timer.measure(Stage::Rank, || {
+            rank_scored(index, &query, &filtered, now, limit, boosts, paths)
+        });
+        let (results, scores) = ranked.into_iter().unzip();
 
         QueryPipeline {
             ctx,
-            state: RankedState { results: ranked },
+            state: RankedState {
+                matched: filtered,
+                results,
+                scores,
+            },
             timer,
         }
     }
@@ -332,10 +462,15 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, ExecutedState, T> {
 
         let results = timer.measure(Stage::Rank, || hits);
         ctx.result_total = results.len();
+        timer.record(|m| m.filtered_candidates = Some(results.len()));
 
         QueryPipeline {
             ctx,
-            state: RankedState { results },
+            state: RankedState {
+                matched: results.clone(),
+                results,
+                scores: Vec::new(),
+            },
             timer,
         }
     }
@@ -362,6 +497,21 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, RankedState, T> {
         &self.state.results
     }
 
+    /// Every result in index order, including those cut off by the limit.
+    pub fn matched(&self) -> &[FileId] {
+        &self.state.matched
+    }
+
+    /// Consume the pipeline and return every result in index order.
+    pub fn into_matched(self) -> Vec<FileId> {
+        self.state.matched
+    }
+
+    /// Relevance score of the result at 0-based position `i`, if ranked.
+    pub fn score(&self, i: usize) -> Option<i32> {
+        self.state.scores.get(i).copied()
+    }
+
     /// Get the total number of results after filtering,
     /// not just the number stored (which may be limited by ranking).
     pub fn count(&self) -> usize {
@@ -374,16 +524,17 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, RankedState, T> {
     }
 
     /// `reconstruct_full_path` may return absolute or root-relative paths.
-    /// If the path is already absolute (starts with `/`), we use it as-is.
-    /// Otherwise we prefix with `/` to display a Unix-style absolute path.
+    /// If the path is already absolute (starts with `/`, or a drive letter
+    /// on Windows), we use it as-is. Otherwise we prefix with `/` to display
+    /// a Unix-style absolute path. Separators are the platform's own.
     pub fn iter_with_paths(&self) -> impl Iterator<Item = (usize, FileId, String)> + '_ {
         self.state.results.iter().enumerate().map(move |(i, &fid)| {
-            let rel_path = self.ctx.index.reconstruct_full_path(fid);
+            let rel_path = self.ctx.paths.take(self.ctx.index, fid);
 
             let display_path = if rel_path.is_empty() {
                 "/".to_string()
-            } else if rel_path.starts_with('/') {
-                rel_path
+            } else if rel_path.starts_with('/') || has_drive_letter(&rel_path) {
+                native_path(&rel_path).into_owned()
             } else {
                 format!("/{}", rel_path)
             };
@@ -403,6 +554,7 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, RankedState, T> {
     ///
     /// This is best-effort: failures are logged but not propagated.
     /// Requires that `parse()` was called (not `with_query()`), otherwise
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn log_history(&self) {
         let Some(query_str) = self.query_str() else {
             debug!("Cannot log history: no query_str (was with_query() used?)");
@@ -425,12 +577,19 @@ impl<'a, I: IndexReader, T: Timer> QueryPipeline<'a, I, RankedState, T> {
             query_str.to_string(),
             self.count(),
             duration_ms.unwrap_or(0),
-        );
+        )
+        .with_origin(self.ctx.origin);
 
         history.log_query(event)
     }
 }
 
+/// Whether `path` starts like `C:/`, an absolute path on Windows.
+fn has_drive_letter(path: &str) -> bool {
+    let b = path.as_bytes();
+    cfg!(windows) && b.len() >= 3 && b[0].is_ascii_alphabetic() && b[1] == b':' && b[2] == b'/'
+}
+
 fn dur_ms(d: std::time::Duration) -> f64 {
     d.as_secs_f64() * 1000.0
 }
@@ -440,5 +599,11 @@ pub fn to_query_metrics(m: &PipelineMetrics) -> QueryMetrics {
         total_ms: dur_ms(m.total()),
         exec_ms: dur_ms(m.exec_time.unwrap()),
         rank_ms: dur_ms(m.rank_time.unwrap()),
+        candidates: CandidateCounts {
+            initial: m.initial_candidates.unwrap_or_default(),
+            trigram: m.trigram_candidates.unwrap_or_default(),
+            verified: m.verified_candidates.unwrap_or_default(),
+            filtered: m.filtered_candidates.unwrap_or_default(),
+        },
     }
 }
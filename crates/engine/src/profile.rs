@@ -0,0 +1,251 @@
+//! Opt-in counters for engine hot paths.
+//!
+//! With `BLAZE_PROFILE=1`, trigram lookups, posting intersections, substring
+//! verification, path reconstruction, heap allocations and reuse of pooled
+//! id buffers are counted and timed per query, and the query runner hands back a [`Profile`]. When
+//! profiling is off every hook is a single relaxed load.
+//!
+//! Counters are thread-local: a query is evaluated on one thread, so
+//! concurrent daemon queries do not mix their numbers.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use blaze_runtime::PROFILE_ENV;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static CURRENT: Cell<Profile> = const { Cell::new(Profile::EMPTY) };
+}
+
+/// An instrumented operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Fetching one trigram's postings.
+    TrigramLookup,
+    /// Intersecting two sorted id lists.
+    Intersect,
+    /// Substring checks of a batch of candidates.
+    Verify,
+    /// Rebuilding a file's full path.
+    PathReconstruct,
+}
+
+impl Op {
+    const ALL: [Op; 4] = [
+        Op::TrigramLookup,
+        Op::Intersect,
+        Op::Verify,
+        Op::PathReconstruct,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Op::TrigramLookup => "trigram lookups",
+            Op::Intersect => "intersections",
+            Op::Verify => "verifications",
+            Op::PathReconstruct => "path rebuilds",
+        }
+    }
+}
+
+/// Totals for one [`Op`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpStats {
+    pub calls: u64,
+    /// Ids processed, where the operation works on a list.
+    pub items: u64,
+    pub time: Duration,
+}
+
+/// Everything counted while profiling one query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Profile {
+    ops: [OpStats; Op::ALL.len()],
+    /// Heap allocations, when the binary installs [`CountingAlloc`].
+    pub allocs: u64,
+    pub alloc_bytes: u64,
+    /// Id buffers taken from the evaluator's pool rather than allocated.
+    pub buffers_reused: u64,
+    /// Id buffers the pool had none free for.
+    pub buffers_new: u64,
+}
+
+impl Profile {
+    const EMPTY: Self = Self {
+        ops: [OpStats {
+            calls: 0,
+            items: 0,
+            time: Duration::ZERO,
+        }; Op::ALL.len()],
+        allocs: 0,
+        alloc_bytes: 0,
+        buffers_reused: 0,
+        buffers_new: 0,
+    };
+
+    pub fn op(&self, op: Op) -> OpStats {
+        self.ops[op as usize]
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "profile:")?;
+        for op in Op::ALL {
+            let stats = self.op(op);
+            writeln!(
+                f,
+                "  {:<16} {:>8} calls {:>10} items {:>10.3} ms",
+                op.label(),
+                stats.calls,
+                stats.items,
+                stats.time.as_secs_f64() * 1000.0,
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<16} {:>8} calls {:>10} bytes",
+            "allocations", self.allocs, self.alloc_bytes
+        )?;
+        write!(
+            f,
+            "  {:<16} {:>8} reused {:>9} new",
+            "id buffers", self.buffers_reused, self.buffers_new
+        )
+    }
+}
+
+/// Turn profiling on if `BLAZE_PROFILE` is `1` or `true`.
+pub fn init_from_env() {
+    let on =
+        std::env::var(PROFILE_ENV).is_ok_and(|val| val == "1" || val.eq_ignore_ascii_case("true"));
+    set_enabled(on);
+}
+
+pub fn set_enabled(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Clear this thread's counters.
+pub fn reset() {
+    CURRENT.with(|p| p.set(Profile::EMPTY));
+}
+
+/// This thread's counters since the last [`reset`], which they are reset to.
+pub fn take() -> Profile {
+    CURRENT.with(|p| p.replace(Profile::EMPTY))
+}
+
+/// Time `op` over `items` ids until the returned guard is dropped.
+#[inline]
+pub fn span(op: Op, items: usize) -> Span {
+    Span {
+        op,
+        items: items as u64,
+        start: enabled().then(Instant::now),
+    }
+}
+
+/// Guard returned by [`span`].
+#[must_use]
+pub struct Span {
+    op: Op,
+    items: u64,
+    start: Option<Instant>,
+}
+
+impl Span {
+    /// Record `items` ids instead, once the count is known.
+    pub fn set_items(&mut self, items: usize) {
+        self.items = items as u64;
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(start) = self.start else { return };
+        let elapsed = start.elapsed();
+        CURRENT.with(|p| {
+            let mut profile = p.get();
+            let stats = &mut profile.ops[self.op as usize];
+            stats.calls += 1;
+            stats.items += self.items;
+            stats.time += elapsed;
+            p.set(profile);
+        });
+    }
+}
+
+/// Count an id buffer handed out by the evaluator's pool, `reused` from an
+/// earlier stage or query or newly allocated.
+#[inline]
+pub fn count_buffer(reused: bool) {
+    if !enabled() {
+        return;
+    }
+    CURRENT.with(|p| {
+        let mut profile = p.get();
+        if reused {
+            profile.buffers_reused += 1;
+        } else {
+            profile.buffers_new += 1;
+        }
+        p.set(profile);
+    });
+}
+
+/// The system allocator, counting allocations while profiling is on.
+///
+/// Install it with `#[global_allocator]` to fill [`Profile::allocs`].
+pub struct CountingAlloc;
+
+impl CountingAlloc {
+    #[inline]
+    fn count(bytes: usize) {
+        if !enabled() {
+            return;
+        }
+        // `try_with`: the allocator also runs while thread-locals are torn down.
+        let _ = CURRENT.try_with(|p| {
+            let mut profile = p.get();
+            profile.allocs += 1;
+            profile.alloc_bytes += bytes as u64;
+            p.set(profile);
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[cfg(test)]
+#[path = "profile_tests.rs"]
+mod tests;
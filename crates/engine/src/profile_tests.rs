@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn spans_count_only_while_enabled() {
+    set_enabled(false);
+    reset();
+    drop(span(Op::Verify, 10));
+    assert_eq!(take(), Profile::default());
+
+    set_enabled(true);
+    drop(span(Op::Verify, 10));
+    drop(span(Op::Verify, 5));
+    drop(span(Op::Intersect, 3));
+    set_enabled(false);
+
+    let profile = take();
+    assert_eq!(profile.op(Op::Verify).calls, 2);
+    assert_eq!(profile.op(Op::Verify).items, 15);
+    assert_eq!(profile.op(Op::Intersect).calls, 1);
+    assert_eq!(profile.op(Op::TrigramLookup), OpStats::default());
+    assert_eq!(take(), Profile::default(), "take resets");
+}
@@ -1,15 +1,27 @@
-use crate::{FileId, Index, PipelineMetrics, QueryPipeline};
+use std::{convert::Infallible, sync::Arc};
+
+use blaze_protocol::HitKind;
+use blaze_runtime::{PinSet, history::QueryOrigin, is_archive_ext, settings};
+
+use crate::{
+    DirAccess, FileId, Index, IndexReader, PipelineMetrics, QueryPipeline, StagedIndex,
+    flags::FileFlags,
+    profile::{self, Profile},
+};
 
 #[derive(Debug, Clone)]
 pub struct EngineQueryHit {
     pub rank: usize,
     pub file_id: FileId,
+    /// Relevance score; only comparable within one query.
+    pub score: i32,
     pub path: String,
+    pub kind: HitKind,
 }
 
 #[derive(Debug, Clone)]
 pub struct EngineQueryResult {
-    /// Top N hits
+    /// Top N hits; empty when they were streamed instead
     pub hits: Vec<EngineQueryHit>,
     /// Total logical hits after ranking and other filters
     pub total: usize,
@@ -17,35 +29,169 @@ pub struct EngineQueryResult {
     pub metrics: Option<PipelineMetrics>,
     /// Normalised query string
     pub query_str: Option<String>,
+    /// Every match in index order, including those past the limit.
+    pub matched: Vec<FileId>,
+    /// Hot-path counters, when profiling is enabled.
+    pub profile: Option<Profile>,
+}
+
+/// Limits on which files a query may match, beyond the query itself.
+#[derive(Debug, Clone, Default)]
+pub struct QueryScope {
+    /// Only these files, e.g. the matches of an earlier query.
+    pub within: Option<Vec<FileId>>,
+    /// Only files at or below this absolute directory.
+    pub under: Option<String>,
+    /// Only files in directories the querying user can reach.
+    pub access: Option<Arc<DirAccess>>,
+    /// Leave the query out of the history, e.g. when it also ran elsewhere.
+    pub skip_history: bool,
 }
 
 impl Index {
-    pub fn run_query(&self, query: &str, limit: usize) -> EngineQueryResult {
-        let pipeline = QueryPipeline::new_timed(self)
-            .parse(query)
-            .execute()
-            .rank_with_limit(Some(limit));
-
-        let total = pipeline.count();
-        let metrics = pipeline.metrics().cloned();
-        let query_str = pipeline.query_str().map(|s| s.to_owned());
-
-        let mut hits = Vec::with_capacity(limit.min(total));
-        for (rank, fid, path) in pipeline.iter_with_paths() {
-            hits.push(EngineQueryHit {
-                rank,
-                file_id: fid,
-                path,
-            });
-        }
+    pub fn run_query(&self, query: &str, limit: usize, origin: QueryOrigin) -> EngineQueryResult {
+        collect_query(self, query, Some(limit), origin, QueryScope::default())
+    }
 
-        pipeline.log_history();
+    /// Run a query over the files allowed by `scope` only; a `limit` of
+    /// `None` returns every match.
+    pub fn run_query_scoped(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        origin: QueryOrigin,
+        scope: QueryScope,
+    ) -> EngineQueryResult {
+        collect_query(self, query, limit, origin, scope)
+    }
+
+    /// Like [`Index::run_query_scoped`], handing each hit to `on_hit` in rank
+    /// order instead of collecting them, and stopping at its first error.
+    pub fn stream_query_scoped<E>(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        origin: QueryOrigin,
+        scope: QueryScope,
+        on_hit: impl FnMut(EngineQueryHit) -> Result<(), E>,
+    ) -> Result<EngineQueryResult, E> {
+        run_query(self, query, limit, origin, scope, on_hit)
+    }
+}
+
+impl StagedIndex {
+    /// Run a query against the in-memory index, without persisting it.
+    pub fn run_query(&self, query: &str, limit: usize, origin: QueryOrigin) -> EngineQueryResult {
+        collect_query(self, query, Some(limit), origin, QueryScope::default())
+    }
+
+    /// Like [`StagedIndex::run_query`], over the files allowed by `scope`.
+    pub fn run_query_scoped(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        origin: QueryOrigin,
+        scope: QueryScope,
+    ) -> EngineQueryResult {
+        collect_query(self, query, limit, origin, scope)
+    }
+
+    /// Like [`Index::stream_query_scoped`], against the in-memory index.
+    pub fn stream_query_scoped<E>(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        origin: QueryOrigin,
+        scope: QueryScope,
+        on_hit: impl FnMut(EngineQueryHit) -> Result<(), E>,
+    ) -> Result<EngineQueryResult, E> {
+        run_query(self, query, limit, origin, scope, on_hit)
+    }
+}
+
+/// Classify a file for result annotations.
+pub fn hit_kind<I: IndexReader>(index: &I, fid: FileId) -> HitKind {
+    let flags = index.get_file_flags(fid);
+    HitKind {
+        dir: flags.contains(FileFlags::IS_DIR),
+        symlink: flags.contains(FileFlags::IS_SYMLINK),
+        trash: flags.contains(FileFlags::IN_TRASH),
+        escaped: flags.contains(FileFlags::ESCAPED_NAME),
+        archive: is_archive_ext(index.get_file_ext(fid)),
+    }
+}
+
+/// Run a query and collect its hits.
+fn collect_query<I: IndexReader + Sync>(
+    index: &I,
+    query: &str,
+    limit: Option<usize>,
+    origin: QueryOrigin,
+    scope: QueryScope,
+) -> EngineQueryResult {
+    let mut hits = Vec::new();
+    let Ok(mut result) = run_query(index, query, limit, origin, scope, |hit| {
+        hits.push(hit);
+        Ok::<_, Infallible>(())
+    });
+    result.hits = hits;
+    result
+}
+
+fn run_query<I: IndexReader + Sync, E>(
+    index: &I,
+    query: &str,
+    limit: Option<usize>,
+    origin: QueryOrigin,
+    scope: QueryScope,
+    mut on_hit: impl FnMut(EngineQueryHit) -> Result<(), E>,
+) -> Result<EngineQueryResult, E> {
+    if profile::enabled() {
+        profile::reset();
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let skip_history = scope.skip_history;
+    let mut pipeline = QueryPipeline::new_timed(index)
+        .with_origin(origin)
+        .with_pins(PinSet::load())
+        .with_boosts(settings().boost.clone());
+    if let Some(within) = scope.within {
+        pipeline = pipeline.with_candidates(within);
+    }
+    if let Some(under) = scope.under {
+        pipeline = pipeline.with_under(under);
+    }
+    if let Some(access) = scope.access {
+        pipeline = pipeline.with_access(access);
+    }
+    let pipeline = pipeline.parse(query).execute().rank_with_limit(limit);
+
+    let total = pipeline.count();
+    let metrics = pipeline.metrics().cloned();
+    let query_str = pipeline.query_str().map(|s| s.to_owned());
 
-        EngineQueryResult {
-            hits,
-            total,
-            metrics,
-            query_str,
-        }
+    for (rank, fid, path) in pipeline.iter_with_paths() {
+        on_hit(EngineQueryHit {
+            rank,
+            file_id: fid,
+            score: pipeline.score(rank - 1).unwrap_or(0),
+            path,
+            kind: hit_kind(index, fid),
+        })?;
     }
+
+    let profile = profile::enabled().then(profile::take);
+    #[cfg(not(target_arch = "wasm32"))]
+    if !skip_history {
+        pipeline.log_history();
+    }
+
+    Ok(EngineQueryResult {
+        hits: Vec::new(),
+        total,
+        metrics,
+        query_str,
+        matched: pipeline.into_matched(),
+        profile,
+    })
 }
@@ -160,7 +160,7 @@ pub fn build_query_trigrams(text: &str) -> Vec<Trigram> {
 
     // Add the last trigram if we didn't cover the end.
     // This ensures suffix coverage, e.g. "abcdefgh" → [abc, def, fgh].
-    if bytes.len() > 3 && bytes.len() % 3 != 0 {
+    if bytes.len() > 3 && !bytes.len().is_multiple_of(3) {
         let last_pos = bytes.len() - 3;
         let last_tri =
             Trigram::from_bytes(bytes[last_pos], bytes[last_pos + 1], bytes[last_pos + 2]);
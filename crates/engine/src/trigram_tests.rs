@@ -1,3 +1,5 @@
+use proptest::prelude::*;
+
 use super::*;
 
 fn assert_sorted_trigrams(tris: &[Trigram]) {
@@ -111,8 +113,8 @@ fn build_trigrams_for_string_short_inputs_produce_empty() {
 #[test]
 fn build_trigrams_for_bytes_short_inputs_produce_empty() {
     assert!(build_trigrams_for_bytes(&[]).is_empty());
-    assert!(build_trigrams_for_bytes(&[b'a']).is_empty());
-    assert!(build_trigrams_for_bytes(&[b'a', b'b']).is_empty());
+    assert!(build_trigrams_for_bytes(b"a").is_empty());
+    assert!(build_trigrams_for_bytes(b"ab").is_empty());
 }
 
 #[test]
@@ -266,3 +268,41 @@ fn query_trigrams_are_subset_of_full_trigrams() {
         }
     }
 }
+
+proptest! {
+    #[test]
+    fn query_trigrams_are_always_indexed_trigrams(s in any::<String>()) {
+        let full = build_trigrams_for_string(&s);
+        for tri in build_query_trigrams(&s) {
+            prop_assert!(full.binary_search(&tri).is_ok(), "{tri:?} of {s:?}");
+        }
+    }
+
+    // What the evaluator relies on: a name containing the term has every
+    // trigram the term is looked up by.
+    #[test]
+    fn names_containing_a_term_have_its_query_trigrams(
+        prefix in any::<String>(),
+        term in any::<String>(),
+        suffix in any::<String>(),
+    ) {
+        let name = format!("{prefix}{term}{suffix}");
+        let indexed = build_trigrams_for_bytes(name.as_bytes());
+        assert_sorted_trigrams(&indexed);
+        for tri in build_query_trigrams(&term) {
+            prop_assert!(indexed.binary_search(&tri).is_ok(), "{tri:?} of {term:?} in {name:?}");
+        }
+    }
+
+    #[test]
+    fn trigrams_do_not_depend_on_ascii_case(s in any::<String>()) {
+        prop_assert_eq!(
+            build_trigrams_for_string(&s.to_ascii_uppercase()),
+            build_trigrams_for_string(&s.to_ascii_lowercase())
+        );
+        prop_assert_eq!(
+            build_query_trigrams(&s.to_ascii_uppercase()),
+            build_query_trigrams(&s.to_ascii_lowercase())
+        );
+    }
+}
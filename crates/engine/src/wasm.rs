@@ -0,0 +1,174 @@
+//! Queries over an exported index bundle, for web-based index viewers.
+//!
+//! Built with the `wasm` feature, the engine exports a small C ABI that a
+//! browser tool can drive after loading the module: it copies a bundle
+//! written by `blaze index export` into module memory, opens it, and runs
+//! queries against it client-side, so a searchable snapshot of a tree can
+//! be shared as a single file.
+//!
+//! ```text
+//! ptr = blaze_alloc(len)              // copy the bundle to ptr..ptr+len
+//! index = blaze_open_bundle(ptr, len) // null if the bundle is invalid
+//! blaze_free(ptr, len)
+//! ptr = blaze_alloc(len)              // copy the UTF-8 query
+//! blaze_query(index, ptr, len, limit) // -1 if the query is not UTF-8
+//! blaze_free(ptr, len)
+//! read blaze_output_len(index) bytes at blaze_output_ptr(index)
+//! blaze_close(index)
+//! ```
+//!
+//! Query output is UTF-8 text: a first line holding the total number of
+//! matches, then one `score\tpath` line per hit, best first.
+//!
+//! Nothing here maps files, so the module needs no filesystem to search a
+//! bundle. Queries are left out of the query history.
+
+use std::{fmt::Write as _, io, slice};
+
+use blaze_runtime::history::QueryOrigin;
+use log::warn;
+
+use crate::{Index, QueryScope};
+
+/// An index opened from a bundle, with the output of its last query.
+pub struct WasmIndex {
+    index: Index,
+    output: String,
+}
+
+impl WasmIndex {
+    /// Open the index in the bundle held in `bundle`.
+    pub fn open(bundle: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            index: Index::from_bundle(bundle)?,
+            output: String::new(),
+        })
+    }
+
+    /// Run `query` for up to `limit` hits, returning its output.
+    pub fn query(&mut self, query: &str, limit: usize) -> &str {
+        let scope = QueryScope {
+            skip_history: true,
+            ..QueryScope::default()
+        };
+        let result = self
+            .index
+            .run_query_scoped(query, Some(limit), QueryOrigin::Cli, scope);
+
+        self.output.clear();
+        let _ = writeln!(self.output, "{}", result.total);
+        for hit in &result.hits {
+            let _ = writeln!(self.output, "{}\t{}", hit.score, hit.path);
+        }
+        &self.output
+    }
+
+    /// Output of the last query.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+}
+
+/// Allocate `len` bytes for the host to copy a bundle or query into.
+#[unsafe(no_mangle)]
+pub extern "C" fn blaze_alloc(len: usize) -> *mut u8 {
+    let mut buf = Vec::<u8>::with_capacity(len);
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}
+
+/// Free `len` bytes allocated by [`blaze_alloc`].
+///
+/// # Safety
+///
+/// `ptr` must come from `blaze_alloc(len)` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_free(ptr: *mut u8, len: usize) {
+    // SAFETY: the caller passes back an allocation of `len` bytes from
+    // `blaze_alloc`, which is a `Vec<u8>` with that capacity.
+    drop(unsafe { Vec::from_raw_parts(ptr, 0, len) });
+}
+
+/// Open the bundle in `ptr..ptr+len`. The bytes are copied, so they may
+/// be freed afterwards. Returns null if the bundle is invalid.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` initialized bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_open_bundle(ptr: *const u8, len: usize) -> *mut WasmIndex {
+    // SAFETY: guaranteed by the caller.
+    let bundle = unsafe { slice::from_raw_parts(ptr, len) };
+    match WasmIndex::open(bundle) {
+        Ok(index) => Box::into_raw(Box::new(index)),
+        Err(e) => {
+            warn!("Failed to open bundle: {e}");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Close an index opened by [`blaze_open_bundle`].
+///
+/// # Safety
+///
+/// `index` must come from `blaze_open_bundle` and not have been closed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_close(index: *mut WasmIndex) {
+    if !index.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe { Box::from_raw(index) });
+    }
+}
+
+/// Run the UTF-8 query in `ptr..ptr+len` for up to `limit` hits. Returns
+/// `0`, or `-1` if the query is not UTF-8.
+///
+/// # Safety
+///
+/// `index` must be an open index and `ptr` must point to `len`
+/// initialized bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_query(
+    index: *mut WasmIndex,
+    ptr: *const u8,
+    len: usize,
+    limit: usize,
+) -> i32 {
+    // SAFETY: guaranteed by the caller.
+    let (index, query) = unsafe { (&mut *index, slice::from_raw_parts(ptr, len)) };
+    match std::str::from_utf8(query) {
+        Ok(query) => {
+            index.query(query, limit);
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Start of the output of the last query on `index`.
+///
+/// # Safety
+///
+/// `index` must be an open index.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_output_ptr(index: *const WasmIndex) -> *const u8 {
+    // SAFETY: guaranteed by the caller.
+    unsafe { &*index }.output().as_ptr()
+}
+
+/// Length of the output of the last query on `index`.
+///
+/// # Safety
+///
+/// `index` must be an open index.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blaze_output_len(index: *const WasmIndex) -> usize {
+    // SAFETY: guaranteed by the caller.
+    unsafe { &*index }.output().len()
+}
+
+#[cfg(test)]
+#[path = "wasm_tests.rs"]
+mod tests;
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use blaze_fs::FileRecord;
+use tempfile::tempdir;
+
+use super::*;
+use crate::{IndexBuilder, export_bundle, write_index_atomic};
+
+fn bundle(rels: &[&str]) -> Vec<u8> {
+    let root = PathBuf::from("/release");
+    let mut builder = IndexBuilder::new(root.clone());
+    builder.add_batch(rels.iter().map(|rel| FileRecord::test_file(root.join(rel))));
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("index.bin");
+    write_index_atomic(&path, &builder.finish(), 0).unwrap();
+    let bundle = dir.path().join("release.blz");
+    export_bundle(&path, &bundle, true).unwrap();
+    std::fs::read(bundle).unwrap()
+}
+
+#[test]
+fn queries_a_bundle() {
+    let bytes = bundle(&["bin/blaze", "share/doc/blaze/README.md", "lib/libz.so"]);
+    let mut index = WasmIndex::open(&bytes).unwrap();
+
+    let output = index.query("blaze", 10).to_string();
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("2"));
+    let mut paths: Vec<&str> = lines
+        .map(|line| {
+            let (score, path) = line.split_once('\t').unwrap();
+            score.parse::<i32>().unwrap();
+            path
+        })
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["/release/bin/blaze", "/release/share/doc/blaze/README.md"]
+    );
+
+    assert_eq!(index.query("blaze", 1).lines().count(), 2);
+    assert_eq!(index.query("nothing-like-this", 10), "0\n");
+}
+
+#[test]
+fn c_abi_round_trip() {
+    let bytes = bundle(&["lib/libz.so"]);
+    unsafe {
+        let ptr = blaze_alloc(bytes.len());
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+        let index = blaze_open_bundle(ptr, bytes.len());
+        blaze_free(ptr, bytes.len());
+        assert!(!index.is_null());
+
+        let query = b"libz";
+        assert_eq!(blaze_query(index, query.as_ptr(), query.len(), 10), 0);
+        let output = slice::from_raw_parts(blaze_output_ptr(index), blaze_output_len(index));
+        assert!(output.starts_with(b"1\n"));
+        assert!(output.ends_with(b"\t/release/lib/libz.so\n"));
+
+        assert_eq!(blaze_query(index, [0xff].as_ptr(), 1, 10), -1);
+        blaze_close(index);
+
+        assert!(blaze_open_bundle(b"junk".as_ptr(), 4).is_null());
+    }
+}
@@ -1,3 +1,8 @@
+use std::time::Duration;
+
 /// Batch size for sending records through the channel.
 /// Larger batches reduce channel overhead but increase latency.
 pub const BATCH_SIZE: usize = 64;
+
+/// How often the walker checks for workers stuck on a directory.
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_millis(100);
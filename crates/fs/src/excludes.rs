@@ -192,6 +192,12 @@ impl UserExcludes {
         self.roots.push(root);
     }
 
+    /// Effective exclusion roots, after canonicalization and collapsing
+    /// roots that lie below another.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
     #[inline]
     pub fn is_excluded(&self, path: &Path) -> bool {
         self.roots.iter().any(|root| path.starts_with(root))
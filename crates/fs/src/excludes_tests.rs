@@ -70,7 +70,7 @@ fn ignore_engine_respects_extra_ignore_files() {
 
     let ignore_path = root.join(".blazeignore");
     {
-        let mut f: _ = std::fs::File::create(&ignore_path).expect("create ignore file");
+        let mut f = std::fs::File::create(&ignore_path).expect("create ignore file");
         writeln!(f, "foo").unwrap();
         writeln!(f, "bar/").unwrap();
     }
@@ -171,6 +171,17 @@ fn user_excludes_add_root_ignores_child_when_parent_already_present() {
     );
 }
 
+#[test]
+fn user_excludes_roots_lists_effective_roots() {
+    let mut ux = UserExcludes::new(Vec::new());
+
+    ux.add_root(PathBuf::from("root/sub"));
+    ux.add_root(PathBuf::from("other"));
+    ux.add_root(PathBuf::from("root"));
+
+    assert_eq!(ux.roots(), [PathBuf::from("other"), PathBuf::from("root")]);
+}
+
 #[test]
 fn user_excludes_with_system_defaults_covers_configured_prefixes_with_canonicalization() {
     let ux = UserExcludes::with_system_defaults();
@@ -0,0 +1,76 @@
+//! Generated trees, recognised by the entries next to them.
+//!
+//! Component-name lists (see `blaze_runtime::NoiseComponents`) catch the
+//! usual build and cache directories by name alone. A marker in the
+//! directory listing is stronger evidence, and catches trees whose names
+//! say nothing:
+//!
+//! - a directory holding a valid `CACHEDIR.TAG` file is a cache, so
+//!   everything below it is generated (or, under [`CachePolicy::Skip`],
+//!   left out of the walk);
+//! - `node_modules` next to a lockfile (`yarn.lock`, `package-lock.json`,
+//!   ...) is installed packages, as is `target` next to `Cargo.lock`;
+//! - `packages` next to a `.nuget` directory holds restored NuGet packages.
+//!
+//! [`CachePolicy::Skip`]: crate::CachePolicy::Skip
+
+use std::{fs::File, io::Read, path::Path};
+
+use crate::record::FileRecord;
+
+/// File marking its directory as a cache, see <https://bford.info/cachedir/>.
+pub const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+
+/// What a valid [`CACHEDIR_TAG`] starts with.
+const CACHEDIR_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Whether the file at `path` is a valid cache directory tag: one that
+/// starts with the signature the standard prescribes. A file that merely
+/// has the name is not enough.
+pub fn is_cachedir_tag(path: &Path) -> bool {
+    let mut head = [0; CACHEDIR_SIGNATURE.len()];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut head))
+        .is_ok_and(|()| head == CACHEDIR_SIGNATURE)
+}
+
+/// Whether a directory listing holds a valid [`CACHEDIR_TAG`], making the
+/// directory a cache.
+pub(crate) fn has_cachedir_tag(records: &[FileRecord]) -> bool {
+    records
+        .iter()
+        .any(|r| !r.is_dir && r.name == CACHEDIR_TAG && is_cachedir_tag(&r.full_path))
+}
+
+/// Set [`FileRecord::generated`] on the entries of one directory listing.
+///
+/// `parent_generated` is whether the listed directory itself lies in a
+/// generated tree or is a cache; then every entry does.
+pub(crate) fn mark_generated(records: &mut [FileRecord], parent_generated: bool) {
+    let siblings: Vec<&'static str> = records
+        .iter()
+        .filter_map(|r| generated_sibling(&r.name, r.is_dir))
+        .collect();
+
+    for record in records {
+        record.generated =
+            parent_generated || (record.is_dir && siblings.contains(&record.name.as_str()));
+    }
+}
+
+/// The directory that an entry named `name` marks as generated when it
+/// sits next to it.
+fn generated_sibling(name: &str, is_dir: bool) -> Option<&'static str> {
+    match name {
+        ".nuget" if is_dir => Some("packages"),
+        _ if is_dir => None,
+        "Cargo.lock" => Some("target"),
+        "package-lock.json" | "pnpm-lock.yaml" => Some("node_modules"),
+        n if n.ends_with(".lock") => Some("node_modules"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "generated_tests.rs"]
+mod tests;
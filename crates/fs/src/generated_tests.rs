@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use super::*;
+
+fn entry(name: &str, is_dir: bool) -> FileRecord {
+    let path = Path::new("/p").join(name);
+    if is_dir {
+        FileRecord::test_dir(path)
+    } else {
+        FileRecord::test_file(path)
+    }
+}
+
+fn marked(mut records: Vec<FileRecord>, parent_generated: bool) -> Vec<String> {
+    mark_generated(&mut records, parent_generated);
+    records
+        .into_iter()
+        .filter(|r| r.generated)
+        .map(|r| r.name)
+        .collect()
+}
+
+#[test]
+fn cachedir_tags_need_the_signature() {
+    let tmp = tempfile::tempdir().unwrap();
+    let tag = |contents: &[u8]| {
+        let path = tmp.path().join(CACHEDIR_TAG);
+        std::fs::write(&path, contents).unwrap();
+        FileRecord::test_file(path)
+    };
+
+    let valid = tag(b"Signature: 8a477f597d28d172789f06886806bc55\n# a cache\n");
+    assert!(has_cachedir_tag(&[entry("index", false), valid]));
+
+    assert!(!has_cachedir_tag(&[tag(b"Signature: 0000")]));
+    assert!(!has_cachedir_tag(&[tag(b"")]));
+    // Neither a directory with the name nor a tag that cannot be read.
+    assert!(!has_cachedir_tag(&[entry(CACHEDIR_TAG, true)]));
+    assert!(!has_cachedir_tag(&[entry(CACHEDIR_TAG, false)]));
+}
+
+#[test]
+fn lockfiles_mark_their_dependency_directories() {
+    let listing = vec![
+        entry("yarn.lock", false),
+        entry("Cargo.lock", false),
+        entry("node_modules", true),
+        entry("target", true),
+        entry("src", true),
+    ];
+    assert_eq!(marked(listing, false), ["node_modules", "target"]);
+}
+
+#[test]
+fn dependency_directories_need_their_marker() {
+    let listing = vec![
+        entry("package.json", false),
+        entry("node_modules", true),
+        entry("target", true),
+        entry("packages", true),
+    ];
+    assert!(marked(listing, false).is_empty());
+
+    let listing = vec![entry(".nuget", true), entry("packages", true)];
+    assert_eq!(marked(listing, false), ["packages"]);
+
+    // A file cannot stand in for the `.nuget` directory.
+    let listing = vec![entry(".nuget", false), entry("packages", true)];
+    assert!(marked(listing, false).is_empty());
+}
+
+#[test]
+fn generated_parents_mark_every_entry() {
+    let listing = vec![entry("a.txt", false), entry("sub", true)];
+    assert_eq!(marked(listing, true), ["a.txt", "sub"]);
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    io::{self, Result},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    thread,
+};
+
+use crossbeam::channel::{self, Sender};
+use log::debug;
+
+use crate::{
+    config::BATCH_SIZE,
+    record::FileRecord,
+    walker::{ScanContext, WalkSummary, inspect_path},
+};
+
+/// List the files git knows about under `root` and inspect each of them.
+///
+/// Tracked files and untracked files that are not gitignored are included,
+/// so the result matches what `git status` considers part of the tree.
+/// Records go through the same inspection as [`walk_parallel`](crate::walk_parallel);
+/// directories are not reported, except the `.git` entries of the repository
+/// and its submodules so repository detection keeps working.
+pub fn walk_git_files(
+    root: &Path,
+    file_tx: Sender<Vec<FileRecord>>,
+    ctx: Arc<ScanContext>,
+    num_threads: usize,
+) -> Result<WalkSummary> {
+    let mut paths = git_ls_files(root)?;
+    paths.extend(repo_markers(root, &paths));
+
+    debug!(
+        "[walk_git_files] inspecting {} paths with {} threads",
+        paths.len(),
+        num_threads
+    );
+
+    let (work_tx, work_rx) = channel::unbounded::<&[PathBuf]>();
+    for chunk in paths.chunks(BATCH_SIZE) {
+        let _ = work_tx.send(chunk);
+    }
+    drop(work_tx);
+
+    thread::scope(|s| {
+        for _ in 0..num_threads.max(1) {
+            let work_rx = work_rx.clone();
+            let file_tx = file_tx.clone();
+            let ctx = Arc::clone(&ctx);
+
+            s.spawn(move || {
+                for chunk in work_rx {
+                    let batch: Vec<FileRecord> = chunk
+                        .iter()
+                        .filter_map(|path| match inspect_path(path, &ctx) {
+                            Ok(rec) => rec,
+                            // Tracked but deleted in the working tree.
+                            Err(e) => {
+                                debug!("[walk_git_files] inspect_path({:?}) failed: {e}", path);
+                                None
+                            }
+                        })
+                        .collect();
+                    if !batch.is_empty() && file_tx.send(batch).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(WalkSummary::default())
+}
+
+/// Absolute paths from `git ls-files` run in `root`.
+fn git_ls_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run git: {e}")))?;
+
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(io::Error::other(format!(
+            "git ls-files failed in {}: {}",
+            root.display(),
+            stderr.trim()
+        )));
+    }
+
+    Ok(parse_ls_files(root, &out.stdout))
+}
+
+/// Split NUL-terminated `ls-files` output into absolute paths.
+///
+/// Unmerged files are listed once per conflict stage, so duplicates are dropped.
+pub(crate) fn parse_ls_files(root: &Path, stdout: &[u8]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    stdout
+        .split(|&b| b == 0)
+        .filter(|rel| !rel.is_empty())
+        .filter(|rel| seen.insert(*rel))
+        .filter_map(|rel| Some(root.join(rel_path(rel)?)))
+        .collect()
+}
+
+/// A path as `ls-files` prints it. Names that are not UTF-8 are kept on
+/// Unix, where paths are bytes, and skipped elsewhere.
+#[cfg(unix)]
+fn rel_path(rel: &[u8]) -> Option<&Path> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(Path::new(std::ffi::OsStr::from_bytes(rel)))
+}
+
+#[cfg(not(unix))]
+fn rel_path(rel: &[u8]) -> Option<&Path> {
+    match std::str::from_utf8(rel) {
+        Ok(rel) => Some(Path::new(rel)),
+        Err(_) => {
+            log::warn!(
+                "[walk_git_files] skipping non UTF-8 path {:?}",
+                String::from_utf8_lossy(rel)
+            );
+            None
+        }
+    }
+}
+
+/// `.git` entries for the repository rooted at `root`, if it is one, and
+/// for submodules, which `ls-files` lists as directories.
+fn repo_markers(root: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut markers: Vec<PathBuf> = paths
+        .iter()
+        .filter(|p| p.is_dir())
+        .map(|p| p.join(".git"))
+        .filter(|git| git.exists())
+        .collect();
+
+    let git = root.join(".git");
+    if git.exists() {
+        markers.push(git);
+    }
+
+    markers
+}
+
+#[cfg(test)]
+#[path = "gitfiles_tests.rs"]
+mod tests;
@@ -0,0 +1,85 @@
+use super::*;
+
+use std::{
+    fs::{create_dir, write},
+    time::Duration,
+};
+
+use blaze_runtime::DIR_TIMEOUT_SECS;
+
+use crate::{
+    excludes::{IgnoreEngine, TrashConfig, UserExcludes},
+    walker::{CachePolicy, NetworkOptions},
+};
+
+fn default_ctx() -> Arc<ScanContext> {
+    Arc::new(ScanContext {
+        trash: TrashConfig::default(),
+        ignore: IgnoreEngine::default(),
+        user_excludes: UserExcludes::default(),
+        hash_max_bytes: None,
+        dir_timeout: Duration::from_secs(DIR_TIMEOUT_SECS),
+        network: NetworkOptions::default(),
+        caches: CachePolicy::default(),
+    })
+}
+
+fn git(root: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn parse_ls_files_splits_on_nul_and_drops_duplicates() {
+    let root = Path::new("/repo");
+    let out = b"a.rs\0src/b.rs\0src/b.rs\0dir with space/c\0";
+
+    assert_eq!(
+        parse_ls_files(root, out),
+        [
+            PathBuf::from("/repo/a.rs"),
+            PathBuf::from("/repo/src/b.rs"),
+            PathBuf::from("/repo/dir with space/c"),
+        ]
+    );
+    assert!(parse_ls_files(root, b"").is_empty());
+}
+
+#[test]
+fn walk_git_files_lists_tracked_and_unignored_files() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path();
+
+    git(root, &["init", "-q"]);
+    write(root.join(".gitignore"), b"target/\n*.log\n").expect("write .gitignore");
+    write(root.join("tracked.rs"), b"fn main() {}").expect("write tracked.rs");
+    write(root.join("debug.log"), b"noise").expect("write debug.log");
+    create_dir(root.join("src")).expect("create src");
+    write(root.join("src/untracked.rs"), b"").expect("write untracked.rs");
+    create_dir(root.join("target")).expect("create target");
+    write(root.join("target/out.bin"), b"").expect("write out.bin");
+    git(root, &["add", "tracked.rs"]);
+
+    let (tx, rx) = channel::unbounded();
+    walk_git_files(root, tx, default_ctx(), 2).expect("walk_git_files");
+
+    let mut names: Vec<String> = rx.into_iter().flatten().map(|r| r.name).collect();
+    names.sort();
+
+    // `.git` is reported so the repository is still detected.
+    assert_eq!(names, [".git", ".gitignore", "tracked.rs", "untracked.rs"]);
+}
+
+#[test]
+fn walk_git_files_fails_outside_a_repository() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let (tx, _rx) = channel::unbounded();
+
+    let err = walk_git_files(tmp.path(), tx, default_ctx(), 1).unwrap_err();
+    assert!(err.to_string().contains("git ls-files failed"), "{err}");
+}
@@ -1,9 +1,35 @@
+#[cfg(feature = "native")]
 mod config;
+#[cfg(feature = "native")]
 mod excludes;
+#[cfg(feature = "native")]
+mod generated;
+#[cfg(feature = "native")]
+mod gitfiles;
 mod helpers;
+mod netfs;
 mod record;
+mod root;
+#[cfg(feature = "native")]
 mod walker;
+#[cfg(feature = "native")]
+mod watcher;
+pub mod winpath;
 
+#[cfg(feature = "native")]
 pub use excludes::{IgnoreEngine, TrashConfig, UserExcludes};
-pub use record::FileRecord;
-pub use walker::{ScanContext, walk_parallel};
+#[cfg(feature = "native")]
+pub use generated::{CACHEDIR_TAG, is_cachedir_tag};
+#[cfg(feature = "native")]
+pub use gitfiles::walk_git_files;
+pub use netfs::is_network_fs;
+pub use record::{FilePerms, FileRecord, escape_name};
+pub use root::{canonical_root, clean_path};
+#[cfg(feature = "native")]
+pub use walker::{
+    CachePolicy, NetworkOptions, NetworkPolicy, ScanBackend, ScanContext, WalkSummary,
+    content_hash, inspect_path, list_dir, should_recurse, walk_parallel,
+};
+#[cfg(feature = "native")]
+pub use watcher::{ChangeKind, FsEvent, Watcher};
+pub use winpath::{long_path, native_path, normalize_root, portable_path};
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+/// `statfs` magic numbers of network filesystems (see `statfs(2)`).
+#[cfg(target_os = "linux")]
+const NETWORK_FS_MAGICS: &[(i64, &str)] = &[
+    (0x6969, "nfs"),
+    (0x517b, "smb"),
+    (0xfe53_4d42, "smb2"),
+    (0xff53_4d42, "cifs"),
+    (0x5346_414f, "afs"),
+    (0x0000_6b41, "kafs"),
+    (0x00c3_6400, "ceph"),
+    (0x7375_7245, "coda"),
+    (0x0102_1997, "9p"),
+    (0x0bd0_0bd0, "lustre"),
+    (0x0116_1970, "gfs2"),
+    (0x7461_636f, "ocfs2"),
+];
+
+/// Name of the network filesystem with this `statfs` magic, if it is one.
+#[cfg(target_os = "linux")]
+pub(crate) fn network_fs_name(magic: i64) -> Option<&'static str> {
+    NETWORK_FS_MAGICS
+        .iter()
+        .find(|&&(m, _)| m == magic)
+        .map(|&(_, name)| name)
+}
+
+/// Whether `path` is on a network filesystem such as NFS or SMB.
+///
+/// Returns `false` if the filesystem cannot be queried. Only Linux is
+/// detected; elsewhere every filesystem counts as local.
+#[cfg(target_os = "linux")]
+pub fn is_network_fs(path: &Path) -> bool {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut buf = MaybeUninit::<libc::statfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `buf` is large enough for a `statfs`.
+    if unsafe { libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) } != 0 {
+        return false;
+    }
+    // SAFETY: `statfs` returned 0, so it filled in `buf`.
+    let stat = unsafe { buf.assume_init() };
+    // The width and signedness of `f_type` differ between targets.
+    #[allow(clippy::unnecessary_cast)]
+    let magic = stat.f_type as i64;
+    network_fs_name(magic).is_some()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_network_fs(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+#[path = "netfs_tests.rs"]
+mod tests;
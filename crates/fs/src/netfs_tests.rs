@@ -0,0 +1,18 @@
+use super::*;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn network_magics_are_recognised() {
+    assert_eq!(network_fs_name(0x6969), Some("nfs"));
+    assert_eq!(network_fs_name(0xff53_4d42), Some("cifs"));
+    // ext4 and tmpfs are local.
+    assert_eq!(network_fs_name(0xef53), None);
+    assert_eq!(network_fs_name(0x0102_1994), None);
+}
+
+#[test]
+fn local_directories_are_not_network_fs() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    assert!(!is_network_fs(tmp.path()));
+    assert!(!is_network_fs(&tmp.path().join("missing")));
+}
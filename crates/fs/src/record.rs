@@ -1,9 +1,11 @@
-use std::path::PathBuf;
+use std::{borrow::Cow, ffi::OsStr, fmt::Write, path::PathBuf};
 
-#[derive(Debug)]
+use blaze_runtime::LangId;
+
+#[derive(Debug, Clone)]
 pub struct FileRecord {
     pub full_path: PathBuf,
-    /// File name
+    /// File name, with bytes that are not UTF-8 escaped (see [`escape_name`])
     pub name: String,
     /// File size
     pub size: u64,
@@ -13,8 +15,12 @@ pub struct FileRecord {
     pub ctime_secs: u64,
     /// File last accessed time (may be unavailable on some platforms/mount options)
     pub atime_secs: u64,
-    /// Lowercase extension without dot e.g., 'pdf'
+    /// Normalized extension without dot e.g., 'pdf' (see `blaze_runtime::normalize_ext`)
     pub ext: Option<String>,
+    /// Language from the extension, or the `#!` line of extension-less scripts
+    pub lang: Option<LangId>,
+    /// Content hash (see `content_hash`), if hashing was enabled and the file is small enough
+    pub content_hash: Option<u64>,
     /// Visibility and exclusions
     pub is_dir: bool,
     pub is_symlink: bool,
@@ -23,4 +29,89 @@ pub struct FileRecord {
     pub ignored_glob: bool,
     pub hidden_os: bool,
     pub user_excludes: bool,
+    /// In a tree that markers next to it show to be generated, such as a
+    /// directory holding `CACHEDIR.TAG` (see [`CACHEDIR_TAG`](crate::CACHEDIR_TAG))
+    pub generated: bool,
+    /// Owner and permission bits; `None` where the platform has none
+    pub perms: Option<FilePerms>,
 }
+
+#[cfg(any(test, feature = "test-support"))]
+impl FileRecord {
+    /// A regular file at `path` for tests: name and extension taken from the
+    /// path, one byte long, every timestamp `1_700_000_000`, and no flags set.
+    pub fn test_file(path: impl Into<PathBuf>) -> Self {
+        let full_path = path.into();
+        let name = full_path
+            .file_name()
+            .map(|name| escape_name(name).into_owned())
+            .unwrap_or_default();
+        let ext = (full_path.extension())
+            .and_then(OsStr::to_str)
+            .and_then(blaze_runtime::normalize_ext);
+        Self {
+            full_path,
+            name,
+            size: 1,
+            mtime_secs: 1_700_000_000,
+            ctime_secs: 1_700_000_000,
+            atime_secs: 1_700_000_000,
+            ext,
+            lang: None,
+            content_hash: None,
+            is_dir: false,
+            is_symlink: false,
+            is_special: false,
+            in_trash: false,
+            ignored_glob: false,
+            hidden_os: false,
+            user_excludes: false,
+            generated: false,
+            perms: None,
+        }
+    }
+
+    /// A directory at `path` for tests, as the walker reports one: no size,
+    /// timestamps or extension.
+    pub fn test_dir(path: impl Into<PathBuf>) -> Self {
+        Self {
+            size: 0,
+            mtime_secs: 0,
+            ctime_secs: 0,
+            atime_secs: 0,
+            ext: None,
+            is_dir: true,
+            ..Self::test_file(path)
+        }
+    }
+}
+
+/// Owner and mode of a file, as `stat` reports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePerms {
+    pub uid: u32,
+    pub gid: u32,
+    /// Permission bits (`0o7777`)
+    pub mode: u32,
+}
+
+/// `name` as a string, with each byte that is not part of valid UTF-8
+/// written as `\xNN`, so names the platform allows but UTF-8 does not can
+/// still be stored and shown.
+pub fn escape_name(name: &OsStr) -> Cow<'_, str> {
+    if let Some(s) = name.to_str() {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(out, "\\x{byte:02X}");
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+#[path = "record_tests.rs"]
+mod tests;
@@ -0,0 +1,18 @@
+use super::*;
+
+#[test]
+fn escape_name_borrows_utf8_names() {
+    assert!(matches!(
+        escape_name(OsStr::new("résumé.txt")),
+        Cow::Borrowed("résumé.txt")
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn escape_name_writes_invalid_bytes_as_hex() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = OsStr::from_bytes(b"caf\xe9 \xc3\xa9t\xe9.txt");
+    assert_eq!(escape_name(name), "caf\\xE9 ét\\xE9.txt");
+}
@@ -0,0 +1,59 @@
+//! The one form an index root is stored in.
+//!
+//! A root can be reached by many paths: `~/src/`, `./src`, `/home/me/src`,
+//! or a symlink such as `/home` pointing at `/var/home`. Queries match
+//! stored paths by prefix, so an index is always built for the root's
+//! canonical path, and anything comparing a path against a root compares
+//! canonical paths too.
+
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::normalize_root;
+
+/// The canonical form of `root`: absolute, with symlinks resolved and
+/// without `.` or `..` segments or a trailing separator.
+///
+/// A root that cannot be resolved, e.g. because it does not exist, is made
+/// absolute and cleaned up lexically instead.
+pub fn canonical_root(root: &Path) -> PathBuf {
+    match root.canonicalize() {
+        Ok(canonical) => normalize_root(canonical),
+        Err(_) => clean_path(root),
+    }
+}
+
+/// `path` made absolute against the current directory, with `.` and `..`
+/// segments and trailing separators removed without touching the file
+/// system.
+pub fn clean_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path))
+    };
+
+    let mut clean = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                // `..` at the root stays at the root.
+                if !matches!(
+                    clean.components().next_back(),
+                    None | Some(Component::RootDir | Component::Prefix(_))
+                ) {
+                    clean.pop();
+                }
+            }
+            other => clean.push(other),
+        }
+    }
+    clean
+}
+
+#[cfg(test)]
+#[path = "root_tests.rs"]
+mod tests;
@@ -0,0 +1,42 @@
+use std::fs;
+
+use super::*;
+
+#[test]
+fn missing_roots_are_cleaned_lexically() {
+    let cases = [
+        ("/no/such/root/", "/no/such/root"),
+        ("/no/./such/../root", "/no/root"),
+        ("/../no", "/no"),
+        ("/", "/"),
+    ];
+    for (root, expected) in cases {
+        assert_eq!(
+            canonical_root(Path::new(root)),
+            Path::new(expected),
+            "{root}"
+        );
+    }
+}
+
+#[test]
+fn relative_roots_are_made_absolute() {
+    let root = canonical_root(Path::new("no-such-dir/sub/"));
+    assert!(root.is_absolute());
+    assert!(root.ends_with("no-such-dir/sub"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_roots_resolve_to_their_target() {
+    let tmp = tempfile::tempdir().unwrap();
+    let target = tmp.path().join("real");
+    fs::create_dir(&target).unwrap();
+    let link = tmp.path().join("alias");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let canonical = target.canonicalize().unwrap();
+    assert_eq!(canonical_root(&link), canonical);
+    assert_eq!(canonical_root(&link.join(".")), canonical);
+    assert_eq!(canonical_root(&target.join("../real/")), canonical);
+}
@@ -1,93 +1,354 @@
 use std::{
-    fs::{self, read_dir},
-    io::Result,
+    fs::{self, File, read_dir},
+    io::{self, Read, Result},
+    mem,
     path::{Path, PathBuf},
     sync::{
-        Arc,
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
         atomic::{AtomicUsize, Ordering},
     },
     thread,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use blaze_runtime::{
+    LangId, NETWORK_OP_TIMEOUT_SECS, NETWORK_SCAN_THREADS, lang_for_ext, lang_for_shebang,
+    normalize_ext,
+};
 use crossbeam::channel::{self, RecvTimeoutError, Sender};
 use log::{debug, warn};
+use xxhash_rust::xxh3::Xxh3;
 
 use crate::{
-    config::BATCH_SIZE,
+    config::{BATCH_SIZE, WATCHDOG_INTERVAL},
     excludes::{IgnoreEngine, TrashConfig, UserExcludes},
-    record::FileRecord,
+    generated::{has_cachedir_tag, mark_generated},
+    netfs::is_network_fs,
+    record::{FilePerms, FileRecord, escape_name},
+    winpath::{long_path, normalize_root},
 };
 
 pub struct ScanContext {
     pub trash: TrashConfig,
     pub ignore: IgnoreEngine,
     pub user_excludes: UserExcludes,
+    /// Hash the contents of regular files up to this size; `None` disables hashing.
+    pub hash_max_bytes: Option<u64>,
+    /// Longest the walker waits for one directory to be listed before
+    /// giving up on it.
+    pub dir_timeout: Duration,
+    /// How directories on network filesystems are walked.
+    pub network: NetworkOptions,
+    /// What the walker does with directories tagged as caches.
+    pub caches: CachePolicy,
+}
+
+/// How a scan enumerates the files under its root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanBackend {
+    /// Walk the directory tree with [`walk_parallel`].
+    #[default]
+    Walk,
+    /// List files with `git ls-files` (see [`walk_git_files`](crate::walk_git_files)).
+    /// The root must be inside a git work tree.
+    Git,
+}
+
+/// What the walker does with directories on network filesystems.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkPolicy {
+    /// Leave network mounts below the scan roots out of the walk.
+    #[default]
+    Skip,
+    /// Walk them with limited parallelism, giving up on directories that
+    /// do not answer in time.
+    Scan,
+}
+
+/// What the walker does with directories holding a valid `CACHEDIR.TAG`
+/// (see [`CACHEDIR_TAG`](crate::CACHEDIR_TAG)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Walk them, flagging everything below as generated.
+    #[default]
+    Flag,
+    /// Leave their contents out of the walk; the directory itself is kept.
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkOptions {
+    pub policy: NetworkPolicy,
+    /// Network directories listed at the same time.
+    pub max_parallel: usize,
+    /// How long listing one network directory may take.
+    pub op_timeout: Duration,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            policy: NetworkPolicy::default(),
+            max_parallel: NETWORK_SCAN_THREADS,
+            op_timeout: Duration::from_secs(NETWORK_OP_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// Directories a walk left out.
+#[derive(Debug, Default)]
+pub struct WalkSummary {
+    /// Network mounts skipped under [`NetworkPolicy::Skip`].
+    pub skipped_network: Vec<PathBuf>,
+    /// Directories that were not listed before their deadline.
+    pub timed_out: Vec<PathBuf>,
+    /// Caches whose contents were skipped under [`CachePolicy::Skip`].
+    pub skipped_caches: Vec<PathBuf>,
+}
+
+/// A directory waiting to be listed.
+struct QueuedDir {
+    path: PathBuf,
+    /// Whether it lies in a generated tree, see [`mark_generated`].
+    generated: bool,
+}
+
+/// State shared by the walker threads and the watchdog.
+struct WalkShared {
+    ctx: Arc<ScanContext>,
+    skip_network: bool,
+    network_permits: Permits,
+    /// Directories queued or being listed.
+    pending: AtomicUsize,
+    /// Workers that have neither exited nor been abandoned.
+    live: AtomicUsize,
+    summary: Mutex<WalkSummary>,
+}
+
+impl WalkShared {
+    fn note(&self, f: impl FnOnce(&mut WalkSummary)) {
+        f(&mut self.summary.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+/// Counting semaphore that bounds concurrent work on network filesystems.
+///
+/// Permits are released by hand so the watchdog can return the permit of a
+/// worker that hung while holding it.
+struct Permits {
+    free: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Permits {
+    fn new(n: usize) -> Self {
+        Self {
+            free: Mutex::new(n.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) {
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        while *free == 0 {
+            free = self
+                .released
+                .wait(free)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *free -= 1;
+    }
+
+    fn release(&self) {
+        *self.free.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        self.released.notify_one();
+    }
+}
+
+/// What one worker is doing, shared with the watchdog.
+#[derive(Default)]
+struct WorkerSlot {
+    state: Mutex<SlotState>,
+}
+
+#[derive(Default)]
+struct SlotState {
+    /// Directory being listed and the deadline for finishing it.
+    busy: Option<(PathBuf, Instant)>,
+    /// Whether the worker holds a network permit for `busy`.
+    network_permit: bool,
+    /// Records not sent yet.
+    batch: Vec<FileRecord>,
+    /// Set by the watchdog when the worker missed its deadline. The worker
+    /// discards its work and exits if the call it was stuck in ever returns.
+    abandoned: bool,
+}
+
+impl WorkerSlot {
+    fn lock(&self) -> MutexGuard<'_, SlotState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn begin(&self, dir: &Path, timeout: Duration, network_permit: bool) {
+        let mut state = self.lock();
+        state.busy = Some((dir.to_path_buf(), Instant::now() + timeout));
+        state.network_permit = network_permit;
+    }
 }
 
 /// Multi-threaded parallel walk using crossbeam for improved performance.
 ///
 /// Uses a work-stealing approach where multiple threads process directories
 /// concurrently. Records are batched before sending to reduce channel overhead.
+///
+/// Directories on network filesystems follow `ctx.network`. A root that is
+/// itself on a network filesystem is always walked, as if the policy were
+/// [`NetworkPolicy::Scan`].
+///
+/// Listing a directory can block forever, for example on a dead FUSE or NFS
+/// mount. The calling thread acts as a watchdog: a worker that has not
+/// finished a directory within `ctx.dir_timeout` (or `ctx.network.op_timeout`
+/// on network filesystems) is abandoned, the directory is reported in
+/// [`WalkSummary::timed_out`], and a new worker takes its place. Abandoned
+/// workers never touch `file_tx`, so the walk still completes.
 pub fn walk_parallel(
     roots: Vec<PathBuf>,
     file_tx: Sender<Vec<FileRecord>>,
     ctx: Arc<ScanContext>,
     num_threads: usize,
-) -> Result<()> {
-    let (work_tx, work_rx) = channel::unbounded::<PathBuf>();
+) -> Result<WalkSummary> {
+    let (work_tx, work_rx) = channel::unbounded::<QueuedDir>();
+    // Workers send batches here rather than to `file_tx`, so a hung worker
+    // cannot keep the caller's channel open.
+    let (batch_tx, batch_rx) = channel::unbounded::<Vec<FileRecord>>();
 
-    // Track pending work items to know when to terminate
-    let pending = Arc::new(AtomicUsize::new(roots.len()));
+    let shared = Arc::new(WalkShared {
+        skip_network: ctx.network.policy == NetworkPolicy::Skip
+            && !roots.iter().any(|r| is_network_fs(r)),
+        network_permits: Permits::new(ctx.network.max_parallel),
+        pending: AtomicUsize::new(roots.len()),
+        live: AtomicUsize::new(0),
+        summary: Mutex::new(WalkSummary::default()),
+        ctx,
+    });
 
     // Seed work queue with roots
     for root in roots {
-        let _ = work_tx.send(root);
+        let _ = work_tx.send(QueuedDir {
+            path: normalize_root(root),
+            generated: false,
+        });
     }
 
     debug!("[walk_parallel] starting with {} threads", num_threads);
 
-    thread::scope(|s| {
-        for _thread_id in 0..num_threads {
-            let work_rx = work_rx.clone();
-            let work_tx = work_tx.clone();
-            let file_tx = file_tx.clone();
-            let ctx = Arc::clone(&ctx);
-            let pending = Arc::clone(&pending);
+    let spawn = || spawn_worker(&shared, &work_rx, &work_tx, &batch_tx);
+    let mut workers: Vec<Arc<WorkerSlot>> = (0..num_threads).map(|_| spawn()).collect();
 
-            s.spawn(move || {
-                worker_loop(work_rx, work_tx, file_tx, &ctx, &pending);
-            });
+    let mut last_check = Instant::now();
+    while shared.live.load(Ordering::Acquire) > 0 {
+        match batch_rx.recv_timeout(WATCHDOG_INTERVAL) {
+            Ok(batch) => {
+                let _ = file_tx.send(batch);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
         }
-    });
 
-    Ok(())
+        if last_check.elapsed() < WATCHDOG_INTERVAL {
+            continue;
+        }
+        last_check = Instant::now();
+
+        for slot in &mut workers {
+            let Some(batch) = abandon_if_hung(slot, &shared, last_check) else {
+                continue;
+            };
+            if !batch.is_empty() {
+                let _ = file_tx.send(batch);
+            }
+            if shared.pending.load(Ordering::Acquire) > 0 {
+                *slot = spawn();
+            }
+        }
+    }
+
+    // Batches sent by the last workers just before they exited.
+    for batch in batch_rx.try_iter() {
+        let _ = file_tx.send(batch);
+    }
+
+    Ok(mem::take(
+        &mut *shared
+            .summary
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+    ))
+}
+
+fn spawn_worker(
+    shared: &Arc<WalkShared>,
+    work_rx: &channel::Receiver<QueuedDir>,
+    work_tx: &channel::Sender<QueuedDir>,
+    batch_tx: &Sender<Vec<FileRecord>>,
+) -> Arc<WorkerSlot> {
+    let slot = Arc::new(WorkerSlot::default());
+    shared.live.fetch_add(1, Ordering::AcqRel);
+
+    let (slot2, shared) = (Arc::clone(&slot), Arc::clone(shared));
+    let (work_rx, work_tx, batch_tx) = (work_rx.clone(), work_tx.clone(), batch_tx.clone());
+    thread::spawn(move || worker_loop(&slot2, &shared, work_rx, work_tx, batch_tx));
+
+    slot
+}
+
+/// Give up on the worker in `slot` if it is past its deadline.
+///
+/// Returns the records it had batched, which are still good.
+fn abandon_if_hung(
+    slot: &WorkerSlot,
+    shared: &WalkShared,
+    now: Instant,
+) -> Option<Vec<FileRecord>> {
+    let mut state = slot.lock();
+    let (dir, _) = state.busy.take_if(|(_, deadline)| now >= *deadline)?;
+
+    warn!(
+        "[walk] listing {:?} did not finish in time; skipping it",
+        dir
+    );
+    state.abandoned = true;
+    if mem::take(&mut state.network_permit) {
+        shared.network_permits.release();
+    }
+    let batch = mem::take(&mut state.batch);
+    drop(state);
+
+    shared.note(|s| s.timed_out.push(dir));
+    shared.live.fetch_sub(1, Ordering::AcqRel);
+    shared.pending.fetch_sub(1, Ordering::AcqRel);
+    Some(batch)
 }
 
 /// Worker loop for parallel walking.
 /// Each worker processes directories from the work queue and sends batched records.
 fn worker_loop(
-    work_rx: channel::Receiver<PathBuf>,
-    work_tx: channel::Sender<PathBuf>,
-    file_tx: Sender<Vec<FileRecord>>,
-    ctx: &ScanContext,
-    pending: &AtomicUsize,
+    slot: &WorkerSlot,
+    shared: &WalkShared,
+    work_rx: channel::Receiver<QueuedDir>,
+    work_tx: channel::Sender<QueuedDir>,
+    batch_tx: Sender<Vec<FileRecord>>,
 ) {
-    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let pending = &shared.pending;
 
     loop {
         // Use timeout to periodically check if all work is done
         match work_rx.recv_timeout(Duration::from_millis(50)) {
             Ok(dir) => {
-                if let Err(e) = scan_dir_parallel(&dir, &work_tx, &mut batch, ctx, pending) {
-                    warn!("[worker] scan_dir_parallel({:?}) failed: {e}", dir);
-                }
-                // Send batch if it's full
-                if batch.len() >= BATCH_SIZE {
-                    let to_send = std::mem::take(&mut batch);
-                    if file_tx.send(to_send).is_err() {
-                        return;
-                    }
+                if !scan_dir_parallel(&dir, slot, shared, &work_tx, &batch_tx) {
+                    // The watchdog gave up on us and has already accounted
+                    // for this directory.
+                    return;
                 }
 
                 // Decrement pending counter after processing directory
@@ -109,28 +370,89 @@ fn worker_loop(
     }
 
     // Send any remaining records
+    let batch = mem::take(&mut slot.lock().batch);
     if !batch.is_empty() {
-        let _ = file_tx.send(batch);
+        let _ = batch_tx.send(batch);
     }
+    shared.live.fetch_sub(1, Ordering::AcqRel);
 }
 
 /// Scan a directory for the parallel walker.
-/// Pushes subdirectories to the work queue and collects records in a batch.
+/// Pushes subdirectories to the work queue and collects records in the
+/// worker's batch, marking those in generated trees.
+///
+/// Returns `false` if the watchdog abandoned this worker meanwhile.
 fn scan_dir_parallel(
-    dir: &Path,
-    work_tx: &channel::Sender<PathBuf>,
-    batch: &mut Vec<FileRecord>,
-    ctx: &ScanContext,
-    pending: &AtomicUsize,
-) -> Result<()> {
-    let rd = match read_dir(dir) {
+    queued: &QueuedDir,
+    slot: &WorkerSlot,
+    shared: &WalkShared,
+    work_tx: &channel::Sender<QueuedDir>,
+    batch_tx: &Sender<Vec<FileRecord>>,
+) -> bool {
+    let (dir, ctx) = (queued.path.as_path(), &shared.ctx);
+    slot.begin(dir, ctx.dir_timeout, false);
+
+    let mut records = if !is_network_fs(dir) {
+        read_dir_records(dir, ctx)
+    } else if shared.skip_network {
+        debug!("[walk] skipping network filesystem at {:?}", dir);
+        shared.note(|s| s.skipped_network.push(dir.to_path_buf()));
+        Vec::new()
+    } else {
+        // Waiting for a permit is not a hang.
+        slot.lock().busy = None;
+        shared.network_permits.acquire();
+        slot.begin(dir, ctx.network.op_timeout, true);
+        read_dir_records(dir, ctx)
+    };
+
+    if finish_listing(dir, &mut records, ctx, queued.generated) {
+        shared.note(|s| s.skipped_caches.push(dir.to_path_buf()));
+    }
+
+    let mut state = slot.lock();
+    if state.abandoned {
+        return false;
+    }
+    state.busy = None;
+    if mem::take(&mut state.network_permit) {
+        shared.network_permits.release();
+    }
+
+    for outcome in records {
+        if should_recurse(&outcome) {
+            // Increment pending count before sending subdirectory
+            shared.pending.fetch_add(1, Ordering::AcqRel);
+            // Send subdirectory to work queue for parallel processing
+            let _ = work_tx.send(QueuedDir {
+                path: outcome.full_path.clone(),
+                generated: outcome.generated,
+            });
+        }
+        state.batch.push(outcome);
+    }
+
+    // Send batch if it's full
+    if state.batch.len() >= BATCH_SIZE {
+        let to_send = mem::take(&mut state.batch);
+        drop(state);
+        let _ = batch_tx.send(to_send);
+    }
+
+    true
+}
+
+/// Records for the entries of `dir`, skipping the ones that cannot be read.
+fn read_dir_records(dir: &Path, ctx: &ScanContext) -> Vec<FileRecord> {
+    let rd = match read_dir(long_path(dir)) {
         Ok(rd) => rd,
         Err(e) => {
             warn!("[walk] read_dir({:?}) failed: {e}", dir);
-            return Ok(());
+            return Vec::new();
         }
     };
 
+    let mut records = Vec::new();
     for entry_res in rd {
         let entry = match entry_res {
             Ok(e) => e,
@@ -140,16 +462,8 @@ fn scan_dir_parallel(
             }
         };
 
-        match inspect_fs_entry(&entry, ctx) {
-            Ok(Some(outcome)) => {
-                if should_recurse(&outcome) {
-                    // Increment pending count before sending subdirectory
-                    pending.fetch_add(1, Ordering::AcqRel);
-                    // Send subdirectory to work queue for parallel processing
-                    let _ = work_tx.send(outcome.full_path.clone());
-                }
-                batch.push(outcome);
-            }
+        match inspect_fs_entry(dir, &entry, ctx) {
+            Ok(Some(outcome)) => records.push(outcome),
             Ok(None) => {}
             Err(e) => {
                 warn!("[walk] inspect_entry error in {:?}: {e}", dir);
@@ -157,30 +471,86 @@ fn scan_dir_parallel(
         }
     }
 
-    Ok(())
+    records
+}
+
+/// Records for the entries of `dir`, as a walk would list them: cache
+/// contents are left out under [`CachePolicy::Skip`], and entries in
+/// generated trees are marked. `generated` is whether `dir` itself lies in
+/// one.
+///
+/// Unlike [`walk_parallel`], this lists `dir` alone, on the calling thread
+/// and without a deadline.
+pub fn list_dir(dir: &Path, ctx: &ScanContext, generated: bool) -> Vec<FileRecord> {
+    let mut records = read_dir_records(dir, ctx);
+    finish_listing(dir, &mut records, ctx, generated);
+    records
 }
 
-fn should_recurse(f: &FileRecord) -> bool {
+/// Apply the cache policy and generated marking to the listing of `dir`.
+///
+/// Returns `true` if `dir` is a cache whose contents were dropped.
+fn finish_listing(
+    dir: &Path,
+    records: &mut Vec<FileRecord>,
+    ctx: &ScanContext,
+    generated: bool,
+) -> bool {
+    let cache = has_cachedir_tag(records);
+    let skip = cache && ctx.caches == CachePolicy::Skip;
+    if skip {
+        debug!("[walk] skipping cache directory {:?}", dir);
+        records.clear();
+    }
+    mark_generated(records, generated || cache);
+    skip
+}
+
+/// Whether a walk descends into the entry `f`: a directory that is neither
+/// a symlink nor left out by trash, ignore or exclude rules.
+pub fn should_recurse(f: &FileRecord) -> bool {
     // Determine if we should recurse into this directory
     f.is_dir && !f.in_trash && !f.ignored_glob && !f.user_excludes && !f.is_symlink
 }
 
-fn inspect_fs_entry(entry: &fs::DirEntry, ctx: &ScanContext) -> Result<Option<FileRecord>> {
+/// `entry` is read from `dir`, whose path it is recorded under: on Windows
+/// the walk lists the long form of `dir`, which entries would inherit.
+fn inspect_fs_entry(
+    dir: &Path,
+    entry: &fs::DirEntry,
+    ctx: &ScanContext,
+) -> Result<Option<FileRecord>> {
     let metadata = entry.metadata()?;
-    let full_path = entry.path();
+    Ok(record_from_metadata(
+        dir.join(entry.file_name()),
+        &metadata,
+        ctx,
+    ))
+}
 
+/// Build a record for a single path, without following a final symlink.
+///
+/// Returns `None` for paths without a final component, like `/`. The
+/// record is never marked [`generated`](FileRecord::generated), which only
+/// a walk from above the generated tree can tell.
+pub fn inspect_path(path: &Path, ctx: &ScanContext) -> Result<Option<FileRecord>> {
+    let metadata = fs::symlink_metadata(long_path(path))?;
+    Ok(record_from_metadata(path.to_path_buf(), &metadata, ctx))
+}
+
+fn record_from_metadata(
+    full_path: PathBuf,
+    metadata: &fs::Metadata,
+    ctx: &ScanContext,
+) -> Option<FileRecord> {
     let is_dir = metadata.is_dir();
     let is_symlink = metadata.is_symlink();
     let is_file = metadata.is_file();
     let is_special = !is_dir && !is_symlink && !is_file;
 
-    let name_os = entry.file_name();
-    let name = match name_os.to_str() {
-        Some(s) => s.to_owned(),
-        None => return Ok(None),
-    };
+    let name = escape_name(full_path.file_name()?).into_owned();
 
-    let hidden_os = name.starts_with('.');
+    let hidden_os = is_hidden(&name, metadata);
     let in_trash = ctx.trash.is_in_trash(&full_path);
     let ignored_glob = ctx.ignore.is_ignored(&full_path, is_dir);
     let user_excludes = ctx.user_excludes.is_excluded(&full_path);
@@ -202,13 +572,29 @@ fn inspect_fs_entry(entry: &fs::DirEntry, ctx: &ScanContext) -> Result<Option<Fi
         (size, mtime_secs, ctime_secs, atime_secs)
     };
 
-    let extension = entry
-        .path()
+    let extension = full_path
         .extension()
         .and_then(|os| os.to_str())
-        .map(|s| s.to_ascii_lowercase());
+        .and_then(normalize_ext);
+
+    let lang = match &extension {
+        Some(ext) => lang_for_ext(ext),
+        None if is_file && is_executable(metadata) => sniff_shebang(&full_path),
+        None => None,
+    };
+
+    let content_hash = match ctx.hash_max_bytes {
+        Some(max) if is_file && size <= max => match content_hash(&full_path) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                debug!("[walk] hashing {:?} failed: {e}", full_path);
+                None
+            }
+        },
+        _ => None,
+    };
 
-    Ok(Some(FileRecord {
+    Some(FileRecord {
         full_path,
         name,
         size,
@@ -217,13 +603,97 @@ fn inspect_fs_entry(entry: &fs::DirEntry, ctx: &ScanContext) -> Result<Option<Fi
         atime_secs,
         ignored_glob,
         ext: extension,
+        lang,
+        content_hash,
         user_excludes,
+        generated: false,
         is_dir,
         is_symlink,
         is_special,
         in_trash,
         hidden_os,
-    }))
+        perms: file_perms(metadata),
+    })
+}
+
+/// Whether the platform hides `name` from directory listings: a leading dot
+/// everywhere, plus the `UF_HIDDEN` flag on macOS (`chflags hidden`) and the
+/// hidden attribute on Windows.
+fn is_hidden(name: &str, metadata: &fs::Metadata) -> bool {
+    name.starts_with('.') || has_hidden_flag(metadata)
+}
+
+#[cfg(target_os = "macos")]
+fn has_hidden_flag(metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    /// `UF_HIDDEN` from `<sys/stat.h>`.
+    const UF_HIDDEN: u32 = 0x0000_8000;
+    metadata.st_flags() & UF_HIDDEN != 0
+}
+
+#[cfg(windows)]
+fn has_hidden_flag(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    /// `FILE_ATTRIBUTE_HIDDEN` from `winnt.h`.
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x0000_0002;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn has_hidden_flag(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn file_perms(metadata: &fs::Metadata) -> Option<FilePerms> {
+    use std::os::unix::fs::MetadataExt;
+    Some(FilePerms {
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        mode: metadata.mode() & 0o7777,
+    })
+}
+
+#[cfg(not(unix))]
+fn file_perms(_metadata: &fs::Metadata) -> Option<FilePerms> {
+    None
+}
+
+/// Longest `#!` line we look at when sniffing a script's language.
+const SHEBANG_SNIFF_BYTES: u64 = 128;
+
+/// Detect the language of an extension-less script from its `#!` line.
+///
+/// Only executables are sniffed, to keep the walk from opening every file.
+fn sniff_shebang(path: &Path) -> Option<LangId> {
+    let mut head = Vec::with_capacity(SHEBANG_SNIFF_BYTES as usize);
+    File::open(long_path(path))
+        .ok()?
+        .take(SHEBANG_SNIFF_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    lang_for_shebang(&head)
+}
+
+/// Fast 64-bit fingerprint of a file's contents.
+///
+/// This is the file's XXH3-64 digest (seed 0), so `{:016x}` prints the same
+/// digits as `xxhsum -H3`.
+pub fn content_hash(path: &Path) -> Result<u64> {
+    let mut hasher = Xxh3::new();
+    io::copy(&mut File::open(long_path(path))?, &mut hasher)?;
+    Ok(hasher.digest())
 }
 
 fn to_unix_secs(t: Option<SystemTime>) -> u64 {
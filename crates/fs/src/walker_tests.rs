@@ -1,5 +1,7 @@
 use super::*;
 
+use blaze_runtime::DIR_TIMEOUT_SECS;
+
 use crossbeam::channel;
 use std::{
     fs::{self, create_dir, write},
@@ -16,6 +18,10 @@ fn default_ctx() -> ScanContext {
         trash: TrashConfig::default(),
         ignore: IgnoreEngine::default(),
         user_excludes: UserExcludes::default(),
+        hash_max_bytes: None,
+        dir_timeout: Duration::from_secs(DIR_TIMEOUT_SECS),
+        network: NetworkOptions::default(),
+        caches: CachePolicy::default(),
     }
 }
 
@@ -62,7 +68,7 @@ fn inspect_fs_entry_returns_record_for_regular_file() {
         .expect("file entry")
         .expect("file entry ok");
 
-    let outcome = inspect_fs_entry(&dir_entry, &ctx)
+    let outcome = inspect_fs_entry(root, &dir_entry, &ctx)
         .expect("inspect_fs_entry ok")
         .expect("some entry");
 
@@ -102,7 +108,7 @@ fn inspect_fs_entry_marks_directories_and_recurse_flag() {
         .expect("subdir entry")
         .expect("subdir entry ok");
 
-    let outcome = inspect_fs_entry(&dir_entry, &ctx)
+    let outcome = inspect_fs_entry(root, &dir_entry, &ctx)
         .expect("inspect_fs_entry ok")
         .expect("some entry");
 
@@ -137,7 +143,7 @@ fn inspect_fs_entry_marks_hidden_files() {
         .expect("hidden entry")
         .expect("hidden entry ok");
 
-    let outcome = inspect_fs_entry(&dir_entry, &ctx)
+    let outcome = inspect_fs_entry(root, &dir_entry, &ctx)
         .expect("inspect_fs_entry ok")
         .expect("some entry");
 
@@ -147,6 +153,160 @@ fn inspect_fs_entry_marks_hidden_files() {
     assert_eq!(rec.name, ".hidden");
 }
 
+#[test]
+fn dot_names_are_hidden_on_every_platform() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let dotfile = tmp.path().join(".profile");
+    let plain = tmp.path().join("profile");
+    write(&dotfile, b"x").expect("write dotfile");
+    write(&plain, b"x").expect("write plain file");
+
+    let metadata = fs::metadata(&dotfile).expect("metadata");
+    assert!(is_hidden(".profile", &metadata));
+    assert!(!has_hidden_flag(&metadata));
+
+    let metadata = fs::metadata(&plain).expect("metadata");
+    assert!(!is_hidden("profile", &metadata));
+    assert!(!has_hidden_flag(&metadata));
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn files_flagged_hidden_are_hidden() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let path = tmp.path().join("secret");
+    write(&path, b"x").expect("write file");
+    let status = std::process::Command::new("chflags")
+        .arg("hidden")
+        .arg(&path)
+        .status()
+        .expect("run chflags");
+    assert!(status.success());
+
+    let metadata = fs::metadata(&path).expect("metadata");
+    assert!(has_hidden_flag(&metadata));
+    assert!(is_hidden("secret", &metadata));
+}
+
+#[cfg(windows)]
+#[test]
+fn files_with_the_hidden_attribute_are_hidden() {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x0000_0002;
+
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let path = tmp.path().join("secret");
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .attributes(FILE_ATTRIBUTE_HIDDEN)
+        .open(&path)
+        .expect("create hidden file");
+
+    let metadata = fs::metadata(&path).expect("metadata");
+    assert!(has_hidden_flag(&metadata));
+    assert!(is_hidden("secret", &metadata));
+}
+
+#[test]
+fn inspect_fs_entry_normalizes_extension() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path();
+
+    write(root.join("Report.PDF"), b"x").expect("write file");
+
+    let ctx = default_ctx();
+
+    let dir_entry = fs::read_dir(root)
+        .expect("read_dir")
+        .find(|res| {
+            res.as_ref()
+                .ok()
+                .map(|e| e.file_name() == "Report.PDF")
+                .unwrap_or(false)
+        })
+        .expect("pdf entry")
+        .expect("pdf entry ok");
+
+    let rec = inspect_fs_entry(root, &dir_entry, &ctx)
+        .expect("inspect_fs_entry ok")
+        .expect("some entry");
+
+    assert_eq!(rec.name, "Report.PDF");
+    assert_eq!(rec.ext.as_deref(), Some("pdf"));
+}
+
+#[test]
+fn inspect_path_detects_language() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path();
+    let ctx = default_ctx();
+
+    let make = |name: &str, body: &[u8], mode: u32| {
+        let path = root.join(name);
+        write(&path, body).expect("write file");
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).expect("chmod");
+        inspect_path(&path, &ctx).unwrap().unwrap().lang
+    };
+
+    let python = blaze_runtime::lang_by_name("python");
+    let shell = blaze_runtime::lang_by_name("shell");
+
+    // Extensions win; no sniffing needed.
+    assert_eq!(make("tool.py", b"#!/bin/sh\n", 0o755), python);
+    // Extension-less executables are sniffed.
+    assert_eq!(
+        make("deploy", b"#!/usr/bin/env bash\nset -e\n", 0o755),
+        shell
+    );
+    // Non-executables are not opened.
+    assert_eq!(make("notes", b"#!/usr/bin/env bash\n", 0o644), None);
+    assert_eq!(make("binary", b"\x7fELF\x02\x01", 0o755), None);
+}
+
+#[test]
+fn inspect_path_hashes_small_files_when_enabled() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path();
+    let hashing = ScanContext {
+        hash_max_bytes: Some(8),
+        ..default_ctx()
+    };
+
+    let hash_of = |name: &str, body: &[u8], ctx: &ScanContext| {
+        let path = root.join(name);
+        write(&path, body).expect("write file");
+        inspect_path(&path, ctx).unwrap().unwrap().content_hash
+    };
+
+    let a = hash_of("a.txt", b"same", &hashing);
+    assert!(a.is_some());
+    assert_eq!(hash_of("b.txt", b"same", &hashing), a);
+    assert_ne!(hash_of("c.txt", b"other", &hashing), a);
+    // Over the cap, or with hashing off, nothing is read.
+    assert_eq!(hash_of("big.txt", b"more than eight bytes", &hashing), None);
+    assert_eq!(hash_of("d.txt", b"same", &default_ctx()), None);
+    // Directories are never hashed.
+    let dir = root.join("dir");
+    create_dir(&dir).expect("create dir");
+    assert_eq!(
+        inspect_path(&dir, &hashing).unwrap().unwrap().content_hash,
+        None
+    );
+}
+
+#[test]
+fn content_hash_matches_xxhsum_h3() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let path = tmp.path().join("empty");
+    write(&path, b"").expect("write file");
+
+    // `xxhsum -H3` of empty input is 2d06800538d394c2.
+    assert_eq!(content_hash(&path).unwrap(), 0x2d06_8005_38d3_94c2);
+}
+
 #[test]
 fn scan_dir_parallel_enqueues_subdirs_and_builds_batch() {
     let tmp = tempfile::tempdir().expect("create temp dir");
@@ -160,25 +320,32 @@ fn scan_dir_parallel_enqueues_subdirs_and_builds_batch() {
     create_dir(root.join("sub")).expect("create sub");
     write(root.join("sub").join("b.txt"), b"b").expect("write b.txt");
 
-    let ctx = default_ctx();
-    let (work_tx, work_rx) = channel::unbounded::<PathBuf>();
-    let mut batch = Vec::new();
-    let pending = AtomicUsize::new(0);
+    let shared = shared(0);
+    let slot = WorkerSlot::default();
+    let (work_tx, work_rx) = channel::unbounded::<QueuedDir>();
+    let (batch_tx, _batch_rx) = channel::unbounded();
+    let dir = QueuedDir {
+        path: root.to_path_buf(),
+        generated: false,
+    };
 
-    scan_dir_parallel(root, &work_tx, &mut batch, &ctx, &pending).expect("scan_dir_parallel");
+    assert!(scan_dir_parallel(&dir, &slot, &shared, &work_tx, &batch_tx));
 
     // Exactly one subdirectory should be enqueued.
     let queued = work_rx.try_recv().expect("a subdir should be queued");
-    assert_eq!(queued, root.join("sub"));
+    assert_eq!(queued.path, root.join("sub"));
+    assert!(!queued.generated);
     assert!(work_rx.try_recv().is_err(), "only one subdir expected");
 
     // Batch should contain records for "a.txt" and "sub".
-    let mut names: Vec<_> = batch.iter().map(|r| r.name.as_str()).collect();
+    let state = slot.lock();
+    let mut names: Vec<_> = state.batch.iter().map(|r| r.name.as_str()).collect();
     names.sort();
     assert_eq!(names, vec!["a.txt", "sub"]);
+    assert!(state.busy.is_none());
 
     // Pending should reflect the one enqueued subdir.
-    assert_eq!(pending.load(AtomicOrdering::Relaxed), 1);
+    assert_eq!(shared.pending.load(AtomicOrdering::Relaxed), 1);
 }
 
 #[test]
@@ -198,7 +365,10 @@ fn walk_parallel_scans_tree_and_emits_all_records() {
     let (file_tx, file_rx) = channel::unbounded::<Vec<FileRecord>>();
 
     // Use multiple threads to exercise the parallel path.
-    walk_parallel(vec![root.clone()], file_tx.clone(), ctx, 4).expect("walk_parallel");
+    let summary =
+        walk_parallel(vec![root.clone()], file_tx.clone(), ctx, 4).expect("walk_parallel");
+    assert!(summary.skipped_network.is_empty());
+    assert!(summary.timed_out.is_empty());
 
     // Drop our sender so the receiver will eventually see Disconnected
     drop(file_tx);
@@ -227,6 +397,136 @@ fn walk_parallel_scans_tree_and_emits_all_records() {
     assert_eq!(rel_paths, expected);
 }
 
+fn write_cachedir_tag(dir: &Path) {
+    write(
+        dir.join(crate::CACHEDIR_TAG),
+        b"Signature: 8a477f597d28d172789f06886806bc55\n",
+    )
+    .expect("write CACHEDIR.TAG");
+}
+
+#[test]
+fn walk_parallel_marks_trees_below_cachedir_tags() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path().to_path_buf();
+
+    // root/
+    //   src/main.js
+    //   yarn.lock
+    //   node_modules/dep/index.js
+    //   cache/CACHEDIR.TAG
+    //   cache/deep/blob
+    for dir in ["src", "node_modules/dep", "cache/deep"] {
+        fs::create_dir_all(root.join(dir)).expect("create dir");
+    }
+    for file in [
+        "src/main.js",
+        "yarn.lock",
+        "node_modules/dep/index.js",
+        "cache/deep/blob",
+    ] {
+        write(root.join(file), b"x").expect("write file");
+    }
+    write_cachedir_tag(&root.join("cache"));
+
+    let (file_tx, file_rx) = channel::unbounded::<Vec<FileRecord>>();
+    walk_parallel(vec![root.clone()], file_tx, Arc::new(default_ctx()), 2).expect("walk");
+
+    let mut generated: Vec<PathBuf> = file_rx
+        .iter()
+        .flatten()
+        .filter(|r| r.generated)
+        .map(|r| r.full_path.strip_prefix(&root).unwrap().to_path_buf())
+        .collect();
+    generated.sort();
+
+    let expected: Vec<PathBuf> = [
+        "cache/CACHEDIR.TAG",
+        "cache/deep",
+        "cache/deep/blob",
+        "node_modules",
+        "node_modules/dep",
+        "node_modules/dep/index.js",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(generated, expected);
+}
+
+#[test]
+fn walk_parallel_can_skip_tagged_caches() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path().to_path_buf();
+
+    // root/
+    //   a.txt
+    //   cache/CACHEDIR.TAG
+    //   cache/deep/blob
+    //   fake/CACHEDIR.TAG   (no signature)
+    fs::create_dir_all(root.join("cache/deep")).expect("create dir");
+    create_dir(root.join("fake")).expect("create dir");
+    write(root.join("a.txt"), b"a").expect("write a.txt");
+    write(root.join("cache/deep/blob"), b"x").expect("write blob");
+    write(root.join("fake/CACHEDIR.TAG"), b"not a tag").expect("write tag");
+    write_cachedir_tag(&root.join("cache"));
+
+    let ctx = ScanContext {
+        caches: CachePolicy::Skip,
+        ..default_ctx()
+    };
+    let (file_tx, file_rx) = channel::unbounded::<Vec<FileRecord>>();
+    let summary = walk_parallel(vec![root.clone()], file_tx, Arc::new(ctx), 2).expect("walk");
+    assert_eq!(summary.skipped_caches, [root.join("cache")]);
+
+    let mut rel_paths: Vec<PathBuf> = file_rx
+        .iter()
+        .flatten()
+        .map(|r| r.full_path.strip_prefix(&root).unwrap().to_path_buf())
+        .collect();
+    rel_paths.sort();
+    let expected: Vec<PathBuf> = ["a.txt", "cache", "fake", "fake/CACHEDIR.TAG"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(rel_paths, expected);
+}
+
+#[test]
+fn list_dir_lists_one_level_like_a_walk() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let root = tmp.path().to_path_buf();
+
+    // root/
+    //   Cargo.lock
+    //   target/debug/app
+    //   cache/CACHEDIR.TAG
+    fs::create_dir_all(root.join("target/debug")).expect("create dir");
+    create_dir(root.join("cache")).expect("create dir");
+    write(root.join("Cargo.lock"), b"").expect("write Cargo.lock");
+    write(root.join("target/debug/app"), b"x").expect("write app");
+    write_cachedir_tag(&root.join("cache"));
+
+    let ctx = ScanContext {
+        caches: CachePolicy::Skip,
+        ..default_ctx()
+    };
+    let mut records = list_dir(&root, &ctx, false);
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+    let listed: Vec<(&str, bool)> = records
+        .iter()
+        .map(|r| (r.name.as_str(), r.generated))
+        .collect();
+    assert_eq!(
+        listed,
+        [("Cargo.lock", false), ("cache", false), ("target", true)]
+    );
+
+    assert!(list_dir(&root.join("cache"), &ctx, false).is_empty());
+    let inherited = list_dir(&root.join("target"), &ctx, true);
+    assert!(inherited.iter().all(|r| r.generated));
+}
+
 #[test]
 fn walk_parallel_with_no_roots_emits_nothing() {
     let ctx = Arc::new(default_ctx());
@@ -238,3 +538,98 @@ fn walk_parallel_with_no_roots_emits_nothing() {
     // No batches should be received.
     assert!(file_rx.recv().is_err());
 }
+
+fn shared(pending: usize) -> WalkShared {
+    WalkShared {
+        ctx: Arc::new(default_ctx()),
+        skip_network: true,
+        network_permits: Permits::new(1),
+        pending: AtomicUsize::new(pending),
+        live: AtomicUsize::new(1),
+        summary: Mutex::new(WalkSummary::default()),
+    }
+}
+
+#[test]
+fn permits_bound_concurrent_holders() {
+    let permits = Permits::new(2);
+    let held = AtomicUsize::new(0);
+    let peak = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        for _ in 0..6 {
+            s.spawn(|| {
+                permits.acquire();
+                let now = held.fetch_add(1, AtomicOrdering::AcqRel) + 1;
+                peak.fetch_max(now, AtomicOrdering::AcqRel);
+                thread::sleep(Duration::from_millis(10));
+                held.fetch_sub(1, AtomicOrdering::AcqRel);
+                permits.release();
+            });
+        }
+    });
+
+    assert!(peak.load(AtomicOrdering::Acquire) <= 2);
+}
+
+#[test]
+fn watchdog_abandons_workers_past_their_deadline() {
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    write(tmp.path().join("a.txt"), b"a").expect("write a.txt");
+    let hung = tmp.path().join("hung");
+
+    let shared = shared(2);
+    let slot = WorkerSlot::default();
+    slot.lock().batch.push(
+        inspect_path(&tmp.path().join("a.txt"), &shared.ctx)
+            .unwrap()
+            .unwrap(),
+    );
+
+    // Idle and on-time workers are left alone.
+    assert!(abandon_if_hung(&slot, &shared, Instant::now()).is_none());
+    slot.begin(&hung, Duration::from_secs(60), false);
+    assert!(abandon_if_hung(&slot, &shared, Instant::now()).is_none());
+
+    // Past the deadline, the batch is salvaged and the directory given up.
+    slot.begin(&hung, Duration::ZERO, false);
+    let salvaged = abandon_if_hung(&slot, &shared, Instant::now()).expect("abandoned");
+    assert_eq!(salvaged.len(), 1);
+    assert_eq!(shared.pending.load(AtomicOrdering::Acquire), 1);
+    assert_eq!(shared.live.load(AtomicOrdering::Acquire), 0);
+    assert_eq!(shared.summary.lock().unwrap().timed_out, [hung]);
+
+    // If the stuck call ever returns, the worker drops its results.
+    let (work_tx, work_rx) = channel::unbounded();
+    let (batch_tx, batch_rx) = channel::unbounded();
+    let dir = QueuedDir {
+        path: tmp.path().to_path_buf(),
+        generated: false,
+    };
+    assert!(!scan_dir_parallel(
+        &dir, &slot, &shared, &work_tx, &batch_tx
+    ));
+    assert!(work_rx.try_recv().is_err());
+    assert!(batch_rx.try_recv().is_err());
+    assert_eq!(shared.pending.load(AtomicOrdering::Acquire), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn inspect_path_keeps_non_utf8_names_escaped() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let tmp = tempfile::tempdir().expect("create temp dir");
+    let path = tmp.path().join(OsStr::from_bytes(b"r\xe9sum\xe9.txt"));
+    if write(&path, b"cv").is_err() {
+        // Some filesystems refuse names that are not UTF-8.
+        return;
+    }
+
+    let rec = inspect_path(&path, &default_ctx())
+        .expect("inspect_path ok")
+        .expect("some entry");
+    assert_eq!(rec.full_path, path);
+    assert_eq!(rec.name, "r\\xE9sum\\xE9.txt");
+    assert_eq!(rec.ext.as_deref(), Some("txt"));
+}
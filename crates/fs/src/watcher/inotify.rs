@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr, OsString},
+    fs,
+    io::{Error, ErrorKind, Result},
+    mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::{OsStrExt, OsStringExt},
+    },
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::{debug, warn};
+
+use super::{ChangeKind, FsEvent, is_reported, should_watch};
+use crate::walker::{ScanContext, inspect_path};
+
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_MOVED_TO
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_MODIFY
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_DELETE_SELF
+    | libc::IN_ONLYDIR;
+
+/// Room for a few hundred events per read.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(super) struct InotifyWatcher {
+    fd: OwnedFd,
+    ctx: Arc<ScanContext>,
+    /// Watch descriptor -> directory it watches.
+    dirs: HashMap<i32, PathBuf>,
+    buf: Vec<u8>,
+}
+
+impl InotifyWatcher {
+    pub(super) fn new(root: &Path, ctx: Arc<ScanContext>) -> Result<Self> {
+        // SAFETY: plain syscall; the returned descriptor is owned below.
+        let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if raw < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut watcher = Self {
+            // SAFETY: `raw` is a fresh descriptor we exclusively own.
+            fd: unsafe { OwnedFd::from_raw_fd(raw) },
+            ctx,
+            dirs: HashMap::new(),
+            buf: vec![0; READ_BUFFER_SIZE],
+        };
+
+        watcher.watch_tree(root, None)?;
+        Ok(watcher)
+    }
+
+    fn add_watch(&mut self, dir: &Path) -> Result<()> {
+        let c_path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+        // SAFETY: `c_path` is a valid NUL-terminated string.
+        let wd =
+            unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        self.dirs.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Watch `root` and every directory below it.
+    ///
+    /// With `report`, files already present are reported as created: a
+    /// directory that appears with content (e.g. moved in, or filled before
+    /// its watch was added) would otherwise go unnoticed.
+    fn watch_tree(&mut self, root: &Path, mut report: Option<&mut Vec<FsEvent>>) -> Result<()> {
+        self.add_watch(root)?;
+        let mut stack = vec![root.to_path_buf()];
+
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let Ok(Some(rec)) = inspect_path(&entry.path(), &self.ctx) else {
+                    continue;
+                };
+
+                if !rec.is_dir {
+                    if let Some(events) = report.as_deref_mut()
+                        && is_reported(&rec)
+                    {
+                        events.push(FsEvent::now(rec.full_path, ChangeKind::Created));
+                    }
+                    continue;
+                }
+                if !should_watch(&rec) {
+                    continue;
+                }
+
+                match self.add_watch(&rec.full_path) {
+                    Ok(()) => stack.push(rec.full_path),
+                    Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => {
+                        warn!(
+                            "[watch] inotify watch limit reached at {}; \
+                             raise fs.inotify.max_user_watches to watch more",
+                            rec.full_path.display()
+                        );
+                        return Ok(());
+                    }
+                    Err(e) => debug!("[watch] cannot watch {}: {e}", rec.full_path.display()),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn wait(&mut self, timeout: Duration) -> Result<Vec<FsEvent>> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+        // SAFETY: `pfd` is a single valid pollfd.
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+        if ready < 0 {
+            let err = Error::last_os_error();
+            return if err.kind() == ErrorKind::Interrupted {
+                Ok(Vec::new())
+            } else {
+                Err(err)
+            };
+        }
+
+        let mut events = Vec::new();
+        loop {
+            // SAFETY: `buf` is valid for writes of its full length.
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    self.buf.as_mut_ptr().cast(),
+                    self.buf.len(),
+                )
+            };
+            if n < 0 {
+                let err = Error::last_os_error();
+                match err.kind() {
+                    ErrorKind::WouldBlock => break,
+                    ErrorKind::Interrupted => continue,
+                    _ => return Err(err),
+                }
+            }
+            if n == 0 {
+                break;
+            }
+
+            let raw = self.parse(n as usize);
+            for (wd, mask, name) in raw {
+                self.handle(wd, mask, name, &mut events);
+            }
+        }
+
+        // A single write usually yields several modify events.
+        events.dedup_by(|later, earlier| {
+            later.kind == ChangeKind::Modified && later.path == earlier.path
+        });
+
+        Ok(events)
+    }
+
+    /// Split the first `len` bytes of the read buffer into raw events.
+    fn parse(&self, len: usize) -> Vec<(i32, u32, Option<OsString>)> {
+        let header = mem::size_of::<libc::inotify_event>();
+        let mut out = Vec::new();
+        let mut off = 0;
+
+        while off + header <= len {
+            // SAFETY: the kernel writes whole events; the header fits in
+            // bounds and read_unaligned tolerates any alignment.
+            let ev: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(self.buf[off..].as_ptr().cast()) };
+            let name_start = off + header;
+            let name_end = (name_start + ev.len as usize).min(len);
+
+            let name = (ev.len > 0).then(|| {
+                let raw = &self.buf[name_start..name_end];
+                let trimmed = raw.split(|&b| b == 0).next().unwrap_or(&[]);
+                OsString::from_vec(trimmed.to_vec())
+            });
+
+            out.push((ev.wd, ev.mask, name));
+            off = name_end;
+        }
+
+        out
+    }
+
+    fn handle(&mut self, wd: i32, mask: u32, name: Option<OsString>, events: &mut Vec<FsEvent>) {
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            warn!("[watch] inotify queue overflowed; some changes were missed");
+            return;
+        }
+        if mask & (libc::IN_IGNORED | libc::IN_DELETE_SELF) != 0 {
+            self.dirs.remove(&wd);
+            return;
+        }
+
+        let (Some(dir), Some(name)) = (self.dirs.get(&wd), name) else {
+            return;
+        };
+        let path = dir.join(OsStr::new(&name));
+
+        if mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+            if mask & libc::IN_ISDIR == 0 {
+                events.push(FsEvent::now(path, ChangeKind::Removed));
+            }
+            return;
+        }
+
+        let Ok(Some(rec)) = inspect_path(&path, &self.ctx) else {
+            return;
+        };
+
+        if rec.is_dir {
+            // New directories need their own watches; anything created in
+            // them before the watch existed is reported now.
+            if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+                && should_watch(&rec)
+                && let Err(e) = self.watch_tree(&path, Some(events))
+            {
+                debug!("[watch] cannot watch {}: {e}", path.display());
+            }
+            return;
+        }
+
+        if !is_reported(&rec) {
+            return;
+        }
+
+        let kind = if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+            ChangeKind::Created
+        } else {
+            ChangeKind::Modified
+        };
+        events.push(FsEvent::now(path, kind));
+    }
+}
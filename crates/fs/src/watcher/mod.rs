@@ -0,0 +1,110 @@
+//! Filesystem change notifications for a directory tree.
+//!
+//! On Linux changes are delivered by inotify as they happen. Elsewhere, and
+//! on request, the tree is rescanned periodically and diffed.
+
+#[cfg(target_os = "linux")]
+mod inotify;
+mod poll;
+
+use std::{
+    io::Result,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{record::FileRecord, walker::ScanContext};
+
+/// What happened to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// A single observed change under the watched root.
+#[derive(Debug, Clone)]
+pub struct FsEvent {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    /// When the change was observed (not the file's mtime).
+    pub observed_at: SystemTime,
+}
+
+impl FsEvent {
+    fn now(path: PathBuf, kind: ChangeKind) -> Self {
+        Self {
+            path,
+            kind,
+            observed_at: SystemTime::now(),
+        }
+    }
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::InotifyWatcher),
+    Poll(poll::PollWatcher),
+}
+
+/// Watches a directory tree, honouring the same trash, ignore, and
+/// exclude rules as the walker.
+pub struct Watcher {
+    backend: Backend,
+}
+
+impl Watcher {
+    /// Watch `root` with the platform's native notification mechanism,
+    /// falling back to polling where there is none.
+    pub fn new(root: &Path, ctx: Arc<ScanContext>) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let backend = inotify::InotifyWatcher::new(root, ctx)?;
+            Ok(Self {
+                backend: Backend::Inotify(backend),
+            })
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self::polling(root, ctx)
+        }
+    }
+
+    /// Watch `root` by rescanning it on every call to [`Watcher::wait`].
+    ///
+    /// Works on any filesystem, including network mounts that do not
+    /// deliver native notifications, but costs a full walk per poll.
+    pub fn polling(root: &Path, ctx: Arc<ScanContext>) -> Result<Self> {
+        Ok(Self {
+            backend: Backend::Poll(poll::PollWatcher::new(root, ctx)),
+        })
+    }
+
+    /// Wait up to `timeout` for changes and return those observed.
+    ///
+    /// May return early with the first batch of changes, and returns an
+    /// empty list if nothing changed.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<FsEvent>> {
+        match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Inotify(w) => w.wait(timeout),
+            Backend::Poll(w) => w.wait(timeout),
+        }
+    }
+}
+
+/// Whether the walker would descend into `rec`.
+fn should_watch(rec: &FileRecord) -> bool {
+    crate::walker::should_recurse(rec)
+}
+
+/// Whether changes to `rec` are worth reporting.
+fn is_reported(rec: &FileRecord) -> bool {
+    !rec.in_trash && !rec.ignored_glob && !rec.user_excludes
+}
+
+#[cfg(test)]
+#[path = "mod_tests.rs"]
+mod tests;
@@ -0,0 +1,121 @@
+use std::{
+    fs::{create_dir, remove_file, write},
+    time::Instant,
+};
+
+use blaze_runtime::DIR_TIMEOUT_SECS;
+
+use super::*;
+use crate::{
+    excludes::{IgnoreEngine, TrashConfig, UserExcludes},
+    walker::{CachePolicy, NetworkOptions},
+};
+
+fn default_ctx() -> Arc<ScanContext> {
+    Arc::new(ScanContext {
+        trash: TrashConfig::default(),
+        ignore: IgnoreEngine::default(),
+        user_excludes: UserExcludes::default(),
+        hash_max_bytes: None,
+        dir_timeout: Duration::from_secs(DIR_TIMEOUT_SECS),
+        network: NetworkOptions::default(),
+        caches: CachePolicy::default(),
+    })
+}
+
+/// Collect events until one matches `path` and `kind`, or give up.
+fn collect_until(watcher: &mut Watcher, path: &Path, kind: ChangeKind) -> Vec<FsEvent> {
+    let mut seen = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        seen.extend(watcher.wait(Duration::from_millis(100)).unwrap());
+        if seen.iter().any(|e| e.path == path && e.kind == kind) {
+            break;
+        }
+    }
+    seen
+}
+
+fn wait_for(watcher: &mut Watcher, path: &Path, kind: ChangeKind) -> bool {
+    collect_until(watcher, path, kind)
+        .iter()
+        .any(|e| e.path == path && e.kind == kind)
+}
+
+fn assert_lifecycle(mut watcher: Watcher, root: &Path) {
+    let file = root.join("notes.txt");
+    write(&file, b"a").unwrap();
+    assert!(wait_for(&mut watcher, &file, ChangeKind::Created));
+
+    write(&file, b"longer").unwrap();
+    assert!(wait_for(&mut watcher, &file, ChangeKind::Modified));
+
+    remove_file(&file).unwrap();
+    assert!(wait_for(&mut watcher, &file, ChangeKind::Removed));
+}
+
+#[test]
+fn native_watcher_reports_file_lifecycle() {
+    let tmp = tempfile::tempdir().unwrap();
+    let watcher = Watcher::new(tmp.path(), default_ctx()).unwrap();
+    assert_lifecycle(watcher, tmp.path());
+}
+
+#[test]
+fn polling_watcher_reports_file_lifecycle() {
+    let tmp = tempfile::tempdir().unwrap();
+    let watcher = Watcher::polling(tmp.path(), default_ctx()).unwrap();
+    assert_lifecycle(watcher, tmp.path());
+}
+
+#[test]
+fn native_watcher_follows_new_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut watcher = Watcher::new(tmp.path(), default_ctx()).unwrap();
+
+    let sub = tmp.path().join("sub");
+    create_dir(&sub).unwrap();
+    let early = sub.join("early.txt");
+    write(&early, b"x").unwrap();
+    assert!(wait_for(&mut watcher, &early, ChangeKind::Created));
+
+    let late = sub.join("late.txt");
+    write(&late, b"y").unwrap();
+    assert!(wait_for(&mut watcher, &late, ChangeKind::Created));
+}
+
+#[test]
+fn watchers_skip_ignored_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let ignored = tmp.path().join("node_modules");
+    create_dir(&ignored).unwrap();
+
+    let ctx = Arc::new(ScanContext {
+        trash: TrashConfig::default(),
+        ignore: IgnoreEngine::with_defaults(tmp.path()).unwrap(),
+        user_excludes: UserExcludes::default(),
+        hash_max_bytes: None,
+        dir_timeout: Duration::from_secs(DIR_TIMEOUT_SECS),
+        network: NetworkOptions::default(),
+        caches: CachePolicy::default(),
+    });
+
+    for mut watcher in [
+        Watcher::new(tmp.path(), Arc::clone(&ctx)).unwrap(),
+        Watcher::polling(tmp.path(), Arc::clone(&ctx)).unwrap(),
+    ] {
+        let hidden = ignored.join("dep.js");
+        write(&hidden, b"x").unwrap();
+        let visible = tmp.path().join("app.js");
+        write(&visible, b"x").unwrap();
+
+        let mut events = collect_until(&mut watcher, &visible, ChangeKind::Created);
+        events.extend(watcher.wait(Duration::from_millis(50)).unwrap());
+
+        assert!(events.iter().any(|e| e.path == visible));
+        assert!(events.iter().all(|e| !e.path.starts_with(&ignored)));
+
+        remove_file(&hidden).unwrap();
+        remove_file(&visible).unwrap();
+    }
+}
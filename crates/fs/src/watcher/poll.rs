@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use super::{ChangeKind, FsEvent, is_reported, should_watch};
+use crate::walker::{ScanContext, inspect_path};
+
+/// What we remember about a file between scans.
+type Stamp = (u64, u64);
+
+pub(super) struct PollWatcher {
+    root: PathBuf,
+    ctx: Arc<ScanContext>,
+    files: HashMap<PathBuf, Stamp>,
+}
+
+impl PollWatcher {
+    pub(super) fn new(root: &Path, ctx: Arc<ScanContext>) -> Self {
+        let files = snapshot(root, &ctx);
+        Self {
+            root: root.to_path_buf(),
+            ctx,
+            files,
+        }
+    }
+
+    pub(super) fn wait(&mut self, timeout: Duration) -> Result<Vec<FsEvent>> {
+        thread::sleep(timeout);
+
+        let current = snapshot(&self.root, &self.ctx);
+        let mut events = Vec::new();
+
+        for (path, stamp) in &current {
+            match self.files.remove(path) {
+                None => events.push(FsEvent::now(path.clone(), ChangeKind::Created)),
+                Some(prev) if prev != *stamp => {
+                    events.push(FsEvent::now(path.clone(), ChangeKind::Modified))
+                }
+                Some(_) => {}
+            }
+        }
+        events.extend(
+            self.files
+                .drain()
+                .map(|(path, _)| FsEvent::now(path, ChangeKind::Removed)),
+        );
+
+        self.files = current;
+        Ok(events)
+    }
+}
+
+/// Size and mtime of every non-directory under `root`.
+fn snapshot(root: &Path, ctx: &ScanContext) -> HashMap<PathBuf, Stamp> {
+    let mut files = HashMap::new();
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(Some(rec)) = inspect_path(&entry.path(), ctx) else {
+                continue;
+            };
+
+            if rec.is_dir {
+                if should_watch(&rec) {
+                    stack.push(rec.full_path);
+                }
+            } else if is_reported(&rec) {
+                files.insert(rec.full_path, (rec.size, rec.mtime_secs));
+            }
+        }
+    }
+
+    files
+}
@@ -0,0 +1,148 @@
+//! Windows path forms.
+//!
+//! Indexes keep `/` as the separator on every platform: a drive path is
+//! stored as `C:/Users/me` and a UNC path as `//server/share/dir`. The file
+//! system is read through the verbatim `\\?\` form, which lifts the
+//! 260-character `MAX_PATH` limit that deep `node_modules` trees run into,
+//! and paths are shown with `\`.
+//!
+//! The string conversions work the same on every platform so they can be
+//! tested anywhere; the `Path` helpers only convert on Windows.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+const VERBATIM: &str = r"\\?\";
+const VERBATIM_UNC: &str = r"\\?\UNC\";
+const DEVICE: &str = r"\\.\";
+
+/// `path` without a verbatim prefix: `\\?\C:\x` becomes `C:\x` and
+/// `\\?\UNC\server\share` becomes `\\server\share`.
+pub fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(rest) = strip_prefix_ignore_case(path, VERBATIM_UNC) {
+        return Cow::Owned(format!(r"\\{rest}"));
+    }
+    match path.strip_prefix(VERBATIM) {
+        Some(rest) if has_drive(rest) => Cow::Borrowed(rest),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// The verbatim form of an absolute drive or UNC path, with `\`
+/// separators, or `None` for paths that have none: relative paths, paths
+/// with `.` or `..` components (verbatim paths are not normalized), and
+/// paths that already are verbatim or device paths.
+pub fn to_verbatim(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM) || path.starts_with(DEVICE) {
+        return None;
+    }
+    if path
+        .split(['/', '\\'])
+        .skip(1)
+        .any(|c| c == "." || c == "..")
+    {
+        return None;
+    }
+
+    let native = path.replace('/', "\\");
+    if let Some(unc) = native.strip_prefix(r"\\") {
+        let mut parts = unc.splitn(3, '\\');
+        let (server, share) = (parts.next()?, parts.next()?);
+        if server.is_empty() || share.is_empty() {
+            return None;
+        }
+        return Some(format!("{VERBATIM_UNC}{unc}"));
+    }
+    if has_drive(&native) && native[2..].starts_with('\\') {
+        return Some(format!("{VERBATIM}{native}"));
+    }
+    None
+}
+
+/// `path` as stored in an index: without a verbatim prefix, with `/`
+/// separators and an upper-case drive letter.
+pub fn to_portable(path: &str) -> Cow<'_, str> {
+    let stripped = strip_verbatim(path);
+    let lower_drive = has_drive(&stripped) && stripped.as_bytes()[0].is_ascii_lowercase();
+    if !stripped.contains('\\') && !lower_drive {
+        return stripped;
+    }
+
+    let mut portable = stripped.replace('\\', "/");
+    if lower_drive {
+        portable[..1].make_ascii_uppercase();
+    }
+    Cow::Owned(portable)
+}
+
+/// `path` with `\` separators.
+pub fn to_native(path: &str) -> Cow<'_, str> {
+    if path.contains('/') {
+        Cow::Owned(path.replace('/', "\\"))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// The form of `path` to hand to the file system: verbatim on Windows, so
+/// paths longer than `MAX_PATH` can be opened.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(verbatim) = path.to_str().and_then(to_verbatim) {
+        return Cow::Owned(PathBuf::from(verbatim));
+    }
+    Cow::Borrowed(path)
+}
+
+/// `path` as a string in the form indexes store (see [`to_portable`]).
+pub fn portable_path(path: &Path) -> Cow<'_, str> {
+    let lossy = path.to_string_lossy();
+    if cfg!(windows) {
+        match lossy {
+            Cow::Borrowed(s) => to_portable(s),
+            Cow::Owned(s) => Cow::Owned(to_portable(&s).into_owned()),
+        }
+    } else {
+        lossy
+    }
+}
+
+/// A stored path in the platform's own form, for display.
+pub fn native_path(path: &str) -> Cow<'_, str> {
+    if cfg!(windows) {
+        to_native(path)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// An index root without a verbatim prefix, as `canonicalize` returns on
+/// Windows, so it prefixes the paths the walker produces.
+pub fn normalize_root(root: PathBuf) -> PathBuf {
+    if cfg!(windows)
+        && let Some(s) = root.to_str()
+        && let stripped = strip_verbatim(s)
+        && stripped.len() != s.len()
+    {
+        return PathBuf::from(stripped.into_owned());
+    }
+    root
+}
+
+/// Whether `path` starts with a drive letter and a colon, like `C:`.
+fn has_drive(path: &str) -> bool {
+    let b = path.as_bytes();
+    b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':'
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+#[cfg(test)]
+#[path = "winpath_tests.rs"]
+mod tests;
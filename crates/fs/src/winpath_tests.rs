@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use super::*;
+
+#[test]
+fn strip_verbatim_restores_drive_and_unc_paths() {
+    assert_eq!(strip_verbatim(r"\\?\C:\Users\me"), r"C:\Users\me");
+    assert_eq!(strip_verbatim(r"\\?\UNC\srv\share\x"), r"\\srv\share\x");
+    assert_eq!(strip_verbatim(r"\\?\unc\srv\share"), r"\\srv\share");
+    assert_eq!(strip_verbatim(r"\\?\Volume{abc}\x"), r"\\?\Volume{abc}\x");
+    assert_eq!(strip_verbatim("/home/me"), "/home/me");
+}
+
+#[test]
+fn to_verbatim_extends_absolute_paths() {
+    assert_eq!(
+        to_verbatim(r"C:\a\node_modules\b").as_deref(),
+        Some(r"\\?\C:\a\node_modules\b")
+    );
+    assert_eq!(to_verbatim("C:/a/b").as_deref(), Some(r"\\?\C:\a\b"));
+    assert_eq!(
+        to_verbatim(r"\\srv\share\dir").as_deref(),
+        Some(r"\\?\UNC\srv\share\dir")
+    );
+    assert_eq!(
+        to_verbatim("//srv/share").as_deref(),
+        Some(r"\\?\UNC\srv\share")
+    );
+}
+
+#[test]
+fn to_verbatim_leaves_paths_it_cannot_extend() {
+    for path in [
+        r"\\?\C:\a",
+        r"\\.\pipe\x",
+        r"a\b",
+        "C:a",
+        r"C:\a\..\b",
+        r"\\srv",
+        "/home/me",
+    ] {
+        assert_eq!(to_verbatim(path), None, "{path}");
+    }
+}
+
+#[test]
+fn portable_and_native_forms_round_trip() {
+    assert_eq!(to_portable(r"c:\Users\me"), "C:/Users/me");
+    assert_eq!(to_portable(r"\\?\D:\src"), "D:/src");
+    assert_eq!(to_portable(r"\\?\UNC\srv\share\x"), "//srv/share/x");
+    assert_eq!(to_portable("/home/me"), "/home/me");
+    assert_eq!(to_native("C:/Users/me"), r"C:\Users\me");
+    assert_eq!(to_native("//srv/share/x"), r"\\srv\share\x");
+}
+
+#[cfg(not(windows))]
+#[test]
+fn path_helpers_leave_unix_paths_alone() {
+    let path = Path::new(r"/tmp/odd\name");
+    assert_eq!(long_path(path), path);
+    assert_eq!(portable_path(path), r"/tmp/odd\name");
+    assert_eq!(native_path("/tmp/x"), "/tmp/x");
+    assert_eq!(normalize_root(r"\\?\C:\x".into()), Path::new(r"\\?\C:\x"));
+}
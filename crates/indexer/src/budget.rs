@@ -0,0 +1,434 @@
+//! Time-boxed refreshes.
+//!
+//! [`refresh_index`](crate::refresh_index) scans the whole tree, which can
+//! take minutes. [`refresh_within`] instead lists directories one level at
+//! a time, those most likely to have changed first, and stops when its
+//! budget runs out, so it can run in a shell prompt or a git hook. It can
+//! also be kept to one subtree, such as a repository. What it found goes
+//! into the index's delta; directories it did not reach keep their entries
+//! until a later refresh lists them.
+//!
+//! Directories are listed in this order:
+//!
+//! 1. directories a listing turned up that the index does not have, as
+//!    everything in them is new;
+//! 2. the root directories of the index's repositories;
+//! 3. every other directory, by the newest modification time among its
+//!    files, most recent first.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use blaze_engine::{
+    DirId, FileId, Index, IndexBuilder, IndexReader, RepoId, StagedDelta,
+    compat::{IndexCompatibility, check_index_header},
+    flags::{BuildFlags, FileFlags, NoiseFlags},
+};
+use blaze_fs::{CachePolicy, FileRecord, NetworkPolicy, is_network_fs, list_dir, should_recurse};
+use crossbeam::channel;
+
+use crate::{
+    MAX_DELTA_SHARE, ScanOptions, SizeFilter, SkippedFiles, create_scan_context, record_changed,
+    register_index,
+};
+
+/// Directory id of the index root.
+const ROOT: DirId = u32::MAX;
+
+/// Result of [`refresh_within`].
+pub struct BudgetedRefreshReport {
+    pub index: Index,
+    /// Files found in the listed directories but not in the previous index.
+    pub added: usize,
+    /// Files of the listed directories that no longer exist.
+    pub removed: usize,
+    /// Files whose size, modification time or content hash changed.
+    pub modified: usize,
+    /// Whether the delta was rewritten.
+    pub rewritten: bool,
+    /// Directories listed before the budget ran out.
+    pub dirs_scanned: usize,
+    /// Directories left unlisted; `0` when every one was reached.
+    pub dirs_left: usize,
+    /// Whether the delta now covers more than 1/[`MAX_DELTA_SHARE`] of the
+    /// index, which only a full refresh folds back in.
+    pub oversized: bool,
+}
+
+/// A directory waiting to be listed.
+enum Pending {
+    /// A live directory of the previous index.
+    Known(DirId),
+    /// A directory the previous index does not have.
+    New { path: PathBuf, generated: bool },
+}
+
+/// Update the index at `index_path` from the directories under its root,
+/// or under `under` if given, that can be listed within `budget`, see the
+/// [module docs](self).
+///
+/// A quarter of the budget is kept for writing the delta, which is always
+/// rewritten against the index file, so its cost grows with the changes
+/// found since the last full refresh rather than with the tree. New
+/// repositories and directory permissions are only picked up by a full
+/// refresh. The hashing, size and cache settings are those of the existing
+/// index; `opts` supplies the excluded paths and the network policy.
+///
+/// Fails if there is no readable index, since building one cannot be
+/// bounded.
+pub fn refresh_within(
+    index_path: &Path,
+    under: Option<&Path>,
+    skip_nonregular: bool,
+    opts: &ScanOptions,
+    budget: Duration,
+) -> Result<BudgetedRefreshReport> {
+    let started = Instant::now();
+    let deadline = started + (budget - budget / 4);
+
+    let header = check_index_header(index_path)
+        .with_context(|| format!("Failed to probe index at {}", index_path.display()))?;
+    match header {
+        IndexCompatibility::Ok(_) | IndexCompatibility::RootMismatch { .. } => {}
+        IndexCompatibility::Missing => bail!("no index found; a full build is needed"),
+        IndexCompatibility::Corrupt => bail!("existing index is corrupt; a full build is needed"),
+        IndexCompatibility::VersionMismatch { on_disk, expected } => bail!(
+            "index format v{on_disk} does not match expected v{expected}; a full build is needed"
+        ),
+    }
+    let previous = Index::open(index_path)
+        .with_context(|| format!("Failed to open index at {}", index_path.display()))?;
+    let root = PathBuf::from(previous.root_path().unwrap_or_default());
+    let root = root.as_path();
+
+    let filter = SizeFilter::of_index(&previous);
+    let caches = if previous.build_flags().contains(BuildFlags::SKIP_CACHES) {
+        CachePolicy::Skip
+    } else {
+        CachePolicy::Flag
+    };
+    let ctx = create_scan_context(&ScanOptions {
+        hash_max_bytes: previous.hash_max_bytes(),
+        caches,
+        ..opts.clone()
+    })?;
+    let skip_network = ctx.network.policy == NetworkPolicy::Skip && !is_network_fs(root);
+
+    // Listing runs on its own thread, so a directory that hangs (e.g. on a
+    // dead mount) is given up on at the deadline. Its thread is left behind.
+    let (dir_tx, dir_rx) = channel::unbounded::<(PathBuf, bool)>();
+    let (list_tx, list_rx) = channel::unbounded::<Option<Vec<FileRecord>>>();
+    thread::spawn(move || {
+        for (dir, generated) in dir_rx {
+            let listing = if skip_network && is_network_fs(&dir) {
+                None
+            } else {
+                Some(list_dir(&dir, &ctx, generated))
+            };
+            if list_tx.send(listing).is_err() {
+                break;
+            }
+        }
+    });
+
+    let top = match under {
+        None => Some(ROOT),
+        Some(under) if under.starts_with(root) => find_dir(&previous, root, under),
+        Some(under) => bail!(
+            "{} is not under the index root {}",
+            under.display(),
+            root.display()
+        ),
+    };
+    let mut queue: VecDeque<Pending> = match (top, under) {
+        (Some(top), _) => listing_order(&previous, root, top)
+            .into_iter()
+            .map(Pending::Known)
+            .collect(),
+        // A subtree the index does not have yet is new throughout.
+        (None, Some(under)) => VecDeque::from([Pending::New {
+            path: under.to_path_buf(),
+            generated: false,
+        }]),
+        (None, None) => VecDeque::new(),
+    };
+
+    let base_files = previous.base_file_count() as FileId;
+    let live = |fid: &FileId| !previous.is_file_removed(*fid);
+    let mut removed_base: Vec<FileId> = (0..base_files)
+        .filter(|&fid| previous.is_file_removed(fid))
+        .collect();
+    // Delta files already accounted for; the others are carried over.
+    let mut handled: HashSet<FileId> = HashSet::new();
+    let mut gone_dirs: HashSet<DirId> = HashSet::new();
+    let mut delta_records: Vec<FileRecord> = Vec::new();
+    let mut skipped = SkippedFiles::default();
+    let (mut added, mut removed, mut modified) = (0, 0, 0);
+    let mut dirs_scanned = 0;
+    let mut drop_file = |fid: FileId, handled: &mut HashSet<FileId>| {
+        if fid < base_files {
+            removed_base.push(fid);
+        } else {
+            handled.insert(fid);
+        }
+    };
+
+    while let Some(next) = queue.pop_front() {
+        let (path, dir, generated) = match &next {
+            Pending::Known(d) if gone_dirs.contains(d) => continue,
+            Pending::Known(d) => (
+                PathBuf::from(previous.reconstruct_dir_path(*d)),
+                Some(*d),
+                dir_generated(&previous, *d),
+            ),
+            Pending::New { path, generated } => (path.clone(), None, *generated),
+        };
+        if Instant::now() >= deadline || dir_tx.send((path, generated)).is_err() {
+            queue.push_front(next);
+            break;
+        }
+        let records = match list_rx.recv_deadline(deadline) {
+            Ok(Some(records)) => records,
+            Ok(None) => continue,
+            Err(_) => {
+                queue.push_front(next);
+                break;
+            }
+        };
+        dirs_scanned += 1;
+
+        let mut entries: HashMap<&str, FileId> = HashMap::new();
+        let mut children: HashMap<&str, DirId> = HashMap::new();
+        if let Some(d) = dir {
+            for &fid in previous.dir_files(d).iter().filter(|f| live(f)) {
+                entries.insert(previous.get_file_name(fid), fid);
+            }
+            for &child in previous.dir_children(d) {
+                if !previous.is_dir_removed(child) {
+                    children.insert(previous.get_dir_name(child), child);
+                }
+            }
+        }
+
+        for rec in records {
+            if should_recurse(&rec) && children.remove(rec.name.as_str()).is_none() {
+                queue.push_front(Pending::New {
+                    path: rec.full_path.clone(),
+                    generated: rec.generated,
+                });
+            }
+
+            // Keep what a full scan would keep.
+            let regular = !rec.is_dir && !rec.is_symlink && !rec.is_special;
+            if (regular && !skipped.keep(filter, &rec)) || (skip_nonregular && !regular) {
+                continue;
+            }
+            match entries.remove(rec.name.as_str()) {
+                None => {
+                    added += 1;
+                    delta_records.push(rec);
+                }
+                Some(fid) => {
+                    let indexed = (
+                        previous.get_file_size(fid),
+                        previous.get_file_modified_epoch(fid),
+                        previous.get_file_hash(fid),
+                    );
+                    if record_changed(indexed, &rec) {
+                        modified += 1;
+                        drop_file(fid, &mut handled);
+                        delta_records.push(rec);
+                    } else if fid >= base_files {
+                        handled.insert(fid);
+                        delta_records.push(rec);
+                    }
+                }
+            }
+        }
+
+        // What the listing no longer has is gone, with everything below
+        // directories that are gone or no longer walked.
+        for fid in entries.into_values() {
+            removed += 1;
+            drop_file(fid, &mut handled);
+        }
+        for child in children.into_values() {
+            for &fid in previous
+                .files_in_dir(child, true)
+                .iter()
+                .filter(|f| live(f))
+            {
+                removed += 1;
+                drop_file(fid, &mut handled);
+            }
+            mark_gone(&previous, child, &mut gone_dirs);
+        }
+    }
+    drop(dir_tx);
+    let dirs_left = queue
+        .iter()
+        .filter(|p| !matches!(p, Pending::Known(d) if gone_dirs.contains(d)))
+        .count();
+
+    if added + removed + modified == 0 {
+        return Ok(BudgetedRefreshReport {
+            index: previous,
+            added,
+            removed,
+            modified,
+            rewritten: false,
+            dirs_scanned,
+            dirs_left,
+            oversized: false,
+        });
+    }
+
+    let carried = (base_files..previous.get_file_count() as FileId)
+        .filter(|fid| live(fid) && !handled.contains(fid))
+        .map(|fid| indexed_record(&previous, fid));
+    delta_records.extend(carried);
+    let oversized =
+        delta_records.len() + removed_base.len() > previous.base_file_count() / MAX_DELTA_SHARE;
+
+    let mut builder = IndexBuilder::new(root.to_path_buf());
+    builder.add_batch(delta_records);
+    let staged = builder.finish();
+
+    // Base directories hidden before or found gone now, unless the delta
+    // brings them back.
+    let found: HashSet<String> = (0..staged.dir_count() as DirId)
+        .map(|d| staged.reconstruct_dir_path(d))
+        .collect();
+    let removed_dirs: Vec<DirId> = (0..previous.base_dir_count() as DirId)
+        .filter(|&d| previous.is_dir_removed(d) || gone_dirs.contains(&d))
+        .filter(|&d| !found.contains(&previous.reconstruct_dir_path(d)))
+        .collect();
+
+    StagedDelta::new(&previous, staged, removed_base, removed_dirs)
+        .write_atomic(index_path)
+        .with_context(|| format!("Failed to write index delta for {}", index_path.display()))?;
+    drop(previous);
+
+    let index = Index::open(index_path)
+        .with_context(|| format!("Failed to open refreshed index at {}", index_path.display()))?;
+    register_index(opts, index_path, &index);
+
+    Ok(BudgetedRefreshReport {
+        index,
+        added,
+        removed,
+        modified,
+        rewritten: true,
+        dirs_scanned,
+        dirs_left,
+        oversized,
+    })
+}
+
+/// Live directories of `index` from `top` down, `top` included, in the
+/// order they are listed.
+fn listing_order(index: &Index, root: &Path, top: DirId) -> Vec<DirId> {
+    let mut order = Vec::new();
+    let mut stack = vec![top];
+    while let Some(d) = stack.pop() {
+        order.push(d);
+        let children = index.dir_children(d).iter();
+        stack.extend(children.filter(|&&c| !index.is_dir_removed(c)));
+    }
+
+    let mut newest: HashMap<DirId, i64> = HashMap::new();
+    for &fid in index.files_in_dir(top, true).iter() {
+        if !index.is_file_removed(fid) {
+            let mtime = index.get_file_modified_epoch(fid);
+            let n = newest.entry(index.get_file_dir_id(fid)).or_insert(mtime);
+            *n = (*n).max(mtime);
+        }
+    }
+
+    let repos: HashSet<DirId> = (0..index.repo_count() as RepoId)
+        .filter_map(|r| find_dir(index, root, Path::new(&index.get_repo_root_path(r))))
+        .collect();
+    order.sort_by_key(|&d| {
+        let newest = newest.get(&d).copied().unwrap_or(i64::MIN);
+        (!repos.contains(&d), Reverse(newest), d)
+    });
+    order
+}
+
+/// The live directory of `index` at `path`, found by walking down from
+/// `root`; [`ROOT`] for `root` itself.
+fn find_dir(index: &Index, root: &Path, path: &Path) -> Option<DirId> {
+    let rel = path.strip_prefix(root).ok()?;
+    let mut dir = ROOT;
+    for component in rel.components() {
+        let name = component.as_os_str().to_str()?;
+        dir = *index
+            .dir_children(dir)
+            .iter()
+            .find(|&&c| !index.is_dir_removed(c) && index.get_dir_name(c) == name)?;
+    }
+    Some(dir)
+}
+
+/// Whether directory `dir` lies in a generated tree: its files are flagged
+/// as generated, or, when it has none, its parent is.
+fn dir_generated(index: &Index, mut dir: DirId) -> bool {
+    while dir != ROOT {
+        let first = index
+            .dir_files(dir)
+            .iter()
+            .find(|&&fid| !index.is_file_removed(fid));
+        if let Some(&fid) = first {
+            return index
+                .get_file_noise_bits(fid)
+                .contains(NoiseFlags::GENERATED);
+        }
+        dir = index.get_dir_parent(dir);
+    }
+    false
+}
+
+/// Add `dir` and every directory below it to `gone`.
+fn mark_gone(index: &Index, dir: DirId, gone: &mut HashSet<DirId>) {
+    let mut stack = vec![dir];
+    while let Some(d) = stack.pop() {
+        if gone.insert(d) {
+            stack.extend_from_slice(index.dir_children(d));
+        }
+    }
+}
+
+/// The record a file of `index` was indexed from, as far as the index
+/// keeps it; access times are not kept.
+fn indexed_record(index: &Index, fid: FileId) -> FileRecord {
+    let flags = index.get_file_flags(fid);
+    let ext = index.get_file_ext(fid);
+    FileRecord {
+        full_path: PathBuf::from(index.reconstruct_full_path(fid)),
+        name: index.get_file_name(fid).to_owned(),
+        size: index.get_file_size(fid),
+        mtime_secs: index.get_file_modified_epoch(fid).max(0) as u64,
+        ctime_secs: index.get_file_created_epoch(fid).max(0) as u64,
+        atime_secs: 0,
+        ext: (!ext.is_empty()).then(|| ext.to_owned()),
+        lang: index.get_file_lang(fid),
+        content_hash: index.get_file_hash(fid),
+        is_dir: flags.contains(FileFlags::IS_DIR),
+        is_symlink: flags.contains(FileFlags::IS_SYMLINK),
+        is_special: flags.contains(FileFlags::SPECIAL),
+        in_trash: flags.contains(FileFlags::IN_TRASH),
+        ignored_glob: flags.contains(FileFlags::EXCLUDED_GLOB),
+        hidden_os: flags.contains(FileFlags::HIDDEN),
+        user_excludes: flags.contains(FileFlags::EXCLUDED_USER),
+        generated: index
+            .get_file_noise_bits(fid)
+            .contains(NoiseFlags::GENERATED),
+        perms: index.get_file_perms(fid),
+    }
+}
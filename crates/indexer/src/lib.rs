@@ -1,66 +1,487 @@
-use std::{path::Path, sync::Arc, thread};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{Context, Error, Result};
-use blaze_engine::{Index, IndexBuilder, StagedIndex, write_index_atomic};
-use blaze_fs::{FileRecord, IgnoreEngine, ScanContext, TrashConfig, UserExcludes, walk_parallel};
-use crossbeam::channel;
+use anyhow::{Context, Result, bail};
+use blaze_engine::{
+    BuildStats, DirId, FileId, Index, IndexBuilder, IndexReader, RepoId, StagedDelta, StagedIndex,
+    VerifyReport,
+    compat::{IndexCompatibility, check_index_compatibility, check_index_header},
+    flags::{BuildFlags, FileFlags},
+    manifest_path, write_index_atomic,
+};
+use blaze_fs::{
+    CachePolicy, FileRecord, IgnoreEngine, NetworkOptions, ScanBackend, ScanContext, TrashConfig,
+    UserExcludes, WalkSummary, canonical_root, inspect_path, should_recurse,
+};
+use blaze_runtime::{DIR_TIMEOUT_SECS, IndexRegistry, registry_path, settings};
+use log::warn;
 
-pub fn create_scan_context() -> Result<Arc<ScanContext>> {
+mod budget;
+mod progress;
+mod source;
+pub use budget::{BudgetedRefreshReport, refresh_within};
+pub use progress::{ScanProgress, SkipReason, SkippedDir};
+pub use source::{RecordSource, ScanSource};
+
+/// Settings for a filesystem scan.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Hash the contents of regular files up to this size; `None` disables
+    /// hashing (or, for [`refresh_index`], keeps the existing index's setting).
+    pub hash_max_bytes: Option<u64>,
+    /// Precompute directory paths in the index for faster path reconstruction
+    /// (for [`refresh_index`], an existing index that has them keeps them).
+    pub dir_paths: bool,
+    /// Leave out regular files larger than this many bytes; `None` indexes
+    /// every size (or, for [`refresh_index`], keeps the existing limit).
+    pub max_file_bytes: Option<u64>,
+    /// Leave out empty regular files (for [`refresh_index`], an existing
+    /// index built this way keeps doing so).
+    pub skip_empty: bool,
+    /// Paths to leave out of the scan; directories among them are not walked.
+    /// Defaults to the paths of the `exclude_paths` setting.
+    pub exclude_paths: Vec<PathBuf>,
+    /// How files are enumerated.
+    pub backend: ScanBackend,
+    /// Longest to wait for one directory before skipping it.
+    pub dir_timeout: Duration,
+    /// How directories on network filesystems are handled.
+    pub network: NetworkOptions,
+    /// How directories tagged as caches are handled (for [`refresh_index`],
+    /// an existing index that skipped them keeps doing so).
+    pub caches: CachePolicy,
+    /// Updated while scanning, for callers that report progress.
+    pub progress: Option<Arc<ScanProgress>>,
+    /// Once the scan is done, read the files modified since it started
+    /// again, so their records match the files as they ended up. Files
+    /// that changed after the scan started are flagged either way, unless
+    /// they were read again after they last changed.
+    pub recheck_changed: bool,
+    /// Registry to record written indexes in; `None` leaves it alone.
+    /// Defaults to [`registry_path`].
+    pub registry: Option<PathBuf>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            hash_max_bytes: None,
+            dir_paths: false,
+            max_file_bytes: None,
+            skip_empty: false,
+            exclude_paths: settings().exclude_paths.value.clone(),
+            backend: ScanBackend::default(),
+            dir_timeout: Duration::from_secs(DIR_TIMEOUT_SECS),
+            network: NetworkOptions::default(),
+            caches: CachePolicy::default(),
+            progress: None,
+            recheck_changed: false,
+            registry: Some(registry_path()),
+        }
+    }
+}
+
+/// Which regular files are left out of the index by size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SizeFilter {
+    max_bytes: Option<u64>,
+    skip_empty: bool,
+}
+
+impl SizeFilter {
+    fn from_options(opts: &ScanOptions) -> Self {
+        Self {
+            max_bytes: opts.max_file_bytes,
+            skip_empty: opts.skip_empty,
+        }
+    }
+
+    /// The filter an existing index was built with.
+    fn of_index(index: &Index) -> Self {
+        Self {
+            max_bytes: index.max_file_bytes(),
+            skip_empty: index.build_flags().contains(BuildFlags::SKIP_EMPTY),
+        }
+    }
+
+    /// Record this filter in the staged index's build options.
+    fn apply_to(&self, staged: &mut StagedIndex) {
+        staged
+            .build_flags
+            .set(BuildFlags::SKIP_EMPTY, self.skip_empty);
+        staged
+            .build_flags
+            .set(BuildFlags::MAX_FILE_SIZE, self.max_bytes.is_some());
+        staged.max_file_bytes = self.max_bytes.unwrap_or(0);
+    }
+}
+
+/// Regular files a scan left out because of their size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SkippedFiles {
+    too_large: usize,
+    empty: usize,
+}
+
+impl SkippedFiles {
+    /// Whether `rec` passes `filter`, counting it if not.
+    fn keep(&mut self, filter: SizeFilter, rec: &FileRecord) -> bool {
+        if filter.skip_empty && rec.size == 0 {
+            self.empty += 1;
+            false
+        } else if filter.max_bytes.is_some_and(|max| rec.size > max) {
+            self.too_large += 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+/// Create the scan context used for indexing.
+pub fn create_scan_context(opts: &ScanOptions) -> Result<Arc<ScanContext>> {
     let ignore = IgnoreEngine::default();
+    let mut user_excludes = UserExcludes::new(Vec::new());
+    for path in &opts.exclude_paths {
+        user_excludes.add_root(path.clone());
+    }
 
     Ok(Arc::new(ScanContext {
         trash: TrashConfig::new(),
         ignore,
-        user_excludes: UserExcludes::new(Vec::new()),
+        user_excludes,
+        hash_max_bytes: opts.hash_max_bytes,
+        dir_timeout: opts.dir_timeout,
+        network: opts.network.clone(),
+        caches: opts.caches,
     }))
 }
 
-/// Build index from filesystem scan with optional filtering and atime checking.
+/// Build index from filesystem scan with optional filtering.
 ///
-/// Returns (StagedIndex, optional atime warning message).
+/// Returns (StagedIndex, optional warning about directories left out).
 pub fn build_index_from_scan(
     root: &Path,
     ctx: Arc<ScanContext>,
     skip_nonregular: bool,
+    backend: ScanBackend,
 ) -> Result<(StagedIndex, Option<String>)> {
-    let (file_tx, file_rx) = channel::unbounded::<Vec<FileRecord>>();
+    let source = ScanSource::spawn(root, Arc::clone(&ctx), backend);
+    build_index_from_source(root, source, &ctx, skip_nonregular)
+}
 
-    let num_threads = thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(4);
+/// Build an index of `root` from the records of `source`, such as a file
+/// list or an archive listing, filtering them and recording the build
+/// options of `ctx` as a scan would.
+///
+/// The index is stored under the canonical path of `root` (see
+/// [`canonical_root`]); records may use either path.
+///
+/// Returns (StagedIndex, optional warning about what was left out).
+pub fn build_index_from_source<S: RecordSource>(
+    root: &Path,
+    source: S,
+    ctx: &ScanContext,
+    skip_nonregular: bool,
+) -> Result<(StagedIndex, Option<String>)> {
+    let built = build_with(
+        root,
+        source,
+        ctx,
+        skip_nonregular,
+        BuildSettings::default(),
+        |_| {},
+    )?;
+    Ok((built.staged, built.warning))
+}
 
-    let walker_handle = {
-        let ctx = Arc::clone(&ctx);
-        let root = root.to_path_buf();
-        let tx = file_tx.clone();
+/// How records become an index, beyond what the scan itself reads.
+#[derive(Clone, Copy, Default)]
+struct BuildSettings<'a> {
+    filter: SizeFilter,
+    /// See [`ScanOptions::recheck_changed`].
+    recheck_changed: bool,
+    progress: Option<&'a ScanProgress>,
+}
 
-        thread::spawn(move || walk_parallel(vec![root], tx, ctx, num_threads))
-    };
+impl<'a> BuildSettings<'a> {
+    fn new(opts: &'a ScanOptions, filter: SizeFilter) -> Self {
+        Self {
+            filter,
+            recheck_changed: opts.recheck_changed,
+            progress: opts.progress.as_deref(),
+        }
+    }
+}
 
-    drop(file_tx);
+/// An index staged from a scan.
+struct Built {
+    staged: StagedIndex,
+    warning: Option<String>,
+    /// Records modified after this Unix time were flagged
+    /// [`FileFlags::CHANGED_DURING_SCAN`]; indexes built from the same
+    /// records flag them by it too.
+    changed_since: u64,
+}
 
-    let mut builder = IndexBuilder::new(root.to_path_buf());
+/// Scan `root` into a staged index, calling `on_record` for every record
+/// that is added to the builder.
+fn scan_with<F>(
+    root: &Path,
+    ctx: Arc<ScanContext>,
+    skip_nonregular: bool,
+    backend: ScanBackend,
+    settings: BuildSettings,
+    on_record: F,
+) -> Result<Built>
+where
+    F: FnMut(&FileRecord),
+{
+    // Walk the canonical root so every record lies under the stored root.
+    let root = canonical_root(root);
+    let source = ScanSource::spawn(&root, Arc::clone(&ctx), backend);
+    build_with(&root, source, &ctx, skip_nonregular, settings, on_record)
+}
 
-    while let Ok(batch) = file_rx.recv() {
-        if skip_nonregular {
-            builder.add_batch(
-                batch
-                    .into_iter()
-                    .filter(|r| !r.is_dir && !r.is_symlink && !r.is_special),
-            );
-        } else {
-            builder.add_batch(batch);
+/// Build a staged index of `root` from `source`, calling `on_record` for
+/// every record that is added to the builder.
+///
+/// Records modified after the source was first read from are flagged, as
+/// the file may have changed again after its record was taken. With
+/// `recheck_changed`, those files, and the ones modified in the second the
+/// scan started, are read once more after the rest, and only flagged if
+/// they changed even then.
+fn build_with<S, F>(
+    root: &Path,
+    mut source: S,
+    ctx: &ScanContext,
+    skip_nonregular: bool,
+    settings: BuildSettings,
+    mut on_record: F,
+) -> Result<Built>
+where
+    S: RecordSource,
+    F: FnMut(&FileRecord),
+{
+    let build_started = Instant::now();
+    let scan_started = unix_now();
+    let hash_max_bytes = ctx.hash_max_bytes.unwrap_or(0);
+    let canonical = canonical_root(root);
+    let mut builder = IndexBuilder::new(canonical.clone());
+    if canonical != root {
+        builder.alias_root(root.to_path_buf());
+    }
+    builder.flag_changed_since(scan_started);
+    let mut skipped = SkippedFiles::default();
+    let mut dirs_scanned = 0u64;
+    let mut dirs_excluded = 0u64;
+
+    let mut add = |builder: &mut IndexBuilder, batch: Vec<FileRecord>| {
+        let mut kept = Vec::with_capacity(batch.len());
+        for r in batch {
+            dirs_scanned += u64::from(should_recurse(&r));
+            dirs_excluded += u64::from(r.is_dir && r.user_excludes);
+            let regular = !r.is_dir && !r.is_symlink && !r.is_special;
+            if regular && !skipped.keep(settings.filter, &r) {
+                continue;
+            }
+            if !skip_nonregular || regular {
+                on_record(&r);
+                kept.push(r);
+                continue;
+            }
+            if r.is_dir
+                && let Some(perms) = r.perms
+            {
+                builder.add_dir_perms(&r.full_path, perms);
+            }
+            if r.name == ".git" {
+                // Keep repository detection working when `.git` dirs are skipped.
+                builder.add_repo_marker(&r.full_path);
+            }
+        }
+        if let Some(progress) = settings.progress {
+            progress.add_files(kept.len());
+        }
+        builder.add_batch(kept);
+    };
+
+    // Files changed since the scan started, held back to be read again.
+    let mut changed = Vec::new();
+    while let Some(mut batch) = source.next_batch() {
+        if settings.recheck_changed {
+            let (later, now) = batch
+                .into_iter()
+                .partition(|r| !r.is_dir && r.mtime_secs >= scan_started);
+            changed.extend::<Vec<_>>(later);
+            batch = now;
         }
+        add(&mut builder, batch);
     }
 
-    let walk_result = walker_handle
-        .join()
-        .map_err(|_| Error::msg("filesystem walker thread panicked"))?;
-    walk_result?;
+    let summary = source.finish()?;
 
-    let staged = builder.finish();
+    // Every record read so far predates the scan start, so moving the
+    // threshold up to the recheck only affects the records read again.
+    let mut changed_since = scan_started;
+    if !changed.is_empty() {
+        changed_since = unix_now();
+        builder.flag_changed_since(changed_since);
+        let fresh = changed
+            .into_iter()
+            .filter_map(|old| match inspect_path(&old.full_path, ctx) {
+                Ok(Some(rec)) => Some(FileRecord {
+                    generated: old.generated,
+                    ..rec
+                }),
+                // Gone since the scan listed it.
+                Ok(None) | Err(_) => None,
+            })
+            .collect();
+        add(&mut builder, fresh);
+    }
+
+    let mut staged = builder.finish();
+    staged.hash_max_bytes = hash_max_bytes;
+    settings.filter.apply_to(&mut staged);
+    staged
+        .build_flags
+        .set(BuildFlags::SKIP_CACHES, ctx.caches == CachePolicy::Skip);
+    staged.scan_started_secs = scan_started;
+    staged.scan_finished_secs = unix_now();
+
+    let still_changed = staged
+        .files
+        .iter()
+        .filter(|f| {
+            FileFlags::from_bits_truncate(f.flag_bits).contains(FileFlags::CHANGED_DURING_SCAN)
+        })
+        .count();
+    let excluded = ctx.user_excludes.roots();
+    staged.build_stats = BuildStats {
+        build_millis: build_started.elapsed().as_millis() as u64,
+        dirs_scanned,
+        dirs_skipped: dirs_excluded
+            + (summary.skipped_network.len()
+                + summary.timed_out.len()
+                + summary.skipped_caches.len()) as u64,
+        files_skipped: (skipped.too_large + skipped.empty) as u64,
+        peak_memory_bytes: peak_resident_bytes().unwrap_or(0),
+    };
+    if let Some(progress) = settings.progress {
+        progress.record_skipped(&summary, excluded);
+    }
+    let warning = scan_warning(
+        &summary,
+        skipped,
+        excluded,
+        still_changed,
+        settings.recheck_changed,
+    );
+    Ok(Built {
+        staged,
+        warning,
+        changed_since,
+    })
+}
 
-    Ok((staged, None))
+/// Current Unix time in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Most memory this process has had resident, from `/proc/self/status`.
+#[cfg(target_os = "linux")]
+fn peak_resident_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_resident_bytes() -> Option<u64> {
+    None
+}
+
+/// Describe what a scan left out, or could not pin down, if anything.
+fn scan_warning(
+    summary: &WalkSummary,
+    skipped: SkippedFiles,
+    excluded: &[PathBuf],
+    changed: usize,
+    rechecked: bool,
+) -> Option<String> {
+    let list = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut lines = Vec::new();
+    if !excluded.is_empty() {
+        lines.push(format!(
+            "excluded {} path(s): {}",
+            excluded.len(),
+            list(excluded)
+        ));
+    }
+    if !summary.skipped_network.is_empty() {
+        lines.push(format!(
+            "skipped {} network mount(s): {}",
+            summary.skipped_network.len(),
+            list(&summary.skipped_network)
+        ));
+    }
+    if !summary.timed_out.is_empty() {
+        lines.push(format!(
+            "gave up on {} unresponsive director(ies): {}",
+            summary.timed_out.len(),
+            list(&summary.timed_out)
+        ));
+    }
+    if !summary.skipped_caches.is_empty() {
+        lines.push(format!(
+            "skipped the contents of {} cache director(ies): {}",
+            summary.skipped_caches.len(),
+            list(&summary.skipped_caches)
+        ));
+    }
+    if skipped.too_large > 0 {
+        lines.push(format!(
+            "skipped {} file(s) over the size limit",
+            skipped.too_large
+        ));
+    }
+    if skipped.empty > 0 {
+        lines.push(format!("skipped {} empty file(s)", skipped.empty));
+    }
+    if changed > 0 {
+        let hint = if rechecked {
+            ""
+        } else {
+            " (--recheck-changed reads them again at the end of the scan)"
+        };
+        lines.push(format!(
+            "{changed} file(s) changed while the scan ran and may be out of date{hint}"
+        ));
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
 }
 
 /// Build an index on disk and then open it.
@@ -68,9 +489,24 @@ pub fn build_initial_index(
     root: &Path,
     index_path: &Path,
     skip_nonregular: bool,
+    opts: &ScanOptions,
 ) -> Result<(Index, Option<String>)> {
-    let scan_context = create_scan_context()?;
-    let (staged, atime_warning) = build_index_from_scan(root, scan_context, skip_nonregular)?;
+    let scan_context = create_scan_context(opts)?;
+    let Built {
+        mut staged,
+        warning: scan_warning,
+        ..
+    } = scan_with(
+        root,
+        scan_context,
+        skip_nonregular,
+        opts.backend,
+        BuildSettings::new(opts, SizeFilter::from_options(opts)),
+        |_| {},
+    )?;
+    if opts.dir_paths {
+        staged.store_dir_paths();
+    }
 
     write_index_atomic(index_path, &staged, 0)
         .with_context(|| format!("Failed to write index to {}", index_path.display()))?;
@@ -81,8 +517,34 @@ pub fn build_initial_index(
             index_path.display()
         )
     })?;
+    register_index(opts, index_path, &idx);
 
-    Ok((idx, atime_warning))
+    Ok((idx, scan_warning))
+}
+
+/// Record a freshly written index in the registry named by `opts`.
+///
+/// The registry is only used to list indexes, so failing to update it is
+/// logged rather than failing the build.
+fn register_index(opts: &ScanOptions, index_path: &Path, index: &Index) {
+    let Some(registry) = &opts.registry else {
+        return;
+    };
+    // Relative paths from the command line would be meaningless elsewhere.
+    let absolute = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let root = absolute(Path::new(index.root_path().unwrap_or_default()));
+    if let Err(e) = IndexRegistry::update(
+        registry,
+        &absolute(index_path),
+        &root,
+        index.created_secs().unwrap_or_default(),
+        index.live_file_count() as u64,
+    ) {
+        warn!(
+            "failed to update index registry {}: {e}",
+            registry.display()
+        );
+    }
 }
 
 /// Open an existing index, or build a new one if it does not exist.
@@ -90,12 +552,472 @@ pub fn open_or_build_index(
     root: &Path,
     index_path: &Path,
     skip_nonregular: bool,
+    opts: &ScanOptions,
 ) -> Result<(Index, Option<String>)> {
     if index_path.exists() {
         let idx = Index::open(index_path)
             .with_context(|| format!("Failed to open index at {}", index_path.display()))?;
         Ok((idx, None))
     } else {
-        build_initial_index(root, index_path, skip_nonregular)
+        build_initial_index(root, index_path, skip_nonregular, opts)
     }
 }
+
+/// Open an existing index, building it if it does not exist and rebuilding
+/// it if it cannot be read.
+///
+/// An index that fails to open with [`io::ErrorKind::InvalidData`] (bad
+/// magic, another format version, sections out of bounds) is moved aside by
+/// [`rebuild_corrupt_index`] and replaced by a full build. The returned
+/// warning says where the unreadable file went.
+pub fn open_or_heal_index(
+    root: &Path,
+    index_path: &Path,
+    skip_nonregular: bool,
+    opts: &ScanOptions,
+) -> Result<(Index, Option<String>)> {
+    if !index_path.exists() {
+        return build_initial_index(root, index_path, skip_nonregular, opts);
+    }
+    match Index::open(index_path) {
+        Ok(idx) => Ok((idx, None)),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            rebuild_corrupt_index(root, index_path, skip_nonregular, opts, &e)
+        }
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to open index at {}", index_path.display()))
+        }
+    }
+}
+
+/// Move the unreadable index at `index_path` to `<name>.corrupt` and build a
+/// fresh one for `root` in its place.
+///
+/// `error` is why the index could not be opened; it is reported along with
+/// the backup location, ahead of any scan warnings.
+pub fn rebuild_corrupt_index(
+    root: &Path,
+    index_path: &Path,
+    skip_nonregular: bool,
+    opts: &ScanOptions,
+    error: &io::Error,
+) -> Result<(Index, Option<String>)> {
+    let mut backup = index_path.as_os_str().to_owned();
+    backup.push(".corrupt");
+    let backup = PathBuf::from(backup);
+
+    fs::rename(index_path, &backup).with_context(|| {
+        format!(
+            "Failed to move unreadable index {} aside",
+            index_path.display()
+        )
+    })?;
+
+    let (index, scan_warning) = build_initial_index(root, index_path, skip_nonregular, opts)?;
+    let healed = format!(
+        "index was unreadable ({error}); moved it to {} and rebuilt {}",
+        backup.display(),
+        root.display()
+    );
+    let warning = match scan_warning {
+        Some(w) => format!("{healed}\n{w}"),
+        None => healed,
+    };
+    Ok((index, Some(warning)))
+}
+
+/// Why an existing index could not be reused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildReason {
+    Missing,
+    Corrupt,
+    VersionMismatch { on_disk: u32, expected: u32 },
+    RootMismatch,
+}
+
+impl std::fmt::Display for RebuildReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RebuildReason::Missing => f.write_str("no index found"),
+            RebuildReason::Corrupt => f.write_str("existing index is corrupt"),
+            RebuildReason::VersionMismatch { on_disk, expected } => write!(
+                f,
+                "index format v{on_disk} does not match expected v{expected}"
+            ),
+            RebuildReason::RootMismatch => f.write_str("existing index is for a different root"),
+        }
+    }
+}
+
+/// Check whether the index at `index_path` can be reused for `root`.
+fn reuse_check(root: &Path, index_path: &Path) -> Result<Option<RebuildReason>> {
+    let compat = check_index_compatibility(index_path, root)
+        .with_context(|| format!("Failed to probe index at {}", index_path.display()))?;
+
+    Ok(match compat {
+        IndexCompatibility::Ok(_) => None,
+        IndexCompatibility::Missing => Some(RebuildReason::Missing),
+        IndexCompatibility::Corrupt => Some(RebuildReason::Corrupt),
+        IndexCompatibility::VersionMismatch { on_disk, expected } => {
+            Some(RebuildReason::VersionMismatch { on_disk, expected })
+        }
+        IndexCompatibility::RootMismatch { .. } => Some(RebuildReason::RootMismatch),
+    })
+}
+
+/// Result of [`ensure_index`].
+pub enum EnsureOutcome {
+    /// A usable index already existed and was opened.
+    Existing(Index),
+    /// A new index was built.
+    Built {
+        index: Index,
+        reason: RebuildReason,
+        scan_warning: Option<String>,
+    },
+}
+
+/// Build an index only if there is no usable one for `root`.
+///
+/// Unlike [`open_or_build_index`], an index that is corrupt, from another
+/// format version, or for a different root counts as missing.
+pub fn ensure_index(
+    root: &Path,
+    index_path: &Path,
+    skip_nonregular: bool,
+    opts: &ScanOptions,
+) -> Result<EnsureOutcome> {
+    match reuse_check(root, index_path)? {
+        None => {
+            let index = Index::open(index_path)
+                .with_context(|| format!("Failed to open index at {}", index_path.display()))?;
+            Ok(EnsureOutcome::Existing(index))
+        }
+        Some(reason) => {
+            let (index, scan_warning) =
+                build_initial_index(root, index_path, skip_nonregular, opts)?;
+            Ok(EnsureOutcome::Built {
+                index,
+                reason,
+                scan_warning,
+            })
+        }
+    }
+}
+
+/// A refresh rewrites the index in full once the files differing from the
+/// index file exceed this share (1/n) of it.
+pub const MAX_DELTA_SHARE: usize = 8;
+
+/// Result of [`refresh_index`].
+pub struct RefreshReport {
+    pub index: Index,
+    /// Files present on disk but not in the previous index.
+    pub added: usize,
+    /// Files in the previous index that no longer exist.
+    pub removed: usize,
+    /// Files whose size, modification time or content hash changed.
+    pub modified: usize,
+    /// Whether the index was updated.
+    pub rewritten: bool,
+    /// Whether the update went into a delta file next to the index rather
+    /// than rewriting it.
+    pub delta: bool,
+    /// Set when the previous index could not be reused and a full build ran.
+    pub rebuilt: Option<RebuildReason>,
+    pub scan_warning: Option<String>,
+}
+
+impl RefreshReport {
+    pub fn changed(&self) -> usize {
+        self.added + self.removed + self.modified
+    }
+}
+
+/// Rescan `root` and update the index only if the tree changed.
+///
+/// The scan itself is always full; what is incremental is the write, which
+/// is skipped when every file matches the existing index by path, size and
+/// modification time, and by content hash where both sides have one. When
+/// the files that differ from the index file are few, they are written as
+/// a delta next to it (see [`StagedDelta`]) instead of rewriting it; the
+/// delta is folded into a full rewrite once it covers more than
+/// 1/[`MAX_DELTA_SHARE`] of the index.
+///
+/// `opts.hash_max_bytes` overrides the hashing setting of the existing index;
+/// `None` keeps it.
+pub fn refresh_index(
+    root: &Path,
+    index_path: &Path,
+    skip_nonregular: bool,
+    opts: &ScanOptions,
+) -> Result<RefreshReport> {
+    if let Some(reason) = reuse_check(root, index_path)? {
+        let (index, scan_warning) = build_initial_index(root, index_path, skip_nonregular, opts)?;
+        let added = index.live_file_count();
+        return Ok(RefreshReport {
+            index,
+            added,
+            removed: 0,
+            modified: 0,
+            rewritten: true,
+            delta: false,
+            rebuilt: Some(reason),
+            scan_warning,
+        });
+    }
+
+    let previous = Index::open(index_path)
+        .with_context(|| format!("Failed to open index at {}", index_path.display()))?;
+
+    let mut known: HashMap<String, (u64, i64, Option<u64>, FileId)> = (0
+        ..previous.get_file_count() as FileId)
+        .filter(|&fid| !previous.is_file_removed(fid))
+        .map(|fid| {
+            (
+                previous.reconstruct_full_path(fid),
+                (
+                    previous.get_file_size(fid),
+                    previous.get_file_modified_epoch(fid),
+                    previous.get_file_hash(fid),
+                    fid,
+                ),
+            )
+        })
+        .collect();
+
+    let previous_hash_max = previous.hash_max_bytes();
+    let hash_max_bytes = opts.hash_max_bytes.or(previous_hash_max);
+    let previous_dir_paths = previous.has_dir_paths();
+    let dir_paths = opts.dir_paths || previous_dir_paths;
+    let previous_filter = SizeFilter::of_index(&previous);
+    let filter = SizeFilter {
+        max_bytes: opts.max_file_bytes.or(previous_filter.max_bytes),
+        skip_empty: opts.skip_empty || previous_filter.skip_empty,
+    };
+    let previous_caches = if previous.build_flags().contains(BuildFlags::SKIP_CACHES) {
+        CachePolicy::Skip
+    } else {
+        CachePolicy::Flag
+    };
+    let caches = match opts.caches {
+        CachePolicy::Flag => previous_caches,
+        CachePolicy::Skip => CachePolicy::Skip,
+    };
+
+    // Files that differ from the index file itself, not counting its delta:
+    // the base files they replace and the records that go into a new delta.
+    let base_files = previous.base_file_count() as FileId;
+    let max_delta = previous.base_file_count() / MAX_DELTA_SHARE;
+    let mut removed_base: Vec<FileId> = (0..base_files)
+        .filter(|&fid| previous.is_file_removed(fid))
+        .collect();
+    let mut delta_records: Vec<FileRecord> = Vec::new();
+
+    let (mut added, mut modified) = (0, 0);
+    let scan_context = create_scan_context(&ScanOptions {
+        hash_max_bytes,
+        caches,
+        ..opts.clone()
+    })?;
+    let Built {
+        mut staged,
+        warning: scan_warning,
+        changed_since,
+    } = scan_with(
+        root,
+        scan_context,
+        skip_nonregular,
+        opts.backend,
+        BuildSettings::new(opts, filter),
+        |rec| {
+            let in_delta = match known.remove(rec.full_path.to_string_lossy().as_ref()) {
+                None => {
+                    added += 1;
+                    true
+                }
+                Some((size, prev_mtime, prev_hash, fid)) => {
+                    let changed = record_changed((size, prev_mtime, prev_hash), rec);
+                    if changed {
+                        modified += 1;
+                        if fid < base_files {
+                            removed_base.push(fid);
+                        }
+                    }
+                    changed || fid >= base_files
+                }
+            };
+            // Past the limit the index is rewritten anyway.
+            if in_delta && delta_records.len() <= max_delta {
+                delta_records.push(rec.clone());
+            }
+        },
+    )?;
+    let removed = known.len();
+    removed_base.extend(known.values().map(|k| k.3).filter(|&fid| fid < base_files));
+
+    // A changed hashing, dir paths, size or cache setting also needs a
+    // rewrite, even with no file changes.
+    if added + removed + modified == 0
+        && hash_max_bytes == previous_hash_max
+        && dir_paths == previous_dir_paths
+        && filter == previous_filter
+        && caches == previous_caches
+    {
+        return Ok(RefreshReport {
+            index: previous,
+            added,
+            removed,
+            modified,
+            rewritten: false,
+            delta: false,
+            rebuilt: None,
+            scan_warning,
+        });
+    }
+
+    // Small changes go into a delta next to the index; anything a delta
+    // cannot express, or a delta grown too large, rewrites the index.
+    let delta = delta_records.len() + removed_base.len() <= max_delta
+        && hash_max_bytes == previous_hash_max
+        && dir_paths == previous_dir_paths
+        && filter == previous_filter
+        && caches == previous_caches
+        && repo_roots(&previous) == repo_roots(&staged);
+    if delta {
+        let mut builder = IndexBuilder::new(canonical_root(root));
+        builder.flag_changed_since(changed_since);
+        builder.add_batch(delta_records);
+        let mut added = builder.finish();
+        added.scan_started_secs = staged.scan_started_secs;
+        added.scan_finished_secs = staged.scan_finished_secs;
+        added.build_stats = staged.build_stats;
+        let removed_dirs = removed_dirs(&previous, &staged);
+        StagedDelta::new(&previous, added, removed_base, removed_dirs)
+            .write_atomic(index_path)
+            .with_context(|| format!("Failed to write index delta for {}", index_path.display()))?;
+    } else {
+        if dir_paths {
+            staged.store_dir_paths();
+        }
+        write_index_atomic(index_path, &staged, 0)
+            .with_context(|| format!("Failed to write index to {}", index_path.display()))?;
+    }
+    drop(previous);
+
+    let index = Index::open(index_path)
+        .with_context(|| format!("Failed to open refreshed index at {}", index_path.display()))?;
+    register_index(opts, index_path, &index);
+
+    Ok(RefreshReport {
+        index,
+        added,
+        removed,
+        modified,
+        rewritten: true,
+        delta,
+        rebuilt: None,
+        scan_warning,
+    })
+}
+
+/// Whether `rec` differs from an indexed file with the given size,
+/// modification time and content hash.
+fn record_changed(indexed: (u64, i64, Option<u64>), rec: &FileRecord) -> bool {
+    let (size, mtime, hash) = indexed;
+    // Stored mtimes are narrowed to u32 seconds.
+    let rec_mtime = rec.mtime_secs.min(u64::from(u32::MAX)) as i64;
+    // Some tools rewrite files but restore the mtime; trust the hash when
+    // both scans have one.
+    let hash_changed = matches!(
+        (hash, rec.content_hash),
+        (Some(a), Some(b)) if a != b.max(1)
+    );
+    size != rec.size || mtime != rec_mtime || hash_changed
+}
+
+/// Root directories of the repositories in `index`.
+fn repo_roots<I: IndexReader>(index: &I) -> HashSet<String> {
+    (0..index.repo_count() as RepoId)
+        .map(|r| index.get_repo_root_path(r))
+        .collect()
+}
+
+/// Directories of the index file behind `previous` that a new scan no
+/// longer found.
+fn removed_dirs(previous: &Index, scanned: &StagedIndex) -> Vec<DirId> {
+    let found: HashSet<String> = (0..scanned.dir_count() as DirId)
+        .map(|d| scanned.reconstruct_dir_path(d))
+        .collect();
+    (0..previous.base_dir_count() as DirId)
+        .filter(|&d| !found.contains(&previous.reconstruct_dir_path(d)))
+        .collect()
+}
+
+/// Run structural checks over the index at `index_path`.
+///
+/// Fails if the index is missing, its header is unusable, or it does not
+/// match its manifest when it has one; structural problems inside a
+/// readable index are returned in the report.
+pub fn verify_index(index_path: &Path) -> Result<VerifyReport> {
+    let header = check_index_header(index_path)
+        .with_context(|| format!("Failed to probe index at {}", index_path.display()))?;
+
+    match header {
+        IndexCompatibility::Missing => bail!("no index found at {}", index_path.display()),
+        IndexCompatibility::Corrupt => bail!("index header is corrupt"),
+        IndexCompatibility::VersionMismatch { on_disk, expected } => {
+            bail!("index format v{on_disk} does not match expected v{expected}")
+        }
+        IndexCompatibility::Ok(_) | IndexCompatibility::RootMismatch { .. } => {}
+    }
+
+    let index = if manifest_path(index_path).exists() {
+        Index::open_verified(index_path)
+    } else {
+        Index::open(index_path)
+    }
+    .with_context(|| format!("Failed to open index at {}", index_path.display()))?;
+    Ok(index.verify())
+}
+
+/// Count regular files under `root`, giving up once `limit` is exceeded.
+///
+/// This is a cheap pre-flight check for on-the-fly indexing: it does not
+/// apply ignore rules (so it may overestimate) and never follows symlinks.
+/// Returns `None` if the tree has more than `limit` files.
+pub fn estimate_file_count(root: &Path, limit: usize) -> Option<usize> {
+    let mut count = 0usize;
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(ft) = entry.file_type() else {
+                continue;
+            };
+
+            if ft.is_dir() {
+                stack.push(entry.path());
+            } else if ft.is_file() {
+                count += 1;
+                if count > limit {
+                    return None;
+                }
+            }
+        }
+    }
+
+    Some(count)
+}
+
+/// Scan `root` into an index that is queried straight from memory, without
+/// writing anything to disk.
+pub fn build_in_memory_index(root: &Path) -> Result<StagedIndex> {
+    let scan_context = create_scan_context(&ScanOptions::default())?;
+    let (staged, _) = build_index_from_scan(root, scan_context, true, ScanBackend::Walk)?;
+    Ok(staged)
+}
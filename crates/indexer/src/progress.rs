@@ -0,0 +1,84 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use blaze_fs::WalkSummary;
+
+/// Why a directory was left out of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Listed in the exclude paths.
+    Excluded,
+    /// A network mount skipped by the network policy.
+    NetworkMount,
+    /// Not listed before the directory timeout.
+    TimedOut,
+    /// A cache whose contents were skipped, see `CachePolicy::Skip`.
+    Cache,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::Excluded => "excluded",
+            SkipReason::NetworkMount => "network",
+            SkipReason::TimedOut => "timeout",
+            SkipReason::Cache => "cache",
+        }
+    }
+}
+
+/// A directory the scan did not walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedDir {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Live view of a running scan, shared with whoever started it.
+///
+/// The file count grows as records are added to the index; skipped
+/// directories are filled in once the walk has finished.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    files: AtomicUsize,
+    skipped_dirs: Mutex<Vec<SkippedDir>>,
+}
+
+impl ScanProgress {
+    /// Entries added to the index so far.
+    pub fn files(&self) -> usize {
+        self.files.load(Ordering::Relaxed)
+    }
+
+    /// Directories the last scan left out, draining the list.
+    pub fn take_skipped_dirs(&self) -> Vec<SkippedDir> {
+        std::mem::take(&mut *self.skipped_dirs.lock().unwrap())
+    }
+
+    pub(crate) fn add_files(&self, n: usize) {
+        self.files.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_skipped(&self, summary: &WalkSummary, excluded: &[PathBuf]) {
+        let tagged = |paths: &[PathBuf], reason| {
+            paths
+                .iter()
+                .map(move |path| SkippedDir {
+                    path: path.clone(),
+                    reason,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut dirs = self.skipped_dirs.lock().unwrap();
+        dirs.extend(tagged(excluded, SkipReason::Excluded));
+        dirs.extend(tagged(&summary.skipped_network, SkipReason::NetworkMount));
+        dirs.extend(tagged(&summary.timed_out, SkipReason::TimedOut));
+        dirs.extend(tagged(&summary.skipped_caches, SkipReason::Cache));
+    }
+}
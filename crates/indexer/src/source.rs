@@ -0,0 +1,85 @@
+//! Where the records an index is built from come from.
+//!
+//! The builder reads batches of [`FileRecord`]s from a [`RecordSource`].
+//! [`ScanSource`] walks the filesystem, or lists a repository's files, on a
+//! background thread; other sources, such as a file list, an archive
+//! scanner or records sent by a remote agent, implement the trait to be
+//! indexed by [`build_index_from_source`](crate::build_index_from_source)
+//! with the same filtering and build options as a scan.
+
+use std::{
+    path::Path,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{Error, Result};
+use blaze_fs::{FileRecord, ScanBackend, ScanContext, WalkSummary, walk_git_files, walk_parallel};
+use crossbeam::channel::{self, Receiver};
+
+/// A stream of file records to build an index from.
+///
+/// Any iterator over batches of records is a source that leaves nothing
+/// out.
+pub trait RecordSource {
+    /// The next batch of records, or `None` once there are no more.
+    fn next_batch(&mut self) -> Option<Vec<FileRecord>>;
+
+    /// Called once the source is exhausted: what it left out, or why it
+    /// failed part way.
+    fn finish(self) -> Result<WalkSummary>
+    where
+        Self: Sized,
+    {
+        Ok(WalkSummary::default())
+    }
+}
+
+impl<I> RecordSource for I
+where
+    I: Iterator<Item = Vec<FileRecord>>,
+{
+    fn next_batch(&mut self) -> Option<Vec<FileRecord>> {
+        self.next()
+    }
+}
+
+/// Records found by scanning a root with a [`ScanBackend`].
+pub struct ScanSource {
+    rx: Receiver<Vec<FileRecord>>,
+    walker: JoinHandle<Result<WalkSummary>>,
+}
+
+impl ScanSource {
+    /// Start scanning `root` on a background thread.
+    pub fn spawn(root: &Path, ctx: Arc<ScanContext>, backend: ScanBackend) -> Self {
+        let (tx, rx) = channel::unbounded::<Vec<FileRecord>>();
+
+        let num_threads = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+
+        let root = root.to_path_buf();
+        let walker = thread::spawn(move || {
+            let summary = match backend {
+                ScanBackend::Walk => walk_parallel(vec![root], tx, ctx, num_threads),
+                ScanBackend::Git => walk_git_files(&root, tx, ctx, num_threads),
+            };
+            Ok(summary?)
+        });
+
+        Self { rx, walker }
+    }
+}
+
+impl RecordSource for ScanSource {
+    fn next_batch(&mut self) -> Option<Vec<FileRecord>> {
+        self.rx.recv().ok()
+    }
+
+    fn finish(self) -> Result<WalkSummary> {
+        self.walker
+            .join()
+            .map_err(|_| Error::msg("filesystem walker thread panicked"))?
+    }
+}
@@ -0,0 +1,37 @@
+//! Records the commit blaze is built from as `BLAZE_BUILD_COMMIT`.
+//!
+//! Packagers building outside a git checkout can set `BLAZE_BUILD_COMMIT`
+//! themselves; otherwise the commit is `unknown`.
+
+use std::{path::PathBuf, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=BLAZE_BUILD_COMMIT");
+    let commit = match std::env::var("BLAZE_BUILD_COMMIT") {
+        Ok(commit) if !commit.is_empty() => commit,
+        _ => git_commit().unwrap_or_else(|| "unknown".to_string()),
+    };
+    println!("cargo:rustc-env=BLAZE_BUILD_COMMIT={commit}");
+}
+
+/// Short hash of `HEAD`, asking Cargo to rerun when it moves.
+fn git_commit() -> Option<String> {
+    let git = |args: &[&str]| {
+        let out = Command::new("git").args(args).output().ok()?;
+        out.status.success().then_some(())?;
+        Some(String::from_utf8(out.stdout).ok()?.trim().to_string())
+    };
+
+    let git_dir = PathBuf::from(git(&["rev-parse", "--absolute-git-dir"])?);
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        let loose = git_dir.join(head_ref);
+        let watched = if loose.exists() {
+            loose
+        } else {
+            git_dir.join("packed-refs")
+        };
+        println!("cargo:rerun-if-changed={}", watched.display());
+    }
+    git(&["rev-parse", "--short=12", "HEAD"])
+}
@@ -2,10 +2,83 @@ pub mod codec;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Version of the request/response messages below. Bump it whenever a
+/// message changes shape; the CLI refuses to talk to a daemon advertising a
+/// different version.
+pub const PROTOCOL_VERSION: u32 = 13;
+
+/// Commit blaze was built from, or `unknown` outside a git checkout.
+pub const BUILD_COMMIT: &str = env!("BLAZE_BUILD_COMMIT");
+
+/// Release and commit of this build, as `blaze --version` prints them.
+pub const BUILD: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("BLAZE_BUILD_COMMIT"),
+    ")"
+);
+
+/// Which build of blaze a binary is. The CLI and daemon of one install share
+/// it; when they do not, one of them was upgraded without the other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    pub commit: String,
+}
+
+impl BuildInfo {
+    /// The build of this binary.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: BUILD_COMMIT.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.version, self.commit)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryRequest {
     pub query: String,
+    /// Most hits to return; `None` returns every match.
     pub limit: Option<usize>,
+    /// Only match files at or below this absolute directory.
+    pub under: Option<String>,
+    /// Search-as-you-type session, chosen by the client. When a query is the
+    /// session's previous query typed further, the daemon re-verifies the
+    /// previous matches instead of searching the whole index.
+    pub session: Option<u64>,
+    /// Hits to skip, counted in rank order, before the first one returned:
+    /// how a client fetches the hits past a response the daemon cut short
+    /// (see [`QueryResponse::next_offset`]). `limit` counts from here.
+    pub offset: usize,
+}
+
+/// A query whose matches the daemon keeps the client informed about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscribeRequest {
+    pub query: String,
+    /// Only match files at or below this absolute directory.
+    pub under: Option<String>,
+}
+
+/// How the matches of a subscription changed, pushed by the daemon.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultsChanged {
+    /// Generation of the daemon index the matches now come from, as in
+    /// [`QueryResponse::generation`].
+    pub generation: u64,
+    /// Paths that match now but did not before, sorted.
+    pub added: Vec<String>,
+    /// Paths that matched before but no longer do, sorted.
+    pub removed: Vec<String>,
+    /// Files matching after the change.
+    pub total: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,32 +89,197 @@ pub struct QueryMetrics {
     pub exec_ms: f64,
     /// Time spent in ranking / scoring.
     pub rank_ms: f64,
+    /// Candidate counts at each pipeline stage.
+    pub candidates: CandidateCounts,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Number of candidate files surviving each pipeline stage.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CandidateCounts {
+    /// Files the query started from.
+    pub initial: usize,
+    /// Candidates left after trigram filtering.
+    pub trigram: usize,
+    /// Candidates that passed verification.
+    pub verified: usize,
+    /// Results left after the path-order filter.
+    pub filtered: usize,
+}
+
+/// What kind of entry a hit is, taken from the index's file flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitKind {
+    pub dir: bool,
+    pub symlink: bool,
+    /// The file is in a trash directory.
+    pub trash: bool,
+    /// The file has an archive or compressed-file extension.
+    pub archive: bool,
+    /// The file's name is not valid UTF-8 and is shown with the offending
+    /// bytes escaped as `\xNN`, so it is not the name on disk.
+    pub escaped: bool,
+}
+
+impl HitKind {
+    /// One-character annotation in the style of `ls -F`: `@` for symlinks,
+    /// `/` for directories, `~` for trashed files and `#` for archives.
+    /// Plain files have none.
+    pub fn marker(self) -> Option<char> {
+        if self.symlink {
+            Some('@')
+        } else if self.dir {
+            Some('/')
+        } else if self.trash {
+            Some('~')
+        } else if self.archive {
+            Some('#')
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryHit {
     pub rank: u32,
+    /// Relevance score; only comparable within one response.
+    pub score: i32,
     pub path: String,
+    pub kind: HitKind,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResponse {
     pub hits: Vec<QueryHit>,
+    /// Files matching the query, including those past the limit.
     pub total: u32,
+    /// Hits in this response: `total` cut down to the requested limit,
+    /// or to the daemon's caps.
+    pub returned: u32,
     pub metrics: Option<QueryMetrics>,
+    /// Generation of the daemon index that answered the query.
+    ///
+    /// Bumped every time the daemon swaps in a reloaded index, but starts
+    /// again at 0 when the daemon restarts; clients caching results should
+    /// compare it together with `index_created_secs`.
+    pub generation: u64,
+    /// Unix time the answering index (or its delta) was written.
+    pub index_created_secs: Option<u64>,
+    /// Root directory of the answering index.
+    pub root: Option<String>,
+    /// Whether the daemon answered from its result cache, e.g. after a
+    /// [`DaemonRequest::Prefetch`]; `metrics` are those of the original run.
+    pub cached: bool,
+    /// Whether only the matches of the session's previous query were
+    /// searched, see [`QueryRequest::session`].
+    pub refined: bool,
+    /// Which of the daemon's caps cut this response short of the requested
+    /// limit, if one did.
+    pub truncated: Option<Truncation>,
+    /// Where the next page starts, as [`QueryRequest::offset`], while
+    /// matches are left past this response.
+    pub next_offset: Option<usize>,
+}
+
+/// A cap on the size of one query response, set when the daemon starts.
+///
+/// A client wanting more hits than a cap allows asks again from
+/// [`QueryResponse::next_offset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Truncation {
+    /// At most this many hits are returned at once (`--max-hits`).
+    MaxHits(u32),
+    /// Hits past this many bytes of paths are left for the next page
+    /// (`--max-response-bytes`).
+    MaxBytes(u64),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum DaemonRequest {
     Query(QueryRequest),
+    /// The user paused while typing this query: run it and its likely
+    /// continuations in the background so the next query is answered from
+    /// the cache. Answered with [`DaemonResponse::Prefetching`] at once.
+    Prefetch(QueryRequest),
+    /// A piece of the daemon's index, packed as an index bundle, for a
+    /// daemon following this one. Answered with [`DaemonResponse::IndexChunk`],
+    /// or [`DaemonResponse::IndexUnchanged`].
+    FetchIndex(FetchIndexRequest),
+    /// Keep the connection open and send [`DaemonResponse::ResultsChanged`]
+    /// whenever the query's matches change: first with every match added,
+    /// then after each index reload that adds or removes some. The daemon
+    /// stops when the client hangs up.
+    Subscribe(SubscribeRequest),
     Ping,
     Status,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchIndexRequest {
+    /// Id of the bundle the follower already has: if the daemon's index is
+    /// still that one, it answers [`DaemonResponse::IndexUnchanged`].
+    pub have: Option<u64>,
+    /// Where in the bundle to start.
+    pub offset: u64,
+    /// Most bytes to return; the daemon may return fewer.
+    pub max_len: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexChunk {
+    /// Identifies the bundle's contents. A follower that sees it change
+    /// midway has to start over, as the daemon swapped in a new index.
+    pub bundle: u64,
+    /// Length of the whole bundle.
+    pub total_len: u64,
+    pub offset: u64,
+    /// Empty past the end of the bundle.
+    pub data: Vec<u8>,
+    /// CRC32 of `data`.
+    pub crc32: u32,
+}
+
+/// How far the daemon has got with loading its index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Readiness {
+    /// The index is still being opened or built; queries wait for it.
+    Starting,
+    /// The index is open and its hot sections are in memory; the rest is
+    /// still being paged in, so queries may be slower than usual.
+    Warming,
+    /// The whole index has been paged in.
+    Ready,
+}
+
+impl Readiness {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Readiness::Starting => "starting",
+            Readiness::Warming => "warming",
+            Readiness::Ready => "ready",
+        }
+    }
+}
+
+/// Answer to [`DaemonRequest::Ping`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pong {
+    pub readiness: Readiness,
+    /// Build of the daemon, to compare against the client's own.
+    pub build: BuildInfo,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum DaemonResponse {
     QueryResult(QueryResponse),
-    Pong,
+    Pong(Pong),
     Status(String),
+    /// Number of queries a [`DaemonRequest::Prefetch`] will run.
+    Prefetching(u32),
+    IndexChunk(IndexChunk),
+    /// The daemon's index is the bundle the follower already has.
+    IndexUnchanged,
+    /// Pushed on a [`DaemonRequest::Subscribe`] connection.
+    ResultsChanged(ResultsChanged),
     Error(String),
 }
@@ -0,0 +1,130 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::config_file::{ConfigFile, dotted, expand_home};
+
+/// Where a boost applies.
+#[derive(Debug, Clone)]
+enum DirMatcher {
+    /// This directory and everything below it.
+    Prefix(String),
+    /// Directories matching the glob, and everything below them.
+    Glob(GlobMatcher),
+}
+
+#[derive(Debug, Clone)]
+struct BoostRule {
+    /// The key as written in the config file.
+    pattern: String,
+    matcher: DirMatcher,
+    score: i32,
+}
+
+/// Constant score boosts for files under configured directories, from the
+/// `[boost]` table of the config file:
+///
+/// ```toml
+/// [boost]
+/// "~/work/acme/**" = 50
+/// "~/work/*/vendor" = -30
+/// ```
+///
+/// Keys are directory paths or globs, with `~` standing for the home
+/// directory; a trailing `/**` is implied. When several rules match, the
+/// last one listed wins.
+#[derive(Debug, Clone, Default)]
+pub struct BoostRules {
+    rules: Vec<BoostRule>,
+}
+
+impl BoostRules {
+    /// Boosts from the `[boost]` table of `file`. Entries that cannot be
+    /// read are skipped with a warning.
+    pub fn resolve(file: &ConfigFile, warnings: &mut Vec<String>) -> Self {
+        let home = std::env::var("HOME").ok();
+        let mut rules = Vec::new();
+        for (key, value) in file.table("boost", warnings).into_iter().flatten() {
+            let Some(score) = value.as_integer().and_then(|n| i32::try_from(n).ok()) else {
+                warnings.push(file.bad_value("boost", key, value));
+                continue;
+            };
+            let Some(matcher) = DirMatcher::new(&expand_home(key, home.as_deref())) else {
+                let msg = format!("ignoring `{}`: bad glob pattern", dotted("boost", key));
+                warnings.push(file.warning(msg));
+                continue;
+            };
+            rules.push(BoostRule {
+                pattern: key.clone(),
+                matcher,
+                score,
+            });
+        }
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Boost for files directly in the absolute directory `dir`: the score
+    /// of the last rule covering it, or `0`.
+    pub fn score_for_dir(&self, dir: &str) -> i32 {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matcher.covers(dir))
+            .map_or(0, |rule| rule.score)
+    }
+
+    /// Each rule's pattern, as written, and score, in config file order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.pattern.as_str(), rule.score))
+    }
+}
+
+impl DirMatcher {
+    fn new(pattern: &str) -> Option<Self> {
+        let pattern = pattern.strip_suffix("/**").unwrap_or(pattern);
+        let pattern = match pattern.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+
+        if !pattern.contains(['*', '?', '[', ']', '{', '}', '\\']) {
+            return Some(Self::Prefix(pattern.to_owned()));
+        }
+        GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()
+            .ok()
+            .map(|g| Self::Glob(g.compile_matcher()))
+    }
+
+    /// Whether `dir` is, or is below, a matching directory.
+    fn covers(&self, dir: &str) -> bool {
+        match self {
+            Self::Prefix(prefix) => {
+                prefix == "/"
+                    || dir
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+            Self::Glob(glob) => ancestors(dir).any(|d| glob.is_match(d)),
+        }
+    }
+}
+
+/// `dir` followed by each of its parents, up to `/`.
+fn ancestors(dir: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(dir), |d| match d.rsplit_once('/') {
+        Some(("", "")) | None => None,
+        Some(("", _)) => Some("/"),
+        Some((parent, _)) => Some(parent),
+    })
+}
+
+#[cfg(test)]
+#[path = "boost_tests.rs"]
+mod tests;
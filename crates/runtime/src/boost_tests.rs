@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use super::*;
+
+fn resolve(contents: &str) -> (BoostRules, Vec<String>) {
+    let file = ConfigFile::parse(PathBuf::from("config.toml"), contents);
+    let mut warnings = file.warnings.clone();
+    let rules = BoostRules::resolve(&file, &mut warnings);
+    (rules, warnings)
+}
+
+#[test]
+fn boosts_cover_directories_and_everything_below() {
+    let (rules, warnings) = resolve(
+        "# user config\n\
+         [other]\n\
+         \"/ignored\" = 5\n\
+         \n\
+         [boost]\n\
+         \"/home/me/work/**\" = 20\n\
+         \"/home/me/work/acme\" = +50\n\
+         \"/home/me/work/*/vendor\" = -30\n",
+    );
+
+    assert!(warnings.is_empty(), "{warnings:?}");
+    assert_eq!(rules.score_for_dir("/home/me/work"), 20);
+    assert_eq!(rules.score_for_dir("/home/me/work/other/src"), 20);
+    assert_eq!(rules.score_for_dir("/home/me/work/acme"), 50);
+    assert_eq!(rules.score_for_dir("/home/me/work/acme/src"), 50);
+    assert_eq!(rules.score_for_dir("/home/me/work/acme/vendor/x"), -30);
+    assert_eq!(rules.score_for_dir("/home/me/workshop"), 0);
+    assert_eq!(rules.score_for_dir("/ignored"), 0);
+    assert_eq!(
+        rules.entries().collect::<Vec<_>>(),
+        [
+            ("/home/me/work/**", 20),
+            ("/home/me/work/acme", 50),
+            ("/home/me/work/*/vendor", -30),
+        ]
+    );
+}
+
+#[test]
+fn invalid_entries_are_skipped_with_a_warning() {
+    let (rules, warnings) = resolve("[boost]\n\"/a\" = \"lots\"\n\"/b/[\" = 1\n\"/c\" = 2\n");
+    assert_eq!(rules.entries().collect::<Vec<_>>(), [("/c", 2)]);
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[1].contains("bad glob pattern"));
+
+    let (rules, warnings) = resolve("[boost]\n\"/a\n");
+    assert!(rules.is_empty());
+    assert!(warnings[0].contains("line 2"), "{warnings:?}");
+
+    let (rules, warnings) = resolve("boost = 5\n");
+    assert!(rules.is_empty());
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(resolve("").0.is_empty());
+}
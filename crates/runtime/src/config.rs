@@ -1,9 +1,35 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::OnceLock};
 
 pub const PROGRAM_NAME: &str = "blaze";
 pub const PROGRAM_LOG_LEVEL: &str = "BLAZE_LOG_LEVEL";
 // TODO - Change this to be dynamically generated
 pub const INDEX_FILE_NAME: &str = "index.bin";
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+/// Index file the CLI reads and the daemon serves, when not given as a flag.
+pub const INDEX_PATH_ENV: &str = "BLAZE_INDEX_PATH";
+/// Socket the daemon listens on, when not given as a flag.
+pub const SOCKET_PATH_ENV: &str = "BLAZE_SOCKET_PATH";
+/// Paths to leave out of every index, separated like `PATH`.
+pub const EXCLUDE_PATHS_ENV: &str = "BLAZE_EXCLUDE_PATHS";
+/// `utc` reads query dates and times without an offset as UTC instead of
+/// local time.
+pub const TIMEZONE_ENV: &str = "BLAZE_TIMEZONE";
+/// Set to `1` to print a per-query profile of engine hot paths.
+pub const PROFILE_ENV: &str = "BLAZE_PROFILE";
+/// Results `blaze query` shows without `-n`; `0` shows them all.
+pub const LIMIT_ENV: &str = "BLAZE_LIMIT";
+/// Results `blaze query` shows when neither `-n` nor [`LIMIT_ENV`] is given.
+pub const DEFAULT_QUERY_LIMIT: usize = 20;
+/// Resident memory the daemon may use, e.g. `512M`, when `--memory-limit`
+/// is not given.
+pub const MEMORY_LIMIT_ENV: &str = "BLAZE_MEMORY_LIMIT";
+/// Where `blaze index --system` writes the shared index of system paths.
+#[cfg(unix)]
+pub const SYSTEM_INDEX_PATH: &str = "/var/lib/blaze/system.bin";
+#[cfg(not(unix))]
+pub const SYSTEM_INDEX_PATH: &str = "C:/ProgramData/blaze/system.bin";
+/// Set to `1` to refuse indexes that do not match their BLAKE3 manifest.
+pub const VERIFY_MANIFEST_ENV: &str = "BLAZE_VERIFY_MANIFEST";
 
 pub fn xdg_or_home(xdg_var: &str, home_suffix: &str) -> PathBuf {
     if let Some(dir) = std::env::var_os(xdg_var) {
@@ -50,6 +76,26 @@ pub fn default_scan_root() -> PathBuf {
     }
 }
 
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Read the config file at `path` instead of the user's, as `--config`
+/// does. Only the first call counts, and it must come before anything
+/// reads the config file.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+/// The config file given to [`set_config_path`], or else the user's,
+/// `$XDG_CONFIG_HOME/blaze/config.toml`.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = CONFIG_PATH.get() {
+        return path.clone();
+    }
+    xdg_or_home("XDG_CONFIG_HOME", ".config")
+        .join(PROGRAM_NAME)
+        .join(CONFIG_FILE_NAME)
+}
+
 pub fn blaze_dir() -> PathBuf {
     xdg_or_home("XDG_CACHE_HOME", ".cache").join(PROGRAM_NAME)
 }
@@ -59,6 +105,16 @@ pub fn default_index_path() -> PathBuf {
     blaze_dir().join(INDEX_FILE_NAME)
 }
 
+/// Maximum number of history events kept when the log is rotated.
+pub const HISTORY_MAX_EVENTS: usize = 10_000;
+
+/// History events older than this many days are dropped on rotation.
+pub const HISTORY_MAX_AGE_DAYS: u32 = 180;
+
+/// History log size in bytes that triggers rotation on the next append;
+/// rotation leaves at most half of it.
+pub const HISTORY_ROTATE_BYTES: u64 = 4 * 1024 * 1024;
+
 /// Default project-relative ignore patterns for common build artifacts, VCS dirs, etc.
 pub const DEFAULT_PROJECT_IGNORE_PATTERNS: &[&str] = &[
     "venv/",
@@ -105,7 +161,7 @@ pub const SYSTEM_ROOTS: &[&str] = &[
 ];
 
 #[cfg(target_os = "macos")]
-const SYSTEM_ROOTS: &[&str] = &[
+pub const SYSTEM_ROOTS: &[&str] = &[
     "/system/",
     "/library/",
     "/applications/",
@@ -119,8 +175,8 @@ const SYSTEM_ROOTS: &[&str] = &[
 
 // Fallback for other platforms
 #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub const SYSTEM_ROOTS: &[&str] = &["/usr/", "/lib/", "/opt/", "/var/", "/etc/"];
 
-const SYSTEM_ROOTS: &[&str] = &["/usr/", "/lib/", "/opt/", "/var/", "/etc/"];
 /// Build, dependency, and VCS directories
 /// All entries must be lowercase for case-insensitive matching on macOS
 pub const NOISY_COMPONENTS: &[&str] = &[
@@ -154,6 +210,66 @@ pub const CACHE_COMPONENTS: &[&str] = &[
     "__pycache__",
 ];
 
+/// Extension aliases consulted by `ext:` queries.
+/// Each entry maps an alias to its canonical extension (alias, canonical).
+/// All entries must be normalized (see `normalize_ext`).
+pub const EXT_ALIASES: &[(&str, &str)] = &[
+    ("jpeg", "jpg"),
+    ("jpe", "jpg"),
+    ("tiff", "tif"),
+    ("htm", "html"),
+    ("markdown", "md"),
+    ("mkd", "md"),
+    ("yml", "yaml"),
+    ("mpeg", "mpg"),
+    ("cxx", "cpp"),
+    ("cc", "cpp"),
+    ("hxx", "hpp"),
+];
+
+/// Extensions of archive and compressed files, marked as such in results.
+pub const ARCHIVE_EXTS: &[&str] = &[
+    "7z", "apk", "bz2", "cab", "deb", "dmg", "ear", "gz", "iso", "jar", "lz", "lz4", "lzma", "rar",
+    "rpm", "tar", "tbz2", "tgz", "txz", "war", "whl", "xz", "z", "zip", "zst",
+];
+
+/// Largest directory (by sampled file count) that `blaze query --root`
+/// indexes on the fly when it has no index of its own.
+pub const AUTO_INDEX_MAX_FILES: usize = 20_000;
+
+/// Largest file whose contents `blaze index --hash` hashes by default.
+pub const DEFAULT_HASH_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Seconds to wait for one directory to be listed before skipping it.
+pub const DIR_TIMEOUT_SECS: u64 = 60;
+
+/// Directories on network filesystems listed at the same time during a scan.
+pub const NETWORK_SCAN_THREADS: usize = 2;
+
+/// Seconds to wait for one network directory to be listed before giving up on it.
+pub const NETWORK_OP_TIMEOUT_SECS: u64 = 10;
+
+/// Files or directories whose presence marks a project root.
+/// Checked in order at each ancestor of the working directory.
+pub const PROJECT_MARKERS: &[&str] = &[
+    ".blaze",
+    ".git",
+    ".hg",
+    ".svn",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "Gemfile",
+    "composer.json",
+    "mix.exs",
+];
+
+/// Per-project directory that, when present, holds the project's own index.
+pub const PROJECT_DIR_NAME: &str = ".blaze";
+
 /// Log and debug directories
 pub const LOG_COMPONENTS: &[&str] = &[
     "logs",
@@ -0,0 +1,160 @@
+//! The config file, parsed once into a TOML table that each group of
+//! settings reads its keys from: the top-level keys for
+//! [`Settings`](crate::Settings), `[boost]` for
+//! [`BoostRules`](crate::BoostRules), `[planner]` for
+//! [`PlannerOverrides`](crate::PlannerOverrides) and `[noise]` for
+//! [`NoiseComponents`](crate::NoiseComponents).
+//!
+//! Nothing here fails: a file that cannot be read or parsed holds no
+//! values, and a value that cannot be read is skipped. Either way a warning
+//! is recorded and the layers below stay in effect.
+
+use std::{fs, io, path::PathBuf};
+
+use toml::{Table, Value};
+
+use crate::config::config_path;
+
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    /// The file read, whether or not it exists.
+    pub path: PathBuf,
+    table: Table,
+    /// Why the file could not be read, if it exists and could not.
+    pub warnings: Vec<String>,
+}
+
+impl ConfigFile {
+    /// The config file at [`config_path`].
+    pub fn load() -> Self {
+        Self::read(config_path())
+    }
+
+    /// The config file at `path`. A missing file holds no values.
+    pub fn read(path: PathBuf) -> Self {
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(path, &contents),
+            Err(e) => {
+                let mut file = Self {
+                    path,
+                    ..Self::default()
+                };
+                if e.kind() != io::ErrorKind::NotFound {
+                    let msg = format!("failed to read {}: {e}", file.path.display());
+                    file.warnings.push(msg);
+                }
+                file
+            }
+        }
+    }
+
+    /// The config file `path`, holding `contents`.
+    pub fn parse(path: PathBuf, contents: &str) -> Self {
+        match toml::from_str(contents) {
+            Ok(table) => Self {
+                path,
+                table,
+                warnings: Vec::new(),
+            },
+            Err(e) => {
+                let line = e.span().map_or(1, |span| {
+                    1 + contents[..span.start]
+                        .bytes()
+                        .filter(|&b| b == b'\n')
+                        .count()
+                });
+                let msg = format!("{} line {line}: {}", path.display(), e.message());
+                Self {
+                    path,
+                    table: Table::new(),
+                    warnings: vec![msg],
+                }
+            }
+        }
+    }
+
+    /// The top-level keys and values, tables included.
+    pub(crate) fn top_level(&self) -> &Table {
+        &self.table
+    }
+
+    /// The keys and values of the table `[name]`, if the file has one.
+    pub(crate) fn table(&self, name: &str, warnings: &mut Vec<String>) -> Option<&Table> {
+        match self.table.get(name)? {
+            Value::Table(table) => Some(table),
+            _ => {
+                warnings.push(self.warning(format!("ignoring `{name}`: expected a table")));
+                None
+            }
+        }
+    }
+
+    /// `msg` about a value in this file.
+    pub(crate) fn warning(&self, msg: String) -> String {
+        format!("{}: {msg}", self.path.display())
+    }
+
+    /// Warning for the `key` of table `[table]`, or a top-level key if
+    /// `table` is empty, whose `value` could not be read.
+    pub(crate) fn bad_value(&self, table: &str, key: &str, value: &Value) -> String {
+        self.warning(format!(
+            "ignoring `{}`: bad value {value}",
+            dotted(table, key)
+        ))
+    }
+
+    /// Warning for the `key` of table `[table]` that no setting reads.
+    pub(crate) fn unknown_key(&self, table: &str, key: &str) -> String {
+        self.warning(format!("unknown setting `{}`", dotted(table, key)))
+    }
+}
+
+/// `key` of table `[table]` as a dotted key, e.g. `boost."~/src"`, or
+/// just `key` if `table` is empty.
+pub(crate) fn dotted(table: &str, key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let key = if bare {
+        key.to_owned()
+    } else {
+        format!("{key:?}")
+    };
+    if table.is_empty() {
+        key
+    } else {
+        format!("{table}.{key}")
+    }
+}
+
+/// A non-negative integer.
+pub(crate) fn as_count(value: &Value) -> Option<usize> {
+    value.as_integer().and_then(|n| usize::try_from(n).ok())
+}
+
+/// A single string, or an array of strings.
+pub(crate) fn as_strings(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::String(s) => Some(vec![s.clone()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_owned))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Replace a leading `~` with the home directory.
+pub(crate) fn expand_home(pattern: &str, home: Option<&str>) -> String {
+    match (pattern.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{rest}", home.trim_end_matches('/'))
+        }
+        _ => pattern.to_owned(),
+    }
+}
+
+#[cfg(test)]
+#[path = "config_file_tests.rs"]
+mod tests;
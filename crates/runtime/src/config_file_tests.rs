@@ -0,0 +1,53 @@
+use super::*;
+
+#[test]
+fn missing_file_holds_no_values() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let file = ConfigFile::read(dir.path().join("config.toml"));
+    assert!(file.top_level().is_empty());
+    assert!(file.warnings.is_empty());
+
+    // A directory cannot be read as a file.
+    let file = ConfigFile::read(dir.path().to_path_buf());
+    assert!(file.top_level().is_empty());
+    assert_eq!(file.warnings.len(), 1, "{:?}", file.warnings);
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    let file = ConfigFile::parse(PathBuf::from("config.toml"), "limit = 5\n\n[boost\n");
+    assert!(file.top_level().is_empty());
+    assert_eq!(file.warnings.len(), 1);
+    assert!(
+        file.warnings[0].starts_with("config.toml line 3: "),
+        "{:?}",
+        file.warnings
+    );
+}
+
+#[test]
+fn tables_must_be_tables() {
+    let file = ConfigFile::parse(PathBuf::from("config.toml"), "planner = 5\n");
+    let mut warnings = Vec::new();
+    assert!(file.table("planner", &mut warnings).is_none());
+    assert!(file.table("boost", &mut warnings).is_none());
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+}
+
+#[test]
+fn keys_are_quoted_unless_bare() {
+    assert_eq!(dotted("", "limit"), "limit");
+    assert_eq!(
+        dotted("planner", "early_verify_cutoff"),
+        "planner.early_verify_cutoff"
+    );
+    assert_eq!(dotted("boost", "~/work/**"), "boost.\"~/work/**\"");
+}
+
+#[test]
+fn tilde_means_home() {
+    assert_eq!(expand_home("~/work", Some("/home/me/")), "/home/me/work");
+    assert_eq!(expand_home("~", Some("/home/me")), "/home/me");
+    assert_eq!(expand_home("~bob/work", Some("/home/me")), "~bob/work");
+    assert_eq!(expand_home("~/work", None), "~/work");
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "native")]
+use std::io::Write;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blaze_dir;
+
+/// Socket the daemon listens on unless told otherwise.
+pub fn default_socket_path() -> PathBuf {
+    blaze_dir().join("daemon.sock")
+}
+
+/// Location of the file a running daemon advertises itself in.
+pub fn discovery_path() -> PathBuf {
+    blaze_dir().join("daemon.json")
+}
+
+/// What a running daemon tells clients about itself.
+///
+/// The daemon writes this on startup so the CLI connects to the socket it
+/// actually listens on, even when `--socket-path` or `--index-path` were
+/// customised, can refuse to talk to a daemon with another protocol, and can
+/// tell when the daemon is another build than itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonDiscovery {
+    pub socket_path: PathBuf,
+    pub pid: u32,
+    pub index_path: PathBuf,
+    pub protocol_version: u32,
+    /// Release and commit of the daemon, as `blaze --version` prints them.
+    /// Absent from files written by daemons that predate it.
+    #[serde(default)]
+    pub build: Option<String>,
+}
+
+impl DaemonDiscovery {
+    /// Read a discovery file. A missing file means no daemon advertised itself.
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write the discovery file atomically.
+    #[cfg(feature = "native")]
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer_pretty(&mut tmp, self).map_err(io::Error::other)?;
+        writeln!(tmp)?;
+        tmp.as_file().sync_all()?;
+        tmp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Remove the discovery file if it still belongs to process `pid`.
+    ///
+    /// A daemon started later may have replaced the file; that one is left
+    /// alone. Returns whether the file was removed.
+    pub fn remove_if_owned(path: &Path, pid: u32) -> io::Result<bool> {
+        match Self::read(path) {
+            Ok(Some(d)) if d.pid == pid => {}
+            Ok(_) => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+#[path = "discovery_tests.rs"]
+mod tests;
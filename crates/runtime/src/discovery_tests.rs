@@ -0,0 +1,63 @@
+use super::*;
+use tempfile::tempdir;
+
+fn discovery(pid: u32) -> DaemonDiscovery {
+    DaemonDiscovery {
+        socket_path: PathBuf::from("/run/user/1000/blaze.sock"),
+        pid,
+        index_path: PathBuf::from("/data/index.bin"),
+        protocol_version: 3,
+        build: Some("0.2.0 (0123456789ab)".to_string()),
+    }
+}
+
+#[test]
+fn write_read_round_trip() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("cache/daemon.json");
+
+    assert_eq!(DaemonDiscovery::read(&path).expect("read missing"), None);
+
+    discovery(42).write(&path).expect("write");
+    assert_eq!(
+        DaemonDiscovery::read(&path).expect("read"),
+        Some(discovery(42))
+    );
+}
+
+#[test]
+fn files_from_older_daemons_have_no_build() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("daemon.json");
+    let old = r#"{"socket_path": "/s", "pid": 7, "index_path": "/i", "protocol_version": 11}"#;
+    fs::write(&path, old).unwrap();
+
+    let read = DaemonDiscovery::read(&path)
+        .expect("read")
+        .expect("present");
+    assert_eq!((read.protocol_version, read.build), (11, None));
+}
+
+#[test]
+fn malformed_file_is_invalid_data() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("daemon.json");
+    fs::write(&path, "{not json").unwrap();
+
+    let err = DaemonDiscovery::read(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn remove_only_when_owned() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("daemon.json");
+    discovery(42).write(&path).expect("write");
+
+    assert!(!DaemonDiscovery::remove_if_owned(&path, 7).expect("remove other"));
+    assert!(path.exists());
+
+    assert!(DaemonDiscovery::remove_if_owned(&path, 42).expect("remove own"));
+    assert!(!path.exists());
+    assert!(!DaemonDiscovery::remove_if_owned(&path, 42).expect("remove missing"));
+}
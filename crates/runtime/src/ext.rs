@@ -0,0 +1,59 @@
+use std::borrow::Cow;
+
+use unicode_normalization::{UnicodeNormalization, is_nfc_quick};
+
+use crate::config::{ARCHIVE_EXTS, EXT_ALIASES};
+
+/// Normalize a raw file extension for storage and comparison.
+///
+/// This is the single source of truth shared by the walker, the index
+/// builder and `ext:` predicate parsing, so that every side agrees on what
+/// an extension looks like:
+///
+/// - Surrounding whitespace and a single leading `.` are stripped.
+/// - The result is Unicode NFC (macOS hands out NFD names).
+/// - The result is lowercased (ASCII fast path, full Unicode otherwise).
+///
+/// Returns `None` if nothing is left after stripping.
+pub fn normalize_ext(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    let trimmed = trimmed.strip_prefix('.').unwrap_or(trimmed);
+
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    if trimmed.is_ascii() {
+        return Some(trimmed.to_ascii_lowercase());
+    }
+
+    let composed: Cow<'_, str> = match is_nfc_quick(trimmed.chars()) {
+        unicode_normalization::IsNormalized::Yes => Cow::Borrowed(trimmed),
+        _ => Cow::Owned(trimmed.nfc().collect()),
+    };
+
+    Some(composed.to_lowercase())
+}
+
+/// Resolve an already-normalized extension to its canonical form.
+///
+/// Extensions without an alias entry are returned unchanged.
+#[inline]
+pub fn canonical_ext(ext: &str) -> &str {
+    EXT_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == ext)
+        .map(|(_, canonical)| *canonical)
+        .unwrap_or(ext)
+}
+
+/// Whether a normalized extension belongs to an archive or compressed file.
+///
+/// Only the last extension is stored, so `tar.gz` files count through `gz`.
+pub fn is_archive_ext(ext: &str) -> bool {
+    ARCHIVE_EXTS.contains(&canonical_ext(ext))
+}
+
+#[cfg(test)]
+#[path = "ext_tests.rs"]
+mod tests;
@@ -0,0 +1,61 @@
+use super::*;
+
+#[test]
+fn normalize_ext_handles_case_and_dots() {
+    let cases: &[(&str, Option<&str>)] = &[
+        ("pdf", Some("pdf")),
+        ("PDF", Some("pdf")),
+        (".PDF", Some("pdf")),
+        ("  .Rs ", Some("rs")),
+        ("tar.GZ", Some("tar.gz")),
+        ("", None),
+        (".", None),
+        ("   ", None),
+    ];
+
+    for (input, expected) in cases {
+        let got = normalize_ext(input);
+        assert_eq!(got.as_deref(), *expected, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn normalize_ext_composes_and_lowercases_unicode() {
+    // "É" as NFD (E + combining acute) and NFC (precomposed).
+    let nfd = "E\u{0301}XT";
+    let nfc = "\u{00C9}xt";
+
+    let a = normalize_ext(nfd).unwrap();
+    let b = normalize_ext(nfc).unwrap();
+
+    assert_eq!(a, b);
+    assert_eq!(a, "\u{00E9}xt");
+}
+
+#[test]
+fn canonical_ext_resolves_aliases() {
+    assert_eq!(canonical_ext("jpeg"), "jpg");
+    assert_eq!(canonical_ext("jpg"), "jpg");
+    assert_eq!(canonical_ext("htm"), "html");
+    assert_eq!(canonical_ext("rs"), "rs");
+    assert_eq!(canonical_ext(""), "");
+}
+
+#[test]
+fn ext_aliases_are_normalized() {
+    for (alias, canonical) in EXT_ALIASES {
+        assert_eq!(normalize_ext(alias).as_deref(), Some(*alias));
+        assert_eq!(normalize_ext(canonical).as_deref(), Some(*canonical));
+        assert_ne!(alias, canonical);
+    }
+}
+
+#[test]
+fn archive_exts_are_recognised() {
+    for ext in ["zip", "gz", "tgz", "7z", "jar"] {
+        assert!(is_archive_ext(ext), "{ext}");
+    }
+    for ext in ["rs", "md", "", "gzip-notes"] {
+        assert!(!is_archive_ext(ext), "{ext}");
+    }
+}
@@ -1,23 +1,58 @@
+use std::{borrow::Cow, env, fmt, path::PathBuf};
+#[cfg(feature = "native")]
 use std::{
-    env,
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
     fs::{self, File, OpenOptions},
     io::{self, BufRead, BufReader, Write},
-    path::{Path, PathBuf},
+    path::Path,
+    sync::OnceLock,
 };
 
 use chrono::{DateTime, Utc};
 use log::debug;
 use serde::{Deserialize, Serialize};
 
+use crate::{HISTORY_MAX_AGE_DAYS, HISTORY_MAX_EVENTS, HISTORY_ROTATE_BYTES};
+
 pub const HISTORY_VERSION: u8 = 1;
 
 pub const HISTORY_DISABLED_ENV: &str = "BLAZE_HISTORY";
 
+/// Controls how query strings are stored: `plain` (default), `hash` or `redact`.
+pub const HISTORY_ANONYMIZE_ENV: &str = "BLAZE_HISTORY_ANONYMIZE";
+
+/// Overrides [`HISTORY_MAX_EVENTS`]; `0` disables the count limit.
+pub const HISTORY_MAX_EVENTS_ENV: &str = "BLAZE_HISTORY_MAX_EVENTS";
+
+/// Overrides [`HISTORY_MAX_AGE_DAYS`]; `0` disables the age limit.
+pub const HISTORY_MAX_DAYS_ENV: &str = "BLAZE_HISTORY_MAX_DAYS";
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum HistoryEvent {
     Query(QueryEvent),
 }
 
+/// Which process executed (and logged) a query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryOrigin {
+    /// Query ran in-process in the CLI.
+    #[default]
+    Cli,
+    /// Query ran inside the background daemon.
+    Daemon,
+}
+
+impl fmt::Display for QueryOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryOrigin::Cli => f.pad("cli"),
+            QueryOrigin::Daemon => f.pad("daemon"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct QueryEvent {
     /// Schema version
@@ -26,14 +61,23 @@ pub struct QueryEvent {
     /// Timestamp
     pub timestamp: DateTime<Utc>,
 
-    /// Raw query string
+    /// Raw query string. Empty when the query was hashed or redacted.
     pub raw_query: String,
 
+    /// Salted hash of the query string, set when history is anonymized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_hash: Option<String>,
+
     /// Number of results returned.
     pub hits: usize,
 
     /// Query execution time in milliseconds.
     pub duration_ms: u32,
+
+    /// Process that executed the query. Older entries predate this field
+    /// and were always written by the CLI.
+    #[serde(default)]
+    pub origin: QueryOrigin,
 }
 
 impl QueryEvent {
@@ -42,14 +86,147 @@ impl QueryEvent {
             version: HISTORY_VERSION,
             timestamp: Utc::now(),
             raw_query,
+            query_hash: None,
             hits,
             duration_ms,
+            origin: QueryOrigin::Cli,
+        }
+    }
+
+    /// Query text suitable for display, without revealing anonymized queries.
+    pub fn display_query(&self) -> Cow<'_, str> {
+        match &self.query_hash {
+            Some(hash) => Cow::Owned(format!("<hash:{}>", &hash[..hash.len().min(12)])),
+            None if self.raw_query.is_empty() => Cow::Borrowed("<redacted>"),
+            None => Cow::Borrowed(&self.raw_query),
+        }
+    }
+
+    /// Set the process that executed this query.
+    pub fn with_origin(mut self, origin: QueryOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+/// How query strings are written to the history log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HistoryPrivacy {
+    /// Store the query string as typed.
+    #[default]
+    Plain,
+    /// Store a salted hash of the query instead of its text.
+    Hash,
+    /// Store only metadata (hits, timing, origin).
+    Redact,
+}
+
+impl HistoryPrivacy {
+    /// Read the privacy mode from [`HISTORY_ANONYMIZE_ENV`].
+    pub fn from_env() -> Self {
+        match env::var(HISTORY_ANONYMIZE_ENV) {
+            Ok(val) => match val.trim().to_ascii_lowercase().as_str() {
+                "" | "0" | "false" | "plain" | "off" => HistoryPrivacy::Plain,
+                "redact" => HistoryPrivacy::Redact,
+                // Any other truthy value opts into hashing.
+                _ => HistoryPrivacy::Hash,
+            },
+            Err(_) => HistoryPrivacy::Plain,
+        }
+    }
+}
+
+/// How much history to keep when the log is rotated or pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep at most this many of the newest events.
+    pub max_events: Option<usize>,
+    /// Drop events older than this many days.
+    pub max_age_days: Option<u32>,
+    /// Rotate once the log grows past this many bytes, dropping the oldest
+    /// events until it is at most half as long.
+    pub rotate_bytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_events: Some(HISTORY_MAX_EVENTS),
+            max_age_days: Some(HISTORY_MAX_AGE_DAYS),
+            rotate_bytes: HISTORY_ROTATE_BYTES,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Default policy with any environment overrides applied.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+
+        if let Some(n) = env_limit::<usize>(HISTORY_MAX_EVENTS_ENV) {
+            policy.max_events = n;
         }
+        if let Some(days) = env_limit::<u32>(HISTORY_MAX_DAYS_ENV) {
+            policy.max_age_days = days;
+        }
+
+        policy
+    }
+
+    /// Apply this policy to `events`, returning the ones to keep in
+    /// chronological order.
+    #[cfg(feature = "native")]
+    fn apply(&self, mut events: Vec<QueryEvent>, now: DateTime<Utc>) -> Vec<QueryEvent> {
+        events.sort_by_key(|q| q.timestamp);
+
+        if let Some(days) = self.max_age_days {
+            let cutoff = now - chrono::Duration::days(i64::from(days));
+            events.retain(|q| q.timestamp >= cutoff);
+        }
+
+        if let Some(max) = self.max_events
+            && events.len() > max
+        {
+            events.drain(..events.len() - max);
+        }
+
+        events
     }
 }
 
+/// Parse a numeric limit from the environment.
+///
+/// Returns `Some(None)` for `0` (no limit) and `None` when unset or invalid.
+fn env_limit<T>(var: &str) -> Option<Option<T>>
+where
+    T: std::str::FromStr + PartialEq + Default,
+{
+    let raw = env::var(var).ok()?;
+    match raw.trim().parse::<T>() {
+        Ok(v) if v == T::default() => Some(None),
+        Ok(v) => Some(Some(v)),
+        Err(_) => {
+            debug!("Ignoring invalid {var}={raw}");
+            None
+        }
+    }
+}
+
+/// Outcome of pruning the history log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    /// Events left in the log.
+    pub kept: usize,
+    /// Events removed from the log.
+    pub removed: usize,
+}
+
+#[cfg(feature = "native")]
 pub struct HistoryStore {
     path: PathBuf,
+    retention: RetentionPolicy,
+    privacy: HistoryPrivacy,
+    salt: OnceLock<Option<[u8; 32]>>,
 }
 
 pub fn state_dir() -> Option<PathBuf> {
@@ -70,6 +247,7 @@ pub fn history_log_path() -> Option<PathBuf> {
     state_dir().map(|d| d.join("history.log"))
 }
 
+#[cfg(feature = "native")]
 fn history_disabled() -> bool {
     match env::var(HISTORY_DISABLED_ENV) {
         Ok(val) => val == "0" || val.eq_ignore_ascii_case("false"),
@@ -77,6 +255,7 @@ fn history_disabled() -> bool {
     }
 }
 
+#[cfg(feature = "native")]
 impl HistoryStore {
     // TODO: Use different history path
     pub fn new() -> Option<Self> {
@@ -85,20 +264,145 @@ impl HistoryStore {
         }
 
         let path = history_log_path()?;
-        Some(Self { path })
+        Some(Self {
+            path,
+            retention: RetentionPolicy::from_env(),
+            privacy: HistoryPrivacy::from_env(),
+            salt: OnceLock::new(),
+        })
     }
 
     /// Create a history store with a custom path (for testing).
     #[cfg(test)]
     pub fn with_path(path: PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            retention: RetentionPolicy::default(),
+            privacy: HistoryPrivacy::Plain,
+            salt: OnceLock::new(),
+        }
+    }
+
+    /// Replace the retention policy used for automatic rotation.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn retention(&self) -> &RetentionPolicy {
+        &self.retention
+    }
+
+    /// Replace the privacy mode applied to logged queries.
+    pub fn with_privacy(mut self, privacy: HistoryPrivacy) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    pub fn privacy(&self) -> HistoryPrivacy {
+        self.privacy
+    }
+
+    /// Per-user random salt, stored next to the log and created on first use.
+    fn salt(&self) -> Option<&[u8; 32]> {
+        self.salt
+            .get_or_init(|| {
+                self.load_or_create_salt()
+                    .inspect_err(|e| debug!("Failed to load history salt: {e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    fn load_or_create_salt(&self) -> io::Result<[u8; 32]> {
+        let path = self.path.with_file_name("history.salt");
+
+        if !path.exists() {
+            let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+            fs::create_dir_all(dir)?;
+
+            let mut salt = [0u8; 32];
+            getrandom::fill(&mut salt).map_err(io::Error::other)?;
+
+            let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+            tmp.write_all(&salt)?;
+            tmp.as_file().sync_all()?;
+            // Another process may have won the race; its salt is kept.
+            if let Err(e) = tmp.persist_noclobber(&path)
+                && e.error.kind() != io::ErrorKind::AlreadyExists
+            {
+                return Err(e.error);
+            }
+        }
+
+        let bytes = fs::read(&path)?;
+        bytes
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed history salt"))
+    }
+
+    /// Salted hash of a query string, as stored in anonymized history.
+    pub fn hash_query(&self, query: &str) -> Option<String> {
+        let salt = self.salt()?;
+        Some(
+            blake3::keyed_hash(salt, query.trim().as_bytes())
+                .to_hex()
+                .to_string(),
+        )
+    }
+
+    /// Strip the query text from `event` according to the privacy mode.
+    fn anonymize(&self, mut event: QueryEvent) -> QueryEvent {
+        match self.privacy {
+            HistoryPrivacy::Plain => return event,
+            HistoryPrivacy::Hash => {
+                // Fall back to redaction rather than storing plaintext.
+                event.query_hash = self.hash_query(&event.raw_query);
+            }
+            HistoryPrivacy::Redact => event.query_hash = None,
+        }
+        event.raw_query.clear();
+        event
     }
 
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// Sidecar file used for advisory locking.
+    ///
+    /// The CLI and daemon both append to the same log, so every read and
+    /// write goes through this lock. It lives next to the log rather than on
+    /// it so that `clear` can remove the log while holding the lock.
+    fn lock_path(&self) -> PathBuf {
+        let mut name = self
+            .path
+            .file_name()
+            .map(OsString::from)
+            .unwrap_or_default();
+        name.push(".lock");
+        self.path.with_file_name(name)
+    }
+
+    /// Acquire the history lock; it is released when the returned file drops.
+    fn lock(&self, exclusive: bool) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.lock_path())?;
+
+        if exclusive {
+            file.lock()?;
+        } else {
+            file.lock_shared()?;
+        }
+
+        Ok(file)
+    }
+
     pub fn log_query(&self, event: QueryEvent) {
+        let event = self.anonymize(event);
         if let Err(e) = self.append_event(&HistoryEvent::Query(event)) {
             debug!("Failed to log history event: {}", e);
         }
@@ -112,24 +416,119 @@ impl HistoryStore {
         let mut line = serde_json::to_string(event).map_err(io::Error::other)?;
         line.push('\n');
 
+        let _guard = self.lock(true)?;
+
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&self.path)?;
 
-        // We write a single line-encoded JSON event and rely on O_APPEND so that each individual write call appends atomically.
-        // However, because write_all may perform multiple write calls in case of interruption, full-line atomicity is not guaranteed under all failure modes.
-        // In practice, this is acceptable for a best-effort history log
+        // The exclusive lock keeps lines from the CLI and daemon from
+        // interleaving even if `write_all` needs more than one write call.
         file.write_all(line.as_bytes())?;
 
+        let rotate_bytes = self.retention.rotate_bytes;
+        if file.metadata()?.len() > rotate_bytes {
+            drop(file);
+            // Rotating down to half the threshold leaves room for the next
+            // appends; trimming only to the retention limits could leave a
+            // log still over it, rewritten again on every append.
+            let stats = self.prune_locked(&self.retention, Some(rotate_bytes / 2))?;
+            debug!(
+                "Rotated history log: kept {}, removed {}",
+                stats.kept, stats.removed
+            );
+        }
+
         Ok(())
     }
 
+    /// Trim the log according to `policy`, rewriting it atomically.
+    pub fn prune(&self, policy: &RetentionPolicy) -> io::Result<PruneStats> {
+        if !self.path.exists() {
+            return Ok(PruneStats::default());
+        }
+
+        let _guard = self.lock(true)?;
+        self.prune_locked(policy, None)
+    }
+
+    /// Prune while the caller holds the exclusive lock, also dropping the
+    /// oldest events until the log is at most `max_bytes` long.
+    fn prune_locked(
+        &self,
+        policy: &RetentionPolicy,
+        max_bytes: Option<u64>,
+    ) -> io::Result<PruneStats> {
+        let Some(events) = self.read_events_unlocked() else {
+            return Ok(PruneStats::default());
+        };
+
+        let total = events.len();
+        let queries = events
+            .into_iter()
+            .map(|e| match e {
+                HistoryEvent::Query(q) => q,
+            })
+            .collect();
+        let mut lines = policy
+            .apply(queries, Utc::now())
+            .into_iter()
+            .map(|q| {
+                let mut line =
+                    serde_json::to_string(&HistoryEvent::Query(q)).map_err(io::Error::other)?;
+                line.push('\n');
+                Ok(line)
+            })
+            .collect::<io::Result<VecDeque<String>>>()?;
+        if let Some(max_bytes) = max_bytes {
+            let mut len: u64 = lines.iter().map(|line| line.len() as u64).sum();
+            while len > max_bytes
+                && let Some(oldest) = lines.pop_front()
+            {
+                len -= oldest.len() as u64;
+            }
+        }
+
+        let stats = PruneStats {
+            kept: lines.len(),
+            removed: total - lines.len(),
+        };
+
+        // Write to a sibling temp file and rename over the log so readers
+        // never observe a partially rewritten file.
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        {
+            let mut out = io::BufWriter::new(tmp.as_file_mut());
+            for line in lines {
+                out.write_all(line.as_bytes())?;
+            }
+            out.flush()?;
+        }
+        tmp.as_file().sync_all()?;
+        tmp.persist(&self.path).map_err(|e| e.error)?;
+
+        Ok(stats)
+    }
+
     pub fn iter_events(&self) -> impl Iterator<Item = HistoryEvent> {
         self.read_events().into_iter().flatten()
     }
 
     fn read_events(&self) -> Option<Vec<HistoryEvent>> {
+        if !self.path.exists() {
+            return None;
+        }
+
+        let _guard = self
+            .lock(false)
+            .inspect_err(|e| debug!("Failed to lock history log: {e}"))
+            .ok()?;
+        self.read_events_unlocked()
+    }
+
+    fn read_events_unlocked(&self) -> Option<Vec<HistoryEvent>> {
         let file = File::open(&self.path).ok()?;
         let reader = BufReader::new(file);
         let mut events = Vec::new();
@@ -148,6 +547,7 @@ impl HistoryStore {
         Some(events)
     }
 
+    /// Most recent queries first, across both CLI and daemon entries.
     pub fn recent_queries(&self, limit: usize) -> Vec<QueryEvent> {
         let mut queries: Vec<QueryEvent> = self
             .iter_events()
@@ -156,16 +556,46 @@ impl HistoryStore {
             })
             .collect();
 
+        // Appends are ordered by lock acquisition, not by when the query ran,
+        // so order by timestamp to merge CLI and daemon entries correctly.
+        queries.sort_by_key(|q| q.timestamp);
         queries.reverse();
         queries.truncate(limit);
         queries
     }
 
+    /// Number of times each query was run, keyed by its salted hash.
+    ///
+    /// Plaintext and hashed entries share keys, so frequency stays stable
+    /// when anonymization is switched on. Redacted entries are skipped.
+    pub fn query_frequencies(&self) -> HashMap<String, usize> {
+        let mut freq = HashMap::new();
+
+        for HistoryEvent::Query(q) in self.iter_events() {
+            let key = match q.query_hash {
+                Some(hash) => hash,
+                None if q.raw_query.is_empty() => continue,
+                None => match self.hash_query(&q.raw_query) {
+                    Some(hash) => hash,
+                    None => continue,
+                },
+            };
+            *freq.entry(key).or_insert(0) += 1;
+        }
+
+        freq
+    }
+
     pub fn count(&self) -> usize {
         self.iter_events().count()
     }
 
     pub fn clear(&self) -> io::Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let _guard = self.lock(true)?;
         match fs::remove_file(&self.path) {
             Ok(()) => Ok(()),
             Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
@@ -174,6 +604,6 @@ impl HistoryStore {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 #[path = "history_tests.rs"]
 mod tests;
@@ -1,4 +1,5 @@
 use super::*;
+use chrono::Duration;
 use serial_test::serial;
 use tempfile::tempdir;
 
@@ -150,3 +151,276 @@ fn new_respects_history_disabled_env_false() {
     assert!(HistoryStore::new().is_some());
     unsafe { std::env::remove_var(HISTORY_DISABLED_ENV) };
 }
+
+#[test]
+fn origin_defaults_to_cli_for_legacy_entries() {
+    let line = r#"{"Query":{"version":1,"timestamp":"2024-01-01T00:00:00Z","raw_query":"old","hits":1,"duration_ms":2}}"#;
+    let ev: HistoryEvent = serde_json::from_str(line).expect("parse legacy line");
+
+    match ev {
+        HistoryEvent::Query(q) => assert_eq!(q.origin, QueryOrigin::Cli),
+    }
+}
+
+#[test]
+fn origin_round_trips() {
+    let (store, _dir) = temp_store();
+
+    store.log_query(QueryEvent::new("d".into(), 1, 1).with_origin(QueryOrigin::Daemon));
+
+    let recent = store.recent_queries(1);
+    assert_eq!(recent[0].origin, QueryOrigin::Daemon);
+}
+
+#[test]
+fn recent_queries_merges_by_timestamp() {
+    let (store, _dir) = temp_store();
+
+    let mut newer = QueryEvent::new("newer".into(), 1, 1).with_origin(QueryOrigin::Daemon);
+    let mut older = QueryEvent::new("older".into(), 1, 1);
+    older.timestamp = newer.timestamp - chrono::Duration::seconds(5);
+    newer.timestamp += chrono::Duration::seconds(5);
+
+    // Logged out of order, as can happen when two processes race.
+    store.log_query(newer);
+    store.log_query(older);
+
+    let recent = store.recent_queries(2);
+    assert_eq!(recent[0].raw_query, "newer");
+    assert_eq!(recent[1].raw_query, "older");
+}
+
+#[test]
+fn concurrent_appends_do_not_interleave() {
+    let (store, _dir) = temp_store();
+    let path = store.path().to_path_buf();
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let store = HistoryStore::with_path(path);
+                for i in 0..50 {
+                    let query = format!("thread{t}-{i}-{}", "x".repeat(512));
+                    store.log_query(QueryEvent::new(query, i, 1));
+                }
+            })
+        })
+        .collect();
+
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(store.count(), 200);
+}
+
+fn event_at(query: &str, timestamp: DateTime<Utc>) -> QueryEvent {
+    let mut ev = QueryEvent::new(query.into(), 1, 1);
+    ev.timestamp = timestamp;
+    ev
+}
+
+#[test]
+fn prune_keeps_newest_events() {
+    let (store, _dir) = temp_store();
+    let now = Utc::now();
+
+    for i in 0..5 {
+        store.log_query(event_at(&format!("q{i}"), now + Duration::seconds(i)));
+    }
+
+    let policy = RetentionPolicy {
+        max_events: Some(2),
+        max_age_days: None,
+        ..RetentionPolicy::default()
+    };
+    let stats = store.prune(&policy).expect("prune");
+
+    assert_eq!(
+        stats,
+        PruneStats {
+            kept: 2,
+            removed: 3
+        }
+    );
+    let recent: Vec<_> = store
+        .recent_queries(10)
+        .into_iter()
+        .map(|q| q.raw_query)
+        .collect();
+    assert_eq!(recent, vec!["q4", "q3"]);
+}
+
+#[test]
+fn prune_drops_events_older_than_max_age() {
+    let (store, _dir) = temp_store();
+    let now = Utc::now();
+
+    store.log_query(event_at("old", now - Duration::days(10)));
+    store.log_query(event_at("new", now - Duration::days(1)));
+
+    let policy = RetentionPolicy {
+        max_events: None,
+        max_age_days: Some(7),
+        ..RetentionPolicy::default()
+    };
+    let stats = store.prune(&policy).expect("prune");
+
+    assert_eq!(
+        stats,
+        PruneStats {
+            kept: 1,
+            removed: 1
+        }
+    );
+    assert_eq!(store.recent_queries(10)[0].raw_query, "new");
+}
+
+#[test]
+fn prune_on_missing_file_is_noop() {
+    let (store, _dir) = temp_store();
+    let stats = store.prune(&RetentionPolicy::default()).expect("prune");
+
+    assert_eq!(stats, PruneStats::default());
+    assert!(!store.path().exists());
+}
+
+#[test]
+fn append_rotates_when_log_exceeds_size() {
+    let (store, _dir) = temp_store();
+    let store = store.with_retention(RetentionPolicy {
+        max_events: Some(3),
+        max_age_days: None,
+        rotate_bytes: 1024,
+    });
+
+    for i in 0..20 {
+        store.log_query(QueryEvent::new(format!("query-{i}"), i, 1));
+    }
+
+    // Rotation trims to 3 and subsequent appends grow it until the next
+    // rotation, so the log never holds everything.
+    let count = store.count();
+    assert!(count < 20, "expected rotation, found {count} events");
+    assert!(std::fs::metadata(store.path()).unwrap().len() <= 1024 + 256);
+}
+
+#[test]
+fn rotation_leaves_room_below_the_threshold() {
+    let (store, _dir) = temp_store();
+    // The count limit alone would keep a log over the threshold.
+    let store = store.with_retention(RetentionPolicy {
+        max_events: Some(1_000),
+        max_age_days: None,
+        rotate_bytes: 4096,
+    });
+
+    let mut rotated = 0;
+    let mut last_count = 0;
+    for i in 0..200 {
+        store.log_query(QueryEvent::new(format!("query-{i}"), i, 1));
+        let count = store.count();
+        if count <= last_count {
+            rotated += 1;
+            assert!(std::fs::metadata(store.path()).unwrap().len() <= 2048);
+        }
+        last_count = count;
+    }
+    assert!(std::fs::metadata(store.path()).unwrap().len() <= 4096);
+    // Each rotation frees half the log, so the log is not rewritten on
+    // every append once it first reaches the threshold.
+    assert!((1..20).contains(&rotated), "rotated {rotated} times");
+}
+
+#[test]
+#[serial]
+fn retention_from_env_overrides_defaults() {
+    unsafe {
+        std::env::set_var(HISTORY_MAX_EVENTS_ENV, "50");
+        std::env::set_var(HISTORY_MAX_DAYS_ENV, "0");
+    }
+
+    let policy = RetentionPolicy::from_env();
+    assert_eq!(policy.max_events, Some(50));
+    assert_eq!(policy.max_age_days, None);
+
+    unsafe {
+        std::env::remove_var(HISTORY_MAX_EVENTS_ENV);
+        std::env::remove_var(HISTORY_MAX_DAYS_ENV);
+    }
+    assert_eq!(RetentionPolicy::from_env(), RetentionPolicy::default());
+}
+
+#[test]
+fn hash_privacy_stores_no_plaintext() {
+    let (store, _dir) = temp_store();
+    let store = store.with_privacy(HistoryPrivacy::Hash);
+
+    store.log_query(QueryEvent::new("secret project".into(), 3, 1));
+
+    let contents = std::fs::read_to_string(store.path()).unwrap();
+    assert!(!contents.contains("secret"));
+
+    let q = &store.recent_queries(1)[0];
+    assert!(q.raw_query.is_empty());
+    assert_eq!(q.query_hash, store.hash_query("secret project"));
+    assert!(q.display_query().starts_with("<hash:"));
+}
+
+#[test]
+fn redact_privacy_keeps_only_metadata() {
+    let (store, _dir) = temp_store();
+    let store = store.with_privacy(HistoryPrivacy::Redact);
+
+    store.log_query(QueryEvent::new("secret".into(), 3, 7));
+
+    let q = &store.recent_queries(1)[0];
+    assert!(q.raw_query.is_empty());
+    assert!(q.query_hash.is_none());
+    assert_eq!((q.hits, q.duration_ms), (3, 7));
+    assert_eq!(q.display_query(), "<redacted>");
+    assert_eq!(store.query_frequencies().len(), 0);
+}
+
+#[test]
+fn frequencies_merge_plain_and_hashed_entries() {
+    let (store, dir) = temp_store();
+    store.log_query(QueryEvent::new("ext:rs".into(), 1, 1));
+
+    let hashed =
+        HistoryStore::with_path(dir.path().join("history.log")).with_privacy(HistoryPrivacy::Hash);
+    hashed.log_query(QueryEvent::new("ext:rs".into(), 1, 1));
+    hashed.log_query(QueryEvent::new("other".into(), 1, 1));
+
+    let frequencies = hashed.query_frequencies();
+    let frequency = |query: &str| frequencies.get(&hashed.hash_query(query).unwrap()).copied();
+    assert_eq!(frequency("ext:rs"), Some(2));
+    assert_eq!(frequency("other"), Some(1));
+    assert_eq!(frequency("missing"), None);
+}
+
+#[test]
+fn salt_is_stable_across_stores() {
+    let (store, dir) = temp_store();
+    let first = store.hash_query("q").unwrap();
+
+    let reopened = HistoryStore::with_path(dir.path().join("history.log"));
+    assert_eq!(reopened.hash_query("q").unwrap(), first);
+    assert_ne!(reopened.hash_query("other").unwrap(), first);
+}
+
+#[test]
+#[serial]
+fn privacy_from_env() {
+    unsafe { std::env::remove_var(HISTORY_ANONYMIZE_ENV) };
+    assert_eq!(HistoryPrivacy::from_env(), HistoryPrivacy::Plain);
+
+    unsafe { std::env::set_var(HISTORY_ANONYMIZE_ENV, "hash") };
+    assert_eq!(HistoryPrivacy::from_env(), HistoryPrivacy::Hash);
+
+    unsafe { std::env::set_var(HISTORY_ANONYMIZE_ENV, "REDACT") };
+    assert_eq!(HistoryPrivacy::from_env(), HistoryPrivacy::Redact);
+
+    unsafe { std::env::remove_var(HISTORY_ANONYMIZE_ENV) };
+}
@@ -0,0 +1,281 @@
+/// Compact language identifier stored per file in the index.
+///
+/// `0` means "unknown"; other values are 1-based positions in [`LANGUAGES`].
+pub type LangId = u8;
+
+/// A programming or markup language recognised at index time.
+pub struct Language {
+    /// Canonical name, used by `lang:` and in stats output.
+    pub name: &'static str,
+    /// Other names accepted by `lang:`.
+    pub aliases: &'static [&'static str],
+    /// Normalized extensions (see `normalize_ext`).
+    pub exts: &'static [&'static str],
+    /// Interpreter names recognised in a `#!` line, without version suffixes.
+    pub interpreters: &'static [&'static str],
+}
+
+/// Known languages. Append only: positions are persisted as [`LangId`]s.
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "rust",
+        aliases: &["rs"],
+        exts: &["rs"],
+        interpreters: &[],
+    },
+    Language {
+        name: "python",
+        aliases: &["py"],
+        exts: &["py", "pyi", "pyw"],
+        interpreters: &["python"],
+    },
+    Language {
+        name: "javascript",
+        aliases: &["js"],
+        exts: &["js", "mjs", "cjs", "jsx"],
+        interpreters: &["node", "nodejs"],
+    },
+    Language {
+        name: "typescript",
+        aliases: &["ts"],
+        exts: &["ts", "mts", "cts", "tsx"],
+        interpreters: &["deno", "ts-node", "bun"],
+    },
+    Language {
+        name: "go",
+        aliases: &["golang"],
+        exts: &["go"],
+        interpreters: &[],
+    },
+    Language {
+        name: "c",
+        aliases: &[],
+        exts: &["c", "h"],
+        interpreters: &[],
+    },
+    Language {
+        name: "cpp",
+        aliases: &["c++", "cxx"],
+        exts: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        interpreters: &[],
+    },
+    Language {
+        name: "java",
+        aliases: &[],
+        exts: &["java"],
+        interpreters: &[],
+    },
+    Language {
+        name: "kotlin",
+        aliases: &["kt"],
+        exts: &["kt", "kts"],
+        interpreters: &[],
+    },
+    Language {
+        name: "swift",
+        aliases: &[],
+        exts: &["swift"],
+        interpreters: &["swift"],
+    },
+    Language {
+        name: "csharp",
+        aliases: &["c#", "cs"],
+        exts: &["cs"],
+        interpreters: &[],
+    },
+    Language {
+        name: "ruby",
+        aliases: &["rb"],
+        exts: &["rb"],
+        interpreters: &["ruby"],
+    },
+    Language {
+        name: "php",
+        aliases: &[],
+        exts: &["php"],
+        interpreters: &["php"],
+    },
+    Language {
+        name: "perl",
+        aliases: &["pl"],
+        exts: &["pl", "pm"],
+        interpreters: &["perl"],
+    },
+    Language {
+        name: "lua",
+        aliases: &[],
+        exts: &["lua"],
+        interpreters: &["lua", "luajit"],
+    },
+    Language {
+        name: "shell",
+        aliases: &["sh", "bash", "zsh"],
+        exts: &["sh", "bash", "zsh", "ksh"],
+        interpreters: &["sh", "bash", "zsh", "ksh", "dash"],
+    },
+    Language {
+        name: "fish",
+        aliases: &[],
+        exts: &["fish"],
+        interpreters: &["fish"],
+    },
+    Language {
+        name: "powershell",
+        aliases: &["ps1", "pwsh"],
+        exts: &["ps1", "psm1"],
+        interpreters: &["pwsh"],
+    },
+    Language {
+        name: "haskell",
+        aliases: &["hs"],
+        exts: &["hs"],
+        interpreters: &["runghc", "runhaskell"],
+    },
+    Language {
+        name: "ocaml",
+        aliases: &["ml"],
+        exts: &["ml", "mli"],
+        interpreters: &["ocaml"],
+    },
+    Language {
+        name: "elixir",
+        aliases: &["ex"],
+        exts: &["ex", "exs"],
+        interpreters: &["elixir"],
+    },
+    Language {
+        name: "erlang",
+        aliases: &["erl"],
+        exts: &["erl", "hrl"],
+        interpreters: &["escript"],
+    },
+    Language {
+        name: "scala",
+        aliases: &[],
+        exts: &["scala", "sc"],
+        interpreters: &["scala"],
+    },
+    Language {
+        name: "zig",
+        aliases: &[],
+        exts: &["zig"],
+        interpreters: &[],
+    },
+    Language {
+        name: "r",
+        aliases: &[],
+        exts: &["r"],
+        interpreters: &["rscript"],
+    },
+    Language {
+        name: "julia",
+        aliases: &["jl"],
+        exts: &["jl"],
+        interpreters: &["julia"],
+    },
+    Language {
+        name: "html",
+        aliases: &["htm"],
+        exts: &["html", "htm"],
+        interpreters: &[],
+    },
+    Language {
+        name: "css",
+        aliases: &[],
+        exts: &["css", "scss", "sass", "less"],
+        interpreters: &[],
+    },
+    Language {
+        name: "markdown",
+        aliases: &["md"],
+        exts: &["md", "markdown", "mkd"],
+        interpreters: &[],
+    },
+    Language {
+        name: "sql",
+        aliases: &[],
+        exts: &["sql"],
+        interpreters: &[],
+    },
+    Language {
+        name: "toml",
+        aliases: &[],
+        exts: &["toml"],
+        interpreters: &[],
+    },
+    Language {
+        name: "yaml",
+        aliases: &["yml"],
+        exts: &["yaml", "yml"],
+        interpreters: &[],
+    },
+    Language {
+        name: "json",
+        aliases: &[],
+        exts: &["json"],
+        interpreters: &[],
+    },
+];
+
+#[inline]
+fn id_of(pos: usize) -> LangId {
+    (pos + 1) as LangId
+}
+
+/// Look up a language by its id.
+pub fn language(id: LangId) -> Option<&'static Language> {
+    LANGUAGES.get((id as usize).checked_sub(1)?)
+}
+
+/// Resolve a language by canonical name or alias, ignoring ASCII case.
+pub fn lang_by_name(name: &str) -> Option<LangId> {
+    LANGUAGES
+        .iter()
+        .position(|l| {
+            l.name.eq_ignore_ascii_case(name)
+                || l.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+        })
+        .map(id_of)
+}
+
+/// Map a normalized extension to its language.
+pub fn lang_for_ext(ext: &str) -> Option<LangId> {
+    LANGUAGES
+        .iter()
+        .position(|l| l.exts.contains(&ext))
+        .map(id_of)
+}
+
+/// Map the first line of a script (`#!...`) to its language.
+///
+/// Handles both direct interpreter paths (`#!/bin/bash`) and `env`
+/// indirection (`#!/usr/bin/env -S python3 -u`). Version suffixes such as
+/// `python3.12` are ignored.
+pub fn lang_for_shebang(first_line: &[u8]) -> Option<LangId> {
+    let line = first_line.strip_prefix(b"#!")?;
+    let line = line.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+
+    let mut words = line.split_whitespace();
+    let mut program = basename(words.next()?);
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-')).map(basename)?;
+    }
+
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let program = program.to_ascii_lowercase();
+
+    LANGUAGES
+        .iter()
+        .position(|l| l.interpreters.contains(&program.as_str()))
+        .map(id_of)
+}
+
+#[inline]
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+#[path = "lang_tests.rs"]
+mod tests;
@@ -0,0 +1,66 @@
+use super::*;
+
+fn name(id: Option<LangId>) -> Option<&'static str> {
+    id.and_then(language).map(|l| l.name)
+}
+
+#[test]
+fn extensions_map_to_languages() {
+    let cases: &[(&str, Option<&str>)] = &[
+        ("rs", Some("rust")),
+        ("py", Some("python")),
+        ("tsx", Some("typescript")),
+        ("hpp", Some("cpp")),
+        ("yml", Some("yaml")),
+        ("pdf", None),
+        ("", None),
+    ];
+
+    for (ext, expected) in cases {
+        assert_eq!(name(lang_for_ext(ext)), *expected, "ext: {ext:?}");
+    }
+}
+
+#[test]
+fn names_and_aliases_resolve_case_insensitively() {
+    assert_eq!(name(lang_by_name("Rust")), Some("rust"));
+    assert_eq!(name(lang_by_name("py")), Some("python"));
+    assert_eq!(name(lang_by_name("C++")), Some("cpp"));
+    assert_eq!(name(lang_by_name("golang")), Some("go"));
+    assert_eq!(lang_by_name("klingon"), None);
+}
+
+#[test]
+fn shebangs_map_to_languages() {
+    let cases: &[(&[u8], Option<&str>)] = &[
+        (b"#!/bin/bash\necho hi\n", Some("shell")),
+        (b"#!/usr/bin/env python3\n", Some("python")),
+        (b"#!/usr/bin/python3.12 -u\n", Some("python")),
+        (b"#!/usr/bin/env -S node --harmony\n", Some("javascript")),
+        (b"#! /usr/bin/perl -w\n", Some("perl")),
+        (b"#!/usr/bin/env\n", None),
+        (b"#!/opt/custom/interp\n", None),
+        (b"echo no shebang\n", None),
+        (b"", None),
+    ];
+
+    for (line, expected) in cases {
+        assert_eq!(
+            name(lang_for_shebang(line)),
+            *expected,
+            "line: {:?}",
+            String::from_utf8_lossy(line)
+        );
+    }
+}
+
+#[test]
+fn ids_round_trip_and_zero_is_unknown() {
+    assert!(language(0).is_none());
+    for (pos, lang) in LANGUAGES.iter().enumerate() {
+        let id = lang_by_name(lang.name).unwrap();
+        assert_eq!(id as usize, pos + 1);
+        assert_eq!(language(id).unwrap().name, lang.name);
+    }
+    assert!(LANGUAGES.len() < LangId::MAX as usize);
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blaze_dir;
+
+/// Location of the most recent local query's result set.
+pub fn last_results_path() -> PathBuf {
+    blaze_dir().join("last_results")
+}
+
+/// Which index a saved result set refers to.
+///
+/// File ids are only meaningful for the index that produced them; any
+/// rebuild or refresh that rewrites the file changes the stamp.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStamp {
+    pub index_path: PathBuf,
+    /// Creation time recorded in the index metadata.
+    pub created_secs: u64,
+    pub file_count: u64,
+}
+
+/// Every match of the most recent local query, for `blaze query --refine`.
+///
+/// Stored as one JSON line (the stamp and query) followed by the file ids as
+/// little-endian `u32`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastResults {
+    pub stamp: IndexStamp,
+    pub query: String,
+    /// Matching file ids in index order.
+    pub file_ids: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    stamp: IndexStamp,
+    query: String,
+    count: usize,
+}
+
+impl LastResults {
+    /// Load a saved result set. A missing file means nothing was saved yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut reader = BufReader::new(file);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let header: Header = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut bytes = Vec::with_capacity(header.count.saturating_mul(4));
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() != header.count.saturating_mul(4) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "saved result set is truncated",
+            ));
+        }
+
+        Ok(Some(Self {
+            stamp: header.stamp,
+            query: header.query,
+            file_ids: bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .collect(),
+        }))
+    }
+
+    /// Replace the saved result set atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let header = Header {
+            stamp: self.stamp.clone(),
+            query: self.query.clone(),
+            count: self.file_ids.len(),
+        };
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        {
+            let mut out = io::BufWriter::new(tmp.as_file_mut());
+            serde_json::to_writer(&mut out, &header).map_err(io::Error::other)?;
+            out.write_all(b"\n")?;
+            for id in &self.file_ids {
+                out.write_all(&id.to_le_bytes())?;
+            }
+            out.flush()?;
+        }
+        tmp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "last_results_tests.rs"]
+mod tests;
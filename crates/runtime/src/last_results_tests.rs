@@ -0,0 +1,43 @@
+use super::*;
+use tempfile::tempdir;
+
+fn results(ids: Vec<u32>) -> LastResults {
+    LastResults {
+        stamp: IndexStamp {
+            index_path: PathBuf::from("/cache/blaze/index.bin"),
+            created_secs: 1_700_000_000,
+            file_count: 1_000,
+        },
+        query: "ext:rs main".into(),
+        file_ids: ids,
+    }
+}
+
+#[test]
+fn save_load_round_trip() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("cache/last_results");
+
+    assert_eq!(LastResults::load(&path).expect("load missing"), None);
+
+    let saved = results(vec![0, 7, 10, 999, 0x0102_0304]);
+    saved.save(&path).expect("save");
+    assert_eq!(LastResults::load(&path).expect("load"), Some(saved));
+
+    let empty = results(Vec::new());
+    empty.save(&path).expect("save empty");
+    assert_eq!(LastResults::load(&path).expect("load empty"), Some(empty));
+}
+
+#[test]
+fn truncated_file_is_invalid_data() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("last_results");
+    results(vec![1, 2, 3]).save(&path).expect("save");
+
+    let bytes = fs::read(&path).unwrap();
+    fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+
+    let err = LastResults::load(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
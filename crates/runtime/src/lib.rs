@@ -1,11 +1,46 @@
+pub mod boost;
 mod config;
+mod config_file;
+pub mod discovery;
+pub mod ext;
 pub mod history;
+pub mod lang;
+#[cfg(feature = "native")]
+pub mod last_results;
 pub mod logging;
+pub mod noise;
+pub mod pins;
+pub mod planner;
+pub mod project;
+#[cfg(feature = "native")]
+pub mod registry;
+pub mod settings;
+mod size;
 
+pub use boost::BoostRules;
 pub use config::{
-    CACHE_COMPONENTS, DEFAULT_PROJECT_IGNORE_PATTERNS, DEFAULT_SYSTEM_SKIP_PREFIXES,
-    LOG_COMPONENTS, NOISY_COMPONENTS, SYSTEM_ROOTS, blaze_dir, default_index_path,
-    default_scan_root,
+    ARCHIVE_EXTS, AUTO_INDEX_MAX_FILES, CACHE_COMPONENTS, CONFIG_FILE_NAME, DEFAULT_HASH_MAX_BYTES,
+    DEFAULT_PROJECT_IGNORE_PATTERNS, DEFAULT_QUERY_LIMIT, DEFAULT_SYSTEM_SKIP_PREFIXES,
+    DIR_TIMEOUT_SECS, EXCLUDE_PATHS_ENV, EXT_ALIASES, HISTORY_MAX_AGE_DAYS, HISTORY_MAX_EVENTS,
+    HISTORY_ROTATE_BYTES, INDEX_PATH_ENV, LIMIT_ENV, LOG_COMPONENTS, MEMORY_LIMIT_ENV,
+    NETWORK_OP_TIMEOUT_SECS, NETWORK_SCAN_THREADS, NOISY_COMPONENTS, PROFILE_ENV, PROJECT_DIR_NAME,
+    PROJECT_MARKERS, SOCKET_PATH_ENV, SYSTEM_INDEX_PATH, SYSTEM_ROOTS, TIMEZONE_ENV,
+    VERIFY_MANIFEST_ENV, blaze_dir, config_path, default_index_path, default_scan_root,
+    set_config_path, xdg_or_home,
 };
+pub use config_file::ConfigFile;
+pub use discovery::{DaemonDiscovery, default_socket_path, discovery_path};
+pub use ext::{canonical_ext, is_archive_ext, normalize_ext};
+pub use lang::{LangId, lang_by_name, lang_for_ext, lang_for_shebang, language};
+#[cfg(feature = "native")]
+pub use last_results::{IndexStamp, LastResults, last_results_path};
+pub use noise::{NoiseComponents, noise_components};
+pub use pins::{PinSet, PinStore};
+pub use planner::{DEFAULT_RARE_TRIGRAMS, PlannerOverrides, TrigramSelection, planner_overrides};
+pub use project::{find_indexed_project, find_project_root, find_repo_root, project_index_path};
+#[cfg(feature = "native")]
+pub use registry::{IndexEntry, IndexRegistry, registry_path};
+pub use settings::{Setting, SettingFlags, Settings, Source, settings};
+pub use size::parse_size;
 
 pub use logging::init;
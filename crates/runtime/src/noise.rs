@@ -0,0 +1,140 @@
+use crate::{
+    CACHE_COMPONENTS, LOG_COMPONENTS, NOISY_COMPONENTS,
+    config_file::{ConfigFile, as_strings},
+    settings::{Setting, Source, settings},
+};
+
+/// Adjusts [`NOISY_COMPONENTS`] (build and dependency directories).
+pub const NOISE_BUILD_ENV: &str = "BLAZE_NOISE_BUILD";
+
+/// Adjusts [`CACHE_COMPONENTS`].
+pub const NOISE_CACHE_ENV: &str = "BLAZE_NOISE_CACHE";
+
+/// Adjusts [`LOG_COMPONENTS`].
+pub const NOISE_LOG_ENV: &str = "BLAZE_NOISE_LOG";
+
+/// Path components that mark noisy locations, consulted when indexing.
+///
+/// Each list starts from its compile-time default and can be adjusted in
+/// the `[noise]` table of the config file or with a comma-separated
+/// environment variable, which takes precedence: `name` or `+name` adds a
+/// component and `-name` removes one.
+///
+/// ```toml
+/// [noise]
+/// build = ["-target", "+_site"]
+/// log = "+traces"
+/// ```
+///
+/// Entries are lowercase, like the defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoiseComponents {
+    pub build: Setting<Vec<String>>,
+    pub cache: Setting<Vec<String>>,
+    pub log: Setting<Vec<String>>,
+}
+
+impl Default for NoiseComponents {
+    fn default() -> Self {
+        let owned = |list: &[&str]| Setting {
+            value: list.iter().map(|s| (*s).to_owned()).collect(),
+            source: Source::Default,
+        };
+        Self {
+            build: owned(NOISY_COMPONENTS),
+            cache: owned(CACHE_COMPONENTS),
+            log: owned(LOG_COMPONENTS),
+        }
+    }
+}
+
+impl NoiseComponents {
+    /// Defaults adjusted by the `[noise]` table of `file`, or instead by
+    /// the environment variables `env` looks up. Values that cannot be
+    /// read are skipped with a warning.
+    pub fn resolve(
+        file: &ConfigFile,
+        env: impl Fn(&str) -> Option<String>,
+        warnings: &mut Vec<String>,
+    ) -> Self {
+        let mut components = Self::default();
+        let table = file.table("noise", warnings);
+
+        for (key, value) in table.into_iter().flatten() {
+            let list = match key.as_str() {
+                "build" => &mut components.build,
+                "cache" => &mut components.cache,
+                "log" => &mut components.log,
+                other => {
+                    warnings.push(file.unknown_key("noise", other));
+                    continue;
+                }
+            };
+            match as_strings(value) {
+                Some(specs) => {
+                    for spec in specs {
+                        apply_overrides(&mut list.value, &spec);
+                    }
+                    list.source = Source::File;
+                }
+                None => warnings.push(file.bad_value("noise", key, value)),
+            }
+        }
+
+        let defaults = Self::default();
+        for (var, list, default) in [
+            (NOISE_BUILD_ENV, &mut components.build, defaults.build),
+            (NOISE_CACHE_ENV, &mut components.cache, defaults.cache),
+            (NOISE_LOG_ENV, &mut components.log, defaults.log),
+        ] {
+            if let Some(spec) = env(var) {
+                let mut value = default.value;
+                apply_overrides(&mut value, &spec);
+                list.set(value, Source::Env(var));
+            }
+        }
+
+        components
+    }
+
+    pub fn is_build(&self, comp: &str) -> bool {
+        self.build.value.iter().any(|n| n == comp)
+    }
+
+    pub fn is_cache(&self, comp: &str) -> bool {
+        self.cache.value.iter().any(|n| n == comp)
+    }
+
+    pub fn is_log(&self, comp: &str) -> bool {
+        self.log.value.iter().any(|n| n == comp)
+    }
+}
+
+/// Process-wide noise components, from [`settings`].
+pub fn noise_components() -> &'static NoiseComponents {
+    &settings().noise
+}
+
+/// Apply a `+name,-name,name` override list to `list`.
+pub(crate) fn apply_overrides(list: &mut Vec<String>, spec: &str) {
+    for entry in spec.split(',').map(str::trim) {
+        let (remove, name) = match entry.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, entry.strip_prefix('+').unwrap_or(entry)),
+        };
+        let name = name.trim().to_lowercase();
+        if name.is_empty() {
+            continue;
+        }
+
+        if remove {
+            list.retain(|n| *n != name);
+        } else if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "noise_tests.rs"]
+mod tests;
@@ -0,0 +1,79 @@
+use super::*;
+
+#[test]
+fn overrides_add_and_remove_components() {
+    let mut list = vec!["node_modules".to_owned(), "target".to_owned()];
+
+    apply_overrides(
+        &mut list,
+        "-target, +_Site,bazel-out,,+node_modules, -missing",
+    );
+
+    assert_eq!(list, ["node_modules", "_site", "bazel-out"]);
+}
+
+#[test]
+fn defaults_match_the_compile_time_lists() {
+    let components = NoiseComponents::default();
+
+    assert!(components.is_build("target"));
+    assert!(components.is_cache("__pycache__"));
+    assert!(components.is_log("logs"));
+    assert!(!components.is_build("src"));
+}
+
+fn resolve(contents: &str, env: &[(&str, &str)]) -> (NoiseComponents, Vec<String>) {
+    let file = ConfigFile::parse(std::path::PathBuf::from("config.toml"), contents);
+    let mut warnings = file.warnings.clone();
+    let components = NoiseComponents::resolve(
+        &file,
+        |var| {
+            env.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        },
+        &mut warnings,
+    );
+    (components, warnings)
+}
+
+#[test]
+fn reads_the_noise_table() {
+    let (components, warnings) = resolve(
+        "[noise]\nbuild = [\"-target\", \"+_site\"]\nlog = \"traces,-logs\"\n",
+        &[],
+    );
+
+    assert!(warnings.is_empty(), "{warnings:?}");
+    assert!(!components.is_build("target"));
+    assert!(components.is_build("_site"));
+    assert_eq!(components.build.source, Source::File);
+    assert!(components.is_log("traces"));
+    assert!(!components.is_log("logs"));
+    assert_eq!(components.cache, NoiseComponents::default().cache);
+}
+
+#[test]
+fn environment_overrides_the_table() {
+    let (components, warnings) = resolve(
+        "[noise]\nbuild = \"-target\"\ncache = \"+.gradle\"\n",
+        &[(NOISE_BUILD_ENV, "-node_modules")],
+    );
+
+    assert!(warnings.is_empty(), "{warnings:?}");
+    assert_eq!(components.build.source, Source::Env(NOISE_BUILD_ENV));
+    assert!(components.is_build("target"));
+    assert!(!components.is_build("node_modules"));
+    assert_eq!(components.cache.source, Source::File);
+    assert!(components.is_cache(".gradle"));
+}
+
+#[test]
+fn unknown_keys_and_bad_values_are_skipped_with_a_warning() {
+    let (components, warnings) = resolve("[noise]\nbuild = 5\ntemp = \"+tmp\"\n", &[]);
+
+    assert_eq!(components, NoiseComponents::default());
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[0].contains("`noise.build`"));
+    assert!(warnings[1].contains("unknown setting `noise.temp`"));
+}
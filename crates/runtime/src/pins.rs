@@ -0,0 +1,182 @@
+#[cfg(feature = "native")]
+use std::io::Write;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::history::state_dir;
+
+/// Location of the pin list: one absolute path per line.
+pub fn pins_path() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("pins"))
+}
+
+/// Persistent list of pinned paths, shared by the CLI and the daemon.
+pub struct PinStore {
+    path: PathBuf,
+}
+
+impl PinStore {
+    pub fn new() -> Option<Self> {
+        pins_path().map(|path| Self { path })
+    }
+
+    /// Create a pin store with a custom path (for testing).
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Pinned paths, sorted. A missing file means nothing is pinned.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut pins: Vec<String> = contents
+            .lines()
+            .map(normalize_pin)
+            .filter(|p| !p.is_empty())
+            .map(str::to_owned)
+            .collect();
+        pins.sort();
+        pins.dedup();
+        Ok(pins)
+    }
+
+    /// Pin `path`. Returns `false` if it was already pinned.
+    #[cfg(feature = "native")]
+    pub fn add(&self, path: &str) -> io::Result<bool> {
+        let path = normalize_pin(path);
+        if path.is_empty() || path.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot pin an empty path or a path containing a newline",
+            ));
+        }
+
+        let mut pins = self.list()?;
+        match pins.binary_search_by(|p| p.as_str().cmp(path)) {
+            Ok(_) => Ok(false),
+            Err(pos) => {
+                pins.insert(pos, path.to_owned());
+                self.write(&pins)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Unpin `path`. Returns `false` if it was not pinned.
+    #[cfg(feature = "native")]
+    pub fn remove(&self, path: &str) -> io::Result<bool> {
+        let path = normalize_pin(path);
+        let mut pins = self.list()?;
+        match pins.binary_search_by(|p| p.as_str().cmp(path)) {
+            Ok(pos) => {
+                pins.remove(pos);
+                self.write(&pins)?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Replace the pin list atomically.
+    #[cfg(feature = "native")]
+    fn write(&self, pins: &[String]) -> io::Result<()> {
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        for pin in pins {
+            writeln!(tmp, "{pin}")?;
+        }
+        tmp.as_file().sync_all()?;
+        tmp.persist(&self.path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+/// Strip surrounding whitespace and trailing slashes (but keep `/` itself).
+fn normalize_pin(path: &str) -> &str {
+    let path = path.trim();
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Pinned paths, loaded once per query.
+///
+/// A pin covers the path itself and, if it is a directory, everything
+/// below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PinSet {
+    paths: Vec<String>,
+}
+
+impl PinSet {
+    pub fn new<S: AsRef<str>>(paths: impl IntoIterator<Item = S>) -> Self {
+        let mut paths: Vec<String> = paths
+            .into_iter()
+            .map(|p| normalize_pin(p.as_ref()).to_owned())
+            .filter(|p| !p.is_empty())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        Self { paths }
+    }
+
+    /// The user's pins, or an empty set if they cannot be read.
+    pub fn load() -> Self {
+        let Some(store) = PinStore::new() else {
+            return Self::default();
+        };
+        match store.list() {
+            Ok(paths) => Self { paths },
+            Err(e) => {
+                debug!("Failed to load pins from {}: {e}", store.path().display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Whether `path` is pinned or lies below a pinned directory.
+    pub fn covers(&self, path: &str) -> bool {
+        self.iter().any(|pin| {
+            pin == "/"
+                || path
+                    .strip_prefix(pin)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// Names of the pins whose parent directory is exactly `dir`.
+    pub fn names_in<'a>(&'a self, dir: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter().filter_map(move |pin| {
+            let (parent, name) = pin.rsplit_once('/')?;
+            let parent = if parent.is_empty() { "/" } else { parent };
+            (parent == dir && !name.is_empty()).then_some(name)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+#[path = "pins_tests.rs"]
+mod tests;
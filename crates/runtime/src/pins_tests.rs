@@ -0,0 +1,52 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn add_list_remove_round_trip() {
+    let dir = tempdir().expect("create temp dir");
+    let store = PinStore::with_path(dir.path().join("state/pins"));
+
+    assert!(store.list().expect("list").is_empty());
+    assert!(store.add("/home/me/notes.md").expect("add"));
+    assert!(store.add("/home/me/src/").expect("add"));
+    assert!(!store.add("/home/me/src").expect("add again"));
+
+    assert_eq!(
+        store.list().expect("list"),
+        ["/home/me/notes.md", "/home/me/src"]
+    );
+
+    assert!(store.remove("/home/me/src/").expect("remove"));
+    assert!(!store.remove("/home/me/src").expect("remove again"));
+    assert_eq!(store.list().expect("list"), ["/home/me/notes.md"]);
+}
+
+#[test]
+fn add_rejects_newlines() {
+    let dir = tempdir().expect("create temp dir");
+    let store = PinStore::with_path(dir.path().join("pins"));
+
+    assert!(store.add("/tmp/a\nb").is_err());
+    assert!(store.add("  ").is_err());
+}
+
+#[test]
+fn covers_matches_the_pin_and_everything_below_it() {
+    let pins = PinSet::new(["/home/me/src/", "/etc/hosts"]);
+
+    assert!(pins.covers("/home/me/src"));
+    assert!(pins.covers("/home/me/src/blaze/main.rs"));
+    assert!(pins.covers("/etc/hosts"));
+    assert!(!pins.covers("/home/me/src2/main.rs"));
+    assert!(!pins.covers("/etc/hosts.allow"));
+    assert!(PinSet::new(["/"]).covers("/anything"));
+}
+
+#[test]
+fn names_in_lists_pins_directly_inside_a_directory() {
+    let pins = PinSet::new(["/etc/hosts", "/etc/ssh/sshd_config", "/vmlinuz"]);
+
+    assert_eq!(pins.names_in("/etc").collect::<Vec<_>>(), ["hosts"]);
+    assert_eq!(pins.names_in("/").collect::<Vec<_>>(), ["vmlinuz"]);
+    assert_eq!(pins.names_in("/usr").count(), 0);
+}
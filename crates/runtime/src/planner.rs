@@ -0,0 +1,173 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    config_file::{ConfigFile, as_count},
+    settings::{Setting, Source, settings},
+};
+
+/// Overrides [`PlannerOverrides::small_candidate_cutoff`].
+pub const SMALL_CANDIDATE_CUTOFF_ENV: &str = "BLAZE_SMALL_CANDIDATE_CUTOFF";
+
+/// Overrides [`PlannerOverrides::early_verify_cutoff`].
+pub const EARLY_VERIFY_CUTOFF_ENV: &str = "BLAZE_EARLY_VERIFY_CUTOFF";
+
+/// Overrides [`PlannerOverrides::trigram_selection`].
+pub const TRIGRAM_SELECTION_ENV: &str = "BLAZE_TRIGRAM_SELECTION";
+
+/// Trigrams [`TrigramSelection::RareFirst`] intersects when no count is
+/// given.
+pub const DEFAULT_RARE_TRIGRAMS: usize = 3;
+
+/// Which of a text term's trigrams are intersected to find its candidates.
+///
+/// Every file containing the term contains all of its trigrams, so any
+/// subset finds every match; more trigrams filter harder at the cost of
+/// more posting lookups. Trigrams common to a large share of the index
+/// are never used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrigramSelection {
+    /// Pick one of the others for each term, from its length and how
+    /// common its trigrams are in the index.
+    #[default]
+    Auto,
+    /// Every trigram of the term, a sliding window over it.
+    Full,
+    /// Non-overlapping trigrams spread across the whole term.
+    Sparse,
+    /// The term's rarest trigrams, up to this many.
+    RareFirst(usize),
+}
+
+impl FromStr for TrigramSelection {
+    type Err = String;
+
+    /// `auto`, `full`, `sparse`, `rare`, or `rare:K` for the `K` rarest.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "full" => Ok(Self::Full),
+            "sparse" => Ok(Self::Sparse),
+            "rare" => Ok(Self::RareFirst(DEFAULT_RARE_TRIGRAMS)),
+            other => match other.strip_prefix("rare:").map(str::parse) {
+                Some(Ok(k)) if k > 0 => Ok(Self::RareFirst(k)),
+                _ => Err(format!(
+                    "unknown trigram selection `{spec}` (expected auto, full, sparse, rare or rare:K)"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TrigramSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Full => f.write_str("full"),
+            Self::Sparse => f.write_str("sparse"),
+            Self::RareFirst(k) => write!(f, "rare:{k}"),
+        }
+    }
+}
+
+/// Fixed values for the candidate-set sizes at which text evaluation
+/// changes strategy, which otherwise scale with the size of the index, and
+/// for how a text term's trigrams are selected.
+///
+/// Read from the `[planner]` table of the config file,
+///
+/// ```toml
+/// [planner]
+/// small_candidate_cutoff = 5000
+/// early_verify_cutoff = 500
+/// trigram_selection = "rare:4"
+/// ```
+///
+/// with [`SMALL_CANDIDATE_CUTOFF_ENV`], [`EARLY_VERIFY_CUTOFF_ENV`] and
+/// [`TRIGRAM_SELECTION_ENV`] taking precedence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlannerOverrides {
+    /// Candidates at or below which a text term is verified by scanning
+    /// them rather than intersecting trigram postings.
+    pub small_candidate_cutoff: Setting<Option<usize>>,
+    /// Trigram-filtered candidates at or below which no further trigrams
+    /// are intersected before verification.
+    pub early_verify_cutoff: Setting<Option<usize>>,
+    /// Trigrams of a text term to intersect.
+    pub trigram_selection: Setting<Option<TrigramSelection>>,
+}
+
+impl PlannerOverrides {
+    /// Overrides from the `[planner]` table of `file`, then the environment
+    /// variables `env` looks up. Values that cannot be read are skipped
+    /// with a warning.
+    pub fn resolve(
+        file: &ConfigFile,
+        env: impl Fn(&str) -> Option<String>,
+        warnings: &mut Vec<String>,
+    ) -> Self {
+        let env = |name: &str| env(name).filter(|v| !v.trim().is_empty());
+        let mut overrides = Self::default();
+
+        for (key, value) in file.table("planner", warnings).into_iter().flatten() {
+            let parsed = match key.as_str() {
+                "small_candidate_cutoff" => as_count(value)
+                    .map(|n| overrides.small_candidate_cutoff.set(Some(n), Source::File)),
+                "early_verify_cutoff" => as_count(value)
+                    .map(|n| overrides.early_verify_cutoff.set(Some(n), Source::File)),
+                "trigram_selection" => match value.as_str().map(str::parse) {
+                    Some(Ok(selection)) => {
+                        let setting = &mut overrides.trigram_selection;
+                        setting.set(Some(selection), Source::File);
+                        Some(())
+                    }
+                    Some(Err(e)) => {
+                        let msg = format!("ignoring `planner.trigram_selection`: {e}");
+                        warnings.push(file.warning(msg));
+                        continue;
+                    }
+                    None => None,
+                },
+                other => {
+                    warnings.push(file.unknown_key("planner", other));
+                    continue;
+                }
+            };
+            if parsed.is_none() {
+                warnings.push(file.bad_value("planner", key, value));
+            }
+        }
+
+        for (var, setting) in [
+            (
+                SMALL_CANDIDATE_CUTOFF_ENV,
+                &mut overrides.small_candidate_cutoff,
+            ),
+            (EARLY_VERIFY_CUTOFF_ENV, &mut overrides.early_verify_cutoff),
+        ] {
+            if let Some(spec) = env(var) {
+                match spec.trim().replace('_', "").parse() {
+                    Ok(n) => setting.set(Some(n), Source::Env(var)),
+                    Err(_) => warnings.push(format!("ignoring {var}={spec:?}: not a number")),
+                }
+            }
+        }
+        if let Some(spec) = env(TRIGRAM_SELECTION_ENV) {
+            match spec.parse() {
+                Ok(selection) => overrides
+                    .trigram_selection
+                    .set(Some(selection), Source::Env(TRIGRAM_SELECTION_ENV)),
+                Err(e) => warnings.push(format!("ignoring {TRIGRAM_SELECTION_ENV}: {e}")),
+            }
+        }
+        overrides
+    }
+}
+
+/// Process-wide planner overrides, from [`settings`].
+pub fn planner_overrides() -> &'static PlannerOverrides {
+    &settings().planner
+}
+
+#[cfg(test)]
+#[path = "planner_tests.rs"]
+mod tests;
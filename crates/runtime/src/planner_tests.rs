@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use super::*;
+
+fn resolve(contents: &str, env: &[(&str, &str)]) -> (PlannerOverrides, Vec<String>) {
+    let file = ConfigFile::parse(PathBuf::from("config.toml"), contents);
+    let mut warnings = file.warnings.clone();
+    let overrides = PlannerOverrides::resolve(
+        &file,
+        |var| {
+            env.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        },
+        &mut warnings,
+    );
+    (overrides, warnings)
+}
+
+#[test]
+fn reads_the_planner_table() {
+    let (overrides, warnings) = resolve(
+        "[boost]\n\"~/src\" = 10\n\n[planner]\n# tuned for a slow disk\nsmall_candidate_cutoff = 5_000\nearly_verify_cutoff=512\n",
+        &[],
+    );
+
+    assert_eq!(
+        overrides.small_candidate_cutoff,
+        Setting {
+            value: Some(5_000),
+            source: Source::File,
+        }
+    );
+    assert_eq!(overrides.early_verify_cutoff.value, Some(512));
+    assert_eq!(overrides.trigram_selection, Setting::default());
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
+
+#[test]
+fn nothing_is_overridden_without_the_table() {
+    let (overrides, warnings) = resolve("[boost]\n\"~/src\" = 10\n", &[]);
+    assert_eq!(overrides, PlannerOverrides::default());
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
+
+#[test]
+fn unknown_settings_and_bad_numbers_are_skipped_with_a_warning() {
+    let (overrides, warnings) = resolve(
+        "[planner]\nsmall_cutoff = 5\nearly_verify_cutoff = -1\nsmall_candidate_cutoff = 7\n",
+        &[],
+    );
+    assert_eq!(overrides.small_candidate_cutoff.value, Some(7));
+    assert_eq!(overrides.early_verify_cutoff.value, None);
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[0].contains("unknown setting `planner.small_cutoff`"));
+    assert!(warnings[1].contains("`planner.early_verify_cutoff`"));
+}
+
+#[test]
+fn environment_overrides_the_table() {
+    let (overrides, warnings) = resolve(
+        "[planner]\nsmall_candidate_cutoff = 5\nearly_verify_cutoff = 6\n",
+        &[
+            (SMALL_CANDIDATE_CUTOFF_ENV, "50"),
+            (EARLY_VERIFY_CUTOFF_ENV, "lots"),
+        ],
+    );
+    assert_eq!(
+        overrides.small_candidate_cutoff,
+        Setting {
+            value: Some(50),
+            source: Source::Env(SMALL_CANDIDATE_CUTOFF_ENV),
+        }
+    );
+    assert_eq!(overrides.early_verify_cutoff.source, Source::File);
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].contains(EARLY_VERIFY_CUTOFF_ENV));
+}
+
+#[test]
+fn reads_the_trigram_selection() {
+    let (overrides, _) = resolve("[planner]\ntrigram_selection = \"rare:5\"\n", &[]);
+    assert_eq!(
+        overrides.trigram_selection.value,
+        Some(TrigramSelection::RareFirst(5))
+    );
+
+    for (spec, selection) in [
+        ("auto", TrigramSelection::Auto),
+        ("Full", TrigramSelection::Full),
+        ("sparse", TrigramSelection::Sparse),
+        ("rare", TrigramSelection::RareFirst(DEFAULT_RARE_TRIGRAMS)),
+    ] {
+        assert_eq!(spec.parse(), Ok(selection), "{spec}");
+        assert_eq!(selection.to_string().parse(), Ok(selection));
+    }
+    for spec in ["rare:0", "rare:x", "dense"] {
+        assert!(spec.parse::<TrigramSelection>().is_err(), "{spec}");
+    }
+
+    let (overrides, warnings) = resolve("[planner]\ntrigram_selection = \"dense\"\n", &[]);
+    assert_eq!(overrides.trigram_selection.value, None);
+    assert!(warnings[0].contains("unknown trigram selection `dense`"));
+    let (_, warnings) = resolve("[planner]\ntrigram_selection = 3\n", &[]);
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::{INDEX_FILE_NAME, PROJECT_DIR_NAME, PROJECT_MARKERS, blaze_dir};
+
+/// Find the nearest project root at or above `start`.
+///
+/// A directory is a project root if it contains any of [`PROJECT_MARKERS`].
+/// The home directory and filesystem root are never treated as projects, so
+/// a stray `~/.git` or `/package.json` does not turn everything into one.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    find_project_root_below(start, home.as_deref())
+}
+
+/// Find the nearest project at or above `start` that has an index, and
+/// that index.
+///
+/// Nested workspaces (a crate in a monorepo, a submodule) carry markers of
+/// their own but are often indexed only as part of the outer project, so
+/// project roots without an index are passed over. The search stops below
+/// the home directory, like [`find_project_root`].
+pub fn find_indexed_project(start: &Path) -> Option<(PathBuf, PathBuf)> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    find_indexed_project_below(start, home.as_deref())
+}
+
+fn find_project_root_below(start: &Path, stop: Option<&Path>) -> Option<PathBuf> {
+    project_roots_below(start, stop)
+        .next()
+        .map(Path::to_path_buf)
+}
+
+fn find_indexed_project_below(start: &Path, stop: Option<&Path>) -> Option<(PathBuf, PathBuf)> {
+    project_roots_below(start, stop).find_map(|root| {
+        let index = project_index_path(root);
+        index.exists().then(|| (root.to_path_buf(), index))
+    })
+}
+
+/// Project roots at or above `start`, innermost first, up to but excluding
+/// `stop` and the filesystem root.
+fn project_roots_below<'a>(
+    start: &'a Path,
+    stop: Option<&'a Path>,
+) -> impl Iterator<Item = &'a Path> {
+    start
+        .ancestors()
+        .take_while(move |dir| Some(*dir) != stop && dir.parent().is_some())
+        .filter(|dir| {
+            PROJECT_MARKERS
+                .iter()
+                .any(|marker| dir.join(marker).exists())
+        })
+}
+
+/// Find the git repository containing `start`.
+///
+/// Returns the nearest directory at or above `start` holding a `.git` entry.
+/// Both directories and files count, so worktrees and submodules (whose
+/// `.git` is a file) resolve to their own root rather than the outer one.
+pub fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Location of the index for the project rooted at `root`.
+///
+/// Projects that opt in with a `.blaze/` directory keep their index there;
+/// all others get one in the cache dir, keyed by a hash of the root path.
+pub fn project_index_path(root: &Path) -> PathBuf {
+    let local = root.join(PROJECT_DIR_NAME);
+    if local.is_dir() {
+        return local.join(INDEX_FILE_NAME);
+    }
+
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let hash = blake3::hash(canonical.as_os_str().as_encoded_bytes());
+    let key = &hash.to_hex()[..16];
+
+    blaze_dir().join("projects").join(format!("{key}.bin"))
+}
+
+#[cfg(test)]
+#[path = "project_tests.rs"]
+mod tests;
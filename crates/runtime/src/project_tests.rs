@@ -0,0 +1,119 @@
+use std::fs;
+
+use serial_test::serial;
+use tempfile::tempdir;
+
+use super::*;
+
+#[test]
+fn finds_nearest_marker_above_start() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("work/app");
+    let nested = project.join("src/bin");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(project.join("Cargo.toml"), "").unwrap();
+
+    let found = find_project_root_below(&nested, Some(dir.path()));
+    assert_eq!(found.as_deref(), Some(project.as_path()));
+}
+
+#[test]
+fn nested_project_wins_over_outer_one() {
+    let dir = tempdir().unwrap();
+    let outer = dir.path().join("outer");
+    let inner = outer.join("crates/inner");
+    fs::create_dir_all(&inner).unwrap();
+    fs::create_dir(outer.join(".git")).unwrap();
+    fs::write(inner.join("package.json"), "{}").unwrap();
+
+    let found = find_project_root_below(&inner, Some(dir.path()));
+    assert_eq!(found.as_deref(), Some(inner.as_path()));
+}
+
+#[test]
+fn stops_at_boundary_without_marker() {
+    let dir = tempdir().unwrap();
+    let nested = dir.path().join("a/b");
+    fs::create_dir_all(&nested).unwrap();
+    // A marker at the boundary itself (e.g. `~/.git`) must not count.
+    fs::create_dir(dir.path().join(".git")).unwrap();
+
+    assert_eq!(find_project_root_below(&nested, Some(dir.path())), None);
+}
+
+#[test]
+fn nested_workspace_without_index_uses_outer_project_index() {
+    let dir = tempdir().unwrap();
+    let outer = dir.path().join("outer");
+    let inner = outer.join("crates/inner");
+    fs::create_dir_all(inner.join("src")).unwrap();
+    fs::create_dir(outer.join(".git")).unwrap();
+    fs::write(inner.join("Cargo.toml"), "").unwrap();
+    fs::create_dir(outer.join(PROJECT_DIR_NAME)).unwrap();
+    let outer_index = outer.join(PROJECT_DIR_NAME).join(INDEX_FILE_NAME);
+    fs::write(&outer_index, "").unwrap();
+
+    let start = inner.join("src");
+    assert_eq!(
+        find_indexed_project_below(&start, Some(dir.path())),
+        Some((outer.clone(), outer_index))
+    );
+    // Only projects below the boundary count.
+    assert_eq!(find_indexed_project_below(&start, Some(&outer)), None);
+
+    // Once the inner workspace has an index of its own, it wins.
+    fs::create_dir(inner.join(PROJECT_DIR_NAME)).unwrap();
+    let inner_index = inner.join(PROJECT_DIR_NAME).join(INDEX_FILE_NAME);
+    fs::write(&inner_index, "").unwrap();
+    assert_eq!(
+        find_indexed_project_below(&start, Some(dir.path())),
+        Some((inner, inner_index))
+    );
+}
+
+#[test]
+fn repo_root_stops_at_submodule() {
+    let dir = tempdir().unwrap();
+    let outer = dir.path().join("outer");
+    let sub = outer.join("vendor/lib");
+    fs::create_dir_all(sub.join("src")).unwrap();
+    fs::create_dir(outer.join(".git")).unwrap();
+    fs::write(sub.join(".git"), "gitdir: ../../.git/modules/lib\n").unwrap();
+
+    assert_eq!(
+        find_repo_root(&sub.join("src")).as_deref(),
+        Some(sub.as_path())
+    );
+    assert_eq!(
+        find_repo_root(&outer.join("vendor")).as_deref(),
+        Some(outer.as_path())
+    );
+}
+
+#[test]
+fn local_blaze_dir_holds_project_index() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join(PROJECT_DIR_NAME)).unwrap();
+
+    assert_eq!(
+        project_index_path(dir.path()),
+        dir.path().join(PROJECT_DIR_NAME).join(INDEX_FILE_NAME)
+    );
+}
+
+#[test]
+#[serial]
+fn cache_index_path_is_stable_per_root() {
+    let cache = tempdir().unwrap();
+    unsafe { std::env::set_var("XDG_CACHE_HOME", cache.path()) };
+
+    let a = tempdir().unwrap();
+    let b = tempdir().unwrap();
+
+    let path_a = project_index_path(a.path());
+    assert!(path_a.starts_with(cache.path().join("blaze/projects")));
+    assert_eq!(path_a, project_index_path(a.path()));
+    assert_ne!(path_a, project_index_path(b.path()));
+
+    unsafe { std::env::remove_var("XDG_CACHE_HOME") };
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::blaze_dir;
+
+/// Location of the registry of known indexes.
+pub fn registry_path() -> PathBuf {
+    blaze_dir().join("indexes.toml")
+}
+
+/// One index the indexer has written.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IndexEntry {
+    /// The index file.
+    #[serde(serialize_with = "lossy")]
+    pub path: PathBuf,
+    /// Directory the index covers.
+    #[serde(serialize_with = "lossy")]
+    pub root: PathBuf,
+    /// Number of times the index file has been written, starting at 1.
+    #[serde(default)]
+    pub generation: u64,
+    /// When the index was last written, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub built_secs: u64,
+    /// Files in the index when it was last written.
+    #[serde(default)]
+    pub files: u64,
+}
+
+/// The indexes blaze knows about, kept in `indexes.toml`.
+///
+/// The file holds one `[[index]]` table per index. It is rewritten
+/// atomically, so readers never see a partial registry; concurrent writers
+/// can still lose each other's update, which the next build repairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IndexRegistry {
+    #[serde(rename = "index", default)]
+    pub entries: Vec<IndexEntry>,
+}
+
+impl IndexRegistry {
+    /// Read the registry at `path`. A missing file is an empty registry.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the registry to `path` atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        tmp.write_all(self.to_toml()?.as_bytes())?;
+        tmp.as_file().sync_all()?;
+        tmp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// The entry for the index file at `index_path`, if any.
+    pub fn get(&self, index_path: &Path) -> Option<&IndexEntry> {
+        self.entries.iter().find(|e| e.path == index_path)
+    }
+
+    /// Record that `index_path` was just written for `root`, bumping its
+    /// generation, and drop entries whose index file no longer exists.
+    /// Returns the new generation.
+    pub fn record_build(
+        &mut self,
+        index_path: &Path,
+        root: &Path,
+        built_secs: u64,
+        files: u64,
+    ) -> u64 {
+        self.entries
+            .retain(|e| e.path == index_path || e.path.exists());
+
+        let generation = match self.entries.iter_mut().find(|e| e.path == index_path) {
+            Some(entry) => {
+                entry.root = root.to_path_buf();
+                entry.generation += 1;
+                entry.built_secs = built_secs;
+                entry.files = files;
+                entry.generation
+            }
+            None => {
+                self.entries.push(IndexEntry {
+                    path: index_path.to_path_buf(),
+                    root: root.to_path_buf(),
+                    generation: 1,
+                    built_secs,
+                    files,
+                });
+                1
+            }
+        };
+        self.entries.sort_by(|a, b| a.root.cmp(&b.root));
+        generation
+    }
+
+    /// Load the registry at `registry`, record a build and save it again.
+    pub fn update(
+        registry: &Path,
+        index_path: &Path,
+        root: &Path,
+        built_secs: u64,
+        files: u64,
+    ) -> io::Result<u64> {
+        // A damaged registry is rebuilt from scratch rather than blocking builds.
+        let mut reg = match Self::load(registry) {
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Self::default(),
+            other => other?,
+        };
+        let generation = reg.record_build(index_path, root, built_secs, files);
+        reg.save(registry)?;
+        Ok(generation)
+    }
+
+    fn to_toml(&self) -> io::Result<String> {
+        let tables = toml::to_string(self).map_err(io::Error::other)?;
+        Ok(format!(
+            "# Indexes written by blaze; maintained automatically.\n\n{tables}"
+        ))
+    }
+
+    /// Parse `[[index]]` tables written by [`Self::to_toml`]. Unknown keys
+    /// are ignored.
+    fn parse(contents: &str) -> io::Result<Self> {
+        toml::from_str(contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("indexes.toml: {}", e.message()),
+            )
+        })
+    }
+}
+
+/// `path` as a string, with anything that is not UTF-8 replaced, since TOML
+/// strings must be UTF-8.
+fn lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+#[cfg(test)]
+#[path = "registry_tests.rs"]
+mod tests;
@@ -0,0 +1,66 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn missing_registry_is_empty() {
+    let dir = tempdir().expect("create temp dir");
+    let reg = IndexRegistry::load(&dir.path().join("indexes.toml")).expect("load");
+    assert!(reg.entries.is_empty());
+}
+
+#[test]
+fn save_load_round_trip_with_awkward_paths() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("cache/indexes.toml");
+
+    let reg = IndexRegistry {
+        entries: vec![IndexEntry {
+            path: PathBuf::from("/data/\"quoted\" \\ dir/index.bin"),
+            root: PathBuf::from("/data/tab\there # not a comment"),
+            generation: 7,
+            built_secs: 1_760_000_000,
+            files: 1234,
+        }],
+    };
+    reg.save(&path).expect("save");
+
+    assert_eq!(IndexRegistry::load(&path).expect("load"), reg);
+}
+
+#[test]
+fn record_build_bumps_generation_and_prunes_missing() {
+    let dir = tempdir().expect("create temp dir");
+    let live = dir.path().join("live.bin");
+    fs::write(&live, b"").unwrap();
+
+    let mut reg = IndexRegistry::default();
+    assert_eq!(reg.record_build(&live, Path::new("/b"), 10, 1), 1);
+    assert_eq!(
+        reg.record_build(&dir.path().join("gone.bin"), Path::new("/a"), 11, 2),
+        1
+    );
+    assert_eq!(reg.entries.len(), 2);
+    assert_eq!(reg.entries[0].root, Path::new("/a"), "sorted by root");
+
+    // The next build drops the entry whose index file does not exist.
+    assert_eq!(reg.record_build(&live, Path::new("/b"), 20, 3), 2);
+    assert_eq!(reg.entries.len(), 1);
+    let entry = reg.get(&live).expect("live entry");
+    assert_eq!((entry.built_secs, entry.files), (20, 3));
+}
+
+#[test]
+fn update_replaces_damaged_registry() {
+    let dir = tempdir().expect("create temp dir");
+    let path = dir.path().join("indexes.toml");
+    let index = dir.path().join("index.bin");
+    fs::write(&path, "garbage without equals\n").unwrap();
+
+    let err = IndexRegistry::load(&path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let generation =
+        IndexRegistry::update(&path, &index, Path::new("/data"), 5, 6).expect("update");
+    assert_eq!(generation, 1);
+    assert_eq!(IndexRegistry::load(&path).expect("load").entries.len(), 1);
+}
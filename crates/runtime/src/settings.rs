@@ -0,0 +1,336 @@
+//! Settings shared by the CLI and the daemon.
+//!
+//! Each setting is resolved in layers, later ones winning: the built-in
+//! default, the config file, an environment variable, then a command-line
+//! flag.
+//!
+//! ```toml
+//! index_path = "~/.cache/blaze/index.bin"
+//! socket_path = "/run/user/1000/blaze.sock"
+//! limit = 50
+//! exclude_paths = ["~/VirtualBox VMs", "~/Downloads/isos"]
+//! memory_limit = "512M"
+//!
+//! [boost]
+//! "~/work/**" = 20
+//!
+//! [planner]
+//! early_verify_cutoff = 500
+//!
+//! [noise]
+//! build = ["-target", "+_site"]
+//! ```
+//!
+//! The file is parsed once, into a [`ConfigFile`]. A value that cannot be
+//! read is skipped with a warning, leaving the layers below it in effect.
+
+use std::{env, fmt, path::PathBuf, sync::OnceLock};
+
+use log::warn;
+use toml::Value;
+
+use crate::{
+    boost::BoostRules,
+    config::{
+        DEFAULT_QUERY_LIMIT, EXCLUDE_PATHS_ENV, INDEX_PATH_ENV, LIMIT_ENV, MEMORY_LIMIT_ENV,
+        SOCKET_PATH_ENV, default_index_path,
+    },
+    config_file::{ConfigFile, as_count, as_strings, dotted, expand_home},
+    discovery::default_socket_path,
+    noise::NoiseComponents,
+    planner::PlannerOverrides,
+    size::parse_size,
+};
+
+/// Where the value of a setting came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Default,
+    File,
+    Env(&'static str),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => f.write_str("default"),
+            Source::File => f.write_str("config file"),
+            Source::Env(var) => write!(f, "${var}"),
+            Source::Flag => f.write_str("command line"),
+        }
+    }
+}
+
+/// The effective value of a setting and the layer it came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            source: Source::Default,
+        }
+    }
+
+    pub(crate) fn set(&mut self, value: T, source: Source) {
+        self.value = value;
+        self.source = source;
+    }
+}
+
+/// Every layered setting, resolved.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The config file read, whether or not it exists.
+    pub file: PathBuf,
+    /// Index the CLI reads and the daemon serves.
+    pub index_path: Setting<PathBuf>,
+    /// Socket the daemon listens on.
+    pub socket_path: Setting<PathBuf>,
+    /// Results `blaze query` shows without `-n`; 0 shows them all.
+    pub limit: Setting<usize>,
+    /// Paths left out of every index build.
+    pub exclude_paths: Setting<Vec<PathBuf>>,
+    /// Resident memory the daemon may use, in bytes.
+    pub memory_limit: Setting<Option<u64>>,
+    /// The `[planner]` table and its environment variables.
+    pub planner: PlannerOverrides,
+    /// The `[boost]` table.
+    pub boost: BoostRules,
+    /// The `[noise]` table and its environment variables.
+    pub noise: NoiseComponents,
+    /// Values skipped because they could not be read.
+    pub warnings: Vec<String>,
+}
+
+/// Values given on the command line, overriding every other layer.
+#[derive(Debug, Clone, Default)]
+pub struct SettingFlags {
+    pub index_path: Option<PathBuf>,
+    pub socket_path: Option<PathBuf>,
+    pub limit: Option<usize>,
+    pub memory_limit: Option<u64>,
+}
+
+impl Settings {
+    /// Settings from the config file at [`config_path`](crate::config_path)
+    /// and the process environment.
+    pub fn load() -> Self {
+        Self::resolve(&ConfigFile::load(), |var| env::var(var).ok())
+    }
+
+    /// Settings from `file` and the environment variables `env` looks up.
+    pub fn resolve(file: &ConfigFile, env: impl Fn(&str) -> Option<String>) -> Self {
+        let mut warnings = file.warnings.clone();
+        let planner = PlannerOverrides::resolve(file, &env, &mut warnings);
+        let boost = BoostRules::resolve(file, &mut warnings);
+        let noise = NoiseComponents::resolve(file, &env, &mut warnings);
+        let mut settings = Self {
+            file: file.path.clone(),
+            index_path: Setting::new(default_index_path()),
+            socket_path: Setting::new(default_socket_path()),
+            limit: Setting::new(DEFAULT_QUERY_LIMIT),
+            exclude_paths: Setting::new(Vec::new()),
+            memory_limit: Setting::new(None),
+            planner,
+            boost,
+            noise,
+            warnings,
+        };
+        settings.apply_file(file);
+        settings.apply_env(env);
+        settings
+    }
+
+    /// Override settings with the values given on the command line.
+    pub fn with_flags(mut self, flags: &SettingFlags) -> Self {
+        if let Some(path) = &flags.index_path {
+            self.index_path.set(path.clone(), Source::Flag);
+        }
+        if let Some(path) = &flags.socket_path {
+            self.socket_path.set(path.clone(), Source::Flag);
+        }
+        if let Some(limit) = flags.limit {
+            self.limit.set(limit, Source::Flag);
+        }
+        if let Some(limit) = flags.memory_limit {
+            self.memory_limit.set(Some(limit), Source::Flag);
+        }
+        self
+    }
+
+    /// Each setting's config file key, value for display, and source.
+    pub fn entries(&self) -> Vec<(String, String, Source)> {
+        let paths = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let number = |n: Option<usize>| n.map_or_else(String::new, |n| n.to_string());
+        let planner = &self.planner;
+
+        let mut entries = vec![
+            (
+                "index_path".to_owned(),
+                self.index_path.value.display().to_string(),
+                self.index_path.source,
+            ),
+            (
+                "socket_path".to_owned(),
+                self.socket_path.value.display().to_string(),
+                self.socket_path.source,
+            ),
+            (
+                "limit".to_owned(),
+                self.limit.value.to_string(),
+                self.limit.source,
+            ),
+            (
+                "exclude_paths".to_owned(),
+                paths(&self.exclude_paths.value),
+                self.exclude_paths.source,
+            ),
+            (
+                "memory_limit".to_owned(),
+                self.memory_limit
+                    .value
+                    .map_or_else(String::new, |bytes| bytes.to_string()),
+                self.memory_limit.source,
+            ),
+            (
+                dotted("planner", "small_candidate_cutoff"),
+                number(planner.small_candidate_cutoff.value),
+                planner.small_candidate_cutoff.source,
+            ),
+            (
+                dotted("planner", "early_verify_cutoff"),
+                number(planner.early_verify_cutoff.value),
+                planner.early_verify_cutoff.source,
+            ),
+            (
+                dotted("planner", "trigram_selection"),
+                planner
+                    .trigram_selection
+                    .value
+                    .unwrap_or_default()
+                    .to_string(),
+                planner.trigram_selection.source,
+            ),
+        ];
+
+        for (key, list) in [
+            ("build", &self.noise.build),
+            ("cache", &self.noise.cache),
+            ("log", &self.noise.log),
+        ] {
+            entries.push((dotted("noise", key), list.value.join(", "), list.source));
+        }
+
+        if self.boost.is_empty() {
+            entries.push(("boost".to_owned(), String::new(), Source::Default));
+        }
+        for (pattern, score) in self.boost.entries() {
+            entries.push((dotted("boost", pattern), score.to_string(), Source::File));
+        }
+        entries
+    }
+
+    /// Apply the top-level keys of `file`. Its tables are read by the
+    /// settings they hold, e.g. [`BoostRules`].
+    fn apply_file(&mut self, file: &ConfigFile) {
+        let home = env::var("HOME").ok();
+        let path = |value: &str| PathBuf::from(expand_home(value, home.as_deref()));
+
+        for (key, value) in file.top_level() {
+            let parsed = match key.as_str() {
+                "index_path" => value
+                    .as_str()
+                    .map(|v| self.index_path.set(path(v), Source::File)),
+                "socket_path" => value
+                    .as_str()
+                    .map(|v| self.socket_path.set(path(v), Source::File)),
+                "limit" => as_count(value).map(|v| self.limit.set(v, Source::File)),
+                "exclude_paths" => as_strings(value).map(|v| {
+                    let paths = v.iter().map(|p| path(p)).collect();
+                    self.exclude_paths.set(paths, Source::File)
+                }),
+                "memory_limit" => match value {
+                    Value::String(size) => parse_size(size),
+                    other => other.as_integer().and_then(|n| u64::try_from(n).ok()),
+                }
+                .map(|v| self.memory_limit.set(Some(v), Source::File)),
+                "boost" | "noise" | "planner" => continue,
+                other => {
+                    self.warnings.push(file.unknown_key("", other));
+                    continue;
+                }
+            };
+            if parsed.is_none() {
+                self.warnings.push(file.bad_value("", key, value));
+            }
+        }
+    }
+
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) {
+        let var = |name: &'static str| env(name).filter(|v| !v.trim().is_empty());
+
+        if let Some(path) = var(INDEX_PATH_ENV) {
+            self.index_path
+                .set(PathBuf::from(path), Source::Env(INDEX_PATH_ENV));
+        }
+        if let Some(path) = var(SOCKET_PATH_ENV) {
+            self.socket_path
+                .set(PathBuf::from(path), Source::Env(SOCKET_PATH_ENV));
+        }
+        if let Some(value) = var(LIMIT_ENV) {
+            match value.trim().replace('_', "").parse() {
+                Ok(limit) => self.limit.set(limit, Source::Env(LIMIT_ENV)),
+                Err(_) => self
+                    .warnings
+                    .push(format!("ignoring {LIMIT_ENV}={value:?}: not a number")),
+            }
+        }
+        if let Some(value) = var(EXCLUDE_PATHS_ENV) {
+            let paths = env::split_paths(&value)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect();
+            self.exclude_paths
+                .set(paths, Source::Env(EXCLUDE_PATHS_ENV));
+        }
+        if let Some(value) = var(MEMORY_LIMIT_ENV) {
+            match parse_size(&value) {
+                Some(limit) => self
+                    .memory_limit
+                    .set(Some(limit), Source::Env(MEMORY_LIMIT_ENV)),
+                None => self
+                    .warnings
+                    .push(format!("ignoring {MEMORY_LIMIT_ENV}={value:?}: not a size")),
+            }
+        }
+    }
+}
+
+/// Process-wide settings, read on first use. Values that could not be read
+/// are logged then.
+pub fn settings() -> &'static Settings {
+    static SETTINGS: OnceLock<Settings> = OnceLock::new();
+    SETTINGS.get_or_init(|| {
+        let settings = Settings::load();
+        for warning in &settings.warnings {
+            warn!("{warning}");
+        }
+        settings
+    })
+}
+
+#[cfg(test)]
+#[path = "settings_tests.rs"]
+mod tests;
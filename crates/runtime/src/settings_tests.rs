@@ -0,0 +1,176 @@
+use super::*;
+use crate::{noise::NOISE_LOG_ENV, planner::TRIGRAM_SELECTION_ENV};
+
+fn resolve(contents: Option<&str>, env: &[(&str, &str)]) -> Settings {
+    let file = ConfigFile::parse(PathBuf::from("/etc/blaze.toml"), contents.unwrap_or(""));
+    Settings::resolve(&file, |var| {
+        env.iter()
+            .find(|(name, _)| *name == var)
+            .map(|(_, value)| value.to_string())
+    })
+}
+
+#[test]
+fn defaults_apply_without_a_file_or_environment() {
+    let settings = resolve(None, &[]);
+    assert_eq!(settings.limit, Setting::new(DEFAULT_QUERY_LIMIT));
+    assert_eq!(settings.index_path.source, Source::Default);
+    assert_eq!(settings.exclude_paths.value, Vec::<PathBuf>::new());
+    assert_eq!(settings.memory_limit.value, None);
+    assert!(settings.warnings.is_empty());
+}
+
+#[test]
+fn reads_top_level_keys_and_tables() {
+    let settings = resolve(
+        Some(
+            "# shared settings\nindex_path = \"/data/index.bin\"\nlimit = 1_000\nexclude_paths = [\"/mnt\", \"/media/usb\" ]\nmemory_limit = \"512M\"\n\n[planner]\nearly_verify_cutoff = 5\n\n[boost]\n\"/data\" = 10\n",
+        ),
+        &[],
+    );
+
+    assert_eq!(
+        settings.index_path,
+        Setting {
+            value: PathBuf::from("/data/index.bin"),
+            source: Source::File,
+        }
+    );
+    assert_eq!(settings.limit.value, 1000);
+    assert_eq!(
+        settings.exclude_paths.value,
+        [PathBuf::from("/mnt"), PathBuf::from("/media/usb")]
+    );
+    assert_eq!(settings.memory_limit.value, Some(512 << 20));
+    assert_eq!(settings.socket_path.source, Source::Default);
+    assert_eq!(settings.planner.early_verify_cutoff.value, Some(5));
+    assert_eq!(settings.boost.score_for_dir("/data/src"), 10);
+    assert!(settings.warnings.is_empty(), "{:?}", settings.warnings);
+}
+
+#[test]
+fn environment_overrides_the_file_and_flags_override_both() {
+    let settings = resolve(
+        Some("limit = 50\nmemory_limit = 1048576\nsocket_path = \"/run/blaze.sock\"\n"),
+        &[(LIMIT_ENV, "7"), (MEMORY_LIMIT_ENV, "2G")],
+    );
+    assert_eq!(
+        settings.limit,
+        Setting {
+            value: 7,
+            source: Source::Env(LIMIT_ENV),
+        }
+    );
+    assert_eq!(settings.memory_limit.value, Some(2 << 30));
+    assert_eq!(settings.socket_path.source, Source::File);
+
+    let settings = settings.with_flags(&SettingFlags {
+        limit: Some(3),
+        ..SettingFlags::default()
+    });
+    assert_eq!(
+        settings.limit,
+        Setting {
+            value: 3,
+            source: Source::Flag,
+        }
+    );
+    assert_eq!(settings.memory_limit.source, Source::Env(MEMORY_LIMIT_ENV));
+}
+
+#[test]
+fn bad_values_warn_and_leave_lower_layers_in_effect() {
+    let settings = resolve(
+        Some(
+            "limit = \"many\"\ncolour = \"auto\"\nmemory_limit = \"512M\"\n\n[planner]\nsmall_cutoff = 5\nearly_verify_cutoff = -1\n\n[boost]\n\"/a\" = \"lots\"\n\"/b\" = 3\n",
+        ),
+        &[(MEMORY_LIMIT_ENV, "lots")],
+    );
+
+    assert_eq!(settings.limit, Setting::new(DEFAULT_QUERY_LIMIT));
+    assert_eq!(settings.memory_limit.value, Some(512 << 20));
+    assert_eq!(settings.memory_limit.source, Source::File);
+    assert_eq!(settings.planner, PlannerOverrides::default());
+    assert_eq!(settings.boost.entries().collect::<Vec<_>>(), [("/b", 3)]);
+
+    // Every table is held to the same rules as the top-level keys.
+    let warnings = &settings.warnings;
+    assert_eq!(warnings.len(), 6, "{warnings:?}");
+    for expected in [
+        "unknown setting `planner.small_cutoff`",
+        "ignoring `planner.early_verify_cutoff`: bad value -1",
+        "ignoring `boost.\"/a\"`: bad value \"lots\"",
+        "ignoring `limit`: bad value \"many\"",
+        "unknown setting `colour`",
+        MEMORY_LIMIT_ENV,
+    ] {
+        assert!(
+            warnings.iter().any(|w| w.contains(expected)),
+            "{expected} in {warnings:?}"
+        );
+    }
+}
+
+#[test]
+fn a_file_that_does_not_parse_sets_nothing() {
+    let settings = resolve(Some("limit = 5\nlimit = many\n"), &[]);
+    assert_eq!(settings.limit, Setting::new(DEFAULT_QUERY_LIMIT));
+    assert_eq!(settings.warnings.len(), 1, "{:?}", settings.warnings);
+    assert!(settings.warnings[0].starts_with("/etc/blaze.toml line 2: "));
+}
+
+#[test]
+fn entries_show_planner_and_boost_sources() {
+    let settings = resolve(
+        Some("[planner]\nearly_verify_cutoff = 500\n\n[boost]\n\"~/work/**\" = -30\n"),
+        &[
+            (TRIGRAM_SELECTION_ENV, "rare:4"),
+            (NOISE_LOG_ENV, "+traces"),
+        ],
+    );
+    let entries = settings.entries();
+    let entry = |key: &str| {
+        entries
+            .iter()
+            .find(|(k, ..)| k == key)
+            .map(|(_, value, source)| (value.as_str(), *source))
+    };
+
+    assert_eq!(
+        entry("planner.early_verify_cutoff"),
+        Some(("500", Source::File))
+    );
+    assert_eq!(
+        entry("planner.small_candidate_cutoff"),
+        Some(("", Source::Default))
+    );
+    assert_eq!(
+        entry("planner.trigram_selection"),
+        Some(("rare:4", Source::Env(TRIGRAM_SELECTION_ENV)))
+    );
+    assert_eq!(entry("boost.\"~/work/**\""), Some(("-30", Source::File)));
+    let (log, source) = entry("noise.log").unwrap();
+    assert!(
+        log.starts_with("logs, log, ") && log.ends_with(", traces"),
+        "{log}"
+    );
+    assert_eq!(source, Source::Env(NOISE_LOG_ENV));
+    assert_eq!(entry("noise.cache").unwrap().1, Source::Default);
+    assert_eq!(resolve(None, &[]).entries().last().unwrap().0, "boost");
+}
+
+#[test]
+fn exclude_paths_accept_a_single_string() {
+    let settings = resolve(Some("exclude_paths = \"/mnt\"\n"), &[]);
+    assert_eq!(settings.exclude_paths.value, [PathBuf::from("/mnt")]);
+
+    let settings = resolve(Some("exclude_paths = [\"/mnt\", 5]\n"), &[]);
+    assert_eq!(settings.exclude_paths.source, Source::Default);
+    assert_eq!(settings.warnings.len(), 1, "{:?}", settings.warnings);
+}
+
+#[test]
+fn sources_name_their_layer() {
+    assert_eq!(Source::Env(LIMIT_ENV).to_string(), "$BLAZE_LIMIT");
+    assert_eq!(Source::File.to_string(), "config file");
+}
@@ -0,0 +1,103 @@
+//! Human-readable byte sizes.
+
+/// Detects if a unit suffix indicates bits using smartcasing.
+///
+/// This is very similar to how Vim smartcasing operates. The goal
+/// is to allow the user to use units like Mega[BIT]s or Mega[Byte]s.
+/// Smartcase logic:
+///  1) All lowercase (i.e. `mb`, `kb`) will be interpreted as bytes
+///  2) Prefix starst with uppercase + lowercase `b` (e.g., `Mb`, `Kb`) will be interpreted as bits
+///  3) All uppercase (e.g., `MB`, `KB`) will be interpreted as bytes
+///  4) No `b` or `B` suffix will default to bytes
+#[inline]
+fn is_bits_unit(unit: &[u8]) -> bool {
+    let Some(&last) = unit.last() else {
+        return false;
+    };
+
+    last == b'b' && unit.len() > 1 && unit[0].is_ascii_uppercase()
+}
+
+const KIB: u64 = 1024;
+const MIB: u64 = KIB * 1024;
+const GIB: u64 = MIB * 1024;
+const TIB: u64 = GIB * 1024;
+
+/// Parse sizes like "10MB", "500k", "1.5G", "10Mb" into **bytes**.
+/// Prefix letters K/M/G/T (optionally with 'i' for KiB/MiB/etc.) use 1024-based multipliers.
+/// No unit means raw bytes. Fractional sizes are rounded to the nearest byte.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+
+    let bytes = s.as_bytes();
+
+    // Split into number part and unit part by scanning backwards for alphabetic chars.
+    let mut split = bytes.len();
+    for i in (0..bytes.len()).rev() {
+        if bytes[i].is_ascii_alphabetic() {
+            split = i;
+        } else {
+            break;
+        }
+    }
+
+    let (num_bytes, unit_bytes) = bytes.split_at(split);
+
+    // Parse number from the byte slice (safe since digits are ASCII).
+    let num_str = std::str::from_utf8(num_bytes).ok()?.trim();
+    if unit_bytes.is_empty() {
+        return parse_scaled(num_str, 1); // raw bytes, no unit
+    }
+
+    let is_bits = is_bits_unit(unit_bytes);
+
+    let last = *unit_bytes.last().unwrap(); // safe: not empty
+    let prefix_bytes = if last == b'b' || last == b'B' {
+        &unit_bytes[..unit_bytes.len() - 1]
+    } else {
+        unit_bytes
+    };
+
+    let mut lower = prefix_bytes.to_vec();
+    lower.make_ascii_lowercase();
+
+    let factor: u64 = match lower.as_slice() {
+        b"" => 1,
+        b"k" | b"ki" => KIB,
+        b"m" | b"mi" => MIB,
+        b"g" | b"gi" => GIB,
+        b"t" | b"ti" => TIB,
+        _ => return None,
+    };
+
+    let value = parse_scaled(num_str, factor)?;
+
+    if is_bits {
+        Some(value / 8)
+    } else {
+        Some(value)
+    }
+}
+
+/// `num` (digits with at most one decimal point) times `factor`, saturating.
+fn parse_scaled(num: &str, factor: u64) -> Option<u64> {
+    if let Ok(n) = num.parse::<u64>() {
+        return Some(n.saturating_mul(factor));
+    }
+
+    let (int, frac) = num.split_once('.')?;
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.len() + frac.len() == 0 || !digits(int) || !digits(frac) {
+        return None;
+    }
+    let n: f64 = num.parse().ok()?;
+    // `as` saturates at `u64::MAX`.
+    Some((n * factor as f64).round() as u64)
+}
+
+#[cfg(test)]
+#[path = "size_tests.rs"]
+mod tests;
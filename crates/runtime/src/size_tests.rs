@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn is_bits_unit_smartcase_behavior() {
+    let cases: &[(&[u8], bool)] = &[
+        (b"", false),
+        (b"b", false),
+        (b"kb", false),
+        (b"mb", false),
+        (b"MB", false),
+        (b"Kb", true),
+        (b"Mb", true),
+        (b"Gb", true),
+        (b"Tb", true),
+        (b"KiB", false),
+        (b"MiB", false),
+        (b"Kib", true),
+    ];
+
+    for (unit, expected) in cases {
+        let got = is_bits_unit(unit);
+        assert_eq!(got, *expected, "unit: {:?}", std::str::from_utf8(unit));
+    }
+}
+
+#[test]
+fn parse_size_parses_raw_bytes_and_units() {
+    let cases: &[(&str, Option<u64>)] = &[
+        ("0", Some(0)),
+        ("10", Some(10)),
+        ("  10  ", Some(10)),
+        ("10k", Some(10 * KIB)),
+        ("10K", Some(10 * KIB)),
+        ("10kb", Some(10 * KIB)),
+        ("10KB", Some(10 * KIB)),
+        ("10Ki", Some(10 * KIB)),
+        ("10KiB", Some(10 * KIB)),
+        ("1m", Some(MIB)),
+        ("1M", Some(MIB)),
+        ("1Mi", Some(MIB)),
+        ("1MiB", Some(MIB)),
+        ("2g", Some(2 * GIB)),
+        ("2G", Some(2 * GIB)),
+        ("2GiB", Some(2 * GIB)),
+        ("3t", Some(3 * TIB)),
+        ("3T", Some(3 * TIB)),
+        ("3Ti", Some(3 * TIB)),
+        ("3TiB", Some(3 * TIB)),
+        ("", None),
+        ("   ", None),
+        ("abc", None),
+        ("10x", None),
+        ("1.5G", Some(GIB + GIB / 2)),
+        ("0.5k", Some(KIB / 2)),
+        (".5k", Some(KIB / 2)),
+        ("2.5", Some(3)),
+        ("1.2.3k", None),
+        ("-1k", None),
+        (".k", None),
+    ];
+
+    for (input, expected) in cases {
+        let got = parse_size(input);
+        assert_eq!(got, *expected, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn parse_size_handles_bits_via_smartcase() {
+    let one_mib_bytes = MIB;
+    let expected_one_megabit_bytes = one_mib_bytes / 8;
+
+    let cases: &[(&str, Option<u64>)] = &[
+        ("1Mb", Some(expected_one_megabit_bytes)),
+        ("1mb", Some(MIB)),
+        ("1MB", Some(MIB)),
+        ("8Kb", Some(KIB)),
+        ("1.5Mb", Some((MIB + MIB / 2) / 8)),
+    ];
+
+    for (input, expected) in cases {
+        let got = parse_size(input);
+        assert_eq!(got, *expected, "input: {:?}", input);
+    }
+}
@@ -9,6 +9,10 @@ WARMUP_RUNS="${WARMUP_RUNS:-3}"
 MEASURE_RUNS="${MEASURE_RUNS:-10}"
 TODAY_EPOCH="$(date -d 'today 00:00' +%s)"
 RESULTS_DIR="${RESULTS_DIR:-$REPO_ROOT/benchmark_results}"
+# COLD=1 drops the page cache before every run (needs sudo) to measure
+# queries against an index that is not in memory.
+COLD="${COLD:-0}"
+DROP_CACHES_CMD="${DROP_CACHES_CMD:-sync; echo 3 | sudo tee /proc/sys/vm/drop_caches >/dev/null}"
 
 BLAZE_BIN="${BLAZE_BIN:-$REPO_ROOT/target/release/blaze}"
 BLAZE_CLI_CMD="${BLAZE_CLI_CMD:-$BLAZE_BIN query %q}"
@@ -35,11 +39,18 @@ fi
 
 mkdir -p "$RESULTS_DIR"
 
+HYPERFINE_CACHE_ARGS=()
+if [ "$COLD" = "1" ]; then
+  WARMUP_RUNS=0
+  HYPERFINE_CACHE_ARGS=(--prepare "$DROP_CACHES_CMD")
+fi
+
 echo "Benchmarking on ROOT: $ROOT"
 echo "Using blaze binary:        $BLAZE_BIN"
 echo "Blaze CLI template:        $BLAZE_CLI_CMD"
 echo "Blaze daemon template:     $BLAZE_DAEMON_CMD"
 echo "Warmup runs:               $WARMUP_RUNS, measured runs: $MEASURE_RUNS"
+echo "Cold caches:               $([ "$COLD" = "1" ] && echo yes || echo no)"
 echo
 
 expand_cmd() {
@@ -107,12 +118,13 @@ for name in "${queries[@]}"; do
   echo "plocate:        $CMD_PLOCATE"
   echo
 
-  RESULT_JSON="$RESULTS_DIR/${name}.json"
+  RESULT_JSON="$RESULTS_DIR/${name}$([ "$COLD" = "1" ] && echo _cold).json"
 
   hyperfine \
     --warmup "$WARMUP_RUNS" \
     --runs "$MEASURE_RUNS" \
     --export-json "$RESULT_JSON" \
+    "${HYPERFINE_CACHE_ARGS[@]}" \
     "${CMDS[@]}"
 
   echo "Saved results to: $RESULT_JSON"
@@ -0,0 +1,282 @@
+//! End-to-end fixtures for blaze.
+//!
+//! A [`Fixture`] is a temporary directory holding a file tree to index and a
+//! home of its own, so the `blaze` and `blaze-daemon` binaries it runs never
+//! see the user's config, index or daemon. The tests in `tests/` drive the
+//! real binaries the way a user would and check what they print, which
+//! catches regressions that fall between crates.
+//!
+//! The binaries are built on first use, with the profile the tests were
+//! built with.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use blaze_engine::Index;
+use blaze_protocol::{
+    DaemonRequest, DaemonResponse,
+    codec::{read_message, write_message},
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// Longest to wait for a daemon to come up or shut down.
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A file tree and a private home to run blaze in.
+pub struct Fixture {
+    _dir: TempDir,
+    /// The temporary directory, with symlinks resolved so paths compare
+    /// equal to the ones blaze prints.
+    root: PathBuf,
+}
+
+impl Fixture {
+    /// A tree holding `paths`, relative to its root. Paths ending in `/` are
+    /// created as empty directories; any other path is a file containing its
+    /// own path.
+    pub fn new(paths: &[&str]) -> Self {
+        let dir = TempDir::new().expect("create fixture directory");
+        let fixture = Self {
+            root: dir.path().canonicalize().unwrap(),
+            _dir: dir,
+        };
+        fs::create_dir_all(fixture.tree()).unwrap();
+        fs::create_dir_all(fixture.home()).unwrap();
+        for path in paths {
+            match path.strip_suffix('/') {
+                Some(dir) => fs::create_dir_all(fixture.tree().join(dir)).unwrap(),
+                None => fixture.write(path, path),
+            }
+        }
+        fixture
+    }
+
+    /// Root of the file tree.
+    pub fn tree(&self) -> PathBuf {
+        self.root().join("tree")
+    }
+
+    /// `$HOME` of the binaries; their config, index and daemon files live here.
+    pub fn home(&self) -> PathBuf {
+        self.root().join("home")
+    }
+
+    /// The global index, where `blaze index` writes by default.
+    pub fn index_path(&self) -> PathBuf {
+        self.home().join(".cache/blaze/index.bin")
+    }
+
+    /// Absolute path of `rel` in the tree, as blaze prints it.
+    pub fn path(&self, rel: &str) -> String {
+        self.tree().join(rel).to_string_lossy().into_owned()
+    }
+
+    /// Create or overwrite a file in the tree, with its parent directories.
+    pub fn write(&self, rel: &str, contents: &str) {
+        let path = self.tree().join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Remove a file or directory from the tree.
+    pub fn remove(&self, rel: &str) {
+        let path = self.tree().join(rel);
+        if path.is_dir() {
+            fs::remove_dir_all(path).unwrap();
+        } else {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// Index the tree with `blaze index` and open the result.
+    pub fn build_index(&self) -> Index {
+        let tree = self.tree();
+        self.blaze(&["index", tree.to_str().unwrap()]).success();
+        Index::open(&self.index_path()).expect("open the index blaze wrote")
+    }
+
+    /// Run `blaze` with `args` and wait for it.
+    pub fn blaze(&self, args: &[&str]) -> Run {
+        let output = self
+            .command("blaze")
+            .args(args)
+            .output()
+            .expect("run blaze");
+        Run {
+            status: output.status,
+            stdout: String::from_utf8(output.stdout).expect("stdout is UTF-8"),
+            stderr: String::from_utf8(output.stderr).expect("stderr is UTF-8"),
+        }
+    }
+
+    /// Start `blaze-daemon` on a socket in the fixture's home and wait until
+    /// it answers pings.
+    pub fn start_daemon(&self) -> Daemon {
+        let socket = self.home().join("daemon.sock");
+        let child = self
+            .command("blaze-daemon")
+            .arg("--socket-path")
+            .arg(&socket)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("start blaze-daemon");
+        let daemon = Daemon { child, socket };
+
+        let deadline = Instant::now() + DAEMON_TIMEOUT;
+        while daemon.ping().is_err() {
+            assert!(Instant::now() < deadline, "blaze-daemon did not come up");
+            thread::sleep(Duration::from_millis(20));
+        }
+        daemon
+    }
+
+    /// A command for one of the blaze binaries, run in the fixture directory
+    /// with nothing but `PATH` inherited from the test's environment.
+    pub fn command(&self, name: &str) -> Command {
+        let home = self.home();
+        let mut cmd = Command::new(bin_dir().join(name));
+        cmd.current_dir(self.root())
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", &home)
+            .env("XDG_CACHE_HOME", home.join(".cache"))
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("XDG_DATA_HOME", home.join(".local/share"))
+            .stdin(Stdio::null());
+        cmd
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// What a finished `blaze` run printed.
+#[derive(Debug)]
+pub struct Run {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Run {
+    /// Exit code; panics if the process was killed by a signal.
+    pub fn code(&self) -> i32 {
+        self.status.code().expect("blaze exited normally")
+    }
+
+    /// Assert the run succeeded and return it.
+    pub fn success(self) -> Self {
+        assert!(self.status.success(), "blaze failed: {self:#?}");
+        self
+    }
+
+    /// Lines of stdout.
+    pub fn lines(&self) -> Vec<&str> {
+        self.stdout.lines().collect()
+    }
+
+    /// Stdout parsed as NDJSON.
+    pub fn json_lines(&self) -> Vec<Value> {
+        (self.stdout.lines())
+            .map(|line| serde_json::from_str(line).expect("stdout line is JSON"))
+            .collect()
+    }
+}
+
+/// A running `blaze-daemon`, killed when dropped.
+pub struct Daemon {
+    child: Child,
+    socket: PathBuf,
+}
+
+impl Daemon {
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// Send one request on a fresh connection and read the answer.
+    pub fn request(&self, req: &DaemonRequest) -> io::Result<DaemonResponse> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+        write_message(&mut stream, req).map_err(io::Error::other)?;
+        read_message(&mut stream).map_err(io::Error::other)
+    }
+
+    fn ping(&self) -> io::Result<DaemonResponse> {
+        match self.request(&DaemonRequest::Ping)? {
+            pong @ DaemonResponse::Pong(_) => Ok(pong),
+            other => Err(io::Error::new(ErrorKind::InvalidData, format!("{other:?}"))),
+        }
+    }
+
+    /// Shut the daemon down with SIGTERM and wait for it to exit.
+    pub fn stop(mut self) -> ExitStatus {
+        // SAFETY: plain syscall on the pid of a child we have not reaped.
+        unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGTERM) };
+        let deadline = Instant::now() + DAEMON_TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait().expect("wait for blaze-daemon") {
+                return status;
+            }
+            assert!(Instant::now() < deadline, "blaze-daemon did not shut down");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+    }
+}
+
+/// Directory holding the `blaze` and `blaze-daemon` binaries, built the
+/// first time it is asked for.
+fn bin_dir() -> &'static Path {
+    static BIN_DIR: OnceLock<PathBuf> = OnceLock::new();
+    BIN_DIR.get_or_init(build_binaries)
+}
+
+/// Build the binaries into the target directory the tests run from.
+fn build_binaries() -> PathBuf {
+    // Test executables live in `target/<profile>/deps`.
+    let exe = std::env::current_exe().expect("locate the test executable");
+    let profile_dir = (exe.parent().and_then(Path::parent))
+        .expect("test executable in a target directory")
+        .to_path_buf();
+
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args(["build", "--quiet", "-p", "blaze-cli", "-p", "blaze-daemon"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"));
+    match profile_dir.file_name().and_then(|name| name.to_str()) {
+        Some("debug") | None => {}
+        Some(profile) => {
+            cargo.args(["--profile", profile]);
+        }
+    }
+    let status = cargo.status().expect("run cargo build");
+    assert!(status.success(), "building blaze and blaze-daemon failed");
+
+    for bin in ["blaze", "blaze-daemon"] {
+        assert!(
+            profile_dir.join(bin).is_file(),
+            "{bin} was not built into {}",
+            profile_dir.display()
+        );
+    }
+    profile_dir
+}
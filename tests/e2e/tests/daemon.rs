@@ -0,0 +1,101 @@
+//! Talking to a real `blaze-daemon` over a socket in a temporary home.
+
+use blaze_e2e::Fixture;
+use blaze_protocol::{BuildInfo, DaemonRequest, DaemonResponse, QueryRequest};
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new(&["src/main.rs", "src/lib.rs", "docs/guide.md"]);
+    fixture.build_index();
+    fixture
+}
+
+#[test]
+fn daemon_queries_match_local_ones() {
+    let fixture = fixture();
+    let daemon = fixture.start_daemon();
+
+    // The daemon ranks like a local query but cannot sort by path.
+    let sorted = |args: &[&str]| {
+        let run = fixture.blaze(args).success();
+        let mut lines: Vec<String> = run.lines().iter().map(|l| l.to_string()).collect();
+        lines.sort_unstable();
+        lines
+    };
+    let local = sorted(&["query", "-q", "ext:rs"]);
+    assert_eq!(
+        local,
+        [fixture.path("src/lib.rs"), fixture.path("src/main.rs")]
+    );
+    assert_eq!(sorted(&["query", "-q", "--daemon", "ext:rs"]), local);
+
+    let req = DaemonRequest::Query(QueryRequest {
+        query: "guide".into(),
+        limit: Some(10),
+        under: None,
+        session: None,
+        offset: 0,
+    });
+    match daemon.request(&req).unwrap() {
+        DaemonResponse::QueryResult(resp) => {
+            let paths: Vec<&str> = resp.hits.iter().map(|h| h.path.as_str()).collect();
+            assert_eq!(paths, [fixture.path("docs/guide.md")]);
+        }
+        other => panic!("expected query results, got {other:?}"),
+    }
+
+    assert!(daemon.stop().success());
+}
+
+#[test]
+fn the_daemon_reports_its_build_and_cleans_up() {
+    let fixture = fixture();
+    let daemon = fixture.start_daemon();
+    let discovery = fixture.home().join(".cache/blaze/daemon.json");
+    assert!(discovery.is_file());
+
+    match daemon.request(&DaemonRequest::Ping).unwrap() {
+        DaemonResponse::Pong(pong) => assert_eq!(pong.build, BuildInfo::current()),
+        other => panic!("expected a pong, got {other:?}"),
+    }
+    let status = fixture.blaze(&["status"]).success();
+    assert!(
+        status.stdout.contains("daemon:   running"),
+        "{}",
+        status.stdout
+    );
+    assert!(
+        !status.stdout.contains("another build"),
+        "{}",
+        status.stdout
+    );
+
+    let socket = daemon.socket().to_path_buf();
+    assert!(daemon.stop().success());
+    assert!(!socket.exists());
+    assert!(!discovery.exists());
+}
+
+#[test]
+fn unreadable_daemon_hits_make_room_for_readable_ones() {
+    let fixture = Fixture::new(&["notes/a.txt", "notes/b.txt", "notes/c.txt"]);
+    fixture.build_index();
+    let daemon = fixture.start_daemon();
+    // Files removed since indexing cannot be read either.
+    fixture.remove("notes/a.txt");
+    fixture.remove("notes/b.txt");
+
+    let run = fixture.blaze(&[
+        "query",
+        "-q",
+        "--daemon",
+        "-n",
+        "1",
+        "--accessible-only",
+        "ext:txt",
+    ]);
+    // Unreadable matches past the one shown may still count towards the total.
+    let run = run.success();
+    assert_eq!(run.lines()[0], fixture.path("notes/c.txt"));
+
+    assert!(daemon.stop().success());
+}
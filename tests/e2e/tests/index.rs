@@ -0,0 +1,56 @@
+//! `blaze index` writes an index the engine can open and queries can use.
+
+use blaze_e2e::Fixture;
+use blaze_engine::IndexReader;
+
+#[test]
+fn the_index_covers_the_tree() {
+    let fixture = Fixture::new(&["src/main.rs", "src/lib.rs", "docs/guide.md"]);
+    let index = fixture.build_index();
+
+    assert_eq!(index.root_path(), fixture.tree().to_str());
+    assert_eq!(index.live_file_count(), 3);
+}
+
+#[test]
+fn refreshes_pick_up_new_and_removed_files() {
+    let fixture = Fixture::new(&["src/main.rs", "src/old.rs"]);
+    fixture.build_index();
+
+    fixture.write("src/new.rs", "fn new() {}");
+    fixture.remove("src/old.rs");
+    let tree = fixture.tree();
+    fixture
+        .blaze(&["index", "--refresh", tree.to_str().unwrap()])
+        .success();
+
+    let run = fixture.blaze(&["query", "-q", "--sort", "path", "ext:rs"]);
+    assert_eq!(
+        run.success().lines(),
+        [fixture.path("src/main.rs"), fixture.path("src/new.rs")]
+    );
+}
+
+#[test]
+fn build_stats_count_the_excluded_dirs_the_scan_met() {
+    let fixture = Fixture::new(&["src/main.rs", "skip/me.rs", "skip/deeper/too.rs"]);
+    let tree = fixture.tree();
+    let (skip, elsewhere) = (fixture.path("skip"), fixture.path("not/there"));
+    fixture
+        .blaze(&[
+            "index",
+            "--exclude-path",
+            &skip,
+            "--exclude-path",
+            &elsewhere,
+            tree.to_str().unwrap(),
+        ])
+        .success();
+
+    let status = fixture.blaze(&["status"]).success();
+    assert!(
+        status.lines().contains(&"skipped:  1 dirs, 0 files"),
+        "{}",
+        status.stdout
+    );
+}
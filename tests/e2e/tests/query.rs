@@ -0,0 +1,129 @@
+//! What `blaze query` prints and how it exits.
+
+use blaze_e2e::Fixture;
+use serde_json::Value;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new(&[
+        "src/main.rs",
+        "src/lib.rs",
+        "docs/guide.md",
+        "logs/build.log",
+    ]);
+    fixture.build_index();
+    fixture
+}
+
+#[test]
+fn queries_print_matching_paths() {
+    let fixture = fixture();
+
+    let run = fixture.blaze(&["query", "-q", "--sort", "path", "ext:rs"]);
+    assert_eq!(
+        run.success().lines(),
+        [fixture.path("src/lib.rs"), fixture.path("src/main.rs")]
+    );
+
+    let run = fixture.blaze(&["query", "-q", "guide"]);
+    assert_eq!(run.success().lines(), [fixture.path("docs/guide.md")]);
+}
+
+#[test]
+fn json_rows_keep_their_schema() {
+    let fixture = fixture();
+    let run = fixture
+        .blaze(&["query", "--json", "--sort", "path", "ext:rs"])
+        .success();
+
+    let rows = run.json_lines();
+    assert_eq!(rows.len(), 2);
+    for (rank, row) in rows.iter().enumerate() {
+        let mut keys: Vec<&str> = row
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "archive", "dir", "escaped", "kind", "path", "query", "rank", "symlink", "trash"
+            ]
+        );
+        assert_eq!(row["query"], "ext:rs");
+        assert_eq!(row["rank"], rank + 1);
+        assert_eq!(row["dir"], false);
+    }
+    assert_eq!(rows[0]["path"], fixture.path("src/lib.rs").as_str());
+
+    // The summary goes to stderr, so stdout stays one row per line.
+    let summary: Value = serde_json::from_str(run.stderr.trim()).unwrap();
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["total"], 2);
+    assert_eq!(summary["returned"], 2);
+    assert_eq!(summary["truncated"], false);
+}
+
+#[test]
+fn plumbing_lines_are_rank_score_path() {
+    let fixture = fixture();
+    let run = fixture.blaze(&["query", "--plumbing", "ext:log"]).success();
+
+    let fields: Vec<Vec<&str>> = run
+        .lines()
+        .iter()
+        .map(|l| l.split('\t').collect())
+        .collect();
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0][0], "1");
+    assert!(fields[0][1].parse::<f64>().is_ok());
+    assert_eq!(fields[0][2], fixture.path("logs/build.log"));
+}
+
+#[test]
+fn exit_codes() {
+    let fixture = Fixture::new(&["a.txt"]);
+
+    // Without an index, queries fail and say how to build one.
+    let run = fixture.blaze(&["query", "a.txt"]);
+    assert_eq!(run.code(), 2, "{run:#?}");
+    assert!(run.stdout.is_empty());
+
+    fixture.build_index();
+    assert_eq!(fixture.blaze(&["query", "a.txt"]).code(), 0);
+    // Finding nothing is not an error.
+    let run = fixture.blaze(&["query", "-q", "no-such-file"]);
+    assert_eq!(run.code(), 0);
+    assert!(run.stdout.is_empty());
+
+    // Usage errors come from argument parsing.
+    assert_eq!(fixture.blaze(&["query", "--no-such-flag", "x"]).code(), 2);
+    assert_eq!(fixture.blaze(&[]).code(), 2);
+}
+
+#[test]
+fn unreadable_results_make_room_for_readable_ones() {
+    let fixture = Fixture::new(&["notes/a.txt", "notes/b.txt", "notes/c.txt"]);
+    fixture.build_index();
+    // Files removed since indexing cannot be read either.
+    fixture.remove("notes/a.txt");
+    fixture.remove("notes/b.txt");
+
+    for sort in ["relevance", "path"] {
+        let run = fixture.blaze(&[
+            "query",
+            "-q",
+            "-n",
+            "1",
+            "--sort",
+            sort,
+            "--accessible-only",
+            "ext:txt",
+        ]);
+        // Unreadable matches past the one shown may still count towards the
+        // total.
+        let run = run.success();
+        assert_eq!(run.lines()[0], fixture.path("notes/c.txt"), "--sort {sort}");
+    }
+}